@@ -1,13 +1,22 @@
 use anyhow::Result;
 use serde_json::{Value, json};
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::{Arc, Mutex};
 use tokio::fs;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::Child;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::process::{Child, ChildStdin, ChildStdout};
 
 use crate::analyzer::protocol::*;
+use crate::analyzer::transaction::FileTransaction;
+use crate::compiler::packages::PackageResolver;
+use crate::compiler::progress::{CargoProgressSender, ProgressTracker};
+use crate::config::{AnalyzerConfig, merge_json};
+use crate::inspection::unified_diff;
 
 #[derive(Debug, Clone)]
 pub struct DefinitionDetails {
@@ -15,6 +24,24 @@ pub struct DefinitionDetails {
     pub symbol_path: SymbolPath,
 }
 
+/// The nearest directory at or above `dir` holding a `Cargo.toml` (or a
+/// `rust-project.json`), which rust-analyzer needs as its root to load a
+/// workspace. Without one it would start, index nothing and answer every
+/// request with empty results.
+fn find_project_root(dir: &Path) -> Result<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| {
+            ancestor.join("Cargo.toml").is_file() || ancestor.join("rust-project.json").is_file()
+        })
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No Cargo project found: neither {} nor any parent directory contains a Cargo.toml. Start the server from inside a Cargo project.",
+                dir.display()
+            )
+        })
+}
+
 fn get_rust_analyzer_path() -> String {
     std::env::var("RUST_ANALYZER_PATH").unwrap_or_else(|_| {
         // Default to ~/.cargo/bin/rust-analyzer
@@ -23,11 +50,207 @@ fn get_rust_analyzer_path() -> String {
     })
 }
 
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let content = message.to_string();
+    let header = format!("Content-Length: {}\r\n\r\n", content.len());
+
+    writer.write_all(header.as_bytes()).await?;
+    writer.write_all(content.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads messages until the response to `expected_id` arrives. Notifications
+/// are handled inline; requests from the server are answered on `writer`, as
+/// rust-analyzer may hold back work until they are.
+async fn read_until_response<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    expected_id: u64,
+    diagnostics_store: &Mutex<HashMap<String, Vec<Diagnostic>>>,
+    settings: &Value,
+) -> Result<Value>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        // Read headers
+        let mut content_length: Option<usize> = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Err(anyhow::anyhow!("rust-analyzer closed its output"));
+            }
+
+            if line == "\r\n" {
+                break;
+            }
+
+            if let Some(stripped) = line.strip_prefix("Content-Length:") {
+                let length_str = stripped.trim();
+                content_length = Some(length_str.parse()?);
+            }
+        }
+
+        let Some(length) = content_length else {
+            continue;
+        };
+        let mut content = vec![0u8; length];
+        reader.read_exact(&mut content).await?;
+
+        let message: Value = serde_json::from_slice(&content)?;
+        let method = message.get("method").and_then(|m| m.as_str());
+
+        match (message.get("id"), method) {
+            // Request from the server: it needs a reply.
+            (Some(id), Some(method)) => {
+                let reply = server_request_reply(id, method, message.get("params"), settings);
+                write_message(writer, &reply).await?;
+            }
+            (Some(id), None) => {
+                if id.as_u64() == Some(expected_id) {
+                    return Ok(message);
+                }
+            }
+            // Notification - inline handling
+            (None, Some("textDocument/publishDiagnostics")) => {
+                if let Some(params) = message.get("params")
+                    && let Ok(diag_params) =
+                        serde_json::from_value::<PublishDiagnosticsParams>(params.clone())
+                    && let Ok(mut store) = diagnostics_store.lock()
+                {
+                    store.insert(diag_params.uri, diag_params.diagnostics);
+                }
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+/// Builds the reply to a server-to-client request.
+fn server_request_reply(id: &Value, method: &str, params: Option<&Value>, settings: &Value) -> Value {
+    let result = match method {
+        "workspace/configuration" => {
+            let items = params
+                .and_then(|params| params.get("items"))
+                .and_then(|items| items.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            Value::Array(
+                items
+                    .iter()
+                    .map(|item| {
+                        configuration_section(
+                            settings,
+                            item.get("section").and_then(|section| section.as_str()),
+                        )
+                    })
+                    .collect(),
+            )
+        }
+        "window/workDoneProgress/create"
+        | "client/registerCapability"
+        | "client/unregisterCapability"
+        | "workspace/semanticTokens/refresh"
+        | "workspace/inlayHint/refresh"
+        | "workspace/codeLens/refresh"
+        | "workspace/diagnostic/refresh" => Value::Null,
+        _ => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": -32601,
+                    "message": format!("Unsupported request: {method}")
+                }
+            });
+        }
+    };
+
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": result
+    })
+}
+
+/// Value of a `workspace/configuration` section: `rust-analyzer` is the whole
+/// settings object, `rust-analyzer.cargo.features` a part of it.
+fn configuration_section(settings: &Value, section: Option<&str>) -> Value {
+    let Some(section) = section else {
+        return json!({ "rust-analyzer": settings });
+    };
+    let Some(rest) = section.strip_prefix("rust-analyzer") else {
+        return Value::Null;
+    };
+    rest.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(settings, |value, key| value.get(key))
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+/// Settings that switch between full analysis (proc-macros, build script
+/// out dirs) and the quicker analysis without them.
+fn analysis_settings(full: bool) -> Value {
+    json!({
+        "cargo": {
+            "loadOutDirsFromCheck": full
+        },
+        "procMacro": {
+            "enable": full
+        }
+    })
+}
+
+/// What rust-analyzer last received for an open document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentVersion {
+    pub version: i32,
+    pub content_hash: u64,
+}
+
+impl DocumentVersion {
+    fn new(version: i32, text: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        Self {
+            version,
+            content_hash: hasher.finish(),
+        }
+    }
+
+    /// Whether `text` is the content rust-analyzer has, so positions and
+    /// edits it computed still apply to it.
+    pub fn matches(&self, text: &str) -> bool {
+        Self::new(self.version, text).content_hash == self.content_hash
+    }
+}
+
+/// The rust-analyzer process, shared so the keepalive can kill it while a
+/// stuck request holds the client.
+pub type ProcessHandle = Arc<Mutex<Option<Child>>>;
+
 pub struct RustAnalyzerClient {
-    process: Option<Child>,
+    process: ProcessHandle,
+    stdin: Option<ChildStdin>,
+    stdout: Option<ChildStdout>,
     request_id: u64,
     initialized: bool,
     diagnostics: Arc<Mutex<HashMap<String, Vec<Diagnostic>>>>,
+    /// Versions and content hashes of the documents opened with `didOpen`,
+    /// keyed by URI.
+    document_versions: HashMap<String, DocumentVersion>,
+    packages: PackageResolver,
+    /// Settings sent as `initializationOptions`; also the answer to
+    /// `workspace/configuration` requests for the `rust-analyzer` section.
+    settings: Value,
+    full_analysis: bool,
+    config: AnalyzerConfig,
+    allow_writes: bool,
+    /// The `rootUri` rust-analyzer was started with, resolved by `start`.
+    project_root: Option<PathBuf>,
 }
 
 impl Default for RustAnalyzerClient {
@@ -38,56 +261,180 @@ impl Default for RustAnalyzerClient {
 
 impl RustAnalyzerClient {
     pub fn new() -> Self {
+        Self::with_config(AnalyzerConfig::default())
+    }
+
+    pub fn with_config(config: AnalyzerConfig) -> Self {
         Self {
-            process: None,
+            process: Arc::new(Mutex::new(None)),
+            stdin: None,
+            stdout: None,
             request_id: 0,
             initialized: false,
             diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            document_versions: HashMap::new(),
+            packages: PackageResolver::default(),
+            settings: Value::Null,
+            full_analysis: true,
+            config,
+            allow_writes: true,
+            project_root: None,
+        }
+    }
+
+    /// In read-only mode (`allow_writes == false`) every file mutation is
+    /// reported as a diff instead of being written.
+    pub fn with_allow_writes(mut self, allow_writes: bool) -> Self {
+        self.allow_writes = allow_writes;
+        self
+    }
+
+    pub fn allow_writes(&self) -> bool {
+        self.allow_writes
+    }
+
+    /// The project directory rust-analyzer was started in: the nearest
+    /// directory at or above the current one with a `Cargo.toml`.
+    pub fn project_root(&self) -> Option<&Path> {
+        self.project_root.as_deref()
+    }
+
+    /// Writes `updated` over `original` at `file_path`. In read-only mode the
+    /// file is left untouched and the unified diff is returned instead.
+    pub async fn write_file(
+        &self,
+        file_path: &str,
+        original: &str,
+        updated: &str,
+    ) -> Result<Option<String>> {
+        if self.allow_writes {
+            fs::write(file_path, updated)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", file_path, e))?;
+            return Ok(None);
         }
+
+        Ok(Some(unified_diff(
+            original,
+            updated,
+            &format!("a{file_path}"),
+            &format!("b{file_path}"),
+        )))
     }
 
     pub async fn start(&mut self) -> Result<()> {
-        let rust_analyzer_path = get_rust_analyzer_path();
-        let child = tokio::process::Command::new(&rust_analyzer_path)
+        let project_root = find_project_root(&std::env::current_dir()?)?;
+        // stdout carries the MCP transport.
+        eprintln!("rust-analyzer project root: {}", project_root.display());
+        self.project_root = Some(project_root);
+
+        let rust_analyzer_path = self
+            .config
+            .path
+            .clone()
+            .unwrap_or_else(get_rust_analyzer_path);
+        let mut child = tokio::process::Command::new(&rust_analyzer_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
 
-        self.process = Some(child);
+        self.stdin = child.stdin.take();
+        self.stdout = child.stdout.take();
+        if let Ok(mut process) = self.process.lock() {
+            *process = Some(child);
+        }
         self.initialize().await?;
         Ok(())
     }
 
+    /// Kills the rust-analyzer process and starts a fresh one. Open documents
+    /// and cached diagnostics belong to the old process and are dropped.
+    pub async fn restart(&mut self) -> Result<()> {
+        let child = self
+            .process
+            .lock()
+            .ok()
+            .and_then(|mut process| process.take());
+        if let Some(mut child) = child {
+            let _ = child.kill().await;
+        }
+        self.stdin = None;
+        self.stdout = None;
+        self.initialized = false;
+        self.document_versions.clear();
+        if let Ok(mut store) = self.diagnostics.lock() {
+            store.clear();
+        }
+        self.start().await
+    }
+
+    /// Handle to the running process that does not need the client itself.
+    pub fn process_handle(&self) -> ProcessHandle {
+        self.process.clone()
+    }
+
+    pub fn full_analysis(&self) -> bool {
+        self.full_analysis
+    }
+
+    /// Switches proc-macro expansion and build script out dirs on or off
+    /// without a restart. rust-analyzer answers `didChangeConfiguration` by
+    /// asking for the settings again, so the new values are served from
+    /// `settings`. Switching makes it reload the workspace.
+    pub async fn set_full_analysis(&mut self, full: bool) -> Result<()> {
+        self.ensure_initialized()?;
+        if self.full_analysis == full {
+            return Ok(());
+        }
+
+        merge_json(&mut self.settings, &analysis_settings(full));
+        self.full_analysis = full;
+        self.send_notification(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": { "rust-analyzer": self.settings } }),
+        )
+        .await
+    }
+
+    /// Cheapest round trip rust-analyzer answers: a `workspace/symbol` query
+    /// that matches nothing. An error response still proves it is responsive.
+    pub async fn ping(&mut self) -> Result<()> {
+        self.ensure_initialized()?;
+        self.send_request_internal(
+            "workspace/symbol",
+            json!({ "query": "__rust_mcp_keepalive__" }),
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn initialize(&mut self) -> Result<()> {
-        // Get current working directory
-        let current_dir = std::env::current_dir()?;
-        let root_uri = format!("file://{}", current_dir.display());
-
-        let full_analysis = std::env::var("RUST_MCP_FULL_ANALYSIS")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse::<bool>()
-            .unwrap_or(true);
-
-        let initialization_options = if full_analysis {
-            json!({
-                "cargo": {
-                    "loadOutDirsFromCheck": true
-                },
-                "procMacro": {
-                    "enable": true
-                }
-            })
-        } else {
-            json!({
-                "cargo": {
-                    "loadOutDirsFromCheck": false
-                },
-                "procMacro": {
-                    "enable": false
-                }
-            })
+        let root = match &self.project_root {
+            Some(root) => root.clone(),
+            None => std::env::current_dir()?,
         };
+        let root_uri = format!("file://{}", root.display());
+
+        let full_analysis = self.config.full_analysis.unwrap_or_else(|| {
+            std::env::var("RUST_MCP_FULL_ANALYSIS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .unwrap_or(true)
+        });
+
+        let mut initialization_options = analysis_settings(full_analysis);
+        // Type hints are read back verbatim by `infer_type`; the default
+        // `maxLength` of 25 would cut long iterator and closure types short.
+        merge_json(
+            &mut initialization_options,
+            &json!({ "inlayHints": { "maxLength": null } }),
+        );
+        if let Some(overrides) = &self.config.init_options {
+            merge_json(&mut initialization_options, overrides);
+        }
+        self.settings = initialization_options.clone();
+        self.full_analysis = full_analysis;
 
         // Send initialize request
         let init_params = json!({
@@ -116,7 +463,8 @@ impl RustAnalyzerClient {
                 "workspace": {
                     "symbol": {
                         "dynamicRegistration": false
-                    }
+                    },
+                    "configuration": true
                 }
             }
         });
@@ -156,15 +504,8 @@ impl RustAnalyzerClient {
     }
 
     async fn send_message(&mut self, message: &Value) -> Result<()> {
-        let content = message.to_string();
-        let header = format!("Content-Length: {}\r\n\r\n", content.len());
-
-        if let Some(child) = &mut self.process {
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(header.as_bytes()).await?;
-                stdin.write_all(content.as_bytes()).await?;
-                stdin.flush().await?;
-            }
+        if let Some(stdin) = self.stdin.as_mut() {
+            write_message(stdin, message).await?;
         }
 
         Ok(())
@@ -173,54 +514,16 @@ impl RustAnalyzerClient {
     async fn read_response(&mut self, expected_id: u64) -> Result<Value> {
         let diagnostics_store = self.diagnostics.clone();
 
-        if let Some(child) = &mut self.process {
-            if let Some(stdout) = child.stdout.as_mut() {
-                let mut reader = BufReader::new(stdout);
-
-                loop {
-                    // Read headers
-                    let mut content_length: Option<usize> = None;
-                    loop {
-                        let mut line = String::new();
-                        reader.read_line(&mut line).await?;
-
-                        if line == "\r\n" {
-                            break;
-                        }
-
-                        if let Some(stripped) = line.strip_prefix("Content-Length:") {
-                            let length_str = stripped.trim();
-                            content_length = Some(length_str.parse()?);
-                        }
-                    }
-
-                    if let Some(length) = content_length {
-                        let mut content = vec![0u8; length];
-                        reader.read_exact(&mut content).await?;
-
-                        let response: Value = serde_json::from_slice(&content)?;
-
-                        if let Some(id) = response.get("id") {
-                            if id.as_u64() == Some(expected_id) {
-                                return Ok(response);
-                            }
-                        } else {
-                            // Notification - inline handling
-                            if let Some(method) = response.get("method").and_then(|m| m.as_str()) {
-                                if method == "textDocument/publishDiagnostics" {
-                                    if let Some(params) = response.get("params") {
-                                        if let Ok(diag_params) = serde_json::from_value::<PublishDiagnosticsParams>(params.clone()) {
-                                            if let Ok(mut store) = diagnostics_store.lock() {
-                                                store.insert(diag_params.uri, diag_params.diagnostics);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        if let (Some(stdout), Some(stdin)) = (self.stdout.as_mut(), self.stdin.as_mut()) {
+            let mut reader = BufReader::new(stdout);
+            return read_until_response(
+                &mut reader,
+                stdin,
+                expected_id,
+                &diagnostics_store,
+                &self.settings,
+            )
+            .await;
         }
 
         Err(anyhow::anyhow!("Failed to read response"))
@@ -242,7 +545,7 @@ impl RustAnalyzerClient {
             .ok_or_else(|| anyhow::anyhow!("Missing result field in LSP response"))
     }
 
-    fn position_in_range(range: &Range, position: &Position) -> bool {
+    pub(crate) fn position_in_range(range: &Range, position: &Position) -> bool {
         let starts_before = range.start.line < position.line
             || (range.start.line == position.line && range.start.character <= position.character);
         let ends_after = range.end.line > position.line
@@ -250,15 +553,37 @@ impl RustAnalyzerClient {
         starts_before && ends_after
     }
 
+    /// Normalizes every shape a location-returning request may answer with
+    /// (`Location`, `Location[]`, `LocationLink[]`) into plain locations.
+    fn response_locations(response: DefinitionResponse) -> Vec<Location> {
+        match response {
+            DefinitionResponse::SingleLocation(location) => vec![location],
+            DefinitionResponse::LocationArray(locations) => locations,
+            DefinitionResponse::LocationLinks(links) => links
+                .into_iter()
+                .map(|link| Location {
+                    uri: link.target_uri,
+                    range: link.target_selection_range,
+                })
+                .collect(),
+        }
+    }
+
     fn select_definition_location(definition: DefinitionResponse) -> Option<Location> {
-        match definition {
-            DefinitionResponse::SingleLocation(location) => Some(location),
-            DefinitionResponse::LocationArray(mut locations) => locations.pop(),
-            DefinitionResponse::LocationLinks(mut links) => links.pop().map(|link| Location {
-                uri: link.target_uri,
-                range: link.target_selection_range,
-            }),
+        Self::response_locations(definition).pop()
+    }
+
+    fn parse_reference_locations(result: Value) -> Result<Vec<Location>> {
+        if result.is_null() {
+            return Ok(Vec::new());
         }
+        let response: DefinitionResponse = serde_json::from_value(result)?;
+        Ok(Self::response_locations(response))
+    }
+
+    fn location_within(location: &Location, scope: &Path) -> bool {
+        let path = location.uri.strip_prefix("file://").unwrap_or(&location.uri);
+        Path::new(path).starts_with(scope)
     }
 
     fn find_symbol_path_in_document_symbols(
@@ -406,33 +731,75 @@ impl RustAnalyzerClient {
         ))
     }
 
+    /// References to the symbol at the position. With `within` (a file or a
+    /// directory), only locations under that path are listed.
     pub async fn find_references(
         &mut self,
         file_path: &str,
         line: u32,
         character: u32,
+        within: Option<&Path>,
     ) -> Result<String> {
-        if !self.initialized {
-            return Err(anyhow::anyhow!("Client not initialized"));
+        let mut locations = self.reference_locations(file_path, line, character).await?;
+        let total = locations.len();
+        if let Some(scope) = within {
+            locations.retain(|location| Self::location_within(location, scope));
+        }
+        if locations.is_empty() {
+            return Ok(match within {
+                Some(scope) if total > 0 => format!(
+                    "No references found under {} ({} in the workspace)",
+                    scope.display(),
+                    total
+                ),
+                _ => "No references found".to_string(),
+            });
         }
 
-        let params = create_references_params(file_path, line, character);
-        let response = self
-            .send_request_internal("textDocument/references", params)
-            .await?;
-
-        Ok(format!("References response: {response}"))
+        let mut output = match within {
+            Some(scope) => format!(
+                "Found {} references under {} ({} in the workspace):",
+                locations.len(),
+                scope.display(),
+                total
+            ),
+            None => format!("Found {} references:", locations.len()),
+        };
+        for location in &locations {
+            let start = &location.range.start;
+            output.push_str(&format!(
+                "\n{}:{}:{}",
+                location.uri,
+                start.line + 1,
+                start.character + 1
+            ));
+        }
+        Ok(output)
     }
 
-    pub async fn get_diagnostics(&mut self, file_path: &str) -> Result<String> {
+    /// Sends `text` as the current content of `file_path`: `didOpen` the
+    /// first time, a full-text `didChange` with a bumped version afterwards.
+    pub async fn sync_document(&mut self, file_path: &str, text: &str) -> Result<()> {
         self.ensure_initialized()?;
-        
-        let uri = format!("file://{}", file_path);
 
-        // 1. Open the file to ensure analysis is fresh and we get diagnostics
-        match fs::read_to_string(file_path).await {
-            Ok(text) => {
-                 let did_open_params = json!({
+        let uri = format!("file://{}", file_path);
+        match self.document_versions.get_mut(&uri) {
+            Some(document) => {
+                *document = DocumentVersion::new(document.version + 1, text);
+                let did_change_params = json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "version": document.version
+                    },
+                    "contentChanges": [{ "text": text }]
+                });
+                self.send_notification("textDocument/didChange", did_change_params)
+                    .await
+            }
+            None => {
+                self.document_versions
+                    .insert(uri.clone(), DocumentVersion::new(1, text));
+                let did_open_params = json!({
                     "textDocument": {
                         "uri": uri,
                         "languageId": "rust",
@@ -440,8 +807,63 @@ impl RustAnalyzerClient {
                         "text": text
                     }
                 });
-                self.send_notification("textDocument/didOpen", did_open_params).await?;
+                self.send_notification("textDocument/didOpen", did_open_params)
+                    .await
             }
+        }
+    }
+
+    /// Sends `didClose` for `file_path`, after which rust-analyzer reads it
+    /// from disk again. Does nothing for a document that was never synced.
+    pub async fn close_document(&mut self, file_path: &str) -> Result<()> {
+        let uri = format!("file://{}", file_path);
+        if self.document_versions.remove(&uri).is_none() {
+            return Ok(());
+        }
+        self.send_notification(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+        .await
+    }
+
+    /// The version rust-analyzer has of `file_path`, if it was ever synced.
+    pub fn document_version(&self, file_path: &str) -> Option<DocumentVersion> {
+        self.document_versions
+            .get(&format!("file://{}", file_path))
+            .copied()
+    }
+
+    /// Fails when `content`, just read from disk, is not what rust-analyzer
+    /// last received for `file_path`: positions and edits it computed refer
+    /// to the old text. The new content is synced before failing, so a retry
+    /// works from fresh positions. Files never synced are read from disk by
+    /// rust-analyzer itself and always pass.
+    pub async fn ensure_document_current(&mut self, file_path: &str, content: &str) -> Result<()> {
+        let Some(known) = self.document_version(file_path) else {
+            return Ok(());
+        };
+        if known.matches(content) {
+            return Ok(());
+        }
+        self.sync_document(file_path, content).await?;
+        Err(anyhow::anyhow!(
+            "{} changed on disk since rust-analyzer saw version {}: file changed, recompute positions and retry",
+            file_path,
+            known.version
+        ))
+    }
+
+    /// Syncs `file_path` from disk and returns its published diagnostics, or
+    /// `None` when rust-analyzer has not published any for it yet.
+    pub async fn file_diagnostics(&mut self, file_path: &str) -> Result<Option<Vec<Diagnostic>>> {
+        self.ensure_initialized()?;
+        
+        let uri = format!("file://{}", file_path);
+
+        // 1. Sync the file to ensure analysis is fresh and we get diagnostics
+        match fs::read_to_string(file_path).await {
+            Ok(text) => self.sync_document(file_path, &text).await?,
             Err(e) => {
                  return Err(anyhow::anyhow!("Failed to read file for diagnostics: {}", e));
             }
@@ -455,13 +877,79 @@ impl RustAnalyzerClient {
 
         // 3. Check if we have diagnostics in our store
         let diagnostics_lock = self.diagnostics.lock().map_err(|e| anyhow::anyhow!("Failed to lock diagnostics: {}", e))?;
-        if let Some(diagnostics) = diagnostics_lock.get(&uri) {
+        Ok(diagnostics_lock.get(&uri).cloned())
+    }
+
+    /// Syncs `text` as the content of `file_path` without touching disk and
+    /// returns the diagnostics rust-analyzer publishes for it, or `None` when
+    /// none arrive after a few polls. Diagnostics stored for the previous
+    /// text are dropped first so they are not mistaken for the new ones.
+    pub async fn diagnostics_for_text(
+        &mut self,
+        file_path: &str,
+        text: &str,
+    ) -> Result<Option<Vec<Diagnostic>>> {
+        self.ensure_initialized()?;
+
+        let uri = format!("file://{}", file_path);
+        self.diagnostics
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to lock diagnostics: {}", e))?
+            .remove(&uri);
+        self.sync_document(file_path, text).await?;
+
+        for attempt in 0..5 {
+            if attempt > 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            }
+            // Any request pumps the notifications that arrived meanwhile.
+            let _ = self.request_document_symbols(&uri).await;
+            let diagnostics = self
+                .diagnostics
+                .lock()
+                .map_err(|e| anyhow::anyhow!("Failed to lock diagnostics: {}", e))?;
+            if let Some(published) = diagnostics.get(&uri) {
+                return Ok(Some(published.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Runs `check`, which may hand rust-analyzer other text for `file_paths`
+    /// (through [`Self::diagnostics_for_text`] or [`Self::sync_document`]),
+    /// then puts it back on the files as they are on disk: documents that
+    /// were open are synced from disk again, the others closed.
+    pub async fn with_document_text<T>(
+        &mut self,
+        file_paths: &[&str],
+        check: impl AsyncFnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let was_open: Vec<bool> = file_paths
+            .iter()
+            .map(|file_path| self.document_version(file_path).is_some())
+            .collect();
+        let outcome = check(self).await;
+        for (file_path, was_open) in file_paths.iter().zip(was_open) {
+            if was_open {
+                let current = fs::read_to_string(file_path)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+                self.sync_document(file_path, &current).await?;
+            } else {
+                self.close_document(file_path).await?;
+            }
+        }
+        outcome
+    }
+
+    pub async fn get_diagnostics(&mut self, file_path: &str) -> Result<String> {
+        if let Some(diagnostics) = self.file_diagnostics(file_path).await? {
             if diagnostics.is_empty() {
                  return Ok("No diagnostics found.".to_string());
             }
 
             let mut result = format!("Diagnostics for {}:\n\n", file_path);
-            for diag in diagnostics {
+            for diag in &diagnostics {
                 let severity = match diag.severity.unwrap_or(1) {
                     1 => "ERROR",
                     2 => "WARNING",
@@ -487,17 +975,17 @@ impl RustAnalyzerClient {
         }
     }
 
-    pub async fn workspace_symbols(&mut self, query: &str) -> Result<String> {
-        if !self.initialized {
-            return Err(anyhow::anyhow!("Client not initialized"));
-        }
+    /// Returns the raw `SymbolInformation` entries rust-analyzer reports for `query`.
+    pub async fn workspace_symbol_infos(&mut self, query: &str) -> Result<Vec<Value>> {
+        self.ensure_initialized()?;
 
         let params = create_workspace_symbol_params(query);
         let response = self
             .send_request_internal("workspace/symbol", params)
             .await?;
 
-        Ok(format!("Workspace symbols response: {response}"))
+        let result_value = Self::extract_result(&response)?;
+        Ok(result_value.as_array().cloned().unwrap_or_default())
     }
 
     pub async fn get_hover(
@@ -522,15 +1010,155 @@ impl RustAnalyzerClient {
         Ok(hover.contents.value)
     }
 
-    pub async fn get_document_symbols(&mut self, file_path: &str) -> Result<String> {
+    /// Hover over a range instead of a position, a rust-analyzer extension
+    /// that describes the type of the selected expression. `None` when the
+    /// range is not an expression (or the server does not support it).
+    pub async fn hover_range(&mut self, file_path: &str, range: &Range) -> Result<Option<String>> {
+        self.ensure_initialized()?;
+
+        let params = json!({
+            "textDocument": {
+                "uri": format!("file://{}", file_path)
+            },
+            "position": range
+        });
+        let response = self
+            .send_request_internal("textDocument/hover", params)
+            .await?;
+        if response.get("error").is_some() {
+            return Ok(None);
+        }
+
+        let result_value = Self::extract_result(&response)?;
+        if result_value.is_null() {
+            return Ok(None);
+        }
+        let hover: Hover = serde_json::from_value(result_value)?;
+        Ok(Some(hover.contents.value))
+    }
+
+    pub async fn document_symbols(&mut self, file_path: &str) -> Result<DocumentSymbolResponse> {
+        self.ensure_initialized()?;
+        self.request_document_symbols(&format!("file://{}", file_path))
+            .await
+    }
+
+    pub async fn get_document_symbols(
+        &mut self,
+        file_path: &str,
+        line_range: Option<(u32, u32)>,
+    ) -> Result<String> {
         self.ensure_initialized()?;
 
         let uri = format!("file://{}", file_path);
         let symbols = self.request_document_symbols(&uri).await?;
 
+        let symbols = match line_range {
+            Some((start_line, end_line)) => {
+                let filter = Range {
+                    start: Position {
+                        line: start_line,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: end_line,
+                        character: u32::MAX,
+                    },
+                };
+                Self::filter_symbols_by_range(symbols, &filter)
+            }
+            None => symbols,
+        };
+
         Ok(serde_json::to_string_pretty(&symbols)?)
     }
 
+    fn ranges_overlap(range: &Range, filter: &Range) -> bool {
+        Self::position_in_range(filter, &range.start)
+            || Self::position_in_range(filter, &range.end)
+            || Self::position_in_range(range, &filter.start)
+    }
+
+    fn filter_document_symbols(symbols: Vec<DocumentSymbol>, filter: &Range) -> Vec<DocumentSymbol> {
+        symbols
+            .into_iter()
+            .filter(|symbol| Self::ranges_overlap(&symbol.range, filter))
+            .map(|mut symbol| {
+                symbol.children = symbol
+                    .children
+                    .take()
+                    .map(|children| Self::filter_document_symbols(children, filter))
+                    .filter(|children| !children.is_empty());
+                symbol
+            })
+            .collect()
+    }
+
+    fn filter_symbols_by_range(
+        symbols: DocumentSymbolResponse,
+        filter: &Range,
+    ) -> DocumentSymbolResponse {
+        match symbols {
+            DocumentSymbolResponse::DocumentSymbols(symbols) => {
+                DocumentSymbolResponse::DocumentSymbols(Self::filter_document_symbols(
+                    symbols, filter,
+                ))
+            }
+            DocumentSymbolResponse::SymbolInformation(infos) => {
+                DocumentSymbolResponse::SymbolInformation(
+                    infos
+                        .into_iter()
+                        .filter(|info| Self::ranges_overlap(&info.location.range, filter))
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// The chain of symbols enclosing the innermost symbol at `position`,
+    /// outermost first. The innermost symbol itself is not included.
+    fn symbol_ancestors(symbols: &[DocumentSymbol], position: &Position) -> Vec<DocumentSymbol> {
+        let mut ancestors = Vec::new();
+        let mut level = symbols;
+        while let Some(symbol) = level
+            .iter()
+            .find(|symbol| Self::position_in_range(&symbol.range, position))
+        {
+            let Some(children) = symbol.children.as_deref().filter(|children| {
+                children
+                    .iter()
+                    .any(|child| Self::position_in_range(&child.range, position))
+            }) else {
+                break;
+            };
+            ancestors.push(DocumentSymbol {
+                children: None,
+                ..symbol.clone()
+            });
+            level = children;
+        }
+        ancestors
+    }
+
+    /// Symbols (modules, impls, traits, ...) enclosing the item at `position`,
+    /// outermost first. Flat symbol responses carry no nesting and yield none.
+    pub async fn enclosing_symbols(
+        &mut self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Vec<DocumentSymbol>> {
+        self.ensure_initialized()?;
+        match self
+            .request_document_symbols(&format!("file://{}", file_path))
+            .await?
+        {
+            DocumentSymbolResponse::DocumentSymbols(symbols) => {
+                Ok(Self::symbol_ancestors(&symbols, &position))
+            }
+            DocumentSymbolResponse::SymbolInformation(_) => Ok(Vec::new()),
+        }
+    }
+
     fn find_symbol_range_recursive(
         symbols: &[DocumentSymbol],
         position: &Position,
@@ -660,9 +1288,25 @@ impl RustAnalyzerClient {
     }
 
     pub async fn apply_workspace_edit(&mut self, edit: WorkspaceEdit) -> Result<String> {
-        let mut files_updated = 0;
+        self.apply_workspace_edit_checked(edit, false).await
+    }
 
+    /// Applies every file of `edit` or none of them. All edits are computed
+    /// before the first write; a failed write, or with `verify` a failing
+    /// `cargo check` of the workspace afterwards, restores every edited file.
+    /// Nothing is written when an open file changed on disk since
+    /// rust-analyzer computed the edit.
+    pub async fn apply_workspace_edit_checked(
+        &mut self,
+        edit: WorkspaceEdit,
+        verify: bool,
+    ) -> Result<String> {
+        let mut updates = Vec::new();
+        let mut stale = Vec::new();
         if let Some(changes) = edit.changes {
+            let mut changes: Vec<_> = changes.into_iter().collect();
+            changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
             for (uri, edits) in changes {
                 let file_path = if uri.starts_with("file://") {
                     uri.strip_prefix("file://").unwrap().to_string()
@@ -671,18 +1315,109 @@ impl RustAnalyzerClient {
                 };
 
                 let content = fs::read_to_string(&file_path).await?;
+                if let Err(e) = self.ensure_document_current(&file_path, &content).await {
+                    stale.push(e.to_string());
+                    continue;
+                }
                 let updated_content = self.apply_text_edits(&content, edits)?;
-                
-                fs::write(&file_path, updated_content).await?;
-                files_updated += 1;
+                updates.push((file_path, content, updated_content));
             }
         }
+        if !stale.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No files were changed; the edit was computed from outdated content:\n{}",
+                stale.join("\n")
+            ));
+        }
 
-        Ok(format!("Successfully applied edits to {} file(s).", files_updated))
-    }
+        if !self.allow_writes {
+            let mut diffs = Vec::new();
+            for (file_path, content, updated_content) in &updates {
+                if let Some(diff) = self.write_file(file_path, content, updated_content).await? {
+                    diffs.push(diff);
+                }
+            }
+            return Ok(format!(
+                "Writes are disabled (read-only mode); no files were changed. Proposed edits:\n{}",
+                diffs.join("\n")
+            ));
+        }
 
-    fn apply_text_edits(&self, content: &str, mut edits: Vec<TextEdit>) -> Result<String> {
-        // Sort edits in reverse order (by line, then character) to avoid shifting issues
+        let mut transaction = FileTransaction::new();
+        for (file_path, content, updated_content) in &updates {
+            if let Err(e) = transaction.write(file_path, content, updated_content).await {
+                let restored = transaction.files().len();
+                let failures = transaction.rollback().await;
+                return Err(Self::rollback_error(
+                    format!("{e}; restored {restored} file(s) already written"),
+                    failures,
+                ));
+            }
+        }
+
+        let message = if verify && !updates.is_empty() {
+            let workspace = match &self.project_root {
+                Some(root) => Ok(root.clone()),
+                None => std::env::current_dir().map_err(anyhow::Error::from),
+            };
+            let check = match workspace {
+                Ok(workspace) => {
+                    self.cargo_check_status(&workspace.to_string_lossy(), None, None)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            let failure = match check {
+                Ok((true, _)) => None,
+                Ok((false, report)) => Some((
+                    "cargo check failed after the edit".to_string(),
+                    format!("\n\n{report}"),
+                )),
+                Err(e) => Some((
+                    format!("cargo check could not run after the edit ({e})"),
+                    String::new(),
+                )),
+            };
+            if let Some((reason, report)) = failure {
+                let files = transaction.files().join(", ");
+                let failures = transaction.rollback().await;
+                return Err(Self::rollback_error(
+                    format!("{reason}; rolled back {files}{report}"),
+                    failures,
+                ));
+            }
+            format!(
+                "Successfully applied edits to {} file(s); cargo check passed.",
+                updates.len()
+            )
+        } else {
+            format!("Successfully applied edits to {} file(s).", updates.len())
+        };
+
+        // Keep open documents in step with disk so the next edit passes the
+        // staleness check.
+        for (file_path, _, updated_content) in &updates {
+            if self.document_version(file_path).is_some() {
+                self.sync_document(file_path, updated_content).await?;
+            }
+        }
+
+        Ok(message)
+    }
+
+    fn rollback_error(message: String, failures: Vec<String>) -> anyhow::Error {
+        if failures.is_empty() {
+            return anyhow::anyhow!(message);
+        }
+        anyhow::anyhow!("{message}\n\nCould not restore:\n{}", failures.join("\n"))
+    }
+
+    pub(crate) fn apply_text_edits(
+        &self,
+        content: &str,
+        mut edits: Vec<TextEdit>,
+    ) -> Result<String> {
+        // Sort edits in reverse order (by line, then character) to avoid shifting issues
         edits.sort_by(|a, b| {
             if a.range.start.line != b.range.start.line {
                 b.range.start.line.cmp(&a.range.start.line)
@@ -759,12 +1494,16 @@ impl RustAnalyzerClient {
         Ok(result)
     }
 
+    /// Renames the symbol at the position. With `limit_to`, only edits inside
+    /// that range of `file_path` are applied; the rest are dropped.
     pub async fn rename_symbol(
         &mut self,
         file_path: &str,
         line: u32,
         character: u32,
         new_name: &str,
+        limit_to: Option<&Range>,
+        verify: bool,
     ) -> Result<String> {
         self.ensure_initialized()?;
 
@@ -779,7 +1518,89 @@ impl RustAnalyzerClient {
         }
 
         let edit: WorkspaceEdit = serde_json::from_value(result_value)?;
-        self.apply_workspace_edit(edit).await
+        let Some(range) = limit_to else {
+            return self.apply_workspace_edit_checked(edit, verify).await;
+        };
+
+        let uri = format!("file://{}", file_path);
+        let (edit, skipped) = Self::restrict_edit(edit, &uri, range);
+        let mut message = self.apply_workspace_edit_checked(edit, verify).await?;
+        if skipped > 0 {
+            message.push_str(&format!(
+                "\nSkipped {} edit(s) outside the enclosing function.",
+                skipped
+            ));
+        }
+        Ok(message)
+    }
+
+    /// Keeps only the edits of `uri` that lie within `range`, returning the
+    /// narrowed edit and how many edits were dropped.
+    fn restrict_edit(edit: WorkspaceEdit, uri: &str, range: &Range) -> (WorkspaceEdit, usize) {
+        let mut skipped = 0;
+        let changes = edit.changes.map(|changes| {
+            changes
+                .into_iter()
+                .filter_map(|(file_uri, edits)| {
+                    let total = edits.len();
+                    let kept: Vec<TextEdit> = if file_uri == uri {
+                        edits
+                            .into_iter()
+                            .filter(|edit| {
+                                Self::position_in_range(range, &edit.range.start)
+                                    && Self::position_in_range(range, &edit.range.end)
+                            })
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    skipped += total - kept.len();
+                    (!kept.is_empty()).then_some((file_uri, kept))
+                })
+                .collect()
+        });
+        (WorkspaceEdit { changes }, skipped)
+    }
+
+    /// Range of the innermost function or method containing `position`.
+    fn enclosing_function(symbols: &[DocumentSymbol], position: &Position) -> Option<Range> {
+        symbols
+            .iter()
+            .find(|symbol| Self::position_in_range(&symbol.range, position))
+            .and_then(|symbol| {
+                symbol
+                    .children
+                    .as_deref()
+                    .and_then(|children| Self::enclosing_function(children, position))
+                    // 6 = Method, 12 = Function
+                    .or_else(|| matches!(symbol.kind, 6 | 12).then(|| symbol.range.clone()))
+            })
+    }
+
+    /// Range of the innermost function or method in `file_path` containing the
+    /// position, if any.
+    pub async fn enclosing_function_range(
+        &mut self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<Range>> {
+        self.ensure_initialized()?;
+        match self
+            .request_document_symbols(&format!("file://{}", file_path))
+            .await?
+        {
+            DocumentSymbolResponse::DocumentSymbols(symbols) => {
+                Ok(Self::enclosing_function(&symbols, &position))
+            }
+            DocumentSymbolResponse::SymbolInformation(infos) => Ok(infos
+                .into_iter()
+                .filter(|info| {
+                    matches!(info.kind, 6 | 12)
+                        && Self::position_in_range(&info.location.range, &position)
+                })
+                .map(|info| info.location.range)
+                .min_by_key(|range| range.end.line - range.start.line)),
+        }
     }
 
     pub async fn format_code(&mut self, file_path: &str) -> Result<String> {
@@ -795,24 +1616,137 @@ impl RustAnalyzerClient {
         Ok(format!("Formatting response: {response}"))
     }
 
+    /// `textDocument/rangeFormatting` edits for `range`. An error response is
+    /// returned as `Err`: rust-analyzer refuses range formatting unless
+    /// `rustfmt.rangeFormatting.enable` is set and rustfmt is a nightly build.
+    pub async fn range_formatting(
+        &mut self,
+        file_path: &str,
+        range: &Range,
+    ) -> Result<Vec<TextEdit>> {
+        self.ensure_initialized()?;
+
+        let params = create_range_formatting_params(file_path, range);
+        let response = self
+            .send_request_internal("textDocument/rangeFormatting", params)
+            .await?;
+        Self::formatting_edits(&response)
+    }
+
+    pub async fn inlay_hints(&mut self, file_path: &str, range: &Range) -> Result<Vec<InlayHint>> {
+        self.ensure_initialized()?;
+
+        let params = create_inlay_hint_params(file_path, range);
+        let response = self
+            .send_request_internal("textDocument/inlayHint", params)
+            .await?;
+        let result = Self::extract_result(&response)?;
+        if result.is_null() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// `textDocument/formatting` edits for the whole document.
+    pub async fn document_formatting(&mut self, file_path: &str) -> Result<Vec<TextEdit>> {
+        self.ensure_initialized()?;
+
+        let params = create_formatting_params(file_path);
+        let response = self
+            .send_request_internal("textDocument/formatting", params)
+            .await?;
+        Self::formatting_edits(&response)
+    }
+
+    fn formatting_edits(response: &Value) -> Result<Vec<TextEdit>> {
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error");
+            return Err(anyhow::anyhow!("{}", message));
+        }
+        let result = Self::extract_result(response)?;
+        if result.is_null() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_value(result)?)
+    }
+
     pub async fn analyze_manifest(&mut self, manifest_path: &str) -> Result<String> {
         // This would analyze Cargo.toml file
         Ok(format!("Manifest analysis for: {manifest_path}"))
     }
 
-    pub async fn run_cargo_check(&mut self, workspace_path: &str) -> Result<String> {
-        let output = tokio::process::Command::new("cargo")
-            .arg("check")
-            .arg("--message-format=json")
-            .current_dir(workspace_path)
-            .output()
+    /// The workspace package that owns `file_path`, from cached `cargo metadata`.
+    pub async fn package_for_file(
+        &mut self,
+        workspace_path: &str,
+        file_path: &str,
+    ) -> Result<Option<String>> {
+        self.packages
+            .package_for_file(Path::new(workspace_path), Path::new(file_path))
+            .await
+    }
+
+    pub async fn run_cargo_check(
+        &mut self,
+        workspace_path: &str,
+        package: Option<&str>,
+        progress: Option<&CargoProgressSender>,
+    ) -> Result<String> {
+        let (_, result) = self
+            .cargo_check_status(workspace_path, package, progress)
             .await?;
+        Ok(result)
+    }
+
+    /// Runs `cargo check` and returns whether it succeeded with the report.
+    pub async fn cargo_check_status(
+        &mut self,
+        workspace_path: &str,
+        package: Option<&str>,
+        progress: Option<&CargoProgressSender>,
+    ) -> Result<(bool, String)> {
+        let mut command = tokio::process::Command::new("cargo");
+        command.arg("check").arg("--message-format=json");
+        if let Some(package) = package {
+            command.arg("-p").arg(package);
+        }
+        let mut child = command
+            .current_dir(workspace_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to capture cargo stdout"))?;
+        let mut stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to capture cargo stderr"))?;
+
+        // Drain stderr concurrently so cargo never blocks on a full pipe.
+        let stderr_task = tokio::spawn(async move {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).await?;
+            Ok::<_, anyhow::Error>(buf)
+        });
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut tracker = ProgressTracker::default();
         let mut result = String::new();
-        
-        for line in stdout.lines() {
-            if let Ok(msg) = serde_json::from_str::<Value>(line) {
+        let mut lines = BufReader::new(stdout).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if let Ok(msg) = serde_json::from_str::<Value>(&line) {
+                if let Some(update) = tracker.observe(&msg)
+                    && let Some(sender) = progress
+                {
+                    let _ = sender.send(update);
+                }
+
                 if msg.get("reason").and_then(|r| r.as_str()) == Some("compiler-message") {
                     if let Some(message) = msg.get("message") {
                         let level = message.get("level").and_then(|l| l.as_str()).unwrap_or("unknown");
@@ -826,16 +1760,26 @@ impl RustAnalyzerClient {
             }
         }
 
+        let status = child.wait().await?;
+        let stderr = stderr_task.await??;
+
         if result.is_empty() {
-            if output.status.success() {
-                Ok("Cargo check passed with no errors or warnings.".to_string())
+            if status.success() {
+                result = "Cargo check passed with no errors or warnings.".to_string();
             } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                Ok(format!("Cargo check failed:\n{}", stderr))
+                let stderr = String::from_utf8_lossy(&stderr);
+                result = format!("Cargo check failed:\n{}", stderr);
             }
-        } else {
-            Ok(result)
         }
+
+        // Without a progress channel the caller only sees the final result, so
+        // hand back the buffered progress log alongside it.
+        if progress.is_none() && !tracker.log().is_empty() {
+            result.push_str("\n\nProgress log:\n");
+            result.push_str(&tracker.log().join("\n"));
+        }
+
+        Ok((status.success(), result))
     }
 
     pub async fn extract_function(
@@ -922,6 +1866,66 @@ impl RustAnalyzerClient {
         }
     }
 
+    /// Code actions (not bare commands) rust-analyzer offers for a range.
+    pub async fn code_actions(
+        &mut self,
+        file_path: &str,
+        start: Position,
+        end: Position,
+    ) -> Result<Vec<CodeAction>> {
+        self.ensure_initialized()?;
+
+        let params = create_code_action_params(
+            file_path,
+            start.line,
+            start.character,
+            end.line,
+            end.character,
+        );
+        let response = self
+            .send_request_internal("textDocument/codeAction", params)
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        if result_value.is_null() {
+            return Ok(Vec::new());
+        }
+        let actions: CodeActionResponse = serde_json::from_value(result_value)?;
+
+        Ok(actions
+            .into_iter()
+            .filter_map(|a| match a {
+                CodeActionOrCommand::CodeAction(ca) => Some(ca),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect())
+    }
+
+    /// Requests rust-analyzer's "Fill match arms" assist for the `match` at the
+    /// given position. Returns `None` when the assist is not offered, which is
+    /// the case for matches that are already exhaustive.
+    pub async fn fill_match_arms(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<WorkspaceEdit>> {
+        let position = Position { line, character };
+        let action = self
+            .code_actions(file_path, position.clone(), position)
+            .await?
+            .into_iter()
+            .find(|ca| ca.title.to_lowercase().contains("match arms"));
+
+        match action {
+            Some(ca) => ca
+                .edit
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Fill match arms code action has no edit")),
+            None => Ok(None),
+        }
+    }
+
     pub async fn apply_clippy_suggestions(&mut self, file_path: &str) -> Result<String> {
         // This would apply clippy suggestions to the file
         Ok(format!("Applied clippy suggestions to {file_path}"))
@@ -992,6 +1996,236 @@ impl RustAnalyzerClient {
         Ok(items)
     }
 
+    pub async fn prepare_call_hierarchy(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<CallHierarchyItem>> {
+        self.ensure_initialized()?;
+
+        let params = create_text_document_position_params(file_path, line, character);
+        let response = self
+            .send_request_internal("textDocument/prepareCallHierarchy", params)
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        if result_value.is_null() {
+            return Ok(Vec::new());
+        }
+        let items: Vec<CallHierarchyItem> = serde_json::from_value(result_value)?;
+        Ok(items)
+    }
+
+    pub async fn incoming_calls(
+        &mut self,
+        item: CallHierarchyItem,
+    ) -> Result<Vec<CallHierarchyIncomingCall>> {
+        self.ensure_initialized()?;
+
+        let response = self
+            .send_request_internal("callHierarchy/incomingCalls", json!({ "item": item }))
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        if result_value.is_null() {
+            return Ok(Vec::new());
+        }
+        let calls: Vec<CallHierarchyIncomingCall> = serde_json::from_value(result_value)?;
+        Ok(calls)
+    }
+
+    pub async fn find_implementations(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<Location>> {
+        self.request_locations("textDocument/implementation", file_path, line, character)
+            .await
+    }
+
+    pub async fn definition_locations(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<Location>> {
+        self.request_locations("textDocument/definition", file_path, line, character)
+            .await
+    }
+
+    /// Every reference to the symbol at the position, its declaration included.
+    pub async fn reference_locations(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<Location>> {
+        self.ensure_initialized()?;
+
+        let params = create_references_params(file_path, line, character);
+        let response = self
+            .send_request_internal("textDocument/references", params)
+            .await?;
+        Self::parse_reference_locations(Self::extract_result(&response)?)
+    }
+
+    pub async fn type_definition_locations(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<Location>> {
+        self.request_locations("textDocument/typeDefinition", file_path, line, character)
+            .await
+    }
+
+    /// Label of the active signature from `textDocument/signatureHelp`, e.g.
+    /// `fn connect(addr: &str, timeout: Duration) -> Result<Conn>`. The
+    /// position must be inside a call's argument list.
+    pub async fn signature_help(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<String>> {
+        self.ensure_initialized()?;
+
+        let params = create_text_document_position_params(file_path, line, character);
+        let response = self
+            .send_request_internal("textDocument/signatureHelp", params)
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        let signatures = result_value
+            .get("signatures")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let active = result_value
+            .get("activeSignature")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as usize;
+
+        Ok(signatures
+            .get(active)
+            .or_else(|| signatures.first())
+            .and_then(|signature| signature.get("label"))
+            .and_then(|label| label.as_str())
+            .map(str::to_string))
+    }
+
+    /// The documentation URL rust-analyzer's `experimental/externalDocs`
+    /// reports for the symbol at the position (docs.rs for dependencies,
+    /// doc.rust-lang.org for the standard library).
+    pub async fn external_docs(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<String>> {
+        self.ensure_initialized()?;
+
+        let params = create_text_document_position_params(file_path, line, character);
+        let response = self
+            .send_request_internal("experimental/externalDocs", params)
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        // Either a bare URL or, for clients with local docs support, `{ web, local }`.
+        Ok(match result_value {
+            Value::String(url) => Some(url),
+            Value::Object(links) => links
+                .get("web")
+                .and_then(|web| web.as_str())
+                .map(str::to_string),
+            _ => None,
+        })
+    }
+
+    /// rust-analyzer's `rust-analyzer/expandMacro` at the position: the
+    /// macro's name and its recursive expansion, or `None` when no macro call
+    /// or derive is there. On a trait inside `#[derive(..)]`, only that
+    /// derive is expanded.
+    pub async fn expand_macro(
+        &mut self,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Option<(String, String)>> {
+        self.ensure_initialized()?;
+
+        let params = create_text_document_position_params(file_path, line, character);
+        let response = self
+            .send_request_internal("rust-analyzer/expandMacro", params)
+            .await?;
+
+        let result_value = Self::extract_result(&response)?;
+        let name = result_value.get("name").and_then(|name| name.as_str());
+        let expansion = result_value
+            .get("expansion")
+            .and_then(|expansion| expansion.as_str());
+        Ok(name
+            .zip(expansion)
+            .map(|(name, expansion)| (name.to_string(), expansion.to_string())))
+    }
+
+    /// The runnables (tests, test modules, doctests, binaries, benches) in
+    /// `file_path`, from rust-analyzer's `experimental/runnables`, as the
+    /// server returns them.
+    pub async fn runnables(&mut self, file_path: &str) -> Result<Vec<Value>> {
+        self.ensure_initialized()?;
+
+        let params = json!({
+            "textDocument": { "uri": format!("file://{}", file_path) },
+            "position": null
+        });
+        let response = self
+            .send_request_internal("experimental/runnables", params)
+            .await?;
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error");
+            return Err(anyhow::anyhow!(
+                "experimental/runnables failed: {}",
+                message
+            ));
+        }
+
+        match Self::extract_result(&response)? {
+            Value::Array(runnables) => Ok(runnables),
+            Value::Null => Ok(Vec::new()),
+            other => Err(anyhow::anyhow!(
+                "Unexpected experimental/runnables result: {}",
+                other
+            )),
+        }
+    }
+
+    async fn request_locations(
+        &mut self,
+        method: &str,
+        file_path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<Location>> {
+        self.ensure_initialized()?;
+
+        let params = create_text_document_position_params(file_path, line, character);
+        let response = self.send_request_internal(method, params).await?;
+
+        let result_value = Self::extract_result(&response)?;
+        if result_value.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let response: DefinitionResponse = serde_json::from_value(result_value)?;
+        Ok(Self::response_locations(response))
+    }
+
     pub async fn get_type_hierarchy(
         &mut self,
         file_path: &str,
@@ -1039,3 +2273,339 @@ impl RustAnalyzerClient {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, kind: u32, lines: (u32, u32), children: Vec<DocumentSymbol>) -> DocumentSymbol {
+        let range = Range {
+            start: Position {
+                line: lines.0,
+                character: 0,
+            },
+            end: Position {
+                line: lines.1,
+                character: 1,
+            },
+        };
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            range: range.clone(),
+            selection_range: range,
+            children: (!children.is_empty()).then_some(children),
+        }
+    }
+
+    #[test]
+    fn test_symbol_ancestors_of_nested_method() {
+        let symbols = vec![
+            symbol("helper", 12, (0, 2), vec![]),
+            symbol(
+                "net",
+                2,
+                (4, 20),
+                vec![symbol(
+                    "impl Server",
+                    19,
+                    (6, 18),
+                    vec![
+                        symbol("new", 6, (7, 9), vec![]),
+                        symbol("bind", 6, (11, 17), vec![]),
+                    ],
+                )],
+            ),
+        ];
+
+        let position = Position {
+            line: 12,
+            character: 8,
+        };
+        let names: Vec<_> = RustAnalyzerClient::symbol_ancestors(&symbols, &position)
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        assert_eq!(names, vec!["net", "impl Server"]);
+
+        let top_level = Position {
+            line: 1,
+            character: 0,
+        };
+        assert!(RustAnalyzerClient::symbol_ancestors(&symbols, &top_level).is_empty());
+    }
+
+    #[test]
+    fn test_filter_symbols_by_line_range() {
+        let symbols = DocumentSymbolResponse::DocumentSymbols(vec![
+            symbol("helper", 12, (0, 2), vec![]),
+            symbol(
+                "impl Server",
+                19,
+                (4, 20),
+                vec![
+                    symbol("new", 6, (5, 9), vec![]),
+                    symbol("bind", 6, (11, 17), vec![]),
+                ],
+            ),
+            symbol("tail", 12, (22, 24), vec![]),
+        ]);
+        let lines = |start: u32, end: u32| Range {
+            start: Position {
+                line: start,
+                character: 0,
+            },
+            end: Position {
+                line: end,
+                character: u32::MAX,
+            },
+        };
+        let names = |symbols: DocumentSymbolResponse| -> Vec<String> {
+            let DocumentSymbolResponse::DocumentSymbols(symbols) = symbols else {
+                panic!("expected document symbols");
+            };
+            let mut names = Vec::new();
+            let mut pending = symbols;
+            while let Some(symbol) = pending.pop() {
+                names.push(symbol.name);
+                pending.extend(symbol.children.unwrap_or_default());
+            }
+            names.sort();
+            names
+        };
+
+        // A single line keeps the symbols covering it.
+        let filtered = RustAnalyzerClient::filter_symbols_by_range(symbols.clone(), &lines(12, 12));
+        assert_eq!(names(filtered), vec!["bind", "impl Server"]);
+        // A range keeps every symbol it overlaps, even partly.
+        let filtered = RustAnalyzerClient::filter_symbols_by_range(symbols.clone(), &lines(1, 6));
+        assert_eq!(names(filtered), vec!["helper", "impl Server", "new"]);
+        let filtered = RustAnalyzerClient::filter_symbols_by_range(symbols, &lines(18, 30));
+        assert_eq!(names(filtered), vec!["impl Server", "tail"]);
+    }
+
+    #[test]
+    fn test_restrict_edit_to_function_range() {
+        let edit: WorkspaceEdit = serde_json::from_value(json!({
+            "changes": {
+                "file:///src/lib.rs": [
+                    {"range": {"start": {"line": 2, "character": 8}, "end": {"line": 2, "character": 13}}, "newText": "total"},
+                    {"range": {"start": {"line": 4, "character": 4}, "end": {"line": 4, "character": 9}}, "newText": "total"},
+                    {"range": {"start": {"line": 20, "character": 8}, "end": {"line": 20, "character": 13}}, "newText": "total"}
+                ],
+                "file:///src/other.rs": [
+                    {"range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 5}}, "newText": "total"}
+                ]
+            }
+        }))
+        .unwrap();
+        let function = symbol("sum", 12, (1, 5), vec![]).range;
+
+        let (edit, skipped) =
+            RustAnalyzerClient::restrict_edit(edit, "file:///src/lib.rs", &function);
+        let changes = edit.changes.unwrap();
+        assert_eq!(skipped, 2);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes["file:///src/lib.rs"].len(), 2);
+
+        let symbols = vec![symbol(
+            "impl Counter",
+            19,
+            (0, 30),
+            vec![symbol("sum", 6, (1, 5), vec![])],
+        )];
+        let position = Position {
+            line: 3,
+            character: 4,
+        };
+        assert_eq!(
+            RustAnalyzerClient::enclosing_function(&symbols, &position).map(|r| r.start.line),
+            Some(1)
+        );
+        let outside = Position {
+            line: 10,
+            character: 0,
+        };
+        assert!(RustAnalyzerClient::enclosing_function(&symbols, &outside).is_none());
+    }
+
+    fn frame(message: Value) -> String {
+        let content = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+    }
+
+    #[tokio::test]
+    async fn test_read_loop_answers_server_requests() {
+        let input = [
+            frame(json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "workspace/configuration",
+                "params": {"items": [
+                    {"section": "rust-analyzer"},
+                    {"section": "rust-analyzer.procMacro.enable"},
+                    {"section": "editor"}
+                ]}
+            })),
+            frame(json!({"jsonrpc": "2.0", "id": 1, "method": "window/workDoneProgress/create", "params": {"token": "rustAnalyzer/Indexing"}})),
+            frame(json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {"uri": "file:///src/lib.rs", "diagnostics": []}
+            })),
+            frame(json!({"jsonrpc": "2.0", "id": 2, "method": "experimental/unknown"})),
+            frame(json!({"jsonrpc": "2.0", "id": 5, "result": {"ok": true}})),
+        ]
+        .concat();
+
+        let settings = json!({"procMacro": {"enable": true}});
+        let diagnostics = Mutex::new(HashMap::new());
+        let mut reader = BufReader::new(input.as_bytes());
+        let mut written = Vec::new();
+
+        let response = read_until_response(&mut reader, &mut written, 5, &diagnostics, &settings)
+            .await
+            .unwrap();
+        assert_eq!(response["result"], json!({"ok": true}));
+        assert!(diagnostics.lock().unwrap().contains_key("file:///src/lib.rs"));
+
+        let written = String::from_utf8(written).unwrap();
+        let replies: Vec<Value> = written
+            .split("Content-Length: ")
+            .filter_map(|frame| frame.split_once("\r\n\r\n"))
+            .map(|(_, body)| serde_json::from_str(body).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["id"], 0);
+        assert_eq!(replies[0]["result"], json!([settings, true, null]));
+        assert_eq!(replies[1], json!({"jsonrpc": "2.0", "id": 1, "result": null}));
+        assert_eq!(replies[2]["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_switching_analysis_keeps_init_options() {
+        let mut settings = analysis_settings(true);
+        merge_json(&mut settings, &json!({"cargo": {"features": "all"}}));
+
+        merge_json(&mut settings, &analysis_settings(false));
+        assert_eq!(
+            configuration_section(&settings, Some("rust-analyzer.procMacro.enable")),
+            json!(false)
+        );
+        assert_eq!(
+            configuration_section(&settings, Some("rust-analyzer.cargo")),
+            json!({"loadOutDirsFromCheck": false, "features": "all"})
+        );
+    }
+
+    #[test]
+    fn test_reference_locations_from_location_array() {
+        let result = json!([
+            {
+                "uri": "file:///src/lib.rs",
+                "range": {
+                    "start": {"line": 3, "character": 4},
+                    "end": {"line": 3, "character": 9}
+                }
+            },
+            {
+                "uri": "file:///src/main.rs",
+                "range": {
+                    "start": {"line": 10, "character": 0},
+                    "end": {"line": 10, "character": 5}
+                }
+            }
+        ]);
+
+        let locations = RustAnalyzerClient::parse_reference_locations(result).unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].uri, "file:///src/lib.rs");
+        assert_eq!(locations[0].range.start.character, 4);
+        assert_eq!(locations[1].uri, "file:///src/main.rs");
+        assert_eq!(locations[1].range.start.line, 10);
+    }
+
+    #[test]
+    fn test_reference_locations_from_location_links() {
+        let result = json!([
+            {
+                "originSelectionRange": {
+                    "start": {"line": 1, "character": 0},
+                    "end": {"line": 1, "character": 3}
+                },
+                "targetUri": "file:///src/lib.rs",
+                "targetRange": {
+                    "start": {"line": 5, "character": 0},
+                    "end": {"line": 8, "character": 1}
+                },
+                "targetSelectionRange": {
+                    "start": {"line": 5, "character": 7},
+                    "end": {"line": 5, "character": 10}
+                }
+            }
+        ]);
+
+        let locations = RustAnalyzerClient::parse_reference_locations(result).unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].uri, "file:///src/lib.rs");
+        assert_eq!(locations[0].range.start.line, 5);
+        assert_eq!(locations[0].range.start.character, 7);
+    }
+
+    #[test]
+    fn test_reference_locations_from_null() {
+        let locations = RustAnalyzerClient::parse_reference_locations(Value::Null).unwrap();
+        assert!(locations.is_empty());
+    }
+
+    #[test]
+    fn test_reference_location_within_scope() {
+        let locations = RustAnalyzerClient::parse_reference_locations(json!([
+            {"uri": "file:///ws/src/net/tcp.rs", "range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 3}}},
+            {"uri": "file:///ws/src/network.rs", "range": {"start": {"line": 2, "character": 0}, "end": {"line": 2, "character": 3}}}
+        ]))
+        .unwrap();
+
+        let within = |scope: &str| {
+            locations
+                .iter()
+                .filter(|location| RustAnalyzerClient::location_within(location, Path::new(scope)))
+                .count()
+        };
+        assert_eq!(within("/ws/src/net"), 1);
+        assert_eq!(within("/ws/src/net/tcp.rs"), 1);
+        assert_eq!(within("/ws/src"), 2);
+    }
+
+    #[test]
+    fn test_document_version_matches_content() {
+        let document = DocumentVersion::new(3, "fn main() {}\n");
+        assert_eq!(document.version, 3);
+        assert!(document.matches("fn main() {}\n"));
+        assert!(!document.matches("fn main() { run(); }\n"));
+        assert_eq!(
+            DocumentVersion::new(4, "fn main() {}\n").content_hash,
+            document.content_hash
+        );
+    }
+
+    #[test]
+    fn test_find_project_root_walks_up() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-mcp-project-root-{}", std::process::id()));
+        let nested = dir.join("crates/core/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            dir.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(find_project_root(&nested).unwrap(), dir.join("crates/core"));
+        let error = find_project_root(&dir).unwrap_err().to_string();
+        assert!(error.starts_with("No Cargo project found"), "{error}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
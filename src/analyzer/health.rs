@@ -0,0 +1,257 @@
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::client::ProcessHandle;
+
+/// Default pause between two keepalive pings.
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+/// Default time a ping may take before the analyzer is considered stuck.
+pub const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Default number of consecutive ticks a tool call may hold the analyzer
+/// before it is considered stuck.
+pub const DEFAULT_KEEPALIVE_MAX_BUSY_TICKS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// No ping has completed yet.
+    Starting,
+    Healthy,
+    /// The last ping failed or timed out; a restart is pending.
+    Unhealthy,
+    Restarting,
+    /// The keepalive is turned off (`keepalive_interval_seconds = 0`).
+    Disabled,
+}
+
+/// Health of the rust-analyzer process as observed by the keepalive.
+#[derive(Debug, Clone)]
+pub struct AnalyzerHealth {
+    pub state: HealthState,
+    pub interval: Option<Duration>,
+    pub timeout: Duration,
+    last_ping: Option<Instant>,
+    last_latency: Option<Duration>,
+    /// Ticks skipped since the last ping because a tool call held the analyzer.
+    pub busy_ticks: u64,
+    /// Busy ticks after which the analyzer is killed; `0` never escalates.
+    pub max_busy_ticks: u64,
+    pub consecutive_failures: u64,
+    pub restarts: u64,
+    pub last_error: Option<String>,
+}
+
+impl AnalyzerHealth {
+    /// `interval == None` disables the keepalive.
+    pub fn new(interval: Option<Duration>, timeout: Duration) -> Self {
+        Self {
+            state: if interval.is_some() {
+                HealthState::Starting
+            } else {
+                HealthState::Disabled
+            },
+            interval,
+            timeout,
+            last_ping: None,
+            last_latency: None,
+            busy_ticks: 0,
+            max_busy_ticks: DEFAULT_KEEPALIVE_MAX_BUSY_TICKS,
+            consecutive_failures: 0,
+            restarts: 0,
+            last_error: None,
+        }
+    }
+
+    pub fn with_max_busy_ticks(mut self, max_busy_ticks: u64) -> Self {
+        self.max_busy_ticks = max_busy_ticks;
+        self
+    }
+
+    pub fn record_ping(&mut self, latency: Duration) {
+        self.state = HealthState::Healthy;
+        self.last_ping = Some(Instant::now());
+        self.last_latency = Some(latency);
+        self.busy_ticks = 0;
+        self.consecutive_failures = 0;
+    }
+
+    /// A tick that could not ping because the analyzer was in use. Returns
+    /// `true` once it has been in use for `max_busy_ticks` ticks in a row: the
+    /// call holding it is taken to be stuck and the analyzer unhealthy.
+    pub fn record_busy(&mut self) -> bool {
+        self.busy_ticks += 1;
+        if self.max_busy_ticks == 0 || self.busy_ticks < self.max_busy_ticks {
+            return false;
+        }
+        self.record_failure(format!(
+            "busy for {} consecutive keepalive ticks",
+            self.busy_ticks
+        ));
+        true
+    }
+
+    pub fn record_failure(&mut self, error: String) {
+        self.state = HealthState::Unhealthy;
+        self.consecutive_failures += 1;
+        self.last_error = Some(error);
+    }
+
+    pub fn record_restarting(&mut self) {
+        self.state = HealthState::Restarting;
+        self.restarts += 1;
+    }
+
+    /// Outcome of a restart: the next successful ping marks it healthy again.
+    pub fn record_restart(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.state = HealthState::Starting,
+            Err(error) => self.record_failure(format!("restart failed: {error}")),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "state": self.state,
+            "keepalive": {
+                "enabled": self.interval.is_some(),
+                "interval_seconds": self.interval.map(|interval| interval.as_secs()),
+                "timeout_seconds": self.timeout.as_secs(),
+            },
+            "seconds_since_last_ping": self.last_ping.map(|at| at.elapsed().as_secs()),
+            "last_ping_latency_ms": self.last_latency.map(|latency| latency.as_millis() as u64),
+            "busy_ticks": self.busy_ticks,
+            "max_busy_ticks": self.max_busy_ticks,
+            "consecutive_failures": self.consecutive_failures,
+            "restarts": self.restarts,
+            "last_error": self.last_error,
+        })
+    }
+}
+
+/// Pings rust-analyzer every `interval` and restarts it when a ping fails or
+/// takes longer than `timeout`. Ticks that find the client in use by a tool
+/// call are skipped; after `max_busy_ticks` of them in a row the call is taken
+/// to be stuck and the process is killed through `process`, which fails the
+/// call, releases the client and lets the next tick restart it. Returns at
+/// once when the keepalive is disabled.
+pub async fn run_keepalive(
+    analyzer: Arc<tokio::sync::Mutex<RustAnalyzerClient>>,
+    process: ProcessHandle,
+    health: Arc<Mutex<AnalyzerHealth>>,
+) {
+    let update = |change: &dyn Fn(&mut AnalyzerHealth)| {
+        if let Ok(mut health) = health.lock() {
+            change(&mut health);
+        }
+    };
+    let Some((interval, timeout)) = health
+        .lock()
+        .ok()
+        .and_then(|health| Some((health.interval?, health.timeout)))
+    else {
+        return;
+    };
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let Ok(mut client) = analyzer.try_lock() else {
+            let stuck = health.lock().is_ok_and(|mut health| health.record_busy());
+            if stuck
+                && let Ok(mut process) = process.lock()
+                && let Some(child) = process.as_mut()
+            {
+                let _ = child.start_kill();
+            }
+            continue;
+        };
+
+        let started = Instant::now();
+        let error = match tokio::time::timeout(timeout, client.ping()).await {
+            Ok(Ok(())) => {
+                let latency = started.elapsed();
+                update(&|health| health.record_ping(latency));
+                continue;
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("no response within {}s", timeout.as_secs()),
+        };
+
+        update(&|health| {
+            health.record_failure(error.clone());
+            health.record_restarting();
+        });
+        let result = match tokio::time::timeout(timeout, client.restart()).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err(format!("not initialized within {}s", timeout.as_secs())),
+        };
+        update(&|health| health.record_restart(result.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_escalates_to_unhealthy() {
+        let mut health = AnalyzerHealth::new(Some(Duration::from_secs(5)), Duration::from_secs(2))
+            .with_max_busy_ticks(3);
+        health.record_ping(Duration::from_millis(12));
+
+        assert!(!health.record_busy());
+        assert!(!health.record_busy());
+        assert_eq!(health.state, HealthState::Healthy);
+        assert!(health.record_busy());
+        assert_eq!(health.state, HealthState::Unhealthy);
+        assert_eq!(health.consecutive_failures, 1);
+        assert_eq!(
+            health.last_error.as_deref(),
+            Some("busy for 3 consecutive keepalive ticks")
+        );
+
+        let mut patient = AnalyzerHealth::new(Some(Duration::from_secs(5)), Duration::from_secs(2))
+            .with_max_busy_ticks(0);
+        for _ in 0..100 {
+            assert!(!patient.record_busy());
+        }
+        assert_eq!(patient.state, HealthState::Starting);
+    }
+
+    #[test]
+    fn test_health_transitions() {
+        let mut health = AnalyzerHealth::new(Some(Duration::from_secs(5)), Duration::from_secs(2));
+        assert_eq!(health.state, HealthState::Starting);
+
+        health.record_busy();
+        health.record_ping(Duration::from_millis(12));
+        assert_eq!(health.state, HealthState::Healthy);
+        assert_eq!(health.busy_ticks, 0);
+
+        health.record_failure("ping timed out after 2s".to_string());
+        health.record_restarting();
+        assert_eq!(health.state, HealthState::Restarting);
+        health.record_restart(Err("spawn failed".to_string()));
+        assert_eq!(health.state, HealthState::Unhealthy);
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(
+            health.last_error.as_deref(),
+            Some("restart failed: spawn failed")
+        );
+
+        health.record_restarting();
+        health.record_restart(Ok(()));
+        assert_eq!(health.state, HealthState::Starting);
+        assert_eq!(health.restarts, 2);
+
+        let status = health.to_json();
+        assert_eq!(status["state"], "starting");
+        assert_eq!(status["keepalive"]["interval_seconds"], 5);
+        assert_eq!(status["last_ping_latency_ms"], 12);
+
+        let disabled = AnalyzerHealth::new(None, Duration::from_secs(2));
+        assert_eq!(disabled.to_json()["state"], "disabled");
+    }
+}
@@ -1,6 +1,8 @@
 pub mod client;
+pub mod health;
 pub mod lsp;
 pub mod protocol;
 pub mod symbol;
+pub mod transaction;
 
 pub use client::RustAnalyzerClient;
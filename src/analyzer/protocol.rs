@@ -189,6 +189,50 @@ pub struct TypeHierarchyItem {
     pub data: Option<Value>,
 }
 
+/// Same shape as a type hierarchy item; `data` must be passed back as is.
+pub type CallHierarchyItem = TypeHierarchyItem;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallHierarchyIncomingCall {
+    pub from: CallHierarchyItem,
+    /// Ranges of the calls inside `from`.
+    #[serde(rename = "fromRanges")]
+    pub from_ranges: Vec<Range>,
+}
+
+/// `InlayHintKind::Type`; parameter-name hints are kind 2.
+pub const INLAY_HINT_KIND_TYPE: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: InlayHintLabel,
+    pub kind: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InlayHintLabel {
+    String(String),
+    Parts(Vec<InlayHintLabelPart>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InlayHintLabelPart {
+    pub value: String,
+    /// Definition of the item this part names, when it names one.
+    pub location: Option<Location>,
+}
+
+impl InlayHintLabel {
+    pub fn text(&self) -> String {
+        match self {
+            InlayHintLabel::String(text) => text.clone(),
+            InlayHintLabel::Parts(parts) => parts.iter().map(|part| part.value.as_str()).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrepareTypeHierarchyParams {
     #[serde(rename = "textDocument")]
@@ -296,3 +340,25 @@ pub fn create_formatting_params(file_path: &str) -> Value {
         }
     })
 }
+
+pub fn create_inlay_hint_params(file_path: &str, range: &Range) -> Value {
+    json!({
+        "textDocument": {
+            "uri": format!("file://{}", file_path)
+        },
+        "range": range
+    })
+}
+
+pub fn create_range_formatting_params(file_path: &str, range: &Range) -> Value {
+    json!({
+        "textDocument": {
+            "uri": format!("file://{}", file_path)
+        },
+        "range": range,
+        "options": {
+            "tabSize": 4,
+            "insertSpaces": true
+        }
+    })
+}
@@ -102,6 +102,64 @@ pub fn symbol_information_to_identity(symbol_info: &Value) -> Option<SymbolIdent
     })
 }
 
+/// Splits a Rust path such as `crate::net::Server::bind` into its segments.
+///
+/// Leading `::`, `crate`, `self` and `super` qualifiers as well as generic
+/// arguments (`Vec<T>`) are dropped because they cannot be matched against
+/// workspace symbol information.
+pub fn parse_rust_path(path: &str) -> Vec<String> {
+    let mut segments: Vec<String> = path
+        .trim()
+        .trim_start_matches("::")
+        .split("::")
+        .map(|segment| segment.split('<').next().unwrap_or(segment).trim().to_string())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    while segments
+        .first()
+        .is_some_and(|first| matches!(first.as_str(), "crate" | "self" | "super"))
+    {
+        segments.remove(0);
+    }
+
+    segments
+}
+
+/// How closely a [`SymbolIdentity`] matches the segments of a requested path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PathMatch {
+    /// Only the item name matches.
+    Name,
+    /// Every qualifier appears somewhere in the identity's crate or module path.
+    Partial,
+    /// The qualifiers are a suffix of the identity's crate and module path.
+    Exact,
+}
+
+pub fn match_identity_path(identity: &SymbolIdentity, segments: &[String]) -> Option<PathMatch> {
+    let (item, qualifiers) = segments.split_last()?;
+    if &identity.item_name != item {
+        return None;
+    }
+
+    let mut identity_path = vec![identity.crate_name.clone()];
+    identity_path.extend(identity.module_path.iter().cloned());
+
+    if qualifiers.is_empty() || identity_path.ends_with(qualifiers) {
+        return Some(PathMatch::Exact);
+    }
+
+    if qualifiers
+        .iter()
+        .all(|qualifier| identity_path.contains(qualifier))
+    {
+        return Some(PathMatch::Partial);
+    }
+
+    Some(PathMatch::Name)
+}
+
 fn derive_paths(container_name: Option<&str>, location_uri: Option<&str>) -> (String, Vec<String>) {
     let mut module_path = Vec::new();
     let crate_name = container_name
@@ -178,7 +236,7 @@ fn path_from_uri(uri: &str) -> Option<PathBuf> {
     Some(PathBuf::from(without_scheme))
 }
 
-fn crate_name_from_uri(uri: &str) -> Option<String> {
+pub(crate) fn crate_name_from_uri(uri: &str) -> Option<String> {
     let path = path_from_uri(uri)?;
     let components: Vec<String> = path
         .components()
@@ -199,7 +257,7 @@ fn crate_name_from_uri(uri: &str) -> Option<String> {
         .map(|name| name.to_string_lossy().into_owned())
 }
 
-fn module_path_from_uri(uri: &str) -> Vec<String> {
+pub(crate) fn module_path_from_uri(uri: &str) -> Vec<String> {
     let Some(path) = path_from_uri(uri) else {
         return Vec::new();
     };
@@ -240,8 +298,8 @@ fn module_path_from_uri(uri: &str) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::{
-        SymbolIdentity, SymbolKind, identities_from_workspace_symbols,
-        symbol_information_to_identity,
+        PathMatch, SymbolIdentity, SymbolKind, identities_from_workspace_symbols,
+        match_identity_path, parse_rust_path, symbol_information_to_identity,
     };
     use serde_json::json;
 
@@ -335,4 +393,35 @@ mod tests {
         assert_eq!(item_name, "navigate");
         assert_eq!(kind, SymbolKind::FreeFunction);
     }
+
+    #[test]
+    fn matches_identity_against_qualified_path() {
+        let identity = SymbolIdentity {
+            crate_name: "demo".to_string(),
+            module_path: vec!["net".to_string(), "Server".to_string()],
+            item_name: "bind".to_string(),
+            kind: SymbolKind::Method,
+        };
+
+        let exact = parse_rust_path("crate::net::Server::bind");
+        assert_eq!(exact, vec!["net", "Server", "bind"]);
+        assert_eq!(match_identity_path(&identity, &exact), Some(PathMatch::Exact));
+
+        let partial = parse_rust_path("::demo::Server::bind");
+        assert_eq!(
+            match_identity_path(&identity, &partial),
+            Some(PathMatch::Partial)
+        );
+
+        let name_only = parse_rust_path("other::Client::bind");
+        assert_eq!(
+            match_identity_path(&identity, &name_only),
+            Some(PathMatch::Name)
+        );
+
+        assert_eq!(
+            match_identity_path(&identity, &parse_rust_path("net::Server::listen")),
+            None
+        );
+    }
 }
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use tokio::fs;
+
+/// Files written as one unit. Each file's original content is kept until the
+/// transaction is dropped, so a failed write or a failed verification can put
+/// every file back the way it was.
+#[derive(Debug, Default)]
+pub struct FileTransaction {
+    /// `(path, original content)` in write order; one entry per file.
+    backups: Vec<(String, String)>,
+}
+
+impl FileTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `original` as the backup of `file_path` (the first time the
+    /// file is written) and writes `updated` over it.
+    pub async fn write(&mut self, file_path: &str, original: &str, updated: &str) -> Result<()> {
+        if !self.backups.iter().any(|(path, _)| path == file_path) {
+            self.backups
+                .push((file_path.to_string(), original.to_string()));
+        }
+        fs::write(file_path, updated)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", file_path, e))
+    }
+
+    pub fn files(&self) -> Vec<&str> {
+        self.backups.iter().map(|(path, _)| path.as_str()).collect()
+    }
+
+    /// Restores every written file from its backup, newest first. Returns the
+    /// files that could not be restored, with the reason.
+    pub async fn rollback(self) -> Vec<String> {
+        let mut failures = Vec::new();
+        for (path, original) in self.backups.into_iter().rev() {
+            if let Err(e) = fs::write(&path, original).await {
+                failures.push(format!("{path}: {e}"));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rollback_restores_every_file() {
+        let dir = std::env::temp_dir().join(format!("rust-mcp-transaction-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("a.rs").to_string_lossy().into_owned();
+        let second = dir.join("b.rs").to_string_lossy().into_owned();
+        std::fs::write(&first, "fn a() {}\n").unwrap();
+        std::fs::write(&second, "fn b() {}\n").unwrap();
+
+        let mut transaction = FileTransaction::new();
+        transaction
+            .write(&first, "fn a() {}\n", "fn renamed() {}\n")
+            .await
+            .unwrap();
+        transaction
+            .write(&first, "fn renamed() {}\n", "fn renamed_again() {}\n")
+            .await
+            .unwrap();
+        transaction
+            .write(&second, "fn b() {}\n", "fn b() { renamed() }\n")
+            .await
+            .unwrap();
+        assert_eq!(transaction.files(), vec![first.as_str(), second.as_str()]);
+
+        assert!(transaction.rollback().await.is_empty());
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "fn a() {}\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "fn b() {}\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tokio::process::Command;
+
+/// The `cfg` set rustc enables for a target, from `rustc --print cfg`.
+///
+/// Flags are the bare names (`unix`, `debug_assertions`); values holds every
+/// `key="value"` pair, several per key for `target_feature` and friends.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TargetCfg {
+    pub flags: BTreeSet<String>,
+    pub values: BTreeMap<String, Vec<String>>,
+}
+
+impl TargetCfg {
+    pub fn parse(output: &str) -> Self {
+        let mut cfg = Self::default();
+        for line in output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            match line.split_once('=') {
+                Some((key, value)) => cfg
+                    .values
+                    .entry(key.trim().to_string())
+                    .or_default()
+                    .push(value.trim().trim_matches('"').to_string()),
+                None => {
+                    cfg.flags.insert(line.to_string());
+                }
+            }
+        }
+        cfg
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    pub fn has_value(&self, key: &str, value: &str) -> bool {
+        self.values
+            .get(key)
+            .is_some_and(|values| values.iter().any(|v| v == value))
+    }
+
+    /// Runs `rustc --print cfg` in `dir` (so `rust-toolchain.toml` applies),
+    /// for `target` or the host when `None`.
+    pub async fn load(dir: &Path, target: Option<&str>) -> Result<Self> {
+        let mut command = Command::new("rustc");
+        command.args(["--print", "cfg"]);
+        if let Some(target) = target {
+            command.args(["--target", target]);
+        }
+        let output = command
+            .current_dir(dir)
+            .output()
+            .await
+            .context("running rustc --print cfg")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "rustc --print cfg failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_print_cfg() {
+        let cfg = TargetCfg::parse(
+            "debug_assertions\npanic=\"unwind\"\ntarget_arch=\"x86_64\"\ntarget_feature=\"sse\"\ntarget_feature=\"sse2\"\ntarget_os=\"linux\"\ntarget_pointer_width=\"64\"\nunix\n",
+        );
+        assert!(cfg.has_flag("unix"));
+        assert!(!cfg.has_flag("windows"));
+        assert!(cfg.has_value("target_os", "linux"));
+        assert!(cfg.has_value("target_feature", "sse2"));
+        assert_eq!(cfg.values["target_feature"], vec!["sse", "sse2"]);
+        assert_eq!(cfg.values["target_pointer_width"], vec!["64"]);
+    }
+}
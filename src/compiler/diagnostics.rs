@@ -0,0 +1,491 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+use tokio::process::Command;
+
+/// One `error` or `warning` from `cargo check --message-format=json`, keyed by
+/// its primary span so two runs can be compared.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CargoDiagnostic {
+    pub level: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+}
+
+impl CargoDiagnostic {
+    /// Reads a `compiler-message`; other messages and notes give `None`.
+    pub fn from_message(message: &Value) -> Option<Self> {
+        if message.get("reason").and_then(|r| r.as_str()) != Some("compiler-message") {
+            return None;
+        }
+        let diagnostic = message.get("message")?;
+        let level = diagnostic.get("level").and_then(|l| l.as_str())?;
+        if level != "error" && level != "warning" {
+            return None;
+        }
+        let primary = diagnostic
+            .get("spans")
+            .and_then(|spans| spans.as_array())
+            .and_then(|spans| {
+                spans.iter().find(|span| {
+                    span.get("is_primary")
+                        .and_then(|p| p.as_bool())
+                        .unwrap_or(false)
+                })
+            });
+        Some(Self {
+            level: level.to_string(),
+            code: diagnostic
+                .get("code")
+                .and_then(|code| code.get("code"))
+                .and_then(|code| code.as_str())
+                .map(str::to_string),
+            message: diagnostic.get("message")?.as_str()?.to_string(),
+            file: primary
+                .and_then(|span| span.get("file_name"))
+                .and_then(|file| file.as_str())
+                .map(str::to_string),
+            line: primary
+                .and_then(|span| span.get("line_start"))
+                .and_then(|line| line.as_u64()),
+        })
+    }
+}
+
+/// The features a check runs with, as cargo's `--features`,
+/// `--no-default-features` and `--all-features` flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureSet {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    pub all_features: bool,
+}
+
+impl FeatureSet {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        args
+    }
+}
+
+/// Runs `cargo check` with `features` and returns whether it passed with its
+/// diagnostics, deduplicated across targets.
+///
+/// Builds go to `target/mcp-features` so switching features never invalidates
+/// the regular target directory or rust-analyzer's own check results.
+pub async fn check_with_features(
+    workspace_path: &Path,
+    package: Option<&str>,
+    features: &FeatureSet,
+) -> Result<(bool, BTreeSet<CargoDiagnostic>)> {
+    let mut command = Command::new("cargo");
+    command.arg("check").arg("--message-format=json");
+    if let Some(package) = package {
+        command.arg("-p").arg(package);
+    }
+    let output = command
+        .args(features.args())
+        .env(
+            "CARGO_TARGET_DIR",
+            workspace_path.join("target/mcp-features"),
+        )
+        .current_dir(workspace_path)
+        .output()
+        .await
+        .context("running cargo check")?;
+
+    let diagnostics: BTreeSet<CargoDiagnostic> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|message| CargoDiagnostic::from_message(&message))
+        .collect();
+    // A failure without any diagnostic is cargo itself refusing, e.g. an unknown feature.
+    if !output.status.success() && diagnostics.is_empty() {
+        anyhow::bail!(
+            "cargo check {} failed: {}",
+            features.args().join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok((output.status.success(), diagnostics))
+}
+
+/// Runs a plain `cargo check` and returns whether it passed with its
+/// `compiler-message`s, in the regular target directory so it shares the
+/// build with `run_cargo_check`.
+pub async fn check_messages(
+    workspace_path: &Path,
+    package: Option<&str>,
+) -> Result<(bool, Vec<Value>)> {
+    let mut command = Command::new("cargo");
+    command.arg("check").arg("--message-format=json");
+    if let Some(package) = package {
+        command.arg("-p").arg(package);
+    }
+    let output = command
+        .current_dir(workspace_path)
+        .output()
+        .await
+        .context("running cargo check")?;
+
+    let messages: Vec<Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message.get("reason").and_then(|r| r.as_str()) == Some("compiler-message")
+        })
+        .collect();
+    if !output.status.success() && messages.is_empty() {
+        anyhow::bail!(
+            "cargo check failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok((output.status.success(), messages))
+}
+
+/// A replacement rustc or clippy attaches to a diagnostic, from the
+/// `suggested_replacement` of a span in the diagnostic or one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SuggestedFix {
+    /// Relative to the workspace root, as cargo reports it.
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+    #[serde(skip)]
+    pub byte_start: usize,
+    #[serde(skip)]
+    pub byte_end: usize,
+    pub replacement: String,
+    /// `MachineApplicable`, `MaybeIncorrect`, `HasPlaceholders` or `Unspecified`.
+    pub applicability: String,
+}
+
+impl SuggestedFix {
+    /// Every suggestion of a `compiler-message`, in the order emitted.
+    pub fn all_in(message: &Value) -> Vec<Self> {
+        let Some(diagnostic) = message.get("message") else {
+            return Vec::new();
+        };
+        let children = diagnostic
+            .get("children")
+            .and_then(|children| children.as_array())
+            .into_iter()
+            .flatten();
+        std::iter::once(diagnostic)
+            .chain(children)
+            .filter_map(|entry| entry.get("spans").and_then(|spans| spans.as_array()))
+            .flatten()
+            .filter_map(Self::from_span)
+            .collect()
+    }
+
+    fn from_span(span: &Value) -> Option<Self> {
+        let number = |key: &str| span.get(key).and_then(|value| value.as_u64());
+        Some(Self {
+            file: span.get("file_name")?.as_str()?.to_string(),
+            line: number("line_start")?,
+            column: number("column_start")?,
+            byte_start: number("byte_start")? as usize,
+            byte_end: number("byte_end")? as usize,
+            replacement: span.get("suggested_replacement")?.as_str()?.to_string(),
+            applicability: span
+                .get("suggestion_applicability")
+                .and_then(|applicability| applicability.as_str())
+                .unwrap_or("Unspecified")
+                .to_string(),
+        })
+    }
+}
+
+/// Runs `cargo clippy --all-targets` with `lints` enabled as warnings and
+/// returns its `compiler-message`s.
+///
+/// Like [`check_with_features`], builds go to their own target directory
+/// (`target/mcp-clippy`), so the extra lint flags never invalidate the
+/// regular build.
+pub async fn clippy_messages(
+    workspace_path: &Path,
+    package: Option<&str>,
+    lints: &[&str],
+) -> Result<Vec<Value>> {
+    let mut command = Command::new("cargo");
+    command
+        .arg("clippy")
+        .arg("--all-targets")
+        .arg("--message-format=json");
+    if let Some(package) = package {
+        command.arg("-p").arg(package);
+    }
+    command.arg("--");
+    for lint in lints {
+        command.arg("-W").arg(lint);
+    }
+    let output = command
+        .env("CARGO_TARGET_DIR", workspace_path.join("target/mcp-clippy"))
+        .current_dir(workspace_path)
+        .output()
+        .await
+        .context("running cargo clippy")?;
+
+    let messages: Vec<Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message.get("reason").and_then(|r| r.as_str()) == Some("compiler-message")
+        })
+        .collect();
+    if !output.status.success() && messages.is_empty() {
+        anyhow::bail!(
+            "cargo clippy failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(messages)
+}
+
+/// One doctest from the `test NAME ... ok` lines of `cargo test --doc`, with
+/// the output rustdoc printed for it if it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Doctest {
+    /// As rustdoc names it: `src/lib.rs - path::to::item (line 12)`, optionally
+    /// followed by ` - compile` or ` - compile fail`.
+    pub name: String,
+    /// `passed`, `failed` or `ignored`.
+    pub status: String,
+    pub output: String,
+}
+
+impl Doctest {
+    /// The 1-based line of the example's opening fence.
+    pub fn line(&self) -> Option<usize> {
+        let (_, rest) = self.name.rsplit_once("(line ")?;
+        rest.split(')').next()?.parse().ok()
+    }
+
+    /// The source file the example is in, relative to its package.
+    pub fn file(&self) -> &str {
+        self.name.split(" - ").next().unwrap_or(&self.name)
+    }
+}
+
+/// Reads the test lines and `---- NAME stdout ----` failure sections that
+/// `cargo test --doc` prints.
+pub fn parse_doctest_output(stdout: &str) -> Vec<Doctest> {
+    let mut doctests: Vec<Doctest> = stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, status) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+            let status = match status.trim() {
+                "ok" => "passed",
+                "FAILED" => "failed",
+                "ignored" => "ignored",
+                _ => return None,
+            };
+            Some(Doctest {
+                name: name.to_string(),
+                status: status.to_string(),
+                output: String::new(),
+            })
+        })
+        .collect();
+
+    let mut current: Option<(String, Vec<&str>)> = None;
+    let mut sections = Vec::new();
+    for line in stdout.lines() {
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            sections.extend(current.take());
+            current = Some((name.to_string(), Vec::new()));
+        } else if line == "failures:" {
+            sections.extend(current.take());
+        } else if let Some((_, output)) = current.as_mut() {
+            output.push(line);
+        }
+    }
+    sections.extend(current);
+    for (name, output) in sections {
+        if let Some(doctest) = doctests.iter_mut().find(|doctest| doctest.name == name) {
+            doctest.output = output.join("\n").trim().to_string();
+        }
+    }
+    doctests
+}
+
+/// Runs `cargo test --doc` for the package at `manifest_path`, keeping the
+/// doctests whose name contains `filter`.
+///
+/// Builds go to `target/mcp-doctests` next to the manifest, as with
+/// [`check_with_features`].
+pub async fn run_doc_tests(manifest_path: &Path, filter: &str) -> Result<Vec<Doctest>> {
+    let package_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let output = Command::new("cargo")
+        .arg("test")
+        .arg("--doc")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .arg("--")
+        .arg(filter)
+        .env("CARGO_TARGET_DIR", package_dir.join("target/mcp-doctests"))
+        .current_dir(package_dir)
+        .output()
+        .await
+        .context("running cargo test --doc")?;
+
+    let doctests = parse_doctest_output(&String::from_utf8_lossy(&output.stdout));
+    // Without any test line the library itself failed to build, or has no library target.
+    if !output.status.success() && doctests.is_empty() {
+        anyhow::bail!(
+            "cargo test --doc failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(doctests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diagnostic_from_message() {
+        let message = json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": "error",
+                "code": {"code": "E0425"},
+                "message": "cannot find value `x` in this scope",
+                "spans": [
+                    {"file_name": "src/macros.rs", "line_start": 3, "is_primary": false},
+                    {"file_name": "src/lib.rs", "line_start": 12, "is_primary": true}
+                ]
+            }
+        });
+        assert_eq!(
+            CargoDiagnostic::from_message(&message),
+            Some(CargoDiagnostic {
+                level: "error".to_string(),
+                code: Some("E0425".to_string()),
+                message: "cannot find value `x` in this scope".to_string(),
+                file: Some("src/lib.rs".to_string()),
+                line: Some(12),
+            })
+        );
+
+        let summary = json!({
+            "reason": "compiler-message",
+            "message": {"level": "failure-note", "message": "", "spans": []}
+        });
+        assert_eq!(CargoDiagnostic::from_message(&summary), None);
+        assert_eq!(
+            CargoDiagnostic::from_message(&json!({"reason": "build-finished"})),
+            None
+        );
+
+        let clone = json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": "warning",
+                "code": {"code": "clippy::redundant_clone"},
+                "message": "redundant clone",
+                "spans": [{
+                    "file_name": "src/lib.rs", "line_start": 4, "column_start": 14,
+                    "byte_start": 60, "byte_end": 68, "is_primary": true,
+                    "suggested_replacement": null
+                }],
+                "children": [{
+                    "message": "remove this",
+                    "spans": [{
+                        "file_name": "src/lib.rs", "line_start": 4, "column_start": 14,
+                        "byte_start": 60, "byte_end": 68, "is_primary": true,
+                        "suggested_replacement": "",
+                        "suggestion_applicability": "MachineApplicable"
+                    }]
+                }]
+            }
+        });
+        assert_eq!(
+            SuggestedFix::all_in(&clone),
+            vec![SuggestedFix {
+                file: "src/lib.rs".to_string(),
+                line: 4,
+                column: 14,
+                byte_start: 60,
+                byte_end: 68,
+                replacement: String::new(),
+                applicability: "MachineApplicable".to_string(),
+            }]
+        );
+
+        let features = FeatureSet {
+            features: vec!["serde".to_string(), "tokio/rt".to_string()],
+            no_default_features: true,
+            all_features: false,
+        };
+        assert_eq!(
+            features.args(),
+            vec!["--no-default-features", "--features", "serde,tokio/rt"]
+        );
+    }
+
+    #[test]
+    fn test_parse_doctest_output() {
+        let stdout = "
+running 3 tests
+test src/lib.rs - add (line 13) - compile ... ok
+test src/lib.rs - add (line 18) ... ignored
+test src/m/mod.rs - m::S::f (line 3) ... FAILED
+
+failures:
+
+---- src/m/mod.rs - m::S::f (line 3) stdout ----
+Test executable failed (exit status: 101).
+
+assertion `left == right` failed
+
+
+failures:
+    src/m/mod.rs - m::S::f (line 3)
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let doctests = parse_doctest_output(stdout);
+        let summary: Vec<_> = doctests
+            .iter()
+            .map(|doctest| (doctest.file(), doctest.line(), doctest.status.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/lib.rs", Some(13), "passed"),
+                ("src/lib.rs", Some(18), "ignored"),
+                ("src/m/mod.rs", Some(3), "failed")
+            ]
+        );
+        assert!(doctests[0].output.is_empty());
+        assert_eq!(
+            doctests[2].output,
+            "Test executable failed (exit status: 101).\n\nassertion `left == right` failed"
+        );
+    }
+}
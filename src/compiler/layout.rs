@@ -0,0 +1,203 @@
+use serde::Serialize;
+
+/// Layout of one type as printed by `rustc -Zprint-type-sizes`.
+///
+/// Fields are listed in memory order. Offsets are reconstructed from the
+/// printed sizes and padding, so they are exact for the compiled target.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeLayout {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub size: u64,
+    pub align: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discriminant: Option<u64>,
+    pub fields: Vec<FieldLayout>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<VariantLayout>,
+    pub end_padding: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldLayout {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+    /// Only printed by rustc when the field's alignment caused padding.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<u64>,
+    /// Padding inserted before this field.
+    pub padding_before: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantLayout {
+    pub name: String,
+    pub size: u64,
+    pub fields: Vec<FieldLayout>,
+}
+
+const PREFIX: &str = "print-type-size ";
+
+/// Parses every `print-type-size` block in compiler output; other lines are
+/// ignored.
+pub fn parse_type_sizes(output: &str) -> Vec<TypeLayout> {
+    let mut layouts: Vec<TypeLayout> = Vec::new();
+    // Next free offset in the struct or variant currently being read.
+    let mut cursor = 0;
+    let mut padding = 0;
+
+    for line in output.lines() {
+        let Some(entry) = line.strip_prefix(PREFIX).map(str::trim) else {
+            continue;
+        };
+
+        if let Some(header) = entry.strip_prefix("type: `") {
+            let Some((type_name, rest)) = header.rsplit_once("`: ") else {
+                continue;
+            };
+            let Some((size, align)) = rest.split_once(", alignment: ") else {
+                continue;
+            };
+            let (Some(size), Some(align)) = (bytes(size), bytes(align)) else {
+                continue;
+            };
+            layouts.push(TypeLayout {
+                type_name: type_name.to_string(),
+                size,
+                align,
+                discriminant: None,
+                fields: Vec::new(),
+                variants: Vec::new(),
+                end_padding: 0,
+            });
+            cursor = 0;
+            padding = 0;
+            continue;
+        }
+
+        let Some(layout) = layouts.last_mut() else {
+            continue;
+        };
+        if let Some(size) = entry.strip_prefix("discriminant: ").and_then(bytes) {
+            layout.discriminant = Some(size);
+            cursor = size;
+        } else if let Some(rest) = entry.strip_prefix("variant `") {
+            let Some((name, size)) = rest.split_once("`: ") else {
+                continue;
+            };
+            layout.variants.push(VariantLayout {
+                name: name.to_string(),
+                size: bytes(size).unwrap_or(0),
+                fields: Vec::new(),
+            });
+            cursor = layout.discriminant.unwrap_or(0);
+            padding = 0;
+        } else if let Some(size) = entry.strip_prefix("end padding: ").and_then(bytes) {
+            layout.end_padding = size;
+        } else if let Some(size) = entry.strip_prefix("padding: ").and_then(bytes) {
+            padding += size;
+            cursor += size;
+        } else if let Some(rest) = ["field `", "upvar `", "local `"]
+            .iter()
+            .find_map(|prefix| entry.strip_prefix(prefix))
+        {
+            let Some(field) = parse_field(rest, cursor, padding) else {
+                continue;
+            };
+            cursor = field.offset + field.size;
+            padding = 0;
+            match layout.variants.last_mut() {
+                Some(variant) => variant.fields.push(field),
+                None => layout.fields.push(field),
+            }
+        }
+    }
+
+    layouts
+}
+
+/// `.name`: N bytes[, offset: N bytes][, alignment: N bytes]
+fn parse_field(rest: &str, cursor: u64, padding: u64) -> Option<FieldLayout> {
+    let (name, details) = rest.split_once("`: ")?;
+    let mut parts = details.split(", ");
+    let size = bytes(parts.next()?)?;
+    let mut offset = cursor;
+    let mut align = None;
+    for part in parts {
+        if let Some(value) = part.strip_prefix("offset: ") {
+            offset = bytes(value)?;
+        } else if let Some(value) = part.strip_prefix("alignment: ") {
+            align = bytes(value);
+        }
+    }
+
+    Some(FieldLayout {
+        name: name.trim_start_matches('.').to_string(),
+        offset,
+        size,
+        align,
+        padding_before: padding,
+    })
+}
+
+fn bytes(text: &str) -> Option<u64> {
+    text.trim().strip_suffix(" bytes")?.trim().parse().ok()
+}
+
+/// Whether a printed type (`Foo`, `app::model::Foo<u8>`) is the item named
+/// `name`, ignoring its path and generic arguments.
+pub fn is_layout_of(layout: &TypeLayout, name: &str) -> bool {
+    let base = layout
+        .type_name
+        .split('<')
+        .next()
+        .unwrap_or(&layout.type_name);
+    base.rsplit("::").next() == Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_sizes_struct_and_enum() {
+        let output = "\
+   Compiling app v0.1.0 (/ws)
+print-type-size type: `model::Header`: 24 bytes, alignment: 8 bytes
+print-type-size     field `.id`: 8 bytes
+print-type-size     field `.len`: 4 bytes
+print-type-size     field `.flag`: 1 bytes
+print-type-size     end padding: 3 bytes
+print-type-size type: `Shape`: 12 bytes, alignment: 4 bytes
+print-type-size     discriminant: 1 bytes
+print-type-size     variant `Rect`: 11 bytes
+print-type-size         padding: 3 bytes
+print-type-size         field `.w`: 4 bytes, alignment: 4 bytes
+print-type-size         field `.h`: 4 bytes
+print-type-size     variant `Empty`: 0 bytes
+";
+        let layouts = parse_type_sizes(output);
+        assert_eq!(layouts.len(), 2);
+
+        let header = &layouts[0];
+        assert!(is_layout_of(header, "Header"));
+        assert_eq!((header.size, header.align, header.end_padding), (24, 8, 3));
+        let offsets: Vec<_> = header
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.offset, f.size))
+            .collect();
+        assert_eq!(offsets, vec![("id", 0, 8), ("len", 8, 4), ("flag", 12, 1)]);
+
+        let shape = &layouts[1];
+        assert_eq!(shape.discriminant, Some(1));
+        let rect = &shape.variants[0];
+        assert_eq!(rect.fields[0].offset, 4);
+        assert_eq!(rect.fields[0].padding_before, 3);
+        assert_eq!(rect.fields[0].align, Some(4));
+        assert_eq!(rect.fields[1].offset, 8);
+        assert!(shape.variants[1].fields.is_empty());
+        assert!(!is_layout_of(shape, "Sha"));
+    }
+}
@@ -1,4 +1,9 @@
+pub mod cfg;
+pub mod diagnostics;
 pub mod extract;
+pub mod layout;
+pub mod packages;
+pub mod progress;
 pub mod runner;
 
 pub use runner::*;
@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tokio::process::Command;
+
+/// Source directories of a workspace's packages, built from `cargo metadata`.
+///
+/// Each target's `src_path` contributes its parent directory, as does the
+/// package's manifest directory (for files outside any target's directory,
+/// such as integration-test helpers). A file belongs to the package with the
+/// deepest directory containing it, so nested crates win over the package they
+/// live in.
+#[derive(Debug, Clone, Default)]
+pub struct PackageIndex {
+    roots: Vec<(PathBuf, String)>,
+}
+
+impl PackageIndex {
+    /// Builds the index from `cargo metadata --format-version 1` output.
+    pub fn from_metadata(metadata: &Value) -> Self {
+        let mut roots = Vec::new();
+        for package in metadata
+            .get("packages")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = package.get("name").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            if let Some(dir) = package
+                .get("manifest_path")
+                .and_then(|v| v.as_str())
+                .and_then(|path| Path::new(path).parent())
+            {
+                roots.push((dir.to_path_buf(), name.to_string()));
+            }
+            for target in package
+                .get("targets")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+            {
+                if let Some(dir) = target
+                    .get("src_path")
+                    .and_then(|v| v.as_str())
+                    .and_then(|path| Path::new(path).parent())
+                {
+                    roots.push((dir.to_path_buf(), name.to_string()));
+                }
+            }
+        }
+
+        // Deepest directories first so the first match is the owning package.
+        roots.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+        roots.dedup();
+        Self { roots }
+    }
+
+    /// Runs `cargo metadata` in `workspace_path` and indexes the result.
+    pub async fn load(workspace_path: &Path) -> Result<Self> {
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps", "--offline"])
+            .current_dir(workspace_path)
+            .output()
+            .await
+            .context("running cargo metadata")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let metadata: Value =
+            serde_json::from_slice(&output.stdout).context("parsing cargo metadata output")?;
+        Ok(Self::from_metadata(&metadata))
+    }
+
+    pub fn package_for(&self, file_path: &Path) -> Option<&str> {
+        self.roots
+            .iter()
+            .find(|(dir, _)| file_path.starts_with(dir))
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// Caches `cargo metadata` per workspace and the resulting file → package
+/// lookups, so repeated per-file checks don't pay for metadata each time.
+#[derive(Debug, Default)]
+pub struct PackageResolver {
+    indexes: HashMap<PathBuf, PackageIndex>,
+    files: HashMap<PathBuf, Option<String>>,
+}
+
+impl PackageResolver {
+    /// The package owning `file_path` in the workspace at `workspace_path`, or
+    /// `None` when no package target covers the file.
+    pub async fn package_for_file(
+        &mut self,
+        workspace_path: &Path,
+        file_path: &Path,
+    ) -> Result<Option<String>> {
+        let workspace_path = canonical(workspace_path).await;
+        let file_path = canonical(file_path).await;
+        if let Some(package) = self.files.get(&file_path) {
+            return Ok(package.clone());
+        }
+
+        let cached = self.indexes.contains_key(&workspace_path);
+        let mut package = self
+            .index(&workspace_path)
+            .await?
+            .package_for(&file_path)
+            .map(str::to_string);
+
+        // A miss against cached metadata may mean a package was added since.
+        if package.is_none() && cached {
+            self.indexes.remove(&workspace_path);
+            package = self
+                .index(&workspace_path)
+                .await?
+                .package_for(&file_path)
+                .map(str::to_string);
+        }
+
+        self.files.insert(file_path, package.clone());
+        Ok(package)
+    }
+
+    async fn index(&mut self, workspace_path: &Path) -> Result<&PackageIndex> {
+        if !self.indexes.contains_key(workspace_path) {
+            let index = PackageIndex::load(workspace_path).await?;
+            self.indexes.insert(workspace_path.to_path_buf(), index);
+        }
+        Ok(&self.indexes[workspace_path])
+    }
+}
+
+async fn canonical(path: &Path) -> PathBuf {
+    tokio::fs::canonicalize(path)
+        .await
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_package_for_file_prefers_nested_package() {
+        let metadata = json!({
+            "packages": [
+                {
+                    "name": "app",
+                    "targets": [
+                        {"name": "app", "src_path": "/ws/src/main.rs"},
+                        {"name": "build-script-build", "src_path": "/ws/build.rs"}
+                    ]
+                },
+                {
+                    "name": "app-core",
+                    "manifest_path": "/ws/crates/core/Cargo.toml",
+                    "targets": [{"name": "app_core", "src_path": "/ws/crates/core/src/lib.rs"}]
+                }
+            ]
+        });
+        let index = PackageIndex::from_metadata(&metadata);
+
+        assert_eq!(index.package_for(Path::new("/ws/src/cli/args.rs")), Some("app"));
+        assert_eq!(
+            index.package_for(Path::new("/ws/crates/core/src/model/user.rs")),
+            Some("app-core")
+        );
+        assert_eq!(
+            index.package_for(Path::new("/ws/crates/core/tests/common/mod.rs")),
+            Some("app-core")
+        );
+        assert_eq!(index.package_for(Path::new("/elsewhere/lib.rs")), None);
+    }
+}
@@ -0,0 +1,106 @@
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A progress update derived from `cargo --message-format=json` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoProgress {
+    /// Number of compiler artifacts finished so far.
+    pub completed: usize,
+    pub message: String,
+}
+
+/// Channel used by long-running cargo tools to report progress while they run.
+pub type CargoProgressSender = UnboundedSender<CargoProgress>;
+
+/// Turns cargo's JSON messages into progress updates and keeps a log of them
+/// so callers without a progress channel still see what happened.
+#[derive(Debug, Default)]
+pub struct ProgressTracker {
+    completed: usize,
+    log: Vec<String>,
+}
+
+impl ProgressTracker {
+    /// Inspect one cargo JSON message, returning a progress update for
+    /// `compiler-artifact` and `build-finished` messages.
+    pub fn observe(&mut self, message: &Value) -> Option<CargoProgress> {
+        let text = match message.get("reason").and_then(|r| r.as_str())? {
+            "compiler-artifact" => {
+                self.completed += 1;
+                let name = message
+                    .get("target")
+                    .and_then(|target| target.get("name"))
+                    .and_then(|name| name.as_str())
+                    .unwrap_or("<unknown>");
+                let fresh = message
+                    .get("fresh")
+                    .and_then(|fresh| fresh.as_bool())
+                    .unwrap_or(false);
+                let verb = if fresh { "Fresh" } else { "Compiled" };
+                format!("{verb} {name} ({} done)", self.completed)
+            }
+            "build-finished" => {
+                let success = message
+                    .get("success")
+                    .and_then(|success| success.as_bool())
+                    .unwrap_or(false);
+                let outcome = if success { "succeeded" } else { "failed" };
+                format!("Build {outcome} after {} artifact(s)", self.completed)
+            }
+            _ => return None,
+        };
+
+        self.log.push(text.clone());
+        Some(CargoProgress {
+            completed: self.completed,
+            message: text,
+        })
+    }
+
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tracks_artifacts_and_build_completion() {
+        let mut tracker = ProgressTracker::default();
+
+        let first = tracker
+            .observe(&json!({
+                "reason": "compiler-artifact",
+                "target": {"name": "serde"},
+                "fresh": true
+            }))
+            .unwrap();
+        assert_eq!(first.completed, 1);
+        assert_eq!(first.message, "Fresh serde (1 done)");
+
+        assert!(
+            tracker
+                .observe(&json!({"reason": "compiler-message", "message": {}}))
+                .is_none()
+        );
+
+        let second = tracker
+            .observe(&json!({
+                "reason": "compiler-artifact",
+                "target": {"name": "demo"},
+                "fresh": false
+            }))
+            .unwrap();
+        assert_eq!(second.message, "Compiled demo (2 done)");
+
+        let finished = tracker
+            .observe(&json!({"reason": "build-finished", "success": true}))
+            .unwrap();
+        assert_eq!(finished.completed, 2);
+        assert_eq!(tracker.log().len(), 3);
+        assert_eq!(tracker.log()[2], "Build succeeded after 2 artifact(s)");
+    }
+}
@@ -106,6 +106,11 @@ impl CompilerRunner {
             command_line.push(target_triple);
         }
 
+        for arg in request.cargo_args.iter() {
+            command.arg(arg);
+            command_line.push(arg.clone());
+        }
+
         if let Some(opt_level) = request.opt_level {
             command.arg("--");
             command.arg(format!("-Copt-level={opt_level}"));
@@ -197,6 +202,8 @@ pub struct RunRequest {
     pub manifest_path: Option<PathBuf>,
     pub package: Option<String>,
     pub target_triple: Option<String>,
+    /// Arguments for cargo itself, placed before `--` (e.g. `--lib`).
+    pub cargo_args: Vec<String>,
     pub opt_level: Option<String>,
     pub emit: Option<String>,
     pub unpretty: Option<String>,
@@ -0,0 +1,327 @@
+use crate::analyzer::health::{
+    DEFAULT_KEEPALIVE_INTERVAL, DEFAULT_KEEPALIVE_MAX_BUSY_TICKS, DEFAULT_KEEPALIVE_TIMEOUT,
+};
+use crate::inspection::{GatingMode, InspectionLimits};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{path::Path, str::FromStr, time::Duration};
+
+/// Name of the project-local configuration file, looked up in the workspace root.
+pub const CONFIG_FILE_NAME: &str = ".rust-mcp.toml";
+
+/// Server configuration assembled from `.rust-mcp.toml` and the environment.
+///
+/// Every setting is optional; unset values fall back to the built-in defaults.
+/// Environment variables (`RUST_ANALYZER_PATH`, `RUST_MCP_FULL_ANALYSIS`,
+/// `RUST_MCP_KEEPALIVE_INTERVAL`, `RUST_MCP_KEEPALIVE_TIMEOUT`,
+/// `MCP_GATING_MODE`, `RUST_MCP_READONLY`) take precedence over the file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub analyzer: AnalyzerConfig,
+    pub timeouts: TimeoutConfig,
+    pub features: FeatureConfig,
+    pub limits: LimitConfig,
+    pub blocking: BlockingConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyzerConfig {
+    /// Path to the `rust-analyzer` executable.
+    pub path: Option<String>,
+    /// Enables proc-macros and build scripts during analysis.
+    pub full_analysis: Option<bool>,
+    /// Extra `initializationOptions`, merged over the defaults derived from
+    /// `full_analysis`.
+    pub init_options: Option<Value>,
+    /// Pause between keepalive pings; `0` turns the keepalive off.
+    pub keepalive_interval_seconds: Option<u64>,
+    /// How long a ping may take before rust-analyzer is restarted.
+    pub keepalive_timeout_seconds: Option<u64>,
+    /// Consecutive keepalive ticks a tool call may hold rust-analyzer before
+    /// it is killed and restarted; `0` waits forever.
+    pub keepalive_max_busy_ticks: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// Timeout for compiler runs started by the inspection tools.
+    pub compiler_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    /// Whether nightly-only inspection views are refused (`strict`) or
+    /// attempted anyway (`lenient`).
+    pub gating_mode: Option<GatingMode>,
+    /// When `false`, file-mutating tools return the diff they would apply
+    /// instead of writing it.
+    pub allow_writes: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitConfig {
+    /// Maximum size of compiler output returned by the inspection tools.
+    pub max_output_bytes: Option<usize>,
+    /// Maximum number of compiler output lines returned by the inspection tools.
+    pub max_output_lines: Option<usize>,
+    /// Default page size of `workspace_symbols`.
+    pub workspace_symbols: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockingConfig {
+    /// Calls `find_blocking_in_async` flags besides the built-in ones: a
+    /// path (`my_db::sync::query`) or a method (`.wait()`).
+    pub patterns: Option<Vec<String>>,
+    /// Built-in patterns not to flag, e.g. `.lock()`.
+    pub ignore: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Reads `.rust-mcp.toml` from `workspace_root` (if present) and applies
+    /// environment overrides. A malformed file or an unknown key is an error.
+    pub fn load(workspace_root: &Path) -> Result<Self> {
+        let path = workspace_root.join(CONFIG_FILE_NAME);
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("loading {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("reading {}", path.display()));
+            }
+        };
+        config.apply_overrides(|key| std::env::var(key).ok());
+        Ok(config)
+    }
+
+    /// Configuration made of environment overrides only.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_overrides(|key| std::env::var(key).ok());
+        config
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        if config.timeouts.compiler_seconds == Some(0) {
+            anyhow::bail!("timeouts.compiler_seconds must be greater than zero");
+        }
+        if config.analyzer.keepalive_timeout_seconds == Some(0) {
+            anyhow::bail!("analyzer.keepalive_timeout_seconds must be greater than zero");
+        }
+        if config.limits.workspace_symbols == Some(0) {
+            anyhow::bail!("limits.workspace_symbols must be greater than zero");
+        }
+        Ok(config)
+    }
+
+    fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) {
+        if let Some(path) = lookup("RUST_ANALYZER_PATH") {
+            self.analyzer.path = Some(path);
+        }
+        if let Some(full) = lookup("RUST_MCP_FULL_ANALYSIS").and_then(|v| v.parse().ok()) {
+            self.analyzer.full_analysis = Some(full);
+        }
+        if let Some(seconds) = lookup("RUST_MCP_KEEPALIVE_INTERVAL").and_then(|v| v.parse().ok()) {
+            self.analyzer.keepalive_interval_seconds = Some(seconds);
+        }
+        if let Some(seconds) = lookup("RUST_MCP_KEEPALIVE_TIMEOUT")
+            .and_then(|v| v.parse().ok())
+            .filter(|&seconds| seconds > 0)
+        {
+            self.analyzer.keepalive_timeout_seconds = Some(seconds);
+        }
+        if let Some(mode) = lookup("MCP_GATING_MODE").and_then(|v| GatingMode::from_str(&v).ok()) {
+            self.features.gating_mode = Some(mode);
+        }
+        if let Some(readonly) = lookup("RUST_MCP_READONLY").and_then(|v| parse_flag(&v)) {
+            self.features.allow_writes = Some(!readonly);
+        }
+    }
+
+    pub fn allow_writes(&self) -> bool {
+        self.features.allow_writes.unwrap_or(true)
+    }
+
+    /// Keepalive ping interval (`None` when disabled) and ping timeout.
+    pub fn keepalive(&self) -> (Option<Duration>, Duration) {
+        let interval = match self.analyzer.keepalive_interval_seconds {
+            Some(0) => None,
+            Some(seconds) => Some(Duration::from_secs(seconds)),
+            None => Some(DEFAULT_KEEPALIVE_INTERVAL),
+        };
+        let timeout = self
+            .analyzer
+            .keepalive_timeout_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_KEEPALIVE_TIMEOUT);
+        (interval, timeout)
+    }
+
+    /// Busy keepalive ticks after which rust-analyzer is considered stuck.
+    pub fn keepalive_max_busy_ticks(&self) -> u64 {
+        self.analyzer
+            .keepalive_max_busy_ticks
+            .unwrap_or(DEFAULT_KEEPALIVE_MAX_BUSY_TICKS)
+    }
+
+    pub fn inspection_limits(&self) -> InspectionLimits {
+        let defaults = InspectionLimits::default();
+        InspectionLimits {
+            timeout_seconds: self
+                .timeouts
+                .compiler_seconds
+                .unwrap_or(defaults.timeout_seconds),
+            max_output_bytes: self
+                .limits
+                .max_output_bytes
+                .unwrap_or(defaults.max_output_bytes),
+            max_output_lines: self
+                .limits
+                .max_output_lines
+                .unwrap_or(defaults.max_output_lines),
+        }
+    }
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Recursively merges `overrides` into `base`; non-object values replace.
+pub fn merge_json(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_json(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = Config::parse(
+            r#"
+[analyzer]
+path = "/opt/rust-analyzer"
+full_analysis = false
+init_options = { cargo = { features = ["serde"] } }
+
+[timeouts]
+compiler_seconds = 90
+
+[features]
+gating_mode = "lenient"
+
+[limits]
+max_output_lines = 500
+workspace_symbols = 25
+
+[blocking]
+patterns = ["diesel::RunQueryDsl::load", ".wait()"]
+ignore = [".lock()"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.analyzer.path.as_deref(), Some("/opt/rust-analyzer"));
+        assert_eq!(config.analyzer.full_analysis, Some(false));
+        assert_eq!(
+            config.analyzer.init_options,
+            Some(json!({"cargo": {"features": ["serde"]}}))
+        );
+        assert_eq!(config.features.gating_mode, Some(GatingMode::Lenient));
+        assert_eq!(config.limits.workspace_symbols, Some(25));
+        assert_eq!(
+            config.blocking.patterns,
+            Some(vec![
+                "diesel::RunQueryDsl::load".to_string(),
+                ".wait()".to_string()
+            ])
+        );
+        assert_eq!(config.blocking.ignore, Some(vec![".lock()".to_string()]));
+
+        let limits = config.inspection_limits();
+        assert_eq!(limits.timeout_seconds, 90);
+        assert_eq!(limits.max_output_lines, 500);
+        assert_eq!(
+            limits.max_output_bytes,
+            InspectionLimits::default().max_output_bytes
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        let err = Config::parse("[analyzer]\npaht = \"/opt/ra\"\n").unwrap_err();
+        assert!(err.to_string().contains("paht"), "{err}");
+
+        assert!(Config::parse("[logging]\nlevel = \"debug\"\n").is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let mut config =
+            Config::parse("[analyzer]\npath = \"/from/file\"\nfull_analysis = true\n").unwrap();
+        config.apply_overrides(|key| match key {
+            "RUST_ANALYZER_PATH" => Some("/from/env".to_string()),
+            "MCP_GATING_MODE" => Some("not-a-mode".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(config.analyzer.path.as_deref(), Some("/from/env"));
+        assert_eq!(config.analyzer.full_analysis, Some(true));
+        assert_eq!(config.features.gating_mode, None);
+        assert!(config.allow_writes());
+
+        config.apply_overrides(|key| (key == "RUST_MCP_READONLY").then(|| "1".to_string()));
+        assert!(!config.allow_writes());
+    }
+
+    #[test]
+    fn test_keepalive_settings() {
+        assert_eq!(
+            Config::default().keepalive(),
+            (Some(DEFAULT_KEEPALIVE_INTERVAL), DEFAULT_KEEPALIVE_TIMEOUT)
+        );
+        assert!(Config::parse("[analyzer]\nkeepalive_timeout_seconds = 0\n").is_err());
+
+        let mut config = Config::parse("[analyzer]\nkeepalive_timeout_seconds = 5\n").unwrap();
+        config
+            .apply_overrides(|key| (key == "RUST_MCP_KEEPALIVE_INTERVAL").then(|| "0".to_string()));
+        assert_eq!(config.keepalive(), (None, Duration::from_secs(5)));
+        assert_eq!(
+            config.keepalive_max_busy_ticks(),
+            DEFAULT_KEEPALIVE_MAX_BUSY_TICKS
+        );
+
+        let config = Config::parse("[analyzer]\nkeepalive_max_busy_ticks = 0\n").unwrap();
+        assert_eq!(config.keepalive_max_busy_ticks(), 0);
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let mut base = json!({"cargo": {"loadOutDirsFromCheck": true}, "procMacro": {"enable": true}});
+        merge_json(&mut base, &json!({"cargo": {"features": "all"}, "procMacro": {"enable": false}}));
+        assert_eq!(
+            base,
+            json!({"cargo": {"loadOutDirsFromCheck": true, "features": "all"}, "procMacro": {"enable": false}})
+        );
+    }
+}
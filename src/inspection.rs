@@ -128,6 +128,21 @@ pub struct InspectionResult {
     pub provenance: InspectionProvenance,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectionDiffResult {
+    pub view: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    pub from_opt_level: String,
+    pub to_opt_level: String,
+    pub identical: bool,
+    pub diff: String,
+    pub truncated: bool,
+    #[serde(default)]
+    pub diagnostics: Vec<String>,
+    pub provenance: InspectionProvenance,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InspectionCapabilities {
     pub toolchain_channel: ToolchainChannel,
@@ -247,6 +262,11 @@ impl InspectionContext {
         self
     }
 
+    pub fn with_limits(mut self, limits: InspectionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn limits(&self) -> &InspectionLimits {
         &self.limits
     }
@@ -448,3 +468,346 @@ pub fn truncate_with_limits(
 
     (truncated_output, true, Some(summary))
 }
+
+const DIFF_CONTEXT_LINES: usize = 3;
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Produce a unified diff (three lines of context) between two texts, labelling
+/// the sides with `from_label` and `to_label`. Returns an empty string when the
+/// texts are identical.
+pub fn unified_diff(from: &str, to: &str, from_label: &str, to_label: &str) -> String {
+    let old: Vec<&str> = from.lines().collect();
+    let new: Vec<&str> = to.lines().collect();
+    let ops = diff_ops(&old, &new);
+
+    if ops.iter().all(|op| matches!(op, DiffOp::Equal(..))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {from_label}\n+++ {to_label}\n");
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut hunk_start = 0;
+    while hunk_start < changed.len() {
+        let mut hunk_end = hunk_start;
+        while hunk_end + 1 < changed.len()
+            && changed[hunk_end + 1] - changed[hunk_end] <= DIFF_CONTEXT_LINES * 2
+        {
+            hunk_end += 1;
+        }
+
+        let first = changed[hunk_start].saturating_sub(DIFF_CONTEXT_LINES);
+        let last = (changed[hunk_end] + DIFF_CONTEXT_LINES).min(ops.len() - 1);
+        let hunk = &ops[first..=last];
+
+        let old_start = ops[..first]
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(_)))
+            .count();
+        let new_start = ops[..first]
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(_)))
+            .count();
+        let old_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Insert(_))).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op, DiffOp::Delete(_))).count();
+
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            old_len,
+            new_start + 1,
+            new_len
+        ));
+        for op in hunk {
+            match *op {
+                DiffOp::Equal(idx, _) => output.push_str(&format!(" {}\n", old[idx])),
+                DiffOp::Delete(idx) => output.push_str(&format!("-{}\n", old[idx])),
+                DiffOp::Insert(idx) => output.push_str(&format!("+{}\n", new[idx])),
+            }
+        }
+
+        hunk_start = hunk_end + 1;
+    }
+
+    output
+}
+
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|idx| DiffOp::Equal(idx, idx)).collect();
+
+    if old_mid.len() * new_mid.len() > MAX_DIFF_CELLS {
+        // Too large for an LCS table; report the middle as replaced wholesale.
+        ops.extend((0..old_mid.len()).map(|idx| DiffOp::Delete(prefix + idx)));
+        ops.extend((0..new_mid.len()).map(|idx| DiffOp::Insert(prefix + idx)));
+    } else {
+        let rows = old_mid.len() + 1;
+        let cols = new_mid.len() + 1;
+        let mut lcs = vec![0u32; rows * cols];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * cols + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * cols + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * cols + j].max(lcs[i * cols + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                ops.push(DiffOp::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if i < old_mid.len()
+                && (j == new_mid.len() || lcs[(i + 1) * cols + j] >= lcs[i * cols + j + 1])
+            {
+                ops.push(DiffOp::Delete(prefix + i));
+                i += 1;
+            } else {
+                ops.push(DiffOp::Insert(prefix + j));
+                j += 1;
+            }
+        }
+    }
+
+    let old_tail = old.len() - suffix;
+    let new_tail = new.len() - suffix;
+    ops.extend((0..suffix).map(|idx| DiffOp::Equal(old_tail + idx, new_tail + idx)));
+    ops
+}
+
+/// The changes a unified diff makes to one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// The `---` label, without a trailing timestamp.
+    pub old_label: String,
+    /// The `+++` label, without a trailing timestamp.
+    pub new_label: String,
+    hunks: Vec<PatchHunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PatchHunk {
+    /// 1-based first line of the old side; 0 for a hunk adding to an empty file.
+    old_start: usize,
+    /// Body lines with their ` `, `-` or `+` marker.
+    lines: Vec<(char, String)>,
+}
+
+/// Splits a unified diff, as written by [`unified_diff`] or `git diff`, into
+/// one [`FilePatch`] per file. Lines outside file sections (`diff --git`,
+/// `index`, prose) are skipped.
+pub fn parse_unified_diff(diff: &str) -> Result<Vec<FilePatch>, String> {
+    let label = |line: &str, marker: &str| {
+        let label = line.strip_prefix(marker).unwrap_or_default();
+        label
+            .split('\t')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut lines = diff.lines().peekable();
+    while let Some(line) = lines.next() {
+        if line.starts_with("--- ") && lines.peek().is_some_and(|next| next.starts_with("+++ ")) {
+            let new_line = lines.next().unwrap_or_default();
+            patches.push(FilePatch {
+                old_label: label(line, "--- "),
+                new_label: label(new_line, "+++ "),
+                hunks: Vec::new(),
+            });
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            let patch = patches
+                .last_mut()
+                .ok_or_else(|| format!("Hunk before any --- / +++ header: {line}"))?;
+            let (old_start, old_len, new_len) = parse_hunk_header(header)
+                .ok_or_else(|| format!("Malformed hunk header: {line}"))?;
+            let (mut old_seen, mut new_seen) = (0, 0);
+            let mut body = Vec::new();
+            while old_seen < old_len || new_seen < new_len {
+                let Some(body_line) = lines.next() else {
+                    return Err(format!("Hunk `{}` ends early", line.trim()));
+                };
+                if body_line.starts_with('\\') {
+                    // "\ No newline at end of file"
+                    continue;
+                }
+                let mut chars = body_line.chars();
+                let marker = chars.next().unwrap_or(' ');
+                match marker {
+                    ' ' => {
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                    '-' => old_seen += 1,
+                    '+' => new_seen += 1,
+                    _ => return Err(format!("Unexpected line in hunk: {body_line}")),
+                }
+                body.push((marker, chars.as_str().to_string()));
+            }
+            patch.hunks.push(PatchHunk {
+                old_start,
+                lines: body,
+            });
+        }
+    }
+    if patches.is_empty() {
+        return Err("No --- / +++ file headers found in the diff".to_string());
+    }
+    Ok(patches)
+}
+
+/// `-12,3 +12,4 @@ fn f()` as `(12, 3, 4)`; a missing length means 1.
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize)> {
+    let range = |part: &str, sign: char| -> Option<(usize, usize)> {
+        let part = part.strip_prefix(sign)?;
+        match part.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((part.parse().ok()?, 1)),
+        }
+    };
+    let mut parts = header.split_whitespace();
+    let (old_start, old_len) = range(parts.next()?, '-')?;
+    let (_, new_len) = range(parts.next()?, '+')?;
+    Some((old_start, old_len, new_len))
+}
+
+impl FilePatch {
+    /// Applies the hunks to `original`. Each hunk's context and removed lines
+    /// must match exactly; a hunk is searched near its stated line when
+    /// earlier edits moved it.
+    pub fn apply(&self, original: &str) -> Result<String, String> {
+        let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+        // How far the previous hunk was from its stated line, plus the lines
+        // it added or removed.
+        let mut shift: isize = 0;
+        for (idx, hunk) in self.hunks.iter().enumerate() {
+            let old: Vec<&str> = hunk
+                .lines
+                .iter()
+                .filter(|(marker, _)| *marker != '+')
+                .map(|(_, text)| text.as_str())
+                .collect();
+            let new: Vec<String> = hunk
+                .lines
+                .iter()
+                .filter(|(marker, _)| *marker != '-')
+                .map(|(_, text)| text.clone())
+                .collect();
+
+            let expected = (hunk.old_start.saturating_sub(1) as isize + shift).max(0) as usize;
+            let matches_at = |at: usize| {
+                at + old.len() <= lines.len()
+                    && lines[at..at + old.len()]
+                        .iter()
+                        .zip(&old)
+                        .all(|(a, b)| a == b)
+            };
+            let at = (0..=lines.len())
+                .flat_map(|distance| [expected.checked_sub(distance), Some(expected + distance)])
+                .flatten()
+                .find(|at| matches_at(*at))
+                .ok_or_else(|| {
+                    format!(
+                        "Hunk {} (line {}) of {} does not match the file",
+                        idx + 1,
+                        hunk.old_start,
+                        self.new_label
+                    )
+                })?;
+
+            shift = at as isize - hunk.old_start.saturating_sub(1) as isize + new.len() as isize
+                - old.len() as isize;
+            lines.splice(at..at + old.len(), new);
+        }
+
+        let mut updated = lines.join("\n");
+        if !updated.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+            updated.push('\n');
+        }
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_unified_diff, unified_diff};
+
+    #[test]
+    fn identical_texts_produce_empty_diff() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "opt-level=0", "opt-level=3"), "");
+    }
+
+    #[test]
+    fn diff_reports_changed_lines_with_labels() {
+        let from = "fn f() {\n    let x = 1;\n    let y = x + 1;\n    y\n}\n";
+        let to = "fn f() {\n    2\n}\n";
+
+        let diff = unified_diff(from, to, "opt-level=0", "opt-level=3");
+
+        assert_eq!(
+            diff,
+            "--- opt-level=0\n+++ opt-level=3\n@@ -1,5 +1,3 @@\n fn f() {\n-    let x = 1;\n-    let y = x + 1;\n-    y\n+    2\n }\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_are_split_into_hunks() {
+        let from: String = (0..20).map(|n| format!("line {n}\n")).collect();
+        let to = from.replace("line 2\n", "line two\n").replace("line 17\n", "line seventeen\n");
+
+        let diff = unified_diff(&from, &to, "a", "b");
+
+        assert_eq!(diff.matches("@@ ").count(), 2);
+        assert!(diff.contains("@@ -1,6 +1,6 @@\n"));
+        assert!(diff.contains("-line 17\n+line seventeen\n"));
+    }
+
+    #[test]
+    fn parsed_diff_reapplies_to_the_original() {
+        let from: String = (0..20).map(|n| format!("line {n}\n")).collect();
+        let to = from
+            .replace("line 2\n", "line two\nline two and a half\n")
+            .replace("line 17\n", "");
+
+        let diff = format!(
+            "diff --git a/x b/x\n{}",
+            unified_diff(&from, &to, "a/x", "b/x")
+        );
+        let patches = parse_unified_diff(&diff).unwrap();
+
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].new_label, "b/x");
+        assert_eq!(patches[0].apply(&from).unwrap(), to);
+        // Lines inserted above every hunk only move them.
+        let shifted = format!("extra\n{from}");
+        assert_eq!(patches[0].apply(&shifted).unwrap(), format!("extra\n{to}"));
+        assert!(patches[0].apply("line 0\n").is_err());
+    }
+}
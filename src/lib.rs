@@ -1,5 +1,6 @@
 pub mod analyzer;
 pub mod compiler;
+pub mod config;
 pub mod inspection;
 pub mod server;
 pub mod tools;
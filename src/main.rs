@@ -1,11 +1,15 @@
 use anyhow::Result;
 use rmcp::{ServiceExt, transport::stdio};
+use rustmcp::config::Config;
 use rustmcp::server::RustMcpServer;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Settings from .rust-mcp.toml, overridden by environment variables
+    let config = Config::load(&std::env::current_dir()?)?;
+
     // Initialize the rust-analyzer integration
-    let mut rust_server = RustMcpServer::new();
+    let mut rust_server = RustMcpServer::with_config(config);
     rust_server.start().await?;
 
     // Note: The #[tool] macros generate additional tools beyond our manual list
@@ -1,9 +1,9 @@
 use anyhow::Result;
 use rmcp::{
     tool, tool_handler, tool_router,
-    model::{CallToolResult, Content, ErrorCode, ServerInfo, ProtocolVersion, ServerCapabilities, Implementation, ErrorData as McpError},
+    model::{CallToolResult, Content, ErrorCode, Meta, ProgressNotificationParam, ProgressToken, ServerInfo, ProtocolVersion, ServerCapabilities, Implementation, ErrorData as McpError},
     handler::server::{tool::ToolRouter, wrapper::Parameters},
-    ServerHandler,
+    Peer, RoleServer, ServerHandler,
 };
 use serde::Serialize;
 use serde_json::{Value, json};
@@ -16,18 +16,22 @@ use tokio::{fs, sync::Mutex};
 
 use crate::analyzer::{
     RustAnalyzerClient,
+    health::{AnalyzerHealth, run_keepalive},
     symbol::{SymbolIdentity, SymbolKind, identity_from_definition},
 };
 use crate::compiler::{
     CompilerRunner, RunRequest, RunResult, RunnerError,
     extract::{NormalizedSymbol, TargetedAssembly, extract_asm, extract_llvm_ir, extract_mir},
+    progress::{CargoProgress, CargoProgressSender},
 };
+use crate::config::{BlockingConfig, Config};
 use crate::inspection::{
-    GatingMode, InspectionCapabilities, InspectionContext, InspectionLimits, InspectionResult,
-    InspectionView, TruncationSummary, is_view_advertised, is_view_runnable, truncate_with_limits,
+    GatingMode, InspectionCapabilities, InspectionContext, InspectionDiffResult,
+    InspectionLimits, InspectionResult, InspectionView, TruncationSummary, is_view_advertised,
+    is_view_runnable, truncate_with_limits, unified_diff,
 };
 use crate::server::parameters::*;
-use crate::tools::{execute_tool, get_tools};
+use crate::tools::{execute_tool, execute_tool_with_progress, get_tools};
 
 struct ResolvedDefinition {
     symbol: Option<SymbolIdentity>,
@@ -39,6 +43,9 @@ pub struct RustMcpServer {
     analyzer: Arc<Mutex<RustAnalyzerClient>>,
     tool_router: ToolRouter<RustMcpServer>,
     inspection: InspectionContext,
+    workspace_symbols_limit: Option<usize>,
+    blocking: BlockingConfig,
+    health: Arc<std::sync::Mutex<AnalyzerHealth>>,
 }
 
 impl Default for RustMcpServer {
@@ -50,17 +57,48 @@ impl Default for RustMcpServer {
 #[tool_router]
 impl RustMcpServer {
     pub fn new() -> Self {
+        Self::with_config(Config::from_env())
+    }
+
+    pub fn with_config(config: Config) -> Self {
         let workspace_root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let mut inspection =
+            InspectionContext::new(workspace_root).with_limits(config.inspection_limits());
+        if let Some(mode) = config.features.gating_mode {
+            inspection = inspection.with_gating_mode(mode);
+        }
+        let (interval, timeout) = config.keepalive();
+
         Self {
-            analyzer: Arc::new(Mutex::new(RustAnalyzerClient::new())),
+            analyzer: Arc::new(Mutex::new(
+                RustAnalyzerClient::with_config(config.analyzer.clone())
+                    .with_allow_writes(config.allow_writes()),
+            )),
             tool_router: Self::tool_router(),
-            inspection: InspectionContext::new(workspace_root),
+            inspection,
+            workspace_symbols_limit: config.limits.workspace_symbols,
+            blocking: config.blocking.clone(),
+            health: Arc::new(std::sync::Mutex::new(
+                AnalyzerHealth::new(interval, timeout)
+                    .with_max_busy_ticks(config.keepalive_max_busy_ticks()),
+            )),
         }
     }
 
+    /// Starts rust-analyzer and the keepalive that restarts it when it stops
+    /// answering.
     pub async fn start(&mut self) -> Result<()> {
-        let mut analyzer = self.analyzer.lock().await;
-        analyzer.start().await
+        let process = {
+            let mut analyzer = self.analyzer.lock().await;
+            analyzer.start().await?;
+            analyzer.process_handle()
+        };
+        tokio::spawn(run_keepalive(
+            self.analyzer.clone(),
+            process,
+            self.health.clone(),
+        ));
+        Ok(())
     }
 
     pub fn list_tools(&self) -> Vec<crate::tools::ToolDefinition> {
@@ -111,6 +149,20 @@ impl RustMcpServer {
         Ok(CallToolResult::success(vec![json_content(capabilities).map_err(to_mcp_error)?]))
     }
 
+    #[tool(description = "Report rust-analyzer health as seen by the keepalive: state, last ping, restarts")]
+    async fn server_status(&self) -> std::result::Result<CallToolResult, McpError> {
+        let mut status = self
+            .health
+            .lock()
+            .map(|health| health.to_json())
+            .map_err(|_| McpError::internal_error("Health state is unavailable", None))?;
+        // Never wait for the client here: a stuck tool call would block the
+        // status report too.
+        status["busy"] = json!(self.analyzer.try_lock().is_err());
+
+        Ok(CallToolResult::success(vec![json_content(status).map_err(to_mcp_error)?]))
+    }
+
     #[tool(description = "Inspect compiler artifacts using curated presets")]
     async fn inspect(
         &self,
@@ -143,23 +195,2887 @@ impl RustMcpServer {
         Ok(CallToolResult::success(vec![json_content(result).map_err(to_mcp_error)?]))
     }
 
-    fn inspection_context(&self, gating_override: Option<&str>) -> InspectionContext {
-        let mut context = self.inspection.clone();
-        if let Some(mode) = gating_override.and_then(|value| GatingMode::from_str(value).ok()) {
-            context = context.with_gating_mode(mode);
+    #[tool(description = "Diff a symbol's MIR, LLVM IR or assembly between two optimization levels")]
+    async fn inspect_diff(
+        &self,
+        Parameters(InspectDiffParams {
+            view,
+            file_path,
+            line,
+            character,
+            symbol_name,
+            from_opt_level,
+            to_opt_level,
+            target,
+            gating_mode,
+        }): Parameters<InspectDiffParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        if view.eq_ignore_ascii_case("def") {
+            return Err(to_mcp_error(mcp_error(
+                ErrorCode::INVALID_PARAMS,
+                "The `def` view does not depend on the optimization level",
+                None,
+            )));
+        }
+
+        let context = self.inspection_context(gating_mode.as_deref());
+        let from_opt_level = from_opt_level.unwrap_or_else(|| "0".to_string());
+        let to_opt_level = to_opt_level.unwrap_or_else(|| "3".to_string());
+
+        let mut sides = Vec::new();
+        for opt_level in [&from_opt_level, &to_opt_level] {
+            let result = self
+                .perform_inspection(
+                    &context,
+                    &view,
+                    &file_path,
+                    Some(line),
+                    Some(character),
+                    symbol_name.clone(),
+                    Some(opt_level.clone()),
+                    target.clone(),
+                )
+                .await
+                .map_err(to_mcp_error)?;
+            sides.push(result);
+        }
+
+        let to = sides.pop().expect("two inspection results");
+        let from = sides.pop().expect("two inspection results");
+
+        let mut diagnostics = Vec::new();
+        for (opt_level, side) in [(&from_opt_level, &from), (&to_opt_level, &to)] {
+            diagnostics.extend(
+                side.diagnostics
+                    .iter()
+                    .map(|diagnostic| format!("[opt-level={opt_level}] {diagnostic}")),
+            );
+        }
+
+        let diff = unified_diff(
+            &from.text,
+            &to.text,
+            &format!("{} at opt-level={from_opt_level}", from.view),
+            &format!("{} at opt-level={to_opt_level}", to.view),
+        );
+        let identical = diff.is_empty();
+        let (diff, truncated, truncation) = truncate_with_limits(&diff, context.limits());
+        if let Some(summary) = &truncation {
+            diagnostics.push(truncation_note(summary));
+        }
+
+        let result = InspectionDiffResult {
+            view: to.view,
+            symbol: to.symbol,
+            from_opt_level,
+            to_opt_level,
+            identical,
+            diff,
+            truncated,
+            diagnostics,
+            provenance: to.provenance.with_truncation(truncation),
+        };
+
+        Ok(CallToolResult::success(vec![json_content(result).map_err(to_mcp_error)?]))
+    }
+
+    fn inspection_context(&self, gating_override: Option<&str>) -> InspectionContext {
+        let mut context = self.inspection.clone();
+        if let Some(mode) = gating_override.and_then(|value| GatingMode::from_str(value).ok()) {
+            context = context.with_gating_mode(mode);
+        }
+        context
+    }
+
+    #[tool(description = "Find the definition of a symbol at a given position")]
+    async fn find_definition(
+        &self,
+        Parameters(FindDefinitionParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            analysis,
+        }): Parameters<FindDefinitionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_definition", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No definition found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find the definitions of several symbols in one file in a single call")]
+    async fn find_definitions_batch(
+        &self,
+        Parameters(FindDefinitionsBatchParams { file_path, queries }): Parameters<
+            FindDefinitionsBatchParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "queries": queries
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_definitions_batch", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No definitions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find all references to a symbol at a given position")]
+    async fn find_references(
+        &self,
+        Parameters(FindReferencesParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            scope,
+            analysis,
+        }): Parameters<FindReferencesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "scope": scope,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_references", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No references found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List every call site of a function, grouped by the calling module")]
+    async fn find_callers(
+        &self,
+        Parameters(FindCallersParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<FindCallersParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_callers", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No callers found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List the loops and blocks enclosing a position, innermost first")]
+    async fn enclosing_blocks(
+        &self,
+        Parameters(EnclosingBlocksParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<EnclosingBlocksParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("enclosing_blocks", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No enclosing blocks found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get compiler diagnostics for a file")]
+    async fn get_diagnostics(
+        &self,
+        Parameters(GetDiagnosticsParams { file_path }): Parameters<GetDiagnosticsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_diagnostics", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No diagnostics found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Extract a diagnostic's item and the definitions it uses into a snippet")]
+    async fn diagnostic_snippet(
+        &self,
+        Parameters(DiagnosticSnippetParams {
+            file_path,
+            line,
+            message,
+            max_depth,
+        }): Parameters<DiagnosticSnippetParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "line": line,
+            "message": message,
+            "max_depth": max_depth
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("diagnostic_snippet", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No snippet extracted",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Search for symbols in the workspace")]
+    async fn workspace_symbols(
+        &self,
+        Parameters(WorkspaceSymbolsParams {
+            query,
+            limit,
+            offset,
+            analysis,
+        }): Parameters<WorkspaceSymbolsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "query": query,
+            "limit": limit.map(|limit| limit as usize).or(self.workspace_symbols_limit),
+            "offset": offset,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("workspace_symbols", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No symbols found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Resolve a fully-qualified Rust path to its definition location")]
+    async fn resolve_path(
+        &self,
+        Parameters(ResolvePathParams { path }): Parameters<ResolvePathParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("resolve_path", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Path could not be resolved",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find functions whose signature matches simple predicates")]
+    async fn find_by_signature(
+        &self,
+        Parameters(FindBySignatureParams {
+            query,
+            is_async,
+            returns,
+            takes,
+            limit,
+        }): Parameters<FindBySignatureParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "query": query,
+            "is_async": is_async,
+            "returns": returns,
+            "takes": takes,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_by_signature", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No matching functions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List macro_rules! and procedural macros defined in a crate")]
+    async fn list_macros(
+        &self,
+        Parameters(ListMacrosParams { crate_path }): Parameters<ListMacrosParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "crate_path": crate_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_macros", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No macros found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Show the definition source of an invoked macro")]
+    async fn goto_macro_definition(
+        &self,
+        Parameters(GotoMacroDefinitionParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            max_lines,
+        }): Parameters<GotoMacroDefinitionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "max_lines": max_lines
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("goto_macro_definition", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No macro definition found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Show the impl generated for one trait of a derive attribute")]
+    async fn expand_derive(
+        &self,
+        Parameters(ExpandDeriveParams {
+            file_path,
+            code_block,
+            trait_name,
+        }): Parameters<ExpandDeriveParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "code_block": code_block,
+            "trait_name": trait_name
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("expand_derive", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No expansion found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find the parent module file and the line of its mod declaration")]
+    async fn goto_parent_module(
+        &self,
+        Parameters(GotoParentModuleParams { file_path }): Parameters<GotoParentModuleParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("goto_parent_module", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No parent module found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List a module's pub use re-exports and the items they resolve to")]
+    async fn list_reexports(
+        &self,
+        Parameters(ListReexportsParams {
+            file_path,
+            include_restricted,
+        }): Parameters<ListReexportsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "include_restricted": include_restricted
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_reexports", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No re-exports found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Check whether a file is reachable from its crate root via mod")]
+    async fn is_file_in_module_tree(
+        &self,
+        Parameters(IsFileInModuleTreeParams { file_path }): Parameters<IsFileInModuleTreeParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("is_file_in_module_tree", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No module tree information",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find the use and mod declarations that import a module")]
+    async fn find_module_importers(
+        &self,
+        Parameters(FindModuleImportersParams { module, path }): Parameters<
+            FindModuleImportersParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "module": module,
+            "path": path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_module_importers", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No importers found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List the symbols a file exports and the external symbols it uses")]
+    async fn file_interface(
+        &self,
+        Parameters(FileInterfaceParams { file_path }): Parameters<FileInterfaceParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("file_interface", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No interface found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List cfg-gated regions of a file with readable conditions")]
+    async fn list_cfg_regions(
+        &self,
+        Parameters(ListCfgRegionsParams { file_path, target }): Parameters<ListCfgRegionsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "target": target
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_cfg_regions", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No cfg regions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Map Cargo features to the code they gate")]
+    async fn feature_map(
+        &self,
+        Parameters(FeatureMapParams { manifest_path }): Parameters<FeatureMapParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "manifest_path": manifest_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("feature_map", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No features found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find the Cargo features a file needs for all its items to compile")]
+    async fn required_features(
+        &self,
+        Parameters(RequiredFeaturesParams { file_path }): Parameters<RequiredFeaturesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("required_features", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No required features found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List items that only exist in test builds (#[cfg(test)])")]
+    async fn list_test_only_items(
+        &self,
+        Parameters(ListTestOnlyItemsParams { path }): Parameters<ListTestOnlyItemsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_test_only_items", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No test-only items found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Show the cfg flags rustc enables for a target (defaults to the host)")]
+    async fn show_cfg(
+        &self,
+        Parameters(ShowCfgParams { target, file_path }): Parameters<ShowCfgParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "target": target,
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("show_cfg", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No cfg flags reported",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get the inferred type of a let binding, verbatim and fully qualified")]
+    async fn infer_type(
+        &self,
+        Parameters(InferTypeParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<InferTypeParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("infer_type", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No inferred type found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Show the type after each call of a method chain")]
+    async fn trace_chain(
+        &self,
+        Parameters(TraceChainParams {
+            file_path,
+            expression,
+            code_block,
+            occurrence,
+        }): Parameters<TraceChainParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "expression": expression,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("trace_chain", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No chain types found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Check whether two types are the same after resolving aliases and paths")]
+    async fn types_equal(
+        &self,
+        Parameters(TypesEqualParams {
+            file_path,
+            left,
+            right,
+            right_file_path,
+        }): Parameters<TypesEqualParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "left": left,
+            "right": right,
+            "right_file_path": right_file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("types_equal", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No comparison result",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(
+        description = "Get hover information (signature and documentation) for a symbol at a given position"
+    )]
+    async fn get_hover(
+        &self,
+        Parameters(GetHoverParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            expand_aliases,
+            analysis,
+        }): Parameters<GetHoverParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "expand_aliases": expand_aliases,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_hover", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No hover information found"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No hover information found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get the structure (symbols) of a file (outline)")]
+    async fn document_symbols(
+        &self,
+        Parameters(GetDocumentSymbolsParams {
+            file_path,
+            start_line,
+            end_line,
+            analysis,
+        }): Parameters<GetDocumentSymbolsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "start_line": start_line,
+            "end_line": end_line,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("document_symbols", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No symbols found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Show the token, its kind and range at a line and character")]
+    async fn token_at(
+        &self,
+        Parameters(TokenAtParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<TokenAtParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "line": line,
+            "character": character
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("token_at", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No token found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get only the rustdoc comment attached to a symbol")]
+    async fn get_docs(
+        &self,
+        Parameters(GetDocsParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<GetDocsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_docs", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No documentation found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Run the code examples in a symbol's doc comment as doctests")]
+    async fn check_doc_example(
+        &self,
+        Parameters(CheckDocExampleParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            run,
+        }): Parameters<CheckDocExampleParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "run": run
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("check_doc_example", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No doc examples checked",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Diff one item's source between two git refs")]
+    async fn symbol_diff(
+        &self,
+        Parameters(SymbolDiffParams {
+            file_path,
+            symbol,
+            from_ref,
+            to_ref,
+        }): Parameters<SymbolDiffParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "from_ref": from_ref,
+            "to_ref": to_ref
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("symbol_diff", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No diff produced",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get the docs.rs or doc.rust-lang.org URL and inline docs for a symbol")]
+    async fn get_external_docs(
+        &self,
+        Parameters(GetExternalDocsParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<GetExternalDocsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_external_docs", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No documentation found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get the std or dependency source a symbol resolves to")]
+    async fn get_external_source(
+        &self,
+        Parameters(GetExternalSourceParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            max_lines,
+        }): Parameters<GetExternalSourceParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "max_lines": max_lines
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_external_source", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No source found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get the source code of a specific symbol (function, struct, etc.)")]
+    async fn get_symbol_source(
+        &self,
+        Parameters(GetSymbolSourceParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            include_impl_context,
+            max_lines,
+            analysis,
+        }): Parameters<GetSymbolSourceParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "include_impl_context": include_impl_context,
+            "max_lines": max_lines,
+            "analysis": analysis
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_symbol_source", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No source found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Rename a symbol with scope awareness")]
+    async fn rename_symbol(
+        &self,
+        Parameters(RenameSymbolParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            new_name,
+            scope,
+            verify,
+        }): Parameters<RenameSymbolParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "new_name": new_name,
+            "scope": scope,
+            "verify": verify
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("rename_symbol", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Rename operation completed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Rename an item and classify the semver impact of the change")]
+    async fn rename_api_item(
+        &self,
+        Parameters(RenameApiItemParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            new_name,
+            verify,
+        }): Parameters<RenameApiItemParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "new_name": new_name,
+            "verify": verify
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("rename_api_item", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Rename operation completed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Execute cargo check and parse errors")]
+    async fn run_cargo_check(
+        &self,
+        Parameters(RunCargoCheckParams {
+            workspace_path,
+            file_path,
+        }): Parameters<RunCargoCheckParams>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "workspace_path": workspace_path,
+            "file_path": file_path
+        });
+
+        let progress = meta
+            .get_progress_token()
+            .map(|token| spawn_progress_forwarder(peer, token));
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool_with_progress("run_cargo_check", args, &mut analyzer, progress).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Cargo check completed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Count cargo check diagnostics by error or lint code and by crate")]
+    async fn diagnostic_histogram(
+        &self,
+        Parameters(DiagnosticHistogramParams {
+            workspace_path,
+            file_path,
+            limit,
+        }): Parameters<DiagnosticHistogramParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "workspace_path": workspace_path,
+            "file_path": file_path,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("diagnostic_histogram", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No diagnostics counted",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List a file's runnable tests and binaries with their cargo commands")]
+    async fn list_runnables(
+        &self,
+        Parameters(ListRunnablesParams { file_path }): Parameters<ListRunnablesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_runnables", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No runnables found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List a package's tests and benches with paths and cargo filters")]
+    async fn list_tests(
+        &self,
+        Parameters(ListTestsParams { path, limit }): Parameters<ListTestsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("list_tests", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No tests found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Report edition and MSRV and flag newer features used in a file")]
+    async fn check_msrv(
+        &self,
+        Parameters(CheckMsrvParams { file_path }): Parameters<CheckMsrvParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("check_msrv", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No MSRV report",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find the package and workspace Cargo.toml governing a file")]
+    async fn find_manifest(
+        &self,
+        Parameters(FindManifestParams { file_path }): Parameters<FindManifestParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_manifest", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No manifest found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Compare cargo check diagnostics with and without a set of features")]
+    async fn preview_features(
+        &self,
+        Parameters(PreviewFeaturesParams {
+            workspace_path,
+            features,
+            no_default_features,
+            all_features,
+            file_path,
+        }): Parameters<PreviewFeaturesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "workspace_path": workspace_path,
+            "features": features,
+            "no_default_features": no_default_features,
+            "all_features": all_features,
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("preview_features", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Feature preview completed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Extract selected code into a new function")]
+    async fn extract_function(
+        &self,
+        Parameters(ExtractFunctionParams {
+            file_path,
+            code_block,
+            occurrence,
+            function_name,
+        }): Parameters<ExtractFunctionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "function_name": function_name
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("extract_function", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Function extracted successfully",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Inline a function call at specified position")]
+    async fn inline_function(
+        &self,
+        Parameters(InlineFunctionParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<InlineFunctionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("inline_function", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Function inlined successfully",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Add a test stub for a function to the file's test module")]
+    async fn scaffold_test(
+        &self,
+        Parameters(ScaffoldTestParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<ScaffoldTestParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("scaffold_test", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Test stub added",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Insert the missing arms of a match expression")]
+    async fn fill_match_arms(
+        &self,
+        Parameters(FillMatchArmsParams {
+            file_path,
+            code_block,
+            occurrence,
+        }): Parameters<FillMatchArmsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("fill_match_arms", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Match arms filled",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Report which enum variants a match covers or misses")]
+    async fn match_coverage(
+        &self,
+        Parameters(MatchCoverageParams {
+            file_path,
+            code_block,
+            occurrence,
+        }): Parameters<MatchCoverageParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("match_coverage", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No match coverage available",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List the use paths rust-analyzer would auto-import for an unresolved identifier")]
+    async fn suggest_import(
+        &self,
+        Parameters(SuggestImportParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<SuggestImportParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("suggest_import", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No import candidates found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Report the trait a method call comes from, if any, and its import path")]
+    async fn method_trait(
+        &self,
+        Parameters(MethodTraitParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<MethodTraitParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("method_trait", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Method not resolved",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Report the Self type and implemented trait of the impl around a symbol")]
+    async fn self_type(
+        &self,
+        Parameters(SelfTypeParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<SelfTypeParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("self_type", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No enclosing impl found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Generate a From or TryFrom impl skeleton between two structs")]
+    async fn generate_conversion(
+        &self,
+        Parameters(GenerateConversionParams {
+            source_type,
+            target_type,
+            kind,
+            error_type,
+            insert,
+        }): Parameters<GenerateConversionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "source_type": source_type,
+            "target_type": target_type,
+            "kind": kind,
+            "error_type": error_type,
+            "insert": insert
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("generate_conversion", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No conversion generated",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Generate a Default impl for a struct, or suggest deriving it")]
+    async fn generate_default(
+        &self,
+        Parameters(GenerateDefaultParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            insert,
+        }): Parameters<GenerateDefaultParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "insert": insert
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("generate_default", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No Default impl generated",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Generate a builder with per-field setters and build() for a struct")]
+    async fn generate_builder(
+        &self,
+        Parameters(GenerateBuilderParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            setters,
+            required,
+            insert,
+        }): Parameters<GenerateBuilderParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "setters": setters,
+            "required": required,
+            "insert": insert
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("generate_builder", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No builder generated",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Move methods of an inherent impl into a new trait")]
+    async fn extract_trait(
+        &self,
+        Parameters(ExtractTraitParams {
+            file_path,
+            code_block,
+            occurrence,
+            trait_name,
+            methods,
+        }): Parameters<ExtractTraitParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "trait_name": trait_name,
+            "methods": methods
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("extract_trait", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No trait extracted",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Import a chosen path for an unresolved identifier and verify it resolves")]
+    async fn add_import(
+        &self,
+        Parameters(AddImportParams {
+            file_path,
+            symbol,
+            code_block,
+            path,
+            occurrence,
+        }): Parameters<AddImportParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "path": path,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("add_import", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text("Import added")]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Create a module file and declare it in its parent module")]
+    async fn create_module(
+        &self,
+        Parameters(CreateModuleParams {
+            parent_file,
+            name,
+            visibility,
+            mod_rs,
+            content,
+        }): Parameters<CreateModuleParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "parent_file": parent_file,
+            "name": name,
+            "visibility": visibility,
+            "mod_rs": mod_rs,
+            "content": content
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("create_module", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Module created",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Propose a heuristic split of a large file into submodules")]
+    async fn suggest_module_split(
+        &self,
+        Parameters(SuggestModuleSplitParams {
+            file_path,
+            min_lines,
+        }): Parameters<SuggestModuleSplitParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "min_lines": min_lines
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("suggest_module_split", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No split proposed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Replace a range or code block in a file and return its fresh diagnostics")]
+    async fn apply_edit(
+        &self,
+        Parameters(ApplyEditParams {
+            file_path,
+            new_text,
+            range,
+            code_block,
+        }): Parameters<ApplyEditParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "new_text": new_text,
+            "range": range,
+            "code_block": code_block
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("apply_edit", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text("Edit applied")]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Check whether a diff or edits would compile without writing to disk")]
+    async fn dry_run_patch(
+        &self,
+        Parameters(DryRunPatchParams { diff, edits }): Parameters<DryRunPatchParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "diff": diff,
+            "edits": edits
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("dry_run_patch", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No dry run result",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Check a new body for one function in memory and return its diagnostics")]
+    async fn check_function(
+        &self,
+        Parameters(CheckFunctionParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            new_body,
+        }): Parameters<CheckFunctionParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "new_body": new_body
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("check_function", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No check result",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Diff a file's diagnostics before and after an in-memory edit")]
+    async fn diagnostics_delta(
+        &self,
+        Parameters(DiagnosticsDeltaParams {
+            file_path,
+            new_text,
+            range,
+            code_block,
+        }): Parameters<DiagnosticsDeltaParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "new_text": new_text,
+            "range": range,
+            "code_block": code_block
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("diagnostics_delta", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No diagnostics delta",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Format only the lines of a range or code block, leaving the rest of the file as is")]
+    async fn format_range(
+        &self,
+        Parameters(FormatRangeParams {
+            file_path,
+            range,
+            code_block,
+        }): Parameters<FormatRangeParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "range": range,
+            "code_block": code_block
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("format_range", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Range formatted",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Run rustfmt on a file with a given edition and inline config options")]
+    async fn format_with_config(
+        &self,
+        Parameters(FormatWithConfigParams {
+            file_path,
+            edition,
+            config,
+            use_project_config,
+            write,
+        }): Parameters<FormatWithConfigParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "edition": edition,
+            "config": config,
+            "use_project_config": use_project_config,
+            "write": write
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("format_with_config", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "File formatted",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Apply clippy lint suggestions to improve code quality")]
+    async fn apply_clippy_suggestions(
+        &self,
+        Parameters(ApplyClippySuggestionsParams { file_path }): Parameters<
+            ApplyClippySuggestionsParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("apply_clippy_suggestions", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Clippy suggestions applied successfully",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find redundant clones and clones of Copy values, optionally fixing them")]
+    async fn find_unnecessary_clones(
+        &self,
+        Parameters(FindUnnecessaryClonesParams {
+            workspace_path,
+            file_path,
+            apply,
+        }): Parameters<FindUnnecessaryClonesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "workspace_path": workspace_path,
+            "file_path": file_path,
+            "apply": apply
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_unnecessary_clones", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No unnecessary clones found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List Cargo.toml dependencies that the crate's source never references")]
+    async fn unused_dependencies(
+        &self,
+        Parameters(UnusedDependenciesParams { manifest_path }): Parameters<
+            UnusedDependenciesParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "manifest_path": manifest_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("unused_dependencies", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No unused dependencies found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Rank functions in a file or crate by estimated cyclomatic complexity")]
+    async fn complexity_report(
+        &self,
+        Parameters(ComplexityReportParams { path, limit }): Parameters<ComplexityReportParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("complexity_report", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No functions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List functions longer than a line threshold, largest first")]
+    async fn find_large_functions(
+        &self,
+        Parameters(FindLargeFunctionsParams { path, threshold }): Parameters<
+            FindLargeFunctionsParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "threshold": threshold
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_large_functions", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No large functions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Flag functions returning Result, Option or Self that lack #[must_use]")]
+    async fn must_use_candidates(
+        &self,
+        Parameters(MustUseCandidatesParams {
+            path,
+            include_private,
+            apply,
+            limit,
+        }): Parameters<MustUseCandidatesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "include_private": include_private,
+            "apply": apply,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("must_use_candidates", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No candidates found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List dyn Trait and impl Trait types by position")]
+    async fn find_dyn_usages(
+        &self,
+        Parameters(FindDynUsagesParams { path, include_impl }): Parameters<FindDynUsagesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "include_impl": include_impl
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_dyn_usages", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No dyn usages found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List async functions and the await points in each")]
+    async fn async_map(
+        &self,
+        Parameters(AsyncMapParams { path }): Parameters<AsyncMapParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("async_map", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No async functions found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Flag blocking calls such as std::fs or thread::sleep inside async code")]
+    async fn find_blocking_in_async(
+        &self,
+        Parameters(FindBlockingInAsyncParams {
+            path,
+            patterns,
+            ignore,
+        }): Parameters<FindBlockingInAsyncParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        // `.rust-mcp.toml` patterns first, then the ones of this call.
+        let merge = |configured: &Option<Vec<String>>, given: Option<Vec<String>>| {
+            configured
+                .iter()
+                .flatten()
+                .cloned()
+                .chain(given.into_iter().flatten())
+                .collect::<Vec<_>>()
+        };
+        let args = serde_json::json!({
+            "path": path,
+            "patterns": merge(&self.blocking.patterns, patterns),
+            "ignore": merge(&self.blocking.ignore, ignore)
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_blocking_in_async", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No blocking calls found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List panic sites: panic!, unwrap, expect, todo!, indexing and the like")]
+    async fn find_panics(
+        &self,
+        Parameters(FindPanicsParams {
+            path,
+            categories,
+            include_tests,
+            limit,
+        }): Parameters<FindPanicsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "categories": categories,
+            "include_tests": include_tests,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_panics", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No panic sites found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Find format!/println! calls whose placeholders and arguments disagree")]
+    async fn check_format_args(
+        &self,
+        Parameters(CheckFormatArgsParams { path, limit }): Parameters<CheckFormatArgsParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path,
+            "limit": limit
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("check_format_args", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No format calls checked",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Locate a package's build script and list the cargo directives it emits")]
+    async fn describe_build_script(
+        &self,
+        Parameters(DescribeBuildScriptParams { manifest_path }): Parameters<
+            DescribeBuildScriptParams,
+        >,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "manifest_path": manifest_path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("describe_build_script", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No build script found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Get type hierarchy for a symbol at specified position")]
+    async fn get_type_hierarchy(
+        &self,
+        Parameters(GetTypeHierarchyParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<GetTypeHierarchyParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("get_type_hierarchy", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first() {
+                    if let Some(text) = content.get("text") {
+                        return Ok(CallToolResult::success(vec![Content::text(
+                            text.as_str().unwrap_or("No result"),
+                        )]));
+                    }
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Type hierarchy retrieved successfully",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "Check whether a type implements a specific trait")]
+    async fn check_trait_impl(
+        &self,
+        Parameters(CheckTraitImplParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+            trait_name,
+        }): Parameters<CheckTraitImplParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence,
+            "trait_name": trait_name
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("check_trait_impl", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "Trait implementation check completed",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
         }
-        context
     }
 
-    #[tool(description = "Find the definition of a symbol at a given position")]
-    async fn find_definition(
+    #[tool(description = "List a type's methods grouped into inherent and per-trait sets")]
+    async fn list_methods(
         &self,
-        Parameters(FindDefinitionParams {
+        Parameters(ListMethodsParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<FindDefinitionParams>,
+        }): Parameters<ListMethodsParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
@@ -169,17 +3085,17 @@ impl RustMcpServer {
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("find_definition", args, &mut analyzer).await {
+        match execute_tool("list_methods", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No definition found",
+                    "No methods found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -188,15 +3104,15 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Find all references to a symbol at a given position")]
-    async fn find_references(
+    #[tool(description = "Find the impl method that runs for a trait method call on a receiver")]
+    async fn find_method_impl(
         &self,
-        Parameters(FindReferencesParams {
+        Parameters(FindMethodImplParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<FindReferencesParams>,
+        }): Parameters<FindMethodImplParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
@@ -206,17 +3122,17 @@ impl RustMcpServer {
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("find_references", args, &mut analyzer).await {
+        match execute_tool("find_method_impl", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No references found",
+                    "No impl found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -225,27 +3141,27 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Get compiler diagnostics for a file")]
-    async fn get_diagnostics(
+    #[tool(description = "Map a crate's error types and the From conversions between them")]
+    async fn error_map(
         &self,
-        Parameters(GetDiagnosticsParams { file_path }): Parameters<GetDiagnosticsParams>,
+        Parameters(ErrorMapParams { path }): Parameters<ErrorMapParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
-            "file_path": file_path
+            "path": path
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("get_diagnostics", args, &mut analyzer).await {
+        match execute_tool("error_map", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No diagnostics found",
+                    "No error types found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -254,27 +3170,33 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Search for symbols in the workspace")]
-    async fn workspace_symbols(
+    #[tool(description = "Explain what a `?` operator propagates and the From conversion it uses")]
+    async fn trace_question_mark(
         &self,
-        Parameters(WorkspaceSymbolsParams { query }): Parameters<WorkspaceSymbolsParams>,
+        Parameters(TraceQuestionMarkParams {
+            file_path,
+            code_block,
+            occurrence,
+        }): Parameters<TraceQuestionMarkParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
-            "query": query
+            "file_path": file_path,
+            "code_block": code_block,
+            "occurrence": occurrence.unwrap_or(1)
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("workspace_symbols", args, &mut analyzer).await {
+        match execute_tool("trace_question_mark", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No symbols found",
+                    "No propagation found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -283,17 +3205,15 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(
-        description = "Get hover information (signature and documentation) for a symbol at a given position"
-    )]
-    async fn get_hover(
+    #[tool(description = "Find the sites that construct instances of a type")]
+    async fn find_constructions(
         &self,
-        Parameters(GetHoverParams {
+        Parameters(FindConstructionsParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<GetHoverParams>,
+        }): Parameters<FindConstructionsParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
@@ -303,17 +3223,17 @@ impl RustMcpServer {
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("get_hover", args, &mut analyzer).await {
+        match execute_tool("find_constructions", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No hover information found"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No hover information found",
+                    "No constructions found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -322,27 +3242,35 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Get the structure (symbols) of a file (outline)")]
-    async fn document_symbols(
+    #[tool(description = "List an enum's variants with their kinds and field types")]
+    async fn describe_enum(
         &self,
-        Parameters(GetDocumentSymbolsParams { file_path }): Parameters<GetDocumentSymbolsParams>,
+        Parameters(DescribeEnumParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<DescribeEnumParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
-            "file_path": file_path
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("document_symbols", args, &mut analyzer).await {
+        match execute_tool("describe_enum", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No symbols found",
+                    "No enum description available",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -351,35 +3279,37 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Get the source code of a specific symbol (function, struct, etc.)")]
-    async fn get_symbol_source(
+    #[tool(description = "Report the size, alignment and field offsets of a struct")]
+    async fn struct_layout(
         &self,
-        Parameters(GetSymbolSourceParams {
+        Parameters(StructLayoutParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<GetSymbolSourceParams>,
+            use_compiler,
+        }): Parameters<StructLayoutParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
             "symbol": symbol,
             "code_block": code_block,
-            "occurrence": occurrence
+            "occurrence": occurrence,
+            "use_compiler": use_compiler
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("get_symbol_source", args, &mut analyzer).await {
+        match execute_tool("struct_layout", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "No source found",
+                    "No layout available",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -388,37 +3318,64 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Rename a symbol with scope awareness")]
-    async fn rename_symbol(
+    #[tool(description = "Find structs and enums that contain themselves without Box/Rc/Arc")]
+    async fn find_recursive_types(
         &self,
-        Parameters(RenameSymbolParams {
+        Parameters(FindRecursiveTypesParams { path }): Parameters<FindRecursiveTypesParams>,
+    ) -> std::result::Result<CallToolResult, McpError> {
+        let args = serde_json::json!({
+            "path": path
+        });
+
+        let mut analyzer = self.analyzer.lock().await;
+        match execute_tool("find_recursive_types", args, &mut analyzer).await {
+            Ok(result) => {
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
+                }
+                Ok(CallToolResult::success(vec![Content::text(
+                    "No recursive types found",
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
+                "Error: {e}"
+            ))])),
+        }
+    }
+
+    #[tool(description = "List the generic parameters, bounds and where-clause of an item")]
+    async fn describe_generics(
+        &self,
+        Parameters(DescribeGenericsParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-            new_name,
-        }): Parameters<RenameSymbolParams>,
+        }): Parameters<DescribeGenericsParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
             "symbol": symbol,
             "code_block": code_block,
-            "occurrence": occurrence,
-            "new_name": new_name
+            "occurrence": occurrence
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("rename_symbol", args, &mut analyzer).await {
+        match execute_tool("describe_generics", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Rename operation completed",
+                    "No generics description available",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -427,27 +3384,35 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Execute cargo check and parse errors")]
-    async fn run_cargo_check(
+    #[tool(description = "List the lifetimes and generics in scope at a position, per impl and fn")]
+    async fn generics_in_scope(
         &self,
-        Parameters(RunCargoCheckParams { workspace_path }): Parameters<RunCargoCheckParams>,
+        Parameters(GenericsInScopeParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<GenericsInScopeParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
-            "workspace_path": workspace_path
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("run_cargo_check", args, &mut analyzer).await {
+        match execute_tool("generics_in_scope", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Cargo check completed",
+                    "No generics in scope found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -456,35 +3421,35 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Extract selected code into a new function")]
-    async fn extract_function(
+    #[tool(description = "Explain the bounds a type's derives impose on its type parameters")]
+    async fn derive_bounds(
         &self,
-        Parameters(ExtractFunctionParams {
+        Parameters(DeriveBoundsParams {
             file_path,
+            symbol,
             code_block,
             occurrence,
-            function_name,
-        }): Parameters<ExtractFunctionParams>,
+        }): Parameters<DeriveBoundsParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
+            "symbol": symbol,
             "code_block": code_block,
-            "occurrence": occurrence,
-            "function_name": function_name
+            "occurrence": occurrence
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("extract_function", args, &mut analyzer).await {
+        match execute_tool("derive_bounds", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Function extracted successfully",
+                    "No derive bounds available",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -493,35 +3458,37 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Inline a function call at specified position")]
-    async fn inline_function(
+    #[tool(description = "Report where a named lifetime appears in a function")]
+    async fn trace_lifetime(
         &self,
-        Parameters(InlineFunctionParams {
+        Parameters(TraceLifetimeParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<InlineFunctionParams>,
+            lifetime,
+        }): Parameters<TraceLifetimeParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
             "symbol": symbol,
             "code_block": code_block,
-            "occurrence": occurrence
+            "occurrence": occurrence,
+            "lifetime": lifetime
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("inline_function", args, &mut analyzer).await {
+        match execute_tool("trace_lifetime", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Function inlined successfully",
+                    "No lifetime uses found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -530,29 +3497,35 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Apply clippy lint suggestions to improve code quality")]
-    async fn apply_clippy_suggestions(
+    #[tool(description = "Describe a function's parameters, receiver, return type and qualifiers")]
+    async fn describe_function(
         &self,
-        Parameters(ApplyClippySuggestionsParams { file_path }): Parameters<
-            ApplyClippySuggestionsParams,
-        >,
+        Parameters(DescribeFunctionParams {
+            file_path,
+            symbol,
+            code_block,
+            occurrence,
+        }): Parameters<DescribeFunctionParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
-            "file_path": file_path
+            "file_path": file_path,
+            "symbol": symbol,
+            "code_block": code_block,
+            "occurrence": occurrence
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("apply_clippy_suggestions", args, &mut analyzer).await {
+        match execute_tool("describe_function", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Clippy suggestions applied successfully",
+                    "No function description available",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -561,15 +3534,15 @@ impl RustMcpServer {
         }
     }
 
-    #[tool(description = "Get type hierarchy for a symbol at specified position")]
-    async fn get_type_hierarchy(
+    #[tool(description = "Show a function's inline, cold, export and target_feature attributes")]
+    async fn optimization_attributes(
         &self,
-        Parameters(GetTypeHierarchyParams {
+        Parameters(OptimizationAttributesParams {
             file_path,
             symbol,
             code_block,
             occurrence,
-        }): Parameters<GetTypeHierarchyParams>,
+        }): Parameters<OptimizationAttributesParams>,
     ) -> std::result::Result<CallToolResult, McpError> {
         let args = serde_json::json!({
             "file_path": file_path,
@@ -579,17 +3552,17 @@ impl RustMcpServer {
         });
 
         let mut analyzer = self.analyzer.lock().await;
-        match execute_tool("get_type_hierarchy", args, &mut analyzer).await {
+        match execute_tool("optimization_attributes", args, &mut analyzer).await {
             Ok(result) => {
-                if let Some(content) = result.content.first() {
-                    if let Some(text) = content.get("text") {
-                        return Ok(CallToolResult::success(vec![Content::text(
-                            text.as_str().unwrap_or("No result"),
-                        )]));
-                    }
+                if let Some(content) = result.content.first()
+                    && let Some(text) = content.get("text")
+                {
+                    return Ok(CallToolResult::success(vec![Content::text(
+                        text.as_str().unwrap_or("No result"),
+                    )]));
                 }
                 Ok(CallToolResult::success(vec![Content::text(
-                    "Type hierarchy retrieved successfully",
+                    "No attributes found",
                 )]))
             }
             Err(e) => Ok(CallToolResult::success(vec![Content::text(format!(
@@ -995,6 +3968,7 @@ impl RustMcpServer {
             manifest_path: None,
             package: None,
             target_triple: target,
+            cargo_args: Vec::new(),
             opt_level,
             emit: emit.map(|emit| emit.to_string()),
             unpretty: unpretty.map(|unpretty| unpretty.to_string()),
@@ -1033,6 +4007,30 @@ impl RustMcpServer {
     }
 }
 
+/// Forwards cargo progress updates to the client as `notifications/progress`
+/// until the returned sender is dropped.
+fn spawn_progress_forwarder(
+    peer: Peer<RoleServer>,
+    progress_token: ProgressToken,
+) -> CargoProgressSender {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<CargoProgress>();
+
+    tokio::spawn(async move {
+        while let Some(update) = receiver.recv().await {
+            let _ = peer
+                .notify_progress(ProgressNotificationParam {
+                    progress_token: progress_token.clone(),
+                    progress: update.completed as f64,
+                    total: None,
+                    message: Some(update.message),
+                })
+                .await;
+        }
+    });
+
+    sender
+}
+
 fn truncation_note(summary: &TruncationSummary) -> String {
     format!(
         "Output truncated to {} lines/{} bytes from {} lines/{} bytes",
@@ -7,6 +7,20 @@ pub struct FindDefinitionParams {
     pub symbol: String,
     pub code_block: String,
     pub occurrence: Option<u32>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+pub struct DefinitionQuery {
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindDefinitionsBatchParams {
+    pub file_path: String,
+    pub queries: Vec<DefinitionQuery>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -15,6 +29,16 @@ pub struct FindReferencesParams {
     pub symbol: String,
     pub code_block: String,
     pub occurrence: Option<u32>,
+    pub scope: Option<String>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindCallersParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -23,6 +47,48 @@ pub struct GetHoverParams {
     pub symbol: String,
     pub code_block: String,
     pub occurrence: Option<u32>,
+    pub expand_aliases: Option<bool>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct InferTypeParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TraceChainParams {
+    pub file_path: String,
+    pub expression: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TypesEqualParams {
+    pub file_path: String,
+    pub left: String,
+    pub right: String,
+    pub right_file_path: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindConstructionsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EnclosingBlocksParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -30,9 +96,27 @@ pub struct GetDiagnosticsParams {
     pub file_path: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiagnosticSnippetParams {
+    pub file_path: String,
+    pub line: u32,
+    pub message: Option<String>,
+    pub max_depth: Option<u32>,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetDocumentSymbolsParams {
     pub file_path: String,
+    pub start_line: Option<u32>,
+    pub end_line: Option<u32>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TokenAtParams {
+    pub file_path: String,
+    pub line: u32,
+    pub character: u32,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -41,11 +125,150 @@ pub struct GetSymbolSourceParams {
     pub symbol: String,
     pub code_block: String,
     pub occurrence: Option<u32>,
+    pub include_impl_context: Option<bool>,
+    pub max_lines: Option<u32>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SymbolDiffParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub from_ref: String,
+    pub to_ref: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetExternalDocsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetExternalSourceParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub max_lines: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetDocsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckDocExampleParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub run: Option<bool>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct WorkspaceSymbolsParams {
     pub query: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub analysis: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindBySignatureParams {
+    pub query: String,
+    pub is_async: Option<bool>,
+    pub returns: Option<String>,
+    pub takes: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListCfgRegionsParams {
+    pub file_path: String,
+    pub target: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FeatureMapParams {
+    pub manifest_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RequiredFeaturesParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListTestOnlyItemsParams {
+    pub path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ShowCfgParams {
+    pub target: Option<String>,
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListMacrosParams {
+    pub crate_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GotoMacroDefinitionParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub max_lines: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExpandDeriveParams {
+    pub file_path: String,
+    pub code_block: String,
+    pub trait_name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GotoParentModuleParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListReexportsParams {
+    pub file_path: String,
+    pub include_restricted: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct IsFileInModuleTreeParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindModuleImportersParams {
+    /// Module path such as `crate::tools::navigation`.
+    pub module: String,
+    /// File or directory to search; defaults to the project root.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FileInterfaceParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ResolvePathParams {
+    pub path: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -55,11 +278,83 @@ pub struct RenameSymbolParams {
     pub code_block: String,
     pub occurrence: Option<u32>,
     pub new_name: String,
+    pub scope: Option<String>,
+    pub verify: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RenameApiItemParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub new_name: String,
+    pub verify: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateModuleParams {
+    pub parent_file: String,
+    pub name: String,
+    pub visibility: Option<String>,
+    pub mod_rs: Option<bool>,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SuggestModuleSplitParams {
+    pub file_path: String,
+    pub min_lines: Option<u32>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct RunCargoCheckParams {
     pub workspace_path: String,
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiagnosticHistogramParams {
+    pub workspace_path: String,
+    pub file_path: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindUnnecessaryClonesParams {
+    pub workspace_path: String,
+    pub file_path: Option<String>,
+    pub apply: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListRunnablesParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListTestsParams {
+    pub path: String,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckMsrvParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindManifestParams {
+    pub file_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PreviewFeaturesParams {
+    pub workspace_path: String,
+    pub features: Option<Vec<String>>,
+    pub no_default_features: Option<bool>,
+    pub all_features: Option<bool>,
+    pub file_path: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -83,6 +378,68 @@ pub struct ApplyClippySuggestionsParams {
     pub file_path: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct UnusedDependenciesParams {
+    pub manifest_path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ComplexityReportParams {
+    pub path: String,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindLargeFunctionsParams {
+    pub path: String,
+    pub threshold: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct MustUseCandidatesParams {
+    pub path: String,
+    pub include_private: Option<bool>,
+    pub apply: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindDynUsagesParams {
+    pub path: String,
+    pub include_impl: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AsyncMapParams {
+    pub path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindBlockingInAsyncParams {
+    pub path: String,
+    pub patterns: Option<Vec<String>>,
+    pub ignore: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindPanicsParams {
+    pub path: String,
+    pub categories: Option<Vec<String>>,
+    pub include_tests: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckFormatArgsParams {
+    pub path: String,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeBuildScriptParams {
+    pub manifest_path: String,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetTypeHierarchyParams {
     pub file_path: String,
@@ -91,6 +448,117 @@ pub struct GetTypeHierarchyParams {
     pub occurrence: Option<u32>,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ErrorMapParams {
+    pub path: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TraceQuestionMarkParams {
+    pub file_path: String,
+    /// A snippet containing the `?` operator.
+    pub code_block: String,
+    /// The 1-based index of the `?` within the code_block.
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckTraitImplParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub trait_name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListMethodsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindMethodImplParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeEnumParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeFunctionParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct OptimizationAttributesParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeGenericsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenericsInScopeParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeriveBoundsParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TraceLifetimeParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub lifetime: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct StructLayoutParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    /// Build with `-Zprint-type-sizes` on nightly toolchains (default true).
+    pub use_compiler: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FindRecursiveTypesParams {
+    pub path: String,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct InspectMirParams {
     pub file_path: String,
@@ -133,7 +601,192 @@ pub struct InspectParams {
     pub gating_mode: Option<String>,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct InspectDiffParams {
+    pub view: String,
+    pub file_path: String,
+    pub line: u32,
+    pub character: u32,
+    pub symbol_name: Option<String>,
+    pub from_opt_level: Option<String>,
+    pub to_opt_level: Option<String>,
+    pub target: Option<String>,
+    pub gating_mode: Option<String>,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct CapabilitiesParams {
     pub gating_mode: Option<String>,
 }
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ScaffoldTestParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SuggestImportParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct MethodTraitParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SelfTypeParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddImportParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub path: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenerateConversionParams {
+    /// Path of the struct converted from, e.g. `dto::UserDto`.
+    pub source_type: String,
+    /// Path of the struct converted into, e.g. `model::User`.
+    pub target_type: String,
+    /// `From` (default) or `TryFrom`.
+    pub kind: Option<String>,
+    /// `TryFrom::Error`; defaults to `Box<dyn std::error::Error + Send + Sync>`.
+    pub error_type: Option<String>,
+    /// Insert the impl after the target struct instead of only returning it.
+    pub insert: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenerateDefaultParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    /// Insert the impl after the struct instead of only returning it.
+    pub insert: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenerateBuilderParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    /// `owned` (`fn x(mut self, ..) -> Self`, the default) or `mut_ref`
+    /// (`fn x(&mut self, ..) -> &mut Self`).
+    pub setters: Option<String>,
+    /// Fields build() fails without; defaults to every non-`Option` field.
+    pub required: Option<Vec<String>>,
+    /// Insert the builder after the struct instead of only returning it.
+    pub insert: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExtractTraitParams {
+    pub file_path: String,
+    /// A snippet containing the inherent impl's `impl` keyword.
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub trait_name: String,
+    /// Methods, associated consts and types to move into the trait.
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FillMatchArmsParams {
+    pub file_path: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct MatchCoverageParams {
+    pub file_path: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct EditPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct EditRange {
+    pub start: EditPosition,
+    pub end: EditPosition,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyEditParams {
+    pub file_path: String,
+    pub new_text: String,
+    pub range: Option<EditRange>,
+    pub code_block: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct PatchEdit {
+    pub file_path: String,
+    pub new_text: String,
+    pub range: Option<EditRange>,
+    pub code_block: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DryRunPatchParams {
+    pub diff: Option<String>,
+    pub edits: Option<Vec<PatchEdit>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DiagnosticsDeltaParams {
+    pub file_path: String,
+    pub new_text: String,
+    pub range: Option<EditRange>,
+    pub code_block: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckFunctionParams {
+    pub file_path: String,
+    pub symbol: String,
+    pub code_block: String,
+    pub occurrence: Option<u32>,
+    pub new_body: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FormatRangeParams {
+    pub file_path: String,
+    pub range: Option<EditRange>,
+    pub code_block: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FormatWithConfigParams {
+    pub file_path: String,
+    pub edition: Option<String>,
+    pub config: Option<serde_json::Map<String, serde_json::Value>>,
+    pub use_project_config: Option<bool>,
+    pub write: Option<bool>,
+}
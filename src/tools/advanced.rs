@@ -1,10 +1,29 @@
 use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::protocol::{DocumentSymbol, DocumentSymbolResponse, Location, Position};
+use crate::compiler::layout::{TypeLayout, is_layout_of, parse_type_sizes};
+use crate::compiler::{CompilerRunner, RunRequest};
+use crate::inspection::{DEFAULT_TARGET_DIR, InspectionLimits, detect_toolchain_channel};
 use crate::tools::types::ToolResult;
 use anyhow::Result;
+use serde::Serialize;
 use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::analyzer::symbol::{crate_name_from_uri, module_path_from_uri};
+use crate::tools::analysis::{
+    DocsOrigin, find_symbol_location, index_to_line_col, is_valid_code_context, line_start_offset,
+    utf16_column_offset,
+};
+use crate::tools::cargo::owning_manifest;
+use crate::tools::navigation::{collect_rust_files, tokenize};
+use crate::tools::quality::declared_dependencies;
+use crate::tools::refactoring::{
+    HoverSignature, PRELUDE_TRAITS, derives, find_matching_paren, parse_hover_signature,
+    resolve_impl_trait, split_top_level, trait_import_path,
+};
 use tokio::fs;
-use crate::tools::analysis::find_symbol_location;
 
 pub async fn get_type_hierarchy_impl(
     args: Value,
@@ -49,3 +68,5138 @@ pub async fn get_type_hierarchy_impl(
         ],
     })
 }
+
+pub async fn check_trait_impl_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+    let trait_name = args
+        .get("trait_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing trait_name parameter"))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let locations = analyzer
+        .find_implementations(file_path, line, character)
+        .await?;
+
+    let wanted = last_path_segment(trait_name);
+    let mut impls = Vec::new();
+    let mut negative = false;
+
+    for location in locations {
+        let impl_path = location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+            .to_string();
+        let Ok(content) = fs::read_to_string(&impl_path).await else {
+            continue;
+        };
+        let Some(header) = read_impl_header(&content, location.range.start.line as usize) else {
+            continue;
+        };
+
+        let matched = match parse_impl_header(&header) {
+            Some(ImplHeader::Derive(traits)) => traits
+                .iter()
+                .any(|name| last_path_segment(name) == wanted)
+                .then_some((false, true, false)),
+            Some(ImplHeader::Trait {
+                trait_path,
+                generic,
+                negative,
+                ..
+            }) => (last_path_segment(&trait_path) == wanted).then_some((generic, false, negative)),
+            None => None,
+        };
+
+        if let Some((generic, derived, is_negative)) = matched {
+            negative |= is_negative;
+            impls.push(json!({
+                "file_path": impl_path,
+                "line": location.range.start.line + 1,
+                "header": header,
+                "generic": generic,
+                "derived": derived,
+                "negative": is_negative
+            }));
+        }
+    }
+
+    let implemented = !negative && !impls.is_empty();
+    let mut notes = vec![
+        "Blanket impls (e.g. `impl<T: Display> ToString for T`) are not reported by rust-analyzer's implementation search, so `false` does not rule them out.".to_string(),
+    ];
+    if impls.iter().any(|item| item["generic"] == json!(true)) {
+        notes.push(
+            "Some matching impls are generic; they only apply when their bounds are satisfied."
+                .to_string(),
+        );
+    }
+    if negative {
+        notes.push(format!("`{symbol}` explicitly opts out of `{trait_name}`."));
+    }
+
+    let result = json!({
+        "type": symbol,
+        "trait": trait_name,
+        "implemented": implemented,
+        "impls": impls,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn list_methods_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let locations = analyzer
+        .find_implementations(file_path, line, character)
+        .await?;
+
+    let mut inherent = Vec::new();
+    let mut traits = Vec::new();
+    let mut derived_seen = Vec::new();
+    let mut unknown_derives = Vec::new();
+
+    for location in locations {
+        let impl_path = location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+            .to_string();
+        let Ok(content) = fs::read_to_string(&impl_path).await else {
+            continue;
+        };
+        let header_line = location.range.start.line as usize;
+        let Some(header) = read_impl_header(&content, header_line) else {
+            continue;
+        };
+
+        match parse_impl_header(&header) {
+            Some(ImplHeader::Derive(names)) => {
+                for name in names {
+                    let name = last_path_segment(&name).to_string();
+                    if derived_seen.contains(&name) {
+                        continue;
+                    }
+                    derived_seen.push(name.clone());
+                    let Some((path, signatures)) = derived_methods(&name) else {
+                        unknown_derives.push(name);
+                        continue;
+                    };
+                    let mut methods: Vec<Value> = signatures
+                        .iter()
+                        .map(|signature| {
+                            json!({
+                                "name": signature_name(signature),
+                                "signature": signature,
+                                "file_path": impl_path,
+                                "line": header_line + 1
+                            })
+                        })
+                        .collect();
+                    sort_methods(&mut methods);
+                    traits.push(json!({
+                        "trait": name,
+                        "path": path,
+                        "in_prelude": PRELUDE_TRAITS.contains(&name.as_str()),
+                        "derived": true,
+                        "header": header,
+                        "file_path": impl_path,
+                        "line": header_line + 1,
+                        "methods": methods
+                    }));
+                }
+            }
+            Some(ImplHeader::Trait {
+                trait_path,
+                negative: false,
+                ..
+            }) => {
+                let methods = impl_methods(analyzer, &impl_path, &content, header_line).await;
+                let name = last_path_segment(&trait_path).to_string();
+                let resolved =
+                    resolve_impl_trait(analyzer, &impl_path, header_line as u32, &name).await;
+                let (path, in_prelude) = match resolved {
+                    Some((trait_file, inline_modules, name)) => {
+                        let origin = DocsOrigin::of_path(&trait_file);
+                        let mut modules = module_path_from_uri(&trait_file);
+                        modules.extend(inline_modules);
+                        let path = trait_import_path(
+                            &origin,
+                            crate_name_from_uri(&trait_file).as_deref(),
+                            crate_name_from_uri(file_path).as_deref(),
+                            &modules,
+                            &name,
+                        );
+                        let in_prelude = matches!(origin, DocsOrigin::Std(_))
+                            && PRELUDE_TRAITS.contains(&name.as_str());
+                        (path, in_prelude)
+                    }
+                    None => (trait_path.clone(), false),
+                };
+                traits.push(json!({
+                    "trait": trait_path,
+                    "path": path,
+                    "in_prelude": in_prelude,
+                    "derived": false,
+                    "header": header,
+                    "file_path": impl_path,
+                    "line": header_line + 1,
+                    "methods": methods
+                }));
+            }
+            // `impl !Send for T` has no methods.
+            Some(ImplHeader::Trait { .. }) => {}
+            None => {
+                inherent.extend(impl_methods(analyzer, &impl_path, &content, header_line).await);
+            }
+        }
+    }
+
+    sort_methods(&mut inherent);
+    traits.sort_by(|a, b| {
+        let key = |group: &Value| group["trait"].as_str().unwrap_or_default().to_lowercase();
+        key(a).cmp(&key(b))
+    });
+
+    let method_count = inherent.len()
+        + traits
+            .iter()
+            .map(|group| group["methods"].as_array().map_or(0, Vec::len))
+            .sum::<usize>();
+    let mut notes = vec![
+        "Only methods written in the impl blocks are listed: provided (default) trait methods the impl does not override and blanket impls such as `impl<T: Display> ToString for T` are not.".to_string(),
+    ];
+    if !unknown_derives.is_empty() {
+        notes.push(format!(
+            "Methods generated by {} are not listed; expand_derive shows them.",
+            unknown_derives
+                .iter()
+                .map(|name| format!("`#[derive({name})]`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let result = json!({
+        "type": symbol,
+        "method_count": method_count,
+        "inherent": inherent,
+        "traits": traits,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The methods of the impl block whose header starts at `header_line`, each
+/// with its signature from hover (or, failing that, from the source).
+async fn impl_methods(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    content: &str,
+    header_line: usize,
+) -> Vec<Value> {
+    let mut methods = Vec::new();
+    for (name, offset) in impl_method_names(content, header_line) {
+        let (line, character) = index_to_line_col(content, offset);
+        let signature = match analyzer.get_hover(file_path, line, character).await {
+            Ok(hover) => parse_hover_signature(&hover).map(|signature| signature.signature),
+            Err(_) => None,
+        };
+        let signature = signature.unwrap_or_else(|| {
+            let header = &content[content[..offset].rfind('\n').map_or(0, |idx| idx + 1)..];
+            let header = header.split(['{', ';']).next().unwrap_or(header);
+            header.split_whitespace().collect::<Vec<_>>().join(" ")
+        });
+        methods.push(json!({
+            "name": name,
+            "signature": signature,
+            "file_path": file_path,
+            "line": line + 1
+        }));
+    }
+    methods
+}
+
+/// Names and byte offsets of the functions declared directly in the body of
+/// the impl block whose header starts at `header_line`.
+fn impl_method_names(content: &str, header_line: usize) -> Vec<(String, usize)> {
+    let start: usize = content
+        .split_inclusive('\n')
+        .take(header_line)
+        .map(str::len)
+        .sum();
+    let mut names = Vec::new();
+    // Depth 0 is the header, 1 the impl body.
+    let mut depth = 0;
+    let mut after_fn = false;
+    for token in tokenize(&content[start..]) {
+        let text = &content[start + token.start..start + token.end];
+        match (token.kind, text) {
+            ("whitespace" | "comment" | "doc_comment", _) => continue,
+            ("punctuation", "{") => depth += 1,
+            ("punctuation", "}") => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            ("identifier", name) if after_fn && depth == 1 => {
+                names.push((name.to_string(), start + token.start));
+            }
+            _ => {}
+        }
+        after_fn = token.kind == "keyword" && text == "fn";
+    }
+    names
+}
+
+/// The `std` path and method signatures of a derivable std trait.
+fn derived_methods(name: &str) -> Option<(&'static str, &'static [&'static str])> {
+    Some(match name {
+        "Clone" => ("std::clone::Clone", &["fn clone(&self) -> Self"]),
+        "Copy" => ("std::marker::Copy", &[]),
+        "Debug" => (
+            "std::fmt::Debug",
+            &["fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result"],
+        ),
+        "Default" => ("std::default::Default", &["fn default() -> Self"]),
+        "PartialEq" => (
+            "std::cmp::PartialEq",
+            &["fn eq(&self, other: &Self) -> bool"],
+        ),
+        "Eq" => ("std::cmp::Eq", &[]),
+        "PartialOrd" => (
+            "std::cmp::PartialOrd",
+            &["fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering>"],
+        ),
+        "Ord" => (
+            "std::cmp::Ord",
+            &["fn cmp(&self, other: &Self) -> std::cmp::Ordering"],
+        ),
+        "Hash" => (
+            "std::hash::Hash",
+            &["fn hash<H: std::hash::Hasher>(&self, state: &mut H)"],
+        ),
+        _ => return None,
+    })
+}
+
+/// `clone` for `fn clone(&self) -> Self`.
+fn signature_name(signature: &str) -> &str {
+    let name = signature.strip_prefix("fn ").unwrap_or(signature);
+    name.split(['<', '(']).next().unwrap_or(name)
+}
+
+fn sort_methods(methods: &mut [Value]) {
+    methods.sort_by(|a, b| {
+        let key = |method: &Value| method["name"].as_str().unwrap_or_default().to_string();
+        key(a).cmp(&key(b))
+    });
+}
+
+pub async fn find_method_impl_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let mut notes = Vec::new();
+
+    let definition = analyzer
+        .definition_locations(file_path, line, character)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No definition found for `{}`", symbol))?;
+    let (definition_path, definition_header) = located_item_header(&definition).await;
+
+    // rust-analyzer already resolves calls on concrete types to the impl.
+    if let Some(ImplHeader::Trait { self_type, .. }) = definition_header
+        .as_ref()
+        .and_then(|(_, header)| parse_impl_header(header))
+    {
+        let result = json!({
+            "method": symbol,
+            "receiver_type": last_path_segment(&self_type),
+            "resolved_by": "definition",
+            "impl": method_location_json(&definition_path, &definition, definition_header),
+            "candidates": [],
+            "notes": notes
+        });
+        return Ok(ToolResult {
+            content: vec![
+                json!({
+                    "type": "text",
+                    "text": serde_json::to_string_pretty(&result)?
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ],
+        });
+    }
+
+    let receiver_type = match receiver_anchor(&file_content, line, character) {
+        Some((receiver_line, receiver_character)) => {
+            let locations = analyzer
+                .type_definition_locations(file_path, receiver_line, receiver_character)
+                .await?;
+            match locations.first() {
+                Some(location) => type_name_at(location).await,
+                None => None,
+            }
+        }
+        None => {
+            notes.push(format!(
+                "`{symbol}` is not called as `receiver.{symbol}()` on a simple receiver; listing every impl."
+            ));
+            None
+        }
+    };
+
+    let implementations = analyzer
+        .find_implementations(
+            &definition_path,
+            definition.range.start.line,
+            definition.range.start.character,
+        )
+        .await?;
+
+    let mut matches = Vec::new();
+    let mut blanket_impls = Vec::new();
+    let mut candidates = Vec::new();
+    for location in implementations {
+        let (impl_path, header) = located_item_header(&location).await;
+        let Some(ImplHeader::Trait {
+            self_type, blanket, ..
+        }) = header
+            .as_ref()
+            .and_then(|(_, header)| parse_impl_header(header))
+        else {
+            continue;
+        };
+        let entry = method_location_json(&impl_path, &location, header);
+        let self_name =
+            last_path_segment(self_type.trim_start_matches('&').trim_start_matches("mut "));
+        if blanket {
+            blanket_impls.push(entry.clone());
+        } else if receiver_type.as_deref() == Some(self_name) {
+            matches.push(entry.clone());
+        }
+        candidates.push(entry);
+    }
+
+    let chosen = if matches.len() == 1 {
+        matches.pop()
+    } else if matches.is_empty() && receiver_type.is_some() && blanket_impls.len() == 1 {
+        notes.push(
+            "Only a blanket impl can apply; check that the receiver meets its bounds.".to_string(),
+        );
+        blanket_impls.pop()
+    } else if receiver_type.is_none() && candidates.len() == 1 {
+        candidates.first().cloned()
+    } else {
+        None
+    };
+    if chosen.is_none() {
+        match &receiver_type {
+            Some(name) if matches.is_empty() => notes.push(format!(
+                "No impl for `{name}` was found; the call may use the trait's default body, a blanket impl or a `dyn` receiver."
+            )),
+            Some(name) => notes.push(format!(
+                "Several impls match `{name}` (different generic arguments); see candidates."
+            )),
+            None => notes.push("The receiver's type could not be resolved; see candidates.".to_string()),
+        }
+    }
+
+    let result = json!({
+        "method": symbol,
+        "receiver_type": receiver_type,
+        "resolved_by": "implementation",
+        "trait_method": method_location_json(&definition_path, &definition, definition_header),
+        "impl": chosen,
+        "candidates": candidates,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// File path of `location` and the header of the `impl` or `trait` block
+/// around it, with the header's 0-based line.
+async fn located_item_header(location: &Location) -> (String, Option<(usize, String)>) {
+    let path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri)
+        .to_string();
+    let header = match fs::read_to_string(&path).await {
+        Ok(content) => enclosing_item_header(&content, location.range.start.line as usize),
+        Err(_) => None,
+    };
+    (path, header)
+}
+
+fn method_location_json(path: &str, location: &Location, header: Option<(usize, String)>) -> Value {
+    json!({
+        "file_path": path,
+        "line": location.range.start.line + 1,
+        "column": location.range.start.character + 1,
+        "block": header.map(|(_, header)| header)
+    })
+}
+
+/// Header of the closest `impl` or `trait` block starting at or above `line`.
+fn enclosing_item_header(content: &str, line: usize) -> Option<(usize, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    (0..=line.min(lines.len().checked_sub(1)?))
+        .rev()
+        .find(|&idx| {
+            let text = strip_visibility(lines[idx]);
+            let text = text.strip_prefix("unsafe ").unwrap_or(text);
+            text.starts_with("impl<") || text.starts_with("impl ") || text.starts_with("trait ")
+        })
+        .and_then(|idx| Some((idx, read_impl_header(content, idx)?)))
+}
+
+/// Position of the identifier whose type is the receiver of the method call
+/// at `line`/`character`: `x` in `x.foo()`, `items` in `self.items.foo()`,
+/// `iter` in `v.iter().foo()` (whose type is that of the call). `None` when
+/// the method is not called with `.` or the receiver ends in an index or `?`.
+fn receiver_anchor(content: &str, line: u32, character: u32) -> Option<(u32, u32)> {
+    let line_start = line_start_offset(content, line as usize)?;
+    let offset = line_start + utf16_column_offset(&content[line_start..], character);
+
+    let before = content[..offset].trim_end().strip_suffix('.')?.trim_end();
+    let before = match before.strip_suffix(')') {
+        Some(call) => {
+            let mut depth = 1;
+            let open = call.char_indices().rev().find_map(|(idx, c)| {
+                match c {
+                    ')' => depth += 1,
+                    '(' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(idx);
+                        }
+                    }
+                    _ => {}
+                }
+                None
+            })?;
+            let callee = call[..open].trim_end();
+            // Turbofish: `collect::<Vec<_>>()`.
+            match callee.strip_suffix('>') {
+                Some(_) => &callee[..callee.rfind("::<")?],
+                None => callee,
+            }
+        }
+        None => before,
+    };
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()?
+        .0;
+    if before[start..].starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let prefix = &content[..start];
+    let anchor_line = prefix.matches('\n').count() as u32;
+    let line_begin = prefix.rfind('\n').map_or(0, |idx| idx + 1);
+    Some((
+        anchor_line,
+        prefix[line_begin..].encode_utf16().count() as u32,
+    ))
+}
+
+/// Name of the type declared at `location` (the target of a type definition).
+async fn type_name_at(location: &Location) -> Option<String> {
+    let path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri);
+    let content = fs::read_to_string(path).await.ok()?;
+    let text = content.lines().nth(location.range.start.line as usize)?;
+    let start = utf16_column_offset(text, location.range.start.character);
+    let name: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+pub async fn error_map_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+
+    let root = Path::new(path);
+    let files = collect_rust_files(root).await?;
+    let mut errors: Vec<ErrorType> = Vec::new();
+    let mut conversions: Vec<(String, ErrorConversion)> = Vec::new();
+    // The first `impl Error for` header, where the trait can be queried.
+    let mut trait_anchor: Option<(String, u32, u32)> = None;
+    let mut anyhow_files = 0;
+    let mut boxed_files = 0;
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let file_path = file.to_string_lossy().to_string();
+        anyhow_files += usize::from(content.contains("anyhow::") || content.contains("anyhow!"));
+        boxed_files += usize::from(
+            content.contains("Box<dyn Error") || content.contains("Box<dyn std::error::Error"),
+        );
+        for item in scan_error_items(&content) {
+            match item {
+                ErrorItem::Type {
+                    name,
+                    line,
+                    defined_by,
+                    anchor,
+                } => {
+                    if let (None, Some(character)) = (&trait_anchor, anchor) {
+                        trait_anchor = Some((file_path.clone(), line, character));
+                    }
+                    errors.push(ErrorType {
+                        name,
+                        file_path: file_path.clone(),
+                        line: line + 1,
+                        defined_by,
+                        converts_from: Vec::new(),
+                    });
+                }
+                ErrorItem::From { target, conversion } => conversions.push((
+                    target,
+                    ErrorConversion {
+                        file_path: file_path.clone(),
+                        ..conversion
+                    },
+                )),
+            }
+        }
+    }
+
+    // Implementations of the trait itself catch what the scan cannot tell
+    // apart, such as an `Error` imported under another name.
+    let mut resolved_by = "scan";
+    if let Some((anchor_path, line, character)) = &trait_anchor
+        && let Ok(locations) = analyzer
+            .find_implementations(anchor_path, *line, *character)
+            .await
+        && !locations.is_empty()
+    {
+        resolved_by = "rust-analyzer";
+        for location in locations {
+            let impl_path = location
+                .uri
+                .strip_prefix("file://")
+                .unwrap_or(&location.uri)
+                .to_string();
+            if !Path::new(&impl_path).starts_with(root) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&impl_path).await else {
+                continue;
+            };
+            let Some(name) = implementing_type(&content, location.range.start.line as usize) else {
+                continue;
+            };
+            if !errors.iter().any(|error| error.name == name) {
+                errors.push(ErrorType {
+                    name,
+                    file_path: impl_path,
+                    line: location.range.start.line + 1,
+                    defined_by: "impl",
+                    converts_from: Vec::new(),
+                });
+            }
+        }
+    }
+
+    let names: Vec<String> = errors.iter().map(|error| error.name.clone()).collect();
+    for (target, mut conversion) in conversions {
+        conversion.local_error = names.iter().any(|name| *name == conversion.source_name());
+        if let Some(error) = errors.iter_mut().find(|error| error.name == target) {
+            error.converts_from.push(conversion);
+        }
+    }
+    errors.sort_by(|a, b| a.name.cmp(&b.name));
+    let converts_into = |name: &str| -> Vec<&str> {
+        errors
+            .iter()
+            .filter(|error| {
+                error
+                    .converts_from
+                    .iter()
+                    .any(|conversion| conversion.source_name() == name)
+            })
+            .map(|error| error.name.as_str())
+            .collect()
+    };
+    let entries: Vec<Value> = errors
+        .iter()
+        .map(|error| {
+            json!({
+                "name": error.name,
+                "file_path": error.file_path,
+                "line": error.line,
+                "defined_by": error.defined_by,
+                "converts_from": error.converts_from,
+                "converts_into": converts_into(&error.name)
+            })
+        })
+        .collect();
+
+    let dependencies = match root
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+    {
+        Some(manifest_path) => fs::read_to_string(&manifest_path)
+            .await
+            .ok()
+            .and_then(|text| toml::from_str::<toml::Table>(&text).ok())
+            .map(|manifest| declared_dependencies(&manifest))
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let depends_on = |name: &str| dependencies.iter().any(|dep| dep.name == name);
+    let thiserror =
+        depends_on("thiserror") || errors.iter().any(|error| error.defined_by == "thiserror");
+    let anyhow = depends_on("anyhow") || anyhow_files > 0;
+
+    let mut notes = Vec::new();
+    if thiserror {
+        notes.push(
+            "thiserror: `#[derive(Error)]` also implements Display from `#[error(..)]`, and each `#[from]` field generates the From impl listed under converts_from.".to_string(),
+        );
+    }
+    if anyhow {
+        notes.push(format!(
+            "anyhow ({} file(s)): `?` converts any error that is `std::error::Error + Send + Sync + 'static` into anyhow::Error without a From impl.",
+            anyhow_files
+        ));
+    }
+    if boxed_files > 0 {
+        notes.push(format!(
+            "Box<dyn Error> ({} file(s)): `?` boxes any error type into it.",
+            boxed_files
+        ));
+    }
+    if resolved_by == "scan" {
+        notes.push(
+            "Error types found by scanning for `impl Error for` and `#[derive(Error)]`; an aliased trait import may be missed.".to_string(),
+        );
+    }
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "resolved_by": resolved_by,
+        "uses_thiserror": thiserror,
+        "uses_anyhow": anyhow,
+        "count": entries.len(),
+        "errors": entries,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+struct ErrorType {
+    name: String,
+    file_path: String,
+    line: u32,
+    /// `impl`, `thiserror` or `derive` (another crate's `Error` derive).
+    defined_by: &'static str,
+    converts_from: Vec<ErrorConversion>,
+}
+
+/// A `From` conversion into an error type, which `?` applies.
+#[derive(Debug, PartialEq, Serialize)]
+struct ErrorConversion {
+    from: String,
+    /// `impl From` or `#[from]`.
+    via: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variant: Option<String>,
+    /// Whether `from` is itself one of the crate's error types.
+    local_error: bool,
+    file_path: String,
+    line: u32,
+}
+
+impl ErrorConversion {
+    fn source_name(&self) -> &str {
+        last_path_segment(&self.from)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ErrorItem {
+    Type {
+        name: String,
+        /// 0-based.
+        line: u32,
+        defined_by: &'static str,
+        /// The UTF-16 column of `Error` in an `impl Error for` header.
+        anchor: Option<u32>,
+    },
+    From {
+        target: String,
+        conversion: ErrorConversion,
+    },
+}
+
+/// Finds error types (`impl Error for T` and `#[derive(Error)]`) and the
+/// `From` conversions into types (`impl From<S> for T` and `#[from]` fields
+/// of derived errors) in `content`. Lines are 0-based, file paths left empty.
+fn scan_error_items(content: &str) -> Vec<ErrorItem> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut items = Vec::new();
+    let mut pending_derive: Option<&'static str> = None;
+    // The derived error whose body is being read, with the brace depth
+    // outside it.
+    let mut derived_body: Option<(String, i32)> = None;
+    let mut depth = 0i32;
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("//") {
+            continue;
+        }
+
+        if let Some(list) = trimmed.strip_prefix("#[derive(") {
+            let derives = list.trim_end_matches(']').trim_end_matches(')');
+            if let Some(derive) = derives
+                .split(',')
+                .map(str::trim)
+                .find(|derive| last_path_segment(derive) == "Error")
+            {
+                let thiserror = derive.starts_with("thiserror::") || content.contains("thiserror");
+                pending_derive = Some(if thiserror { "thiserror" } else { "derive" });
+            }
+        } else if trimmed.starts_with("#[") {
+            // Other attributes between the derive and the item.
+        } else if let Some(defined_by) = pending_derive.take() {
+            let item = strip_visibility(trimmed);
+            let name = ["struct ", "enum "]
+                .iter()
+                .find_map(|keyword| item.strip_prefix(keyword))
+                .map(|rest| {
+                    rest.chars()
+                        .take_while(|c| c.is_alphanumeric() || *c == '_')
+                        .collect::<String>()
+                })
+                .filter(|name| !name.is_empty());
+            if let Some(name) = name {
+                items.push(ErrorItem::Type {
+                    name: name.clone(),
+                    line: idx as u32,
+                    defined_by,
+                    anchor: None,
+                });
+                derived_body = Some((name, depth));
+            }
+        } else if let Some(header) = (strip_visibility(trimmed).starts_with("impl")
+            || trimmed.starts_with("unsafe impl"))
+        .then(|| read_impl_header(content, idx))
+        .flatten()
+            && let Some(ImplHeader::Trait {
+                trait_path,
+                self_type,
+                negative: false,
+                ..
+            }) = parse_impl_header(&header)
+        {
+            let target = last_path_segment(&self_type).to_string();
+            match last_path_segment(&trait_path) {
+                // Only the std trait: not `serde::de::Error` and the like.
+                "Error" if !trait_path.contains("::") || trait_path.ends_with("error::Error") => {
+                    let anchor = line.find(trait_path.as_str()).map(|column| {
+                        (line[..column].encode_utf16().count() + trait_path.len() - 5) as u32
+                    });
+                    items.push(ErrorItem::Type {
+                        name: target,
+                        line: idx as u32,
+                        defined_by: "impl",
+                        anchor,
+                    });
+                }
+                "From" => {
+                    if let Some(from) = trait_path
+                        .split_once('<')
+                        .and_then(|(_, args)| args.strip_suffix('>'))
+                    {
+                        items.push(ErrorItem::From {
+                            target,
+                            conversion: ErrorConversion {
+                                from: from.trim().to_string(),
+                                via: "impl From",
+                                variant: None,
+                                local_error: false,
+                                file_path: String::new(),
+                                line: idx as u32 + 1,
+                            },
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((target, _)) = &derived_body
+            && let Some(column) = line.find("#[from]")
+        {
+            let before = line[..column].trim();
+            let mut after = line[column + "#[from]".len()..].trim();
+            if after.is_empty() {
+                after = lines.get(idx + 1).map_or("", |next| next.trim());
+            }
+            let ty = match after.split_once(':') {
+                Some((field, ty)) if !ty.starts_with(':') && !field.contains('<') => ty,
+                _ => after,
+            };
+            let ty = ty
+                .trim()
+                .trim_end_matches(',')
+                .trim_end_matches(')')
+                .trim_end_matches('}')
+                .trim();
+            let variant = before
+                .split(['(', '{'])
+                .next()
+                .map(str::trim)
+                .filter(|variant| !variant.is_empty())
+                .map(str::to_string);
+            items.push(ErrorItem::From {
+                target: target.clone(),
+                conversion: ErrorConversion {
+                    from: ty.to_string(),
+                    via: "#[from]",
+                    variant,
+                    local_error: false,
+                    file_path: String::new(),
+                    line: idx as u32 + 1,
+                },
+            });
+        }
+
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if let Some((_, outer)) = &derived_body
+            && depth <= *outer
+            && (line.contains('}') || line.trim_end().ends_with(';'))
+        {
+            derived_body = None;
+        }
+    }
+    items
+}
+
+/// The type an implementation location names: the self type of an `impl`
+/// header, or the struct or enum a derive applies to.
+fn implementing_type(content: &str, line: usize) -> Option<String> {
+    let header = read_impl_header(content, line)?;
+    if let Some(ImplHeader::Trait { self_type, .. }) = parse_impl_header(&header) {
+        return Some(last_path_segment(&self_type).to_string());
+    }
+    content.lines().skip(line).take(8).find_map(|text| {
+        let item = strip_visibility(text.trim());
+        let rest = item
+            .strip_prefix("struct ")
+            .or_else(|| item.strip_prefix("enum "))?;
+        let name: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        (!name.is_empty()).then_some(name)
+    })
+}
+
+pub async fn trace_question_mark_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let offset = try_operator_offset(&file_content, code_block, occurrence)?;
+    let (line, character) = index_to_line_col(&file_content, offset);
+    let expression = file_content.lines().nth(line as usize).unwrap_or("").trim();
+
+    let hover = analyzer.get_hover(file_path, line, character).await?;
+    let propagation = parse_try_hover(&hover);
+    let return_type = analyzer
+        .enclosing_function_range(file_path, Position { line, character })
+        .await?
+        .and_then(|range| {
+            let source: Vec<&str> = file_content
+                .lines()
+                .skip(range.start.line as usize)
+                .take((range.end.line - range.start.line) as usize + 1)
+                .collect();
+            function_return_type(&source.join("\n"))
+        });
+    let declared_error = return_type
+        .as_deref()
+        .and_then(|ty| declared_error_type(ty, &file_content));
+
+    let mut notes = Vec::new();
+    let (source, target, resolved_by) = match &propagation {
+        Some(propagation) => {
+            // rust-analyzer prints bare type names; the signature may spell
+            // the target out, e.g. `anyhow::Error` for `Error`.
+            let target = match &declared_error {
+                Some(declared)
+                    if last_path_segment(declared) == last_path_segment(&propagation.target)
+                        && declared.len() > propagation.target.len() =>
+                {
+                    declared.clone()
+                }
+                _ => propagation.target.clone(),
+            };
+            (
+                Some(propagation.source.clone()),
+                Some(target),
+                "rust-analyzer",
+            )
+        }
+        // Without a conversion to show, the hover describes the expression's
+        // type: the error is propagated unchanged.
+        None if hover != "No hover information found" => {
+            (declared_error.clone(), declared_error.clone(), "signature")
+        }
+        None => {
+            notes.push(
+                "rust-analyzer has no hover for this `?`; the source error type is unknown and the target comes from the enclosing function's signature.".to_string(),
+            );
+            (None, declared_error.clone(), "signature")
+        }
+    };
+
+    let mut conversion = None;
+    let bridged = match (&source, &target) {
+        _ if propagation.as_ref().is_some_and(|p| !p.errors_only) => {
+            notes.push(
+                "The `?` converts between different try types (not two Results), which no From impl bridges.".to_string(),
+            );
+            Some(false)
+        }
+        (Some(source), Some(target)) if source == target => {
+            conversion = Some(json!({ "via": "identity" }));
+            Some(true)
+        }
+        (Some(source), Some(target)) => {
+            if let Some(blanket) = blanket_conversion(target) {
+                conversion = Some(json!({ "via": "blanket impl", "impl": blanket }));
+            } else {
+                let root = owning_manifest(Path::new(file_path))
+                    .ok()
+                    .and_then(|manifest| manifest.parent().map(Path::to_path_buf))
+                    .unwrap_or_else(|| Path::new(file_path).with_file_name(""));
+                for file in collect_rust_files(&root).await? {
+                    let Ok(content) = fs::read_to_string(&file).await else {
+                        continue;
+                    };
+                    let found =
+                        scan_error_items(&content)
+                            .into_iter()
+                            .find_map(|item| match item {
+                                ErrorItem::From {
+                                    target: into,
+                                    conversion,
+                                } if into == last_path_segment(target)
+                                    && conversion.source_name() == last_path_segment(source) =>
+                                {
+                                    Some(conversion)
+                                }
+                                _ => None,
+                            });
+                    if let Some(found) = found {
+                        conversion = Some(json!(ErrorConversion {
+                            file_path: file.to_string_lossy().to_string(),
+                            ..found
+                        }));
+                        break;
+                    }
+                }
+                if conversion.is_none() {
+                    notes.push(format!(
+                        "No `impl From<{}> for {}` or `#[from]` field found in the crate; a conversion from another crate may still apply, otherwise map the error with `map_err`.",
+                        source, target
+                    ));
+                }
+            }
+            Some(conversion.is_some())
+        }
+        _ => None,
+    };
+
+    let mut errors = Vec::new();
+    for ty in [&source, &target] {
+        let definition = match ty {
+            Some(ty) => error_type_definition(analyzer, ty).await?,
+            None => None,
+        };
+        errors.push(json!({ "type": ty, "definition": definition }));
+    }
+    let target_error = errors.pop();
+    let source_error = errors.pop();
+
+    let result = json!({
+        "file_path": file_path,
+        "line": line + 1,
+        "expression": expression,
+        "return_type": return_type,
+        "resolved_by": resolved_by,
+        "source_error": source_error,
+        "target_error": target_error,
+        "bridged": bridged,
+        "conversion": conversion,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Byte offset of the `occurrence`-th `?` operator in `code_block`, skipping
+/// question marks in comments and strings.
+fn try_operator_offset(content: &str, code_block: &str, occurrence: usize) -> Result<usize> {
+    let block_start = content.find(code_block).ok_or_else(|| {
+        anyhow::anyhow!("Code block not found in file. Ensure the code block is an exact match.")
+    })?;
+    let marks: Vec<usize> = code_block
+        .match_indices('?')
+        .map(|(idx, _)| block_start + idx)
+        .filter(|&idx| is_valid_code_context(content, idx))
+        .collect();
+    let offset = *marks.get(occurrence.max(1) - 1).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Found only {} `?` (not in comment/string) in the code block, expected #{}",
+            marks.len(),
+            occurrence
+        )
+    })?;
+
+    // An operator follows an expression: not `?Sized` after `:` or `+`, nor
+    // the `$(...)?` of a macro repetition.
+    let before = content[..offset].trim_end();
+    let follows_expression = match before.chars().next_back() {
+        Some(')') => {
+            let mut depth = 0;
+            let open = before.char_indices().rev().find_map(|(idx, c)| {
+                match c {
+                    ')' => depth += 1,
+                    '(' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(idx)
+            });
+            !open.is_some_and(|open| before[..open].ends_with('$'))
+        }
+        Some(c) => c.is_alphanumeric() || matches!(c, '_' | ']' | '}' | '?'),
+        None => false,
+    };
+    if !follows_expression {
+        let (line, _) = index_to_line_col(content, offset);
+        return Err(anyhow::anyhow!(
+            "The `?` on line {} is not a `?` operator: it does not follow an expression (e.g. a `?Sized` bound)",
+            line + 1
+        ));
+    }
+    Ok(offset)
+}
+
+/// What rust-analyzer's hover on a `?` reports, absent when the try type and
+/// the enclosing return type are the same.
+#[derive(Debug, PartialEq)]
+struct TryPropagation {
+    /// Both sides are `Result`s, and the types are their error types.
+    errors_only: bool,
+    source: String,
+    target: String,
+}
+
+/// Parses `Try Error Type: E` (or `Try Target Type: T`) and `Propagated as: F`
+/// from a `?` hover.
+fn parse_try_hover(hover: &str) -> Option<TryPropagation> {
+    let mut errors_only = false;
+    let mut source = None;
+    let mut target = None;
+    for line in hover.lines() {
+        if let Some(ty) = line.strip_prefix("Try Error Type:") {
+            errors_only = true;
+            source = Some(ty.trim());
+        } else if let Some(ty) = line.strip_prefix("Try Target Type:") {
+            source = Some(ty.trim());
+        } else if let Some(ty) = line.strip_prefix("Propagated as:") {
+            target = Some(ty.trim());
+        }
+    }
+    Some(TryPropagation {
+        errors_only,
+        source: source?.to_string(),
+        target: target?.to_string(),
+    })
+}
+
+/// The declared return type of the function whose source is `source`.
+fn function_return_type(source: &str) -> Option<String> {
+    let source = strip_attributes(&strip_comments(source));
+    let (keyword, end) = find_item_keyword(&source)?;
+    if keyword != "fn" {
+        return None;
+    }
+    let header = &source[end..];
+    let header = &header[..find_header_end(header)];
+    let open = header.find(['<', '('])?;
+    let (_, rest) = split_generic_params(&header[open..])?;
+    let open = rest.find('(')?;
+    let close = open + find_matching_paren(&rest[open..])?;
+    let after = rest[close + 1..].trim_start().strip_prefix("->")?;
+    let ty = match find_keyword(after, "where") {
+        Some(idx) => &after[..idx],
+        None => after,
+    };
+    Some(collapse_whitespace(ty.trim()))
+}
+
+/// The error type of a declared return type: `E` of `Result<T, E>`,
+/// `io::Error` for `io::Result<T>`, and for a bare `Result<T>` the error of
+/// a `use anyhow::Result` or a local `type Result` alias in `content`.
+fn declared_error_type(return_type: &str, content: &str) -> Option<String> {
+    let (path, args) = return_type.trim().split_once('<')?;
+    let path = path.trim();
+    if last_path_segment(path) != "Result" {
+        return None;
+    }
+    let args = split_top_level(args.trim_end().strip_suffix('>')?);
+    if let Some(error) = args.get(1) {
+        return Some(error.trim().to_string());
+    }
+    if let Some((module, _)) = path.rsplit_once("::")
+        && !module.ends_with("result")
+    {
+        return Some(format!("{}::Error", module));
+    }
+    content.lines().find_map(|line| {
+        let line = strip_visibility(line.trim());
+        if line.starts_with("use anyhow::") && find_keyword(line, "Result").is_some() {
+            return Some("anyhow::Error".to_string());
+        }
+        let (_, aliased) = line.strip_prefix("type Result<")?.split_once('=')?;
+        declared_error_type(aliased.trim().trim_end_matches(';'), "")
+    })
+}
+
+/// The blanket `From` impl through which `?` converts any error into
+/// `target`, for the catch-all error types.
+fn blanket_conversion(target: &str) -> Option<&'static str> {
+    let target = collapse_whitespace(target);
+    if target == "anyhow::Error" {
+        Some("impl<E: std::error::Error + Send + Sync + 'static> From<E> for anyhow::Error")
+    } else if target == "eyre::Report" || target == "color_eyre::Report" {
+        Some("impl<E: std::error::Error + Send + Sync + 'static> From<E> for eyre::Report")
+    } else if target.starts_with("Box<dyn") && target.contains("Error") {
+        Some("impl<'a, E: Error + 'a> From<E> for Box<dyn Error + 'a>")
+    } else {
+        None
+    }
+}
+
+/// Where the struct or enum `ty` names is defined, found by a workspace
+/// symbol lookup; the first match when the bare name is ambiguous.
+async fn error_type_definition(
+    analyzer: &mut RustAnalyzerClient,
+    ty: &str,
+) -> Result<Option<Value>> {
+    let name = last_path_segment(ty);
+    if name.is_empty() || ty.trim_start().starts_with(['&', '(', '[']) || name == "Box" {
+        return Ok(None);
+    }
+    let infos = analyzer.workspace_symbol_infos(name).await?;
+    let matches: Vec<Location> = infos
+        .iter()
+        .filter(|info| info.get("name").and_then(|n| n.as_str()) == Some(name))
+        .filter(|info| matches!(info.get("kind").and_then(|k| k.as_u64()), Some(10 | 23)))
+        .filter_map(|info| serde_json::from_value(info.get("location")?.clone()).ok())
+        .collect();
+    Ok(matches.first().map(|location| {
+        json!({
+            "file_path": location.uri.strip_prefix("file://").unwrap_or(&location.uri),
+            "line": location.range.start.line + 1,
+            "candidates": matches.len()
+        })
+    }))
+}
+
+pub async fn find_constructions_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let mut locations = analyzer
+        .reference_locations(file_path, line, character)
+        .await?;
+    locations.sort_by(|a, b| {
+        (&a.uri, a.range.start.line, a.range.start.character).cmp(&(
+            &b.uri,
+            b.range.start.line,
+            b.range.start.character,
+        ))
+    });
+
+    let mut contents: HashMap<String, Option<String>> = HashMap::new();
+    let mut constructions = Vec::new();
+    let mut patterns = 0;
+    let mut type_uses = 0;
+    for location in &locations {
+        let site_path = location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+            .to_string();
+        if !contents.contains_key(&site_path) {
+            let content = fs::read_to_string(&site_path).await.ok();
+            contents.insert(site_path.clone(), content);
+        }
+        let Some(site_line) = contents[&site_path]
+            .as_deref()
+            .and_then(|content| content.lines().nth(location.range.start.line as usize))
+        else {
+            continue;
+        };
+
+        let start = utf16_column_offset(site_line, location.range.start.character);
+        let end = utf16_column_offset(site_line, location.range.end.character);
+        match classify_type_site(site_line, start, end) {
+            TypeSite::Pattern => patterns += 1,
+            TypeSite::TypeUse => type_uses += 1,
+            site => constructions.push(json!({
+                "file_path": site_path,
+                "line": location.range.start.line + 1,
+                "column": location.range.start.character + 1,
+                "kind": site.as_str(),
+                "constructor": match &site {
+                    TypeSite::ConstructorCall(name) | TypeSite::Variant(name) => Some(name.clone()),
+                    _ => None,
+                },
+                "snippet": site_line.trim()
+            })),
+        }
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for construction in &constructions {
+        if let Some(kind) = construction["kind"].as_str() {
+            *counts.entry(kind).or_default() += 1;
+        }
+    }
+    let mut notes = vec![
+        "Sites are classified from the source line alone; `Self { .. }` / `Self::new()` inside the type's own impl are not references to the name and are not listed."
+            .to_string(),
+    ];
+    if counts.contains_key("value") {
+        notes.push(
+            "`value` sites name the type where an expression is expected: a unit struct, or a tuple struct constructor passed as a function (e.g. `.map(Wrapper)`)."
+                .to_string(),
+        );
+    }
+
+    let result = json!({
+        "type": symbol,
+        "constructions": constructions.len(),
+        "counts": counts,
+        "patterns": patterns,
+        "type_uses": type_uses,
+        "sites": constructions,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// How a reference to a type name is used on its line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TypeSite {
+    /// `Type { .. }`
+    StructLiteral,
+    /// `Type(..)`
+    TupleConstructor,
+    /// `Type::new(..)` and other constructor-like associated functions.
+    ConstructorCall(String),
+    /// `Type::Variant(..)` or `Type::Variant { .. }`.
+    Variant(String),
+    /// The name in expression position: a unit struct or a constructor
+    /// passed as a function value.
+    Value,
+    /// Destructuring in `let`, `match` or `if let`.
+    Pattern,
+    /// Annotations, generic arguments, `impl` headers, imports, the declaration.
+    TypeUse,
+}
+
+impl TypeSite {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TypeSite::StructLiteral => "struct_literal",
+            TypeSite::TupleConstructor => "tuple_constructor",
+            TypeSite::ConstructorCall(_) => "constructor_call",
+            TypeSite::Variant(_) => "variant",
+            TypeSite::Value => "value",
+            TypeSite::Pattern => "pattern",
+            TypeSite::TypeUse => "type_use",
+        }
+    }
+}
+
+/// Classifies the type name at bytes `start..end` of `line`.
+fn classify_type_site(line: &str, start: usize, end: usize) -> TypeSite {
+    // The statement or block the name sits in, up to the name.
+    let segment = line[..start]
+        .rsplit(['{', '}', ';'])
+        .next()
+        .unwrap_or_default();
+    let words: Vec<&str> = segment
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .collect();
+    let head = segment.trim_end();
+    if words.iter().any(|word| {
+        matches!(
+            *word,
+            "impl" | "struct" | "enum" | "union" | "trait" | "type" | "fn" | "use" | "dyn"
+        )
+    }) || head.ends_with("->")
+        || (head.ends_with(':') && !head.ends_with("::"))
+    {
+        return TypeSite::TypeUse;
+    }
+
+    let mut rest = line[end..].trim_start();
+    if let Some(generics) = rest.strip_prefix("::<").or_else(|| rest.strip_prefix('<')) {
+        let Some(close) = closing_angle(generics) else {
+            return TypeSite::TypeUse;
+        };
+        rest = generics[close + 1..].trim_start();
+    }
+
+    let in_pattern = |tail: &str| {
+        tail.contains("=>")
+            || segment
+                .rfind("let ")
+                .is_some_and(|idx| !has_assignment(&segment[idx..]))
+    };
+    if let Some(after) = rest.strip_prefix("::") {
+        let name: String = after
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        let next = after[name.len()..].trim_start();
+        let next = next
+            .strip_prefix("::<")
+            .and_then(|generics| closing_angle(generics).map(|close| &generics[close + 1..]))
+            .unwrap_or(next);
+        if name.starts_with(char::is_uppercase) && (next.starts_with('(') || next.starts_with('{'))
+        {
+            return if in_pattern(next) {
+                TypeSite::Pattern
+            } else {
+                TypeSite::Variant(name)
+            };
+        }
+        let constructor_like = name == "new"
+            || name == "default"
+            || ["new_", "with_", "from", "try_new", "try_from", "builder"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+        return if constructor_like && next.starts_with('(') {
+            TypeSite::ConstructorCall(name)
+        } else {
+            TypeSite::TypeUse
+        };
+    }
+
+    if rest.starts_with('{') || rest.starts_with('(') {
+        return if in_pattern(rest) {
+            TypeSite::Pattern
+        } else if rest.starts_with('{') {
+            TypeSite::StructLiteral
+        } else {
+            TypeSite::TupleConstructor
+        };
+    }
+
+    let value_follows = rest.is_empty() || rest.starts_with([';', ',', ')', '}']);
+    let value_precedes = head.ends_with("=>")
+        || head.ends_with("return")
+        || (head.ends_with('=') && has_assignment(head))
+        || head
+            .strip_suffix('(')
+            .is_some_and(|callee| callee.ends_with(|c: char| c.is_alphanumeric() || c == '_'));
+    if value_follows && value_precedes && !in_pattern(rest) {
+        TypeSite::Value
+    } else {
+        TypeSite::TypeUse
+    }
+}
+
+/// Index of the `>` closing generic arguments whose `<` was just consumed.
+fn closing_angle(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut previous = ' ';
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' if previous != '-' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+/// Whether `text` contains a plain `=` (not `==`, `!=`, `<=`, `>=` or `=>`).
+fn has_assignment(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.iter().enumerate().any(|(idx, &b)| {
+        b == b'='
+            && !matches!(
+                idx.checked_sub(1).map(|i| bytes[i]),
+                Some(b'=' | b'!' | b'<' | b'>')
+            )
+            && !matches!(bytes.get(idx + 1), Some(b'=' | b'>'))
+    })
+}
+
+pub async fn describe_enum_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let description = parse_enum_source(&source)
+        .ok_or_else(|| anyhow::anyhow!("`{}` does not resolve to an enum definition", symbol))?;
+
+    let result = json!({
+        "file_path": actual_path,
+        "line": range.start.line + 1,
+        "enum": description
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EnumDescription {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub variants: Vec<EnumVariant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EnumVariant {
+    pub name: String,
+    pub kind: VariantKind,
+    pub fields: Vec<VariantField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discriminant: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum VariantKind {
+    Unit,
+    Tuple,
+    Struct,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct VariantField {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// Parses an enum definition (as returned by `get_symbol_source`, doc comments
+/// and attributes included) into its variants. Returns `None` when the source
+/// is not an enum.
+pub(crate) fn parse_enum_source(source: &str) -> Option<EnumDescription> {
+    let source = strip_attributes(&strip_comments(source));
+
+    let enum_idx = source.split_whitespace().position(|word| word == "enum")?;
+    let after_keyword = source
+        .split_whitespace()
+        .skip(enum_idx + 1)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let name_end = after_keyword
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+        .unwrap_or(after_keyword.len());
+    let name = after_keyword[..name_end].to_string();
+    if name.is_empty() {
+        return None;
+    }
+
+    let rest = after_keyword[name_end..].trim_start();
+    let generics = match rest.strip_prefix('<') {
+        Some(params) => split_top_level(&params[..find_closing_angle(params)?]),
+        None => Vec::new(),
+    };
+
+    let open = rest.find('{')?;
+    let close = rest.rfind('}')?;
+    if close <= open {
+        return None;
+    }
+
+    let variants = split_top_level(&rest[open + 1..close])
+        .iter()
+        .filter_map(|variant| parse_variant(variant))
+        .collect();
+
+    Some(EnumDescription {
+        name,
+        generics,
+        variants,
+    })
+}
+
+fn parse_variant(text: &str) -> Option<EnumVariant> {
+    let text = text.trim();
+    let name_end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+        .unwrap_or(text.len());
+    let name = text[..name_end].to_string();
+    if name.is_empty() {
+        return None;
+    }
+
+    let rest = text[name_end..].trim_start();
+    let (kind, fields, rest) = if let Some(body) = rest.strip_prefix('(') {
+        let end = body.rfind(')')?;
+        let fields = split_top_level(&body[..end])
+            .into_iter()
+            .map(|ty| VariantField {
+                name: None,
+                ty: strip_visibility(&ty).to_string(),
+            })
+            .collect();
+        (VariantKind::Tuple, fields, &body[end + 1..])
+    } else if let Some(body) = rest.strip_prefix('{') {
+        let end = body.rfind('}')?;
+        let fields = split_top_level(&body[..end])
+            .into_iter()
+            .filter_map(|field| {
+                let (field_name, ty) = strip_visibility(&field).split_once(':')?;
+                Some(VariantField {
+                    name: Some(field_name.trim().to_string()),
+                    ty: ty.trim().to_string(),
+                })
+            })
+            .collect();
+        (VariantKind::Struct, fields, &body[end + 1..])
+    } else {
+        (VariantKind::Unit, Vec::new(), rest)
+    };
+
+    let discriminant = rest
+        .trim()
+        .strip_prefix('=')
+        .map(|value| value.trim().to_string());
+
+    Some(EnumVariant {
+        name,
+        kind,
+        fields,
+        discriminant,
+    })
+}
+
+pub async fn describe_generics_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let generics = parse_item_generics(&source)
+        .ok_or_else(|| anyhow::anyhow!("Could not find an item declaration for `{}`", symbol))?;
+
+    let result = json!({
+        "file_path": actual_path,
+        "line": range.start.line + 1,
+        "generics": generics
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn generics_in_scope_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let position = Position { line, character };
+
+    let symbols = match analyzer.document_symbols(file_path).await? {
+        DocumentSymbolResponse::DocumentSymbols(symbols) => symbols,
+        DocumentSymbolResponse::SymbolInformation(_) => Vec::new(),
+    };
+    let chain = symbols_at(&symbols, &position);
+    let kinds: Vec<u32> = chain.iter().map(|symbol| symbol.kind).collect();
+
+    let mut scopes = Vec::new();
+    let mut in_scope = Vec::new();
+    // Outermost first, as the parameters are introduced.
+    for idx in generic_scope(&kinds).into_iter().rev() {
+        let item = chain[idx];
+        let Some(start) = line_start_offset(&file_content, item.range.start.line as usize) else {
+            continue;
+        };
+        let end = line_start_offset(&file_content, item.range.end.line as usize + 1)
+            .unwrap_or(file_content.len());
+        let Some(generics) = parse_item_generics(&file_content[start..end]) else {
+            continue;
+        };
+        in_scope.extend(generics.lifetimes.iter().map(|param| param.name.clone()));
+        in_scope.extend(
+            generics
+                .type_parameters
+                .iter()
+                .map(|param| param.name.clone()),
+        );
+        in_scope.extend(
+            generics
+                .const_parameters
+                .iter()
+                .map(|param| param.name.clone()),
+        );
+        let self_type = match generics.kind.as_str() {
+            "impl" => Some(
+                generics
+                    .item
+                    .rsplit_once(" for ")
+                    .map_or(generics.item.as_str(), |(_, self_type)| self_type)
+                    .to_string(),
+            ),
+            "trait" => Some(format!("Self: {}", generics.item)),
+            _ => None,
+        };
+        let mut scope = json!({
+            "introduced_by": generics.kind,
+            "item": generics.item,
+            "line": item.range.start.line + 1,
+            "lifetimes": generics.lifetimes,
+            "type_parameters": generics.type_parameters,
+            "const_parameters": generics.const_parameters,
+            "where_clause": generics.where_clause
+        });
+        if let Some(self_type) = self_type {
+            scope["self_type"] = json!(self_type);
+        }
+        scopes.push(scope);
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "line": line + 1,
+        "in_scope": in_scope,
+        "scopes": scopes,
+        "note": "Parameters of items enclosing the innermost item are not in scope (a nested fn cannot name its parent's generics), so only the innermost item and the impl or trait it belongs to are listed. `impl Trait` arguments and elided lifetimes have no name and are not reported."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The symbols whose range contains `position`, outermost first, down to the
+/// innermost one.
+fn symbols_at<'a>(symbols: &'a [DocumentSymbol], position: &Position) -> Vec<&'a DocumentSymbol> {
+    let Some(symbol) = symbols
+        .iter()
+        .find(|symbol| RustAnalyzerClient::position_in_range(&symbol.range, position))
+    else {
+        return Vec::new();
+    };
+    let mut chain = vec![symbol];
+    if let Some(children) = &symbol.children {
+        chain.extend(symbols_at(children, position));
+    }
+    chain
+}
+
+/// Indices into `kinds` (LSP symbol kinds of the symbols at a position,
+/// outermost first) of the items whose generics are in scope, innermost
+/// first: the innermost item, then the impl or trait it is declared in.
+/// Fields, variants and locals are skipped; a module ends the walk.
+fn generic_scope(kinds: &[u32]) -> Vec<usize> {
+    let mut scope = Vec::new();
+    for (idx, kind) in kinds.iter().enumerate().rev() {
+        match kind {
+            // Module.
+            2 => break,
+            // Interface (trait) and Object (impl block).
+            11 | 19 => {
+                scope.push(idx);
+                break;
+            }
+            // Function, method, struct, enum, type alias and constant.
+            12 | 6 | 23 | 10 | 26 | 14 if scope.is_empty() => scope.push(idx),
+            12 | 6 | 23 | 10 | 26 | 14 => break,
+            _ => {}
+        }
+    }
+    scope
+}
+
+pub async fn derive_bounds_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let generics = parse_item_generics(&source)
+        .filter(|generics| matches!(generics.kind.as_str(), "struct" | "enum" | "union"))
+        .ok_or_else(|| {
+            anyhow::anyhow!("`{}` does not resolve to a struct, enum or union", symbol)
+        })?;
+    let field_types: Vec<String> = match generics.kind.as_str() {
+        "struct" => parse_struct_source(&source)
+            .map(|definition| {
+                definition
+                    .fields
+                    .into_iter()
+                    .map(|field| field.ty)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "enum" => parse_enum_source(&source)
+            .map(|description| {
+                description
+                    .variants
+                    .into_iter()
+                    .flat_map(|variant| variant.fields)
+                    .map(|field| field.ty)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let derived = derives(&source);
+    let mut notes = Vec::new();
+    let mut std_derives = Vec::new();
+    let mut other_derives = Vec::new();
+    for name in &derived {
+        let Some((path, _)) = derived_methods(name) else {
+            other_derives.push(name.clone());
+            continue;
+        };
+
+        let implied_bounds: Vec<String> = generics
+            .type_parameters
+            .iter()
+            .map(|param| format!("{}: {}", param.name, name))
+            .collect();
+        let stricter_than_fields: Vec<&str> = generics
+            .type_parameters
+            .iter()
+            .filter(|param| {
+                !field_types
+                    .iter()
+                    .any(|ty| field_requires_bound(ty, &param.name, name))
+            })
+            .map(|param| param.name.as_str())
+            .collect();
+        let requires: Vec<&str> = derive_supertraits(name)
+            .iter()
+            .copied()
+            .filter(|supertrait| !derived.iter().any(|other| other == supertrait))
+            .collect();
+        for supertrait in &requires {
+            notes.push(format!(
+                "`{name}` needs `{supertrait}` implemented too; derive it or write the impl by hand."
+            ));
+        }
+
+        std_derives.push(json!({
+            "trait": name,
+            "path": path,
+            "implied_bounds": implied_bounds,
+            "impl": derived_impl_header(&generics, name),
+            "stricter_than_fields": stricter_than_fields,
+            "missing_supertraits": requires
+        }));
+    }
+
+    if derived.is_empty() {
+        notes.push(format!(
+            "`{}` has no #[derive(..)] attribute.",
+            generics.item
+        ));
+    } else if generics.type_parameters.is_empty() {
+        notes.push(
+            "No type parameters: the derives add no bounds, but every field type must still implement each derived trait."
+                .to_string(),
+        );
+    }
+    if !other_derives.is_empty() {
+        notes.push(format!(
+            "{} {} not std derives; their bounds depend on the macro (serde, for example, infers them from the field types).",
+            other_derives.join(", "),
+            if other_derives.len() == 1 { "is" } else { "are" }
+        ));
+    }
+
+    let result = json!({
+        "file_path": actual_path,
+        "line": range.start.line + 1,
+        "type": generics.item,
+        "kind": generics.kind,
+        "type_parameters": generics.type_parameters.iter().map(|param| &param.name).collect::<Vec<_>>(),
+        "derives": std_derives,
+        "other_derives": other_derives,
+        "notes": notes,
+        "rule": "A std derive adds `P: Trait` for every type parameter P, whether or not the fields need it, and keeps the declared bounds and where-clause. Lifetimes and const parameters get no bounds. `stricter_than_fields` lists parameters only used where the trait holds regardless (PhantomData, and for Clone also Rc/Arc/& ...): a hand-written impl without that bound would accept more types."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Traits a derived std trait needs implemented alongside it.
+fn derive_supertraits(name: &str) -> &'static [&'static str] {
+    match name {
+        "Copy" => &["Clone"],
+        "Eq" | "PartialOrd" => &["PartialEq"],
+        "Ord" => &["Eq", "PartialOrd"],
+        _ => &[],
+    }
+}
+
+/// Types implementing the derivable trait `name` whatever their type
+/// arguments are; `&` and `*` stand for shared references and raw pointers.
+fn bound_free_types(name: &str) -> &'static [&'static str] {
+    match name {
+        "Clone" => &["PhantomData", "Rc", "Arc", "Weak", "&", "*", "fn"],
+        "Copy" => &["PhantomData", "&", "*", "fn"],
+        "Default" => &[
+            "PhantomData",
+            "Option",
+            "Vec",
+            "VecDeque",
+            "LinkedList",
+            "HashMap",
+            "HashSet",
+            "BTreeMap",
+            "BTreeSet",
+        ],
+        _ => &["PhantomData", "*", "fn"],
+    }
+}
+
+/// Whether a field of type `ty` only implements the derived trait `name`
+/// when the type parameter `param` does.
+fn field_requires_bound(ty: &str, param: &str, name: &str) -> bool {
+    let ty = ty.trim();
+    let free = bound_free_types(name);
+    let mentions = |text: &str| {
+        text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(|word| word == param)
+    };
+
+    if let Some(rest) = ty.strip_prefix('&') {
+        let pointee = match rest.trim_start().strip_prefix('\'') {
+            Some(lifetime) => lifetime
+                .split_once(char::is_whitespace)
+                .map_or("", |(_, t)| t),
+            None => rest,
+        };
+        if free.contains(&"&") && !pointee.trim_start().starts_with("mut ") {
+            return false;
+        }
+        return mentions(pointee);
+    }
+    if ty.starts_with('*') {
+        return !free.contains(&"*") && mentions(ty);
+    }
+    if ty.starts_with("fn") && ty[2..].trim_start().starts_with('(') {
+        return !free.contains(&"fn") && mentions(ty);
+    }
+    if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        return split_top_level(inner)
+            .iter()
+            .any(|element| field_requires_bound(element, param, name));
+    }
+    if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let element = inner.rsplit_once(';').map_or(inner, |(element, _)| element);
+        return field_requires_bound(element, param, name);
+    }
+
+    let Some(open) = ty.find('<').filter(|_| !ty.starts_with('<')) else {
+        return mentions(ty);
+    };
+    let path = &ty[..open];
+    if free.contains(&last_path_segment(path)) {
+        return false;
+    }
+    let Some(close) = find_closing_angle(&ty[open + 1..]) else {
+        return mentions(ty);
+    };
+    mentions(path)
+        || split_top_level(&ty[open + 1..open + 1 + close])
+            .iter()
+            .filter(|argument| !argument.starts_with('\''))
+            .any(|argument| field_requires_bound(argument, param, name))
+}
+
+/// The impl header a std derive of `name` generates for `generics`.
+fn derived_impl_header(generics: &ItemGenerics, name: &str) -> String {
+    let mut params = Vec::new();
+    let mut arguments = Vec::new();
+    for lifetime in &generics.lifetimes {
+        params.push(if lifetime.outlives.is_empty() {
+            lifetime.name.clone()
+        } else {
+            format!("{}: {}", lifetime.name, lifetime.outlives.join(" + "))
+        });
+        arguments.push(lifetime.name.clone());
+    }
+    for param in &generics.type_parameters {
+        let mut bounds = param.bounds.clone();
+        bounds.push(name.to_string());
+        params.push(format!("{}: {}", param.name, bounds.join(" + ")));
+        arguments.push(param.name.clone());
+    }
+    for param in &generics.const_parameters {
+        params.push(format!("const {}: {}", param.name, param.ty));
+        arguments.push(param.name.clone());
+    }
+
+    let mut header = if params.is_empty() {
+        format!("impl {name} for {}", generics.item)
+    } else {
+        format!(
+            "impl<{}> {name} for {}<{}>",
+            params.join(", "),
+            generics.item,
+            arguments.join(", ")
+        )
+    };
+    if !generics.where_clause.is_empty() {
+        let predicates: Vec<String> = generics
+            .where_clause
+            .iter()
+            .map(|predicate| format!("{}: {}", predicate.bounded, predicate.bounds.join(" + ")))
+            .collect();
+        header.push_str(&format!(" where {}", predicates.join(", ")));
+    }
+    header
+}
+
+pub async fn trace_lifetime_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let lifetime = args
+        .get("lifetime")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing lifetime parameter"))?;
+
+    let name = lifetime.trim().trim_start_matches('\'');
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(anyhow::anyhow!("`{}` is not a lifetime name", lifetime));
+    }
+    let lifetime = format!("'{name}");
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let generics = parse_item_generics(&source)
+        .filter(|generics| generics.kind == "fn")
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a function", symbol))?;
+
+    let source_lines: Vec<&str> = source.lines().collect();
+    let uses: Vec<Value> = scan_lifetime_uses(&source, &lifetime)
+        .into_iter()
+        .map(|found| {
+            let mut entry = json!({
+                "line": range.start.line as usize + found.line + 1,
+                "column": found.column + 1,
+                "role": found.role,
+                "text": source_lines.get(found.line).map(|text| text.trim())
+            });
+            if let Some(parameter) = found.parameter {
+                entry["parameter"] = json!(parameter);
+            }
+            entry
+        })
+        .collect();
+
+    let declared = generics
+        .lifetimes
+        .iter()
+        .any(|param| param.name == lifetime);
+    let result = json!({
+        "file_path": actual_path,
+        "function": generics.item,
+        "lifetime": lifetime,
+        "declared_on": if declared { "function" } else { "enclosing impl or trait" },
+        "relations": lifetime_relations(&generics, &lifetime),
+        "uses": uses,
+        "note": "`relations` are outlives bounds involving the lifetime: `'b: 'a` means 'b outlives 'a, `T: 'a` means T holds no borrow shorter than 'a. Elided lifetimes do not appear in the source and are not reported."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Where a lifetime is named in a function's source.
+#[derive(Debug, PartialEq)]
+struct LifetimeUse {
+    /// 0-based line within the source.
+    line: usize,
+    /// 0-based byte column.
+    column: usize,
+    /// `declaration`, `bound`, `parameter`, `return_type`, `where_clause`
+    /// or `body`.
+    role: &'static str,
+    /// The parameter whose type names the lifetime.
+    parameter: Option<String>,
+}
+
+/// Finds every `'lifetime` token from the `fn` keyword on, skipping comments,
+/// strings and longer lifetimes or char literals sharing the prefix.
+fn scan_lifetime_uses(source: &str, lifetime: &str) -> Vec<LifetimeUse> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let Some(fn_start) = source.match_indices("fn").map(|(idx, _)| idx).find(|&idx| {
+        source[..idx]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !is_ident(c))
+            && source[idx + 2..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace)
+            && is_valid_code_context(source, idx)
+    }) else {
+        return Vec::new();
+    };
+
+    // Byte ranges of the signature parts, from the name to the body.
+    let header_end = fn_start + 2 + find_header_end(&source[fn_start + 2..]);
+    let name_start = header_end - source[fn_start + 2..header_end].trim_start().len();
+    let name_end = source[name_start..header_end]
+        .find(|c: char| !is_ident(c))
+        .map_or(header_end, |idx| name_start + idx);
+    let generics = source[name_end..header_end]
+        .strip_prefix('<')
+        .and_then(find_closing_angle)
+        .map(|close| (name_end + 1, name_end + 1 + close));
+    let after_generics = generics.map_or(name_end, |(_, close)| close + 1);
+    let params = source[after_generics..header_end]
+        .find('(')
+        .map(|idx| after_generics + idx)
+        .and_then(|open| Some((open + 1, open + find_matching_paren(&source[open..])?)));
+    let after_params = params.map_or(header_end, |(_, close)| close + 1);
+    let where_start = find_keyword(&source[after_params..header_end], "where")
+        .map_or(header_end, |idx| after_params + idx);
+
+    let within = |range: Option<(usize, usize)>, idx: usize| {
+        range.filter(|(start, end)| (*start..*end).contains(&idx))
+    };
+
+    source
+        .match_indices(lifetime)
+        .map(|(idx, _)| idx)
+        .filter(|&idx| {
+            idx > fn_start
+                && source[..idx]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|c| !is_ident(c))
+                && source[idx + lifetime.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| !is_ident(c) && c != '\'')
+                && is_valid_code_context(source, idx)
+        })
+        .map(|idx| {
+            let mut parameter = None;
+            let role = if let Some((start, end)) = within(generics, idx) {
+                let (segment_start, _) = top_level_segment(&source[start..end], idx - start);
+                if source[start + segment_start..idx].trim().is_empty() {
+                    "declaration"
+                } else {
+                    "bound"
+                }
+            } else if let Some((start, end)) = within(params, idx) {
+                let (segment_start, segment_end) =
+                    top_level_segment(&source[start..end], idx - start);
+                parameter = parameter_name(&source[start + segment_start..start + segment_end]);
+                "parameter"
+            } else if idx < where_start {
+                "return_type"
+            } else if idx < header_end {
+                "where_clause"
+            } else {
+                "body"
+            };
+            let line_start = source[..idx].rfind('\n').map_or(0, |pos| pos + 1);
+            LifetimeUse {
+                line: source[..idx].matches('\n').count(),
+                column: idx - line_start,
+                role,
+                parameter,
+            }
+        })
+        .collect()
+}
+
+/// The byte range of the comma-separated, top-level entry of `list` that
+/// contains `idx`.
+fn top_level_segment(list: &str, idx: usize) -> (usize, usize) {
+    let mut depth = 0i32;
+    let mut prev = '\0';
+    let mut start = 0;
+    for (pos, c) in list.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev != '-' => depth -= 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 && pos < idx => start = pos + 1,
+            ',' if depth == 0 => return (start, pos),
+            _ => {}
+        }
+        prev = c;
+    }
+    (start, list.len())
+}
+
+/// The name a function parameter binds: the pattern before its type, or
+/// `self` for the receiver.
+fn parameter_name(parameter: &str) -> Option<String> {
+    let pattern = match split_bound_colon(parameter) {
+        Some((pattern, _)) => pattern,
+        None => parameter,
+    };
+    let pattern = pattern.trim();
+    if pattern.contains(['(', '[', '{']) {
+        return Some(pattern.to_string());
+    }
+    let name = pattern
+        .trim_start_matches(['&', '\''])
+        .split_whitespace()
+        .last()?;
+    Some(name.to_string())
+}
+
+/// The outlives bounds naming `lifetime`, as `longer: shorter` pairs.
+fn lifetime_relations(generics: &ItemGenerics, lifetime: &str) -> Vec<String> {
+    let mut relations = Vec::new();
+    for param in &generics.lifetimes {
+        for outlived in &param.outlives {
+            if param.name == lifetime || outlived == lifetime {
+                relations.push(format!("{}: {}", param.name, outlived));
+            }
+        }
+    }
+    for param in &generics.type_parameters {
+        for bound in param.bounds.iter().chain(&param.where_bounds) {
+            if bound == lifetime {
+                relations.push(format!("{}: {}", param.name, bound));
+            }
+        }
+    }
+    for predicate in &generics.where_clause {
+        let bounded_is_lifetime = predicate.bounded == lifetime;
+        for bound in &predicate.bounds {
+            if (bounded_is_lifetime || bound == lifetime)
+                && !relations.contains(&format!("{}: {}", predicate.bounded, bound))
+            {
+                relations.push(format!("{}: {}", predicate.bounded, bound));
+            }
+        }
+    }
+    relations
+}
+
+pub async fn describe_function_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let hover = analyzer.get_hover(file_path, line, character).await?;
+    let mut signature = parse_hover_signature(&hover).map(|sig| (sig, "hover"));
+
+    // Hover on a call through a closure or fn pointer shows the variable, not
+    // a `fn`; signature help inside the call's parentheses still knows it.
+    if signature.is_none()
+        && let Some(arg_char) = file_content
+            .lines()
+            .nth(line as usize)
+            .and_then(|text| call_arguments_column(text, character as usize, symbol))
+        && let Some(label) = analyzer
+            .signature_help(file_path, line, arg_char as u32)
+            .await?
+    {
+        signature = parse_hover_signature(&format!("```rust\n{label}\n```"))
+            .map(|sig| (sig, "signature_help"));
+    }
+
+    let (signature, source) = signature
+        .ok_or_else(|| anyhow::anyhow!("`{}` does not resolve to a function signature", symbol))?;
+    let mut result = serde_json::to_value(describe_signature(&signature))?;
+    result["source"] = json!(source);
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn optimization_attributes_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let generics = parse_item_generics(&source)
+        .filter(|generics| generics.kind == "fn")
+        .ok_or_else(|| anyhow::anyhow!("`{}` does not resolve to a function", symbol))?;
+    let (attributes, other_attributes) = classify_attributes(&outer_attributes(&source));
+
+    // Type and const parameters are monomorphized; lifetimes are erased.
+    let mut generic_because: Vec<String> = generics
+        .type_parameters
+        .iter()
+        .map(|param| format!("type parameter `{}`", param.name))
+        .chain(
+            generics
+                .const_parameters
+                .iter()
+                .map(|param| format!("const parameter `{}`", param.name)),
+        )
+        .collect();
+    if has_impl_trait_argument(&source) {
+        generic_because.push("`impl Trait` argument".to_string());
+    }
+    let ancestors = analyzer
+        .enclosing_symbols(&actual_path, range.start.clone())
+        .await
+        .unwrap_or_default();
+    // LSP `SymbolKind::Object` is how rust-analyzer reports impl blocks.
+    if let Some(block) = ancestors
+        .iter()
+        .rev()
+        .find(|ancestor| matches!(ancestor.kind, 11 | 19))
+    {
+        if block.kind == 11 {
+            generic_because.push(format!(
+                "declared in trait `{}`: a default body is instantiated per implementing type",
+                block.name
+            ));
+        } else if let Some((params, _)) = block
+            .name
+            .strip_prefix("impl")
+            .and_then(split_generic_params)
+        {
+            generic_because.extend(
+                params
+                    .iter()
+                    .filter(|param| !param.starts_with('\''))
+                    .map(|param| format!("parameter `{param}` of `{}`", block.name)),
+            );
+        }
+    }
+    let monomorphized = !generic_because.is_empty();
+
+    let has = |kind: &str| attributes.iter().any(|attribute| attribute.kind == kind);
+    let inline = attributes
+        .iter()
+        .find(|attribute| attribute.kind == "inline")
+        .map(|attribute| {
+            attribute
+                .value
+                .clone()
+                .unwrap_or_else(|| "hint".to_string())
+        });
+    let target_features: Vec<String> = attributes
+        .iter()
+        .filter(|attribute| attribute.kind == "target_feature")
+        .filter_map(|attribute| attribute.value.as_deref())
+        .flat_map(|features| {
+            features
+                .split(',')
+                .map(|feature| feature.trim().to_string())
+        })
+        .collect();
+    let exported = has("no_mangle") || has("export_name");
+    let public = strip_attributes(&strip_comments(&source))
+        .trim_start()
+        .starts_with("pub");
+
+    let mut notes = Vec::new();
+    if inline.as_deref() == Some("always") && !target_features.is_empty() {
+        notes.push(
+            "`#[inline(always)]` is rejected together with `#[target_feature]`; use `#[inline]`."
+                .to_string(),
+        );
+    }
+    if exported && monomorphized {
+        notes.push(
+            "Generic functions are always mangled, so the export attribute has no effect."
+                .to_string(),
+        );
+    }
+    if monomorphized && inline.is_none() {
+        notes.push(
+            "Generic: instantiated in each crate that uses it, so it can be inlined across crates without `#[inline]`."
+                .to_string(),
+        );
+    } else if public && inline.is_none() && !exported {
+        notes.push(
+            "Non-generic and without `#[inline]`: other crates can only inline it with LTO."
+                .to_string(),
+        );
+    }
+    if has("cold") && inline.is_some() {
+        notes.push(
+            "`#[cold]` together with an inline attribute: callers treat it as unlikely yet may still inline it."
+                .to_string(),
+        );
+    }
+    if has("track_caller") {
+        notes.push(
+            "`#[track_caller]` passes the caller's location as a hidden argument.".to_string(),
+        );
+    }
+    if attributes.iter().any(|attribute| attribute.cfg.is_some()) {
+        notes.push("Attributes with `cfg` only apply when that condition holds.".to_string());
+    }
+
+    let result = json!({
+        "file_path": actual_path,
+        "line": range.start.line + 1,
+        "function": generics.item,
+        "attributes": attributes,
+        "inline": inline,
+        "cold": has("cold"),
+        "exported": exported,
+        "target_features": target_features,
+        "monomorphized": monomorphized,
+        "generic_because": generic_because,
+        "other_attributes": other_attributes,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// An attribute that changes how a function is optimized, exported or
+/// code-generated.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct OptimizationAttribute {
+    /// `inline`, `cold`, `no_mangle`, `export_name`, `link_section`,
+    /// `target_feature`, `track_caller`, `optimize`, `naked` or `instruction_set`.
+    kind: String,
+    /// `always` for `#[inline(always)]`, the features of `#[target_feature]`,
+    /// the name of `#[export_name]`...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    /// The condition of the `#[cfg_attr]` applying it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cfg: Option<String>,
+    text: String,
+}
+
+/// The contents of the outer attributes heading an item's source, in order.
+fn outer_attributes(source: &str) -> Vec<String> {
+    let source = strip_comments(source);
+    let mut rest = source.trim_start();
+    let mut attributes = Vec::new();
+    while let Some(body) = rest.strip_prefix("#[") {
+        let mut depth = 1;
+        let Some(end) = body.char_indices().find_map(|(idx, c)| {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(idx)
+        }) else {
+            break;
+        };
+        attributes.push(collapse_whitespace(&body[..end]));
+        rest = body[end + 1..].trim_start();
+    }
+    attributes
+}
+
+/// Sorts attribute contents into optimization attributes, looking inside
+/// `cfg_attr` and `unsafe(..)`, and the names of all other attributes.
+fn classify_attributes(attributes: &[String]) -> (Vec<OptimizationAttribute>, Vec<String>) {
+    let mut relevant = Vec::new();
+    let mut other = Vec::new();
+    for attribute in attributes {
+        if let Some(args) = attribute
+            .strip_prefix("cfg_attr(")
+            .and_then(|args| args.strip_suffix(')'))
+        {
+            let mut parts = split_top_level(args).into_iter();
+            let condition = parts.next().unwrap_or_default();
+            for inner in parts {
+                let (found, _) = classify_attributes(std::slice::from_ref(&inner));
+                relevant.extend(found.into_iter().map(|found| OptimizationAttribute {
+                    cfg: Some(condition.clone()),
+                    ..found
+                }));
+            }
+            continue;
+        }
+
+        let inner = attribute
+            .strip_prefix("unsafe(")
+            .and_then(|inner| inner.strip_suffix(')'))
+            .unwrap_or(attribute)
+            .trim();
+        let name_end = inner
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(inner.len());
+        let name = &inner[..name_end];
+        let rest = inner[name_end..].trim();
+        let args = rest
+            .strip_prefix('(')
+            .and_then(|args| args.strip_suffix(')'))
+            .map(str::trim);
+        let assigned = rest
+            .strip_prefix('=')
+            .map(|value| value.trim().trim_matches('"').to_string());
+
+        let value = match name {
+            "inline" | "optimize" | "instruction_set" => args.map(str::to_string),
+            "export_name" | "link_section" => assigned,
+            "target_feature" => args.and_then(|args| {
+                let (_, features) = args.split_once('=')?;
+                Some(features.trim().trim_matches('"').to_string())
+            }),
+            "cold" | "no_mangle" | "track_caller" | "naked" => None,
+            _ => {
+                if name != "doc" {
+                    other.push(name.to_string());
+                }
+                continue;
+            }
+        };
+        relevant.push(OptimizationAttribute {
+            kind: name.to_string(),
+            value,
+            cfg: None,
+            text: format!("#[{attribute}]"),
+        });
+    }
+    (relevant, other)
+}
+
+/// Whether a function's parameter list takes an `impl Trait` argument, which
+/// makes it generic.
+fn has_impl_trait_argument(source: &str) -> bool {
+    let source = strip_attributes(&strip_comments(source));
+    let Some(fn_idx) = find_keyword(&source, "fn") else {
+        return false;
+    };
+    let header = &source[fn_idx..];
+    let Some(open) = header.find('(') else {
+        return false;
+    };
+    let close = find_matching_paren(&header[open..])
+        .map(|close| open + close)
+        .unwrap_or(header.len());
+    find_keyword(&header[open..close], "impl").is_some()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FunctionDescription {
+    name: String,
+    owner: Option<String>,
+    is_async: bool,
+    is_unsafe: bool,
+    is_const: bool,
+    is_generic: bool,
+    receiver: Option<FunctionReceiver>,
+    parameters: Vec<FunctionParam>,
+    return_type: Option<String>,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FunctionReceiver {
+    text: String,
+    passing: Passing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FunctionParam {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    passing: Passing,
+    /// Whether the binding is declared `mut`.
+    mutable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Passing {
+    Owned,
+    Ref,
+    MutRef,
+    RawPointer,
+}
+
+impl Passing {
+    fn of_type(ty: &str) -> Self {
+        let Some(referent) = ty.strip_prefix('&') else {
+            return if ty.starts_with("*const ") || ty.starts_with("*mut ") {
+                Passing::RawPointer
+            } else {
+                Passing::Owned
+            };
+        };
+        // Skip an explicit lifetime: `&'a mut T`.
+        let referent = match referent.strip_prefix('\'') {
+            Some(lifetime) => lifetime
+                .split_once(char::is_whitespace)
+                .map_or("", |(_, rest)| rest),
+            None => referent,
+        };
+        if referent.trim_start().starts_with("mut ") {
+            Passing::MutRef
+        } else {
+            Passing::Ref
+        }
+    }
+}
+
+fn describe_signature(signature: &HoverSignature) -> FunctionDescription {
+    let receiver = signature.receiver.as_ref().map(|text| {
+        // `self: Box<Self>` carries its type; `&self`/`&mut self`/`self` are shorthand.
+        let ty = text.split_once(':').map_or(text.as_str(), |(_, ty)| ty.trim());
+        FunctionReceiver {
+            text: text.clone(),
+            passing: Passing::of_type(ty),
+        }
+    });
+
+    let parameters = signature
+        .param_names
+        .iter()
+        .zip(&signature.param_types)
+        .map(|(name, ty)| {
+            let (mutable, name) = match name.strip_prefix("mut ") {
+                Some(name) => (true, name.trim()),
+                None => (false, name.as_str()),
+            };
+            FunctionParam {
+                name: name.to_string(),
+                ty: ty.clone(),
+                passing: Passing::of_type(ty),
+                mutable,
+            }
+        })
+        .collect();
+
+    FunctionDescription {
+        name: signature.name.clone(),
+        owner: signature.owner.clone(),
+        is_async: signature.is_async,
+        is_unsafe: signature.is_unsafe,
+        is_const: signature.is_const,
+        is_generic: signature.is_generic,
+        receiver,
+        parameters,
+        return_type: signature.return_type.clone(),
+        signature: signature.signature.clone(),
+    }
+}
+
+/// Character column just inside the `(` of a call to `symbol` starting at
+/// `character` on `line`, allowing a turbofish in between.
+fn call_arguments_column(line: &str, character: usize, symbol: &str) -> Option<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut idx = character + symbol.chars().count();
+    let mut depth = 0;
+    while let Some(&c) = chars.get(idx) {
+        match c {
+            '(' if depth == 0 => return Some(idx + 1),
+            ':' | ' ' if depth == 0 => {}
+            '<' => depth += 1,
+            '>' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            _ => return None,
+        }
+        idx += 1;
+    }
+    None
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ItemGenerics {
+    kind: String,
+    item: String,
+    lifetimes: Vec<LifetimeParam>,
+    type_parameters: Vec<TypeParam>,
+    const_parameters: Vec<ConstParam>,
+    where_clause: Vec<WherePredicate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LifetimeParam {
+    name: String,
+    outlives: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TypeParam {
+    name: String,
+    bounds: Vec<String>,
+    /// Bounds placed on the parameter in the `where` clause.
+    where_bounds: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConstParam {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WherePredicate {
+    bounded: String,
+    bounds: Vec<String>,
+}
+
+const ITEM_KEYWORDS: [&str; 7] = ["fn", "struct", "enum", "union", "trait", "impl", "type"];
+
+/// Parses the generic parameter list and `where` clause of an item's source.
+fn parse_item_generics(source: &str) -> Option<ItemGenerics> {
+    let source = strip_attributes(&strip_comments(source));
+
+    let (kind, keyword_end) = find_item_keyword(&source)?;
+    let header = &source[keyword_end..];
+    let header = header[..find_header_end(header)].trim();
+
+    let (item, params, after_params) = if kind == "impl" {
+        let (params, rest) = split_generic_params(header)?;
+        let rest = rest.trim_start();
+        let name_end = find_keyword(rest, "where").unwrap_or(rest.len());
+        (collapse_whitespace(&rest[..name_end]), params, rest)
+    } else {
+        let name_end = header
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+            .unwrap_or(header.len());
+        let (params, rest) = split_generic_params(&header[name_end..])?;
+        (header[..name_end].to_string(), params, rest)
+    };
+
+    let where_clause: Vec<WherePredicate> = find_keyword(after_params, "where")
+        .map(|idx| {
+            split_top_level(&after_params[idx + "where".len()..])
+                .iter()
+                .filter_map(|predicate| {
+                    let (bounded, bounds) = split_bound_colon(predicate)?;
+                    Some(WherePredicate {
+                        bounded: collapse_whitespace(bounded),
+                        bounds: split_bounds(bounds),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut generics = ItemGenerics {
+        kind: kind.to_string(),
+        item,
+        lifetimes: Vec::new(),
+        type_parameters: Vec::new(),
+        const_parameters: Vec::new(),
+        where_clause: Vec::new(),
+    };
+
+    for param in params {
+        let (param, default) = match split_default(&param) {
+            Some((param, default)) => (param, Some(default.to_string())),
+            None => (param.as_str(), None),
+        };
+
+        if param.starts_with('\'') {
+            let (name, outlives) = split_bound_colon(param).unwrap_or((param, ""));
+            generics.lifetimes.push(LifetimeParam {
+                name: name.trim().to_string(),
+                outlives: split_bounds(outlives),
+            });
+        } else if let Some(rest) = param.strip_prefix("const ") {
+            let (name, ty) = rest.split_once(':').unwrap_or((rest, ""));
+            generics.const_parameters.push(ConstParam {
+                name: name.trim().to_string(),
+                ty: ty.trim().to_string(),
+                default,
+            });
+        } else {
+            let (name, bounds) = split_bound_colon(param).unwrap_or((param, ""));
+            let name = name.trim().to_string();
+            let where_bounds = where_clause
+                .iter()
+                .filter(|predicate| predicate.bounded == name)
+                .flat_map(|predicate| predicate.bounds.clone())
+                .collect();
+            generics.type_parameters.push(TypeParam {
+                name,
+                bounds: split_bounds(bounds),
+                where_bounds,
+                default,
+            });
+        }
+    }
+
+    generics.where_clause = where_clause;
+    Some(generics)
+}
+
+/// Finds the first item keyword that is not part of a longer identifier and
+/// returns it together with the offset just past it.
+fn find_item_keyword(source: &str) -> Option<(&'static str, usize)> {
+    let mut start = None;
+    for (idx, c) in source.char_indices().chain(std::iter::once((source.len(), ' '))) {
+        let is_ident = c.is_alphanumeric() || c == '_';
+        match (start, is_ident) {
+            (None, true) => start = Some(idx),
+            (Some(word_start), false) => {
+                let word = &source[word_start..idx];
+                if let Some(keyword) = ITEM_KEYWORDS.iter().find(|keyword| **keyword == word) {
+                    return Some((keyword, idx));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+        if start.is_none() && matches!(c, '{' | ';') {
+            return None;
+        }
+    }
+    None
+}
+
+/// Offset of the `{` or `;` that ends an item header, skipping nested
+/// brackets.
+fn find_header_end(header: &str) -> usize {
+    let mut depth = 0i32;
+    let mut prev = '\0';
+    for (idx, c) in header.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev != '-' => depth -= 1,
+            ')' | ']' => depth -= 1,
+            '{' | ';' if depth <= 0 => return idx,
+            _ => {}
+        }
+        prev = c;
+    }
+    header.len()
+}
+
+/// Splits a leading `<...>` parameter list off `text`.
+pub(crate) fn split_generic_params(text: &str) -> Option<(Vec<String>, &str)> {
+    let trimmed = text.trim_start();
+    match trimmed.strip_prefix('<') {
+        Some(params) => {
+            let end = find_closing_angle(params)?;
+            Some((split_top_level(&params[..end]), &params[end + 1..]))
+        }
+        None => Some((Vec::new(), text)),
+    }
+}
+
+fn find_keyword(text: &str, keyword: &str) -> Option<usize> {
+    text.match_indices(keyword).map(|(idx, _)| idx).find(|&idx| {
+        let before = text[..idx].chars().next_back();
+        let after = text[idx + keyword.len()..].chars().next();
+        before.is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+            && after.is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+    })
+}
+
+/// Splits `T: Bound` at the first top-level `:` that is not part of a `::`.
+fn split_bound_colon(text: &str) -> Option<(&str, &str)> {
+    let bytes = text.as_bytes();
+    let mut depth = 0i32;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ':' if depth == 0 => {
+                let prev_colon = idx > 0 && bytes[idx - 1] == b':';
+                let next_colon = bytes.get(idx + 1) == Some(&b':');
+                if !prev_colon && !next_colon {
+                    return Some((&text[..idx], &text[idx + 1..]));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits `T = Default` at a top-level `=` (ignoring `==`, `=>` and `->`).
+fn split_default(text: &str) -> Option<(&str, &str)> {
+    let mut depth = 0i32;
+    let mut prev = '\0';
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev != '-' && prev != '=' => depth -= 1,
+            ')' | ']' => depth -= 1,
+            '=' if depth == 0 && !text[idx + 1..].starts_with(['=', '>']) => {
+                return Some((text[..idx].trim(), text[idx + 1..].trim()));
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// Splits a bound list such as `Clone + Iterator<Item = (A, B)> + 'a` at its
+/// top-level `+` signs.
+fn split_bounds(bounds: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    let mut prev = '\0';
+
+    for c in bounds.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' if prev != '-' => depth -= 1,
+            ')' | ']' => depth -= 1,
+            '+' if depth == 0 => {
+                let part = collapse_whitespace(&current);
+                if !part.is_empty() {
+                    parts.push(part);
+                }
+                current.clear();
+                prev = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        prev = c;
+    }
+
+    let part = collapse_whitespace(&current);
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn find_closing_angle(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut prev = '\0';
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' if prev != '-' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+/// `text`, trimmed, without a leading `pub` or `pub(...)`.
+pub(crate) fn strip_visibility(text: &str) -> &str {
+    let text = text.trim();
+    let Some(rest) = text.strip_prefix("pub") else {
+        return text;
+    };
+    if let Some(scoped) = rest.trim_start().strip_prefix('(') {
+        return scoped.find(')').map_or(text, |end| scoped[end + 1..].trim_start());
+    }
+    if rest.starts_with(char::is_whitespace) {
+        rest.trim_start()
+    } else {
+        text
+    }
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    output.push(escaped);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                for skipped in chars.by_ref() {
+                    if skipped == '\n' {
+                        output.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut depth = 1;
+                let mut prev = '\0';
+                for skipped in chars.by_ref() {
+                    match (prev, skipped) {
+                        ('/', '*') => {
+                            depth += 1;
+                            prev = '\0';
+                            continue;
+                        }
+                        ('*', '/') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                            prev = '\0';
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    prev = skipped;
+                }
+                output.push(' ');
+            }
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+fn strip_attributes(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("#[") {
+        output.push_str(&rest[..start]);
+        let mut depth = 0;
+        let mut end = rest.len();
+        for (idx, c) in rest[start + 1..].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = start + 1 + idx + 1;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        output.push(' ');
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ImplHeader {
+    Derive(Vec<String>),
+    Trait {
+        trait_path: String,
+        /// The type after `for`, without a `where` clause.
+        self_type: String,
+        generic: bool,
+        /// `impl<T: Bound> Trait for T`: applies to every type meeting the bounds.
+        blanket: bool,
+        negative: bool,
+    },
+}
+
+pub(crate) fn last_path_segment(path: &str) -> &str {
+    let path = path.split('<').next().unwrap_or(path).trim();
+    path.rsplit("::").next().unwrap_or(path).trim()
+}
+
+/// Collects the `impl` header (or derive attribute) starting at `line`, up to
+/// the opening brace.
+fn read_impl_header(content: &str, line: usize) -> Option<String> {
+    let mut header = String::new();
+    for text in content.lines().skip(line).take(8) {
+        let text = text.trim();
+        if text.starts_with("#[") {
+            return Some(text.to_string());
+        }
+        if let Some(idx) = text.find('{') {
+            header.push_str(&text[..idx]);
+            break;
+        }
+        header.push_str(text);
+        header.push(' ');
+    }
+
+    let header = header.split_whitespace().collect::<Vec<_>>().join(" ");
+    if header.is_empty() { None } else { Some(header) }
+}
+
+pub(crate) fn parse_impl_header(header: &str) -> Option<ImplHeader> {
+    if let Some(rest) = header.strip_prefix("#[derive(") {
+        let list = rest.trim_end_matches(']').trim_end_matches(')');
+        return Some(ImplHeader::Derive(
+            list.split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+        ));
+    }
+
+    let rest = header.trim_start_matches("unsafe ").strip_prefix("impl")?;
+    let generic = rest.starts_with('<');
+    let (params, rest) = split_generic_params(rest)?;
+
+    let (trait_part, self_part) = rest.split_once(" for ")?;
+    let trait_part = trait_part.trim();
+    let negative = trait_part.starts_with('!');
+    let self_type = self_part
+        .split(" where ")
+        .next()
+        .unwrap_or(self_part)
+        .trim();
+    let bare_self = self_type
+        .trim_start_matches('&')
+        .trim_start_matches("mut ")
+        .trim();
+    let blanket = params.iter().any(|param| {
+        split_bound_colon(param)
+            .map_or(param.as_str(), |(name, _)| name)
+            .trim()
+            == bare_self
+    });
+
+    Some(ImplHeader::Trait {
+        trait_path: trait_part.trim_start_matches('!').to_string(),
+        self_type: self_type.to_string(),
+        generic,
+        blanket,
+        negative,
+    })
+}
+
+pub async fn struct_layout_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let use_compiler = args
+        .get("use_compiler")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+
+    let definition = parse_struct_source(&source)
+        .ok_or_else(|| anyhow::anyhow!("`{}` does not resolve to a struct definition", symbol))?;
+
+    let mut notes = Vec::new();
+    let mut exact = Vec::new();
+    if !use_compiler {
+        notes.push("Compiler layout skipped (use_compiler = false).".to_string());
+    } else if !detect_toolchain_channel().is_nightly_like() {
+        notes.push(
+            "Exact layouts need a nightly toolchain for `-Zprint-type-sizes`; showing a static estimate."
+                .to_string(),
+        );
+    } else {
+        match compiler_layouts(&actual_path, &definition.name).await {
+            Ok(layouts) if layouts.is_empty() => notes.push(format!(
+                "rustc printed no layout for `{}`: only types whose layout codegen needs are reported, so unused or never-instantiated types are missing.",
+                definition.name
+            )),
+            Ok(layouts) => exact = layouts,
+            Err(e) => notes.push(format!("Compiler layout unavailable: {e:#}")),
+        }
+    }
+
+    let (precision, layouts) = if exact.is_empty() {
+        let estimate = estimate_struct_layout(&definition);
+        notes.extend(estimate.notes.iter().cloned());
+        ("estimated", vec![serde_json::to_value(&estimate)?])
+    } else {
+        let layouts = exact
+            .iter()
+            .map(|layout| exact_layout_json(layout, &definition))
+            .collect();
+        ("exact", layouts)
+    };
+
+    let result = json!({
+        "file_path": actual_path,
+        "line": range.start.line + 1,
+        "name": definition.name,
+        "repr": definition.repr,
+        "precision": precision,
+        "layouts": layouts,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Builds the crate owning `file_path` with `-Zprint-type-sizes` and returns
+/// the printed layouts of the type named `name` (one per instantiation).
+async fn compiler_layouts(file_path: &str, name: &str) -> Result<Vec<TypeLayout>> {
+    let file_path = Path::new(file_path);
+    let manifest_path = file_path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+        .ok_or_else(|| anyhow::anyhow!("No Cargo.toml above {}", file_path.display()))?;
+    let crate_dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let mut cargo_args = Vec::new();
+    if crate_dir.join("src/lib.rs").is_file() && !file_path.starts_with(crate_dir.join("src/bin")) {
+        cargo_args.push("--lib".to_string());
+    }
+
+    // A fresh cfg value changes cargo's fingerprint, so the crate is rebuilt
+    // and rustc prints the sizes again instead of cargo reporting it fresh.
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let request = RunRequest {
+        manifest_path: Some(manifest_path.clone()),
+        cargo_args,
+        additional_rustc_args: vec![
+            "-Zprint-type-sizes".to_string(),
+            format!("--cfg=rust_mcp_layout=\"{nonce}\""),
+        ],
+        ..RunRequest::default()
+    };
+
+    let runner = CompilerRunner::with_target_dir(crate_dir.join(DEFAULT_TARGET_DIR));
+    let result = runner.run(request, &InspectionLimits::default()).await?;
+    if !result.status.success() {
+        let stderr: Vec<_> = result.stderr.lines().rev().take(20).collect();
+        let stderr: Vec<_> = stderr.into_iter().rev().collect();
+        anyhow::bail!(
+            "`{}` failed:\n{}",
+            result.command.join(" "),
+            stderr.join("\n")
+        );
+    }
+
+    Ok(parse_type_sizes(&result.stdout)
+        .into_iter()
+        .filter(|layout| is_layout_of(layout, name))
+        .collect())
+}
+
+/// A compiler layout with each field's declared type filled in.
+fn exact_layout_json(layout: &TypeLayout, definition: &StructDefinition) -> Value {
+    let field_type = |name: &str| {
+        definition
+            .fields
+            .iter()
+            .enumerate()
+            .find(|(idx, field)| field.name.as_deref().unwrap_or(&idx.to_string()) == name)
+            .map(|(_, field)| field.ty.clone())
+    };
+
+    let fields: Vec<Value> = layout
+        .fields
+        .iter()
+        .map(|field| {
+            json!({
+                "name": field.name,
+                "type": field_type(&field.name),
+                "offset": field.offset,
+                "size": field.size,
+                "align": field.align,
+                "padding_before": field.padding_before
+            })
+        })
+        .collect();
+    let field_bytes: u64 = layout.fields.iter().map(|field| field.size).sum();
+
+    json!({
+        "type": layout.type_name,
+        "size": layout.size,
+        "align": layout.align,
+        "padding": layout.size.saturating_sub(field_bytes),
+        "end_padding": layout.end_padding,
+        "fields": fields
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct StructDefinition {
+    pub name: String,
+    pub generics: Vec<String>,
+    /// Hints from `#[repr(..)]`, e.g. `C`, `packed(2)`, `align(16)`.
+    pub repr: Vec<String>,
+    /// Tuple-struct fields have no name.
+    pub fields: Vec<VariantField>,
+}
+
+/// Parses a struct definition (as returned by `get_symbol_source`) into its
+/// repr hints and fields. Returns `None` when the source is not a struct.
+pub(crate) fn parse_struct_source(source: &str) -> Option<StructDefinition> {
+    let source = strip_comments(source);
+    let repr = repr_hints(&source);
+    let source = strip_attributes(&source);
+
+    let struct_idx = source
+        .split_whitespace()
+        .position(|word| word == "struct")?;
+    let after_keyword = source
+        .split_whitespace()
+        .skip(struct_idx + 1)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let name_end = after_keyword
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+        .unwrap_or(after_keyword.len());
+    let name = after_keyword[..name_end].to_string();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut rest = after_keyword[name_end..].trim_start();
+    let mut generics = Vec::new();
+    if let Some(params) = rest.strip_prefix('<') {
+        let close = find_closing_angle(params)?;
+        generics = split_top_level(&params[..close]);
+        rest = params[close + 1..].trim_start();
+    }
+
+    let fields = if let Some(body) = rest.strip_prefix('(') {
+        let end = body.rfind(')')?;
+        split_top_level(&body[..end])
+            .into_iter()
+            .map(|ty| VariantField {
+                name: None,
+                ty: strip_visibility(&ty).to_string(),
+            })
+            .collect()
+    } else if let Some(open) = rest.find('{') {
+        let close = rest.rfind('}')?;
+        split_top_level(&rest[open + 1..close])
+            .into_iter()
+            .filter_map(|field| {
+                let (field_name, ty) = strip_visibility(&field).split_once(':')?;
+                Some(VariantField {
+                    name: Some(field_name.trim().to_string()),
+                    ty: ty.trim().to_string(),
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Some(StructDefinition {
+        name,
+        generics,
+        repr,
+        fields,
+    })
+}
+
+fn repr_hints(source: &str) -> Vec<String> {
+    let mut hints = Vec::new();
+    for (idx, _) in source.match_indices("#[repr(") {
+        let args = &source[idx + "#[repr(".len()..];
+        let Some(end) = args.find(")]") else {
+            continue;
+        };
+        hints.extend(split_top_level(&args[..end]));
+    }
+    hints
+}
+
+pub async fn find_recursive_types_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut nodes = Vec::new();
+    let mut lines = Vec::new();
+    for (file_idx, file) in files.iter().enumerate() {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        for (offset, source) in type_definition_sources(&content) {
+            if let Some(node) = type_node(source, file_idx) {
+                nodes.push(node);
+                lines.push(content[..offset].matches('\n').count() + 1);
+            }
+        }
+    }
+
+    let location = |idx: usize| {
+        json!({
+            "name": nodes[idx].name,
+            "kind": nodes[idx].kind,
+            "file_path": files[nodes[idx].file].to_string_lossy(),
+            "line": lines[idx]
+        })
+    };
+
+    let recursive: Vec<Value> = recursive_cycles(&nodes)
+        .iter()
+        .map(|cycle| {
+            let mut path: Vec<&str> = cycle
+                .iter()
+                .map(|edge| nodes[edge.from].name.as_str())
+                .collect();
+            path.push(&nodes[cycle[0].from].name);
+
+            let fields: Vec<Value> = cycle
+                .iter()
+                .map(|edge| {
+                    let (label, ty) = &nodes[edge.from].fields[edge.field];
+                    json!({
+                        "type": nodes[edge.from].name,
+                        "field": label,
+                        "field_type": ty,
+                        "contains": nodes[edge.to].name
+                    })
+                })
+                .collect();
+
+            // Boxing the edge that closes the cycle is enough to break it.
+            let closing = &cycle[cycle.len() - 1];
+            let (label, ty) = &nodes[closing.from].fields[closing.field];
+            let mut fix = location(closing.from);
+            fix["field"] = json!(label);
+            fix["current_type"] = json!(ty);
+            fix["suggested_type"] = json!(boxed_field_type(ty, &closing.via));
+            fix["indirection"] = json!("Box");
+
+            json!({
+                "types": cycle.iter().map(|edge| location(edge.from)).collect::<Vec<_>>(),
+                "cycle": path.join(" -> "),
+                "fields": fields,
+                "fix": fix
+            })
+        })
+        .collect();
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "types_scanned": nodes.len(),
+        "recursive_types": recursive,
+        "heuristic": "Types are matched by name, preferring a definition in the same file; fields reached through Box, Rc, Arc, Vec, other heap collections, references and pointers are indirect, while Option, Result, Cell, RefCell, Mutex, tuples and arrays store their contents inline. Generic arguments of other types are not followed. Use Rc or Arc instead of Box when the value must be shared."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Containers that keep their contents behind a pointer (or store none), so a
+/// field of these types does not make its type infinitely sized.
+const INDIRECT_CONTAINERS: &[&str] = &[
+    "Box",
+    "Rc",
+    "Arc",
+    "Weak",
+    "NonNull",
+    "Vec",
+    "VecDeque",
+    "LinkedList",
+    "BinaryHeap",
+    "HashMap",
+    "HashSet",
+    "BTreeMap",
+    "BTreeSet",
+    "Cow",
+    "PhantomData",
+];
+
+/// Wrappers that store their type arguments inline.
+const INLINE_WRAPPERS: &[&str] = &[
+    "Option",
+    "Result",
+    "Cell",
+    "RefCell",
+    "UnsafeCell",
+    "OnceCell",
+    "OnceLock",
+    "Mutex",
+    "RwLock",
+    "ManuallyDrop",
+    "MaybeUninit",
+    "Wrapping",
+    "Reverse",
+    "Pin",
+];
+
+/// A struct or enum with its fields, as scanned by `find_recursive_types`.
+#[derive(Debug)]
+struct TypeNode {
+    name: String,
+    kind: &'static str,
+    /// Index of the file defining the type.
+    file: usize,
+    /// `(label, type)` per field; enum fields are labelled `Variant.field`.
+    fields: Vec<(String, String)>,
+}
+
+/// A field of `from` storing `to` inline, spelled `via` in the field type.
+#[derive(Debug, Clone, PartialEq)]
+struct InlineEdge {
+    from: usize,
+    field: usize,
+    to: usize,
+    via: String,
+}
+
+/// The struct and enum definitions in `content` as `(offset, source)` pairs,
+/// each running from the keyword to the closing `}` or `;`.
+fn type_definition_sources(content: &str) -> Vec<(usize, &str)> {
+    let tokens: Vec<_> = tokenize(content)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, "whitespace" | "comment" | "doc_comment"))
+        .collect();
+
+    let mut sources = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        let keyword = &content[token.start..token.end];
+        if token.kind != "keyword" || !matches!(keyword, "struct" | "enum") {
+            continue;
+        }
+        if tokens
+            .get(idx + 1)
+            .is_none_or(|name| name.kind != "identifier")
+        {
+            continue;
+        }
+
+        let mut depth = 0;
+        for next in &tokens[idx + 2..] {
+            match &content[next.start..next.end] {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" => depth -= 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        sources.push((token.start, &content[token.start..next.end]));
+                        break;
+                    }
+                }
+                ";" if depth == 0 => {
+                    sources.push((token.start, &content[token.start..next.end]));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+    sources
+}
+
+/// Parses a struct or enum definition with the `struct_layout` and
+/// `describe_enum` parsers.
+fn type_node(source: &str, file: usize) -> Option<TypeNode> {
+    let label = |name: &Option<String>, idx: usize| name.clone().unwrap_or_else(|| idx.to_string());
+
+    if source.starts_with("enum") {
+        let description = parse_enum_source(source)?;
+        let fields = description
+            .variants
+            .iter()
+            .flat_map(|variant| {
+                variant.fields.iter().enumerate().map(|(idx, field)| {
+                    (
+                        format!("{}.{}", variant.name, label(&field.name, idx)),
+                        field.ty.clone(),
+                    )
+                })
+            })
+            .collect();
+        return Some(TypeNode {
+            name: description.name,
+            kind: "enum",
+            file,
+            fields,
+        });
+    }
+
+    let definition = parse_struct_source(source)?;
+    let fields = definition
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| (label(&field.name, idx), field.ty.clone()))
+        .collect();
+    Some(TypeNode {
+        name: definition.name,
+        kind: "struct",
+        file,
+        fields,
+    })
+}
+
+/// Names of the types a field of type `ty` stores inline: through tuples,
+/// arrays and `INLINE_WRAPPERS`, stopping at references, pointers, trait
+/// objects and `INDIRECT_CONTAINERS`. Other generic types contribute only
+/// their own name.
+fn inline_type_names(ty: &str) -> Vec<String> {
+    let ty = ty.trim();
+    let first_word = ty
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or("");
+    if ty.starts_with(['&', '*', '<', '!'])
+        || matches!(
+            first_word,
+            "fn" | "dyn" | "impl" | "unsafe" | "extern" | "for"
+        )
+    {
+        return Vec::new();
+    }
+
+    if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        return split_top_level(inner)
+            .iter()
+            .flat_map(|element| inline_type_names(element))
+            .collect();
+    }
+    if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        // A slice is unsized rather than inline; only `[T; N]` counts.
+        return inner
+            .rsplit_once(';')
+            .map_or_else(Vec::new, |(element, _)| inline_type_names(element));
+    }
+
+    let (path, arguments) = match ty.find('<') {
+        Some(open) => match find_closing_angle(&ty[open + 1..]) {
+            Some(close) => (
+                &ty[..open],
+                split_top_level(&ty[open + 1..open + 1 + close]),
+            ),
+            None => return Vec::new(),
+        },
+        None => (ty, Vec::new()),
+    };
+    let name = path.rsplit("::").next().unwrap_or(path).trim();
+
+    if name.is_empty() || INDIRECT_CONTAINERS.contains(&name) {
+        Vec::new()
+    } else if INLINE_WRAPPERS.contains(&name) {
+        arguments
+            .iter()
+            .filter(|argument| !argument.starts_with('\''))
+            .flat_map(|argument| inline_type_names(argument))
+            .collect()
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+/// One cycle of inline fields per group of recursive types, found by a
+/// breadth-first search from each type back to itself so the shortest cycle
+/// is reported. A type already part of a reported cycle starts no search.
+fn recursive_cycles(nodes: &[TypeNode]) -> Vec<Vec<InlineEdge>> {
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, node) in nodes.iter().enumerate() {
+        by_name.entry(node.name.as_str()).or_default().push(idx);
+    }
+
+    let mut edges: Vec<Vec<InlineEdge>> = Vec::with_capacity(nodes.len());
+    for (from, node) in nodes.iter().enumerate() {
+        let mut outgoing = Vec::new();
+        for (field, (_, ty)) in node.fields.iter().enumerate() {
+            for via in inline_type_names(ty) {
+                let name = if via == "Self" {
+                    node.name.as_str()
+                } else {
+                    via.as_str()
+                };
+                let Some(candidates) = by_name.get(name) else {
+                    continue;
+                };
+                let same_file = candidates.iter().find(|&&idx| nodes[idx].file == node.file);
+                let to = match (same_file, candidates.as_slice()) {
+                    (Some(&idx), _) => idx,
+                    (None, [only]) => *only,
+                    _ => continue,
+                };
+                outgoing.push(InlineEdge {
+                    from,
+                    field,
+                    to,
+                    via,
+                });
+            }
+        }
+        edges.push(outgoing);
+    }
+
+    let mut reported = vec![false; nodes.len()];
+    let mut cycles = Vec::new();
+    for start in 0..nodes.len() {
+        if reported[start] || edges[start].is_empty() {
+            continue;
+        }
+
+        // `reached_by[n]` is the edge index (in its source's list) first reaching `n`.
+        let mut reached_by: Vec<Option<(usize, usize)>> = vec![None; nodes.len()];
+        let mut queue = VecDeque::from([start]);
+        let mut closing = None;
+        'search: while let Some(current) = queue.pop_front() {
+            for (edge_idx, edge) in edges[current].iter().enumerate() {
+                if edge.to == start {
+                    closing = Some((current, edge_idx));
+                    break 'search;
+                }
+                if reached_by[edge.to].is_none() {
+                    reached_by[edge.to] = Some((current, edge_idx));
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+
+        let Some(mut step) = closing else {
+            continue;
+        };
+        let mut cycle = Vec::new();
+        loop {
+            let (node, edge_idx) = step;
+            cycle.push(edges[node][edge_idx].clone());
+            reported[node] = true;
+            match reached_by[node] {
+                Some(previous) if node != start => step = previous,
+                _ => break,
+            }
+        }
+        cycle.reverse();
+        cycles.push(cycle);
+    }
+    cycles
+}
+
+/// `ty` with the inline use of `name` (path and generic arguments included)
+/// wrapped in `Box`, e.g. `Option<Node<T>>` becomes `Option<Box<Node<T>>>`.
+fn boxed_field_type(ty: &str, name: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let found = ty.match_indices(name).find(|(idx, _)| {
+        !ty[..*idx].ends_with(is_ident) && !ty[idx + name.len()..].starts_with(is_ident)
+    });
+    let Some((idx, _)) = found else {
+        return format!("Box<{ty}>");
+    };
+
+    let start = ty[..idx]
+        .trim_end_matches(|c: char| is_ident(c) || c == ':')
+        .len();
+    let mut end = idx + name.len();
+    if let Some(arguments) = ty[end..].strip_prefix('<')
+        && let Some(close) = find_closing_angle(arguments)
+    {
+        end += close + 2;
+    }
+    format!("{}Box<{}>{}", &ty[..start], &ty[start..end], &ty[end..])
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EstimatedLayout {
+    pub size: Option<u64>,
+    pub align: Option<u64>,
+    /// Bytes of the size not covered by fields.
+    pub padding: Option<u64>,
+    pub fields: Vec<EstimatedField>,
+    #[serde(skip)]
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EstimatedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub size: Option<u64>,
+    pub align: Option<u64>,
+    /// Only determinable when fields keep declaration order (`repr(C)`,
+    /// `packed`, `transparent`).
+    pub offset: Option<u64>,
+}
+
+/// Best-effort layout from the field types alone, for a 64-bit target.
+/// Unknown field types leave the totals (and later offsets) undetermined.
+pub(crate) fn estimate_struct_layout(definition: &StructDefinition) -> EstimatedLayout {
+    let hint_value = |name: &str| {
+        definition.repr.iter().find_map(|hint| {
+            let hint = hint.trim();
+            if hint == name {
+                return Some(None);
+            }
+            let value = hint
+                .strip_prefix(name)?
+                .trim()
+                .strip_prefix('(')?
+                .strip_suffix(')')?;
+            Some(value.trim().parse::<u64>().ok())
+        })
+    };
+    let packed = hint_value("packed").map(|value| value.unwrap_or(1));
+    let min_align = hint_value("align").flatten();
+    let ordered = packed.is_some()
+        || definition
+            .repr
+            .iter()
+            .any(|hint| matches!(hint.trim(), "C" | "transparent"));
+
+    let footprints: Vec<Option<(u64, u64)>> = definition
+        .fields
+        .iter()
+        .map(|field| {
+            let (size, align) = estimate_type(&field.ty)?;
+            Some((size, packed.map_or(align, |packed| align.min(packed))))
+        })
+        .collect();
+
+    let mut offsets = Vec::new();
+    let mut cursor = Some(0);
+    for footprint in &footprints {
+        let offset = match (ordered, cursor, footprint) {
+            (true, Some(cursor), Some((_, align))) => Some(round_up(cursor, *align)),
+            _ => None,
+        };
+        cursor = offset
+            .zip(*footprint)
+            .map(|(offset, (size, _))| offset + size);
+        offsets.push(offset);
+    }
+
+    let known: Option<Vec<(u64, u64)>> = footprints.iter().copied().collect();
+    let align = known.as_ref().map(|known| {
+        let natural = known.iter().map(|(_, align)| *align).max().unwrap_or(1);
+        natural.max(min_align.unwrap_or(1))
+    });
+    let field_bytes = known
+        .as_ref()
+        .map(|known| known.iter().map(|(size, _)| size).sum::<u64>());
+    // Without repr(C) the compiler sorts fields to minimize padding, which
+    // leaves none between fields for the alignments seen here.
+    let unpadded = if ordered { cursor } else { field_bytes };
+    let size = unpadded
+        .zip(align)
+        .map(|(bytes, align)| round_up(bytes, align));
+
+    let mut notes = vec!["Estimated from field types for a 64-bit target.".to_string()];
+    if !ordered && definition.fields.len() > 1 {
+        notes.push(
+            "The default repr lets the compiler reorder fields, so offsets are not determined; the size assumes fields sorted by alignment."
+                .to_string(),
+        );
+    }
+    let unknown: Vec<_> = definition
+        .fields
+        .iter()
+        .zip(&footprints)
+        .filter(|(_, footprint)| footprint.is_none())
+        .map(|(field, _)| format!("`{}`", field.ty))
+        .collect();
+    if !unknown.is_empty() {
+        notes.push(format!(
+            "Sizes of {} are unknown to the estimate; build on nightly for exact numbers.",
+            unknown.join(", ")
+        ));
+    }
+
+    let fields = definition
+        .fields
+        .iter()
+        .enumerate()
+        .zip(footprints.iter().zip(offsets))
+        .map(|((idx, field), (footprint, offset))| EstimatedField {
+            name: field.name.clone().unwrap_or_else(|| idx.to_string()),
+            ty: field.ty.clone(),
+            size: footprint.map(|(size, _)| size),
+            align: footprint.map(|(_, align)| align),
+            offset,
+        })
+        .collect();
+
+    EstimatedLayout {
+        size,
+        align,
+        padding: size.zip(field_bytes).map(|(size, bytes)| size - bytes),
+        fields,
+        notes,
+    }
+}
+
+fn round_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// Size and alignment of common field types on a 64-bit target, or `None`
+/// when the type is not known to the estimate (user types, generics, ...).
+fn estimate_type(ty: &str) -> Option<(u64, u64)> {
+    let ty = ty.trim();
+    if let Some(pointee) = ty.strip_prefix('&') {
+        return Some(pointer_to(pointee));
+    }
+    if let Some(pointee) = ty
+        .strip_prefix("*const ")
+        .or_else(|| ty.strip_prefix("*mut "))
+    {
+        return Some(pointer_to(pointee));
+    }
+    if ty.starts_with("fn(") || ty.starts_with("unsafe fn") || ty.starts_with("extern ") {
+        return Some((8, 8));
+    }
+    if let Some(inner) = ty
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+    {
+        let (element, len) = inner.rsplit_once(';')?;
+        let len: u64 = len.trim().parse().ok()?;
+        let (size, align) = estimate_type(element)?;
+        return Some((size * len, align));
+    }
+    if let Some(inner) = ty
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+    {
+        let members = split_top_level(inner)
+            .iter()
+            .map(|member| estimate_type(member))
+            .collect::<Option<Vec<_>>>()?;
+        let align = members.iter().map(|(_, align)| *align).max().unwrap_or(1);
+        return Some((
+            round_up(members.iter().map(|(size, _)| size).sum(), align),
+            align,
+        ));
+    }
+
+    let (path, args) = match ty.split_once('<') {
+        Some((path, args)) => (path, args.strip_suffix('>')?),
+        None => (ty, ""),
+    };
+    let name = last_path_segment(path);
+    let arg = split_top_level(args)
+        .into_iter()
+        .find(|arg| !arg.starts_with('\''));
+
+    if let Some(primitive) = primitive_footprint(name) {
+        return Some(primitive);
+    }
+    if let Some(int) = name.strip_prefix("NonZero") {
+        return match int {
+            "" => estimate_type(arg.as_deref()?),
+            _ => primitive_footprint(&int.to_ascii_lowercase()),
+        };
+    }
+    match name {
+        "Box" | "Rc" | "Arc" | "Weak" | "NonNull" => Some(pointer_to(arg.as_deref()?)),
+        "String" | "Vec" | "PathBuf" | "OsString" => Some((24, 8)),
+        "PhantomData" | "PhantomPinned" => Some((0, 1)),
+        "Cell" | "UnsafeCell" | "ManuallyDrop" | "MaybeUninit" | "Wrapping" | "Pin" => {
+            estimate_type(arg.as_deref()?)
+        }
+        "Option" => {
+            let inner = arg?;
+            let (size, align) = estimate_type(&inner)?;
+            if has_niche(&inner) {
+                Some((size, align))
+            } else if primitive_footprint(last_path_segment(&inner)).is_some() {
+                // A separate tag, padded to the payload's alignment.
+                Some((round_up(size + align, align), align))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn primitive_footprint(name: &str) -> Option<(u64, u64)> {
+    match name {
+        "()" => Some((0, 1)),
+        "u8" | "i8" | "bool" => Some((1, 1)),
+        "u16" | "i16" => Some((2, 2)),
+        "u32" | "i32" | "f32" | "char" => Some((4, 4)),
+        "u64" | "i64" | "f64" | "usize" | "isize" => Some((8, 8)),
+        "u128" | "i128" => Some((16, 16)),
+        _ => None,
+    }
+}
+
+/// Pointers to unsized types (`str`, slices, trait objects) are fat.
+fn pointer_to(pointee: &str) -> (u64, u64) {
+    let mut pointee = pointee.trim();
+    if pointee.starts_with('\'') {
+        pointee = pointee
+            .split_once(' ')
+            .map_or("", |(_, rest)| rest.trim_start());
+    }
+    let pointee = pointee.strip_prefix("mut ").unwrap_or(pointee).trim();
+    let is_slice = pointee.starts_with('[') && !pointee.contains(';');
+    if pointee == "str" || is_slice || pointee.starts_with("dyn ") {
+        (16, 8)
+    } else {
+        (8, 8)
+    }
+}
+
+/// Types with invalid bit patterns that `Option` stores its `None` in.
+fn has_niche(ty: &str) -> bool {
+    let ty = ty.trim();
+    let name = last_path_segment(ty);
+    ty.starts_with('&')
+        || ty.starts_with("fn(")
+        || name.starts_with("NonZero")
+        || matches!(
+            name,
+            "bool" | "char" | "Box" | "Rc" | "Arc" | "NonNull" | "String" | "Vec" | "PathBuf"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_impl_header() {
+        let trait_impl = |trait_path: &str, self_type: &str, flags: (bool, bool, bool)| {
+            Some(ImplHeader::Trait {
+                trait_path: trait_path.to_string(),
+                self_type: self_type.to_string(),
+                generic: flags.0,
+                blanket: flags.1,
+                negative: flags.2,
+            })
+        };
+        assert_eq!(
+            parse_impl_header("impl<T: Clone> fmt::Display for Wrapper<T>"),
+            trait_impl("fmt::Display", "Wrapper<T>", (true, false, false))
+        );
+        assert_eq!(
+            parse_impl_header("impl !Send for Guard"),
+            trait_impl("Send", "Guard", (false, false, true))
+        );
+        assert_eq!(
+            parse_impl_header("unsafe impl Sync for Ptr"),
+            trait_impl("Sync", "Ptr", (false, false, false))
+        );
+        assert_eq!(
+            parse_impl_header("impl<T: Display + ?Sized> ToString for T"),
+            trait_impl("ToString", "T", (true, true, false))
+        );
+        assert_eq!(
+            parse_impl_header("impl<'a, T> From<&'a [T]> for Stack<T> where T: Clone"),
+            trait_impl("From<&'a [T]>", "Stack<T>", (true, false, false))
+        );
+        assert_eq!(
+            parse_impl_header("impl<F: Fn() -> u8> Foo for F"),
+            trait_impl("Foo", "F", (true, true, false))
+        );
+        assert_eq!(parse_impl_header("impl Config"), None);
+
+        let content = "#[derive(Debug, Clone)]\nstruct Key;\n\nimpl<K, V> Extend<(K, V)>\n    for Map<K, V>\nwhere\n    K: Hash,\n{\n}\n";
+        let header = read_impl_header(content, 3).unwrap();
+        assert_eq!(
+            header,
+            "impl<K, V> Extend<(K, V)> for Map<K, V> where K: Hash,"
+        );
+        assert_eq!(
+            parse_impl_header(&header),
+            trait_impl("Extend<(K, V)>", "Map<K, V>", (true, false, false))
+        );
+        let derive = read_impl_header(content, 0).unwrap();
+        assert_eq!(
+            parse_impl_header(&derive),
+            Some(ImplHeader::Derive(vec![
+                "Debug".to_string(),
+                "Clone".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_impl_method_names() {
+        let content = "struct S;\n\nimpl Iterator for S {\n    type Item = u8;\n    /// fn doc() {}\n    fn next(&mut self) -> Option<u8> {\n        fn helper() {}\n        let s = \"fn fake() {\";\n        None\n    }\n\n    pub fn r#try(&self) {}\n}\n\nfn outside() {}\n";
+        let names: Vec<String> = impl_method_names(content, 2)
+            .into_iter()
+            .map(|(name, offset)| {
+                assert!(content[offset..].starts_with(&name));
+                name
+            })
+            .collect();
+        assert_eq!(names, vec!["next", "r#try"]);
+
+        assert_eq!(
+            signature_name("fn hash<H: Hasher>(&self, state: &mut H)"),
+            "hash"
+        );
+        assert_eq!(
+            derived_methods("Eq").map(|(_, methods)| methods.len()),
+            Some(0)
+        );
+        assert!(derived_methods("Serialize").is_none());
+    }
+
+    #[test]
+    fn test_scan_error_items() {
+        let content = r#"use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("config")]
+    Config {
+        #[from]
+        source: ConfigError,
+    },
+}
+
+#[derive(Debug)]
+pub struct ConfigError;
+
+impl std::error::Error for ConfigError {}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(_: toml::de::Error) -> Self {
+        ConfigError
+    }
+}
+
+impl serde::de::Error for ConfigError {}
+"#;
+        let items = scan_error_items(content);
+        let types: Vec<(&str, u32, &str)> = items
+            .iter()
+            .filter_map(|item| match item {
+                ErrorItem::Type {
+                    name,
+                    line,
+                    defined_by,
+                    ..
+                } => Some((name.as_str(), *line, *defined_by)),
+                ErrorItem::From { .. } => None,
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![("AppError", 3, "thiserror"), ("ConfigError", 16, "impl")]
+        );
+        assert!(matches!(
+            &items[3],
+            ErrorItem::Type {
+                anchor: Some(17),
+                ..
+            }
+        ));
+
+        let conversions: Vec<(&str, &str, &str, Option<&str>)> = items
+            .iter()
+            .filter_map(|item| match item {
+                ErrorItem::From { target, conversion } => Some((
+                    target.as_str(),
+                    conversion.from.as_str(),
+                    conversion.via,
+                    conversion.variant.as_deref(),
+                )),
+                ErrorItem::Type { .. } => None,
+            })
+            .collect();
+        assert_eq!(
+            conversions,
+            vec![
+                ("AppError", "std::io::Error", "#[from]", Some("Io")),
+                ("AppError", "ConfigError", "#[from]", None),
+                ("ConfigError", "toml::de::Error", "impl From", None),
+            ]
+        );
+
+        assert_eq!(implementing_type(content, 2), Some("AppError".to_string()));
+        assert_eq!(
+            implementing_type(content, 16),
+            Some("ConfigError".to_string())
+        );
+    }
+    #[test]
+    fn test_trace_question_mark_helpers() {
+        let content = "fn load(path: &str) -> Result<Config, LoadError> {\n    // is it there?\n    let text = fs::read_to_string(path)?;\n    let n = parse::<u8>(\"?\")?;\n    Ok(toml::from_str(&text)?)\n}\nfn size<T: ?Sized>(_: &T) {}\n";
+        let first = try_operator_offset(content, "read_to_string(path)?;", 1).unwrap();
+        assert_eq!(&content[first - 1..=first], ")?");
+        let third = try_operator_offset(content, content, 3).unwrap();
+        assert!(content[..third].ends_with("(&text)"));
+        assert!(try_operator_offset(content, content, 4).is_err());
+        assert!(try_operator_offset(content, "T: ?Sized", 1).is_err());
+        assert!(
+            try_operator_offset("macro_rules! m { ($($x:expr),* $(,)?) => {} }", "$(,)?", 1)
+                .is_err()
+        );
+
+        let hover = "```text\nTry Error Type: io::Error\nPropagated as:  LoadError\n```\n";
+        assert_eq!(
+            parse_try_hover(hover),
+            Some(TryPropagation {
+                errors_only: true,
+                source: "io::Error".to_string(),
+                target: "LoadError".to_string(),
+            })
+        );
+        assert_eq!(parse_try_hover("```rust\nString\n```"), None);
+
+        assert_eq!(
+            function_return_type(
+                "/// Loads.\n#[inline]\npub fn load<F: Fn(u8) -> u8>(f: F) -> Result<(), Box<dyn Error>>\nwhere\n    F: Copy,\n{\n    Ok(())\n}"
+            ),
+            Some("Result<(), Box<dyn Error>>".to_string())
+        );
+        assert_eq!(function_return_type("fn run(&self) {}"), None);
+
+        assert_eq!(
+            declared_error_type("Result<Config, LoadError>", ""),
+            Some("LoadError".to_string())
+        );
+        assert_eq!(
+            declared_error_type("io::Result<()>", ""),
+            Some("io::Error".to_string())
+        );
+        assert_eq!(
+            declared_error_type("Result<()>", "use anyhow::{Context, Result};"),
+            Some("anyhow::Error".to_string())
+        );
+        assert_eq!(
+            declared_error_type(
+                "Result<u8>",
+                "pub type Result<T> = std::result::Result<T, Error>;"
+            ),
+            Some("Error".to_string())
+        );
+        assert_eq!(declared_error_type("Option<u8>", ""), None);
+
+        assert!(blanket_conversion("anyhow::Error").is_some());
+        assert!(blanket_conversion("Box<dyn std::error::Error + Send + Sync>").is_some());
+        assert_eq!(blanket_conversion("LoadError"), None);
+    }
+    #[test]
+    fn test_classify_type_site() {
+        let classify = |line: &str| {
+            let start = line.find("Point").unwrap();
+            classify_type_site(line, start, start + "Point".len())
+        };
+        assert_eq!(
+            classify("    let p = Point { x: 1, y: 2 };"),
+            TypeSite::StructLiteral
+        );
+        assert_eq!(
+            classify("    points.push(Point(1, 2));"),
+            TypeSite::TupleConstructor
+        );
+        assert_eq!(
+            classify("    let p = Point::new(1, 2);"),
+            TypeSite::ConstructorCall("new".to_string())
+        );
+        assert_eq!(
+            classify("    Point::<f32>::with_origin()"),
+            TypeSite::ConstructorCall("with_origin".to_string())
+        );
+        assert_eq!(
+            classify("        return Point::Polar { r, theta };"),
+            TypeSite::Variant("Polar".to_string())
+        );
+        assert_eq!(
+            classify("    let coords = raw.map(Point);"),
+            TypeSite::Value
+        );
+        assert_eq!(classify("    let Point { x, y } = p;"), TypeSite::Pattern);
+        assert_eq!(
+            classify("    if let Some(Point(x, _)) = first {"),
+            TypeSite::Pattern
+        );
+        assert_eq!(classify("        Point { x, .. } => x,"), TypeSite::Pattern);
+        assert_eq!(classify("pub struct Point {"), TypeSite::TypeUse);
+        assert_eq!(classify("impl Display for Point {"), TypeSite::TypeUse);
+        assert_eq!(classify("fn origin() -> Point {"), TypeSite::TypeUse);
+        assert_eq!(classify("    let p: Point = make();"), TypeSite::TypeUse);
+        assert_eq!(
+            classify("    let all: Vec<Point> = Vec::new();"),
+            TypeSite::TypeUse
+        );
+        assert_eq!(classify("    Point::distance(&a, &b)"), TypeSite::TypeUse);
+        assert_eq!(classify("use crate::geo::Point;"), TypeSite::TypeUse);
+    }
+
+    #[test]
+    fn test_optimization_attributes() {
+        let source = r#"/// Sums lanes.
+#[inline(always)]
+#[cfg_attr(target_arch = "x86_64", target_feature(enable = "avx2,fma"))]
+#[unsafe(no_mangle)]
+#[must_use]
+#[doc(hidden)]
+pub fn sum(values: &[f32], extra: impl Into<f32>) -> impl Sized { 0.0 }"#;
+
+        let attributes = outer_attributes(source);
+        assert_eq!(attributes.len(), 5);
+        assert_eq!(attributes[0], "inline(always)");
+
+        let (relevant, other) = classify_attributes(&attributes);
+        let kinds: Vec<_> = relevant
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.kind.as_str(),
+                    attribute.value.as_deref(),
+                    attribute.cfg.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("inline", Some("always"), None),
+                (
+                    "target_feature",
+                    Some("avx2,fma"),
+                    Some("target_arch = \"x86_64\"")
+                ),
+                ("no_mangle", None, None),
+            ]
+        );
+        assert_eq!(other, vec!["must_use"]);
+
+        assert!(has_impl_trait_argument(source));
+        assert!(!has_impl_trait_argument(
+            "fn make() -> impl Iterator<Item = u8> { [].into_iter() }"
+        ));
+    }
+
+    #[test]
+    fn test_describe_signature() {
+        let hover = "```rust\nnet::Pool\n```\n\n```rust\nimpl<'a> Pool<'a>\npub const unsafe fn lease(&'a mut self, mut key: &str, buf: &'a mut [u8], raw: *const u8, n: usize) -> Option<Lease<'a>>\n```";
+        let signature = parse_hover_signature(hover).unwrap();
+        let description = describe_signature(&signature);
+
+        assert!(description.is_const && description.is_unsafe && !description.is_async);
+        assert_eq!(description.owner.as_deref(), Some("Pool"));
+        assert_eq!(description.receiver.as_ref().unwrap().passing, Passing::MutRef);
+        let params: Vec<_> = description
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.ty.as_str(), p.passing, p.mutable))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("key", "&str", Passing::Ref, true),
+                ("buf", "&'a mut [u8]", Passing::MutRef, false),
+                ("raw", "*const u8", Passing::RawPointer, false),
+                ("n", "usize", Passing::Owned, false),
+            ]
+        );
+        assert_eq!(description.return_type.as_deref(), Some("Option<Lease<'a>>"));
+
+        let boxed = parse_hover_signature("```rust\nfn run(self: Box<Self>)\n```").unwrap();
+        assert_eq!(
+            describe_signature(&boxed).receiver.unwrap().passing,
+            Passing::Owned
+        );
+    }
+
+    #[test]
+    fn test_call_arguments_column() {
+        assert_eq!(call_arguments_column("    let x = parse::<u8>(s);", 12, "parse"), Some(24));
+        assert_eq!(call_arguments_column("    handler (req)", 4, "handler"), Some(13));
+        assert_eq!(call_arguments_column("    let f = handler;", 12, "handler"), None);
+    }
+
+    #[test]
+    fn test_receiver_anchor() {
+        let content = "fn f() {\n    let n = self.items.len();\n    v.iter()\n        .map(g)\n        .collect::<Vec<_>>().go();\n    data[0].go();\n}\n";
+        assert_eq!(receiver_anchor(content, 1, 23), Some((1, 17)));
+        assert_eq!(receiver_anchor(content, 3, 9), Some((2, 6)));
+        assert_eq!(receiver_anchor(content, 4, 29), Some((4, 9)));
+        assert_eq!(receiver_anchor(content, 5, 12), None);
+        assert_eq!(receiver_anchor(content, 1, 12), None);
+    }
+
+    #[test]
+    fn test_enclosing_item_header_and_blanket_impls() {
+        let content = "pub trait Greet {\n    fn hi(&self) {}\n}\n\nimpl<T: Display> Greet for T\nwhere\n    T: Debug,\n{\n    fn hi(&self) {}\n}\n";
+        assert_eq!(
+            enclosing_item_header(content, 1),
+            Some((0, "pub trait Greet".to_string()))
+        );
+        let (line, header) = enclosing_item_header(content, 8).unwrap();
+        assert_eq!(line, 4);
+        match parse_impl_header(&header) {
+            Some(ImplHeader::Trait {
+                self_type,
+                blanket,
+                generic,
+                ..
+            }) => {
+                assert_eq!(self_type, "T");
+                assert!(blanket && generic);
+            }
+            _ => panic!("expected a trait impl: {header}"),
+        }
+        match parse_impl_header("impl<'a, T> From<&'a T> for Wrapper<T>") {
+            Some(ImplHeader::Trait {
+                self_type, blanket, ..
+            }) => {
+                assert_eq!(self_type, "Wrapper<T>");
+                assert!(!blanket);
+            }
+            _ => panic!("expected a trait impl"),
+        }
+    }
+
+    #[test]
+    fn test_parse_enum_source_variant_kinds() {
+        let source = r#"/// Events emitted by the watcher.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Nothing changed.
+    Idle,
+    Moved(PathBuf, Option<PathBuf>),
+    #[serde(rename = "err")]
+    Failed { path: PathBuf, pub(crate) error: Box<dyn Error + Send> },
+    Code = 4,
+}"#;
+
+        let description = parse_enum_source(source).unwrap();
+        assert_eq!(description.name, "Event");
+        assert!(description.generics.is_empty());
+
+        let kinds: Vec<_> = description.variants.iter().map(|v| (v.name.as_str(), v.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("Idle", VariantKind::Unit),
+                ("Moved", VariantKind::Tuple),
+                ("Failed", VariantKind::Struct),
+                ("Code", VariantKind::Unit),
+            ]
+        );
+
+        let moved: Vec<_> = description.variants[1].fields.iter().map(|f| f.ty.as_str()).collect();
+        assert_eq!(moved, vec!["PathBuf", "Option<PathBuf>"]);
+
+        let failed = &description.variants[2].fields;
+        assert_eq!(failed[0].name.as_deref(), Some("path"));
+        assert_eq!(failed[1].name.as_deref(), Some("error"));
+        assert_eq!(failed[1].ty, "Box<dyn Error + Send>");
+
+        assert_eq!(description.variants[3].discriminant.as_deref(), Some("4"));
+    }
+
+    #[test]
+    fn test_recursive_cycles() {
+        let content = r#"
+/// A list that cannot be sized.
+enum List {
+    Nil,
+    Cons(i32, List),
+}
+
+struct Tree {
+    children: Vec<Tree>,
+    parent: Option<Box<Tree>>,
+}
+
+struct Expr {
+    kind: ExprKind,
+}
+
+enum ExprKind {
+    Literal(i64),
+    Paren { inner: Option<crate::ast::Expr> },
+}
+
+struct Pair(Self, &'static str);
+"#;
+        let nodes: Vec<_> = type_definition_sources(content)
+            .into_iter()
+            .filter_map(|(_, source)| type_node(source, 0))
+            .collect();
+        let names: Vec<_> = nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["List", "Tree", "Expr", "ExprKind", "Pair"]);
+        assert_eq!(
+            nodes[0].fields[1],
+            ("Cons.1".to_string(), "List".to_string())
+        );
+
+        let cycles = recursive_cycles(&nodes);
+        let described: Vec<Vec<_>> = cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|edge| {
+                        (
+                            names[edge.from],
+                            nodes[edge.from].fields[edge.field].0.as_str(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            described,
+            vec![
+                vec![("List", "Cons.1")],
+                vec![("Expr", "kind"), ("ExprKind", "Paren.inner")],
+                vec![("Pair", "0")],
+            ]
+        );
+
+        assert_eq!(
+            inline_type_names("[Option<(u8, Node)>; 4]"),
+            vec!["u8", "Node"]
+        );
+        assert!(inline_type_names("Rc<RefCell<Node>>").is_empty());
+        assert!(inline_type_names("&'a Node").is_empty());
+        assert_eq!(
+            boxed_field_type("Option<crate::ast::Expr>", "Expr"),
+            "Option<Box<crate::ast::Expr>>"
+        );
+        assert_eq!(
+            boxed_field_type("[Node<T>; 2]", "Node"),
+            "[Box<Node<T>>; 2]"
+        );
+    }
+
+    #[test]
+    fn test_parse_enum_source_generics() {
+        let source = "enum Either<'a, L: Clone, R = ()> where L: Debug {\n    Left(&'a L),\n    Right(R),\n}";
+
+        let description = parse_enum_source(source).unwrap();
+        assert_eq!(description.name, "Either");
+        assert_eq!(description.generics, vec!["'a", "L: Clone", "R = ()"]);
+        assert_eq!(description.variants[0].fields[0].ty, "&'a L");
+        assert_eq!(description.variants[1].fields[0].ty, "R");
+    }
+
+    #[test]
+    fn test_parse_enum_source_rejects_structs() {
+        assert!(parse_enum_source("pub struct Point { x: i32, y: i32 }").is_none());
+    }
+
+    #[test]
+    fn test_estimate_struct_layout_repr_c() {
+        let definition = parse_struct_source(
+            "/// A packet header.\n#[derive(Debug)]\n#[repr(C)]\npub struct Header {\n    pub flag: bool,\n    id: u64,\n    name: &'static str,\n    tag: Option<u32>,\n}",
+        )
+        .unwrap();
+        assert_eq!(definition.repr, vec!["C"]);
+
+        let layout = estimate_struct_layout(&definition);
+        let offsets: Vec<_> = layout.fields.iter().map(|f| (f.offset, f.size)).collect();
+        assert_eq!(
+            offsets,
+            vec![
+                (Some(0), Some(1)),
+                (Some(8), Some(8)),
+                (Some(16), Some(16)),
+                (Some(32), Some(8))
+            ]
+        );
+        assert_eq!(
+            (layout.size, layout.align, layout.padding),
+            (Some(40), Some(8), Some(7))
+        );
+    }
+
+    #[test]
+    fn test_estimate_struct_layout_default_repr_and_unknown_types() {
+        let definition = parse_struct_source("struct Pair(u8, u32, u16);").unwrap();
+        let layout = estimate_struct_layout(&definition);
+        assert_eq!((layout.size, layout.align), (Some(8), Some(4)));
+        assert!(layout.fields.iter().all(|f| f.offset.is_none()));
+        assert_eq!(layout.fields[1].name, "1");
+
+        let packed = parse_struct_source("#[repr(C, packed)] struct P { a: u8, b: u32 }").unwrap();
+        assert_eq!(estimate_struct_layout(&packed).size, Some(5));
+
+        let generic = parse_struct_source("struct Wrapper<T> { value: T, len: usize }").unwrap();
+        let layout = estimate_struct_layout(&generic);
+        assert_eq!(layout.size, None);
+        assert_eq!(layout.fields[1].size, Some(8));
+        assert!(layout.notes.iter().any(|note| note.contains("`T`")));
+    }
+
+    #[test]
+    fn test_scan_lifetime_uses() {
+        let source = "/// Uses 'a twice\npub fn pick<'a, 'b: 'a, T: 'a>(first: &'a str, (x, _): (&'b T, u8), flag: &'ab bool) -> &'a str\nwhere\n    T: Fn(&'a str),\n{\n    let c = 'a';\n    let s: &'a str = first; // 'a\n    s\n}";
+        let uses: Vec<_> = scan_lifetime_uses(source, "'a")
+            .into_iter()
+            .map(|u| (u.line, u.role, u.parameter))
+            .collect();
+        assert_eq!(
+            uses,
+            vec![
+                (1, "declaration", None),
+                (1, "bound", None),
+                (1, "bound", None),
+                (1, "parameter", Some("first".to_string())),
+                (1, "return_type", None),
+                (3, "where_clause", None),
+                (6, "body", None),
+            ]
+        );
+        let receiver = scan_lifetime_uses("fn get(&'a self) -> &'a T {}", "'a");
+        assert_eq!(receiver[0].parameter.as_deref(), Some("self"));
+        assert_eq!(receiver[0].column, 8);
+        let pattern = scan_lifetime_uses(source, "'b");
+        assert_eq!(pattern[1].parameter.as_deref(), Some("(x, _)"));
+    }
+    #[test]
+    fn test_parse_item_generics_function() {
+        let source = "/// Docs\npub async fn merge<'a, 'b: 'a, T: Clone + Into<String>, const N: usize>(\n    left: &'a [T; N],\n) -> Vec<T>\nwhere\n    T: Send + 'static,\n    for<'c> &'c T: IntoIterator<Item = &'c u8>,\n{\n    todo!()\n}";
+
+        let generics = parse_item_generics(source).unwrap();
+        assert_eq!(generics.kind, "fn");
+        assert_eq!(generics.item, "merge");
+
+        let lifetimes: Vec<_> = generics.lifetimes.iter().map(|l| (l.name.as_str(), l.outlives.clone())).collect();
+        assert_eq!(lifetimes, vec![("'a", vec![]), ("'b", vec!["'a".to_string()])]);
+
+        let param = &generics.type_parameters[0];
+        assert_eq!(param.name, "T");
+        assert_eq!(param.bounds, vec!["Clone", "Into<String>"]);
+        assert_eq!(param.where_bounds, vec!["Send", "'static"]);
+
+        assert_eq!(generics.const_parameters[0].name, "N");
+        assert_eq!(generics.const_parameters[0].ty, "usize");
+
+        assert_eq!(generics.where_clause.len(), 2);
+        assert_eq!(generics.where_clause[1].bounded, "for<'c> &'c T");
+        assert_eq!(generics.where_clause[1].bounds, vec!["IntoIterator<Item = &'c u8>"]);
+    }
+
+    #[test]
+    fn test_parse_item_generics_impl_and_defaults() {
+        let generics = parse_item_generics(
+            "impl<S: AsRef<str>, E = ()> Handler for Router<S, E> where S::Target: Sized {}",
+        )
+        .unwrap();
+        assert_eq!(generics.kind, "impl");
+        assert_eq!(generics.item, "Handler for Router<S, E>");
+        assert_eq!(generics.type_parameters[0].bounds, vec!["AsRef<str>"]);
+        assert_eq!(generics.type_parameters[1].default.as_deref(), Some("()"));
+        assert_eq!(generics.where_clause[0].bounded, "S::Target");
+
+        let plain = parse_item_generics("pub struct Point { x: i32 }").unwrap();
+        assert_eq!(plain.item, "Point");
+        assert!(plain.type_parameters.is_empty() && plain.where_clause.is_empty());
+    }
+
+    #[test]
+    fn test_generic_scope() {
+        // impl > method: both introduce parameters.
+        assert_eq!(generic_scope(&[2, 19, 6]), vec![2, 1]);
+        // A fn nested in a method cannot use the outer generics.
+        assert_eq!(generic_scope(&[19, 12, 12]), vec![2]);
+        // Fields are skipped; a struct in a module stops at the module.
+        assert_eq!(generic_scope(&[2, 23, 8]), vec![1]);
+        assert_eq!(generic_scope(&[11]), vec![0]);
+        assert!(generic_scope(&[2]).is_empty());
+    }
+
+    #[test]
+    fn test_derive_bounds() {
+        let generics = parse_item_generics(
+            "#[derive(Clone)]\nstruct Handle<'a, T: Send, const N: usize> where T: 'a { inner: Arc<T>, name: &'a str }",
+        )
+        .unwrap();
+        assert_eq!(
+            derived_impl_header(&generics, "Clone"),
+            "impl<'a, T: Send + Clone, const N: usize> Clone for Handle<'a, T, N> where T: 'a"
+        );
+
+        assert!(!field_requires_bound("Arc<T>", "T", "Clone"));
+        assert!(field_requires_bound("Arc<T>", "T", "Debug"));
+        assert!(!field_requires_bound("&'a T", "T", "Clone"));
+        assert!(field_requires_bound("&'a mut T", "T", "Clone"));
+        assert!(!field_requires_bound("PhantomData<fn(T)>", "T", "Hash"));
+        assert!(field_requires_bound("Option<(u8, Box<T>)>", "T", "Clone"));
+        assert!(!field_requires_bound("Option<Vec<T>>", "T", "Default"));
+        assert!(!field_requires_bound("[u8; 4]", "T", "Hash"));
+        assert!(field_requires_bound("T::Item", "T", "Hash"));
+
+        assert_eq!(derive_supertraits("Ord"), &["Eq", "PartialOrd"]);
+    }
+}
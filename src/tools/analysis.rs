@@ -1,7 +1,19 @@
 use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::protocol::{
+    DocumentSymbol, DocumentSymbolResponse, INLAY_HINT_KIND_TYPE, InlayHint, InlayHintLabel,
+    Location, Position, Range,
+};
+use crate::analyzer::symbol::{crate_name_from_uri, module_path_from_uri};
+use crate::compiler::diagnostics::run_doc_tests;
+use crate::inspection::unified_diff;
+use crate::tools::advanced::{collapse_whitespace, split_generic_params};
+use crate::tools::cargo::owning_manifest;
+use crate::tools::navigation::tokenize;
 use crate::tools::types::ToolResult;
 use anyhow::Result;
 use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 pub async fn find_definition_impl(
@@ -49,6 +61,93 @@ pub async fn find_definition_impl(
     })
 }
 
+pub async fn find_definitions_batch_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let queries = args
+        .get("queries")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow::anyhow!("Missing queries parameter"))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let mut results = Vec::with_capacity(queries.len());
+    for query in queries {
+        let symbol = query.get("symbol").and_then(|v| v.as_str());
+        let resolved = resolve_batch_definition(analyzer, file_path, &file_content, query).await;
+        results.push(match resolved {
+            Ok(definition) => json!({ "symbol": symbol, "definition": definition }),
+            Err(e) => json!({ "symbol": symbol, "error": e.to_string() }),
+        });
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "results": results
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Resolves one `{symbol, code_block, occurrence}` entry of
+/// `find_definitions_batch` against the already read `file_content`.
+async fn resolve_batch_definition(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    file_content: &str,
+    query: &Value,
+) -> Result<Value> {
+    let symbol = query
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = query
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = query
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let (line, character) = find_symbol_location(file_content, symbol, code_block, occurrence)?;
+    let details = analyzer
+        .definition_details(file_path, line, character)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No definition found"))?;
+
+    let start = details.location.range.start;
+    let symbol_path = details
+        .symbol_path
+        .iter()
+        .map(|segment| segment.name.as_str())
+        .collect::<Vec<_>>()
+        .join("::");
+    Ok(json!({
+        "uri": details.location.uri,
+        "line": start.line + 1,
+        "column": start.character + 1,
+        "symbol_path": (!symbol_path.is_empty()).then_some(symbol_path)
+    }))
+}
+
 pub async fn find_references_impl(
     args: Value,
     analyzer: &mut RustAnalyzerClient,
@@ -69,6 +168,10 @@ pub async fn find_references_impl(
         .get("occurrence")
         .and_then(|v| v.as_u64())
         .unwrap_or(1) as usize;
+    let scope = args
+        .get("scope")
+        .and_then(|v| v.as_str())
+        .unwrap_or("workspace");
 
     let file_content = fs::read_to_string(file_path)
         .await
@@ -76,9 +179,25 @@ pub async fn find_references_impl(
 
     let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
 
+    // rust-analyzer reports canonical paths, so compare against the canonical file.
+    let canonical_file = fs::canonicalize(file_path)
+        .await
+        .unwrap_or_else(|_| PathBuf::from(file_path));
+    let within = match scope {
+        "workspace" => None,
+        "file" => Some(canonical_file),
+        "directory" => canonical_file.parent().map(Path::to_path_buf),
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid scope '{}': expected \"file\", \"directory\" or \"workspace\"",
+                other
+            ));
+        }
+    };
+
     // Implementation will use rust-analyzer LSP to find references
     let result = analyzer
-        .find_references(file_path, line, character)
+        .find_references(file_path, line, character, within.as_deref())
         .await?;
 
     Ok(ToolResult {
@@ -94,7 +213,7 @@ pub async fn find_references_impl(
     })
 }
 
-pub async fn get_diagnostics_impl(
+pub async fn find_callers_impl(
     args: Value,
     analyzer: &mut RustAnalyzerClient,
 ) -> Result<ToolResult> {
@@ -102,15 +221,122 @@ pub async fn get_diagnostics_impl(
         .get("file_path")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
 
-    // Implementation will use rust-analyzer LSP to get diagnostics
-    let diagnostics_result = analyzer.get_diagnostics(file_path).await?;
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let item = analyzer
+        .prepare_call_hierarchy(file_path, line, character)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a function or method", symbol))?;
+    let mut calls = analyzer.incoming_calls(item).await?;
+    calls.sort_by(|a, b| {
+        (&a.from.uri, a.from.range.start.line).cmp(&(&b.from.uri, b.from.range.start.line))
+    });
+
+    let mut modules: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut symbols_by_file: HashMap<String, Option<DocumentSymbolResponse>> = HashMap::new();
+    let mut contents: HashMap<String, Option<String>> = HashMap::new();
+    let mut total_calls = 0;
+    let mut other_uses = 0;
+
+    for call in calls {
+        let caller_path = call
+            .from
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&call.from.uri)
+            .to_string();
+        if !symbols_by_file.contains_key(&caller_path) {
+            let symbols = analyzer.document_symbols(&caller_path).await.ok();
+            symbols_by_file.insert(caller_path.clone(), symbols);
+        }
+        if !contents.contains_key(&caller_path) {
+            let content = fs::read_to_string(&caller_path).await.ok();
+            contents.insert(caller_path.clone(), content);
+        }
+
+        let inline = match &symbols_by_file[&caller_path] {
+            Some(DocumentSymbolResponse::DocumentSymbols(symbols)) => {
+                inline_modules(symbols, &call.from.selection_range.start)
+            }
+            _ => Vec::new(),
+        };
+        let module = caller_module(&call.from.uri, &inline);
+
+        let sites: Vec<Value> = call
+            .from_ranges
+            .iter()
+            .map(|range| {
+                let is_call = contents[&caller_path]
+                    .as_deref()
+                    .is_none_or(|content| is_call_site(content, range));
+                if is_call {
+                    total_calls += 1;
+                } else {
+                    other_uses += 1;
+                }
+                json!({
+                    "line": range.start.line + 1,
+                    "column": range.start.character + 1,
+                    "kind": if is_call { "call" } else { "reference" }
+                })
+            })
+            .collect();
+
+        modules.entry(module).or_default().push(json!({
+            "caller": call.from.name,
+            "signature": call.from.detail,
+            "file_path": caller_path,
+            "line": call.from.selection_range.start.line + 1,
+            "sites": sites
+        }));
+    }
+
+    let callers: usize = modules.values().map(Vec::len).sum();
+    let mut notes = Vec::new();
+    if other_uses > 0 {
+        notes.push(format!(
+            "{other_uses} site(s) use `{symbol}` without calling it (e.g. passed as a function value); they are marked \"reference\"."
+        ));
+    }
+    if callers == 0 {
+        notes.push(
+            "No callers found. Calls through trait objects or generic bounds resolve to the trait method, not to this impl."
+                .to_string(),
+        );
+    }
+
+    let result = json!({
+        "function": symbol,
+        "callers": callers,
+        "calls": total_calls,
+        "modules": modules
+            .into_iter()
+            .map(|(module, callers)| json!({ "module": module, "callers": callers }))
+            .collect::<Vec<_>>(),
+        "notes": notes
+    });
 
     Ok(ToolResult {
         content: vec![
             json!({
                 "type": "text",
-                "text": diagnostics_result
+                "text": serde_json::to_string_pretty(&result)?
             })
             .as_object()
             .unwrap()
@@ -119,7 +345,73 @@ pub async fn get_diagnostics_impl(
     })
 }
 
-pub async fn get_hover_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+/// Names of the inline `mod` blocks enclosing `position`, outermost first.
+pub(crate) fn inline_modules(symbols: &[DocumentSymbol], position: &Position) -> Vec<String> {
+    let contains = |range: &Range| {
+        (range.start.line, range.start.character) <= (position.line, position.character)
+            && (position.line, position.character) <= (range.end.line, range.end.character)
+    };
+    let mut modules = Vec::new();
+    let mut level = symbols;
+    while let Some(symbol) = level.iter().find(|symbol| contains(&symbol.range)) {
+        // LSP `SymbolKind::Module`
+        if symbol.kind == 2 {
+            modules.push(symbol.name.clone());
+        }
+        let Some(children) = &symbol.children else {
+            break;
+        };
+        level = children;
+    }
+    modules
+}
+
+/// `crate::net::server` for a caller in `src/net/server.rs`; files outside
+/// `src` (integration tests, examples) are named after their directory.
+fn caller_module(uri: &str, inline: &[String]) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut segments = if path.contains("/src/") {
+        let mut segments = vec!["crate".to_string()];
+        let module = module_path_from_uri(uri);
+        if !matches!(module.as_slice(), [root] if root == "lib" || root == "main") {
+            segments.extend(module);
+        }
+        segments
+    } else {
+        let path = Path::new(path);
+        [path.parent().and_then(Path::file_name), path.file_stem()]
+            .into_iter()
+            .flatten()
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect()
+    };
+    segments.extend(inline.iter().cloned());
+    segments.join("::")
+}
+
+/// Whether the reference at `range` is called: followed by `(` or a
+/// turbofish, rather than used as a value.
+fn is_call_site(content: &str, range: &Range) -> bool {
+    let Some(line) = content.lines().nth(range.end.line as usize) else {
+        return true;
+    };
+    let end = line
+        .char_indices()
+        .scan(0, |units, (idx, c)| {
+            let start = *units;
+            *units += c.len_utf16();
+            Some((idx, start))
+        })
+        .find(|&(_, units)| units >= range.end.character as usize)
+        .map_or(line.len(), |(idx, _)| idx);
+    let rest = line[end..].trim_start();
+    rest.starts_with('(') || rest.starts_with("::<")
+}
+
+pub async fn enclosing_blocks_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
     let file_path = args
         .get("file_path")
         .and_then(|v| v.as_str())
@@ -132,26 +424,75 @@ pub async fn get_hover_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> R
         .get("code_block")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
-    let occurrence = args
-        .get("occurrence")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(1) as usize;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
 
     let file_content = fs::read_to_string(file_path)
         .await
         .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
 
     let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let function = analyzer
+        .enclosing_function_range(file_path, Position { line, character })
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not inside a function or method", symbol))?;
 
-    let hover_result = analyzer
-        .get_hover(file_path, line, character)
-        .await?;
+    let offset_of = |line: u32, character: u32| {
+        line_start_offset(&file_content, line as usize)
+            .map(|start| start + utf16_column_offset(&file_content[start..], character))
+    };
+    let (Some(start), Some(target)) = (
+        offset_of(function.start.line, 0),
+        offset_of(line, character),
+    ) else {
+        return Err(anyhow::anyhow!("Position is past the end of the file"));
+    };
+    let end = offset_of(function.end.line + 1, 0).unwrap_or(file_content.len());
+
+    let blocks = scan_blocks(&file_content[start..end]);
+    let mut enclosing: Vec<&ScannedBlock> = blocks
+        .iter()
+        .filter(|block| block.open + start < target && target <= block.close + start)
+        .collect();
+    enclosing.sort_by_key(|block| std::cmp::Reverse(block.open));
+
+    let line_of = |offset: usize| file_content[..start + offset].matches('\n').count() + 1;
+    let nesting: Vec<Value> = enclosing
+        .iter()
+        .map(|block| {
+            json!({
+                "kind": block.kind.as_str(),
+                "label": block.label,
+                "start_line": line_of(block.open),
+                "end_line": line_of(block.close),
+                "header": block.header,
+                "break_target": block.kind.is_loop() || block.label.is_some()
+            })
+        })
+        .collect();
+    // `break` cannot leave a closure, async block or const block.
+    let reachable = enclosing
+        .iter()
+        .position(|block| block.kind.is_boundary())
+        .unwrap_or(enclosing.len());
+    let innermost_loop = enclosing[..reachable]
+        .iter()
+        .position(|block| block.kind.is_loop());
+
+    let result = json!({
+        "file_path": file_path,
+        "symbol": symbol,
+        "line": line + 1,
+        "nesting": nesting,
+        "innermost_loop": innermost_loop,
+        "break_scope": reachable,
+        "note": "Blocks are found by scanning the enclosing function's source; nesting[0] is innermost. `innermost_loop` indexes the loop a plain `break`/`continue` leaves, and only the first `break_scope` entries can be left with `break` at all: a closure, async block or const block stops it."
+    });
 
     Ok(ToolResult {
         content: vec![
             json!({
                 "type": "text",
-                "text": hover_result
+                "text": serde_json::to_string_pretty(&result)?
             })
             .as_object()
             .unwrap()
@@ -160,291 +501,719 @@ pub async fn get_hover_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> R
     })
 }
 
-pub fn find_block_range(
-    file_content: &str,
-    code_block: &str,
-    occurrence: usize,
-) -> Result<(u32, u32, u32, u32)> {
-    let mut current_pos = 0;
-    let mut current_occurrence = 0;
-
-    while let Some(start_idx) = file_content[current_pos..].find(code_block) {
-        let absolute_start_idx = current_pos + start_idx;
-        current_occurrence += 1;
+/// Byte offset at which 0-based `line` starts.
+pub(crate) fn line_start_offset(content: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return Some(0);
+    }
+    content
+        .match_indices('\n')
+        .nth(line - 1)
+        .map(|(idx, _)| idx + 1)
+}
 
-        if current_occurrence == occurrence {
-            let absolute_end_idx = absolute_start_idx + code_block.len();
-            let (start_line, start_char) = index_to_line_col(file_content, absolute_start_idx);
-            let (end_line, end_char) = index_to_line_col(file_content, absolute_end_idx);
-            return Ok((start_line, start_char, end_line, end_char));
+/// Byte offset of the UTF-16 column `character` in the line `text` starts with.
+pub(crate) fn utf16_column_offset(text: &str, character: u32) -> usize {
+    let line = text.split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= character as usize {
+            return idx;
         }
-
-        current_pos = absolute_start_idx + 1;
+        units += c.len_utf16();
     }
-
-    Err(anyhow::anyhow!(
-        "Code block not found (occurrence #{}) in file. Ensure the code block is an exact match.",
-        occurrence
-    ))
+    line.len()
 }
 
-pub fn find_symbol_location(
-    file_content: &str,
-    symbol: &str,
-    code_block: &str,
-    occurrence: usize,
-) -> Result<(u32, u32)> {
-    // Find the code block
-    // We assume the LLM copies the block accurately.
-    let block_start_idx = file_content
-        .find(code_block)
-        .ok_or_else(|| anyhow::anyhow!("Code block not found in file. Ensure the code block is an exact match."))?;
-
-    // Find the symbol within the code block
-    let block_content = &file_content[block_start_idx..block_start_idx + code_block.len()];
-    
-    let mut current_occurrence = 0;
-    let mut symbol_offset_in_block = 0;
-    let mut found = false;
-    
-    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+/// What a `{ ... }` of a function body is, judged from the code before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Function,
+    Loop,
+    While,
+    For,
+    If,
+    Else,
+    LetElse,
+    Match,
+    MatchArm,
+    Closure,
+    Async,
+    Unsafe,
+    Const,
+    Block,
+    /// `Point { x, y }` or a macro's braces: no scope.
+    Braces,
+}
 
-    for (idx, _) in block_content.match_indices(symbol) {
-        // Check boundary before
-        let valid_start = if idx == 0 {
-            true
-        } else {
-            // Get the character immediately preceding the match
-            block_content[..idx].chars().next_back().map_or(true, |c| !is_ident_char(c))
-        };
+impl BlockKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            BlockKind::Function => "function",
+            BlockKind::Loop => "loop",
+            BlockKind::While => "while",
+            BlockKind::For => "for",
+            BlockKind::If => "if",
+            BlockKind::Else => "else",
+            BlockKind::LetElse => "let_else",
+            BlockKind::Match => "match",
+            BlockKind::MatchArm => "match_arm",
+            BlockKind::Closure => "closure",
+            BlockKind::Async => "async",
+            BlockKind::Unsafe => "unsafe",
+            BlockKind::Const => "const",
+            BlockKind::Block => "block",
+            BlockKind::Braces => "braces",
+        }
+    }
 
-        // Check boundary after
-        let valid_end = if idx + symbol.len() == block_content.len() {
-            true
-        } else {
-            block_content[idx + symbol.len()..].chars().next().map_or(true, |c| !is_ident_char(c))
-        };
+    fn is_loop(self) -> bool {
+        matches!(self, BlockKind::Loop | BlockKind::While | BlockKind::For)
+    }
 
-        let absolute_symbol_idx = block_start_idx + idx;
-        let is_code = is_valid_code_context(file_content, absolute_symbol_idx);
+    fn is_boundary(self) -> bool {
+        matches!(
+            self,
+            BlockKind::Function | BlockKind::Closure | BlockKind::Async | BlockKind::Const
+        )
+    }
 
-        if valid_start && valid_end && is_code {
-            current_occurrence += 1;
-            if current_occurrence == occurrence {
-                symbol_offset_in_block = idx;
-                found = true;
-                break;
+    /// Classifies a brace from the code between it and the previous `;`,
+    /// brace or opening bracket of the same nesting level, labels removed.
+    fn from_header(header: &str) -> BlockKind {
+        let header = header.trim();
+        if header.starts_with('!') || header.ends_with('!') {
+            return BlockKind::Braces;
+        }
+        if header.ends_with("=>") {
+            return BlockKind::MatchArm;
+        }
+        // `Some(x) => loop {`: only the arm's expression matters.
+        let header = header.rsplit("=>").next().unwrap_or(header).trim();
+        if let Some(bar) = header.find('|') {
+            let prefix_is_modifiers = header[..bar]
+                .split_whitespace()
+                .all(|word| matches!(word, "async" | "move" | "static"));
+            let tail = &header[header.rfind('|').unwrap_or(bar)..];
+            if prefix_is_modifiers && (header.ends_with('|') || tail.contains("->")) {
+                return BlockKind::Closure;
             }
         }
-    }
 
-    if !found {
-        return Err(anyhow::anyhow!(
-            "Found only {} occurrences of symbol '{}' (whole word, not in comment/string) in the code block, expected #{}", 
-            current_occurrence, 
-            symbol, 
-            occurrence
-        ));
+        let words: Vec<&str> = header
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.contains(&"fn") {
+            return BlockKind::Function;
+        }
+        let keyword = words.iter().position(|word| {
+            matches!(
+                *word,
+                "loop" | "while" | "for" | "if" | "else" | "match" | "unsafe" | "async" | "const"
+            )
+        });
+        match keyword.map(|idx| (words[idx], words.get(idx + 1).copied())) {
+            Some(("loop", _)) => BlockKind::Loop,
+            Some(("while", _)) => BlockKind::While,
+            Some(("for", _)) if header.contains(" in ") => BlockKind::For,
+            Some(("if", _)) | Some(("else", Some("if"))) => BlockKind::If,
+            Some(("else", _)) if words.first() == Some(&"let") => BlockKind::LetElse,
+            Some(("else", _)) => BlockKind::Else,
+            Some(("match", _)) => BlockKind::Match,
+            Some(("unsafe", _)) => BlockKind::Unsafe,
+            Some(("async", _)) => BlockKind::Async,
+            Some(("const", _)) => BlockKind::Const,
+            _ if header.is_empty() || header.ends_with('=') || header.ends_with("return") => {
+                BlockKind::Block
+            }
+            _ if header.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '>') => {
+                BlockKind::Braces
+            }
+            _ => BlockKind::Block,
+        }
     }
-
-    let absolute_symbol_idx = block_start_idx + symbol_offset_in_block;
-
-    // Convert index to line and character (LSP compatible)
-    Ok(index_to_line_col(file_content, absolute_symbol_idx))
 }
 
-fn index_to_line_col(text: &str, index: usize) -> (u32, u32) {
-    let prefix = &text[..index];
-    let line = prefix.matches('\n').count() as u32;
-    
-    let last_newline_pos = prefix.rfind('\n').map(|p| p + 1).unwrap_or(0);
-    let line_str = &prefix[last_newline_pos..];
-    
-    // Convert to UTF-16 code units for LSP
-    let character = line_str.encode_utf16().count() as u32;
-    
-    (line, character)
+/// A brace pair found by [`scan_blocks`]; offsets are of `{` and `}`.
+#[derive(Debug)]
+struct ScannedBlock {
+    kind: BlockKind,
+    label: Option<String>,
+    open: usize,
+    close: usize,
+    /// The source line the block opens on, trimmed.
+    header: String,
 }
 
-fn is_valid_code_context(text: &str, target_idx: usize) -> bool {
-    let mut chars = text.char_indices().peekable();
-    let mut in_string = false;
-    let mut in_line_comment = false;
-    let mut block_comment_depth = 0;
-    
-    while let Some((idx, c)) = chars.next() {
-        if idx >= target_idx {
-             return !in_string && !in_line_comment && block_comment_depth == 0;
-        }
+/// Finds the brace pairs of `source` that open a scope, skipping strings,
+/// character literals and comments. Unclosed braces are dropped.
+fn scan_blocks(source: &str) -> Vec<ScannedBlock> {
+    let bytes = source.as_bytes();
+    let mut blocks = Vec::new();
+    // Open braces: (kind, label, offset).
+    let mut open: Vec<(BlockKind, Option<String>, usize)> = Vec::new();
+    // Code since the last boundary, one entry per open bracket level.
+    let mut headers: Vec<String> = vec![String::new()];
+    let mut label: Option<String> = None;
 
-        if in_line_comment {
-            if c == '\n' {
-                in_line_comment = false;
-            }
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let rest = &source[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+        if rest.starts_with("//") {
+            idx += rest.find('\n').unwrap_or(rest.len());
             continue;
         }
-        
-        if block_comment_depth > 0 {
-             if c == '/' {
-                if let Some((_, '*')) = chars.peek() {
-                    chars.next();
-                    block_comment_depth += 1;
-                }
-            } else if c == '*' {
-                if let Some((_, '/')) = chars.peek() {
-                    chars.next();
-                    block_comment_depth -= 1;
-                }
-            }
+        if rest.starts_with("/*") {
+            idx += rest.find("*/").map_or(rest.len(), |end| end + 2);
             continue;
         }
-        
-        if in_string {
-            if c == '\\' {
-                chars.next();
-            } else if c == '"' {
-                in_string = false;
+        if let Some(len) = string_literal_len(rest) {
+            headers.last_mut().unwrap().push_str("\"\"");
+            idx += len;
+            continue;
+        }
+        if c == '\'' {
+            let after: Vec<char> = rest.chars().skip(1).take(3).collect();
+            let char_len = if after.first() == Some(&'\\') {
+                rest.get(3..)
+                    .and_then(|tail| tail.find('\''))
+                    .map(|end| end + 4)
+            } else if after.get(1) == Some(&'\'') {
+                Some(1 + after[0].len_utf8() + 1)
+            } else {
+                None
+            };
+            if let Some(len) = char_len {
+                headers.last_mut().unwrap().push_str("' '");
+                idx += len;
+                continue;
+            }
+            // A lifetime, or a label when followed by `:`.
+            let name_len = rest[1..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - 1);
+            let after_name = rest[1 + name_len..].trim_start();
+            if after_name.starts_with(':') && !after_name.starts_with("::") {
+                label = Some(rest[..1 + name_len].to_string());
+                idx += 1 + name_len + (rest[1 + name_len..].len() - after_name.len()) + 1;
+            } else {
+                headers.last_mut().unwrap().push_str(&rest[..1 + name_len]);
+                idx += 1 + name_len;
             }
             continue;
         }
-        
+
         match c {
-            '/' => {
-                if let Some((_, '/')) = chars.peek() {
-                    chars.next();
-                    in_line_comment = true;
-                } else if let Some((_, '*')) = chars.peek() {
-                    chars.next();
-                    block_comment_depth += 1;
+            '{' => {
+                let header = headers.last().map(String::as_str).unwrap_or_default();
+                let kind = match BlockKind::from_header(header) {
+                    BlockKind::Block | BlockKind::Braces if label.is_some() => BlockKind::Block,
+                    // Nested braces of a struct literal or macro input.
+                    BlockKind::Block
+                        if open
+                            .last()
+                            .is_some_and(|(kind, _, _)| *kind == BlockKind::Braces) =>
+                    {
+                        BlockKind::Braces
+                    }
+                    kind => kind,
+                };
+                open.push((kind, label.take(), idx));
+                headers.push(String::new());
+            }
+            '}' => {
+                headers.pop();
+                if headers.is_empty() {
+                    headers.push(String::new());
+                }
+                headers.last_mut().unwrap().clear();
+                match open.pop() {
+                    Some((kind, label, start)) if kind != BlockKind::Braces => {
+                        let line_start = source[..start].rfind('\n').map_or(0, |nl| nl + 1);
+                        let line_end = source[start..]
+                            .find('\n')
+                            .map_or(source.len(), |nl| start + nl);
+                        blocks.push(ScannedBlock {
+                            kind,
+                            label,
+                            open: start,
+                            close: idx,
+                            header: source[line_start..line_end].trim().to_string(),
+                        });
+                    }
+                    _ => {}
                 }
             }
-            '"' => {
-                in_string = true;
+            '(' | '[' => {
+                // Marks macro input, whose braces are not blocks.
+                let is_macro = headers
+                    .last()
+                    .is_some_and(|header| header.trim_end().ends_with('!'));
+                headers.push(if is_macro {
+                    "!".to_string()
+                } else {
+                    String::new()
+                });
             }
-            _ => {}
+            ')' | ']' => {
+                if headers.len() > 1 {
+                    headers.pop();
+                }
+                headers.last_mut().unwrap().push_str("(..)");
+            }
+            ';' => {
+                headers.last_mut().unwrap().clear();
+                label = None;
+            }
+            // A comma between closure parameters does not end the closure header.
+            ',' if headers
+                .last()
+                .is_some_and(|header| header.matches('|').count() % 2 == 0) =>
+            {
+                let header = headers.last_mut().unwrap();
+                let is_macro = header.starts_with('!');
+                header.clear();
+                if is_macro {
+                    header.push('!');
+                }
+                label = None;
+            }
+            _ => headers.last_mut().unwrap().push(c),
         }
+        idx += c.len_utf8();
     }
-    
-    !in_string && !in_line_comment && block_comment_depth == 0
+    blocks
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Length of the string literal (`"..."`, `b"..."`, `r#"..."#`, ...) that
+/// `text` starts with.
+pub(crate) fn string_literal_len(text: &str) -> Option<usize> {
+    let prefix = text.find(['"', '#']).filter(|&at| {
+        text[..at].chars().all(|c| matches!(c, 'b' | 'c' | 'r')) && text[..at].len() <= 2
+    })?;
+    let raw = text[..prefix].contains('r');
+    if !raw && text[prefix..].starts_with('#') {
+        return None;
+    }
+    let hashes = text[prefix..].chars().take_while(|&c| c == '#').count();
+    let quote = prefix + hashes;
+    if !text[quote..].starts_with('"') {
+        return None;
+    }
+    if raw {
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        return text[quote + 1..]
+            .find(&terminator)
+            .map(|end| quote + 1 + end + terminator.len());
+    }
+    let mut escaped = false;
+    for (idx, c) in text[quote + 1..].char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(quote + 1 + idx + 1),
+            _ => escaped = false,
+        }
+    }
+    Some(text.len())
+}
 
-    #[test]
-    fn test_index_to_line_col() {
-        let text = "hello\nworld\n! 123";
-        // "h" -> 0, 0
-        assert_eq!(index_to_line_col(text, 0), (0, 0));
-        // "w" in "world" -> index 6 (hello=5 + \n=1)
-        assert_eq!(index_to_line_col(text, 6), (1, 0));
-        // "!" -> index 12 (hello\n=6, world\n=6)
-        assert_eq!(index_to_line_col(text, 12), (2, 0));
-        // "1" -> index 14
-        assert_eq!(index_to_line_col(text, 14), (2, 2));
+pub async fn get_diagnostics_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    // Implementation will use rust-analyzer LSP to get diagnostics
+    let diagnostics_result = analyzer.get_diagnostics(file_path).await?;
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": diagnostics_result
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn diagnostic_snippet_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let line = args
+        .get("line")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| anyhow::anyhow!("Missing line parameter"))?;
+    let message = args.get("message").and_then(|v| v.as_str());
+    let max_depth = args
+        .get("max_depth")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_SNIPPET_DEPTH, |v| {
+            (v as usize).min(MAX_SNIPPET_DEPTH)
+        });
+
+    let diagnostics = analyzer
+        .file_diagnostics(file_path)
+        .await?
+        .unwrap_or_default();
+    let diagnostic = diagnostics
+        .into_iter()
+        .filter(|diag| diag.range.start.line as u64 + 1 == line)
+        .filter(|diag| message.is_none_or(|text| diag.message.contains(text)))
+        // Errors first, then warnings and hints.
+        .min_by_key(|diag| diag.severity.unwrap_or(1))
+        .ok_or_else(|| anyhow::anyhow!("No diagnostic found on line {} of {}", line, file_path))?;
+
+    let mut files: HashMap<String, String> = HashMap::new();
+    let mut included: Vec<SnippetItem> = Vec::new();
+    let mut skipped: BTreeMap<String, &str> = BTreeMap::new();
+    let mut external: Vec<String> = Vec::new();
+
+    let root = snippet_item_at(analyzer, &mut files, file_path, &diagnostic.range.start, 0)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("The diagnostic is not inside an item"))?;
+    included.push(root);
+
+    // Breadth-first, so that items closer to the diagnostic win the item limit.
+    let mut next = 0;
+    while next < included.len() {
+        let (path, range, depth) = {
+            let item = &included[next];
+            (item.path.clone(), item.range.clone(), item.depth)
+        };
+        next += 1;
+
+        let content = files[&path].clone();
+        let Some(base) = line_start_offset(&content, range.start.line as usize) else {
+            continue;
+        };
+        let end = line_start_offset(&content, range.end.line as usize + 1).unwrap_or(content.len());
+        for (offset, name) in referenced_names(&content[base..end]) {
+            let (ref_line, ref_character) = index_to_line_col(&content, base + offset);
+            let Some(location) = analyzer
+                .definition_locations(&path, ref_line, ref_character)
+                .await?
+                .into_iter()
+                .next()
+            else {
+                continue;
+            };
+            let def_path = location
+                .uri
+                .strip_prefix("file://")
+                .unwrap_or(&location.uri)
+                .to_string();
+            let in_workspace = DocsOrigin::of_path(&def_path) == DocsOrigin::Local
+                && analyzer
+                    .project_root()
+                    .is_none_or(|root| Path::new(&def_path).starts_with(root));
+            if !in_workspace {
+                external.push(name);
+                continue;
+            }
+            if included
+                .iter()
+                .any(|item| item.contains(&def_path, &location.range.start))
+            {
+                continue;
+            }
+            if depth >= max_depth {
+                skipped.entry(name).or_insert("depth_limit");
+                continue;
+            }
+            if included.len() >= MAX_SNIPPET_ITEMS {
+                skipped.entry(name).or_insert("item_limit");
+                continue;
+            }
+            if let Some(item) = snippet_item_at(
+                analyzer,
+                &mut files,
+                &def_path,
+                &location.range.start,
+                depth + 1,
+            )
+            .await?
+            {
+                included.push(item);
+            }
+        }
     }
 
-    #[test]
-    fn test_unicode() {
-        let text = "a\nб\nв"; // 'б' is 2 bytes
-        // 'a' (0) -> 0,0
-        // '\n' (1)
-        // 'б' (2) -> 1,0
-        assert_eq!(index_to_line_col(text, 2), (1, 0));
-        // '\n' after 'б' is at 2+2=4.
-        // 'в' at 5.
-        assert_eq!(index_to_line_col(text, 5), (2, 0));
+    let root_dir = analyzer.project_root().map(Path::to_path_buf);
+    let display_path = |path: &str| {
+        root_dir
+            .as_deref()
+            .and_then(|root| Path::new(path).strip_prefix(root).ok())
+            .map_or(path.to_string(), |rel| rel.display().to_string())
+    };
+
+    let mut used: HashSet<String> = HashSet::new();
+    for item in &included {
+        used.extend(
+            referenced_names(&item.source)
+                .into_iter()
+                .map(|(_, name)| name),
+        );
+    }
+    let mut imports: Vec<String> = Vec::new();
+    for item in &included {
+        for import in snippet_imports(&files[&item.path], &used) {
+            if !imports.contains(&import) {
+                imports.push(import);
+            }
+        }
     }
 
-    #[test]
-    fn test_is_valid_code_context() {
-        let code = "
-        let x = 1; // comment with x
-        let s = \"string with x\";
-        /* 
-           multi-line comment with x
-        */
-        let x = 2;
-        ";
-        
-        // Find all 'x' indices manually
-        // x at line 2: "let x"
-        let first_x = code.find("let x").unwrap() + 4;
-        assert!(is_valid_code_context(code, first_x));
-        
-        // x in comment: "// comment with x"
-        let comment_start = code.find("//").unwrap();
-        let x_in_comment = comment_start + code[comment_start..].find("x").unwrap();
-        assert!(!is_valid_code_context(code, x_in_comment));
-        
-        // x in string: "\"string with x\""
-        let string_start = code.find("\"").unwrap();
-        let x_in_string = string_start + code[string_start..].find("x").unwrap();
-        assert!(!is_valid_code_context(code, x_in_string));
-        
-        // x in block comment
-        let block_start = code.find("/*").unwrap();
-        let x_in_block = block_start + code[block_start..].find("x").unwrap();
-        assert!(!is_valid_code_context(code, x_in_block));
+    let mut sections: Vec<String> = Vec::new();
+    if !imports.is_empty() {
+        sections.push(imports.join("\n"));
+    }
+    for item in &included {
+        sections.push(format!(
+            "// {}:{}\n{}",
+            display_path(&item.path),
+            item.range.start.line + 1,
+            item.source
+        ));
+    }
+    let snippet = sections.join("\n\n");
 
-        // last x
-        let last_let = code.rfind("let x").unwrap();
-        let last_x = last_let + 4;
-        assert!(is_valid_code_context(code, last_x));
+    let items: Vec<Value> = included
+        .iter()
+        .map(|item| {
+            json!({
+                "name": item.name,
+                "file_path": display_path(&item.path),
+                "line": item.range.start.line + 1,
+                "depth": item.depth
+            })
+        })
+        .collect();
+    external.sort();
+    external.dedup();
+    let skipped: Vec<Value> = skipped
+        .into_iter()
+        .map(|(name, reason)| json!({"name": name, "reason": reason}))
+        .collect();
+
+    let result = json!({
+        "diagnostic": {
+            "line": diagnostic.range.start.line + 1,
+            "column": diagnostic.range.start.character + 1,
+            "severity": diagnostic.severity,
+            "code": diagnostic.code,
+            "message": diagnostic.message
+        },
+        "snippet": snippet,
+        "included": items,
+        "imports": imports,
+        "external": external,
+        "skipped": skipped,
+        "note": format!(
+            "The item containing the diagnostic plus the workspace items it references, followed up to depth {max_depth} (at most {MAX_SNIPPET_ITEMS} items). Items are flattened to the top level: methods keep their impl header, modules are dropped, and `crate::`/`super::`/`self::` imports are left out. Names in `external` come from std or dependencies and are reached through the kept imports. The snippet may still need adjusting to compile on its own."
+        )
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Default `max_depth` of `diagnostic_snippet`: the enclosing item, what it
+/// uses and what those use.
+const DEFAULT_SNIPPET_DEPTH: usize = 2;
+const MAX_SNIPPET_DEPTH: usize = 5;
+/// Keeps a snippet small enough to paste into a playground.
+const MAX_SNIPPET_ITEMS: usize = 16;
+
+/// An item copied into a `diagnostic_snippet`.
+struct SnippetItem {
+    name: String,
+    path: String,
+    range: Range,
+    depth: usize,
+    /// The item's source, wrapped in its impl or trait header.
+    source: String,
+}
+
+impl SnippetItem {
+    fn contains(&self, path: &str, position: &Position) -> bool {
+        self.path == path
+            && self.range.start.line <= position.line
+            && position.line <= self.range.end.line
     }
+}
 
-    #[tokio::test]
-    async fn test_word_boundary_logic() {
-        // This simulates the logic inside get_hover_impl
-        let block_content = "let service = rust_server.serve(stdio()).await?;";
-        let symbol = "serve";
-        let occurrence = 1;
+/// The innermost item (function, type, trait, impl, ...) at `position`, with
+/// its source read through the `files` cache.
+async fn snippet_item_at(
+    analyzer: &mut RustAnalyzerClient,
+    files: &mut HashMap<String, String>,
+    path: &str,
+    position: &Position,
+    depth: usize,
+) -> Result<Option<SnippetItem>> {
+    let ancestors = analyzer.enclosing_symbols(path, position.clone()).await?;
+    // Fields, variants and locals resolve to the item that declares them.
+    let Some(symbol) = ancestors
+        .into_iter()
+        .rev()
+        .find(|symbol| matches!(symbol.kind, 5 | 6 | 10 | 11 | 12 | 14 | 19 | 23 | 26))
+    else {
+        return Ok(None);
+    };
 
-        let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
-        
-        let mut current_occurrence = 0;
-        let mut found_idx = None;
+    if !files.contains_key(path) {
+        let content = fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", path, e))?;
+        files.insert(path.to_string(), content);
+    }
+    let content = &files[path];
+    let source = content
+        .lines()
+        .skip(symbol.range.start.line as usize)
+        .take((symbol.range.end.line - symbol.range.start.line) as usize + 1)
+        .collect::<Vec<_>>()
+        .join("\n");
 
-        for (idx, _) in block_content.match_indices(symbol) {
-             let valid_start = if idx == 0 {
-                true
-            } else {
-                block_content[..idx].chars().next_back().map_or(true, |c| !is_ident_char(c))
-            };
+    let headers: Vec<EnclosingHeader> = enclosing_headers(analyzer, path, &symbol.range)
+        .await?
+        .into_iter()
+        .filter(|header| header.kind != "module" && header.line < symbol.range.start.line)
+        .collect();
+    Ok(Some(SnippetItem {
+        name: symbol.name,
+        path: path.to_string(),
+        range: symbol.range,
+        depth,
+        source: wrap_in_headers(&source, &headers),
+    }))
+}
 
-            let valid_end = if idx + symbol.len() == block_content.len() {
-                true
+/// Names in `source` worth resolving for a snippet, with the byte offset of
+/// their first use: capitalized paths (types, traits, variants) and called
+/// functions and methods. Comments, strings and keywords are skipped.
+fn referenced_names(source: &str) -> Vec<(usize, String)> {
+    const IGNORED: &[&str] = &[
+        "Self", "Some", "None", "Ok", "Err", "Box", "Vec", "String", "Option", "Result", "fn",
+        "if", "while", "for", "match", "loop", "return", "in", "as",
+    ];
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    let mut idx = 0;
+    while idx < source.len() {
+        let rest = &source[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+        if rest.starts_with("//") {
+            idx += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("/*") {
+            idx += rest.find("*/").map_or(rest.len(), |end| end + 2);
+            continue;
+        }
+        if let Some(len) = string_literal_len(rest) {
+            idx += len;
+            continue;
+        }
+        if c == '\'' {
+            let after: Vec<char> = rest.chars().skip(1).take(2).collect();
+            let char_len = if after.first() == Some(&'\\') {
+                rest.get(3..)
+                    .and_then(|tail| tail.find('\''))
+                    .map(|end| end + 4)
+            } else if after.get(1) == Some(&'\'') {
+                Some(1 + after[0].len_utf8() + 1)
             } else {
-                block_content[idx + symbol.len()..].chars().next().map_or(true, |c| !is_ident_char(c))
+                None
             };
-
-            if valid_start && valid_end {
-                current_occurrence += 1;
-                if current_occurrence == occurrence {
-                    found_idx = Some(idx);
-                    break;
-                }
-            }
+            // Otherwise a lifetime: skip its name as well.
+            idx += char_len.unwrap_or_else(|| {
+                1 + rest[1..]
+                    .find(|c: char| !is_ident_char(c))
+                    .unwrap_or(rest.len() - 1)
+            });
+            continue;
+        }
+        if !(c.is_alphabetic() || c == '_') {
+            idx += c.len_utf8();
+            continue;
         }
 
-        // "rust_server" starts at 14. "serve" inside it starts at 19.
-        // ".serve" starts at 25 (dot) -> serve at 26.
-        // match_indices("serve") will return indices: 19, 26.
-        
-        // 1. Index 19: char before is '_'. is_ident_char('_') is true. !true is false. valid_start = false. SKIP.
-        // 2. Index 26: char before is '.'. is_ident_char('.') is false. valid_start = true.
-        //              char after is '('. is_ident_char('(') is false. valid_end = true. MATCH.
-        
-        assert_eq!(found_idx, Some(26), "Should find the standalone 'serve', skipping 'rust_server'");
+        let len = rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len());
+        let name = &rest[..len];
+        let after = rest[len..].trim_start();
+        let called = after.starts_with('(') || after.starts_with("::<");
+        let capitalized = name.starts_with(|c: char| c.is_uppercase());
+        if (capitalized || called) && !IGNORED.contains(&name) && seen.insert(name) {
+            names.push((idx, name.to_string()));
+        }
+        idx += len;
     }
+    names
 }
 
+/// The file's top-level `use` declarations that import one of the `used`
+/// names. Imports from `crate`, `super` and `self` are dropped, since the
+/// snippet inlines those items instead.
+fn snippet_imports(content: &str, used: &HashSet<String>) -> Vec<String> {
+    let mut imports = Vec::new();
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line
+            .strip_prefix("use ")
+            .or_else(|| line.strip_prefix("pub use "))
+        else {
+            continue;
+        };
+        let mut declaration = line.to_string();
+        while !declaration.trim_end().ends_with(';') {
+            let Some(more) = lines.next() else { break };
+            declaration.push('\n');
+            declaration.push_str(more);
+        }
+        let rest = rest.trim_start().trim_start_matches("::");
+        if ["crate::", "super::", "self::"]
+            .iter()
+            .any(|prefix| rest.starts_with(prefix))
+        {
+            continue;
+        }
+
+        // The imported names are the path segments not followed by `::`.
+        let mut names = declaration
+            .split(|c: char| !is_ident_char(c) && c != ':')
+            .filter(|token| !token.ends_with("::"))
+            .filter_map(|token| token.rsplit("::").next());
+        if declaration.contains('*') || names.any(|name| used.contains(name)) {
+            imports.push(declaration.trim_start_matches("pub ").to_string());
+        }
+    }
+    imports
+}
 
-pub async fn get_symbol_source_impl(
-    args: Value,
-    analyzer: &mut RustAnalyzerClient,
-) -> Result<ToolResult> {
+pub async fn get_hover_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
     let file_path = args
         .get("file_path")
         .and_then(|v| v.as_str())
@@ -466,61 +1235,3369 @@ pub async fn get_symbol_source_impl(
         .await
         .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
 
+    let expand_aliases = args
+        .get("expand_aliases")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
     let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
-    
-    // Try to read the line content for context (even if get_symbol_source fails)
-    let context_line = file_content
-        .lines()
-        .nth(line as usize)
-        .unwrap_or("<line out of bounds>")
-        .to_string();
 
-    let context_marker = create_position_marker(&context_line, character as u32);
+    let mut hover_result = analyzer
+        .get_hover(file_path, line, character)
+        .await?;
 
-    match analyzer
-        .get_symbol_source(file_path, line as u32, character as u32)
-        .await
-    {
-        Ok((source, range, actual_path)) => {
-            let result = json!({
-                "request": {
-                    "file_path": file_path,
-                    "symbol": symbol,
-                    "occurrence": occurrence,
-                    "line": line,
-                    "character": character,
-                    "context_line": context_line,
-                    "context_marker": context_marker
-                },
-                "result": {
-                    "file_path": actual_path,
-                    "range": range,
-                    "source": source
-                }
-            });
+    if expand_aliases {
+        let steps = expand_type_aliases(analyzer, file_path, line, character).await?;
+        hover_result.push_str(&render_alias_expansion(&steps));
+    }
 
-            Ok(ToolResult {
-                content: vec![
-                    json!({
-                        "type": "text",
-                        "text": serde_json::to_string_pretty(&result)?
-                    })
-                    .as_object()
-                    .unwrap()
-                    .clone(),
-                ],
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": hover_result
             })
-        }
-        Err(e) => Err(anyhow::anyhow!(
-            "Failed to get symbol source. Request context: Line {}: '{}'\nPosition: '{}'. Error: {}",
-            line,
-            context_line.trim(),
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn infer_type_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    // The type hint sits right after the binding name; bindings with an
+    // explicit annotation get none, and hover still knows their type.
+    let line_range = Range {
+        start: Position { line, character: 0 },
+        end: Position {
+            line: line + 1,
+            character: 0,
+        },
+    };
+    let name_end = character + symbol.encode_utf16().count() as u32;
+    let hints = analyzer
+        .inlay_hints(file_path, &line_range)
+        .await
+        .unwrap_or_default();
+    let hint = binding_type_hint(&hints, line, name_end);
+
+    let result = match hint {
+        Some(label) => json!({
+            "symbol": symbol,
+            "line": line + 1,
+            "type": label.text().trim_start_matches(':').trim(),
+            "qualified_type": qualified_label(label),
+            "source": "inlay_hint"
+        }),
+        None => {
+            let hover = analyzer.get_hover(file_path, line, character).await?;
+            let inferred = hover_binding_type(&hover, symbol).ok_or_else(|| {
+                anyhow::anyhow!("No inferred type for '{symbol}'; is it a `let` binding?")
+            })?;
+            json!({
+                "symbol": symbol,
+                "line": line + 1,
+                "type": inferred,
+                "qualified_type": null,
+                "source": "hover"
+            })
+        }
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The type hint placed at the end of a binding name.
+fn binding_type_hint(hints: &[InlayHint], line: u32, name_end: u32) -> Option<&InlayHintLabel> {
+    hints
+        .iter()
+        .find(|hint| {
+            hint.kind == Some(INLAY_HINT_KIND_TYPE)
+                && hint.position.line == line
+                && hint.position.character == name_end
+        })
+        .map(|hint| &hint.label)
+}
+
+/// Renders a hint label with every part that links to a definition spelled
+/// as `crate::module::Name`, e.g. `Map<alloc::vec::IntoIter<i32>, {closure}>`.
+fn qualified_label(label: &InlayHintLabel) -> String {
+    let InlayHintLabel::Parts(parts) = label else {
+        return label.text().trim_start_matches(':').trim().to_string();
+    };
+    let text: String = parts
+        .iter()
+        .map(|part| match &part.location {
+            Some(location) if part.value.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                let mut path: Vec<String> =
+                    crate_name_from_uri(&location.uri).into_iter().collect();
+                let mut modules = module_path_from_uri(&location.uri);
+                if modules.len() == 1 && matches!(modules[0].as_str(), "lib" | "main") {
+                    modules.clear();
+                }
+                path.extend(modules);
+                path.push(part.value.clone());
+                path.join("::")
+            }
+            _ => part.value.clone(),
+        })
+        .collect();
+    text.trim_start_matches(':').trim().to_string()
+}
+
+/// Reads the type out of a binding hover such as `let mut it: Rev<Chars<'_>>`.
+fn hover_binding_type(hover: &str, symbol: &str) -> Option<String> {
+    hover.lines().find_map(|line| {
+        let binding = line.trim().strip_prefix("let ")?;
+        let binding = binding.strip_prefix("mut ").unwrap_or(binding);
+        let (name, ty) = binding.split_once(':')?;
+        (name.trim() == symbol).then(|| ty.trim().to_string())
+    })
+}
+
+pub async fn trace_chain_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let expression = args
+        .get("expression")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing expression parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let block_start = file_content.find(code_block).ok_or_else(|| {
+        anyhow::anyhow!("Code block not found in file. Ensure the code block is an exact match.")
+    })?;
+    let expression_start = block_start
+        + code_block
+            .match_indices(expression)
+            .nth(occurrence.saturating_sub(1))
+            .map(|(idx, _)| idx)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Expression not found in the code block (occurrence {}); copy it exactly, including line breaks.",
+                    occurrence
+                )
+            })?;
+
+    let ends = chain_step_ends(expression);
+    let (start_line, start_character) = index_to_line_col(&file_content, expression_start);
+    let (last_line, _) = index_to_line_col(&file_content, expression_start + expression.len());
+    // rust-analyzer puts a chaining hint at the end of each line of a chain
+    // broken over several lines.
+    let hints = analyzer
+        .inlay_hints(
+            file_path,
+            &Range {
+                start: Position {
+                    line: start_line,
+                    character: 0,
+                },
+                end: Position {
+                    line: last_line + 1,
+                    character: 0,
+                },
+            },
+        )
+        .await
+        .unwrap_or_default();
+
+    let mut steps = Vec::new();
+    let mut previous = 0;
+    for (idx, &end) in ends.iter().enumerate() {
+        let (line, character) = index_to_line_col(&file_content, expression_start + end);
+        let range = Range {
+            start: Position {
+                line: start_line,
+                character: start_character,
+            },
+            end: Position { line, character },
+        };
+        let hovered = analyzer
+            .hover_range(file_path, &range)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|hover| hover_expression_type(&hover));
+        let (ty, source) = match hovered {
+            Some(ty) => (Some(ty), Some("hover_range")),
+            None => match hints.iter().find(|hint| {
+                hint.kind == Some(INLAY_HINT_KIND_TYPE)
+                    && hint.position.line == line
+                    && hint.position.character == character
+            }) {
+                Some(hint) => (
+                    Some(hint.label.text().trim_start_matches(':').trim().to_string()),
+                    Some("chaining_hint"),
+                ),
+                None => (None, None),
+            },
+        };
+        steps.push(json!({
+            "step": idx,
+            "call": expression[previous..end].trim(),
+            "line": line + 1,
+            "type": ty,
+            "source": source
+        }));
+        previous = end;
+    }
+
+    let resolved = steps.iter().filter(|step| !step["type"].is_null()).count();
+    let mut result = json!({
+        "file_path": file_path,
+        "expression": expression,
+        "line": start_line + 1,
+        "final_type": steps.last().map(|step| step["type"].clone()),
+        "steps": steps
+    });
+    if resolved < ends.len() {
+        result["note"] = json!(
+            "Some steps have no type: the server did not answer the range hover and no chaining hint sits at the end of that step. Put each call of the chain on its own line to get chaining hints, or check that the prefix compiles."
+        );
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Byte offsets where each step of a method chain ends: the receiver, then
+/// every `.call(..)`, `.field` or `.await` (with a trailing `?` kept in its
+/// step). Dots nested in arguments, closures or turbofish generics do not
+/// split, nor do range operators and float literals.
+fn chain_step_ends(expression: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut open: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut prev = '\0';
+    let mut chars = expression.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let next = chars.peek().map_or('\0', |&(_, next)| next);
+        if in_string {
+            if c == '\\' {
+                chars.next();
+            } else if c == '"' {
+                in_string = false;
+            }
+            prev = c;
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => open.push(c),
+            // Generic arguments only follow `::` in expressions.
+            '<' if expression[..idx].ends_with("::") || open.last() == Some(&'<') => open.push(c),
+            '>' if open.last() == Some(&'<') => {
+                open.pop();
+            }
+            ')' | ']' | '}' => {
+                open.pop();
+            }
+            '.' if open.is_empty()
+                && prev != '.'
+                && next != '.'
+                && !(prev.is_ascii_digit() && next.is_ascii_digit()) =>
+            {
+                let end = expression[..idx].trim_end().len();
+                if end > 0 {
+                    ends.push(end);
+                }
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    let end = expression.trim_end().len();
+    if ends.last() != Some(&end) && end > 0 {
+        ends.push(end);
+    }
+    ends
+}
+
+/// The type in a range hover: the first line of its code block, or the
+/// `Type:` line when rust-analyzer also shows a coercion.
+fn hover_expression_type(hover: &str) -> Option<String> {
+    if let Some(ty) = hover
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Type:"))
+    {
+        return Some(ty.trim().trim_matches('`').to_string());
+    }
+    let mut in_block = false;
+    let mut lines = Vec::new();
+    for line in hover.lines() {
+        if line.trim_start().starts_with("```") {
+            if in_block {
+                break;
+            }
+            in_block = true;
+        } else if in_block {
+            lines.push(line.trim());
+        }
+    }
+    let ty = lines.join(" ");
+    (!ty.is_empty()).then_some(ty)
+}
+
+/// One hop of a type alias chain: `alias` is declared as `target`.
+struct AliasStep {
+    alias: String,
+    target: String,
+    file_path: String,
+    line: u32,
+}
+
+const MAX_ALIAS_HOPS: usize = 8;
+
+/// Follows type aliases starting at the given position: the symbol's
+/// definition (or, for values, its type definition) and then the head type of
+/// each alias target, until a non-alias type is reached.
+async fn expand_type_aliases(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    line: u32,
+    character: u32,
+) -> Result<Vec<AliasStep>> {
+    let mut steps = Vec::new();
+
+    let mut locations = analyzer.definition_locations(file_path, line, character).await?;
+    let mut next = read_alias_at(&locations).await;
+    if next.is_none() {
+        locations = analyzer
+            .type_definition_locations(file_path, line, character)
+            .await?;
+        next = read_alias_at(&locations).await;
+    }
+
+    while let Some((step, head)) = next.take() {
+        if steps.len() >= MAX_ALIAS_HOPS
+            || steps
+                .iter()
+                .any(|seen: &AliasStep| seen.file_path == step.file_path && seen.line == step.line)
+        {
+            break;
+        }
+        let path = step.file_path.clone();
+        steps.push(step);
+
+        if let Some((head_line, head_character)) = head {
+            let locations = analyzer
+                .definition_locations(&path, head_line, head_character)
+                .await?;
+            next = read_alias_at(&locations).await;
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Reads the alias declared at the first location, together with the
+/// position of its target's head type (used to follow nested aliases).
+async fn read_alias_at(locations: &[Location]) -> Option<(AliasStep, Option<(u32, u32)>)> {
+    let location = locations.first()?;
+    let file_path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri)
+        .to_string();
+    let content = fs::read_to_string(&file_path).await.ok()?;
+
+    let line_start = content
+        .split_inclusive('\n')
+        .take(location.range.start.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let declaration = &content[line_start..];
+    let declaration = &declaration[..declaration.find(';')? + 1];
+    let (alias, target, target_offset) = parse_type_alias(declaration)?;
+
+    let head = type_head_offset(&target)
+        .map(|offset| index_to_line_col(&content, line_start + target_offset + offset));
+
+    Some((
+        AliasStep {
+            alias,
+            target,
+            file_path,
+            line: location.range.start.line,
+        },
+        head,
+    ))
+}
+
+/// Parses `type Name<..> = Target;` into the alias, its target and the byte
+/// offset of the target within `declaration`.
+fn parse_type_alias(declaration: &str) -> Option<(String, String, usize)> {
+    let keyword = declaration
+        .match_indices("type")
+        .map(|(idx, _)| idx)
+        .find(|&idx| {
+            let before_ok = declaration[..idx]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !is_ident_char(c));
+            let after_ok = declaration[idx + 4..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace);
+            before_ok && after_ok
+        })?;
+
+    // Only visibility may precede the keyword; anything else means the
+    // declaration is some other item.
+    let prefix = declaration[..keyword].trim();
+    if !(prefix.is_empty() || prefix.starts_with("pub")) || prefix.contains('\n') {
+        return None;
+    }
+
+    let after_keyword = keyword + 4;
+    let mut depth = 0i32;
+    let mut eq = None;
+    for (idx, c) in declaration[after_keyword..].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            '=' if depth == 0 => {
+                eq = Some(after_keyword + idx);
+                break;
+            }
+            ';' => return None,
+            _ => {}
+        }
+    }
+    let eq = eq?;
+
+    let alias = declaration[after_keyword..eq].split_whitespace().collect::<Vec<_>>().join(" ");
+    let raw_target = &declaration[eq + 1..declaration.rfind(';')?];
+    let target_offset = eq + 1 + (raw_target.len() - raw_target.trim_start().len());
+    let target = raw_target.trim().to_string();
+
+    if alias.is_empty() || target.is_empty() {
+        return None;
+    }
+    Some((alias, target, target_offset))
+}
+
+/// Byte offset of the head type's name in a type expression, e.g. `Result` in
+/// `&'a std::result::Result<T, E>`. `None` for tuples, arrays, slices and
+/// function pointers.
+fn type_head_offset(ty: &str) -> Option<usize> {
+    let mut offset = 0;
+    loop {
+        let rest = &ty[offset..];
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+
+        if let Some(after) = trimmed.strip_prefix('&') {
+            offset += trimmed.len() - after.len();
+        } else if trimmed.starts_with('\'') {
+            offset += trimmed.find(char::is_whitespace)?;
+        } else if let Some(keyword) = ["mut ", "dyn ", "impl "]
+            .iter()
+            .find(|keyword| trimmed.starts_with(**keyword))
+        {
+            offset += keyword.len();
+        } else {
+            break;
+        }
+    }
+
+    let rest = &ty[offset..];
+    if !rest.starts_with(|c: char| is_ident_char(c) || c == ':') || rest.starts_with("fn(") {
+        return None;
+    }
+
+    let path_end = rest.find(|c: char| !(is_ident_char(c) || c == ':')).unwrap_or(rest.len());
+    let head_start = rest[..path_end].rfind("::").map_or(0, |idx| idx + 2);
+    (head_start < path_end).then_some(offset + head_start)
+}
+
+fn render_alias_expansion(steps: &[AliasStep]) -> String {
+    let Some(last) = steps.last() else {
+        return "\n\n---\nType alias expansion: no type alias found for this symbol.".to_string();
+    };
+
+    let mut output = String::from("\n\n---\nType alias expansion:\n");
+    for step in steps {
+        output.push_str(&format!(
+            "- `{}` = `{}` ({}:{})\n",
+            step.alias,
+            step.target,
+            step.file_path,
+            step.line + 1
+        ));
+    }
+    output.push_str(&format!("Resolved type: `{}`", last.target));
+    output
+}
+
+pub async fn types_equal_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let left = args
+        .get("left")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing left parameter"))?;
+    let right = args
+        .get("right")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing right parameter"))?;
+    let right_file_path = args
+        .get("right_file_path")
+        .and_then(|v| v.as_str())
+        .unwrap_or(file_path);
+
+    let mut sides = Vec::new();
+    for (path, written) in [(file_path, left), (right_file_path, right)] {
+        let content = fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        let start = content
+            .match_indices(written)
+            .map(|(idx, _)| idx)
+            .find(|&idx| is_valid_code_context(&content, idx))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "`{}` not found in {}; copy the type exactly as written in the file",
+                    written,
+                    path
+                )
+            })?;
+        let expr = parse_type_expr(written)
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a type expression", written))?;
+        let mut resolution = TypeResolution {
+            aliases: Vec::new(),
+            unresolved: Vec::new(),
+        };
+        let resolved = resolve_type_expr(
+            analyzer,
+            path,
+            &content,
+            start,
+            &expr,
+            &HashMap::new(),
+            &mut resolution,
+        )
+        .await?;
+        sides.push(json!({
+            "written": written,
+            "file_path": path,
+            "resolved": resolved,
+            "aliases": resolution
+                .aliases
+                .iter()
+                .map(|step| json!({
+                    "alias": step.alias,
+                    "target": step.target,
+                    "file_path": step.file_path,
+                    "line": step.line + 1
+                }))
+                .collect::<Vec<_>>(),
+            "unresolved": resolution.unresolved
+        }));
+    }
+
+    let right = sides.pop().unwrap_or_default();
+    let left = sides.pop().unwrap_or_default();
+    let equal = left["resolved"] == right["resolved"];
+    let result = json!({
+        "equal": equal,
+        "common_type": if equal { left["resolved"].clone() } else { Value::Null },
+        "left": left,
+        "right": right,
+        "note": "Each path is resolved with go-to-definition to the crate and module defining it, and type aliases are expanded with their generic arguments (and defaults) substituted. Lifetimes are ignored, and `dyn`/`impl` types and function pointers are compared as written. Paths listed under `unresolved` (primitives, generic parameters, or anything rust-analyzer could not resolve) are compared as written."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// A parsed type expression. Offsets are bytes into the parsed text.
+#[derive(Debug, PartialEq)]
+enum TypeExpr {
+    /// `a::b::Name<Args>`, each segment with its offset; generic arguments
+    /// are kept for the last segment only.
+    Path {
+        segments: Vec<(String, usize)>,
+        args: Vec<TypeArg>,
+    },
+    Reference {
+        mutable: bool,
+        inner: Box<TypeExpr>,
+    },
+    Pointer {
+        mutable: bool,
+        inner: Box<TypeExpr>,
+    },
+    Tuple(Vec<TypeExpr>),
+    Slice(Box<TypeExpr>),
+    Array(Box<TypeExpr>, String),
+    /// `dyn Trait`, `impl Trait`, `fn(..)`, `_` and `!`, kept as written.
+    Verbatim(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum TypeArg {
+    Type(TypeExpr),
+    /// `Item = T` of a trait path.
+    Binding(String, TypeExpr),
+    /// Lifetimes and const arguments, as written.
+    Other(String),
+}
+
+/// Parses a whole type expression; `None` when `text` is not one.
+fn parse_type_expr(text: &str) -> Option<TypeExpr> {
+    let mut tokens: Vec<(&str, usize)> = Vec::new();
+    for token in tokenize(text) {
+        match (token.kind, &text[token.start..token.end]) {
+            ("whitespace" | "comment" | "doc_comment", _) => {}
+            // `Vec<Vec<u8>>` closes two lists.
+            (_, ">>") => {
+                tokens.push((">", token.start));
+                tokens.push((">", token.start + 1));
+            }
+            (_, "&&") => {
+                tokens.push(("&", token.start));
+                tokens.push(("&", token.start + 1));
+            }
+            (_, word) => tokens.push((word, token.start)),
+        }
+    }
+    let mut idx = 0;
+    let expr = parse_type_tokens(text, &tokens, &mut idx)?;
+    (idx == tokens.len()).then_some(expr)
+}
+
+fn parse_type_tokens(text: &str, tokens: &[(&str, usize)], idx: &mut usize) -> Option<TypeExpr> {
+    let (token, start) = *tokens.get(*idx)?;
+    match token {
+        "&" | "*" => {
+            *idx += 1;
+            if token == "&"
+                && tokens
+                    .get(*idx)
+                    .is_some_and(|(next, _)| next.starts_with('\''))
+            {
+                *idx += 1;
+            }
+            let mutable = match tokens.get(*idx).map(|(next, _)| *next) {
+                Some("mut") => true,
+                Some("const") => false,
+                _ if token == "*" => return None,
+                _ => {
+                    *idx -= 1;
+                    false
+                }
+            };
+            *idx += 1;
+            let inner = Box::new(parse_type_tokens(text, tokens, idx)?);
+            Some(if token == "&" {
+                TypeExpr::Reference { mutable, inner }
+            } else {
+                TypeExpr::Pointer { mutable, inner }
+            })
+        }
+        "(" => {
+            *idx += 1;
+            let mut items = Vec::new();
+            let mut trailing_comma = false;
+            while tokens.get(*idx)?.0 != ")" {
+                items.push(parse_type_tokens(text, tokens, idx)?);
+                trailing_comma = tokens.get(*idx)?.0 == ",";
+                if trailing_comma {
+                    *idx += 1;
+                }
+            }
+            *idx += 1;
+            if items.len() == 1 && !trailing_comma {
+                return items.pop();
+            }
+            Some(TypeExpr::Tuple(items))
+        }
+        "[" => {
+            *idx += 1;
+            let inner = Box::new(parse_type_tokens(text, tokens, idx)?);
+            match tokens.get(*idx)?.0 {
+                "]" => {
+                    *idx += 1;
+                    Some(TypeExpr::Slice(inner))
+                }
+                ";" => {
+                    let len_start = tokens.get(*idx + 1)?.1;
+                    let close = balanced_end(tokens, *idx + 1);
+                    let len_end = tokens.get(close).map_or(text.len(), |(_, offset)| *offset);
+                    *idx = close + 1;
+                    Some(TypeExpr::Array(
+                        inner,
+                        collapse_whitespace(&text[len_start..len_end]),
+                    ))
+                }
+                _ => None,
+            }
+        }
+        "dyn" | "impl" | "fn" | "unsafe" | "extern" | "for" | "_" | "!" => {
+            let end = balanced_end(tokens, *idx);
+            let end_offset = tokens.get(end).map_or(text.len(), |(_, offset)| *offset);
+            *idx = end;
+            Some(TypeExpr::Verbatim(collapse_whitespace(
+                &text[start..end_offset],
+            )))
+        }
+        _ => {
+            let mut segments = Vec::new();
+            if token == "::" {
+                *idx += 1;
+            }
+            loop {
+                let (segment, offset) = *tokens.get(*idx)?;
+                if !segment.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                    return None;
+                }
+                segments.push((segment.to_string(), offset));
+                *idx += 1;
+                let next = tokens.get(*idx).map(|(next, _)| *next);
+                let after = tokens.get(*idx + 1).map(|(after, _)| *after);
+                if next == Some("::") && after != Some("<") {
+                    *idx += 1;
+                } else {
+                    break;
+                }
+            }
+            if tokens.get(*idx).is_some_and(|(next, _)| *next == "::") {
+                *idx += 1;
+            }
+            let mut args = Vec::new();
+            if tokens.get(*idx).is_some_and(|(next, _)| *next == "<") {
+                *idx += 1;
+                while tokens.get(*idx)?.0 != ">" {
+                    args.push(parse_type_arg(text, tokens, idx)?);
+                    if tokens.get(*idx)?.0 == "," {
+                        *idx += 1;
+                    }
+                }
+                *idx += 1;
+            }
+            Some(TypeExpr::Path { segments, args })
+        }
+    }
+}
+
+fn parse_type_arg(text: &str, tokens: &[(&str, usize)], idx: &mut usize) -> Option<TypeArg> {
+    let (token, start) = *tokens.get(*idx)?;
+    if token.starts_with('\'') {
+        *idx += 1;
+        return Some(TypeArg::Other(token.to_string()));
+    }
+    if token.starts_with(|c: char| c.is_ascii_digit() || c == '{' || c == '-') {
+        let end = balanced_end(tokens, *idx);
+        let end_offset = tokens.get(end).map_or(text.len(), |(_, offset)| *offset);
+        *idx = end;
+        return Some(TypeArg::Other(collapse_whitespace(
+            &text[start..end_offset],
+        )));
+    }
+    if tokens.get(*idx + 1).is_some_and(|(next, _)| *next == "=") {
+        *idx += 2;
+        let ty = parse_type_tokens(text, tokens, idx)?;
+        return Some(TypeArg::Binding(token.to_string(), ty));
+    }
+    parse_type_tokens(text, tokens, idx).map(TypeArg::Type)
+}
+
+/// Index of the first token from `idx` on that ends the current list item:
+/// a `,`, `;` or closing bracket outside any brackets opened after `idx`.
+fn balanced_end(tokens: &[(&str, usize)], idx: usize) -> usize {
+    let mut depth = 0i32;
+    for (offset, (token, _)) in tokens.iter().enumerate().skip(idx) {
+        match *token {
+            "(" | "[" | "{" | "<" => depth += 1,
+            ")" | "]" | "}" | ">" if depth == 0 => return offset,
+            ")" | "]" | "}" | ">" => depth -= 1,
+            "," | ";" if depth == 0 => return offset,
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// What resolving one side of `types_equal` went through.
+struct TypeResolution {
+    aliases: Vec<AliasStep>,
+    /// Paths compared as written.
+    unresolved: Vec<String>,
+}
+
+/// Renders `expr`, written at byte `base` of `file_path`, with every path
+/// fully qualified and every type alias expanded. `substitutions` maps the
+/// generic parameters of the alias being expanded to their arguments.
+async fn resolve_type_expr(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    content: &str,
+    base: usize,
+    expr: &TypeExpr,
+    substitutions: &HashMap<String, String>,
+    resolution: &mut TypeResolution,
+) -> Result<String> {
+    let mut parts = Vec::new();
+    let children: Vec<&TypeExpr> = match expr {
+        TypeExpr::Reference { inner, .. }
+        | TypeExpr::Pointer { inner, .. }
+        | TypeExpr::Slice(inner)
+        | TypeExpr::Array(inner, _) => vec![inner],
+        TypeExpr::Tuple(items) => items.iter().collect(),
+        TypeExpr::Path { args, .. } => args
+            .iter()
+            .filter_map(|arg| match arg {
+                TypeArg::Type(ty) | TypeArg::Binding(_, ty) => Some(ty),
+                TypeArg::Other(_) => None,
+            })
+            .collect(),
+        TypeExpr::Verbatim(_) => Vec::new(),
+    };
+    for child in children {
+        parts.push(
+            Box::pin(resolve_type_expr(
+                analyzer,
+                file_path,
+                content,
+                base,
+                child,
+                substitutions,
+                resolution,
+            ))
+            .await?,
+        );
+    }
+
+    Ok(match expr {
+        TypeExpr::Reference { mutable: true, .. } => format!("&mut {}", parts[0]),
+        TypeExpr::Reference { .. } => format!("&{}", parts[0]),
+        TypeExpr::Pointer { mutable: true, .. } => format!("*mut {}", parts[0]),
+        TypeExpr::Pointer { .. } => format!("*const {}", parts[0]),
+        TypeExpr::Slice(_) => format!("[{}]", parts[0]),
+        TypeExpr::Array(_, len) => format!("[{}; {}]", parts[0], len),
+        TypeExpr::Tuple(_) if parts.len() == 1 => format!("({},)", parts[0]),
+        TypeExpr::Tuple(_) => format!("({})", parts.join(", ")),
+        TypeExpr::Verbatim(text) => text.clone(),
+        TypeExpr::Path { segments, args } => {
+            if let [(name, _)] = segments.as_slice()
+                && let Some(substituted) = substitutions.get(name)
+            {
+                return Ok(substituted.clone());
+            }
+            // Lifetimes do not make types different here.
+            let mut resolved = parts.into_iter();
+            let args: Vec<(Option<&str>, String)> = args
+                .iter()
+                .filter_map(|arg| match arg {
+                    TypeArg::Type(_) => resolved.next().map(|ty| (None, ty)),
+                    TypeArg::Binding(name, _) => {
+                        resolved.next().map(|ty| (Some(name.as_str()), ty))
+                    }
+                    TypeArg::Other(text) if text.starts_with('\'') => None,
+                    TypeArg::Other(text) => Some((None, text.clone())),
+                })
+                .collect();
+            let (_, head) = segments.last().cloned().unwrap_or_default();
+            let written = segments
+                .iter()
+                .map(|(segment, _)| segment.as_str())
+                .collect::<Vec<_>>()
+                .join("::");
+            resolve_type_path(
+                analyzer,
+                file_path,
+                content,
+                base + head,
+                written,
+                args,
+                resolution,
+            )
+            .await?
+        }
+    })
+}
+
+/// Resolves the path whose last segment is at byte `head` of `file_path`,
+/// with its already resolved generic `args` (named for associated type
+/// bindings): expands it when it names a type
+/// alias, and otherwise qualifies it with the crate and module defining it.
+async fn resolve_type_path(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    content: &str,
+    head: usize,
+    written: String,
+    args: Vec<(Option<&str>, String)>,
+    resolution: &mut TypeResolution,
+) -> Result<String> {
+    let (line, character) = index_to_line_col(content, head);
+    let locations = analyzer
+        .definition_locations(file_path, line, character)
+        .await
+        .unwrap_or_default();
+
+    if resolution.aliases.len() < MAX_ALIAS_HOPS
+        && let Some((step, alias_content, declaration_start, target_start)) =
+            read_alias_definition(&locations).await
+    {
+        let declaration = &alias_content[declaration_start..];
+        let parameters = alias_parameters(declaration);
+        let alias_path = step.file_path.clone();
+        let target = parse_type_expr(&step.target);
+        resolution.aliases.push(step);
+
+        // Arguments fill the alias's type parameters in order, then their
+        // defaults, read in the alias's own scope.
+        let mut positional = args.iter().filter(|(name, _)| name.is_none());
+        let mut substitutions = HashMap::new();
+        for (name, default) in parameters {
+            let value = match (positional.next().map(|(_, ty)| ty), default) {
+                (Some(arg), _) => arg.clone(),
+                (None, Some((offset, text))) => match parse_type_expr(&text) {
+                    Some(default) => {
+                        Box::pin(resolve_type_expr(
+                            analyzer,
+                            &alias_path,
+                            &alias_content,
+                            declaration_start + offset,
+                            &default,
+                            &substitutions,
+                            resolution,
+                        ))
+                        .await?
+                    }
+                    None => text,
+                },
+                (None, None) => name.clone(),
+            };
+            substitutions.insert(name, value);
+        }
+        if let Some(target) = target {
+            return Box::pin(resolve_type_expr(
+                analyzer,
+                &alias_path,
+                &alias_content,
+                target_start,
+                &target,
+                &substitutions,
+                resolution,
+            ))
+            .await;
+        }
+    }
+
+    let qualified = match locations.first() {
+        Some(location) => qualified_item_path(location).await,
+        None => None,
+    };
+    let path = qualified.unwrap_or_else(|| {
+        resolution.unresolved.push(written.clone());
+        written
+    });
+    if args.is_empty() {
+        return Ok(path);
+    }
+    let args: Vec<String> = args
+        .into_iter()
+        .map(|(name, ty)| match name {
+            Some(name) => format!("{} = {}", name, ty),
+            None => ty,
+        })
+        .collect();
+    Ok(format!("{}<{}>", path, args.join(", ")))
+}
+
+/// Like [`read_alias_at`], returning the alias file's content, the byte
+/// offset of the declaration and that of its target in it.
+async fn read_alias_definition(
+    locations: &[Location],
+) -> Option<(AliasStep, String, usize, usize)> {
+    let location = locations.first()?;
+    let file_path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri)
+        .to_string();
+    let content = fs::read_to_string(&file_path).await.ok()?;
+    let line_start = line_start_offset(&content, location.range.start.line as usize)?;
+    let declaration = &content[line_start..];
+    let declaration = &declaration[..declaration.find(';')? + 1];
+    let (alias, target, target_offset) = parse_type_alias(declaration)?;
+    Some((
+        AliasStep {
+            alias,
+            target,
+            file_path,
+            line: location.range.start.line,
+        },
+        content.clone(),
+        line_start,
+        line_start + target_offset,
+    ))
+}
+
+/// The type parameters of a `type Name<..> = ..;` declaration, each with its
+/// default's offset in `declaration` and text. Lifetime and const
+/// parameters are skipped.
+fn alias_parameters(declaration: &str) -> Vec<(String, Option<(usize, String)>)> {
+    let Some((_, _, target_offset)) = parse_type_alias(declaration) else {
+        return Vec::new();
+    };
+    let head = &declaration[..target_offset];
+    let Some(open) = head.find('<') else {
+        return Vec::new();
+    };
+    let Some((list, _)) = split_generic_params(&head[open..]) else {
+        return Vec::new();
+    };
+
+    let mut parameters = Vec::new();
+    let mut search_from = open + 1;
+    for parameter in list {
+        let offset = search_from + head[search_from..].find(parameter.as_str()).unwrap_or(0);
+        search_from = offset + parameter.len();
+        if parameter.starts_with('\'') || parameter.starts_with("const ") {
+            continue;
+        }
+        let (name, default) = match parameter.split_once('=') {
+            Some((name, default)) => {
+                let default_start = offset + name.len() + 1;
+                let leading = default.len() - default.trim_start().len();
+                (
+                    name,
+                    Some((default_start + leading, default.trim().to_string())),
+                )
+            }
+            None => (parameter.as_str(), None),
+        };
+        let name = name.split(':').next().unwrap_or(name).trim().to_string();
+        parameters.push((name, default));
+    }
+    parameters
+}
+
+/// `crate::module::Name` for the item defined at `location`, the crate being
+/// named after its directory (without the version of a registry checkout)
+/// and inline modules included. `None` when the location is not a struct,
+/// enum, union or trait, e.g. a generic parameter.
+async fn qualified_item_path(location: &Location) -> Option<String> {
+    let file_path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri);
+    let content = fs::read_to_string(file_path).await.ok()?;
+    let line = content.lines().nth(location.range.start.line as usize)?;
+    let name_start = utf16_column_offset(line, location.range.start.character);
+    let name: String = line[name_start..]
+        .chars()
+        .take_while(|c| is_ident_char(*c) || *c == '#')
+        .collect();
+    let keyword = line[..name_start].split_whitespace().last()?;
+    if !matches!(keyword, "struct" | "enum" | "union" | "trait") {
+        return None;
+    }
+
+    let crate_dir = crate_name_from_uri(&location.uri)?;
+    let crate_name = split_crate_dir(&crate_dir).map_or(crate_dir.as_str(), |(name, _)| name);
+    let mut path = vec![crate_name.replace('-', "_")];
+    let mut modules = module_path_from_uri(&location.uri);
+    // `module_path_from_uri` keeps the stem of crate roots.
+    if Path::new(file_path)
+        .parent()
+        .is_some_and(|dir| dir.ends_with("src"))
+        && matches!(modules.last().map(String::as_str), Some("lib" | "main"))
+    {
+        modules.pop();
+    }
+    path.extend(modules);
+    path.extend(inline_module_names(
+        &content,
+        location.range.start.line as usize,
+    ));
+    path.push(name);
+    Some(path.join("::"))
+}
+
+/// The inline `mod name {` blocks open at 0-based `line` of `content`,
+/// outermost first.
+fn inline_module_names(content: &str, line: usize) -> Vec<String> {
+    let mut open: Vec<(String, i32)> = Vec::new();
+    let mut depth = 0;
+    for text in content.lines().take(line) {
+        let trimmed = text.trim_start();
+        let item = trimmed
+            .strip_prefix("pub")
+            .map_or(trimmed, |rest| match rest.trim_start().strip_prefix('(') {
+                Some(scoped) => scoped.split_once(')').map_or("", |(_, rest)| rest),
+                None => rest,
+            })
+            .trim_start();
+        if let Some(name) = item.strip_prefix("mod ")
+            && text.trim_end().ends_with('{')
+        {
+            let name = name.trim_end_matches('{').trim();
+            open.push((name.to_string(), depth + 1));
+        }
+        depth += text.matches('{').count() as i32 - text.matches('}').count() as i32;
+        while open.last().is_some_and(|(_, inner)| *inner > depth) {
+            open.pop();
+        }
+    }
+    open.into_iter().map(|(name, _)| name).collect()
+}
+
+pub fn find_block_range(
+    file_content: &str,
+    code_block: &str,
+    occurrence: usize,
+) -> Result<(u32, u32, u32, u32)> {
+    let mut current_pos = 0;
+    let mut current_occurrence = 0;
+
+    while let Some(start_idx) = file_content[current_pos..].find(code_block) {
+        let absolute_start_idx = current_pos + start_idx;
+        current_occurrence += 1;
+
+        if current_occurrence == occurrence {
+            let absolute_end_idx = absolute_start_idx + code_block.len();
+            let (start_line, start_char) = index_to_line_col(file_content, absolute_start_idx);
+            let (end_line, end_char) = index_to_line_col(file_content, absolute_end_idx);
+            return Ok((start_line, start_char, end_line, end_char));
+        }
+
+        current_pos = absolute_start_idx + 1;
+    }
+
+    Err(anyhow::anyhow!(
+        "Code block not found (occurrence #{}) in file. Ensure the code block is an exact match.",
+        occurrence
+    ))
+}
+
+pub fn find_symbol_location(
+    file_content: &str,
+    symbol: &str,
+    code_block: &str,
+    occurrence: usize,
+) -> Result<(u32, u32)> {
+    // Find the code block
+    // We assume the LLM copies the block accurately.
+    let block_start_idx = file_content
+        .find(code_block)
+        .ok_or_else(|| anyhow::anyhow!("Code block not found in file. Ensure the code block is an exact match."))?;
+
+    // Find the symbol within the code block
+    let block_content = &file_content[block_start_idx..block_start_idx + code_block.len()];
+    
+    let mut current_occurrence = 0;
+    let mut symbol_offset_in_block = 0;
+    let mut found = false;
+
+    // `r#type` and `type` name the same identifier; a raw query only matches raw tokens
+    // because the bare spelling would be the keyword.
+    let (raw_query, name) = match symbol.strip_prefix("r#") {
+        Some(name) => (true, name),
+        None => (false, symbol),
+    };
+
+    for (idx, _) in block_content.match_indices(name) {
+        let Some((token_start, is_raw)) = identifier_token_start(block_content, idx, name.len())
+        else {
+            continue;
+        };
+        if raw_query && !is_raw {
+            continue;
+        }
+
+        let absolute_symbol_idx = block_start_idx + token_start;
+        let is_code = is_valid_code_context(file_content, absolute_symbol_idx);
+
+        if is_code {
+            current_occurrence += 1;
+            if current_occurrence == occurrence {
+                symbol_offset_in_block = token_start;
+                found = true;
+                break;
+            }
+        }
+    }
+
+    if !found {
+        return Err(anyhow::anyhow!(
+            "Found only {} occurrences of symbol '{}' (whole word, not in comment/string) in the code block, expected #{}", 
+            current_occurrence, 
+            symbol, 
+            occurrence
+        ));
+    }
+
+    let absolute_symbol_idx = block_start_idx + symbol_offset_in_block;
+
+    // Convert index to line and character (LSP compatible)
+    Ok(index_to_line_col(file_content, absolute_symbol_idx))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Checks that `text[idx..idx + len]` is a whole identifier token and returns where
+/// the token starts, stepping back over an `r#` raw-identifier prefix when present.
+fn identifier_token_start(text: &str, idx: usize, len: usize) -> Option<(usize, bool)> {
+    let valid_end = text[idx + len..]
+        .chars()
+        .next()
+        .is_none_or(|c| !is_ident_char(c));
+    if !valid_end {
+        return None;
+    }
+
+    let before = &text[..idx];
+    if let Some(prefix) = before.strip_suffix("r#") {
+        let valid_start = prefix.chars().next_back().is_none_or(|c| !is_ident_char(c));
+        return valid_start.then_some((idx - 2, true));
+    }
+
+    let valid_start = before.chars().next_back().is_none_or(|c| !is_ident_char(c));
+    valid_start.then_some((idx, false))
+}
+
+pub(crate) fn index_to_line_col(text: &str, index: usize) -> (u32, u32) {
+    let prefix = &text[..index];
+    let line = prefix.matches('\n').count() as u32;
+    
+    let last_newline_pos = prefix.rfind('\n').map(|p| p + 1).unwrap_or(0);
+    let line_str = &prefix[last_newline_pos..];
+    
+    // Convert to UTF-16 code units for LSP
+    let character = line_str.encode_utf16().count() as u32;
+    
+    (line, character)
+}
+
+pub(crate) fn is_valid_code_context(text: &str, target_idx: usize) -> bool {
+    let mut chars = text.char_indices().peekable();
+    let mut in_string = false;
+    let mut in_line_comment = false;
+    let mut block_comment_depth = 0;
+    
+    while let Some((idx, c)) = chars.next() {
+        if idx >= target_idx {
+             return !in_string && !in_line_comment && block_comment_depth == 0;
+        }
+
+        if in_line_comment {
+            if c == '\n' {
+                in_line_comment = false;
+            }
+            continue;
+        }
+        
+        if block_comment_depth > 0 {
+             if c == '/' {
+                if let Some((_, '*')) = chars.peek() {
+                    chars.next();
+                    block_comment_depth += 1;
+                }
+            } else if c == '*' {
+                if let Some((_, '/')) = chars.peek() {
+                    chars.next();
+                    block_comment_depth -= 1;
+                }
+            }
+            continue;
+        }
+        
+        if in_string {
+            if c == '\\' {
+                chars.next();
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        
+        match c {
+            '/' => {
+                if let Some((_, '/')) = chars.peek() {
+                    chars.next();
+                    in_line_comment = true;
+                } else if let Some((_, '*')) = chars.peek() {
+                    chars.next();
+                    block_comment_depth += 1;
+                }
+            }
+            '"' => {
+                in_string = true;
+            }
+            _ => {}
+        }
+    }
+    
+    !in_string && !in_line_comment && block_comment_depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_column_offset() {
+        let content = "fn a() {}\nlet é = 1;\nlet s = \"😀b\";\n";
+        assert_eq!(line_start_offset(content, 0), Some(0));
+        assert_eq!(line_start_offset(content, 1), Some(10));
+        assert_eq!(line_start_offset(content, 3), Some(content.len()));
+        assert_eq!(line_start_offset(content, 4), None);
+
+        assert_eq!(utf16_column_offset("let é = 1;", 4), 4);
+        assert_eq!(utf16_column_offset("let é = 1;", 5), 6);
+        assert_eq!(utf16_column_offset("ab", 2), 2);
+        // Past the end of the line clamps to it, not into the next line.
+        assert_eq!(utf16_column_offset("ab\ncd", 3), 2);
+        // `😀` is a surrogate pair: two UTF-16 units, four bytes.
+        let line = "\"😀b\"";
+        assert_eq!(utf16_column_offset(line, 1), 1);
+        assert_eq!(utf16_column_offset(line, 3), 5);
+        assert_eq!(&line[utf16_column_offset(line, 3)..], "b\"");
+        // A column inside the pair moves past it.
+        assert_eq!(utf16_column_offset(line, 2), 5);
+    }
+
+    #[test]
+    fn test_chain_step_ends() {
+        let expression = "vec.iter().map(|x| x.0 * 2).filter(|x| *x > 1.5).collect::<Vec<_>>()";
+        let steps: Vec<&str> = {
+            let ends = chain_step_ends(expression);
+            let mut previous = 0;
+            ends.iter()
+                .map(|&end| {
+                    let step = expression[previous..end].trim();
+                    previous = end;
+                    step
+                })
+                .collect()
+        };
+        assert_eq!(
+            steps,
+            vec![
+                "vec",
+                ".iter()",
+                ".map(|x| x.0 * 2)",
+                ".filter(|x| *x > 1.5)",
+                ".collect::<Vec<_>>()"
+            ]
+        );
+
+        let multiline = "client\n    .get(\"a.b\")\n    .send()\n    .await?\n    .0";
+        assert_eq!(chain_step_ends(multiline).len(), 5);
+        assert_eq!(chain_step_ends("(0..10).rev()").len(), 2);
+
+        assert_eq!(
+            hover_expression_type("```rust\nMap<Iter<'_, i32>, impl Fn(&i32) -> i32>\n```"),
+            Some("Map<Iter<'_, i32>, impl Fn(&i32) -> i32>".to_string())
+        );
+        assert_eq!(
+            hover_expression_type("```text\nType:       Vec<u8>\nCoerced to: &[u8]\n```"),
+            Some("Vec<u8>".to_string())
+        );
+    }
+    #[test]
+    fn test_named_symbols() {
+        let symbol = |name: &str, line: u32, children: Vec<DocumentSymbol>| {
+            let range = Range {
+                start: Position { line, character: 0 },
+                end: Position { line, character: 1 },
+            };
+            DocumentSymbol {
+                name: name.to_string(),
+                detail: None,
+                kind: 12,
+                range: range.clone(),
+                selection_range: range,
+                children: Some(children),
+            }
+        };
+        let symbols = vec![
+            symbol("parse", 0, vec![]),
+            symbol("impl Parser", 1, vec![symbol("parse", 2, vec![])]),
+            symbol(
+                "impl<T: Clone> fmt::Display for Wrapper<T>",
+                3,
+                vec![symbol("fmt", 4, vec![])],
+            ),
+            symbol("implement", 5, vec![]),
+        ];
+        let lines = |path: &str| -> Vec<u32> {
+            named_symbols(&symbols, path)
+                .iter()
+                .map(|found| found.range.start.line)
+                .collect()
+        };
+
+        assert_eq!(lines("parse"), vec![0, 2]);
+        assert_eq!(lines("Parser::parse"), vec![2]);
+        assert_eq!(lines("Wrapper::fmt"), vec![4]);
+        assert_eq!(lines("Display::fmt"), vec![4]);
+        assert_eq!(lines("impl Parser"), vec![1]);
+        assert_eq!(lines("Parser"), vec![1]);
+        assert!(lines("Other::parse").is_empty());
+        assert!(!symbol_named("implement", "ment"));
+    }
+    #[test]
+    fn test_referenced_names() {
+        let source = r#"fn load(path: &str) -> Result<Config, Error> {
+    // Parser is mentioned in a comment
+    let text = read_config(path, "Loader(x)")?;
+    let value: &'static str = 'a'.to_string().as_str();
+    Ok(Config::parse(&text).map_err(Error::from)?)
+}"#;
+        let names: Vec<String> = referenced_names(source)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "load",
+                "Config",
+                "Error",
+                "read_config",
+                "to_string",
+                "as_str",
+                "parse",
+                "map_err"
+            ]
+        );
+        let (offset, _) = referenced_names(source)[3].clone();
+        assert!(source[offset..].starts_with("read_config(path"));
+    }
+
+    #[test]
+    fn test_snippet_imports() {
+        let content = "use std::collections::{HashMap, HashSet};\nuse std::fmt::Display as Show;\nuse crate::config::Config;\nuse serde::{\n    Deserialize,\n    Serialize,\n};\nuse std::io::*;\n\nfn main() {}\n";
+        let used: HashSet<String> = ["HashSet", "Show", "Config", "Serialize"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            snippet_imports(content, &used),
+            vec![
+                "use std::collections::{HashMap, HashSet};",
+                "use std::fmt::Display as Show;",
+                "use serde::{\n    Deserialize,\n    Serialize,\n};",
+                "use std::io::*;",
+            ]
+        );
+    }
+    #[test]
+    fn test_scan_blocks_nesting() {
+        let source = r#"fn run(items: &[Item]) -> u32 {
+    let name = "}{";
+    'outer: for item in items {
+        let point = Point { x: 1, y: '}' };
+        items.iter().for_each(|a, b| {
+            while let Some(x) = next() {
+                match x {
+                    Some(v) => loop { break },
+                    None => {}
+                }
+            }
+        });
+        if let Some(y) = item.y { continue 'outer } else { json!({"k": {}}) }
+    }
+    0
+}
+"#;
+        let target = source.find("break").unwrap();
+        let blocks = scan_blocks(source);
+        // Blocks are listed as they close, so innermost first.
+        let enclosing: Vec<(&str, Option<&str>)> = blocks
+            .iter()
+            .filter(|block| block.open < target && target <= block.close)
+            .map(|block| (block.kind.as_str(), block.label.as_deref()))
+            .collect();
+        assert_eq!(
+            enclosing,
+            vec![
+                ("loop", None),
+                ("match", None),
+                ("while", None),
+                ("closure", None),
+                ("for", Some("'outer")),
+                ("function", None),
+            ]
+        );
+
+        let kinds: Vec<&str> = blocks.iter().map(|block| block.kind.as_str()).collect();
+        assert!(kinds.contains(&"match_arm"));
+        assert!(kinds.contains(&"if"));
+        assert!(kinds.contains(&"else"));
+        assert!(!kinds.contains(&"braces"));
+    }
+
+    #[test]
+    fn test_block_kind_from_header() {
+        assert_eq!(
+            BlockKind::from_header("let Some(x) = opt else"),
+            BlockKind::LetElse
+        );
+        assert_eq!(BlockKind::from_header("} else if ready"), BlockKind::If);
+        assert_eq!(
+            BlockKind::from_header("move |x: u32| -> u32"),
+            BlockKind::Closure
+        );
+        assert_eq!(
+            BlockKind::from_header("pub unsafe fn raw(..)"),
+            BlockKind::Function
+        );
+        assert_eq!(BlockKind::from_header("let total ="), BlockKind::Block);
+        assert_eq!(BlockKind::from_header("if a || b"), BlockKind::If);
+        assert_eq!(BlockKind::from_header("async move"), BlockKind::Async);
+    }
+    #[test]
+    fn test_docs_origin_of_path() {
+        let origin = DocsOrigin::of_path(
+            "/home/dev/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f/tokio-util-0.7.13/src/codec/mod.rs",
+        );
+        assert_eq!(
+            origin,
+            DocsOrigin::Dependency {
+                name: "tokio-util".to_string(),
+                version: "0.7.13".to_string()
+            }
+        );
+        assert_eq!(
+            origin.search_url("Framed").as_deref(),
+            Some("https://docs.rs/tokio-util/0.7.13/?search=Framed")
+        );
+
+        let std = DocsOrigin::of_path(
+            "/home/dev/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/core/src/option.rs",
+        );
+        assert_eq!(std, DocsOrigin::Std("core".to_string()));
+        assert_eq!(
+            std.search_url("Option").as_deref(),
+            Some("https://doc.rust-lang.org/stable/core/?search=Option")
+        );
+
+        assert_eq!(DocsOrigin::of_path("/work/app/src/main.rs"), DocsOrigin::Local);
+    }
+
+    #[test]
+    fn test_external_source_status() {
+        let std = DocsOrigin::Std("core".to_string());
+        assert_eq!(external_source_status(Some(&std), true, None), "found");
+        assert_eq!(
+            external_source_status(Some(&std), false, Some(false)),
+            "rust_src_missing"
+        );
+        assert_eq!(
+            external_source_status(None, false, Some(false)),
+            "rust_src_missing"
+        );
+        assert_eq!(
+            external_source_status(None, false, Some(true)),
+            "unresolved"
+        );
+        assert_eq!(
+            external_source_status(Some(&DocsOrigin::Local), false, Some(true)),
+            "source_unavailable"
+        );
+
+        let definition: Location = serde_json::from_value(json!({
+            "uri": "file:///work/app/src/main.rs",
+            "range": {
+                "start": {"line": 41, "character": 7},
+                "end": {"line": 41, "character": 12}
+            }
+        }))
+        .unwrap();
+        let result = external_source_result("Config", Some(&definition));
+        assert_eq!(result["line"], 42);
+        assert_eq!(result["definition_file"], "/work/app/src/main.rs");
+        assert_eq!(external_source_result("Config", None)["line"], Value::Null);
+    }
+    #[test]
+    fn test_truncate_middle() {
+        let source = (0..10)
+            .map(|i| match i {
+                0 => "fn big() {".to_string(),
+                9 => "}".to_string(),
+                i => format!("    step{i}();"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(truncate_middle(&source, 10), (source.clone(), 0));
+        assert_eq!(
+            truncate_middle(&source, 5),
+            (
+                "fn big() {\n    step1();\n    step2();\n    ...[5 lines omitted]...\n    step8();\n}".to_string(),
+                5
+            )
+        );
+        // Signature and closing brace survive even a tiny limit.
+        assert_eq!(truncate_middle(&source, 0).0, "fn big() {\n    ...[8 lines omitted]...\n}");
+    }
+
+    #[test]
+    fn test_caller_grouping() {
+        assert_eq!(caller_module("file:///ws/app/src/lib.rs", &[]), "crate");
+        assert_eq!(
+            caller_module("file:///ws/app/src/net/mod.rs", &["tests".to_string()]),
+            "crate::net::tests"
+        );
+        assert_eq!(
+            caller_module("file:///ws/app/src/net/server.rs", &[]),
+            "crate::net::server"
+        );
+        assert_eq!(
+            caller_module("file:///ws/app/tests/api.rs", &[]),
+            "tests::api"
+        );
+
+        let range = |start: (u32, u32), end: (u32, u32)| Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        };
+        let symbol = |name: &str, kind, range: Range, children| DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            selection_range: range.clone(),
+            range,
+            children,
+        };
+        let tree = vec![symbol(
+            "outer",
+            2,
+            range((0, 0), (20, 1)),
+            Some(vec![
+                symbol("run", 12, range((2, 4), (4, 5)), None),
+                symbol("inner", 2, range((10, 4), (18, 5)), None),
+            ]),
+        )];
+        let position = Position {
+            line: 12,
+            character: 8,
+        };
+        assert_eq!(inline_modules(&tree, &position), vec!["outer", "inner"]);
+
+        let content = "    let a = parse::<u8>(s);\n    let f = items.map(parse);\n";
+        assert!(is_call_site(content, &range((0, 12), (0, 17))));
+        assert!(!is_call_site(content, &range((1, 22), (1, 27))));
+    }
+
+    #[test]
+    fn test_item_header_and_wrapping() {
+        let content = "mod net {\n    /// A server.\n    #[allow(dead_code)]\n    impl<T> Server<T>\n    where\n        T: Clone,\n    {\n        pub async fn bind(&self) {}\n    }\n}\n";
+        let range = |start, end| Range {
+            start: crate::analyzer::protocol::Position {
+                line: start,
+                character: 0,
+            },
+            end: crate::analyzer::protocol::Position {
+                line: end,
+                character: 1,
+            },
+        };
+
+        let (line, module) = item_header(content, &range(0, 9)).unwrap();
+        assert_eq!((line, module.as_str()), (0, "mod net {"));
+        let (line, impl_header) = item_header(content, &range(1, 8)).unwrap();
+        assert_eq!(line, 3);
+        assert_eq!(
+            impl_header,
+            "    impl<T> Server<T>\n    where\n        T: Clone,\n    {"
+        );
+
+        let headers = vec![
+            EnclosingHeader {
+                kind: "module",
+                name: "net".to_string(),
+                line: 0,
+                text: module,
+            },
+            EnclosingHeader {
+                kind: "impl",
+                name: "impl<T> Server<T>".to_string(),
+                line: 3,
+                text: impl_header,
+            },
+        ];
+        assert_eq!(
+            wrap_in_headers("        pub async fn bind(&self) {}", &headers),
+            "mod net {\n    impl<T> Server<T>\n    where\n        T: Clone,\n    {\n        pub async fn bind(&self) {}\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn test_binding_type_from_hints_and_hover() {
+        let hints: Vec<InlayHint> = serde_json::from_value(json!([
+            {"position": {"line": 3, "character": 10}, "label": "i32", "kind": 2},
+            {
+                "position": {"line": 3, "character": 14},
+                "label": [
+                    {"value": ": "},
+                    {
+                        "value": "Map",
+                        "location": {
+                            "uri": "file:///rust/library/core/src/iter/adapters/map.rs",
+                            "range": {"start": {"line": 60, "character": 11}, "end": {"line": 60, "character": 14}}
+                        }
+                    },
+                    {"value": "<"},
+                    {
+                        "value": "IntoIter",
+                        "location": {
+                            "uri": "file:///rust/library/alloc/src/vec/into_iter.rs",
+                            "range": {"start": {"line": 40, "character": 11}, "end": {"line": 40, "character": 19}}
+                        }
+                    },
+                    {"value": "<i32>, impl FnMut(i32) -> i32>"}
+                ],
+                "kind": 1
+            }
+        ]))
+        .unwrap();
+
+        assert!(binding_type_hint(&hints, 3, 10).is_none());
+        let label = binding_type_hint(&hints, 3, 14).unwrap();
+        assert_eq!(
+            label.text().trim_start_matches(':').trim(),
+            "Map<IntoIter<i32>, impl FnMut(i32) -> i32>"
+        );
+        assert_eq!(
+            qualified_label(label),
+            "core::iter::adapters::map::Map<alloc::vec::into_iter::IntoIter<i32>, impl FnMut(i32) -> i32>"
+        );
+
+        let hover = "```rust\nlet mut chars: Rev<Chars<'_>>\n```\n___\nsize = 16, align = 0x8";
+        assert_eq!(
+            hover_binding_type(hover, "chars").as_deref(),
+            Some("Rev<Chars<'_>>")
+        );
+        assert_eq!(hover_binding_type(hover, "other"), None);
+    }
+
+    #[test]
+    fn test_doc_comment_extraction() {
+        let content = r#"//! Connection pooling.
+//!
+//! Pools are cheap to clone.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+/// A pooled connection.
+///
+/// Returned to the pool on drop.
+#[derive(Debug)]
+#[doc = "Not `Send`."]
+pub struct Conn;
+
+//// Not a doc comment.
+pub fn undocumented() {}
+"#;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            outer_docs(&lines, 12),
+            vec![
+                (7, "A pooled connection.".to_string()),
+                (8, String::new()),
+                (9, "Returned to the pool on drop.".to_string()),
+                (11, "Not `Send`.".to_string())
+            ]
+        );
+        assert!(outer_docs(&lines, 15).is_empty());
+        assert_eq!(
+            inner_docs(&lines, 0),
+            vec![
+                (0, "Connection pooling.".to_string()),
+                (1, String::new()),
+                (2, "Pools are cheap to clone.".to_string())
+            ]
+        );
+        assert!(inner_docs(&lines, 5).is_empty());
+    }
+
+    #[test]
+    fn test_doc_examples() {
+        let content = r#"Adds numbers.
+
+```
+# use demo::add;
+assert_eq!(add(1, 2), 3);
+```
+
+```text
+not rust
+```
+
+````no_run, edition2021
+## not hidden
+```
+loop {}
+````
+
+~~~ignore
+nope
+~~~
+"#;
+        let docs: Vec<(usize, String)> = content
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 10, line.to_string()))
+            .collect();
+        let examples = doc_examples(&docs);
+        let summary: Vec<_> = examples
+            .iter()
+            .map(|example| (example.line, example.mode, example.code.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (12, "run", "use demo::add;\nassert_eq!(add(1, 2), 3);"),
+                (21, "no_run", "# not hidden\n```\nloop {}"),
+                (27, "ignore", "nope")
+            ]
+        );
+        assert_eq!(examples[1].attributes, vec!["no_run", "edition2021"]);
+    }
+
+    #[test]
+    fn test_index_to_line_col() {
+        let text = "hello\nworld\n! 123";
+        // "h" -> 0, 0
+        assert_eq!(index_to_line_col(text, 0), (0, 0));
+        // "w" in "world" -> index 6 (hello=5 + \n=1)
+        assert_eq!(index_to_line_col(text, 6), (1, 0));
+        // "!" -> index 12 (hello\n=6, world\n=6)
+        assert_eq!(index_to_line_col(text, 12), (2, 0));
+        // "1" -> index 14
+        assert_eq!(index_to_line_col(text, 14), (2, 2));
+    }
+
+    #[test]
+    fn test_unicode() {
+        let text = "a\nб\nв"; // 'б' is 2 bytes
+        // 'a' (0) -> 0,0
+        // '\n' (1)
+        // 'б' (2) -> 1,0
+        assert_eq!(index_to_line_col(text, 2), (1, 0));
+        // '\n' after 'б' is at 2+2=4.
+        // 'в' at 5.
+        assert_eq!(index_to_line_col(text, 5), (2, 0));
+    }
+
+    #[test]
+    fn test_is_valid_code_context() {
+        let code = "
+        let x = 1; // comment with x
+        let s = \"string with x\";
+        /* 
+           multi-line comment with x
+        */
+        let x = 2;
+        ";
+        
+        // Find all 'x' indices manually
+        // x at line 2: "let x"
+        let first_x = code.find("let x").unwrap() + 4;
+        assert!(is_valid_code_context(code, first_x));
+        
+        // x in comment: "// comment with x"
+        let comment_start = code.find("//").unwrap();
+        let x_in_comment = comment_start + code[comment_start..].find("x").unwrap();
+        assert!(!is_valid_code_context(code, x_in_comment));
+        
+        // x in string: "\"string with x\""
+        let string_start = code.find("\"").unwrap();
+        let x_in_string = string_start + code[string_start..].find("x").unwrap();
+        assert!(!is_valid_code_context(code, x_in_string));
+        
+        // x in block comment
+        let block_start = code.find("/*").unwrap();
+        let x_in_block = block_start + code[block_start..].find("x").unwrap();
+        assert!(!is_valid_code_context(code, x_in_block));
+
+        // last x
+        let last_let = code.rfind("let x").unwrap();
+        let last_x = last_let + 4;
+        assert!(is_valid_code_context(code, last_x));
+    }
+
+    #[test]
+    fn test_raw_identifier_symbols() {
+        let code = "fn build() {\n    let r#type = \"x\";\n    type Alias = u8;\n    use_it(r#type);\n}\n";
+        let block = "let r#type = \"x\";\n    type Alias = u8;\n    use_it(r#type);";
+
+        // The raw spelling skips the `type` keyword.
+        assert_eq!(find_symbol_location(code, "r#type", block, 1).unwrap(), (1, 8));
+        assert_eq!(find_symbol_location(code, "r#type", block, 2).unwrap(), (3, 11));
+
+        // The bare spelling resolves to the start of the raw token as well.
+        assert_eq!(find_symbol_location(code, "type", block, 1).unwrap(), (1, 8));
+        assert_eq!(find_symbol_location(code, "type", block, 2).unwrap(), (2, 4));
+    }
+
+    #[test]
+    fn test_parse_type_alias() {
+        let decl = "pub type Result<T, E = MyError> =\n    std::result::Result<T, E>;";
+        let (alias, target, offset) = parse_type_alias(decl).unwrap();
+        assert_eq!(alias, "Result<T, E = MyError>");
+        assert_eq!(target, "std::result::Result<T, E>");
+        assert_eq!(&decl[offset..offset + 3], "std");
+
+        assert!(parse_type_alias("    type Item;").is_none());
+        assert!(parse_type_alias("let typed = 1;").is_none());
+        assert!(parse_type_alias("enum Kind { A }\ntype Alias = Kind;").is_none());
+    }
+
+    #[test]
+    fn test_type_head_offset() {
+        let ty = "std::result::Result<T, E>";
+        assert_eq!(&ty[type_head_offset(ty).unwrap()..][..6], "Result");
+
+        let ty = "&'a mut dyn Handler + Send";
+        assert_eq!(&ty[type_head_offset(ty).unwrap()..][..7], "Handler");
+
+        assert_eq!(type_head_offset("(u8, u16)"), None);
+        assert_eq!(type_head_offset("fn(u8) -> u8"), None);
+    }
+
+    #[test]
+    fn test_parse_type_expr_and_alias_parameters() {
+        let text = "&'a mut Vec<(u8, io::Result<T>)>";
+        let Some(TypeExpr::Reference {
+            mutable: true,
+            inner,
+        }) = parse_type_expr(text)
+        else {
+            panic!("expected a mutable reference");
+        };
+        let TypeExpr::Path { segments, args } = *inner else {
+            panic!("expected a path");
+        };
+        assert_eq!(segments, vec![("Vec".to_string(), 8)]);
+        let [TypeArg::Type(TypeExpr::Tuple(items))] = args.as_slice() else {
+            panic!("expected a tuple argument");
+        };
+        let TypeExpr::Path { segments, .. } = &items[1] else {
+            panic!("expected a path");
+        };
+        assert_eq!(&text[segments[1].1..][..6], "Result");
+
+        assert_eq!(
+            parse_type_expr("[u8; N * 2]"),
+            Some(TypeExpr::Array(
+                Box::new(TypeExpr::Path {
+                    segments: vec![("u8".to_string(), 1)],
+                    args: Vec::new()
+                }),
+                "N * 2".to_string()
+            ))
+        );
+        assert!(matches!(
+            parse_type_expr("Box<dyn Fn(u8) -> u8>"),
+            Some(TypeExpr::Path { .. })
+        ));
+        assert_eq!(parse_type_expr("Vec<u8> extra"), None);
+
+        let decl = "pub type Result<'a, T, E = io::Error> = std::result::Result<T, E>;";
+        let parameters = alias_parameters(decl);
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[0], ("T".to_string(), None));
+        let (offset, default) = parameters[1].1.clone().unwrap();
+        assert_eq!(default, "io::Error");
+        assert_eq!(&decl[offset..][..9], "io::Error");
+        assert!(alias_parameters("type Id = u64;").is_empty());
+    }
+    #[tokio::test]
+    async fn test_word_boundary_logic() {
+        // This simulates the logic inside get_hover_impl
+        let block_content = "let service = rust_server.serve(stdio()).await?;";
+        let symbol = "serve";
+        let occurrence = 1;
+
+        let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+        
+        let mut current_occurrence = 0;
+        let mut found_idx = None;
+
+        for (idx, _) in block_content.match_indices(symbol) {
+             let valid_start = if idx == 0 {
+                true
+            } else {
+                block_content[..idx].chars().next_back().map_or(true, |c| !is_ident_char(c))
+            };
+
+            let valid_end = if idx + symbol.len() == block_content.len() {
+                true
+            } else {
+                block_content[idx + symbol.len()..].chars().next().map_or(true, |c| !is_ident_char(c))
+            };
+
+            if valid_start && valid_end {
+                current_occurrence += 1;
+                if current_occurrence == occurrence {
+                    found_idx = Some(idx);
+                    break;
+                }
+            }
+        }
+
+        // "rust_server" starts at 14. "serve" inside it starts at 19.
+        // ".serve" starts at 25 (dot) -> serve at 26.
+        // match_indices("serve") will return indices: 19, 26.
+        
+        // 1. Index 19: char before is '_'. is_ident_char('_') is true. !true is false. valid_start = false. SKIP.
+        // 2. Index 26: char before is '.'. is_ident_char('.') is false. valid_start = true.
+        //              char after is '('. is_ident_char('(') is false. valid_end = true. MATCH.
+        
+        assert_eq!(found_idx, Some(26), "Should find the standalone 'serve', skipping 'rust_server'");
+    }
+}
+
+
+pub async fn get_symbol_source_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+    let include_impl_context = args
+        .get("include_impl_context")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let max_lines = args
+        .get("max_lines")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_SOURCE_MAX_LINES, |v| v as usize);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    
+    // Try to read the line content for context (even if get_symbol_source fails)
+    let context_line = file_content
+        .lines()
+        .nth(line as usize)
+        .unwrap_or("<line out of bounds>")
+        .to_string();
+
+    let context_marker = create_position_marker(&context_line, character as u32);
+
+    match analyzer
+        .get_symbol_source(file_path, line as u32, character as u32)
+        .await
+    {
+        Ok((source, range, actual_path)) => {
+            let total_lines = source.lines().count();
+            let (source, omitted_lines) = truncate_middle(&source, max_lines);
+
+            let (source, context) = if include_impl_context {
+                let headers = enclosing_headers(analyzer, &actual_path, &range).await?;
+                let context: Vec<Value> = headers
+                    .iter()
+                    .map(|header| {
+                        json!({
+                            "kind": header.kind,
+                            "name": header.name,
+                            "line": header.line + 1,
+                            "header": header.text
+                        })
+                    })
+                    .collect();
+                (wrap_in_headers(&source, &headers), Some(context))
+            } else {
+                (source, None)
+            };
+
+            let mut result = json!({
+                "request": {
+                    "file_path": file_path,
+                    "symbol": symbol,
+                    "occurrence": occurrence,
+                    "line": line,
+                    "character": character,
+                    "context_line": context_line,
+                    "context_marker": context_marker
+                },
+                "result": {
+                    "file_path": actual_path,
+                    "range": range,
+                    "source": source
+                }
+            });
+            if let Some(context) = context {
+                result["result"]["context"] = json!(context);
+            }
+            if omitted_lines > 0 {
+                result["result"]["truncated"] = json!(true);
+                result["result"]["total_lines"] = json!(total_lines);
+                result["result"]["omitted_lines"] = json!(omitted_lines);
+            }
+
+            Ok(ToolResult {
+                content: vec![
+                    json!({
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&result)?
+                    })
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+                ],
+            })
+        }
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to get symbol source. Request context: Line {}: '{}'\nPosition: '{}'. Error: {}",
+            line,
+            context_line.trim(),
             context_marker,
             e
         )),
     }
 }
 
+pub async fn symbol_diff_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let from_ref = args
+        .get("from_ref")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing from_ref parameter"))?;
+    let to_ref = args.get("to_ref").and_then(|v| v.as_str());
+
+    let (toplevel, relative) = git_relative_path(file_path).await?;
+    let from_text = git_show(&toplevel, from_ref, &relative).await?;
+    let to_text = match to_ref {
+        Some(to_ref) => git_show(&toplevel, to_ref, &relative).await?,
+        None => fs::read_to_string(file_path).await.ok(),
+    };
+    let to_label = to_ref.unwrap_or("working tree");
+
+    // Look the symbol up in each version by handing rust-analyzer the text in
+    // memory, then put it back on the file as it is on disk.
+    let versions: Vec<Vec<Range>> = analyzer
+        .with_document_text(&[file_path], async |analyzer| {
+            let mut versions = Vec::new();
+            for text in [&from_text, &to_text] {
+                let found = match text {
+                    Some(text) => {
+                        let symbols = text_symbols(analyzer, file_path, text).await?;
+                        named_symbols(&symbols, symbol)
+                            .into_iter()
+                            .map(|found| found.range.clone())
+                            .collect()
+                    }
+                    None => Vec::new(),
+                };
+                versions.push(found);
+            }
+            Ok(versions)
+        })
+        .await?;
+
+    let side = |git_ref: &str, text: &Option<String>, ranges: &[Range]| {
+        let source = text.as_deref().zip(ranges.first()).map(|(text, range)| {
+            text.lines()
+                .skip(range.start.line as usize)
+                .take((range.end.line - range.start.line) as usize + 1)
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+        let info = json!({
+            "ref": git_ref,
+            "file_exists": text.is_some(),
+            "found": !ranges.is_empty(),
+            "start_line": ranges.first().map(|range| range.start.line + 1),
+            "end_line": ranges.first().map(|range| range.end.line + 1),
+            "matches": ranges.len()
+        });
+        (source, info)
+    };
+    let (from_source, from_info) = side(from_ref, &from_text, &versions[0]);
+    let (to_source, to_info) = side(to_label, &to_text, &versions[1]);
+
+    let status = match (&from_source, &to_source) {
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "Symbol '{}' not found in {} at {} or {}",
+                symbol,
+                relative,
+                from_ref,
+                to_label
+            ));
+        }
+        (None, Some(_)) => "added",
+        (Some(_), None) => "removed",
+        (Some(from), Some(to)) if from == to => "unchanged",
+        (Some(_), Some(_)) => "modified",
+    };
+    let diff = unified_diff(
+        from_source.as_deref().unwrap_or_default(),
+        to_source.as_deref().unwrap_or_default(),
+        &format!("{from_ref}:{relative}"),
+        &format!("{to_label}:{relative}"),
+    );
+
+    let result = json!({
+        "file_path": file_path,
+        "symbol": symbol,
+        "status": status,
+        "from": from_info,
+        "to": to_info,
+        "diff": diff,
+        "note": "Hunk line numbers count from the first line of the item; add start_line - 1 for file lines. When several items match the name, the first is compared: qualify it as `Type::method` to pick another. A file renamed between the refs is reported as missing."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The repository top level of `file_path` and the file's path relative to it.
+async fn git_relative_path(file_path: &str) -> Result<(PathBuf, String)> {
+    let path = Path::new(file_path);
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("{} has no parent directory", file_path))?;
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{} is not inside a git repository: {}",
+            file_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let toplevel = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    // Compare canonical paths, so a symlinked checkout still strips cleanly.
+    let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let relative = canonical
+        .strip_prefix(&toplevel)
+        .or_else(|_| path.strip_prefix(&toplevel))
+        .map_err(|_| anyhow::anyhow!("{} is outside {}", file_path, toplevel.display()))?;
+    Ok((toplevel, relative.to_string_lossy().replace('\\', "/")))
+}
+
+/// The content of `relative` at `git_ref`, or `None` when the file does not
+/// exist there. An unknown ref is an error.
+async fn git_show(toplevel: &Path, git_ref: &str, relative: &str) -> Result<Option<String>> {
+    let verify = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(toplevel)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{git_ref}^{{commit}}"))
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    if !verify.status.success() {
+        return Err(anyhow::anyhow!("Unknown git ref '{}'", git_ref));
+    }
+
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(toplevel)
+        .arg("show")
+        .arg(format!("{git_ref}:{relative}"))
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Document symbols of `text` as if it were the content of `file_path`.
+async fn text_symbols(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    text: &str,
+) -> Result<Vec<DocumentSymbol>> {
+    analyzer.sync_document(file_path, text).await?;
+    match analyzer.document_symbols(file_path).await? {
+        DocumentSymbolResponse::DocumentSymbols(symbols) => Ok(symbols),
+        DocumentSymbolResponse::SymbolInformation(_) => Ok(Vec::new()),
+    }
+}
+
+/// Symbols named by `path` (`parse`, `Parser::parse`, `net::Server`), in
+/// document order. Leading segments must name the nearest enclosing items:
+/// modules, traits, or impls by their self type or trait.
+fn named_symbols<'a>(symbols: &'a [DocumentSymbol], path: &str) -> Vec<&'a DocumentSymbol> {
+    fn walk<'a>(
+        symbols: &'a [DocumentSymbol],
+        ancestors: &mut Vec<&'a DocumentSymbol>,
+        segments: &[&str],
+        found: &mut Vec<&'a DocumentSymbol>,
+    ) {
+        for symbol in symbols {
+            let (last, parents) = segments.split_last().unwrap();
+            if symbol_named(&symbol.name, last)
+                && parents.len() <= ancestors.len()
+                && parents
+                    .iter()
+                    .rev()
+                    .zip(ancestors.iter().rev())
+                    .all(|(segment, ancestor)| symbol_named(&ancestor.name, segment))
+            {
+                found.push(symbol);
+            }
+            if let Some(children) = &symbol.children {
+                ancestors.push(symbol);
+                walk(children, ancestors, segments, found);
+                ancestors.pop();
+            }
+        }
+    }
+
+    let segments: Vec<&str> = path.split("::").map(str::trim).collect();
+    let mut found = Vec::new();
+    walk(symbols, &mut Vec::new(), &segments, &mut found);
+    found
+}
+
+/// Whether the document symbol `name` is called `segment`. Impl blocks, named
+/// `impl<T> Display for Wrapper<T>`, match their trait and their self type.
+fn symbol_named(name: &str, segment: &str) -> bool {
+    if name == segment {
+        return true;
+    }
+    let Some(header) = name
+        .strip_prefix("impl")
+        .filter(|rest| rest.starts_with([' ', '<']))
+    else {
+        return false;
+    };
+    let without_generics = |ty: &str| {
+        let ty = ty.split('<').next().unwrap_or(ty).trim();
+        ty.rsplit("::").next().unwrap_or(ty).to_string()
+    };
+    let header = match header.trim_start().strip_prefix('<') {
+        // Skip the impl's own generic parameters.
+        Some(rest) => {
+            let mut depth = 1;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map_or(rest.len(), |(idx, _)| idx + 1);
+            &rest[end..]
+        }
+        None => header,
+    };
+    match header.split_once(" for ") {
+        Some((trait_name, self_ty)) => {
+            without_generics(trait_name) == segment || without_generics(self_ty) == segment
+        }
+        None => without_generics(header) == segment,
+    }
+}
+
+pub async fn get_external_docs_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let docs = analyzer.get_hover(file_path, line, character).await?;
+    let definition = analyzer
+        .definition_locations(file_path, line, character)
+        .await?
+        .into_iter()
+        .next()
+        .map(|location| {
+            location
+                .uri
+                .strip_prefix("file://")
+                .unwrap_or(&location.uri)
+                .to_string()
+        });
+    let origin = definition
+        .as_deref()
+        .map_or(DocsOrigin::Local, DocsOrigin::of_path);
+
+    // The request is rust-analyzer specific; treat a failure like "no URL".
+    let (url, url_source) = match analyzer.external_docs(file_path, line, character).await {
+        Ok(Some(url)) => (Some(url), Some("rust-analyzer")),
+        _ => match origin.search_url(symbol) {
+            Some(url) => (Some(url), Some("search")),
+            None => (None, None),
+        },
+    };
+
+    let result = json!({
+        "symbol": symbol,
+        "origin": origin.kind(),
+        "crate": origin.crate_name(),
+        "definition_file": definition,
+        "url": url,
+        "url_source": url_source,
+        "docs": docs
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn get_external_source_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let max_lines = args
+        .get("max_lines")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_SOURCE_MAX_LINES, |v| v as usize);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let definition = analyzer
+        .definition_locations(file_path, line, character)
+        .await?
+        .into_iter()
+        .next();
+    let definition_file = definition.as_ref().map(|location| {
+        location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+            .to_string()
+    });
+    let origin = definition_file.as_deref().map(DocsOrigin::of_path);
+    let mut result = external_source_result(symbol, definition.as_ref());
+
+    // Only a definition whose file exists on disk can be read; without
+    // rust-src, std definitions either fail to resolve or point nowhere.
+    let readable = match &definition_file {
+        Some(path) => fs::metadata(path).await.is_ok(),
+        None => false,
+    };
+    let source = if readable {
+        analyzer
+            .get_symbol_source(file_path, line, character)
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let rust_src = if source.is_none() {
+        rust_src_installed().await
+    } else {
+        None
+    };
+    let status = external_source_status(origin.as_ref(), source.is_some(), rust_src);
+    result["status"] = json!(status);
+
+    match source {
+        Some((source, range, actual_path)) => {
+            let total_lines = source.lines().count();
+            let (source, omitted_lines) = truncate_middle(&source, max_lines);
+            let headers = enclosing_headers(analyzer, &actual_path, &range)
+                .await
+                .unwrap_or_default();
+            result["range"] = json!(range);
+            result["context"] = json!(
+                headers
+                    .iter()
+                    .map(|header| json!({
+                        "kind": header.kind,
+                        "name": header.name,
+                        "line": header.line + 1,
+                        "header": header.text
+                    }))
+                    .collect::<Vec<_>>()
+            );
+            result["source"] = json!(source);
+            if omitted_lines > 0 {
+                result["truncated"] = json!(true);
+                result["total_lines"] = json!(total_lines);
+                result["omitted_lines"] = json!(omitted_lines);
+            }
+        }
+        None => {
+            result["signature"] = json!(analyzer.get_hover(file_path, line, character).await?);
+            result["rust_src_installed"] = json!(rust_src);
+            if status == "rust_src_missing" {
+                result["guidance"] = json!(
+                    "The standard library sources are not installed. Run `rustup component add rust-src` and restart the server so rust-analyzer can index them."
+                );
+            }
+        }
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Where `symbol` is defined, as reported before its source: origin, crate,
+/// file and 1-based line of the definition.
+fn external_source_result(symbol: &str, definition: Option<&Location>) -> Value {
+    let definition_file = definition.map(|location| {
+        location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+    });
+    let origin = definition_file.map(DocsOrigin::of_path);
+    json!({
+        "symbol": symbol,
+        "origin": origin.as_ref().map(DocsOrigin::kind),
+        "crate": origin.as_ref().and_then(DocsOrigin::crate_name),
+        "definition_file": definition_file,
+        "line": definition.map(|location| location.range.start.line + 1),
+    })
+}
+
+/// Whether the active toolchain ships the rust-src component, judged by the
+/// library directory under `rustc --print sysroot`. `None` if rustc can't run.
+async fn rust_src_installed() -> Option<bool> {
+    let output = tokio::process::Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let library = Path::new(&sysroot).join("lib/rustlib/src/rust/library");
+    Some(fs::metadata(library).await.is_ok())
+}
+
+/// `found` when the definition source was read; otherwise why not.
+fn external_source_status(
+    origin: Option<&DocsOrigin>,
+    found: bool,
+    rust_src: Option<bool>,
+) -> &'static str {
+    if found {
+        return "found";
+    }
+    match (origin, rust_src) {
+        (None | Some(DocsOrigin::Std(_)), Some(false)) => "rust_src_missing",
+        (None, _) => "unresolved",
+        _ => "source_unavailable",
+    }
+}
+
+pub async fn get_docs_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let item = item_docs(analyzer, file_path, symbol, code_block, occurrence).await?;
+    let mut kinds = Vec::new();
+    let mut docs: Vec<String> = item.outer.iter().map(|(_, doc)| doc.clone()).collect();
+    if !docs.is_empty() {
+        kinds.push("outer");
+    }
+    if !item.inner.is_empty() {
+        kinds.push("inner");
+        if !docs.is_empty() {
+            docs.push(String::new());
+        }
+        docs.extend(item.inner.iter().map(|(_, doc)| doc.clone()));
+    }
+
+    let result = if docs.is_empty() {
+        json!({
+            "symbol": symbol,
+            "file_path": item.file_path,
+            "line": item.line + 1,
+            "documented": false,
+            "docs": null,
+            "message": format!("No documentation: `{symbol}` has no doc comment or #[doc] attribute")
+        })
+    } else {
+        json!({
+            "symbol": symbol,
+            "file_path": item.file_path,
+            "line": item.line + 1,
+            "documented": true,
+            "kinds": kinds,
+            "docs": docs.join("\n")
+        })
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The doc comments of a symbol's definition, with their 0-based lines.
+struct ItemDocs {
+    file_path: String,
+    /// 0-based line of the definition.
+    line: u32,
+    /// `///` lines and `#[doc]` attributes above the item.
+    outer: Vec<(usize, String)>,
+    /// `//!` lines opening a module.
+    inner: Vec<(usize, String)>,
+}
+
+/// Locates a symbol like the other tools do, follows it to its definition
+/// and reads the docs there.
+async fn item_docs(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    symbol: &str,
+    code_block: &str,
+    occurrence: usize,
+) -> Result<ItemDocs> {
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    // The definition may be the symbol itself, in which case there is none to jump to.
+    let (definition_path, definition_line) = match analyzer
+        .definition_locations(file_path, line, character)
+        .await?
+        .into_iter()
+        .next()
+    {
+        Some(location) => (
+            location
+                .uri
+                .strip_prefix("file://")
+                .unwrap_or(&location.uri)
+                .to_string(),
+            location.range.start,
+        ),
+        None => (file_path.to_string(), Position { line, character }),
+    };
+    let content = if definition_path == file_path {
+        file_content
+    } else {
+        fs::read_to_string(&definition_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", definition_path, e))?
+    };
+    let lines: Vec<&str> = content.lines().collect();
+
+    let item = match analyzer.document_symbols(&definition_path).await {
+        Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) => {
+            named_symbol_at(&symbols, definition_line.line).cloned()
+        }
+        _ => None,
+    };
+
+    let outer = outer_docs(&lines, definition_line.line as usize);
+    // `mod foo;` resolves to the start of foo.rs, whose docs are `//!` lines;
+    // an inline `mod foo { .. }` keeps them at the top of its body.
+    let inner_from = match &item {
+        // LSP `SymbolKind::Module`
+        Some(item) if item.kind == 2 => Some(item.selection_range.start.line as usize + 1),
+        None if definition_line.line == 0 && definition_line.character == 0 => Some(0),
+        _ => None,
+    };
+    let inner = inner_from
+        .map(|from| inner_docs(&lines, from))
+        .unwrap_or_default();
+
+    Ok(ItemDocs {
+        file_path: definition_path,
+        line: definition_line.line,
+        outer,
+        inner,
+    })
+}
+
+/// Lines of compiler or test output kept per failed example.
+const DOCTEST_OUTPUT_MAX_LINES: usize = 60;
+
+pub async fn check_doc_example_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let run = args.get("run").and_then(|v| v.as_bool()).unwrap_or(true);
+
+    let item = item_docs(analyzer, file_path, symbol, code_block, occurrence).await?;
+    let docs: Vec<(usize, String)> = item.outer.iter().chain(&item.inner).cloned().collect();
+    let examples = doc_examples(&docs);
+    if examples.is_empty() {
+        let result = json!({
+            "symbol": symbol,
+            "file_path": item.file_path,
+            "line": item.line + 1,
+            "examples": [],
+            "message": format!("`{symbol}` has no Rust code blocks in its documentation")
+        });
+        return Ok(ToolResult {
+            content: vec![
+                json!({
+                    "type": "text",
+                    "text": serde_json::to_string_pretty(&result)?
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ],
+        });
+    }
+
+    // rustdoc names each doctest after the file relative to its package and
+    // the line of the opening fence, which is how examples are matched up.
+    let (relative_path, doctests) = if run {
+        let manifest_path = owning_manifest(Path::new(&item.file_path))?;
+        let package_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        let relative_path = Path::new(&item.file_path)
+            .strip_prefix(package_dir)
+            .unwrap_or(Path::new(&item.file_path))
+            .to_string_lossy()
+            .to_string();
+        let doctests = run_doc_tests(&manifest_path, &relative_path).await?;
+        (relative_path, doctests)
+    } else {
+        (String::new(), Vec::new())
+    };
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let results: Vec<Value> = examples
+        .iter()
+        .map(|example| {
+            let doctest = doctests.iter().find(|doctest| {
+                doctest.file() == relative_path && doctest.line() == Some(example.line + 1)
+            });
+            let status = match doctest {
+                Some(doctest) => doctest.status.as_str(),
+                None if run => "not_found",
+                None => "not_run",
+            };
+            *counts.entry(status).or_default() += 1;
+            let mut entry = json!({
+                "line": example.line + 1,
+                "mode": example.mode,
+                "attributes": example.attributes,
+                "status": status
+            });
+            if let Some(doctest) = doctest.filter(|doctest| doctest.status == "failed") {
+                let (output, _) = truncate_middle(&doctest.output, DOCTEST_OUTPUT_MAX_LINES);
+                entry["output"] = json!(output);
+                entry["code"] = json!(example.code);
+            } else if !run {
+                entry["code"] = json!(example.code);
+            }
+            entry
+        })
+        .collect();
+
+    let mut result = json!({
+        "symbol": symbol,
+        "file_path": item.file_path,
+        "line": item.line + 1,
+        "summary": counts,
+        "examples": results
+    });
+    if counts.contains_key("not_found") {
+        result["note"] = json!(
+            "cargo test --doc did not report some examples: rustdoc only tests the docs of \
+             library crates, and skips items behind an inactive cfg"
+        );
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// A fenced Rust code block in a doc comment.
+#[derive(Debug, PartialEq)]
+struct DocExample {
+    /// 0-based line of the opening fence.
+    line: usize,
+    /// The fence's info string, split into attributes.
+    attributes: Vec<String>,
+    /// How rustdoc treats it: `ignore`, `compile_fail`, `no_run`,
+    /// `should_panic` or `run`.
+    mode: &'static str,
+    /// The code as compiled, with `# ` hidden lines revealed.
+    code: String,
+}
+
+/// Info string attributes rustdoc understands on a Rust code block.
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "ignore",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "standalone_crate",
+    "test_harness",
+];
+
+/// The Rust code blocks in numbered doc lines. Like rustdoc, a fence without
+/// an info string is Rust, as is one whose attributes are all rustdoc's own or
+/// that says `rust`; ` ```text ` and the like are not.
+fn doc_examples(docs: &[(usize, String)]) -> Vec<DocExample> {
+    let mut examples = Vec::new();
+    // The open fence: its marker, its example if Rust, and the code so far.
+    let mut open: Option<(&str, Option<DocExample>, Vec<String>)> = None;
+    for (line, text) in docs {
+        let trimmed = text.trim_start();
+        let fence_len = ["```", "~~~"]
+            .iter()
+            .find(|fence| trimmed.starts_with(*fence))
+            .map_or(0, |fence| {
+                trimmed.len() - trimmed.trim_start_matches(&fence[..1]).len()
+            });
+        let marker = &trimmed[..fence_len];
+
+        if let Some((open_marker, example, code)) = open.as_mut() {
+            let closes = marker.starts_with(*open_marker) && trimmed[fence_len..].trim().is_empty();
+            if fence_len > 0 && closes {
+                if let Some(mut example) = example.take() {
+                    example.code = code.join("\n");
+                    examples.push(example);
+                }
+                open = None;
+            } else {
+                code.push(reveal_hidden_line(text));
+            }
+            continue;
+        }
+        if fence_len == 0 {
+            continue;
+        }
+
+        let attributes: Vec<String> = trimmed[fence_len..]
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|attribute| attribute.trim_matches(|c| c == '{' || c == '}' || c == '.'))
+            .filter(|attribute| !attribute.is_empty())
+            .map(str::to_string)
+            .collect();
+        let known = |attribute: &String| {
+            RUSTDOC_ATTRIBUTES.contains(&attribute.as_str())
+                || attribute.starts_with("edition")
+                || attribute.starts_with("ignore-")
+                || (attribute.len() == 5
+                    && attribute.starts_with('E')
+                    && attribute[1..].chars().all(|c| c.is_ascii_digit()))
+        };
+        let is_rust =
+            attributes.iter().any(|attribute| attribute == "rust") || attributes.iter().all(known);
+        let has = |name: &str| attributes.iter().any(|attribute| attribute == name);
+        let mode = if has("ignore") || attributes.iter().any(|a| a.starts_with("ignore-")) {
+            "ignore"
+        } else if has("compile_fail") {
+            "compile_fail"
+        } else if has("no_run") {
+            "no_run"
+        } else if has("should_panic") {
+            "should_panic"
+        } else {
+            "run"
+        };
+        let example = is_rust.then(|| DocExample {
+            line: *line,
+            attributes,
+            mode,
+            code: String::new(),
+        });
+        open = Some((marker, example, Vec::new()));
+    }
+    examples
+}
+
+/// A code line as rustdoc compiles it: `# hidden` lines lose their `# `,
+/// and `##` escapes a literal `#`.
+fn reveal_hidden_line(text: &str) -> String {
+    let trimmed = text.trim_start();
+    if trimmed == "#" {
+        String::new()
+    } else if let Some(rest) = trimmed.strip_prefix("##") {
+        format!("{}#{rest}", &text[..text.len() - trimmed.len()])
+    } else if let Some(rest) = trimmed.strip_prefix("# ") {
+        rest.to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// The innermost document symbol whose name sits on `line`.
+pub(crate) fn named_symbol_at(symbols: &[DocumentSymbol], line: u32) -> Option<&DocumentSymbol> {
+    symbols.iter().find_map(|symbol| {
+        symbol
+            .children
+            .as_deref()
+            .and_then(|children| named_symbol_at(children, line))
+            .or_else(|| (symbol.selection_range.start.line == line).then_some(symbol))
+    })
+}
+
+/// The `///` lines and `#[doc = ".."]` attributes directly above `line`;
+/// other attributes in between are skipped. Each doc line comes with its
+/// 0-based line number.
+fn outer_docs(lines: &[&str], line: usize) -> Vec<(usize, String)> {
+    let above = &lines[..line.min(lines.len())];
+    let mut docs = Vec::new();
+    for (idx, text) in above.iter().enumerate().rev() {
+        let text = text.trim();
+        if let Some(doc) = text.strip_prefix("///").filter(|doc| !doc.starts_with('/')) {
+            docs.push((idx, doc.strip_prefix(' ').unwrap_or(doc).to_string()));
+        } else if let Some(doc) = doc_attribute(text, "#[doc") {
+            docs.push((idx, doc));
+        } else if !text.starts_with("#[") {
+            break;
+        }
+    }
+    docs.reverse();
+    docs
+}
+
+/// The `//!` lines and `#![doc = ".."]` attributes opening a module body
+/// that starts at `line`, after blank lines and other inner attributes,
+/// with their 0-based line numbers.
+fn inner_docs(lines: &[&str], line: usize) -> Vec<(usize, String)> {
+    let mut docs = Vec::new();
+    for (idx, text) in lines.iter().enumerate().skip(line) {
+        let text = text.trim();
+        if let Some(doc) = text.strip_prefix("//!") {
+            docs.push((idx, doc.strip_prefix(' ').unwrap_or(doc).to_string()));
+        } else if let Some(doc) = doc_attribute(text, "#![doc") {
+            docs.push((idx, doc));
+        } else if text.starts_with("#![") || (text.is_empty() && docs.is_empty()) {
+            continue;
+        } else {
+            break;
+        }
+    }
+    docs
+}
+
+/// The string of a one-line `#[doc = "text"]` attribute.
+fn doc_attribute(text: &str, prefix: &str) -> Option<String> {
+    let value = text
+        .strip_prefix(prefix)?
+        .trim_start()
+        .strip_prefix('=')?
+        .trim()
+        .strip_suffix(']')?
+        .trim_end();
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(value.replace("\\\"", "\"").replace("\\n", "\n"))
+}
+
+/// Where a symbol's definition lives, judged from its file path.
+#[derive(Debug, PartialEq)]
+pub(crate) enum DocsOrigin {
+    /// `std`, `core`, `alloc`, ... from the rust-src component.
+    Std(String),
+    /// A crate from the cargo registry, with its version.
+    Dependency { name: String, version: String },
+    Local,
+}
+
+impl DocsOrigin {
+    pub(crate) fn of_path(path: &str) -> Self {
+        let segments: Vec<&str> = path.split('/').collect();
+        if let Some(idx) = segments.iter().position(|s| *s == "library")
+            && let Some(name) = segments.get(idx + 1)
+            && matches!(*name, "std" | "core" | "alloc" | "proc_macro" | "test")
+        {
+            return DocsOrigin::Std(name.to_string());
+        }
+
+        // ~/.cargo/registry/src/<index>/<name>-<version>/...
+        if let Some(idx) = segments.iter().position(|s| *s == "registry")
+            && segments.get(idx + 1) == Some(&"src")
+            && let Some((name, version)) = segments.get(idx + 3).and_then(|dir| split_crate_dir(dir))
+        {
+            return DocsOrigin::Dependency {
+                name: name.to_string(),
+                version: version.to_string(),
+            };
+        }
+
+        DocsOrigin::Local
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            DocsOrigin::Std(_) => "std",
+            DocsOrigin::Dependency { .. } => "dependency",
+            DocsOrigin::Local => "local",
+        }
+    }
+
+    pub(crate) fn crate_name(&self) -> Option<&str> {
+        match self {
+            DocsOrigin::Std(name) => Some(name),
+            DocsOrigin::Dependency { name, .. } => Some(name),
+            DocsOrigin::Local => None,
+        }
+    }
+
+    /// A documentation search URL, used when rust-analyzer offers no link.
+    fn search_url(&self, symbol: &str) -> Option<String> {
+        match self {
+            DocsOrigin::Std(name) => Some(format!(
+                "https://doc.rust-lang.org/stable/{name}/?search={symbol}"
+            )),
+            DocsOrigin::Dependency { name, version } => Some(format!(
+                "https://docs.rs/{name}/{version}/?search={symbol}"
+            )),
+            DocsOrigin::Local => None,
+        }
+    }
+}
+
+/// Splits a registry checkout directory such as `serde_json-1.0.149` into
+/// crate name and version.
+fn split_crate_dir(dir: &str) -> Option<(&str, &str)> {
+    dir.match_indices('-')
+        .map(|(idx, _)| idx)
+        .find(|&idx| dir[idx + 1..].starts_with(|c: char| c.is_ascii_digit()))
+        .map(|idx| (&dir[..idx], &dir[idx + 1..]))
+}
+
+/// Default `max_lines` of `get_symbol_source`: roomy enough for any reasonable
+/// function, small enough to keep a generated or giant one from flooding the
+/// response.
+pub(crate) const DEFAULT_SOURCE_MAX_LINES: usize = 400;
+
+/// Keeps the first and last lines of `source` (its signature and closing
+/// brace) when it has more than `max_lines` lines, replacing the middle with
+/// a `...[N lines omitted]...` marker. Returns the text and the omitted count.
+pub(crate) fn truncate_middle(source: &str, max_lines: usize) -> (String, usize) {
+    let lines: Vec<&str> = source.lines().collect();
+    let max_lines = max_lines.max(2);
+    if lines.len() <= max_lines {
+        return (source.to_string(), 0);
+    }
+
+    let head = max_lines.div_ceil(2);
+    let tail = max_lines - head;
+    let omitted = lines.len() - max_lines;
+    let indent: String = lines[head]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+
+    let mut kept = lines[..head].to_vec();
+    let marker = format!("{indent}...[{omitted} lines omitted]...");
+    kept.push(&marker);
+    kept.extend(&lines[lines.len() - tail..]);
+    (kept.join("\n"), omitted)
+}
+
+/// Header of an item enclosing an extracted symbol, e.g.
+/// `impl<T: Clone> Display for Wrapper<T> {`.
+struct EnclosingHeader {
+    kind: &'static str,
+    name: String,
+    /// 0-based line of the header.
+    line: u32,
+    text: String,
+}
+
+/// Collects the headers of the modules, impls and traits that enclose `range`,
+/// outermost first.
+async fn enclosing_headers(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    range: &Range,
+) -> Result<Vec<EnclosingHeader>> {
+    let ancestors = analyzer.enclosing_symbols(file_path, range.start.clone()).await?;
+    if ancestors.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", file_path, e))?;
+
+    Ok(ancestors
+        .into_iter()
+        .filter_map(|symbol| {
+            // rust-analyzer reports modules as Module, traits as Interface and
+            // impl blocks as Object.
+            let kind = match symbol.kind {
+                2 => "module",
+                11 => "trait",
+                19 => "impl",
+                _ => return None,
+            };
+            let (line, text) = item_header(&content, &symbol.range)?;
+            Some(EnclosingHeader {
+                kind,
+                name: symbol.name,
+                line,
+                text,
+            })
+        })
+        .collect())
+}
+
+/// The header of the item at `range`: its lines up to and including the
+/// opening brace, without leading doc comments and attributes.
+fn item_header(content: &str, range: &Range) -> Option<(u32, String)> {
+    let mut header = Vec::new();
+    let mut first_line = None;
+    for (idx, line) in content
+        .lines()
+        .enumerate()
+        .take(range.end.line as usize + 1)
+        .skip(range.start.line as usize)
+    {
+        let trimmed = line.trim_start();
+        if first_line.is_none()
+            && (trimmed.is_empty()
+                || trimmed.starts_with("//")
+                || trimmed.starts_with("#[")
+                || trimmed.starts_with("/*")
+                || trimmed.starts_with('*'))
+        {
+            continue;
+        }
+        first_line.get_or_insert(idx as u32);
+        match line.find('{') {
+            Some(brace) => {
+                header.push(&line[..=brace]);
+                return Some((first_line?, header.join("\n")));
+            }
+            None => header.push(line),
+        }
+    }
+    None
+}
+
+/// Surrounds `source` with the enclosing headers and matching closing braces.
+fn wrap_in_headers(source: &str, headers: &[EnclosingHeader]) -> String {
+    if headers.is_empty() {
+        return source.to_string();
+    }
+
+    let mut lines: Vec<String> = headers.iter().map(|header| header.text.clone()).collect();
+    lines.push(source.to_string());
+    for header in headers.iter().rev() {
+        let indent: String = header
+            .text
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        lines.push(format!("{indent}}}"));
+    }
+    lines.join("\n")
+}
+
 fn create_position_marker(line_content: &str, char_idx: u32) -> String {
     let mut marker = String::new();
     let mut current_char_count = 0;
@@ -1,19 +1,49 @@
 use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::protocol::{DocumentSymbolResponse, Position};
+use crate::compiler::diagnostics::{
+    CargoDiagnostic, FeatureSet, check_messages, check_with_features,
+};
+use crate::compiler::progress::CargoProgressSender;
+use crate::tools::quality::declared_dependencies;
 use crate::tools::types::ToolResult;
 use anyhow::Result;
 use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tokio::fs;
 
 pub async fn run_cargo_check_impl(
     args: Value,
     analyzer: &mut RustAnalyzerClient,
+    progress: Option<CargoProgressSender>,
 ) -> Result<ToolResult> {
     let workspace_path = args
         .get("workspace_path")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing workspace_path parameter"))?;
+    let file_path = args.get("file_path").and_then(|v| v.as_str());
 
-    // Implementation will run cargo check and parse results
-    let result = analyzer.run_cargo_check(workspace_path).await?;
+    // With a file, check only the package that owns it instead of the whole workspace.
+    let package = match file_path {
+        Some(file_path) => analyzer.package_for_file(workspace_path, file_path).await?,
+        None => None,
+    };
+
+    let mut result = analyzer
+        .run_cargo_check(workspace_path, package.as_deref(), progress.as_ref())
+        .await?;
+
+    match (file_path, &package) {
+        (Some(_), Some(package)) => {
+            result = format!("Checked package `{package}`.\n\n{result}");
+        }
+        (Some(file_path), None) => {
+            result = format!(
+                "No workspace package owns {file_path}; checked the whole workspace.\n\n{result}"
+            );
+        }
+        (None, _) => {}
+    }
 
     Ok(ToolResult {
         content: vec![
@@ -27,3 +57,1841 @@ pub async fn run_cargo_check_impl(
         ],
     })
 }
+
+/// Raw diagnostics returned by `diagnostic_histogram` unless `limit` says otherwise.
+const DEFAULT_HISTOGRAM_LIMIT: usize = 100;
+
+pub async fn diagnostic_histogram_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let workspace_path = args
+        .get("workspace_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing workspace_path parameter"))?;
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_HISTOGRAM_LIMIT, |limit| limit as usize);
+
+    let package = match args.get("file_path").and_then(|v| v.as_str()) {
+        Some(file_path) => analyzer.package_for_file(workspace_path, file_path).await?,
+        None => None,
+    };
+    let (passed, messages) = check_messages(Path::new(workspace_path), package.as_deref()).await?;
+    let histogram = diagnostic_histogram(&messages);
+    let errors = histogram
+        .diagnostics
+        .iter()
+        .filter(|(_, diagnostic)| diagnostic.level == "error")
+        .count();
+
+    let diagnostics: Vec<Value> = histogram
+        .diagnostics
+        .iter()
+        .take(limit)
+        .map(|(krate, diagnostic)| {
+            let mut entry = json!(diagnostic);
+            entry["crate"] = json!(krate);
+            entry
+        })
+        .collect();
+    let result = json!({
+        "package": package,
+        "passed": passed,
+        "errors": errors,
+        "warnings": histogram.diagnostics.len() - errors,
+        "by_code": histogram.by_code,
+        "by_crate": histogram.by_crate,
+        "diagnostics": diagnostics,
+        "truncated": histogram.diagnostics.len() > limit
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// `cargo check` diagnostics counted by code and by crate.
+#[derive(Debug)]
+struct DiagnosticHistogram {
+    /// Each distinct diagnostic with the crate reporting it, errors first.
+    diagnostics: Vec<(String, CargoDiagnostic)>,
+    /// `{code, level, count}`, most frequent first; diagnostics without a
+    /// code count under `null`.
+    by_code: Vec<Value>,
+    /// `{crate, errors, warnings}`, most diagnostics first.
+    by_crate: Vec<Value>,
+}
+
+/// Builds the histogram from `compiler-message`s. A diagnostic repeated for
+/// several targets of the same crate is counted once.
+fn diagnostic_histogram(messages: &[Value]) -> DiagnosticHistogram {
+    let diagnostics: BTreeSet<(String, CargoDiagnostic)> = messages
+        .iter()
+        .filter_map(|message| {
+            let diagnostic = CargoDiagnostic::from_message(message)?;
+            let krate = message
+                .get("target")
+                .and_then(|target| target.get("name"))
+                .and_then(|name| name.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            Some((krate, diagnostic))
+        })
+        .collect();
+    let mut diagnostics: Vec<_> = diagnostics.into_iter().collect();
+    diagnostics.sort_by_key(|(_, diagnostic)| diagnostic.level != "error");
+
+    let mut codes: BTreeMap<(Option<&str>, &str), usize> = BTreeMap::new();
+    let mut crates: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for (krate, diagnostic) in &diagnostics {
+        *codes
+            .entry((diagnostic.code.as_deref(), diagnostic.level.as_str()))
+            .or_default() += 1;
+        let counts = crates.entry(krate.as_str()).or_default();
+        if diagnostic.level == "error" {
+            counts.0 += 1;
+        } else {
+            counts.1 += 1;
+        }
+    }
+    let mut codes: Vec<_> = codes.into_iter().collect();
+    codes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let mut crates: Vec<_> = crates.into_iter().collect();
+    crates.sort_by_key(|(_, (errors, warnings))| std::cmp::Reverse(errors + warnings));
+
+    DiagnosticHistogram {
+        by_code: codes
+            .into_iter()
+            .map(|((code, level), count)| json!({"code": code, "level": level, "count": count}))
+            .collect(),
+        by_crate: crates
+            .into_iter()
+            .map(|(krate, (errors, warnings))| {
+                json!({"crate": krate, "errors": errors, "warnings": warnings})
+            })
+            .collect(),
+        diagnostics,
+    }
+}
+
+pub async fn preview_features_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let workspace_path = args
+        .get("workspace_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing workspace_path parameter"))?;
+    let features = FeatureSet {
+        features: args
+            .get("features")
+            .and_then(|v| v.as_array())
+            .map(|features| {
+                features
+                    .iter()
+                    .filter_map(|feature| feature.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        no_default_features: args
+            .get("no_default_features")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        all_features: args
+            .get("all_features")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+    };
+    if features == FeatureSet::default() {
+        return Err(anyhow::anyhow!(
+            "Nothing to compare: pass features, no_default_features or all_features"
+        ));
+    }
+
+    let package = match args.get("file_path").and_then(|v| v.as_str()) {
+        Some(file_path) => analyzer.package_for_file(workspace_path, file_path).await?,
+        None => None,
+    };
+
+    let workspace = Path::new(workspace_path);
+    let (default_passed, default_diagnostics) =
+        check_with_features(workspace, package.as_deref(), &FeatureSet::default()).await?;
+    let (passed, diagnostics) =
+        check_with_features(workspace, package.as_deref(), &features).await?;
+
+    let introduced: Vec<_> = diagnostics.difference(&default_diagnostics).collect();
+    let resolved: Vec<_> = default_diagnostics.difference(&diagnostics).collect();
+    let summary = |passed: bool, diagnostics: &BTreeSet<CargoDiagnostic>| {
+        let errors = diagnostics.iter().filter(|d| d.level == "error").count();
+        json!({
+            "passed": passed,
+            "errors": errors,
+            "warnings": diagnostics.len() - errors
+        })
+    };
+
+    let result = json!({
+        "package": package,
+        "flags": features.args().join(" "),
+        "default_features": summary(default_passed, &default_diagnostics),
+        "with_features": summary(passed, &diagnostics),
+        "introduced": introduced,
+        "resolved": resolved,
+        "unchanged": diagnostics.intersection(&default_diagnostics).count()
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn describe_build_script_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let manifest_path = args
+        .get("manifest_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing manifest_path parameter"))?;
+
+    let manifest_text = fs::read_to_string(manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+    let manifest: toml::Table = toml::from_str(&manifest_text)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path, e))?;
+
+    let crate_dir = Path::new(manifest_path).parent().unwrap_or(Path::new("."));
+    let declared = manifest
+        .get("package")
+        .and_then(|package| package.get("build"))
+        .is_some();
+    let build_script = build_script_path(&manifest, crate_dir);
+
+    let result = match &build_script {
+        Some(path) if path.is_file() => {
+            let content = fs::read_to_string(path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to read build script: {}", e))?;
+            let file_path = path.to_string_lossy();
+            let symbols = analyzer
+                .document_symbols(&file_path)
+                .await
+                .ok()
+                .and_then(|symbols| serde_json::to_value(symbols).ok());
+
+            let directives = scan_cargo_directives(&content);
+            let mut notes = Vec::new();
+            if !directives.iter().any(|d| d.key.starts_with("rerun-if-")) {
+                notes.push(
+                    "No rerun-if-changed/rerun-if-env-changed: Cargo reruns the script whenever any file in the package changes."
+                        .to_string(),
+                );
+            }
+            if directives.iter().any(|d| d.value.contains('{')) {
+                notes.push(
+                    "Values containing `{..}` are format placeholders filled in at build time."
+                        .to_string(),
+                );
+            }
+            notes.push(
+                "Directives printed by helper crates (cc, pkg-config, bindgen) or built at runtime are not seen by the scan."
+                    .to_string(),
+            );
+
+            json!({
+                "manifest_path": manifest_path,
+                "build_script": file_path,
+                "exists": true,
+                "line_count": content.lines().count(),
+                "directives": directives
+                    .iter()
+                    .map(|directive| json!({
+                        "line": directive.line + 1,
+                        "category": directive.category(),
+                        "key": directive.key,
+                        "value": directive.value
+                    }))
+                    .collect::<Vec<_>>(),
+                "symbols": symbols,
+                "notes": notes
+            })
+        }
+        _ => {
+            let note = match &build_script {
+                None => "Build scripts are disabled with `build = false`.".to_string(),
+                Some(path) if declared => {
+                    format!(
+                        "`build` points to {}, which does not exist.",
+                        path.display()
+                    )
+                }
+                Some(_) => "The package has no build script.".to_string(),
+            };
+            json!({
+                "manifest_path": manifest_path,
+                "build_script": build_script.map(|path| path.to_string_lossy().into_owned()),
+                "exists": false,
+                "notes": [note]
+            })
+        }
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The build script a manifest uses: `package.build` when it names a path,
+/// `None` for `build = false`, and `build.rs` next to the manifest otherwise.
+pub async fn list_runnables_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (source, runnables, note) = match analyzer.runnables(file_path).await {
+        Ok(runnables) => (
+            "rust-analyzer",
+            runnables.iter().filter_map(Runnable::from_lsp).collect(),
+            None,
+        ),
+        Err(e) => {
+            let runnables = scan_runnables(Path::new(file_path), &content).await?;
+            (
+                "scan",
+                runnables,
+                Some(format!(
+                    "rust-analyzer runnables unavailable ({}); found by scanning for #[test] and fn main, without doctests",
+                    e
+                )),
+            )
+        }
+    };
+
+    let mut result = json!({
+        "file_path": file_path,
+        "source": source,
+        "count": runnables.len(),
+        "runnables": runnables
+    });
+    if let Some(note) = note {
+        result["note"] = json!(note);
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Tests past this many are counted but not listed.
+const DEFAULT_TEST_LIMIT: usize = 500;
+
+pub async fn list_tests_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_TEST_LIMIT as u64)
+        .max(1) as usize;
+
+    let start = Path::new(path);
+    let manifest_path = if start.is_dir() && start.join("Cargo.toml").is_file() {
+        start.join("Cargo.toml")
+    } else {
+        owning_manifest(start)?
+    };
+    let crate_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let manifest = read_manifest(&manifest_path).await?;
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| anyhow::anyhow!("{} has no [package]", manifest_path.display()))?;
+    let has_lib = crate_dir.join("src/lib.rs").is_file();
+
+    let mut tests = Vec::new();
+    let mut symbol_files = 0;
+    for file in crate::tools::navigation::collect_rust_files(crate_dir).await? {
+        // Nested packages list their own tests.
+        if owning_manifest(&file).ok().as_deref() != Some(manifest_path.as_path()) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&file).await else {
+            continue;
+        };
+        let found = scan_harness_functions(&content);
+        if found.is_empty() {
+            continue;
+        }
+
+        let relative = file.strip_prefix(crate_dir).unwrap_or(&file);
+        let suite = match relative.iter().next().and_then(|part| part.to_str()) {
+            Some("tests") => "integration",
+            Some("benches") => "benchmark",
+            Some("examples") => "example",
+            _ => "unit",
+        };
+        let target = runnable_target(relative, package, has_lib);
+        let target_name = if target[0] == "--lib" {
+            package.replace('-', "_")
+        } else {
+            target[1].replace('-', "_")
+        };
+        let module_path = file_module_path(relative);
+        // Inline modules come from the document symbols when the server has
+        // them; the scan's brace counting is the fallback.
+        let symbols = match analyzer.document_symbols(&file.to_string_lossy()).await {
+            Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) => Some(symbols),
+            _ => None,
+        };
+        symbol_files += usize::from(symbols.is_some());
+
+        for (line, scanned, kind) in found {
+            let inner = match &symbols {
+                Some(symbols) => {
+                    let mut inner = crate::tools::analysis::inline_modules(
+                        symbols,
+                        &Position { line, character: 0 },
+                    );
+                    inner.extend(scanned.last().cloned());
+                    inner
+                }
+                None => scanned,
+            };
+            let filter = module_path
+                .iter()
+                .chain(&inner)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("::");
+            let subcommand = if kind == "bench" { "bench" } else { "test" };
+            let command = format!(
+                "cargo {} --package {} {} -- {} --exact",
+                subcommand,
+                package,
+                target.join(" "),
+                filter
+            );
+            tests.push(json!({
+                "path": format!("{}::{}", target_name, filter),
+                "kind": kind,
+                "suite": suite,
+                "file": relative.display().to_string(),
+                "line": line + 1,
+                "filter": filter,
+                "command": command
+            }));
+        }
+    }
+
+    let count = |suite: &str| tests.iter().filter(|test| test["suite"] == suite).count();
+    let by_suite = json!({
+        "unit": count("unit"),
+        "integration": count("integration"),
+        "benchmark": count("benchmark"),
+        "example": count("example")
+    });
+    let total = tests.len();
+    tests.truncate(limit);
+    let result = json!({
+        "package": package,
+        "manifest_path": manifest_path.display().to_string(),
+        "total": total,
+        "truncated": total > tests.len(),
+        "by_suite": by_suite,
+        "tests": tests,
+        "note": format!(
+            "Found by scanning for #[test], #[<path>::test] and #[bench] attributes; inline module paths from document symbols in {} file(s). Doctests and macro-generated tests are not listed.",
+            symbol_files
+        )
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Something rust-analyzer (or the fallback scan) knows how to run, with the
+/// command that runs it.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct Runnable {
+    label: String,
+    /// `test`, `test_module`, `doctest`, `bench`, `bin` or `cargo` (a whole
+    /// package check or test run).
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    /// The program's arguments, without the program itself.
+    args: Vec<String>,
+    command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+}
+
+impl Runnable {
+    fn new(label: String, line: Option<u32>, program: &str, args: Vec<String>) -> Self {
+        let command = std::iter::once(program)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            kind: runnable_kind(&label),
+            label,
+            line,
+            args,
+            command,
+            cwd: None,
+        }
+    }
+
+    /// Reads an `experimental/runnables` entry: `cargo` runnables carry
+    /// `cargoArgs` and `executableArgs` (passed after `--`), `shell` ones a
+    /// `program` and its `args`.
+    fn from_lsp(runnable: &Value) -> Option<Self> {
+        let label = runnable.get("label")?.as_str()?.to_string();
+        let line = runnable
+            .pointer("/location/targetRange/start/line")
+            .and_then(|line| line.as_u64())
+            .map(|line| line as u32 + 1);
+        let details = runnable.get("args")?;
+        let strings = |key: &str| -> Vec<String> {
+            details
+                .get(key)
+                .and_then(|values| values.as_array())
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut parsed = match runnable.get("kind").and_then(|kind| kind.as_str()) {
+            Some("shell") => Self::new(
+                label,
+                line,
+                details.get("program")?.as_str()?,
+                strings("args"),
+            ),
+            _ => {
+                let mut args = strings("cargoArgs");
+                args.extend(strings("cargoExtraArgs"));
+                let executable_args = strings("executableArgs");
+                if !executable_args.is_empty() {
+                    args.push("--".to_string());
+                    args.extend(executable_args);
+                }
+                let program = details
+                    .get("overrideCargo")
+                    .and_then(|program| program.as_str())
+                    .unwrap_or("cargo");
+                Self::new(label, line, program, args)
+            }
+        };
+        parsed.cwd = details
+            .get("cwd")
+            .or_else(|| details.get("workspaceRoot"))
+            .and_then(|cwd| cwd.as_str())
+            .map(str::to_string);
+        Some(parsed)
+    }
+}
+
+/// rust-analyzer has no kind field, but every label starts with one.
+fn runnable_kind(label: &str) -> &'static str {
+    match label.split_whitespace().next() {
+        Some("test") => "test",
+        Some("test-mod") => "test_module",
+        Some("doctest") => "doctest",
+        Some("bench") => "bench",
+        Some("run") => "bin",
+        _ => "cargo",
+    }
+}
+
+/// Finds `#[test]` functions (any attribute path ending in `test`, such as
+/// `#[tokio::test]`) and a crate root's `fn main` by scanning the source, for
+/// servers without `experimental/runnables`. Inline `mod` blocks are tracked
+/// by brace depth, so braces inside strings may throw the test paths off.
+async fn scan_runnables(file_path: &Path, content: &str) -> Result<Vec<Runnable>> {
+    let manifest_path = owning_manifest(file_path)?;
+    let crate_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let manifest = read_manifest(&manifest_path).await?;
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| anyhow::anyhow!("{} has no [package]", manifest_path.display()))?;
+
+    let relative = file_path.strip_prefix(crate_dir).unwrap_or(file_path);
+    let target = runnable_target(relative, package, crate_dir.join("src/lib.rs").is_file());
+    let module_path = file_module_path(relative);
+
+    let mut runnables = Vec::new();
+    for (line, path) in scan_test_functions(content) {
+        let path = module_path
+            .iter()
+            .chain(&path)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("::");
+        let mut args = vec![
+            "test".to_string(),
+            "--package".to_string(),
+            package.to_string(),
+        ];
+        args.extend(target.iter().cloned());
+        args.extend(["--".to_string(), path.clone(), "--exact".to_string()]);
+        runnables.push(Runnable::new(
+            format!("test {}", path),
+            Some(line + 1),
+            "cargo",
+            args,
+        ));
+    }
+
+    if let Some(line) = content
+        .lines()
+        .position(|line| line.starts_with("fn main(") || line.starts_with("async fn main("))
+        .filter(|_| module_path.is_empty() && target[0] == "--bin")
+    {
+        let mut args = vec![
+            "run".to_string(),
+            "--package".to_string(),
+            package.to_string(),
+        ];
+        args.extend(target.iter().cloned());
+        runnables.push(Runnable::new(
+            format!("run {}", target[1]),
+            Some(line as u32 + 1),
+            "cargo",
+            args,
+        ));
+    }
+    Ok(runnables)
+}
+
+/// The cargo target flags selecting the target `relative` (a path inside the
+/// package) belongs to.
+fn runnable_target(relative: &Path, package: &str, has_lib: bool) -> Vec<String> {
+    let parts: Vec<&str> = relative.iter().filter_map(|part| part.to_str()).collect();
+    let stem = |name: &str| name.trim_end_matches(".rs").to_string();
+    let (flag, name) = match parts.as_slice() {
+        ["src", "bin", name] => ("--bin", stem(name)),
+        ["src", "bin", name, ..] => ("--bin", name.to_string()),
+        ["tests", name, ..] => ("--test", stem(name)),
+        ["benches", name, ..] => ("--bench", stem(name)),
+        ["examples", name, ..] => ("--example", stem(name)),
+        ["src", "main.rs"] => ("--bin", package.to_string()),
+        _ if has_lib => return vec!["--lib".to_string()],
+        _ => ("--bin", package.to_string()),
+    };
+    vec![flag.to_string(), name]
+}
+
+/// The module path of the file `relative` within its crate: empty for crate
+/// roots, `["a", "b"]` for `src/a/b.rs` or `src/a/b/mod.rs`.
+pub(crate) fn file_module_path(relative: &Path) -> Vec<String> {
+    let parts: Vec<&str> = relative.iter().filter_map(|part| part.to_str()).collect();
+    let inner = match parts.as_slice() {
+        ["src", "bin", _, rest @ ..] | ["src", rest @ ..] => rest,
+        [_, _, rest @ ..] => rest,
+        _ => &[],
+    };
+    let mut path: Vec<String> = inner.iter().map(|part| part.to_string()).collect();
+    if let Some(last) = path.pop() {
+        let stem = last.trim_end_matches(".rs");
+        if !matches!(stem, "mod" | "lib" | "main") {
+            path.push(stem.to_string());
+        }
+    }
+    path
+}
+
+/// `(0-based line, path within the file)` of each test function, the path
+/// running through the inline modules enclosing it.
+fn scan_test_functions(content: &str) -> Vec<(u32, Vec<String>)> {
+    scan_harness_functions(content)
+        .into_iter()
+        .filter(|(_, _, kind)| *kind == "test")
+        .map(|(line, path, _)| (line, path))
+        .collect()
+}
+
+/// Like [`scan_test_functions`], but also finds `#[bench]` functions; each
+/// entry carries its kind, `test` or `bench`.
+fn scan_harness_functions(content: &str) -> Vec<(u32, Vec<String>, &'static str)> {
+    let mut tests = Vec::new();
+    // Open inline modules as (name, brace depth inside the module).
+    let mut modules: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0usize;
+    let mut pending_test: Option<&'static str> = None;
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("//") {
+            continue;
+        }
+        if let Some(attribute) = trimmed.strip_prefix("#[") {
+            let path = attribute.split(['(', ']']).next().unwrap_or("").trim();
+            if path == "test" || path.ends_with("::test") {
+                pending_test = Some("test");
+            } else if path == "bench" {
+                pending_test = Some("bench");
+            }
+        } else if let Some(name) = item_name(trimmed, "fn") {
+            if let Some(kind) = pending_test {
+                let mut path: Vec<String> = modules.iter().map(|(name, _)| name.clone()).collect();
+                path.push(name.to_string());
+                tests.push((idx as u32, path, kind));
+            }
+            pending_test = None;
+        } else if let Some(name) = item_name(trimmed, "mod").filter(|_| trimmed.contains('{')) {
+            modules.push((name.to_string(), depth + 1));
+            pending_test = None;
+        } else if !trimmed.is_empty() {
+            pending_test = None;
+        }
+
+        for c in line.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    if modules.last().is_some_and(|(_, inner)| depth < *inner) {
+                        modules.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    tests
+}
+
+/// The name declared by a `fn` or `mod` line, past visibility and qualifiers.
+fn item_name<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let mut words = line.split_whitespace();
+    let after = words.find(|word| {
+        !matches!(*word, "async" | "unsafe" | "const" | "extern" | "\"C\"")
+            && !word.starts_with("pub")
+    })?;
+    let rest = if after == keyword {
+        words.next()?
+    } else {
+        return None;
+    };
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    (end > 0).then(|| &rest[..end])
+}
+
+pub async fn check_msrv_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let manifest_path = owning_manifest(Path::new(file_path))?;
+    let manifest = read_manifest(&manifest_path).await?;
+    let edition = package_field(&manifest, &manifest_path, "edition")
+        .await?
+        .unwrap_or_else(|| "2015".to_string());
+    let rust_version = package_field(&manifest, &manifest_path, "rust-version").await?;
+    let msrv = rust_version.as_deref().and_then(parse_rust_version);
+
+    let usages = scan_versioned_features(&content);
+    let required = usages.iter().map(|usage| usage.feature.since).max();
+    let mut violations = Vec::new();
+    for usage in &usages {
+        let feature = usage.feature;
+        if msrv.is_some_and(|msrv| feature.since > msrv) {
+            violations.push(json!({
+                "feature": feature.name,
+                "line": usage.line,
+                "reason": format!(
+                    "stable since {}, after the declared rust-version {}",
+                    render_version(feature.since),
+                    rust_version.as_deref().unwrap_or_default()
+                )
+            }));
+        }
+        if feature
+            .edition
+            .is_some_and(|needed| edition.as_str() < needed)
+        {
+            violations.push(json!({
+                "feature": feature.name,
+                "line": usage.line,
+                "reason": format!(
+                    "needs edition {}, the crate uses {}",
+                    feature.edition.unwrap_or_default(),
+                    edition
+                )
+            }));
+        }
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "manifest_path": manifest_path.display().to_string(),
+        "edition": edition,
+        "rust_version": rust_version,
+        "required_version": required.map(render_version),
+        "compatible": violations.is_empty(),
+        "violations": violations,
+        "features": usages.iter().map(|usage| json!({
+            "feature": usage.feature.name,
+            "since": render_version(usage.feature.since),
+            "line": usage.line,
+            "code": usage.code
+        })).collect::<Vec<_>>(),
+        "heuristic": "Features are found by scanning source lines for their syntax or API names, not by compiling; macros are not expanded, method names can belong to other types, and only the features listed in the tool's description are recognized. Confirm with `cargo +<rust-version> check`."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn find_manifest_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let path = Path::new(file_path);
+    // A directory may hold the manifest itself; a file never does.
+    let start = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    let nearest = start
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file());
+
+    let result = match nearest {
+        None => json!({
+            "file_path": file_path,
+            "found": false,
+            "message": format!("No Cargo.toml in {} or any directory above it", start.display())
+        }),
+        Some(nearest) => {
+            let manifest = read_manifest(&nearest).await?;
+            let package_name = manifest
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(|name| name.as_str());
+            let is_package = manifest.contains_key("package");
+            let is_workspace = manifest.contains_key("workspace");
+            let kind = match (is_package, is_workspace) {
+                (true, true) => "package_and_workspace",
+                (true, false) => "package",
+                (false, true) => "virtual_workspace",
+                (false, false) => "unknown",
+            };
+
+            let (workspace_manifest, excluded_by) = if is_workspace {
+                (Some(nearest.clone()), None)
+            } else if is_package {
+                owning_workspace(&manifest, &nearest).await?
+            } else {
+                (None, None)
+            };
+
+            let mut result = json!({
+                "file_path": file_path,
+                "found": true,
+                "manifest_path": nearest.display().to_string(),
+                "kind": kind,
+                "package_manifest": is_package.then(|| nearest.display().to_string()),
+                "package_name": package_name,
+                "workspace_manifest": workspace_manifest
+                    .as_ref()
+                    .map(|manifest| manifest.display().to_string()),
+                "is_workspace_root": is_workspace,
+                "separate_workspace": workspace_manifest.as_ref().is_some_and(|manifest| *manifest != nearest)
+            });
+            if let Some(excluded_by) = excluded_by {
+                result["not_a_member_of"] = json!(excluded_by.display().to_string());
+            }
+            result
+        }
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The workspace root manifest of the package at `manifest_path`: the one its
+/// `package.workspace` key names, or else the nearest manifest above with a
+/// `[workspace]` that lists the package in `members` and not in `exclude`.
+/// A workspace above that does not include the package is returned second.
+async fn owning_workspace(
+    manifest: &toml::Table,
+    manifest_path: &Path,
+) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
+    let package_dir = manifest_path.parent().unwrap_or(manifest_path);
+    if let Some(root) = manifest
+        .get("package")
+        .and_then(|package| package.get("workspace"))
+        .and_then(|root| root.as_str())
+    {
+        return Ok((Some(package_dir.join(root).join("Cargo.toml")), None));
+    }
+
+    for dir in package_dir.ancestors().skip(1) {
+        let candidate = dir.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+        let root = read_manifest(&candidate).await?;
+        let Some(workspace) = root.get("workspace").and_then(|w| w.as_table()) else {
+            continue;
+        };
+        let relative = package_dir.strip_prefix(dir).unwrap_or(package_dir);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        // Cargo stops at the first workspace root above a package either way.
+        return Ok(if workspace_includes(workspace, &relative) {
+            (Some(candidate), None)
+        } else {
+            (None, Some(candidate))
+        });
+    }
+    Ok((None, None))
+}
+
+/// Whether a `[workspace]` table includes the package directory at `relative`
+/// (to the workspace root) through its `members` globs, minus `exclude`.
+fn workspace_includes(workspace: &toml::Table, relative: &str) -> bool {
+    let patterns = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|list| list.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|pattern| pattern.as_str())
+            .map(|pattern| {
+                pattern
+                    .trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .collect()
+    };
+    let matches = |pattern: &String| {
+        let parts: Vec<&str> = pattern.split('/').collect();
+        let segments: Vec<&str> = relative.split('/').collect();
+        parts.len() == segments.len()
+            && parts
+                .iter()
+                .zip(&segments)
+                .all(|(part, segment)| glob_segment(part, segment))
+    };
+    // Excluding a directory excludes everything under it.
+    let excluded = patterns("exclude")
+        .iter()
+        .any(|excluded| relative == excluded || relative.starts_with(&format!("{excluded}/")));
+    !excluded && patterns("members").iter().any(matches)
+}
+
+/// Matches one path segment against a glob segment with `*` and `?`.
+fn glob_segment(pattern: &str, text: &str) -> bool {
+    match pattern.chars().next() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len())
+            .filter(|&idx| text.is_char_boundary(idx))
+            .any(|idx| glob_segment(&pattern[1..], &text[idx..])),
+        Some('?') => text
+            .chars()
+            .next()
+            .is_some_and(|c| glob_segment(&pattern[1..], &text[c.len_utf8()..])),
+        Some(c) => text
+            .strip_prefix(c)
+            .is_some_and(|rest| glob_segment(&pattern[c.len_utf8()..], rest)),
+    }
+}
+
+/// The edition of the package owning `file_path`, `None` when it has no
+/// manifest or declares none.
+pub(crate) async fn package_edition(file_path: &Path) -> Result<Option<String>> {
+    let Ok(manifest_path) = owning_manifest(file_path) else {
+        return Ok(None);
+    };
+    let manifest = read_manifest(&manifest_path).await?;
+    package_field(&manifest, &manifest_path, "edition").await
+}
+
+/// The name the library of the package at `manifest_path` is imported by:
+/// `[lib] name`, or the package name with `-` replaced by `_`.
+pub(crate) async fn library_crate_name(manifest_path: &Path) -> Result<String> {
+    let manifest = read_manifest(manifest_path).await?;
+    let lib_name = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(|name| name.as_str());
+    let package_name = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str());
+    lib_name
+        .or(package_name)
+        .map(|name| name.replace('-', "_"))
+        .ok_or_else(|| anyhow::anyhow!("{} has no [package]", manifest_path.display()))
+}
+
+/// The `Cargo.toml` of the package containing `file_path`.
+pub(crate) fn owning_manifest(file_path: &Path) -> Result<PathBuf> {
+    file_path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+        .ok_or_else(|| anyhow::anyhow!("No Cargo.toml above {}", file_path.display()))
+}
+
+async fn read_manifest(manifest_path: &Path) -> Result<toml::Table> {
+    let manifest_text = fs::read_to_string(manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+    toml::from_str(&manifest_text)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path.display(), e))
+}
+
+/// A `[package]` string field, following `field.workspace = true` to the
+/// `[workspace.package]` of the nearest manifest above that has a `[workspace]`.
+async fn package_field(
+    manifest: &toml::Table,
+    manifest_path: &Path,
+    field: &str,
+) -> Result<Option<String>> {
+    let Some(value) = manifest
+        .get("package")
+        .and_then(|package| package.get(field))
+    else {
+        return Ok(None);
+    };
+    if let Some(value) = value.as_str() {
+        return Ok(Some(value.to_string()));
+    }
+    if value.get("workspace").and_then(|inherit| inherit.as_bool()) != Some(true) {
+        return Ok(None);
+    }
+    for dir in manifest_path.ancestors().skip(1) {
+        let candidate = dir.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+        let root = read_manifest(&candidate).await?;
+        if let Some(workspace) = root.get("workspace") {
+            return Ok(workspace
+                .get("package")
+                .and_then(|package| package.get(field))
+                .and_then(|value| value.as_str())
+                .map(str::to_string));
+        }
+    }
+    Ok(None)
+}
+
+/// `1.70` or `1.70.0` as `(1, 70)`; patch releases never stabilize features.
+fn parse_rust_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+    Some((major, minor))
+}
+
+fn render_version((major, minor): (u32, u32)) -> String {
+    format!("{major}.{minor}")
+}
+
+/// A language or library feature `check_msrv` recognizes by its spelling.
+#[derive(Debug)]
+struct VersionedFeature {
+    name: &'static str,
+    /// The Rust release that stabilized it.
+    since: (u32, u32),
+    /// The edition it needs, if any.
+    edition: Option<&'static str>,
+    /// Whether a code line (comments stripped) uses it; the flag is set
+    /// inside a `trait` body.
+    used_in: fn(&str, bool) -> bool,
+}
+
+const VERSIONED_FEATURES: &[VersionedFeature] = &[
+    VersionedFeature {
+        name: "let-else",
+        since: (1, 65),
+        edition: None,
+        used_in: |line, _| {
+            line.trim_start().starts_with("let ")
+                && line.contains('=')
+                && (line.contains(" else {") || line.trim_end().ends_with(" else"))
+        },
+    },
+    VersionedFeature {
+        name: "generic associated types",
+        since: (1, 65),
+        edition: None,
+        used_in: |line, in_trait| {
+            let line = line.trim_start();
+            in_trait
+                && line.starts_with("type ")
+                && !line.contains('=')
+                && line
+                    .split([':', ';'])
+                    .next()
+                    .is_some_and(|name| name.contains('<'))
+        },
+    },
+    VersionedFeature {
+        name: "OnceLock",
+        since: (1, 70),
+        edition: None,
+        used_in: |line, _| line.contains("OnceLock"),
+    },
+    VersionedFeature {
+        name: "Option::is_some_and / Result::is_ok_and",
+        since: (1, 70),
+        edition: None,
+        used_in: |line, _| line.contains(".is_some_and(") || line.contains(".is_ok_and("),
+    },
+    VersionedFeature {
+        name: "async fn in traits",
+        since: (1, 75),
+        edition: None,
+        used_in: |line, in_trait| in_trait && line.contains("async fn "),
+    },
+    VersionedFeature {
+        name: "return-position impl Trait in traits",
+        since: (1, 75),
+        edition: None,
+        used_in: |line, in_trait| in_trait && line.contains("-> impl "),
+    },
+    VersionedFeature {
+        name: "C string literals",
+        since: (1, 77),
+        edition: Some("2021"),
+        used_in: |line, _| {
+            line.match_indices("c\"")
+                .any(|(idx, _)| !line[..idx].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+        },
+    },
+    VersionedFeature {
+        name: "#[diagnostic::..] attributes",
+        since: (1, 78),
+        edition: None,
+        used_in: |line, _| line.contains("#[diagnostic::"),
+    },
+    VersionedFeature {
+        name: "inline const blocks",
+        since: (1, 79),
+        edition: None,
+        used_in: |line, _| {
+            line.match_indices("const {").any(|(idx, _)| {
+                !line[..idx]
+                    .trim_end()
+                    .ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            })
+        },
+    },
+    VersionedFeature {
+        name: "LazyLock / LazyCell",
+        since: (1, 80),
+        edition: None,
+        used_in: |line, _| line.contains("LazyLock") || line.contains("LazyCell"),
+    },
+    VersionedFeature {
+        name: "#[expect(..)] lint attribute",
+        since: (1, 81),
+        edition: None,
+        used_in: |line, _| line.contains("#[expect(") || line.contains("#![expect("),
+    },
+    VersionedFeature {
+        name: "&raw const / &raw mut",
+        since: (1, 82),
+        edition: None,
+        used_in: |line, _| line.contains("&raw const ") || line.contains("&raw mut "),
+    },
+    VersionedFeature {
+        name: "unsafe extern blocks",
+        since: (1, 82),
+        edition: None,
+        used_in: |line, _| line.contains("unsafe extern \"") && line.trim_end().ends_with('{'),
+    },
+    VersionedFeature {
+        name: "precise capturing use<..>",
+        since: (1, 82),
+        edition: None,
+        used_in: |line, _| line.contains("+ use<") || line.contains("impl use<"),
+    },
+    VersionedFeature {
+        name: "Option::is_none_or",
+        since: (1, 82),
+        edition: None,
+        used_in: |line, _| line.contains(".is_none_or("),
+    },
+    VersionedFeature {
+        name: "async closures",
+        since: (1, 85),
+        edition: Some("2018"),
+        used_in: |line, _| line.contains("async |") || line.contains("async move |"),
+    },
+    VersionedFeature {
+        name: "let chains",
+        since: (1, 88),
+        edition: Some("2024"),
+        used_in: |line, _| {
+            line.contains("&& let ")
+                || line.trim_start().starts_with("if let ") && line.contains(" && ")
+                || line.trim_start().starts_with("while let ") && line.contains(" && ")
+        },
+    },
+];
+
+/// One line using a [`VersionedFeature`].
+#[derive(Debug)]
+struct FeatureUsage {
+    feature: &'static VersionedFeature,
+    line: usize,
+    code: String,
+}
+
+/// Finds the [`VERSIONED_FEATURES`] used in `content`, line by line. Line
+/// comments are stripped (naively: a `//` inside a string also ends the
+/// line) and `trait` bodies are tracked by brace depth.
+fn scan_versioned_features(content: &str) -> Vec<FeatureUsage> {
+    let mut usages = Vec::new();
+    // Brace depth inside each open `trait` body.
+    let mut traits: Vec<usize> = Vec::new();
+    let mut depth = 0usize;
+    let mut trait_pending = false;
+    for (idx, line) in content.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or("");
+        let trimmed = code.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('*') || trimmed.starts_with("/*") {
+            continue;
+        }
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        trait_pending |= words
+            .iter()
+            .take_while(|word| !word.starts_with('{'))
+            .any(|word| *word == "trait");
+
+        let in_trait = !traits.is_empty() && traits.last() == Some(&depth);
+        for feature in VERSIONED_FEATURES {
+            if (feature.used_in)(code, in_trait) {
+                usages.push(FeatureUsage {
+                    feature,
+                    line: idx + 1,
+                    code: trimmed.trim_end().to_string(),
+                });
+            }
+        }
+
+        for c in code.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    if trait_pending {
+                        traits.push(depth);
+                        trait_pending = false;
+                    }
+                }
+                '}' => {
+                    if traits.last() == Some(&depth) {
+                        traits.pop();
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                ';' => trait_pending = false,
+                _ => {}
+            }
+        }
+    }
+    usages
+}
+
+pub(crate) fn build_script_path(manifest: &toml::Table, crate_dir: &Path) -> Option<PathBuf> {
+    match manifest.get("package").and_then(|p| p.get("build")) {
+        Some(toml::Value::String(path)) => Some(crate_dir.join(path)),
+        Some(toml::Value::Boolean(false)) => None,
+        _ => Some(crate_dir.join("build.rs")),
+    }
+}
+
+/// A feature of a package: a `[features]` entry, or the implicit feature of
+/// an optional dependency that no feature names with `dep:`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct DeclaredFeature {
+    pub(crate) name: String,
+    /// Other features, `dep:name` and `crate/feature` entries it turns on.
+    pub(crate) enables: Vec<String>,
+    pub(crate) implicit: bool,
+}
+
+pub(crate) fn declared_features(manifest: &toml::Table) -> Vec<DeclaredFeature> {
+    let mut features: Vec<DeclaredFeature> = manifest
+        .get("features")
+        .and_then(|features| features.as_table())
+        .into_iter()
+        .flatten()
+        .map(|(name, enables)| DeclaredFeature {
+            name: name.clone(),
+            enables: enables
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.as_str())
+                .map(str::to_string)
+                .collect(),
+            implicit: false,
+        })
+        .collect();
+
+    let dep_entries: Vec<String> = features
+        .iter()
+        .flat_map(|feature| &feature.enables)
+        .filter_map(|entry| entry.strip_prefix("dep:"))
+        .map(str::to_string)
+        .collect();
+    for dependency in declared_dependencies(manifest) {
+        if dependency.optional
+            && !dep_entries.contains(&dependency.name)
+            && !features
+                .iter()
+                .any(|feature| feature.name == dependency.name)
+        {
+            features.push(DeclaredFeature {
+                enables: vec![format!("dep:{}", dependency.name)],
+                name: dependency.name,
+                implicit: true,
+            });
+        }
+    }
+    features
+}
+
+/// The features turned on by enabling `features`: themselves and every
+/// declared feature they name, transitively. `crate/feature` entries of an
+/// optional dependency turn on its implicit feature; `dep:` and `crate?/feature`
+/// entries only affect dependencies.
+pub(crate) fn enabled_features(
+    declared: &[DeclaredFeature],
+    features: &[&str],
+) -> BTreeSet<String> {
+    let mut enabled = BTreeSet::new();
+    let mut stack: Vec<&str> = features.to_vec();
+    while let Some(name) = stack.pop() {
+        if !enabled.insert(name.to_string()) {
+            continue;
+        }
+        let Some(feature) = declared.iter().find(|feature| feature.name == name) else {
+            continue;
+        };
+        for entry in &feature.enables {
+            let target = entry.split('/').next().unwrap_or(entry);
+            if !entry.starts_with("dep:")
+                && !target.ends_with('?')
+                && declared.iter().any(|feature| feature.name == target)
+            {
+                stack.push(target);
+            }
+        }
+    }
+    enabled
+}
+
+/// `required` without the features another of them already turns on, so
+/// `--features` stays as short as possible.
+pub(crate) fn minimal_features<'a>(
+    declared: &[DeclaredFeature],
+    required: &[&'a str],
+) -> Vec<&'a str> {
+    let mut minimal = required.to_vec();
+    for feature in required {
+        let others: Vec<&str> = minimal
+            .iter()
+            .copied()
+            .filter(|other| other != feature)
+            .collect();
+        if enabled_features(declared, &others).contains(*feature) {
+            minimal.retain(|other| other != feature);
+        }
+    }
+    minimal
+}
+
+/// A `cargo:` or `cargo::` instruction found in a string literal of a build script.
+#[derive(Debug, PartialEq)]
+struct CargoDirective {
+    /// 0-based line of the string literal.
+    line: usize,
+    key: String,
+    value: String,
+}
+
+impl CargoDirective {
+    fn category(&self) -> &'static str {
+        match self.key.as_str() {
+            "rerun-if-changed" | "rerun-if-env-changed" => "rerun",
+            "rustc-cfg" | "rustc-check-cfg" => "cfg",
+            "rustc-env" => "env",
+            "warning" | "error" => "message",
+            "rustc-flags" | "rustc-link-lib" | "rustc-link-search" => "link",
+            key if key.starts_with("rustc-link-arg") || key == "rustc-cdylib-link-arg" => "link",
+            // Any other `cargo:KEY=VALUE` is metadata for dependents (`links`).
+            _ => "metadata",
+        }
+    }
+}
+
+/// Finds `"cargo:key=value"` and `"cargo::key=value"` string literals, skipping
+/// line comments. Placeholders such as `{}` are kept verbatim.
+fn scan_cargo_directives(content: &str) -> Vec<CargoDirective> {
+    let mut directives = Vec::new();
+    for (line_index, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("//") {
+            continue;
+        }
+        for (idx, _) in line.match_indices("\"cargo:") {
+            let rest = &line[idx + "\"cargo:".len()..];
+            let rest = rest.strip_prefix(':').unwrap_or(rest);
+            let literal = rest.split('"').next().unwrap_or(rest);
+            let literal = literal.strip_suffix("\\n").unwrap_or(literal);
+            let (key, value) = literal.split_once('=').unwrap_or((literal, ""));
+            if key.is_empty() {
+                continue;
+            }
+            directives.push(CargoDirective {
+                line: line_index,
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+    directives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_owning_workspace() {
+        let dir =
+            std::env::temp_dir().join(format!("rust-mcp-find-manifest-{}", std::process::id()));
+        for package in ["crates/app", "crates/skip", "tools/gen"] {
+            std::fs::create_dir_all(dir.join(package)).unwrap();
+            std::fs::write(
+                dir.join(package).join("Cargo.toml"),
+                "[package]\nname = \"member\"\n",
+            )
+            .unwrap();
+        }
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/skip\"]\n",
+        )
+        .unwrap();
+
+        let owner = |package: &str| {
+            let manifest_path = dir.join(package).join("Cargo.toml");
+            async move {
+                let manifest = read_manifest(&manifest_path).await.unwrap();
+                owning_workspace(&manifest, &manifest_path).await.unwrap()
+            }
+        };
+        let root = dir.join("Cargo.toml");
+        assert_eq!(owner("crates/app").await, (Some(root.clone()), None));
+        assert_eq!(owner("crates/skip").await, (None, Some(root.clone())));
+        assert_eq!(owner("tools/gen").await, (None, Some(root)));
+
+        assert!(glob_segment("rust-*", "rust-mcp"));
+        assert!(glob_segment("a?c", "abc"));
+        assert!(!glob_segment("a*d", "abc"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_test_functions() {
+        let content = r#"fn helper() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        assert!(true);
+    }
+
+    mod nested {
+        #[tokio::test]
+        async fn runs() {}
+    }
+
+    #[test]
+    #[should_panic]
+    fn panics() {}
+}
+"#;
+        assert_eq!(
+            scan_test_functions(content),
+            vec![
+                (7, vec!["tests".to_string(), "parses".to_string()]),
+                (
+                    13,
+                    vec![
+                        "tests".to_string(),
+                        "nested".to_string(),
+                        "runs".to_string()
+                    ]
+                ),
+                (18, vec!["tests".to_string(), "panics".to_string()]),
+            ]
+        );
+
+        assert_eq!(
+            file_module_path(Path::new("src/tools/cargo.rs")),
+            vec!["tools", "cargo"]
+        );
+        assert!(file_module_path(Path::new("src/bin/server/main.rs")).is_empty());
+        assert_eq!(
+            runnable_target(Path::new("src/tools/mod.rs"), "app", true),
+            vec!["--lib"]
+        );
+        assert_eq!(
+            runnable_target(Path::new("tests/integration.rs"), "app", true),
+            vec!["--test", "integration"]
+        );
+        assert_eq!(
+            runnable_target(Path::new("src/main.rs"), "app", true),
+            vec!["--bin", "app"]
+        );
+    }
+
+    #[test]
+    fn test_scan_harness_functions() {
+        let content = r#"#![feature(test)]
+extern crate test;
+
+#[bench]
+fn parse_large(b: &mut test::Bencher) {}
+
+mod cases {
+    #[async_std::test]
+    async fn loads() {}
+}
+"#;
+        assert_eq!(
+            scan_harness_functions(content),
+            vec![
+                (4, vec!["parse_large".to_string()], "bench"),
+                (8, vec!["cases".to_string(), "loads".to_string()], "test"),
+            ]
+        );
+        assert!(
+            scan_test_functions(content)
+                .iter()
+                .all(|(line, _)| *line != 4)
+        );
+    }
+
+    #[test]
+    fn test_runnable_from_lsp() {
+        let runnable = json!({
+            "label": "test tests::parses",
+            "kind": "cargo",
+            "location": {"targetRange": {"start": {"line": 7, "character": 4}}},
+            "args": {
+                "workspaceRoot": "/repo",
+                "cargoArgs": ["test", "--package", "app", "--lib"],
+                "executableArgs": ["tests::parses", "--exact", "--show-output"]
+            }
+        });
+        let parsed = Runnable::from_lsp(&runnable).unwrap();
+        assert_eq!(parsed.kind, "test");
+        assert_eq!(parsed.line, Some(8));
+        assert_eq!(
+            parsed.command,
+            "cargo test --package app --lib -- tests::parses --exact --show-output"
+        );
+        assert_eq!(parsed.cwd.as_deref(), Some("/repo"));
+        assert_eq!(runnable_kind("test-mod tests"), "test_module");
+        assert_eq!(runnable_kind("cargo check -p app"), "cargo");
+    }
+
+    #[test]
+    fn test_diagnostic_histogram() {
+        let message = |krate: &str, level: &str, code: Option<&str>, line: u64| {
+            json!({
+                "reason": "compiler-message",
+                "target": {"name": krate},
+                "message": {
+                    "level": level,
+                    "code": code.map(|code| json!({"code": code})),
+                    "message": "msg",
+                    "spans": [{"file_name": "src/lib.rs", "line_start": line, "is_primary": true}]
+                }
+            })
+        };
+        let messages = vec![
+            message("app", "warning", Some("unused_variables"), 3),
+            message("app", "warning", Some("unused_variables"), 9),
+            // The same warning again for the crate's test target.
+            message("app", "warning", Some("unused_variables"), 9),
+            message("core_lib", "warning", Some("unused_variables"), 4),
+            message("core_lib", "error", Some("E0308"), 12),
+            message("core_lib", "error", None, 20),
+        ];
+        let histogram = diagnostic_histogram(&messages);
+        assert_eq!(histogram.diagnostics.len(), 5);
+        assert_eq!(histogram.diagnostics[0].1.level, "error");
+        assert_eq!(
+            histogram.by_code,
+            vec![
+                json!({"code": "unused_variables", "level": "warning", "count": 3}),
+                json!({"code": null, "level": "error", "count": 1}),
+                json!({"code": "E0308", "level": "error", "count": 1}),
+            ]
+        );
+        assert_eq!(
+            histogram.by_crate,
+            vec![
+                json!({"crate": "core_lib", "errors": 2, "warnings": 1}),
+                json!({"crate": "app", "errors": 0, "warnings": 2}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_features() {
+        let manifest: toml::Table = toml::from_str(
+            r#"
+[features]
+default = ["std"]
+std = []
+json = ["dep:serde_json", "serde/derive"]
+
+[dependencies]
+serde = { version = "1", optional = true }
+serde_json = { version = "1", optional = true }
+log = "0.4"
+"#,
+        )
+        .unwrap();
+        let features = declared_features(&manifest);
+        let summary: Vec<_> = features
+            .iter()
+            .map(|f| (f.name.as_str(), f.enables.join(","), f.implicit))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("default", "std".to_string(), false),
+                ("json", "dep:serde_json,serde/derive".to_string(), false),
+                ("std", String::new(), false),
+                ("serde", "dep:serde".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimal_features() {
+        let manifest: toml::Table = toml::from_str(
+            r#"
+[features]
+default = ["std"]
+std = []
+full = ["json", "tls"]
+json = ["dep:serde_json", "serde/derive"]
+tls = ["rustls?/ring"]
+
+[dependencies]
+serde = { version = "1", optional = true }
+serde_json = { version = "1", optional = true }
+rustls = { version = "0.23", optional = true }
+"#,
+        )
+        .unwrap();
+        let declared = declared_features(&manifest);
+        assert_eq!(
+            enabled_features(&declared, &["full"]),
+            BTreeSet::from(["full", "json", "serde", "tls"].map(String::from))
+        );
+        assert_eq!(
+            minimal_features(&declared, &["json", "std", "full"]),
+            vec!["std", "full"]
+        );
+    }
+
+    #[test]
+    fn test_scan_cargo_directives() {
+        let content = r#"fn main() {
+    // println!("cargo:rerun-if-changed=old.h");
+    println!("cargo:rerun-if-changed=wrapper.h");
+    println!("cargo::rustc-check-cfg=cfg(has_simd)");
+    println!("cargo:rustc-cfg=has_simd");
+    println!("cargo:rustc-link-lib=static={}", lib);
+    println!("cargo:include={}", out_dir.display());
+}
+"#;
+        let directives = scan_cargo_directives(content);
+        let summary: Vec<_> = directives
+            .iter()
+            .map(|d| (d.line, d.category(), d.key.as_str(), d.value.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, "rerun", "rerun-if-changed", "wrapper.h"),
+                (3, "cfg", "rustc-check-cfg", "cfg(has_simd)"),
+                (4, "cfg", "rustc-cfg", "has_simd"),
+                (5, "link", "rustc-link-lib", "static={}"),
+                (6, "metadata", "include", "{}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_script_path() {
+        let dir = Path::new("/work/pkg");
+        let manifest: toml::Table = toml::from_str("[package]\nname = \"pkg\"\n").unwrap();
+        assert_eq!(
+            build_script_path(&manifest, dir),
+            Some(dir.join("build.rs"))
+        );
+        let manifest: toml::Table =
+            toml::from_str("[package]\nname = \"pkg\"\nbuild = \"tools/gen.rs\"\n").unwrap();
+        assert_eq!(
+            build_script_path(&manifest, dir),
+            Some(dir.join("tools/gen.rs"))
+        );
+        let manifest: toml::Table =
+            toml::from_str("[package]\nname = \"pkg\"\nbuild = false\n").unwrap();
+        assert_eq!(build_script_path(&manifest, dir), None);
+    }
+
+    #[test]
+    fn test_scan_versioned_features() {
+        let content = r#"trait Store {
+    type Item<'a>;
+    async fn load(&self) -> u32;
+}
+fn parse(input: Option<u32>) -> u32 {
+    // let Some(x) = input else { return 0 };
+    let Some(value) = input else { return 0 };
+    if let Some(x) = input && x > 1 {}
+    static CACHE: LazyLock<u32> = LazyLock::new(|| 1);
+    value
+}
+"#;
+        let found: Vec<(&str, usize)> = scan_versioned_features(content)
+            .iter()
+            .map(|usage| (usage.feature.name, usage.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("generic associated types", 2),
+                ("async fn in traits", 3),
+                ("let-else", 7),
+                ("let chains", 8),
+                ("LazyLock / LazyCell", 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rust_version() {
+        assert_eq!(parse_rust_version("1.70"), Some((1, 70)));
+        assert_eq!(parse_rust_version("1.82.1"), Some((1, 82)));
+        assert_eq!(parse_rust_version("1"), Some((1, 0)));
+        assert_eq!(parse_rust_version("stable"), None);
+    }
+}
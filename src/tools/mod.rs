@@ -6,7 +6,7 @@ pub mod quality;
 pub mod refactoring;
 pub mod types;
 
-pub use types::{ToolDefinition, ToolResult, execute_tool, get_tools};
+pub use types::{ToolDefinition, ToolResult, execute_tool, execute_tool_with_progress, get_tools};
 
 // Re-export all tool functions for easy access
 pub use advanced::*;
@@ -1,7 +1,30 @@
 use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::client::DefinitionDetails;
+use crate::analyzer::protocol::{DocumentSymbol, DocumentSymbolResponse, Location, Range};
+use crate::analyzer::symbol::{
+    PathMatch, SymbolIdentity, identity_from_definition, match_identity_path, parse_rust_path,
+    symbol_information_to_identity,
+};
+use crate::compiler::cfg::TargetCfg;
+use crate::tools::advanced::{last_path_segment, split_generic_params};
+use crate::tools::analysis::{
+    DEFAULT_SOURCE_MAX_LINES, find_symbol_location, index_to_line_col, is_valid_code_context,
+    line_start_offset, named_symbol_at, string_literal_len, truncate_middle, utf16_column_offset,
+};
+use crate::tools::cargo::{
+    declared_features, enabled_features, file_module_path, library_crate_name, minimal_features,
+    owning_manifest,
+};
+use crate::tools::quality::declared_dependencies;
+use crate::tools::refactoring::{
+    HoverSignature, ItemVisibility, declared_visibility, find_matching_brace, find_matching_paren,
+    parse_hover_signature, split_top_level, text_in_range,
+};
 use crate::tools::types::ToolResult;
 use anyhow::Result;
 use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 
 pub async fn workspace_symbols_impl(
     args: Value,
@@ -11,15 +34,2148 @@ pub async fn workspace_symbols_impl(
         .get("query")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing query parameter"))?;
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_WORKSPACE_SYMBOL_LIMIT as u64)
+        .max(1) as usize;
+    let offset = args
+        .get("offset")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as usize;
 
-    // Implementation will use rust-analyzer LSP to search workspace symbols
-    let result = analyzer.workspace_symbols(query).await?;
+    let infos = analyzer.workspace_symbol_infos(query).await?;
+    let (total, symbols) = paginate_workspace_symbols(&infos, offset, limit);
+    let next_offset = (offset + symbols.len() < total).then_some(offset + symbols.len());
+
+    let result = json!({
+        "query": query,
+        "total": total,
+        "offset": offset,
+        "limit": limit,
+        "next_offset": next_offset,
+        "symbols": symbols
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+const DEFAULT_WORKSPACE_SYMBOL_LIMIT: usize = 100;
+
+/// Orders workspace symbols by crate, module path and name (then location, to
+/// break ties) so that pages stay stable between calls, and returns the total
+/// count together with the requested page.
+fn paginate_workspace_symbols(infos: &[Value], offset: usize, limit: usize) -> (usize, Vec<Value>) {
+    let mut entries: Vec<_> = infos
+        .iter()
+        .filter_map(|info| {
+            let identity = symbol_information_to_identity(info)?;
+            let location: Location = serde_json::from_value(info.get("location")?.clone()).ok()?;
+            Some((identity, location, info.get("kind").cloned()))
+        })
+        .collect();
+
+    entries.sort_by(|(a, a_loc, _), (b, b_loc, _)| {
+        (&a.crate_name, &a.module_path, &a.item_name, &a_loc.uri)
+            .cmp(&(&b.crate_name, &b.module_path, &b.item_name, &b_loc.uri))
+            .then(a_loc.range.start.line.cmp(&b_loc.range.start.line))
+            .then(a_loc.range.start.character.cmp(&b_loc.range.start.character))
+    });
+
+    let total = entries.len();
+    let page = entries
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(identity, location, kind)| {
+            json!({
+                "name": identity.item_name,
+                "kind": kind,
+                "crate": identity.crate_name,
+                "module_path": identity.module_path,
+                "file_path": location.uri.strip_prefix("file://").unwrap_or(&location.uri),
+                "line": location.range.start.line + 1,
+                "character": location.range.start.character + 1
+            })
+        })
+        .collect();
+
+    (total, page)
+}
+
+pub async fn find_by_signature_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let query = args
+        .get("query")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing query parameter"))?;
+    let filter = SignatureFilter {
+        is_async: args.get("is_async").and_then(|v| v.as_bool()),
+        returns: args.get("returns").and_then(|v| v.as_str()).map(str::to_string),
+        takes: args.get("takes").and_then(|v| v.as_str()).map(str::to_string),
+    };
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_SIGNATURE_MATCH_LIMIT as u64)
+        .max(1) as usize;
+
+    // A trailing '#' makes rust-analyzer include functions and methods.
+    let infos = analyzer.workspace_symbol_infos(&format!("{query}#")).await?;
+    let candidates: Vec<(String, Location)> = infos
+        .iter()
+        .filter(|info| matches!(info.get("kind").and_then(|k| k.as_u64()), Some(6 | 12)))
+        .filter_map(|info| {
+            let name = info.get("name")?.as_str()?.to_string();
+            let location = serde_json::from_value(info.get("location")?.clone()).ok()?;
+            Some((name, location))
+        })
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut scanned = 0;
+    for (name, location) in candidates.iter().take(MAX_SIGNATURE_CANDIDATES) {
+        if matches.len() >= limit {
+            break;
+        }
+        scanned += 1;
+
+        let file_path = location.uri.strip_prefix("file://").unwrap_or(&location.uri);
+        let start = &location.range.start;
+        let Ok(hover) = analyzer.get_hover(file_path, start.line, start.character).await else {
+            continue;
+        };
+        let Some(signature) = parse_hover_signature(&hover) else {
+            continue;
+        };
+        if !filter.matches(&signature) {
+            continue;
+        }
+
+        matches.push(json!({
+            "name": name,
+            "owner": signature.owner,
+            "file_path": file_path,
+            "line": start.line + 1,
+            "character": start.character + 1,
+            "signature": signature.signature
+        }));
+    }
+
+    let mut notes = Vec::new();
+    if scanned < candidates.len() {
+        notes.push(format!(
+            "Checked {scanned} of {} candidate functions; narrow the query to check the rest.",
+            candidates.len()
+        ));
+    }
+
+    let result = json!({
+        "query": query,
+        "candidates": candidates.len(),
+        "scanned": scanned,
+        "matches": matches,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+const DEFAULT_SIGNATURE_MATCH_LIMIT: usize = 50;
+/// Each candidate costs a hover request, so the scan is bounded.
+const MAX_SIGNATURE_CANDIDATES: usize = 200;
+
+/// Predicates over a hover-derived signature; unset fields match anything.
+struct SignatureFilter {
+    is_async: Option<bool>,
+    /// Substring of the return type, e.g. `Result`.
+    returns: Option<String>,
+    /// Substring of the receiver or of any parameter type, e.g. `&mut self`.
+    takes: Option<String>,
+}
+
+impl SignatureFilter {
+    fn matches(&self, signature: &HoverSignature) -> bool {
+        if self.is_async.is_some_and(|is_async| is_async != signature.is_async) {
+            return false;
+        }
+        if let Some(pattern) = &self.returns {
+            let returns = signature.return_type.as_deref().unwrap_or("()");
+            if !returns.contains(pattern.as_str()) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.takes {
+            let mut inputs = signature.receiver.iter().chain(&signature.param_types);
+            if !inputs.any(|input| input.contains(pattern.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub async fn list_cfg_regions_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let file_content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let occurrences = scan_cfg_occurrences(&file_content);
+
+    let target_cfg = match args.get("target").and_then(|v| v.as_str()) {
+        Some(target) => {
+            let dir = Path::new(file_path).parent().unwrap_or(Path::new("."));
+            Some(TargetCfg::load(dir, (target != "host").then_some(target)).await?)
+        }
+        None => None,
+    };
+
+    // Symbols are only needed to name gated items; the scan stands on its own.
+    let symbols = if occurrences.is_empty() {
+        Vec::new()
+    } else {
+        flat_document_symbols(analyzer, file_path).await
+    };
+
+    let regions: Vec<Value> = occurrences
+        .iter()
+        .map(|occurrence| {
+            let predicate = CfgPredicate::parse(&occurrence.predicate);
+            let readable = predicate
+                .as_ref()
+                .map(|predicate| predicate.render())
+                .unwrap_or_else(|| occurrence.predicate.clone());
+            let active = target_cfg
+                .as_ref()
+                .zip(predicate.as_ref())
+                .and_then(|(cfg, predicate)| predicate.evaluate(cfg));
+
+            let (scope, start_line, end_line, item) =
+                cfg_region(occurrence, &file_content, &symbols);
+
+            let mut region = json!({
+                "kind": occurrence.kind.as_str(),
+                "line": occurrence.line + 1,
+                "condition": occurrence.predicate,
+                "readable": readable,
+                "applies": occurrence.applies,
+                "scope": scope,
+                "item": item,
+                "start_line": start_line + 1,
+                "end_line": end_line + 1
+            });
+            if target_cfg.is_some() {
+                region["active"] = json!(active);
+            }
+            region
+        })
+        .collect();
+
+    let mut result = json!({
+        "file_path": file_path,
+        "regions": regions
+    });
+    if let Some(target) = args.get("target").and_then(|v| v.as_str()) {
+        result["target"] = json!(target);
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// What a `cfg` occurrence gates: its scope (`file`, `expression` or
+/// `item`), 0-based first and last line, and the gated item's name if known.
+fn cfg_region(
+    occurrence: &CfgOccurrence,
+    content: &str,
+    symbols: &[(String, Range, Range)],
+) -> (&'static str, usize, usize, Option<String>) {
+    match occurrence.kind {
+        CfgKind::InnerAttribute => ("file", 0, content.lines().count().max(1) - 1, None),
+        CfgKind::Macro => ("expression", occurrence.line, occurrence.line, None),
+        CfgKind::Attribute | CfgKind::AttributeCfgAttr => {
+            match gated_symbol(symbols, occurrence.line) {
+                Some((name, range)) => (
+                    "item",
+                    range.start.line as usize,
+                    range.end.line as usize,
+                    Some(name),
+                ),
+                None => {
+                    let end =
+                        gated_item_end_line(content, occurrence.end).unwrap_or(occurrence.line);
+                    ("item", occurrence.line, end, None)
+                }
+            }
+        }
+    }
+}
+
+pub async fn feature_map_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let manifest_path = args
+        .get("manifest_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing manifest_path parameter"))?;
+
+    let manifest_text = tokio::fs::read_to_string(manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+    let manifest: toml::Table = toml::from_str(&manifest_text)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path, e))?;
+    let declared = declared_features(&manifest);
+    let crate_dir = Path::new(manifest_path).parent().unwrap_or(Path::new("."));
+
+    let files = collect_rust_files(crate_dir).await?;
+    let mut gates: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for file in &files {
+        let Ok(content) = tokio::fs::read_to_string(file).await else {
+            continue;
+        };
+        let gating: Vec<_> = scan_cfg_occurrences(&content)
+            .into_iter()
+            .filter_map(|occurrence| {
+                let predicate = CfgPredicate::parse(&occurrence.predicate)?;
+                let features: Vec<String> = predicate
+                    .features()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect();
+                (!features.is_empty()).then(|| (occurrence, predicate.render(), features))
+            })
+            .collect();
+        if gating.is_empty() {
+            continue;
+        }
+
+        let file_path = file.to_string_lossy();
+        let symbols = flat_document_symbols(analyzer, &file_path).await;
+        for (occurrence, readable, features) in gating {
+            let (scope, start_line, end_line, item) = cfg_region(&occurrence, &content, &symbols);
+            let gate = json!({
+                "file_path": file_path,
+                "kind": occurrence.kind.as_str(),
+                "condition": occurrence.predicate,
+                "readable": readable,
+                "scope": scope,
+                "item": item,
+                "start_line": start_line + 1,
+                "end_line": end_line + 1
+            });
+            for feature in features {
+                gates.entry(feature).or_default().push(gate.clone());
+            }
+        }
+    }
+
+    let defaults: Vec<&str> = declared
+        .iter()
+        .find(|feature| feature.name == "default")
+        .map(|feature| feature.enables.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+    let features: Vec<Value> = declared
+        .iter()
+        .map(|feature| {
+            let gated = gates.get(&feature.name).cloned().unwrap_or_default();
+            json!({
+                "name": feature.name,
+                "enables": feature.enables,
+                "implicit": feature.implicit,
+                "default": defaults.contains(&feature.name.as_str()),
+                "gated": gated
+            })
+        })
+        .collect();
+    // `default` and features that only turn on others rarely gate code themselves.
+    let ungated: Vec<Value> = declared
+        .iter()
+        .filter(|feature| feature.name != "default" && !gates.contains_key(&feature.name))
+        .map(|feature| json!({"name": feature.name, "enables": feature.enables}))
+        .collect();
+    let undeclared: Vec<Value> = gates
+        .iter()
+        .filter(|(name, _)| !declared.iter().any(|feature| &feature.name == *name))
+        .map(|(name, gated)| json!({"name": name, "gated": gated}))
+        .collect();
+
+    let result = json!({
+        "manifest_path": manifest_path,
+        "files_scanned": files.len(),
+        "features": features,
+        "ungated_features": ungated,
+        "undeclared_features": undeclared
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn required_features_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let manifest_path = owning_manifest(Path::new(file_path))?;
+    let manifest_text = tokio::fs::read_to_string(&manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+    let manifest: toml::Table = toml::from_str(&manifest_text)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path.display(), e))?;
+    let declared = declared_features(&manifest);
+
+    // `cfg_attr` and `cfg!` never remove code, so only `cfg` attributes count:
+    // those in the file, and those on the `mod` declarations leading to it.
+    let feature_gates = |content: &str| -> Vec<(CfgOccurrence, CfgPredicate)> {
+        scan_cfg_occurrences(content)
+            .into_iter()
+            .filter(|occurrence| {
+                matches!(
+                    occurrence.kind,
+                    CfgKind::Attribute | CfgKind::InnerAttribute
+                )
+            })
+            .filter_map(|occurrence| {
+                let predicate = CfgPredicate::parse(&occurrence.predicate)?;
+                (!predicate.features().is_empty()).then_some((occurrence, predicate))
+            })
+            .collect()
+    };
+    let file_gates = feature_gates(&content);
+    let symbols = if file_gates.is_empty() {
+        Vec::new()
+    } else {
+        flat_document_symbols(analyzer, file_path).await
+    };
+    let mut gates: Vec<(Value, CfgPredicate)> = file_gates
+        .into_iter()
+        .map(|(occurrence, predicate)| {
+            let (scope, _, _, item) = cfg_region(&occurrence, &content, &symbols);
+            let gate = json!({
+                "file_path": file_path,
+                "line": occurrence.line + 1,
+                "scope": scope,
+                "item": item,
+                "condition": occurrence.predicate
+            });
+            (gate, predicate)
+        })
+        .collect();
+
+    let walk = walk_to_crate_root(Path::new(file_path)).await;
+    for link in &walk.links {
+        let (Some(parent_file), Some(line), Some(module)) = (
+            link.get("parent_file").and_then(|v| v.as_str()),
+            link.get("line").and_then(|v| v.as_u64()),
+            link.get("module").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let Ok(parent_content) = tokio::fs::read_to_string(parent_file).await else {
+            continue;
+        };
+        for (occurrence, predicate) in feature_gates(&parent_content) {
+            if occurrence.kind == CfgKind::Attribute
+                && gated_item_end_line(&parent_content, occurrence.end) == Some(line as usize - 1)
+            {
+                let gate = json!({
+                    "file_path": parent_file,
+                    "line": occurrence.line + 1,
+                    "scope": "module",
+                    "item": module,
+                    "condition": occurrence.predicate
+                });
+                gates.push((gate, predicate));
+            }
+        }
+    }
+
+    let is_declared = |name: &str| declared.iter().any(|feature| feature.name == name);
+    let mut required: Vec<&str> = Vec::new();
+    let mut undeclared: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for (gate, predicate) in &gates {
+        for feature in predicate.enabling_features().unwrap_or_default() {
+            if is_declared(feature) && !required.contains(&feature) {
+                required.push(feature);
+            }
+        }
+        for feature in predicate.features() {
+            if !is_declared(feature) {
+                undeclared.entry(feature).or_default().push(json!({
+                    "file_path": gate["file_path"],
+                    "line": gate["line"]
+                }));
+            }
+        }
+    }
+    let minimal = minimal_features(&declared, &required);
+    let enabled = enabled_features(&declared, &minimal);
+    let defaults = if is_declared("default") {
+        enabled_features(&declared, &["default"])
+    } else {
+        Default::default()
+    };
+    let extra: Vec<&str> = minimal
+        .iter()
+        .copied()
+        .filter(|feature| !defaults.contains(*feature))
+        .collect();
+
+    let mut conflicts = Vec::new();
+    let gates: Vec<Value> = gates
+        .iter()
+        .map(|(gate, predicate)| {
+            let mut gate = gate.clone();
+            let clashing: Vec<&str> = predicate
+                .excluded_features()
+                .into_iter()
+                .filter(|feature| enabled.contains(*feature) || defaults.contains(*feature))
+                .collect();
+            if !clashing.is_empty() {
+                conflicts.push(json!({
+                    "file_path": gate["file_path"],
+                    "line": gate["line"],
+                    "condition": gate["condition"],
+                    "features": clashing
+                }));
+            }
+            gate["readable"] = json!(predicate.render());
+            gate["requires"] = json!(predicate.enabling_features());
+            gate
+        })
+        .collect();
+    let undeclared: Vec<Value> = undeclared
+        .into_iter()
+        .map(|(name, locations)| json!({"name": name, "locations": locations}))
+        .collect();
+
+    let mut result = json!({
+        "file_path": file_path,
+        "manifest_path": manifest_path.display().to_string(),
+        "required_features": minimal,
+        "enabled_features": enabled,
+        "default_features_suffice": extra.is_empty(),
+        "cargo_args": (!extra.is_empty()).then(|| format!("--features {}", extra.join(","))),
+        "gates": gates,
+        "undeclared_features": undeclared,
+        "conflicts": conflicts
+    });
+    if gates.is_empty() {
+        result["message"] = json!("No feature-gated code: the file compiles with any feature set");
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn list_test_only_items_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut items = Vec::new();
+    let mut by_kind: BTreeMap<&str, usize> = BTreeMap::new();
+    for file in &files {
+        let Ok(content) = tokio::fs::read_to_string(file).await else {
+            continue;
+        };
+        // `cfg_attr(test, ..)` only adds attributes; the item exists either way.
+        let gating: Vec<_> = scan_cfg_occurrences(&content)
+            .into_iter()
+            .filter(|occurrence| {
+                matches!(
+                    occurrence.kind,
+                    CfgKind::Attribute | CfgKind::InnerAttribute
+                )
+            })
+            .filter_map(|occurrence| {
+                let predicate = CfgPredicate::parse(&occurrence.predicate)?;
+                predicate
+                    .enabled_by_flag("test")
+                    .then_some((occurrence, predicate))
+            })
+            .collect();
+        if gating.is_empty() {
+            continue;
+        }
+
+        let file_path = file.to_string_lossy();
+        let symbols = flat_document_symbols(analyzer, &file_path).await;
+        for (occurrence, predicate) in gating {
+            let (_, start_line, end_line, name) = cfg_region(&occurrence, &content, &symbols);
+            let kind = match occurrence.kind {
+                CfgKind::InnerAttribute => "file",
+                _ => gated_item_kind(&content[occurrence.end..]),
+            };
+            let nested_items = symbols
+                .iter()
+                .filter(|(_, range, _)| {
+                    let (start, end) = (range.start.line as usize, range.end.line as usize);
+                    start >= start_line && end <= end_line && (start, end) != (start_line, end_line)
+                })
+                .count();
+            *by_kind.entry(kind).or_default() += 1;
+            items.push(json!({
+                "file_path": file_path,
+                "kind": kind,
+                "name": name,
+                "line": occurrence.line + 1,
+                "start_line": start_line + 1,
+                "end_line": end_line + 1,
+                "condition": occurrence.predicate,
+                "readable": predicate.render(),
+                "only_in_tests": predicate.requires_flag("test"),
+                "nested_items": nested_items
+            }));
+        }
+    }
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "total": items.len(),
+        "by_kind": by_kind,
+        "items": items
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The kind of item that starts in `rest`, the source just past a `cfg`
+/// attribute: `function`, `module`, `impl`, `use`, ... or `statement`.
+fn gated_item_kind(rest: &str) -> &'static str {
+    let mut rest = rest.trim_start();
+    loop {
+        if rest.starts_with("#[") {
+            let Some(close) = rest.find(']') else {
+                return "statement";
+            };
+            rest = rest[close + 1..].trim_start();
+        } else if rest.starts_with("//") {
+            rest = rest
+                .split_once('\n')
+                .map_or("", |(_, after)| after)
+                .trim_start();
+        } else {
+            break;
+        }
+    }
+    if let Some(after) = rest.strip_prefix("pub") {
+        rest = match after.trim_start().strip_prefix('(') {
+            Some(scope) => scope.split_once(')').map_or("", |(_, after)| after),
+            None => after,
+        };
+    }
+
+    let header_end = rest
+        .find(['{', ';', '=', '(', '<', ':'])
+        .unwrap_or(rest.len());
+    let words: Vec<&str> = rest[..header_end]
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.contains(&"fn") {
+        return "function";
+    }
+    let declaration = rest[header_end..].starts_with(';');
+    const KEYWORD_KINDS: [&str; 8] = [
+        "struct", "enum", "union", "trait", "impl", "use", "const", "static",
+    ];
+    for word in &words {
+        if let Some(kind) = KEYWORD_KINDS.iter().find(|kind| *kind == word) {
+            return kind;
+        }
+        match *word {
+            "mod" if declaration => return "module_declaration",
+            "mod" => return "module",
+            "type" => return "type_alias",
+            "macro_rules" => return "macro",
+            "crate" if words.first() == Some(&"extern") => return "extern_crate",
+            _ => {}
+        }
+    }
+    if words.first() == Some(&"extern") {
+        "extern_block"
+    } else {
+        "statement"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CfgKind {
+    Attribute,
+    AttributeCfgAttr,
+    InnerAttribute,
+    Macro,
+}
+
+impl CfgKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            CfgKind::Attribute => "cfg",
+            CfgKind::AttributeCfgAttr => "cfg_attr",
+            CfgKind::InnerAttribute => "inner_cfg",
+            CfgKind::Macro => "cfg_macro",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CfgOccurrence {
+    kind: CfgKind,
+    /// 0-based line of the attribute or macro.
+    line: usize,
+    /// Byte offset just past the attribute or macro call.
+    end: usize,
+    predicate: String,
+    /// Attributes a `cfg_attr` applies when its predicate holds.
+    applies: Option<String>,
+}
+
+/// Finds `#[cfg(..)]`, `#![cfg(..)]`, `#[cfg_attr(..)]` and `cfg!(..)` in
+/// code, skipping comments and string literals.
+fn scan_cfg_occurrences(content: &str) -> Vec<CfgOccurrence> {
+    const PATTERNS: [(&str, CfgKind); 4] = [
+        ("#![cfg(", CfgKind::InnerAttribute),
+        ("#[cfg(", CfgKind::Attribute),
+        ("#[cfg_attr(", CfgKind::AttributeCfgAttr),
+        ("cfg!(", CfgKind::Macro),
+    ];
+
+    let mut occurrences = Vec::new();
+    for (pattern, kind) in PATTERNS {
+        for (idx, _) in content.match_indices(pattern) {
+            if kind == CfgKind::Macro
+                && content[..idx]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_')
+            {
+                continue;
+            }
+            if !is_valid_code_context(content, idx) {
+                continue;
+            }
+
+            let open = idx + pattern.len() - 1;
+            let Some(close) = find_matching_paren(&content[open..]).map(|close| open + close)
+            else {
+                continue;
+            };
+            let inner = &content[open + 1..close];
+            let end = content[close..]
+                .find(']')
+                .filter(|_| kind != CfgKind::Macro)
+                .map_or(close + 1, |offset| close + offset + 1);
+
+            let (predicate, applies) = if kind == CfgKind::AttributeCfgAttr {
+                let mut parts = split_top_level(inner).into_iter();
+                let predicate = parts.next().unwrap_or_default();
+                let applies = parts.collect::<Vec<_>>().join(", ");
+                (predicate, Some(applies))
+            } else {
+                (collapse(inner), None)
+            };
+
+            occurrences.push(CfgOccurrence {
+                kind,
+                line: content[..idx].matches('\n').count(),
+                end,
+                predicate: collapse(&predicate),
+                applies,
+            });
+        }
+    }
+
+    occurrences.sort_by_key(|occurrence| (occurrence.line, occurrence.end));
+    occurrences
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The document symbols of `file_path` as `flatten_symbols` entries; empty
+/// when rust-analyzer cannot provide them.
+async fn flat_document_symbols(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+) -> Vec<(String, Range, Range)> {
+    match analyzer.document_symbols(file_path).await {
+        Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) => flatten_symbols(&symbols),
+        Ok(DocumentSymbolResponse::SymbolInformation(infos)) => infos
+            .into_iter()
+            .map(|info| (info.name, info.location.range.clone(), info.location.range))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Flattens a symbol tree into `(name, range, selection_range)` entries.
+fn flatten_symbols(symbols: &[DocumentSymbol]) -> Vec<(String, Range, Range)> {
+    let mut flat = Vec::new();
+    for symbol in symbols {
+        flat.push((
+            symbol.name.clone(),
+            symbol.range.clone(),
+            symbol.selection_range.clone(),
+        ));
+        if let Some(children) = &symbol.children {
+            flat.extend(flatten_symbols(children));
+        }
+    }
+    flat
+}
+
+/// The innermost symbol whose range covers the attribute on `line` and whose
+/// name comes after it, i.e. the item the attribute is attached to.
+fn gated_symbol(symbols: &[(String, Range, Range)], line: usize) -> Option<(String, Range)> {
+    let line = line as u32;
+    symbols
+        .iter()
+        .filter(|(_, range, selection)| {
+            range.start.line <= line && range.end.line >= line && selection.start.line >= line
+        })
+        .min_by_key(|(_, range, _)| range.end.line - range.start.line)
+        .map(|(name, range, _)| (name.clone(), range.clone()))
+}
+
+/// Fallback for items without a document symbol (`use`, statements, ...):
+/// the line where the item following the attribute ends.
+fn gated_item_end_line(content: &str, from: usize) -> Option<usize> {
+    gated_item_end(content, from).map(|end| content[..end].matches('\n').count())
+}
+
+/// Byte offset of the `;`, `,` or closing `}` that ends the item starting
+/// at `from`.
+fn gated_item_end(content: &str, from: usize) -> Option<usize> {
+    let rest = &content[from..];
+    let mut depth = 0i32;
+    for (idx, c) in rest.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ';' | ',' if depth == 0 => return Some(from + idx),
+            '{' if depth == 0 => return find_matching_brace(content, from + idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Byte ranges of `content` that only compile for tests: items under a
+/// `cfg` requiring `test` (the whole file for `#![cfg(test)]`) and functions
+/// marked `#[test]` or `#[<runtime>::test]`.
+pub(crate) fn test_code_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    for occurrence in scan_cfg_occurrences(content) {
+        let requires_test = CfgPredicate::parse(&occurrence.predicate)
+            .is_some_and(|predicate| predicate.requires_flag("test"));
+        match occurrence.kind {
+            CfgKind::InnerAttribute if requires_test => return vec![(0, content.len())],
+            CfgKind::Attribute if requires_test => {
+                let end = gated_item_end(content, occurrence.end).unwrap_or(occurrence.end);
+                spans.push((occurrence.end, end));
+            }
+            _ => {}
+        }
+    }
+
+    for (idx, _) in content.match_indices("#[") {
+        if !is_valid_code_context(content, idx) {
+            continue;
+        }
+        let Some(close) = content[idx..].find(']').map(|offset| idx + offset) else {
+            continue;
+        };
+        let attribute = &content[idx + 2..close];
+        let path = attribute.split('(').next().unwrap_or(attribute).trim();
+        if path == "test" || path.ends_with("::test") {
+            let end = gated_item_end(content, close + 1).unwrap_or(close + 1);
+            spans.push((close + 1, end));
+        }
+    }
+    spans.sort_unstable();
+    spans
+}
+
+/// A parsed `cfg` predicate.
+#[derive(Debug, PartialEq)]
+enum CfgPredicate {
+    Flag(String),
+    KeyValue(String, String),
+    All(Vec<CfgPredicate>),
+    Any(Vec<CfgPredicate>),
+    Not(Box<CfgPredicate>),
+}
+
+impl CfgPredicate {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        for (name, build) in [
+            ("all", CfgPredicate::All as fn(Vec<CfgPredicate>) -> CfgPredicate),
+            ("any", CfgPredicate::Any),
+        ] {
+            if let Some(args) = strip_call(text, name) {
+                return split_top_level(args)
+                    .iter()
+                    .map(|arg| Self::parse(arg))
+                    .collect::<Option<Vec<_>>>()
+                    .map(build);
+            }
+        }
+        if let Some(arg) = strip_call(text, "not") {
+            return Self::parse(arg).map(|inner| CfgPredicate::Not(Box::new(inner)));
+        }
+        if let Some((key, value)) = text.split_once('=') {
+            return Some(CfgPredicate::KeyValue(
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            ));
+        }
+        (!text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '_'))
+            .then(|| CfgPredicate::Flag(text.to_string()))
+    }
+
+    fn render(&self) -> String {
+        self.render_nested(false)
+    }
+
+    /// The Cargo features the predicate mentions, in order, without repeats.
+    fn features(&self) -> Vec<&str> {
+        let mut features = Vec::new();
+        let mut stack = vec![self];
+        while let Some(predicate) = stack.pop() {
+            match predicate {
+                CfgPredicate::KeyValue(key, value)
+                    if key == "feature" && !features.contains(&value.as_str()) =>
+                {
+                    features.push(value.as_str());
+                }
+                CfgPredicate::All(items) | CfgPredicate::Any(items) => {
+                    stack.extend(items.iter().rev());
+                }
+                CfgPredicate::Not(inner) => stack.push(inner),
+                _ => {}
+            }
+        }
+        features
+    }
+
+    /// The fewest features that turn the predicate on, counting everything
+    /// else as satisfiable: `not(..)` holds by leaving features off, and
+    /// `any(..)` takes its cheapest branch. `None` when nothing can, as for
+    /// `any()`.
+    fn enabling_features(&self) -> Option<Vec<&str>> {
+        match self {
+            CfgPredicate::KeyValue(key, value) if key == "feature" => Some(vec![value.as_str()]),
+            CfgPredicate::Flag(_) | CfgPredicate::KeyValue(..) | CfgPredicate::Not(_) => {
+                Some(Vec::new())
+            }
+            CfgPredicate::All(items) => {
+                let mut features = Vec::new();
+                for item in items {
+                    for feature in item.enabling_features()? {
+                        if !features.contains(&feature) {
+                            features.push(feature);
+                        }
+                    }
+                }
+                Some(features)
+            }
+            CfgPredicate::Any(items) => items
+                .iter()
+                .filter_map(|item| item.enabling_features())
+                .min_by_key(|features| features.len()),
+        }
+    }
+
+    /// Features that must stay off for the predicate to hold, from
+    /// `not(feature = ..)` and `not(any(feature = .., ..))` terms.
+    fn excluded_features(&self) -> Vec<&str> {
+        fn feature(predicate: &CfgPredicate) -> Option<&str> {
+            match predicate {
+                CfgPredicate::KeyValue(key, value) if key == "feature" => Some(value.as_str()),
+                _ => None,
+            }
+        }
+        match self {
+            CfgPredicate::All(items) => items
+                .iter()
+                .flat_map(|item| item.excluded_features())
+                .collect(),
+            CfgPredicate::Not(inner) => match inner.as_ref() {
+                CfgPredicate::Any(items) => items.iter().filter_map(feature).collect(),
+                inner => feature(inner).into_iter().collect(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether every configuration satisfying the predicate sets `flag`.
+    fn requires_flag(&self, flag: &str) -> bool {
+        match self {
+            CfgPredicate::Flag(name) => name == flag,
+            CfgPredicate::All(items) => items.iter().any(|item| item.requires_flag(flag)),
+            CfgPredicate::Any(items) => {
+                !items.is_empty() && items.iter().all(|item| item.requires_flag(flag))
+            }
+            CfgPredicate::KeyValue(..) | CfgPredicate::Not(_) => false,
+        }
+    }
+
+    /// Whether setting `flag` can turn the predicate on, i.e. the flag is
+    /// mentioned outside any `not(..)`.
+    fn enabled_by_flag(&self, flag: &str) -> bool {
+        match self {
+            CfgPredicate::Flag(name) => name == flag,
+            CfgPredicate::All(items) | CfgPredicate::Any(items) => {
+                items.iter().any(|item| item.enabled_by_flag(flag))
+            }
+            CfgPredicate::KeyValue(..) | CfgPredicate::Not(_) => false,
+        }
+    }
+
+    /// Whether the predicate holds for `cfg`, or `None` when it depends on
+    /// something `rustc --print cfg` cannot tell: features, `test`, `doc`,
+    /// `debug_assertions` (set by the build profile) or custom `--cfg` flags.
+    fn evaluate(&self, cfg: &TargetCfg) -> Option<bool> {
+        match self {
+            CfgPredicate::Flag(flag) if cfg.has_flag(flag) && flag != "debug_assertions" => {
+                Some(true)
+            }
+            CfgPredicate::Flag(flag) => {
+                matches!(flag.as_str(), "unix" | "windows" | "target_thread_local").then_some(false)
+            }
+            CfgPredicate::KeyValue(key, _) if key == "feature" => None,
+            CfgPredicate::KeyValue(key, value) => (key.starts_with("target_")
+                || cfg.values.contains_key(key))
+            .then(|| cfg.has_value(key, value)),
+            CfgPredicate::All(items) => {
+                let results: Vec<_> = items.iter().map(|item| item.evaluate(cfg)).collect();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else {
+                    results.iter().all(Option::is_some).then_some(true)
+                }
+            }
+            CfgPredicate::Any(items) => {
+                let results: Vec<_> = items.iter().map(|item| item.evaluate(cfg)).collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else {
+                    results.iter().all(Option::is_some).then_some(false)
+                }
+            }
+            CfgPredicate::Not(inner) => inner.evaluate(cfg).map(|value| !value),
+        }
+    }
+
+    fn render_nested(&self, nested: bool) -> String {
+        let join = |items: &[CfgPredicate], sep: &str| {
+            let text = items
+                .iter()
+                .map(|item| item.render_nested(true))
+                .collect::<Vec<_>>()
+                .join(sep);
+            if nested && items.len() > 1 { format!("({text})") } else { text }
+        };
+
+        match self {
+            CfgPredicate::Flag(flag) => flag.clone(),
+            CfgPredicate::KeyValue(key, value) if key == "feature" => {
+                format!("feature \"{value}\" enabled")
+            }
+            CfgPredicate::KeyValue(key, value) => format!("{key} is \"{value}\""),
+            CfgPredicate::All(items) if items.is_empty() => "always".to_string(),
+            CfgPredicate::Any(items) if items.is_empty() => "never".to_string(),
+            CfgPredicate::All(items) => join(items, " and "),
+            CfgPredicate::Any(items) => join(items, " or "),
+            CfgPredicate::Not(inner) => format!("not {}", inner.render_nested(true)),
+        }
+    }
+}
+
+fn strip_call<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.strip_prefix(name)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+pub async fn show_cfg_impl(args: Value, _analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let target = args.get("target").and_then(|v| v.as_str());
+    let dir = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .and_then(|path| Path::new(path).parent())
+        .unwrap_or(Path::new("."));
+
+    let cfg = TargetCfg::load(dir, target.filter(|t| *t != "host")).await?;
+    let single = |key: &str| cfg.values.get(key).and_then(|values| values.first());
+    let result = json!({
+        "target": target.unwrap_or("host"),
+        "target_os": single("target_os"),
+        "target_family": cfg.values.get("target_family"),
+        "target_arch": single("target_arch"),
+        "target_pointer_width": single("target_pointer_width"),
+        "target_env": single("target_env"),
+        "target_features": cfg.values.get("target_feature"),
+        "flags": cfg.flags,
+        "values": cfg.values
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn list_macros_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let crate_path = args
+        .get("crate_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing crate_path parameter"))?;
+
+    let files = collect_rust_files(Path::new(crate_path)).await?;
+    let mut macros = Vec::new();
+    for file in &files {
+        let Ok(content) = tokio::fs::read_to_string(file).await else {
+            continue;
+        };
+        let definitions = scan_macro_definitions(&content);
+        if definitions.is_empty() {
+            continue;
+        }
+
+        // Symbols only refine the extent of each definition; the scan finds them.
+        let file_path = file.to_string_lossy();
+        let symbols = flat_document_symbols(analyzer, &file_path).await;
+
+        for definition in definitions {
+            let item = definition.function.as_deref().unwrap_or(&definition.name);
+            let end_line = symbols
+                .iter()
+                .find(|(name, _, selection)| {
+                    name == item && selection.start.line as usize == definition.name_line
+                })
+                .map(|(_, range, _)| range.end.line as usize)
+                .or_else(|| gated_item_end_line(&content, definition.body_start))
+                .unwrap_or(definition.line);
+
+            macros.push(json!({
+                "name": definition.name,
+                "kind": definition.kind.as_str(),
+                "file_path": file_path,
+                "line": definition.line + 1,
+                "end_line": end_line + 1,
+                "exported": definition.exported,
+                "function": definition.function,
+                "helper_attributes": definition.helper_attributes
+            }));
+        }
+    }
+
+    let count = |kind: MacroKind| macros.iter().filter(|m| m["kind"] == kind.as_str()).count();
+    let result = json!({
+        "crate_path": crate_path,
+        "files_scanned": files.len(),
+        "counts": {
+            "macro_rules": count(MacroKind::Declarative),
+            "proc_macro": count(MacroKind::FunctionLike),
+            "proc_macro_derive": count(MacroKind::Derive),
+            "proc_macro_attribute": count(MacroKind::Attribute)
+        },
+        "macros": macros
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn expand_derive_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let trait_name = args
+        .get("trait_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing trait_name parameter"))?;
+
+    let content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let block_start = content.find(code_block).ok_or_else(|| {
+        anyhow::anyhow!("Code block not found in file. Ensure the code block is an exact match.")
+    })?;
+
+    let derives: Vec<(String, usize)> = derive_paths(code_block)
+        .into_iter()
+        .filter(|(_, offset)| is_valid_code_context(&content, block_start + offset))
+        .collect();
+    if derives.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No #[derive(..)] attribute found in the code block"
+        ));
+    }
+    let wanted = last_path_segment(trait_name);
+    let offset = derives
+        .iter()
+        .find(|(path, _)| path == trait_name || last_path_segment(path) == wanted)
+        .map(|(_, offset)| block_start + offset)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "'{}' is not derived here; the derive list has: {}",
+                trait_name,
+                derives
+                    .iter()
+                    .map(|(path, _)| path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+    let (line, character) = index_to_line_col(&content, offset);
+    let (macro_name, expansion) = analyzer
+        .expand_macro(file_path, line, character)
+        .await?
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "rust-analyzer could not expand `{}`; derives from other crates only expand when proc-macro expansion is enabled",
+                trait_name
+            )
+        })?;
+
+    // Older servers expand every derive of the attribute; keep the wanted impls.
+    let impls = trait_impls(&expansion, wanted);
+    let (expansion, filtered) = if impls.is_empty() {
+        (expansion, false)
+    } else {
+        (impls.join("\n\n"), true)
+    };
+
+    let result = json!({
+        "trait": trait_name,
+        "derives": derives.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+        "macro": macro_name,
+        "line": line + 1,
+        "filtered": filtered,
+        "expansion": expansion
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The paths listed by the `derive(..)` attributes in `text` (including
+/// those inside `cfg_attr`), each with the byte offset of its last segment.
+fn derive_paths(text: &str) -> Vec<(String, usize)> {
+    let mut paths = Vec::new();
+    for (idx, _) in text.match_indices("derive(") {
+        // Skips `proc_macro_derive(`.
+        if text[..idx].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let list_start = idx + "derive(".len();
+        let Some(list_len) = text[list_start..].find(')') else {
+            continue;
+        };
+        let mut offset = list_start;
+        for part in text[list_start..list_start + list_len].split(',') {
+            let path = part.trim();
+            if !path.is_empty() {
+                let path_start = offset + part.find(path).unwrap_or(0);
+                let segment = last_path_segment(path);
+                paths.push((path.to_string(), path_start + path.len() - segment.len()));
+            }
+            offset += part.len() + 1;
+        }
+    }
+    paths
+}
+
+/// The top-level `impl .. Trait<..> for ..` blocks of `expansion` whose trait
+/// path ends in `trait_name`.
+fn trait_impls(expansion: &str, trait_name: &str) -> Vec<String> {
+    let mut impls = Vec::new();
+    let mut search_from = 0;
+    while let Some(found) = expansion[search_from..].find("impl") {
+        let start = search_from + found;
+        search_from = start + "impl".len();
+        let is_keyword = !expansion[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            && expansion[search_from..].starts_with(|c: char| c.is_whitespace() || c == '<');
+        let Some(open) = expansion[start..].find('{').map(|open| start + open) else {
+            break;
+        };
+        if !is_keyword {
+            continue;
+        }
+        let Some(close) = find_matching_brace(expansion, open) else {
+            break;
+        };
+        search_from = close + 1;
+
+        let header = &expansion[start + "impl".len()..open];
+        let Some((trait_part, _)) = header.split_once(" for ") else {
+            continue;
+        };
+        let mut trait_part = trait_part.trim();
+        if trait_part.starts_with('<') {
+            let mut depth = 0;
+            let end = trait_part.char_indices().find_map(|(idx, c)| {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(idx)
+            });
+            trait_part = end.map_or("", |end| trait_part[end + 1..].trim());
+        }
+        let trait_path = trait_part.split('<').next().unwrap_or(trait_part);
+        if last_path_segment(trait_path) == trait_name {
+            impls.push(expansion[start..=close].to_string());
+        }
+    }
+    impls
+}
+
+pub async fn goto_macro_definition_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let max_lines = args
+        .get("max_lines")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_SOURCE_MAX_LINES, |v| v as usize);
+    let name = symbol.trim_end_matches('!');
+
+    let content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let (line, character) = find_symbol_location(&content, name, code_block, occurrence)?;
+
+    let location = analyzer
+        .definition_locations(file_path, line, character)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "rust-analyzer could not resolve `{}`; proc macros only resolve when proc-macro expansion is enabled",
+                name
+            )
+        })?;
+    let definition_path = location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&location.uri)
+        .to_string();
+    let definition_content = tokio::fs::read_to_string(&definition_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", definition_path, e))?;
+    let definition = scan_macro_definitions(&definition_content)
+        .into_iter()
+        .find(|definition| definition.name_line == location.range.start.line as usize);
+
+    // Document symbols give the exact extent; the scan covers macros without one.
+    let (source, start_line, end_line) =
+        match analyzer.get_symbol_source(file_path, line, character).await {
+            Ok((source, range, _)) => (source, range.start.line as usize, range.end.line as usize),
+            Err(e) => {
+                let definition = definition.as_ref().ok_or(e)?;
+                let end_line = gated_item_end_line(&definition_content, definition.body_start)
+                    .unwrap_or(definition.name_line);
+                let source = definition_content
+                    .lines()
+                    .skip(definition.line)
+                    .take(end_line + 1 - definition.line)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (source, definition.line, end_line)
+            }
+        };
+    let total_lines = source.lines().count();
+    let (source, omitted_lines) = truncate_middle(&source, max_lines);
+
+    let mut result = json!({
+        "macro": name,
+        "kind": definition.as_ref().map(|definition| definition.kind.as_str()),
+        "file_path": definition_path,
+        "line": start_line + 1,
+        "end_line": end_line + 1,
+        "source": source
+    });
+    match &definition {
+        Some(definition) if definition.kind == MacroKind::Declarative => {
+            result["exported"] = json!(definition.exported);
+        }
+        Some(definition) => {
+            result["function"] = json!(definition.function);
+            result["helper_attributes"] = json!(definition.helper_attributes);
+            result["note"] = json!(
+                "A proc macro's source is the function generating its output, not the code it produces"
+            );
+        }
+        None => {
+            result["note"] = json!(
+                "The definition is not a macro_rules! or proc-macro function; it may be a built-in or re-exported item"
+            );
+        }
+    }
+    if omitted_lines > 0 {
+        result["truncated"] = json!(true);
+        result["total_lines"] = json!(total_lines);
+        result["omitted_lines"] = json!(omitted_lines);
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// `.rs` files under `root`, sorted, skipping `target` and hidden directories.
+pub(crate) async fn collect_rust_files(root: &Path) -> Result<Vec<PathBuf>> {
+    if root.is_file() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+
+    let mut stack = vec![root.to_path_buf()];
+    let mut files = Vec::new();
+    while let Some(dir) = stack.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if dir == root => anyhow::bail!("Failed to read {}: {}", dir.display(), e),
+            Err(_) => continue,
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if entry.file_type().await?.is_dir() {
+                if name != "target" && !name.starts_with('.') {
+                    stack.push(path);
+                }
+            } else if name.ends_with(".rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MacroKind {
+    Declarative,
+    FunctionLike,
+    Derive,
+    Attribute,
+}
+
+impl MacroKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MacroKind::Declarative => "macro_rules",
+            MacroKind::FunctionLike => "proc_macro",
+            MacroKind::Derive => "proc_macro_derive",
+            MacroKind::Attribute => "proc_macro_attribute",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MacroDefinition {
+    kind: MacroKind,
+    /// The name the macro is invoked by (the derive name for derives).
+    name: String,
+    /// 0-based line of `macro_rules!` or of the proc-macro attribute.
+    line: usize,
+    /// 0-based line of the macro or function name.
+    name_line: usize,
+    /// Byte offset just past the name, where the body search starts.
+    body_start: usize,
+    /// `#[macro_export]` for `macro_rules!`; proc macros are always exported.
+    exported: bool,
+    /// The implementing function of a proc macro.
+    function: Option<String>,
+    /// `attributes(..)` declared by a derive.
+    helper_attributes: Vec<String>,
+}
+
+/// Finds `macro_rules!` definitions and `#[proc_macro]`,
+/// `#[proc_macro_derive(..)]` and `#[proc_macro_attribute]` functions in code,
+/// skipping comments and string literals.
+fn scan_macro_definitions(content: &str) -> Vec<MacroDefinition> {
+    let line_of = |idx: usize| content[..idx].matches('\n').count();
+    let mut definitions = Vec::new();
+
+    for (idx, _) in content.match_indices("macro_rules!") {
+        if content[..idx]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+            || !is_valid_code_context(content, idx)
+        {
+            continue;
+        }
+        let Some((name, name_end)) = next_identifier(content, idx + "macro_rules!".len()) else {
+            continue;
+        };
+        definitions.push(MacroDefinition {
+            kind: MacroKind::Declarative,
+            name_line: line_of(name_end),
+            line: line_of(idx),
+            body_start: name_end,
+            exported: preceding_attributes(content, idx).contains("#[macro_export"),
+            name,
+            function: None,
+            helper_attributes: Vec::new(),
+        });
+    }
+
+    const ATTRIBUTES: [(&str, MacroKind); 3] = [
+        ("#[proc_macro]", MacroKind::FunctionLike),
+        ("#[proc_macro_derive(", MacroKind::Derive),
+        ("#[proc_macro_attribute]", MacroKind::Attribute),
+    ];
+    for (pattern, kind) in ATTRIBUTES {
+        for (idx, _) in content.match_indices(pattern) {
+            if !is_valid_code_context(content, idx) {
+                continue;
+            }
+
+            let mut attr_end = idx + pattern.len();
+            let mut derive_name = None;
+            let mut helper_attributes = Vec::new();
+            if kind == MacroKind::Derive {
+                let open = attr_end - 1;
+                let Some(close) = find_matching_paren(&content[open..]).map(|close| open + close)
+                else {
+                    continue;
+                };
+                let mut parts = split_top_level(&content[open + 1..close]).into_iter();
+                derive_name = parts.next().map(|name| name.trim().to_string());
+                if let Some(helpers) =
+                    parts.find_map(|part| strip_call(part.trim(), "attributes").map(str::to_string))
+                {
+                    helper_attributes = split_top_level(&helpers)
+                        .into_iter()
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                }
+                attr_end = close + 1;
+            }
+
+            let Some((function, name_end)) = next_function_name(content, attr_end) else {
+                continue;
+            };
+            definitions.push(MacroDefinition {
+                kind,
+                name: derive_name.unwrap_or_else(|| function.clone()),
+                line: line_of(idx),
+                name_line: line_of(name_end),
+                body_start: name_end,
+                exported: true,
+                function: Some(function),
+                helper_attributes,
+            });
+        }
+    }
+
+    definitions.sort_by_key(|definition| definition.line);
+    definitions
+}
+
+/// The identifier starting at the first non-whitespace character from `from`,
+/// with the byte offset just past it (raw identifiers keep their `r#`).
+fn next_identifier(content: &str, from: usize) -> Option<(String, usize)> {
+    let rest = &content[from..];
+    let start = from + (rest.len() - rest.trim_start().len());
+    let len = content[start..]
+        .char_indices()
+        .find(|&(i, c)| !(c.is_alphanumeric() || c == '_' || (c == '#' && i == 1)))
+        .map_or(content.len() - start, |(i, _)| i);
+    (len > 0).then(|| (content[start..start + len].to_string(), start + len))
+}
+
+/// The name of the first `fn` after `from`, past any further attributes and
+/// qualifiers such as `pub`.
+fn next_function_name(content: &str, from: usize) -> Option<(String, usize)> {
+    let body = content[from..]
+        .find('{')
+        .map_or(content.len(), |i| from + i);
+    content[from..body]
+        .match_indices("fn")
+        .map(|(i, _)| from + i)
+        .find(|&i| {
+            !content[..i]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+                && content[i + 2..].starts_with(char::is_whitespace)
+        })
+        .and_then(|i| next_identifier(content, i + 2))
+}
+
+/// The attribute and doc-comment lines directly above the line holding `idx`.
+fn preceding_attributes(content: &str, idx: usize) -> String {
+    let line_start = content[..idx].rfind('\n').map_or(0, |i| i + 1);
+    let mut lines: Vec<&str> = content[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("#[") || line.starts_with("///"))
+        .collect();
+    lines.reverse();
+    // Attributes on the same line, e.g. `#[macro_export] macro_rules! foo`.
+    lines.push(&content[line_start..idx]);
+    lines.join("\n")
+}
+
+/// The 0-based line range `document_symbols` is narrowed to. A single bound
+/// narrows the outline to the symbols covering that line.
+fn document_line_range(
+    start_line: Option<u64>,
+    end_line: Option<u64>,
+) -> Result<Option<(u32, u32)>> {
+    let (Some(start), Some(end)) = (start_line.or(end_line), end_line.or(start_line)) else {
+        return Ok(None);
+    };
+    let line = |value: u64, name: &str| {
+        u32::try_from(value).map_err(|_| anyhow::anyhow!("{} ({}) is out of range", name, value))
+    };
+    let (start, end) = (line(start, "start_line")?, line(end, "end_line")?);
+    if start > end {
+        return Err(anyhow::anyhow!(
+            "start_line ({}) must not be greater than end_line ({})",
+            start,
+            end
+        ));
+    }
+    Ok(Some((start, end)))
+}
+
+pub async fn document_symbols_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let line_range = document_line_range(
+        args.get("start_line").and_then(|v| v.as_u64()),
+        args.get("end_line").and_then(|v| v.as_u64()),
+    )?;
+
+    let result = analyzer.get_document_symbols(file_path, line_range).await?;
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": result
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Longest token text returned in full; string literals and block comments
+/// can span pages.
+const MAX_TOKEN_TEXT: usize = 200;
+
+pub async fn token_at_impl(args: Value, _analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let line = args
+        .get("line")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| anyhow::anyhow!("Missing line parameter"))? as usize;
+    let character =
+        args.get("character")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow::anyhow!("Missing character parameter"))? as usize;
+
+    let content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let line_text = content.lines().nth(line).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Line {} is past the end of the file ({} lines)",
+            line,
+            content.lines().count()
+        )
+    })?;
+    let width = line_text.encode_utf16().count();
+    if character > width {
+        return Err(anyhow::anyhow!(
+            "Character {} is past the end of line {} ({} UTF-16 code units)",
+            character,
+            line,
+            width
+        ));
+    }
+    let offset = line_start_offset(&content, line).unwrap_or_default()
+        + utf16_column_offset(line_text, character as u32);
+
+    let tokens = tokenize(&content);
+    let at = tokens
+        .iter()
+        .position(|token| token.start <= offset && offset < token.end);
+    let significant = |token: &&Token| token.kind != "whitespace";
+    let previous = tokens[..at.unwrap_or(tokens.len())]
+        .iter()
+        .rev()
+        .find(significant);
+    let next = at
+        .map_or(&tokens[tokens.len()..], |idx| &tokens[idx + 1..])
+        .iter()
+        .find(significant);
+    let describe = |token: &Token| {
+        let text = &content[token.start..token.end];
+        let mut value = json!({
+            "text": text,
+            "kind": token.kind,
+            "range": token_range(&content, token)
+        });
+        if text.len() > MAX_TOKEN_TEXT {
+            let mut cut = MAX_TOKEN_TEXT;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            value["text"] = json!(format!("{}...", &text[..cut]));
+            value["truncated"] = json!(true);
+        }
+        value
+    };
+
+    let result = json!({
+        "file_path": file_path,
+        "line": line,
+        "character": character,
+        "line_text": line_text,
+        "token": at.map(|idx| describe(&tokens[idx])),
+        "previous": previous.map(describe),
+        "next": next.map(describe)
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+fn token_range(content: &str, token: &Token) -> Value {
+    let (start_line, start_character) = index_to_line_col(content, token.start);
+    let (end_line, end_character) = index_to_line_col(content, token.end);
+    json!({
+        "start": {"line": start_line, "character": start_character},
+        "end": {"line": end_line, "character": end_character}
+    })
+}
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe", "use",
+    "where", "while",
+];
+
+/// Multi-character operators and punctuation, longest first.
+const COMPOUND_OPERATORS: &[&str] = &[
+    "<<=", ">>=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..",
+];
+
+/// A lexical token of Rust source: `start..end` byte offsets and its kind.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Token {
+    pub kind: &'static str,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Splits `content` into tokens, whitespace and comments included, so every
+/// byte belongs to exactly one. Unterminated literals and comments run to
+/// the end of the file.
+pub(crate) fn tokenize(content: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(c) = content[pos..].chars().next() {
+        let rest = &content[pos..];
+        let (kind, len) = if c.is_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            ("whitespace", len)
+        } else if rest.starts_with("//") {
+            let doc =
+                (rest.starts_with("///") && !rest.starts_with("////")) || rest.starts_with("//!");
+            let kind = if doc { "doc_comment" } else { "comment" };
+            (kind, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            let doc =
+                (rest.starts_with("/**") && !rest.starts_with("/***") && !rest.starts_with("/**/"))
+                    || rest.starts_with("/*!");
+            let kind = if doc { "doc_comment" } else { "comment" };
+            (kind, block_comment_len(rest))
+        } else if let Some(len) = string_literal_len(rest) {
+            ("string", len)
+        } else if let Some(quoted) = rest.strip_prefix("b'") {
+            ("char", 1 + quote_len(quoted).unwrap_or(quoted.len() + 1))
+        } else if c == '\'' {
+            match quote_len(&rest[1..]) {
+                Some(len) => ("char", len),
+                None => match identifier_len(&rest[1..]) {
+                    0 => ("punctuation", 1),
+                    len => ("lifetime", 1 + len),
+                },
+            }
+        } else if c.is_ascii_digit() {
+            ("number", number_len(rest))
+        } else if let Some(len) = rest
+            .strip_prefix("r#")
+            .map(identifier_len)
+            .filter(|len| *len > 0)
+        {
+            ("identifier", 2 + len)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = identifier_len(rest);
+            let kind = match &rest[..len] {
+                "true" | "false" => "boolean",
+                word if KEYWORDS.contains(&word) => "keyword",
+                _ => "identifier",
+            };
+            (kind, len)
+        } else if let Some(op) = COMPOUND_OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            let kind = match *op {
+                "::" | "->" | "=>" => "punctuation",
+                _ => "operator",
+            };
+            (kind, op.len())
+        } else {
+            let kind = match c {
+                '(' | ')' | '[' | ']' | '{' | '}' | ',' | ';' | ':' | '#' | '$' => "punctuation",
+                '+' | '-' | '*' | '/' | '%' | '^' | '!' | '&' | '|' | '=' | '<' | '>' | '@'
+                | '.' | '?' | '~' => "operator",
+                _ => "unknown",
+            };
+            (kind, c.len_utf8())
+        };
+        tokens.push(Token {
+            kind,
+            start: pos,
+            end: pos + len,
+        });
+        pos += len;
+    }
+    tokens
+}
+
+/// Length of a block comment at the start of `rest`, nested ones included.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut pos = 0;
+    while let Some(c) = rest[pos..].chars().next() {
+        if rest[pos..].starts_with("/*") {
+            depth += 1;
+            pos += 2;
+        } else if rest[pos..].starts_with("*/") {
+            depth -= 1;
+            pos += 2;
+            if depth == 0 {
+                return pos;
+            }
+        } else {
+            pos += c.len_utf8();
+        }
+    }
+    rest.len()
+}
+
+/// Length of a char literal from its opening quote, given the text after
+/// that quote; `None` if the quote opens a lifetime or label instead.
+fn quote_len(after_quote: &str) -> Option<usize> {
+    let mut chars = after_quote.char_indices();
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        let close = after_quote[1..].find('\'')?;
+        // `'\''` escapes the quote itself.
+        let close = if close == 0 {
+            1 + after_quote[2..].find('\'')?
+        } else {
+            close
+        };
+        return Some(close + 3);
+    }
+    match chars.next() {
+        Some((idx, '\'')) => Some(idx + 2),
+        _ => None,
+    }
+}
+
+fn identifier_len(text: &str) -> usize {
+    match text.chars().next() {
+        Some(c) if c.is_alphabetic() || c == '_' => text
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(text.len()),
+        _ => 0,
+    }
+}
+
+/// Length of a numeric literal, including a radix prefix, fraction,
+/// exponent and type suffix (`0xff_u8`, `1.5e-3f64`).
+fn number_len(rest: &str) -> usize {
+    let bytes = rest.as_bytes();
+    let radix = rest.starts_with("0x") || rest.starts_with("0o") || rest.starts_with("0b");
+    let mut len = 0;
+    while let Some(&b) = bytes.get(len) {
+        let plain = rest[..len]
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'_' || b == b'.');
+        if !radix
+            && plain
+            && matches!(b, b'e' | b'E')
+            && matches!(bytes.get(len + 1), Some(b'+' | b'-'))
+        {
+            len += 2;
+        } else if b.is_ascii_alphanumeric()
+            || b == b'_'
+            || (b == b'.'
+                && !radix
+                && !rest[..len].contains('.')
+                && bytes.get(len + 1).is_some_and(u8::is_ascii_digit))
+        {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+pub async fn goto_parent_module_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let path = Path::new(file_path);
+
+    let result = if is_crate_root(path) {
+        json!({
+            "file_path": file_path,
+            "status": "crate_root",
+            "message": "The file is a crate root and has no parent module"
+        })
+    } else {
+        let (module, candidates) = parent_module_candidates(path)
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not a Rust source file path", file_path))?;
+
+        let mut checked = Vec::new();
+        let mut found = None;
+        for candidate in candidates {
+            let Ok(content) = tokio::fs::read_to_string(&candidate).await else {
+                continue;
+            };
+            let declaration = find_mod_declaration(&content, &module);
+            checked.push(candidate.display().to_string());
+            if let Some(declaration) = declaration {
+                found = Some((candidate, declaration));
+                break;
+            }
+        }
+
+        match found {
+            Some((parent, (line, declaration))) => {
+                let layout = match parent.file_name().and_then(|name| name.to_str()) {
+                    Some("mod.rs") => "mod.rs",
+                    Some("lib.rs" | "main.rs") => "crate_root",
+                    _ => "2018",
+                };
+                json!({
+                    "file_path": file_path,
+                    "module": module,
+                    "status": "found",
+                    "parent_file": parent.display().to_string(),
+                    "line": line + 1,
+                    "declaration": declaration,
+                    "layout": layout
+                })
+            }
+            None => json!({
+                "file_path": file_path,
+                "module": module,
+                "status": "not_found",
+                "checked": checked,
+                "message": format!(
+                    "No `mod {};` declaration found; the module may be declared with #[path] or not be part of the crate",
+                    module
+                )
+            }),
+        }
+    };
 
     Ok(ToolResult {
         content: vec![
             json!({
                 "type": "text",
-                "text": result
+                "text": serde_json::to_string_pretty(&result)?
             })
             .as_object()
             .unwrap()
@@ -28,7 +2184,400 @@ pub async fn workspace_symbols_impl(
     })
 }
 
-pub async fn document_symbols_impl(
+pub async fn is_file_in_module_tree_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let path = Path::new(file_path);
+    if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+        return Err(anyhow::anyhow!(
+            "'{}' is not a Rust source file path",
+            file_path
+        ));
+    }
+
+    let walk = walk_to_crate_root(path).await;
+    let crate_root = walk.root.as_ref().map(|root| root.display().to_string());
+    let missing: Vec<Value> = walk
+        .missing
+        .iter()
+        .map(|missing| {
+            json!({
+                "module": missing.module,
+                "parent_file": missing.parent_file.display().to_string(),
+                "parent_exists": missing.parent_exists,
+                "declaration": format!("mod {};", missing.module)
+            })
+        })
+        .collect();
+
+    let result = if walk.root.as_deref() == Some(path) {
+        json!({
+            "file_path": file_path,
+            "reachable": true,
+            "status": "crate_root",
+            "message": "The file is a crate root"
+        })
+    } else if walk.missing.is_empty() && walk.root.is_some() {
+        json!({
+            "file_path": file_path,
+            "reachable": true,
+            "status": "reachable",
+            "crate_root": crate_root,
+            "chain": walk.links
+        })
+    } else {
+        let fixes: Vec<String> = walk
+            .missing
+            .iter()
+            .map(|missing| {
+                let new_file = if missing.parent_exists {
+                    ""
+                } else {
+                    " (new file)"
+                };
+                format!(
+                    "add `mod {};` to {}{}",
+                    missing.module,
+                    missing.parent_file.display(),
+                    new_file
+                )
+            })
+            .collect();
+        let message = if fixes.is_empty() {
+            "No crate root found above the file; it may belong to a test, example or #[path] module"
+                .to_string()
+        } else {
+            format!(
+                "The file is not compiled, so rust-analyzer does not analyze it: {}",
+                fixes.join(", then ")
+            )
+        };
+        json!({
+            "file_path": file_path,
+            "reachable": false,
+            "status": "orphan",
+            "crate_root": crate_root,
+            "chain": walk.links,
+            "missing": missing,
+            "message": message
+        })
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The `mod` declarations linking a file to its crate root.
+#[derive(Debug, Default)]
+pub(crate) struct ModuleWalk {
+    /// Declarations found, from the file upward.
+    pub(crate) links: Vec<Value>,
+    missing: Vec<MissingModule>,
+    pub(crate) root: Option<PathBuf>,
+}
+
+/// A module nobody declares, and the file that should.
+#[derive(Debug, PartialEq)]
+struct MissingModule {
+    module: String,
+    parent_file: PathBuf,
+    parent_exists: bool,
+}
+
+/// Follows parent modules from `path` up to a crate root. A module without a
+/// declaration is recorded as missing in the file that should declare it (the
+/// first existing candidate, or the 2018-style `dir.rs` to create), and the
+/// walk carries on from there so every missing link is reported.
+pub(crate) async fn walk_to_crate_root(path: &Path) -> ModuleWalk {
+    let mut walk = ModuleWalk::default();
+    let mut current = path.to_path_buf();
+    // Far deeper than any real module tree; stops stray `lib.rs` files from looping.
+    for _ in 0..64 {
+        if is_crate_root(&current) {
+            walk.root = Some(current);
+            break;
+        }
+        let Some((module, candidates)) = parent_module_candidates(&current) else {
+            break;
+        };
+
+        let mut existing = None;
+        let mut declared = None;
+        for candidate in &candidates {
+            let Ok(content) = tokio::fs::read_to_string(candidate).await else {
+                continue;
+            };
+            existing.get_or_insert_with(|| candidate.clone());
+            if let Some(declaration) = find_mod_declaration(&content, &module) {
+                declared = Some((candidate.clone(), declaration));
+                break;
+            }
+        }
+
+        current = match (declared, existing) {
+            (Some((parent, (line, declaration))), _) => {
+                walk.links.push(json!({
+                    "module": module,
+                    "file": current.display().to_string(),
+                    "parent_file": parent.display().to_string(),
+                    "line": line + 1,
+                    "declaration": declaration
+                }));
+                parent
+            }
+            (None, Some(parent)) => {
+                walk.missing.push(MissingModule {
+                    module,
+                    parent_file: parent.clone(),
+                    parent_exists: true,
+                });
+                parent
+            }
+            (None, None) => {
+                let Some(sibling) = candidates.into_iter().find(|candidate| {
+                    !matches!(
+                        candidate.file_stem().and_then(|stem| stem.to_str()),
+                        Some("mod" | "lib" | "main")
+                    )
+                }) else {
+                    break;
+                };
+                walk.missing.push(MissingModule {
+                    module,
+                    parent_file: sibling.clone(),
+                    parent_exists: false,
+                });
+                sibling
+            }
+        };
+    }
+    walk
+}
+
+/// `src/lib.rs`, `src/main.rs` and `src/bin/*.rs` are compiled as crate roots.
+pub(crate) fn is_crate_root(path: &Path) -> bool {
+    let parent_name = path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str());
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    matches!(
+        (parent_name, file_name),
+        (Some("src"), Some("lib.rs" | "main.rs")) | (Some("bin"), Some(_))
+    )
+}
+
+/// The name of the module defined by `path` and the files that may declare it,
+/// in lookup order. `a/b.rs` and `a/b/mod.rs` both define `b`, declared in
+/// `a/mod.rs`, the crate root in `a`, or `a.rs` next to the `a` directory.
+fn parent_module_candidates(path: &Path) -> Option<(String, Vec<PathBuf>)> {
+    if path.extension()? != "rs" {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let dir = path.parent()?;
+    let (module, search_dir) = if stem == "mod" {
+        (dir.file_name()?.to_str()?.to_string(), dir.parent()?)
+    } else {
+        (stem.to_string(), dir)
+    };
+
+    let mut candidates = vec![
+        search_dir.join("mod.rs"),
+        search_dir.join("lib.rs"),
+        search_dir.join("main.rs"),
+    ];
+    if let (Some(parent), Some(dir_name)) = (
+        search_dir.parent(),
+        search_dir.file_name().and_then(|name| name.to_str()),
+    ) {
+        candidates.push(parent.join(format!("{dir_name}.rs")));
+    }
+    candidates.retain(|candidate| candidate != path);
+    Some((module, candidates))
+}
+
+/// Finds an out-of-line `mod <module>;` declaration, returning its 0-based
+/// line and text.
+fn find_mod_declaration(content: &str, module: &str) -> Option<(usize, String)> {
+    content.lines().enumerate().find_map(|(idx, line)| {
+        (declared_module(line)? == module).then(|| (idx, line.trim().to_string()))
+    })
+}
+
+/// The module named by a `mod <name>;` line, past attributes and visibility.
+pub(crate) fn declared_module(line: &str) -> Option<&str> {
+    let mut rest = line.trim_start();
+    while rest.starts_with("#[") {
+        rest = rest[rest.find(']')? + 1..].trim_start();
+    }
+    if let Some(after) = rest.strip_prefix("pub") {
+        rest = match after.strip_prefix('(') {
+            Some(scope) => &scope[scope.find(')')? + 1..],
+            None => after,
+        }
+        .trim_start();
+    }
+    let after_mod = rest.strip_prefix("mod")?;
+    if !after_mod.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let name_start = after_mod.trim_start().trim_start_matches("r#");
+    let name_len = name_start
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(name_start.len());
+    let (name, after_name) = name_start.split_at(name_len);
+    (!name.is_empty() && after_name.trim_start().starts_with(';')).then_some(name)
+}
+
+pub async fn resolve_path_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+
+    let (item_name, candidates) = path_candidates(path, analyzer).await?;
+
+    let best = candidates.iter().map(|(quality, _, _)| *quality).max();
+    let matches: Vec<_> = candidates
+        .iter()
+        .filter(|(quality, _, _)| Some(*quality) == best)
+        .map(|(quality, identity, location)| {
+            json!({
+                "match": quality,
+                "file_path": location.uri.strip_prefix("file://").unwrap_or(&location.uri),
+                "line": location.range.start.line + 1,
+                "character": location.range.start.character + 1,
+                "symbol": identity
+            })
+        })
+        .collect();
+
+    let result = match (best, matches.as_slice()) {
+        (None, _) => json!({
+            "path": path,
+            "status": "not_found",
+            "message": format!("No workspace symbol named '{}' was found", item_name)
+        }),
+        (Some(PathMatch::Name), _) => json!({
+            "path": path,
+            "status": "not_found",
+            "message": format!(
+                "Found symbols named '{}' but none under the requested path",
+                item_name
+            ),
+            "candidates": matches
+        }),
+        (Some(_), [single]) => json!({
+            "path": path,
+            "status": "resolved",
+            "location": single
+        }),
+        (Some(_), _) => json!({
+            "path": path,
+            "status": "ambiguous",
+            "candidates": matches
+        }),
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Workspace symbols matching the last segment of `path`, with how well
+/// each one matches the whole path.
+async fn path_candidates(
+    path: &str,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<(String, Vec<(PathMatch, SymbolIdentity, Location)>)> {
+    let segments = parse_rust_path(path);
+    let item_name = segments
+        .last()
+        .ok_or_else(|| anyhow::anyhow!("Path '{}' does not name an item", path))?
+        .clone();
+
+    // rust-analyzer only searches types by default; a trailing '#' widens the
+    // search to functions and methods as well.
+    let infos = analyzer
+        .workspace_symbol_infos(&format!("{item_name}#"))
+        .await?;
+
+    let mut candidates = Vec::new();
+    for info in &infos {
+        let Some(identity) = symbol_information_to_identity(info) else {
+            continue;
+        };
+        let Some(quality) = match_identity_path(&identity, &segments) else {
+            continue;
+        };
+        let Some(location) = info
+            .get("location")
+            .cloned()
+            .and_then(|location| serde_json::from_value::<Location>(location).ok())
+        else {
+            continue;
+        };
+        candidates.push((quality, identity, location));
+    }
+
+    Ok((item_name, candidates))
+}
+
+/// Resolves `path` to the location of the single item it names, failing when
+/// nothing or more than one item matches.
+pub(crate) async fn resolve_unique_path(
+    path: &str,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<Location> {
+    let (item_name, candidates) = path_candidates(path, analyzer).await?;
+    let best = candidates
+        .iter()
+        .map(|(quality, _, _)| *quality)
+        .max()
+        .filter(|quality| *quality != PathMatch::Name)
+        .ok_or_else(|| anyhow::anyhow!("No item named '{}' matches '{}'", item_name, path))?;
+
+    let mut matches = candidates
+        .into_iter()
+        .filter(|(quality, _, _)| *quality == best)
+        .map(|(_, _, location)| location);
+    match (matches.next(), matches.next()) {
+        (Some(location), None) => Ok(location),
+        _ => Err(anyhow::anyhow!(
+            "'{}' is ambiguous; qualify it further (e.g. `module::{}`)",
+            path,
+            item_name
+        )),
+    }
+}
+
+pub async fn list_reexports_impl(
     args: Value,
     analyzer: &mut RustAnalyzerClient,
 ) -> Result<ToolResult> {
@@ -36,14 +2585,676 @@ pub async fn document_symbols_impl(
         .get("file_path")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let include_restricted = args
+        .get("include_restricted")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let content = std::fs::read_to_string(file_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let mut reexports = Vec::new();
+    let mut large_globs = Vec::new();
+    for reexport in scan_reexports(&content) {
+        if reexport.visibility != "pub" && !include_restricted {
+            continue;
+        }
+        let (line, character) = index_to_line_col(&content, reexport.offset);
+        let mut entry = json!({
+            "line": line + 1,
+            "visibility": reexport.visibility,
+            "name": reexport.name,
+            "source_path": reexport.path,
+            "glob": reexport.glob
+        });
+
+        match resolve_reexport(analyzer, file_path, line, character).await {
+            Ok(Some((details, hops))) => {
+                let target_file = details
+                    .location
+                    .uri
+                    .strip_prefix("file://")
+                    .unwrap_or(&details.location.uri)
+                    .to_string();
+                let identity =
+                    identity_from_definition(&details.location.uri, &details.symbol_path);
+                let target_path = identity.as_ref().map(|identity| {
+                    let mut segments = vec![identity.crate_name.clone()];
+                    segments.extend(identity.module_path.iter().cloned());
+                    segments.push(identity.item_name.clone());
+                    segments.join("::")
+                });
+                entry["target"] = json!({
+                    "file_path": target_file,
+                    "line": details.location.range.start.line + 1,
+                    "path": target_path
+                });
+                entry["hops"] = json!(hops);
+
+                if reexport.glob {
+                    let surface =
+                        glob_surface(analyzer, &target_file, &details.location.range).await?;
+                    entry["surface_items"] = json!(surface);
+                    if surface.is_some_and(|count| count >= LARGE_GLOB_SURFACE) {
+                        entry["large_surface"] = json!(true);
+                        large_globs.push(reexport.path.clone());
+                    }
+                }
+            }
+            Ok(None) => entry["target"] = Value::Null,
+            Err(e) => entry["error"] = json!(e.to_string()),
+        }
+        reexports.push(entry);
+    }
 
-    let result = analyzer.get_document_symbols(file_path).await?;
+    let globs = reexports
+        .iter()
+        .filter(|entry| entry["glob"] == json!(true))
+        .count();
+    let result = json!({
+        "file_path": file_path,
+        "total": reexports.len(),
+        "globs": globs,
+        "large_globs": large_globs,
+        "reexports": reexports,
+        "note": format!(
+            "Each `pub use` leaf is resolved with go-to-definition, following re-exports of re-exports to the defining item; `hops` counts the `use` declarations passed through. Glob re-exports report how many public items they pull in and are flagged as `large_surface` from {LARGE_GLOB_SURFACE} items on. Re-exports produced by macros are not seen."
+        )
+    });
 
     Ok(ToolResult {
         content: vec![
             json!({
                 "type": "text",
-                "text": result
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Glob re-exports from this many public items on are flagged by
+/// `list_reexports`.
+const LARGE_GLOB_SURFACE: usize = 20;
+/// Bounds the definition chain of a re-export, in case of a cycle.
+const MAX_REEXPORT_HOPS: usize = 8;
+
+/// One leaf of a `pub use` tree: `pub use net::{Server, client::Client as C}`
+/// has two.
+#[derive(Debug, PartialEq)]
+struct Reexport {
+    visibility: String,
+    /// The imported path as written, `net::client::Client`; globs end in `*`.
+    path: String,
+    /// The name it is exported as, or `*` for a glob.
+    name: String,
+    glob: bool,
+    /// Byte offset of the last path segment, where go-to-definition resolves
+    /// the leaf.
+    offset: usize,
+}
+
+/// Finds the `pub use` / `pub(crate) use` declarations of a file, including
+/// those in inline modules, and splits their trees into leaves.
+fn scan_reexports(content: &str) -> Vec<Reexport> {
+    let mut reexports = Vec::new();
+    let mut line_start = 0;
+    for line in content.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+
+        let trimmed = line.trim_start();
+        let Some(rest) = trimmed.strip_prefix("pub") else {
+            continue;
+        };
+        let (visibility, rest) = match rest.strip_prefix('(') {
+            Some(scope) => match scope.split_once(')') {
+                Some((scope, rest)) => (format!("pub({})", scope.trim()), rest),
+                None => continue,
+            },
+            None => ("pub".to_string(), rest),
+        };
+        let Some(tree) = rest.trim_start().strip_prefix("use ") else {
+            continue;
+        };
+        let tree_start = start + (line.len() - tree.len());
+        if !is_valid_code_context(content, tree_start) {
+            continue;
+        }
+        let tree_end = content[tree_start..]
+            .find(';')
+            .map_or(content.len(), |end| tree_start + end);
+
+        let tokens = use_tree_tokens(&content[tree_start..tree_end], tree_start);
+        let mut idx = 0;
+        let mut leaves = Vec::new();
+        parse_use_tree(&tokens, &mut idx, &[], &mut leaves);
+        reexports.extend(
+            leaves
+                .into_iter()
+                .map(|(path, name, glob, offset)| Reexport {
+                    visibility: visibility.clone(),
+                    path: path.join("::"),
+                    name,
+                    glob,
+                    offset,
+                }),
+        );
+    }
+    reexports
+}
+
+/// Tokens of a use tree with their byte offsets: identifiers, `::`, and the
+/// single characters `{`, `}`, `,` and `*`.
+fn use_tree_tokens(tree: &str, base: usize) -> Vec<(String, usize)> {
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < tree.len() {
+        let rest = &tree[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+        if rest.starts_with("//") {
+            idx += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            idx += rest.find("*/").map_or(rest.len(), |end| end + 2);
+        } else if rest.starts_with("::") {
+            tokens.push(("::".to_string(), base + idx));
+            idx += 2;
+        } else if matches!(c, '{' | '}' | ',' | '*') {
+            tokens.push((c.to_string(), base + idx));
+            idx += 1;
+        } else if c.is_alphanumeric() || c == '_' || c == '#' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '#'))
+                .unwrap_or(rest.len());
+            tokens.push((rest[..len].to_string(), base + idx));
+            idx += len;
+        } else {
+            idx += c.len_utf8();
+        }
+    }
+    tokens
+}
+
+/// Parses one use tree starting at `tokens[*idx]` under `prefix`, pushing its
+/// leaves as `(path, exported name, glob, offset)`.
+fn parse_use_tree(
+    tokens: &[(String, usize)],
+    idx: &mut usize,
+    prefix: &[String],
+    leaves: &mut Vec<(Vec<String>, String, bool, usize)>,
+) {
+    let mut path = prefix.to_vec();
+    let mut last_offset = None;
+    while let Some((token, offset)) = tokens.get(*idx) {
+        match token.as_str() {
+            "::" => *idx += 1,
+            "{" => {
+                *idx += 1;
+                while let Some((token, _)) = tokens.get(*idx) {
+                    match token.as_str() {
+                        "}" => break,
+                        "," => *idx += 1,
+                        _ => parse_use_tree(tokens, idx, &path, leaves),
+                    }
+                }
+                *idx += 1;
+                return;
+            }
+            "*" => {
+                *idx += 1;
+                path.push("*".to_string());
+                // A glob resolves through the module or enum it names.
+                let offset = last_offset.unwrap_or(*offset);
+                leaves.push((path, "*".to_string(), true, offset));
+                return;
+            }
+            "}" | "," => break,
+            name => {
+                path.push(name.to_string());
+                last_offset = Some(*offset);
+                *idx += 1;
+                if tokens.get(*idx).is_none_or(|(token, _)| token != "::") {
+                    break;
+                }
+            }
+        }
+    }
+
+    let Some(offset) = last_offset else {
+        return;
+    };
+    // `module::{self}` exports the module itself.
+    if path.last().is_some_and(|last| last == "self") {
+        path.pop();
+    }
+    let mut name = path.last().cloned().unwrap_or_default();
+    if tokens.get(*idx).is_some_and(|(token, _)| token == "as") {
+        if let Some((alias, _)) = tokens.get(*idx + 1) {
+            name = alias.clone();
+        }
+        *idx += 2;
+    }
+    leaves.push((path, name, false, offset));
+}
+
+pub async fn find_module_importers_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let module = args
+        .get("module")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing module parameter"))?;
+    let search_root = match args.get("path").and_then(|v| v.as_str()) {
+        Some(path) => PathBuf::from(path),
+        None => analyzer
+            .project_root()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| anyhow::anyhow!("No path given and no project root is known"))?,
+    };
+
+    let start = if search_root.is_dir() {
+        search_root.as_path()
+    } else {
+        search_root.parent().unwrap_or(&search_root)
+    };
+    let manifest_path = start
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+        .ok_or_else(|| anyhow::anyhow!("No Cargo.toml in {} or above", start.display()))?;
+    let package_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let crate_name = library_crate_name(&manifest_path).await?;
+
+    let mut target = parse_rust_path(module);
+    if target.first() == Some(&crate_name) {
+        target.remove(0);
+    }
+    if target.is_empty() {
+        return Err(anyhow::anyhow!(
+            "'{}' names the crate root, not a module",
+            module
+        ));
+    }
+
+    let files = collect_rust_files(&search_root).await?;
+    let mut importers = Vec::new();
+    let mut importing_files = HashSet::new();
+    for file in &files {
+        let Ok(content) = tokio::fs::read_to_string(file).await else {
+            continue;
+        };
+        // Only files of the library's module tree reach it through `crate::`,
+        // `self::` and `super::`; the rest import it by the crate name.
+        let relative = file.strip_prefix(package_dir).unwrap_or(file);
+        let in_tree = relative.starts_with("src") && !relative.starts_with("src/bin");
+        let file_module = in_tree.then(|| file_module_path(relative));
+
+        for site in scan_imports(&content) {
+            let current = file_module.as_ref().map(|file_module| {
+                let mut current = file_module.clone();
+                current.extend(site.inline_modules.iter().cloned());
+                current
+            });
+            // The module's own files, and its tests' `use super::*`, are not importers.
+            if current
+                .as_ref()
+                .is_some_and(|current| current.starts_with(&target))
+            {
+                continue;
+            }
+
+            let imports: Vec<String> = site
+                .paths
+                .iter()
+                .filter_map(|path| crate_relative_path(path, current.as_deref(), &crate_name))
+                .filter(|resolved| resolved.starts_with(&target))
+                .filter(|resolved| !site.declares_module || resolved.len() == target.len())
+                .map(|resolved| match &resolved[target.len()..] {
+                    [] => "self".to_string(),
+                    rest => rest.join("::"),
+                })
+                .collect();
+            if imports.is_empty() {
+                continue;
+            }
+
+            let text = content[site.start..site.end]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let text = match text.char_indices().nth(MAX_IMPORT_TEXT) {
+                Some((cut, _)) => format!("{}...", &text[..cut]),
+                None => text,
+            };
+            importing_files.insert(file.clone());
+            importers.push(json!({
+                "file_path": file.to_string_lossy(),
+                "line": content[..site.start].matches('\n').count() + 1,
+                "kind": if site.declares_module { "mod" } else { "use" },
+                "reexport": site.reexport,
+                "imports": imports,
+                "text": text
+            }));
+        }
+    }
+
+    let result = json!({
+        "module": format!("crate::{}", target.join("::")),
+        "crate": crate_name,
+        "files_scanned": files.len(),
+        "files": importing_files.len(),
+        "total": importers.len(),
+        "importers": importers,
+        "note": format!(
+            "`use` declarations are resolved textually against each file's module path (`crate::`, `self::`, `super::`, paths relative to the current module, and `{crate_name}::` from tests, examples, binaries and other crates). Fully qualified paths written inline without a `use`, imports through re-exports or of a parent module, and `#[path]` modules are not found; `imports` lists what each declaration takes from the module, `self` being the module itself."
+        )
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Longest declaration text `find_module_importers` returns, in characters.
+const MAX_IMPORT_TEXT: usize = 200;
+
+/// A `use` declaration or an out-of-line `mod name;` declaration.
+#[derive(Debug, PartialEq)]
+struct ImportSite {
+    /// Byte range from the `use` / `mod` keyword to the `;`.
+    start: usize,
+    end: usize,
+    /// Inline modules enclosing the declaration, outermost first.
+    inline_modules: Vec<String>,
+    /// Each imported path as written; `mod name;` has the single path `self::name`.
+    paths: Vec<Vec<String>>,
+    declares_module: bool,
+    /// `pub use`, with any restriction.
+    reexport: bool,
+}
+
+/// Finds the `use` and `mod name;` declarations of a file, including those in
+/// inline modules and function bodies, skipping comments and strings.
+fn scan_imports(content: &str) -> Vec<ImportSite> {
+    let tokens: Vec<Token> = tokenize(content)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, "whitespace" | "comment" | "doc_comment"))
+        .collect();
+    let text = |token: &Token| &content[token.start..token.end];
+
+    let mut sites = Vec::new();
+    // Open inline modules as (name, brace depth inside the module).
+    let mut modules: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0usize;
+    for (idx, token) in tokens.iter().enumerate() {
+        match (token.kind, text(token)) {
+            ("punctuation", "{") => depth += 1,
+            ("punctuation", "}") => {
+                depth = depth.saturating_sub(1);
+                if modules.last().is_some_and(|(_, inner)| *inner > depth) {
+                    modules.pop();
+                }
+            }
+            ("keyword", "mod") => {
+                let (Some(name), Some(next)) = (tokens.get(idx + 1), tokens.get(idx + 2)) else {
+                    continue;
+                };
+                let name = text(name).trim_start_matches("r#");
+                match text(next) {
+                    "{" => modules.push((name.to_string(), depth + 1)),
+                    ";" => sites.push(ImportSite {
+                        start: token.start,
+                        end: next.end,
+                        inline_modules: modules.iter().map(|(name, _)| name.clone()).collect(),
+                        paths: vec![vec!["self".to_string(), name.to_string()]],
+                        declares_module: true,
+                        reexport: false,
+                    }),
+                    _ => {}
+                }
+            }
+            ("keyword", "use") => {
+                // `impl Trait + use<'a>` captures lifetimes; it imports nothing.
+                if tokens.get(idx + 1).is_some_and(|next| text(next) == "<") {
+                    continue;
+                }
+                let Some(semicolon) = tokens[idx + 1..].iter().find(|next| text(next) == ";")
+                else {
+                    continue;
+                };
+                let tree = use_tree_tokens(&content[token.end..semicolon.start], token.end);
+                let mut leaves = Vec::new();
+                let mut tree_idx = 0;
+                while tree_idx < tree.len() {
+                    let before = tree_idx;
+                    parse_use_tree(&tree, &mut tree_idx, &[], &mut leaves);
+                    tree_idx = tree_idx.max(before + 1);
+                }
+
+                let line_start = content[..token.start].rfind('\n').map_or(0, |idx| idx + 1);
+                sites.push(ImportSite {
+                    start: token.start,
+                    end: semicolon.end,
+                    inline_modules: modules.iter().map(|(name, _)| name.clone()).collect(),
+                    paths: leaves.into_iter().map(|(path, ..)| path).collect(),
+                    declares_module: false,
+                    reexport: content[line_start..token.start]
+                        .trim_start()
+                        .starts_with("pub"),
+                });
+            }
+            _ => {}
+        }
+    }
+    sites
+}
+
+/// Resolves a `use` path written in module `current` to a path from the root
+/// of the crate named `crate_name`. `current` is `None` for files outside the
+/// crate's module tree, which only reach it by the crate name. Returns `None`
+/// for paths into other crates.
+fn crate_relative_path(
+    path: &[String],
+    current: Option<&[String]>,
+    crate_name: &str,
+) -> Option<Vec<String>> {
+    let (first, rest) = path.split_first()?;
+    match first.as_str() {
+        "crate" => current.map(|_| rest.to_vec()),
+        name if name == crate_name => Some(rest.to_vec()),
+        "std" | "core" | "alloc" => None,
+        "self" | "super" => {
+            let mut resolved = current?.to_vec();
+            let mut segments = if first == "self" { rest } else { path };
+            while let Some(("super", after)) = segments
+                .split_first()
+                .map(|(segment, after)| (segment.as_str(), after))
+            {
+                resolved.pop()?;
+                segments = after;
+            }
+            resolved.extend(segments.iter().cloned());
+            Some(resolved)
+        }
+        _ => current.map(|current| [current, path].concat()),
+    }
+}
+
+pub async fn file_interface_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+
+    let content = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let manifest_path = owning_manifest(Path::new(file_path))?;
+    let package_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let crate_name = library_crate_name(&manifest_path).await?;
+    let dependencies = tokio::fs::read_to_string(&manifest_path)
+        .await
+        .ok()
+        .and_then(|text| toml::from_str::<toml::Table>(&text).ok())
+        .map(|manifest| declared_dependencies(&manifest))
+        .unwrap_or_default();
+    let relative = Path::new(file_path)
+        .strip_prefix(package_dir)
+        .unwrap_or(Path::new(file_path));
+    // Only the library's module tree is reachable by the crate name; tests,
+    // examples and binaries are crates of their own.
+    let in_tree = relative.starts_with("src") && !relative.starts_with("src/bin");
+    let root = if in_tree {
+        crate_name.clone()
+    } else {
+        "crate".to_string()
+    };
+    let module = file_module_path(relative);
+    let qualify = |path: &[String]| {
+        let mut qualified = vec![root.clone()];
+        qualified.extend(path.iter().cloned());
+        qualified.join("::")
+    };
+
+    let DocumentSymbolResponse::DocumentSymbols(symbols) =
+        analyzer.document_symbols(file_path).await?
+    else {
+        return Err(anyhow::anyhow!(
+            "rust-analyzer returned flat symbols for {}; the file's items cannot be scoped",
+            file_path
+        ));
+    };
+    let mut exports = Vec::new();
+    let mut items = Vec::new();
+    collect_exported_items(&symbols, &module, &content, &mut items);
+    for (path, symbol, visibility) in items {
+        let source = text_in_range(&content, &symbol.range);
+        exports.push(json!({
+            "path": qualify(&path),
+            "kind": gated_item_kind(&source),
+            "visibility": visibility,
+            "line": symbol.selection_range.start.line + 1
+        }));
+    }
+
+    let sites = scan_imports(&content);
+    // Names in scope at the top of the file, which `use` paths may start from.
+    let local_names: HashSet<&str> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    let externs: Vec<String> = ["std", "core", "alloc"]
+        .into_iter()
+        .map(str::to_string)
+        .chain(dependencies.into_iter().map(|dependency| dependency.ident))
+        .chain((!in_tree).then(|| crate_name.clone()))
+        .collect();
+
+    let mut uses = Vec::new();
+    let mut seen = HashSet::new();
+    // What each imported name stands for, to resolve paths written through it.
+    let mut imported: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for site in sites.iter().filter(|site| !site.declares_module) {
+        let mut current = module.clone();
+        current.extend(site.inline_modules.iter().cloned());
+        for (path, name, offset) in use_leaves(&content, site) {
+            let resolved = resolve_use_path(&path, &current, &local_names, &externs);
+            let (line, character) = index_to_line_col(&content, offset);
+            let text = match &resolved {
+                Some(resolved) => qualify(resolved),
+                None => path.join("::"),
+            };
+            if site.reexport {
+                exports.push(json!({
+                    "path": qualify(&[current.clone(), vec![name.clone()]].concat()),
+                    "kind": "reexport",
+                    "visibility": declared_visibility(&content[site.start..site.end]).to_string(),
+                    "line": line + 1,
+                    "source": text
+                }));
+            }
+            if name != "*" && name != "_" {
+                imported.insert(name, text.split("::").map(str::to_string).collect());
+            }
+            if !seen.insert(text.clone()) {
+                continue;
+            }
+            let definition = match analyzer
+                .definition_details(file_path, line, character)
+                .await
+            {
+                Ok(Some(details)) => json!({
+                    "file_path": details
+                        .location
+                        .uri
+                        .strip_prefix("file://")
+                        .unwrap_or(&details.location.uri),
+                    "line": details.location.range.start.line + 1
+                }),
+                _ => Value::Null,
+            };
+            uses.push(json!({
+                "path": text,
+                "via": "use",
+                "line": line + 1,
+                "definition": definition
+            }));
+        }
+    }
+
+    let mut by_path = 0;
+    for (path, offset) in scan_path_references(&content, &sites) {
+        let resolved = match path[0].as_str() {
+            "crate" | "self" | "super" => {
+                resolve_use_path(&path, &module, &local_names, &externs).map(|path| qualify(&path))
+            }
+            first if externs.iter().any(|name| name == first) => Some(path.join("::")),
+            first => imported
+                .get(first)
+                .map(|prefix| [prefix.as_slice(), &path[1..]].concat().join("::")),
+        };
+        let Some(resolved) = resolved else {
+            continue;
+        };
+        if seen.insert(resolved.clone()) {
+            by_path += 1;
+            uses.push(json!({
+                "path": resolved,
+                "via": "path",
+                "line": content[..offset].matches('\n').count() + 1
+            }));
+        }
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "module": qualify(&module),
+        "exports": exports,
+        "uses": uses,
+        "summary": {
+            "exports": exports.len(),
+            "imports": uses.len() - by_path,
+            "path_references": by_path
+        },
+        "note": "Exports are the file's `pub` and `pub(..)` items outside private inline modules, the public methods of its inherent impls, `#[macro_export]` macros and `pub use` re-exports. Uses are the `use` leaves (with their go-to-definition target) and qualified paths written inline, resolved through the file's imports; paths through the prelude or the file's own items are not listed."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
             })
             .as_object()
             .unwrap()
@@ -51,3 +3262,860 @@ pub async fn document_symbols_impl(
         ],
     })
 }
+
+/// The items of `symbols` visible outside the file, with their path under
+/// `module` and visibility: `pub` and `pub(..)` items, descending into the
+/// modules that are themselves visible and into inherent impls.
+fn collect_exported_items<'a>(
+    symbols: &'a [DocumentSymbol],
+    module: &[String],
+    content: &str,
+    found: &mut Vec<(Vec<String>, &'a DocumentSymbol, String)>,
+) {
+    for symbol in symbols {
+        let source = text_in_range(content, &symbol.range);
+        let children = symbol.children.as_deref().unwrap_or_default();
+        // LSP `SymbolKind::Object` is how rust-analyzer reports impl blocks.
+        if symbol.kind == 19 {
+            if let Some(self_type) = inherent_impl_type(&symbol.name) {
+                let mut path = module.to_vec();
+                path.push(self_type);
+                collect_exported_items(children, &path, content, found);
+            }
+            continue;
+        }
+
+        let mut path = module.to_vec();
+        path.push(symbol.name.clone());
+        let visibility = match declared_visibility(&source) {
+            ItemVisibility::Private
+                if gated_item_kind(&source) == "macro" && source.contains("#[macro_export]") =>
+            {
+                // Exported at the crate root, whatever module defines it.
+                found.push((
+                    vec![symbol.name.clone()],
+                    symbol,
+                    "macro_export".to_string(),
+                ));
+                continue;
+            }
+            ItemVisibility::Private => continue,
+            visibility => visibility.to_string(),
+        };
+        found.push((path.clone(), symbol, visibility));
+        // LSP `SymbolKind::Module`
+        if symbol.kind == 2 {
+            collect_exported_items(children, &path, content, found);
+        }
+    }
+}
+
+/// The self type of an inherent impl's document symbol name, `Foo` for
+/// `impl<T> Foo<T>`; `None` for trait impls.
+fn inherent_impl_type(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("impl")?.trim_start();
+    let (_, rest) = split_generic_params(rest)?;
+    if rest.contains(" for ") {
+        return None;
+    }
+    let self_type = rest.trim().split('<').next()?.trim();
+    let self_type = self_type.rsplit("::").next()?;
+    (!self_type.is_empty()).then(|| self_type.to_string())
+}
+
+/// The leaves of a `use` declaration as `(path, name, offset)`: the path as
+/// written, the name it binds (`*` for globs) and the offset of its last
+/// segment.
+fn use_leaves(content: &str, site: &ImportSite) -> Vec<(Vec<String>, String, usize)> {
+    let Some(tree_start) = content[site.start..site.end]
+        .find("use")
+        .map(|idx| site.start + idx + "use".len())
+    else {
+        return Vec::new();
+    };
+    let tree = use_tree_tokens(&content[tree_start..site.end - 1], tree_start);
+    let mut leaves = Vec::new();
+    let mut idx = 0;
+    while idx < tree.len() {
+        let before = idx;
+        parse_use_tree(&tree, &mut idx, &[], &mut leaves);
+        idx = idx.max(before + 1);
+    }
+    leaves
+        .into_iter()
+        .map(|(path, name, _, offset)| (path, name, offset))
+        .collect()
+}
+
+/// Resolves a `use` path written in module `current` to a path from the
+/// crate root, or `None` when it leads into another crate. A leading name
+/// that is neither an item of the file (`local_names`) nor a known extern
+/// crate is taken as relative to `current`.
+fn resolve_use_path(
+    path: &[String],
+    current: &[String],
+    local_names: &HashSet<&str>,
+    externs: &[String],
+) -> Option<Vec<String>> {
+    let first = path.first()?;
+    match first.as_str() {
+        "crate" | "self" | "super" => crate_relative_path(path, Some(current), ""),
+        name if externs.iter().any(|extern_name| extern_name == name) => None,
+        name if local_names.contains(name) => crate_relative_path(path, Some(current), ""),
+        _ => None,
+    }
+}
+
+/// Qualified paths (`a::b`, at least two segments) written outside `use` and
+/// `mod` declarations, with the offset of their first segment. Paths through
+/// `Self` and the segments after a turbofish are left out.
+fn scan_path_references(content: &str, sites: &[ImportSite]) -> Vec<(Vec<String>, usize)> {
+    let tokens: Vec<Token> = tokenize(content)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, "whitespace" | "comment" | "doc_comment"))
+        .filter(|token| {
+            !sites
+                .iter()
+                .any(|site| (site.start..site.end).contains(&token.start))
+        })
+        .collect();
+    let text = |idx: usize| {
+        tokens
+            .get(idx)
+            .map(|token| &content[token.start..token.end])
+    };
+    let is_segment = |idx: usize| {
+        tokens
+            .get(idx)
+            .is_some_and(|token| matches!(token.kind, "identifier" | "keyword"))
+            && text(idx) != Some("Self")
+    };
+
+    let mut paths = Vec::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let starts_path = is_segment(idx)
+            && (idx == 0 || text(idx - 1) != Some("::"))
+            && text(idx + 1) == Some("::");
+        if !starts_path {
+            idx += 1;
+            continue;
+        }
+        let start = tokens[idx].start;
+        let mut segments = vec![text(idx).unwrap_or_default().to_string()];
+        idx += 1;
+        while text(idx) == Some("::") && is_segment(idx + 1) {
+            segments.push(text(idx + 1).unwrap_or_default().to_string());
+            idx += 2;
+        }
+        if segments.len() > 1 {
+            paths.push((segments, start));
+        }
+    }
+    paths
+}
+
+/// Follows go-to-definition from a re-exported name until it leaves `use`
+/// declarations behind. Returns the final definition and the number of
+/// intermediate re-exports passed through.
+async fn resolve_reexport(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    line: u32,
+    character: u32,
+) -> Result<Option<(DefinitionDetails, usize)>> {
+    let mut current = (file_path.to_string(), line, character);
+    let mut hops = 0;
+    let mut seen = HashSet::new();
+    loop {
+        let Some(details) = analyzer
+            .definition_details(&current.0, current.1, current.2)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let target = details
+            .location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&details.location.uri)
+            .to_string();
+        let start = details.location.range.start.clone();
+        let at_use = std::fs::read_to_string(&target).ok().is_some_and(|text| {
+            text.lines()
+                .nth(start.line as usize)
+                .is_some_and(|line| is_use_declaration(line.trim_start()))
+        });
+        if !at_use
+            || hops >= MAX_REEXPORT_HOPS
+            || !seen.insert((target.clone(), start.line, start.character))
+        {
+            return Ok(Some((details, hops)));
+        }
+        hops += 1;
+        current = (target, start.line, start.character);
+    }
+}
+
+fn is_use_declaration(line: &str) -> bool {
+    let line = match line.strip_prefix("pub") {
+        Some(rest) if rest.starts_with('(') => rest.split_once(')').map_or(rest, |(_, rest)| rest),
+        Some(rest) => rest,
+        None => line,
+    };
+    line.trim_start().starts_with("use ")
+}
+
+/// How many public items the module or enum at `range` in `file_path` holds,
+/// for sizing a glob re-export of it.
+async fn glob_surface(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    range: &Range,
+) -> Result<Option<usize>> {
+    let DocumentSymbolResponse::DocumentSymbols(symbols) =
+        analyzer.document_symbols(file_path).await?
+    else {
+        return Ok(None);
+    };
+    let Ok(content) = std::fs::read_to_string(file_path) else {
+        return Ok(None);
+    };
+    let lines: Vec<&str> = content.lines().collect();
+
+    // A `mod name;` file resolves to its first line; an inline module or an
+    // enum to its declaration.
+    let declared = named_symbol_at(&symbols, range.start.line);
+    let (items, enum_variants) = match declared {
+        Some(symbol) if matches!(symbol.kind, 2 | 10) => (
+            symbol.children.as_deref().unwrap_or_default(),
+            symbol.kind == 10,
+        ),
+        _ if range.start.line == 0 && range.start.character == 0 => (symbols.as_slice(), false),
+        _ => return Ok(None),
+    };
+    Ok(Some(
+        items
+            .iter()
+            .filter(|item| {
+                enum_variants
+                    || lines
+                        .get(item.selection_range.start.line as usize)
+                        .is_some_and(|line| line.trim_start().starts_with("pub"))
+            })
+            .count(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_line_range() {
+        assert_eq!(document_line_range(None, None).unwrap(), None);
+        assert_eq!(document_line_range(Some(4), None).unwrap(), Some((4, 4)));
+        assert_eq!(document_line_range(None, Some(9)).unwrap(), Some((9, 9)));
+        assert_eq!(document_line_range(Some(2), Some(9)).unwrap(), Some((2, 9)));
+        assert!(document_line_range(Some(9), Some(2)).is_err());
+        let err = document_line_range(Some(1), Some(u64::from(u32::MAX) + 1)).unwrap_err();
+        assert!(err.to_string().contains("end_line"), "{err}");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let content = "fn f<'a>(x: &'a str) -> char { let r#type = r#\"q\"#; x.0; 1..2; 0x1f_u8 <<= 1.5e-3; '\\'' /* a /* b */ */ }";
+        let tokens: Vec<(&str, &str)> = tokenize(content)
+            .iter()
+            .filter(|token| token.kind != "whitespace")
+            .map(|token| (token.kind, &content[token.start..token.end]))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("keyword", "fn"),
+                ("identifier", "f"),
+                ("operator", "<"),
+                ("lifetime", "'a"),
+                ("operator", ">"),
+                ("punctuation", "("),
+                ("identifier", "x"),
+                ("punctuation", ":"),
+                ("operator", "&"),
+                ("lifetime", "'a"),
+                ("identifier", "str"),
+                ("punctuation", ")"),
+                ("punctuation", "->"),
+                ("identifier", "char"),
+                ("punctuation", "{"),
+                ("keyword", "let"),
+                ("identifier", "r#type"),
+                ("operator", "="),
+                ("string", "r#\"q\"#"),
+                ("punctuation", ";"),
+                ("identifier", "x"),
+                ("operator", "."),
+                ("number", "0"),
+                ("punctuation", ";"),
+                ("number", "1"),
+                ("operator", ".."),
+                ("number", "2"),
+                ("punctuation", ";"),
+                ("number", "0x1f_u8"),
+                ("operator", "<<="),
+                ("number", "1.5e-3"),
+                ("punctuation", ";"),
+                ("char", "'\\''"),
+                ("comment", "/* a /* b */ */"),
+                ("punctuation", "}"),
+            ]
+        );
+    }
+    #[test]
+    fn test_scan_reexports() {
+        let content = r#"use std::fmt;
+pub use net::Server;
+// pub use ignored::Thing;
+pub(crate) use self::config::{
+    Config as Settings,
+    loader::{self, Loader},
+};
+pub use ::prelude::*;
+
+pub mod inner {
+    pub use super::Server as InnerServer;
+}
+"#;
+        let reexports = scan_reexports(content);
+        let found: Vec<(&str, &str, &str, bool)> = reexports
+            .iter()
+            .map(|reexport| {
+                (
+                    reexport.visibility.as_str(),
+                    reexport.path.as_str(),
+                    reexport.name.as_str(),
+                    reexport.glob,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("pub", "net::Server", "Server", false),
+                ("pub(crate)", "self::config::Config", "Settings", false),
+                ("pub(crate)", "self::config::loader", "loader", false),
+                (
+                    "pub(crate)",
+                    "self::config::loader::Loader",
+                    "Loader",
+                    false
+                ),
+                ("pub", "prelude::*", "*", true),
+                ("pub", "super::Server", "InnerServer", false),
+            ]
+        );
+        assert!(content[reexports[0].offset..].starts_with("Server;"));
+        assert!(content[reexports[2].offset..].starts_with("self, Loader"));
+        // A glob resolves through the module it names.
+        assert!(content[reexports[4].offset..].starts_with("prelude::*"));
+    }
+    #[test]
+    fn test_scan_imports() {
+        let content = r#"pub mod navigation;
+use crate::tools::navigation::{tokenize, Token as Tok};
+pub(crate) use super::navigation::*;
+// use crate::tools::navigation::ignored;
+fn f() -> impl Sized + use<> { use self::navigation; }
+mod tests {
+    use super::*;
+}
+"#;
+        let sites = scan_imports(content);
+        let paths: Vec<_> = sites
+            .iter()
+            .map(|site| {
+                let paths: Vec<_> = site.paths.iter().map(|path| path.join("::")).collect();
+                (
+                    paths,
+                    site.inline_modules.clone(),
+                    site.declares_module,
+                    site.reexport,
+                )
+            })
+            .collect();
+        let strings = |items: &[&str]| {
+            items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths,
+            vec![
+                (strings(&["self::navigation"]), vec![], true, false),
+                (
+                    strings(&[
+                        "crate::tools::navigation::tokenize",
+                        "crate::tools::navigation::Token"
+                    ]),
+                    vec![],
+                    false,
+                    false
+                ),
+                (strings(&["super::navigation::*"]), vec![], false, true),
+                (strings(&["self::navigation"]), vec![], false, false),
+                (strings(&["super::*"]), strings(&["tests"]), false, false),
+            ]
+        );
+
+        let current = strings(&["tools", "server"]);
+        let resolve = |path: &str| {
+            let path = strings(&path.split("::").collect::<Vec<_>>());
+            crate_relative_path(&path, Some(&current), "rust_mcp").map(|path| path.join("::"))
+        };
+        assert_eq!(
+            resolve("crate::tools::navigation::tokenize").as_deref(),
+            Some("tools::navigation::tokenize")
+        );
+        assert_eq!(
+            resolve("super::navigation::*").as_deref(),
+            Some("tools::navigation::*")
+        );
+        assert_eq!(
+            resolve("self::handler").as_deref(),
+            Some("tools::server::handler")
+        );
+        assert_eq!(resolve("rust_mcp::tools").as_deref(), Some("tools"));
+        assert_eq!(resolve("std::path::Path"), None);
+        let external = strings(&["crate", "tools"]);
+        assert_eq!(crate_relative_path(&external, None, "rust_mcp"), None);
+    }
+
+    #[test]
+    fn test_file_interface_helpers() {
+        let content = r#"use std::{fs, io::Error as IoError};
+use crate::tools::{navigation, types::ToolResult};
+use config::Settings;
+use serde::Serialize;
+fn load() -> Result<(), IoError> {
+    let text = fs::read_to_string("a::b")?;
+    let _ = navigation::tokenize(&text);
+    Settings::default().apply::<u8>();
+    Self::check(crate::tools::cargo::run(), std::mem::take(&mut 0));
+}
+"#;
+        let sites = scan_imports(content);
+        let leaves: Vec<(String, String)> = sites
+            .iter()
+            .flat_map(|site| use_leaves(content, site))
+            .map(|(path, name, offset)| {
+                assert!(content[offset..].starts_with(path.last().unwrap().as_str()));
+                (path.join("::"), name)
+            })
+            .collect();
+        assert_eq!(
+            leaves,
+            vec![
+                ("std::fs".to_string(), "fs".to_string()),
+                ("std::io::Error".to_string(), "IoError".to_string()),
+                (
+                    "crate::tools::navigation".to_string(),
+                    "navigation".to_string()
+                ),
+                (
+                    "crate::tools::types::ToolResult".to_string(),
+                    "ToolResult".to_string()
+                ),
+                ("config::Settings".to_string(), "Settings".to_string()),
+                ("serde::Serialize".to_string(), "Serialize".to_string()),
+            ]
+        );
+
+        let current = vec!["server".to_string()];
+        let local_names: HashSet<&str> = ["config", "load"].into_iter().collect();
+        let externs = vec!["std".to_string(), "serde".to_string()];
+        let resolve = |path: &str| {
+            let path: Vec<String> = path.split("::").map(str::to_string).collect();
+            resolve_use_path(&path, &current, &local_names, &externs).map(|path| path.join("::"))
+        };
+        assert_eq!(
+            resolve("crate::tools::navigation").as_deref(),
+            Some("tools::navigation")
+        );
+        assert_eq!(
+            resolve("config::Settings").as_deref(),
+            Some("server::config::Settings")
+        );
+        assert_eq!(resolve("super::handler").as_deref(), Some("handler"));
+        assert_eq!(resolve("serde::Serialize"), None);
+        assert_eq!(resolve("tokio::fs"), None);
+
+        let references: Vec<String> = scan_path_references(content, &sites)
+            .into_iter()
+            .map(|(path, _)| path.join("::"))
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                "fs::read_to_string",
+                "navigation::tokenize",
+                "Settings::default",
+                "crate::tools::cargo::run",
+                "std::mem::take",
+            ]
+        );
+
+        assert_eq!(
+            inherent_impl_type("impl<T> Stack<T>").as_deref(),
+            Some("Stack")
+        );
+        assert_eq!(inherent_impl_type("impl Display for Stack<T>"), None);
+    }
+
+    #[test]
+    fn test_parent_module_candidates() {
+        let (module, candidates) = parent_module_candidates(Path::new("/ws/src/net/tcp.rs")).unwrap();
+        assert_eq!(module, "tcp");
+        assert_eq!(
+            candidates,
+            vec![
+                PathBuf::from("/ws/src/net/mod.rs"),
+                PathBuf::from("/ws/src/net/lib.rs"),
+                PathBuf::from("/ws/src/net/main.rs"),
+                PathBuf::from("/ws/src/net.rs"),
+            ]
+        );
+
+        let (module, candidates) = parent_module_candidates(Path::new("/ws/src/net/mod.rs")).unwrap();
+        assert_eq!(module, "net");
+        assert_eq!(candidates[1], PathBuf::from("/ws/src/lib.rs"));
+
+        assert!(is_crate_root(Path::new("/ws/src/main.rs")));
+        assert!(is_crate_root(Path::new("/ws/src/bin/tool.rs")));
+        assert!(!is_crate_root(Path::new("/ws/src/net/main.rs")));
+    }
+
+    #[tokio::test]
+    async fn test_walk_to_crate_root() {
+        let dir = std::env::temp_dir().join(format!("rust-mcp-module-tree-{}", std::process::id()));
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("net")).unwrap();
+        std::fs::create_dir_all(src.join("extra")).unwrap();
+        std::fs::write(src.join("lib.rs"), "pub mod net;\n").unwrap();
+        std::fs::write(src.join("net.rs"), "mod tcp;\n").unwrap();
+        for file in ["net/tcp.rs", "net/udp.rs", "extra/deep.rs"] {
+            std::fs::write(src.join(file), "").unwrap();
+        }
+
+        let walk = walk_to_crate_root(&src.join("net/tcp.rs")).await;
+        assert_eq!(walk.root, Some(src.join("lib.rs")));
+        assert!(walk.missing.is_empty());
+        assert_eq!(walk.links.len(), 2);
+        assert_eq!(walk.links[0]["declaration"], "mod tcp;");
+        assert_eq!(walk.links[1]["declaration"], "pub mod net;");
+
+        let walk = walk_to_crate_root(&src.join("net/udp.rs")).await;
+        assert_eq!(walk.root, Some(src.join("lib.rs")));
+        assert_eq!(
+            walk.missing,
+            vec![MissingModule {
+                module: "udp".to_string(),
+                parent_file: src.join("net.rs"),
+                parent_exists: true,
+            }]
+        );
+
+        let walk = walk_to_crate_root(&src.join("extra/deep.rs")).await;
+        assert_eq!(walk.root, Some(src.join("lib.rs")));
+        assert_eq!(
+            walk.missing,
+            vec![
+                MissingModule {
+                    module: "deep".to_string(),
+                    parent_file: src.join("extra.rs"),
+                    parent_exists: false,
+                },
+                MissingModule {
+                    module: "extra".to_string(),
+                    parent_file: src.join("lib.rs"),
+                    parent_exists: true,
+                },
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_mod_declaration() {
+        let content = "// mod tcp;\nmod udp;\n#[cfg(unix)] pub(crate) mod tcp;\npub mod tcp_ext;\n";
+        assert_eq!(
+            find_mod_declaration(content, "tcp"),
+            Some((2, "#[cfg(unix)] pub(crate) mod tcp;".to_string()))
+        );
+        assert_eq!(find_mod_declaration(content, "udp").map(|(line, _)| line), Some(1));
+        assert_eq!(find_mod_declaration("mod tcp {}\n", "tcp"), None);
+    }
+
+    #[test]
+    fn test_scan_macro_definitions() {
+        let content = r##"// macro_rules! commented { () => {} }
+/// Builds a map.
+#[macro_export]
+macro_rules! hashmap {
+    ($($k:expr => $v:expr),*) => {{ let _ = "macro_rules! quoted"; }};
+}
+
+macro_rules! private_helper { () => {} }
+
+#[proc_macro_derive(Builder, attributes(builder, default))]
+pub fn derive_builder(input: TokenStream) -> TokenStream { input }
+
+#[proc_macro_attribute]
+#[allow(unused)]
+pub fn traced(attr: TokenStream, item: TokenStream) -> TokenStream { item }
+"##;
+        let definitions = scan_macro_definitions(content);
+        let summary: Vec<_> = definitions
+            .iter()
+            .map(|d| (d.kind, d.name.as_str(), d.line, d.exported))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (MacroKind::Declarative, "hashmap", 3, true),
+                (MacroKind::Declarative, "private_helper", 7, false),
+                (MacroKind::Derive, "Builder", 9, true),
+                (MacroKind::Attribute, "traced", 12, true),
+            ]
+        );
+        assert_eq!(definitions[2].function.as_deref(), Some("derive_builder"));
+        assert_eq!(definitions[2].helper_attributes, vec!["builder", "default"]);
+        assert_eq!(
+            gated_item_end_line(content, definitions[0].body_start),
+            Some(5)
+        );
+    }
+
+    fn symbol(name: &str, container: &str, uri: &str, line: u32) -> Value {
+        json!({
+            "name": name,
+            "kind": 12,
+            "containerName": container,
+            "location": {
+                "uri": uri,
+                "range": {
+                    "start": {"line": line, "character": 0},
+                    "end": {"line": line, "character": 4}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_derive_paths_and_trait_impls() {
+        let block = "#[derive(Debug, Clone)]\n#[cfg_attr(feature = \"serde\", derive(serde::Serialize))]\nstruct Point;";
+        let paths = derive_paths(block);
+        assert_eq!(
+            paths
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Debug", "Clone", "serde::Serialize"]
+        );
+        assert!(
+            paths
+                .iter()
+                .all(|(path, offset)| block[*offset..].starts_with(last_path_segment(path)))
+        );
+
+        let expansion = "// Recursive expansion of derive macro\n\nimpl <T: Clone> ::core::clone::Clone for Point<T> {\n    fn clone(&self) -> Self { Point }\n}\nimpl ::core::fmt::Debug for Point {\n    fn fmt(&self, f: &mut Formatter) -> Result { f.write_str(\"Point\") }\n}\n";
+        let impls = trait_impls(expansion, "Debug");
+        assert_eq!(impls.len(), 1);
+        assert!(impls[0].starts_with("impl ::core::fmt::Debug for Point {"));
+        assert!(impls[0].ends_with('}'));
+        assert_eq!(trait_impls(expansion, "Clone").len(), 1);
+        assert!(trait_impls(expansion, "Hash").is_empty());
+    }
+
+    #[test]
+    fn test_cfg_predicate_rendering() {
+        let predicate =
+            CfgPredicate::parse(r#"all(unix, any(feature = "serde", test), not(target_os = "macos"))"#)
+                .unwrap();
+        assert_eq!(
+            predicate.render(),
+            r#"unix and (feature "serde" enabled or test) and not target_os is "macos""#
+        );
+        assert_eq!(CfgPredicate::parse("test").unwrap().render(), "test");
+        assert_eq!(CfgPredicate::parse("all()").unwrap().render(), "always");
+    }
+
+    #[test]
+    fn test_cfg_predicate_features() {
+        let features = |text: &str| {
+            CfgPredicate::parse(text)
+                .unwrap()
+                .features()
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            features(r#"all(feature = "std", any(feature = "serde", not(feature = "std")))"#),
+            vec!["std", "serde"]
+        );
+        assert!(features(r#"target_os = "linux""#).is_empty());
+    }
+
+    #[test]
+    fn test_cfg_predicate_enabling_features() {
+        let predicate = CfgPredicate::parse(
+            r#"all(feature = "std", any(all(feature = "tls", feature = "ring"), feature = "native"), not(feature = "no_alloc"))"#,
+        )
+        .unwrap();
+        assert_eq!(predicate.enabling_features(), Some(vec!["std", "native"]));
+        assert_eq!(predicate.excluded_features(), vec!["no_alloc"]);
+        assert_eq!(
+            CfgPredicate::parse(r#"all(unix, not(any(feature = "a", test)))"#)
+                .unwrap()
+                .excluded_features(),
+            vec!["a"]
+        );
+        assert_eq!(
+            CfgPredicate::parse("any()").unwrap().enabling_features(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cfg_predicate_evaluation() {
+        let cfg = TargetCfg::parse(
+            "debug_assertions\npanic=\"unwind\"\ntarget_arch=\"x86_64\"\ntarget_family=\"unix\"\ntarget_os=\"linux\"\nunix\n",
+        );
+        let evaluate = |text: &str| CfgPredicate::parse(text).unwrap().evaluate(&cfg);
+        assert_eq!(evaluate("unix"), Some(true));
+        assert_eq!(evaluate("windows"), Some(false));
+        assert_eq!(evaluate(r#"target_os = "macos""#), Some(false));
+        assert_eq!(
+            evaluate(r#"all(unix, not(target_arch = "wasm32"))"#),
+            Some(true)
+        );
+        assert_eq!(evaluate("test"), None);
+        assert_eq!(evaluate("debug_assertions"), None);
+        assert_eq!(evaluate(r#"all(windows, feature = "serde")"#), Some(false));
+        assert_eq!(evaluate(r#"any(unix, feature = "serde")"#), Some(true));
+        assert_eq!(evaluate(r#"any(windows, feature = "serde")"#), None);
+    }
+
+    #[test]
+    fn test_gated_test_items() {
+        let parse = |text: &str| CfgPredicate::parse(text).unwrap();
+        assert!(parse("test").requires_flag("test"));
+        assert!(parse(r#"all(test, feature = "mock")"#).requires_flag("test"));
+        assert!(!parse(r#"any(test, feature = "test-utils")"#).requires_flag("test"));
+        assert!(parse(r#"any(test, feature = "test-utils")"#).enabled_by_flag("test"));
+        assert!(!parse("not(test)").enabled_by_flag("test"));
+
+        assert_eq!(gated_item_kind("\nmod tests {\n"), "module");
+        assert_eq!(
+            gated_item_kind("\nmod test_support;\n"),
+            "module_declaration"
+        );
+        assert_eq!(
+            gated_item_kind("\n#[derive(Default)]\npub(crate) struct Mock;"),
+            "struct"
+        );
+        assert_eq!(gated_item_kind("\nimpl Store for Mock {"), "impl");
+        assert_eq!(
+            gated_item_kind("\npub const fn fixture() -> u32 {"),
+            "function"
+        );
+        assert_eq!(gated_item_kind("\nuse std::fmt;"), "use");
+        assert_eq!(gated_item_kind("\nlet x = 1;"), "statement");
+    }
+    #[test]
+    fn test_scan_cfg_occurrences() {
+        let content = r##"#![cfg(unix)]
+// #[cfg(test)] in a comment is ignored
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Config;
+
+#[cfg(test)]
+use std::fmt;
+
+fn main() {
+    if cfg!(debug_assertions) {
+        let _ = "#[cfg(not(this))]";
+    }
+}
+"##;
+        let occurrences = scan_cfg_occurrences(content);
+        let summary: Vec<_> = occurrences
+            .iter()
+            .map(|o| (o.kind, o.line, o.predicate.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (CfgKind::InnerAttribute, 0, "unix"),
+                (CfgKind::AttributeCfgAttr, 2, "feature = \"serde\""),
+                (CfgKind::Attribute, 5, "test"),
+                (CfgKind::Macro, 9, "debug_assertions"),
+            ]
+        );
+        assert_eq!(
+            occurrences[1].applies.as_deref(),
+            Some("derive(Serialize, Deserialize)")
+        );
+        assert_eq!(gated_item_end_line(content, occurrences[2].end), Some(6));
+    }
+
+    #[test]
+    fn test_signature_filter() {
+        let hover = "```rust\nasync fn load(&mut self, path: &Path) -> Result<Config, Error>\n```";
+        let signature = parse_hover_signature(hover).unwrap();
+
+        let filter = |is_async, returns: Option<&str>, takes: Option<&str>| SignatureFilter {
+            is_async,
+            returns: returns.map(str::to_string),
+            takes: takes.map(str::to_string),
+        };
+
+        assert!(filter(Some(true), Some("Result"), Some("&mut self")).matches(&signature));
+        assert!(filter(None, None, Some("&Path")).matches(&signature));
+        assert!(!filter(Some(false), None, None).matches(&signature));
+        assert!(!filter(None, Some("Option"), None).matches(&signature));
+        assert!(!filter(None, None, Some("&self")).matches(&signature));
+    }
+
+    #[test]
+    fn test_paginate_workspace_symbols_is_stable() {
+        let infos = vec![
+            symbol("run", "app::server", "file:///ws/app/src/server.rs", 10),
+            symbol("alpha", "app::client", "file:///ws/app/src/client.rs", 3),
+            symbol("beta", "app::client", "file:///ws/app/src/client.rs", 1),
+            symbol("run", "app::client", "file:///ws/app/src/client.rs", 20),
+        ];
+        let mut reversed = infos.clone();
+        reversed.reverse();
+
+        let names = |page: &[Value]| -> Vec<String> {
+            page.iter()
+                .map(|item| format!("{}::{}", item["module_path"][0].as_str().unwrap(), item["name"].as_str().unwrap()))
+                .collect()
+        };
+
+        let (total, first) = paginate_workspace_symbols(&infos, 0, 3);
+        assert_eq!(total, 4);
+        assert_eq!(names(&first), vec!["client::alpha", "client::beta", "client::run"]);
+
+        let (_, again) = paginate_workspace_symbols(&reversed, 0, 3);
+        assert_eq!(names(&first), names(&again));
+
+        let (_, last) = paginate_workspace_symbols(&infos, 3, 3);
+        assert_eq!(names(&last), vec!["server::run"]);
+
+        let (_, past_end) = paginate_workspace_symbols(&infos, 10, 3);
+        assert!(past_end.is_empty());
+    }
+}
@@ -1,7 +1,23 @@
 use crate::analyzer::RustAnalyzerClient;
+use crate::analyzer::protocol::{DocumentSymbol, DocumentSymbolResponse};
+use crate::compiler::diagnostics::{CargoDiagnostic, SuggestedFix, clippy_messages};
+use crate::tools::advanced::strip_visibility;
+use crate::tools::analysis::is_valid_code_context;
+use crate::tools::cargo::build_script_path;
+use crate::tools::navigation::{
+    Token, collect_rust_files, test_code_spans, tokenize, walk_to_crate_root,
+};
+use crate::tools::refactoring::{
+    HoverSignature, ItemVisibility, declared_visibility, find_matching_brace, find_matching_paren,
+    parse_hover_signature, text_in_range,
+};
 use crate::tools::types::ToolResult;
 use anyhow::Result;
+use serde::Serialize;
 use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::fs;
 
 pub async fn apply_clippy_suggestions_impl(
     args: Value,
@@ -26,3 +42,2794 @@ pub async fn apply_clippy_suggestions_impl(
         ],
     })
 }
+
+/// Clippy lints flagging a `.clone()` that can go: the value is not used
+/// afterwards, or it is `Copy`.
+const CLONE_LINTS: [&str; 2] = ["clippy::redundant_clone", "clippy::clone_on_copy"];
+
+pub async fn find_unnecessary_clones_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let workspace_path = args
+        .get("workspace_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing workspace_path parameter"))?;
+    let file_path = args.get("file_path").and_then(|v| v.as_str());
+    let apply = args.get("apply").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let package = match file_path {
+        Some(file_path) => analyzer.package_for_file(workspace_path, file_path).await?,
+        None => None,
+    };
+    let workspace = Path::new(workspace_path);
+    let messages = clippy_messages(workspace, package.as_deref(), &CLONE_LINTS).await?;
+
+    let mut findings: Vec<CloneFinding> = Vec::new();
+    for message in &messages {
+        let Some(diagnostic) = CargoDiagnostic::from_message(message) else {
+            continue;
+        };
+        let (Some(lint), Some(file)) = (diagnostic.code, diagnostic.file) else {
+            continue;
+        };
+        if !CLONE_LINTS.contains(&lint.as_str())
+            || file_path.is_some_and(|file_path| !Path::new(file_path).ends_with(&file))
+        {
+            continue;
+        }
+        let finding = CloneFinding {
+            lint,
+            message: diagnostic.message,
+            file,
+            line: diagnostic.line,
+            fix: SuggestedFix::all_in(message).into_iter().next(),
+        };
+        // Targets sharing a file (lib and its tests) report the same clone.
+        if !findings.contains(&finding) {
+            findings.push(finding);
+        }
+    }
+
+    let auto_fixable: Vec<&SuggestedFix> = findings
+        .iter()
+        .filter_map(|finding| finding.fix.as_ref())
+        .filter(|fix| fix.applicability == "MachineApplicable")
+        .collect();
+    let mut result = json!({
+        "package": package,
+        "lints": CLONE_LINTS,
+        "count": findings.len(),
+        "removable": findings.iter().filter(|finding| finding.fix.is_some()).count(),
+        "auto_fixable": auto_fixable.len(),
+        "findings": findings
+    });
+
+    if apply {
+        let mut by_file: BTreeMap<&str, Vec<&SuggestedFix>> = BTreeMap::new();
+        for fix in &auto_fixable {
+            by_file.entry(fix.file.as_str()).or_default().push(fix);
+        }
+        let mut applied = 0;
+        let mut diffs = Vec::new();
+        for (file, fixes) in by_file {
+            let path = workspace.join(file).display().to_string();
+            let content = fs::read_to_string(&path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", path, e))?;
+            let (updated, count) = apply_suggested_fixes(&content, fixes);
+            match analyzer.write_file(&path, &content, &updated).await? {
+                Some(diff) => diffs.push(diff),
+                None if analyzer.document_version(&path).is_some() => {
+                    analyzer.sync_document(&path, &updated).await?
+                }
+                None => {}
+            }
+            applied += count;
+        }
+        result["applied"] = json!(applied);
+        result["written"] = json!(analyzer.allow_writes());
+        if !diffs.is_empty() {
+            result["diff"] = json!(diffs.join("\n"));
+            result["message"] =
+                json!("Writes are disabled (read-only mode); no files were changed.");
+        }
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// One `.clone()` flagged by [`CLONE_LINTS`].
+#[derive(Debug, PartialEq, Serialize)]
+struct CloneFinding {
+    lint: String,
+    message: String,
+    file: String,
+    line: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<SuggestedFix>,
+}
+
+/// Splices `fixes` into `content` from the last to the first, so earlier byte
+/// offsets stay valid. A fix overlapping one already applied, or not on a
+/// character boundary, is skipped. Returns the new content and how many fixes
+/// were applied.
+fn apply_suggested_fixes(content: &str, mut fixes: Vec<&SuggestedFix>) -> (String, usize) {
+    fixes.sort_by_key(|fix| std::cmp::Reverse((fix.byte_start, fix.byte_end)));
+    let mut updated = content.to_string();
+    let mut applied = 0;
+    let mut limit = content.len();
+    for fix in fixes {
+        if fix.byte_start > fix.byte_end
+            || fix.byte_end > limit
+            || !content.is_char_boundary(fix.byte_start)
+            || !content.is_char_boundary(fix.byte_end)
+        {
+            continue;
+        }
+        updated.replace_range(fix.byte_start..fix.byte_end, &fix.replacement);
+        limit = fix.byte_start;
+        applied += 1;
+    }
+    (updated, applied)
+}
+
+pub async fn unused_dependencies_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let manifest_path = args
+        .get("manifest_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing manifest_path parameter"))?;
+
+    let manifest_text = fs::read_to_string(manifest_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest: {}", e))?;
+    let manifest: toml::Table = toml::from_str(&manifest_text)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", manifest_path, e))?;
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no [package]; pass a member's manifest",
+                manifest_path
+            )
+        })?;
+
+    let crate_dir = Path::new(manifest_path).parent().unwrap_or(Path::new("."));
+    let build_script = build_script_path(&manifest, crate_dir);
+
+    let mut sources = Vec::new();
+    for file in collect_rust_files(crate_dir).await? {
+        let Ok(content) = fs::read_to_string(&file).await else {
+            continue;
+        };
+        let target = if build_script.as_deref() == Some(file.as_path()) {
+            DependencyKind::Build
+        } else if file
+            .strip_prefix(crate_dir)
+            .ok()
+            .and_then(|relative| relative.components().next())
+            .is_some_and(|first| {
+                matches!(
+                    first.as_os_str().to_str(),
+                    Some("tests" | "benches" | "examples")
+                )
+            })
+        {
+            DependencyKind::Dev
+        } else {
+            DependencyKind::Normal
+        };
+        sources.push((target, content));
+    }
+
+    let dependencies = declared_dependencies(&manifest);
+    let mut used = Vec::new();
+    let mut unused = Vec::new();
+    let mut dev_only = Vec::new();
+    for dependency in &dependencies {
+        let used_by: Vec<DependencyKind> = sources
+            .iter()
+            .filter(|(_, content)| references_crate(content, &dependency.ident))
+            .map(|(target, _)| *target)
+            .collect();
+
+        let satisfied = match dependency.kind {
+            // Dev-dependencies are also visible to `#[cfg(test)]` code in src/.
+            DependencyKind::Dev => used_by.iter().any(|t| *t != DependencyKind::Build),
+            kind => used_by.contains(&kind),
+        };
+        if satisfied {
+            used.push(dependency.name.clone());
+            if dependency.kind == DependencyKind::Normal
+                && !used_by.contains(&DependencyKind::Normal)
+            {
+                dev_only.push(dependency.name.clone());
+            }
+            continue;
+        }
+
+        let mut caveats = Vec::new();
+        if dependency.optional {
+            caveats.push("optional: may only be needed by a feature".to_string());
+        }
+        if dependency.name.ends_with("-sys") || dependency.name.ends_with("_sys") {
+            caveats
+                .push("`-sys` crates are often linked only for their native library".to_string());
+        }
+        if dependency.name.ends_with("-derive") || dependency.name.ends_with("_derive") {
+            caveats.push(
+                "derive crates are often used through a re-export of their parent crate"
+                    .to_string(),
+            );
+        }
+        if !used_by.is_empty() {
+            caveats.push(format!(
+                "referenced only from {} code",
+                used_by
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            ));
+        }
+        unused.push(json!({
+            "name": dependency.name,
+            "crate_name": dependency.ident,
+            "kind": dependency.kind.as_str(),
+            "target": dependency.target,
+            "caveats": caveats
+        }));
+    }
+
+    // Normal dependencies that only tests, benches and examples reference.
+    dev_only.retain(|name| !unused.iter().any(|entry| entry["name"] == name.as_str()));
+
+    let result = json!({
+        "manifest_path": manifest_path,
+        "package": package,
+        "dependencies_checked": dependencies.len(),
+        "files_scanned": sources.len(),
+        "unused": unused,
+        "only_used_by_tests_benches_or_examples": dev_only,
+        "used": used,
+        "limitations": [
+            "Usage is detected from `name::` paths, `use name` and `extern crate name` in .rs files; code generated by macros or build scripts is not seen.",
+            "A crate re-exported or used by another dependency's macros (e.g. `serde_derive` through `serde`) can look unused.",
+            "Crates linked only for side effects (native `-sys` libraries, allocators, panic handlers) can look unused.",
+            "Verify with `run_cargo_check` (and the crate's tests) after removing anything."
+        ]
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Functions listed when `limit` is not given.
+const DEFAULT_COMPLEXITY_LIMIT: usize = 50;
+
+pub async fn complexity_report_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_COMPLEXITY_LIMIT as u64)
+        .max(1) as usize;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut functions = Vec::new();
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let file_path = file.to_string_lossy();
+        let Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) =
+            analyzer.document_symbols(&file_path).await
+        else {
+            continue;
+        };
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut found = Vec::new();
+        collect_functions(&symbols, None, &mut found);
+        for (symbol, container) in found {
+            let start = symbol.range.start.line as usize;
+            let end = (symbol.range.end.line as usize).min(lines.len().saturating_sub(1));
+            if start > end {
+                continue;
+            }
+            // Nested functions are reported on their own, so blank their lines.
+            let nested: Vec<(usize, usize)> = symbol
+                .children
+                .iter()
+                .flatten()
+                .filter(|child| is_function_kind(child.kind))
+                .map(|child| {
+                    (
+                        child.range.start.line as usize,
+                        child.range.end.line as usize,
+                    )
+                })
+                .collect();
+            let source = (start..=end)
+                .map(|line| {
+                    if nested
+                        .iter()
+                        .any(|(from, to)| (*from..=*to).contains(&line))
+                    {
+                        ""
+                    } else {
+                        lines[line]
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let counts = BranchCounts::of(&source);
+            functions.push(json!({
+                "name": symbol.name,
+                "container": container,
+                "file_path": file_path,
+                "line": start + 1,
+                "end_line": end + 1,
+                "complexity": counts.complexity(),
+                "branches": {
+                    "if": counts.ifs,
+                    "match_arms": counts.match_arms,
+                    "boolean_operators": counts.boolean_operators,
+                    "loops": counts.loops,
+                    "try_operators": counts.try_operators
+                }
+            }));
+        }
+    }
+
+    let analyzed = functions.len();
+    functions.sort_by(|a, b| {
+        b["complexity"]
+            .as_u64()
+            .cmp(&a["complexity"].as_u64())
+            .then_with(|| a["file_path"].as_str().cmp(&b["file_path"].as_str()))
+            .then_with(|| a["line"].as_u64().cmp(&b["line"].as_u64()))
+    });
+    functions.truncate(limit);
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "functions_analyzed": analyzed,
+        "functions": functions,
+        "heuristic": "Cyclomatic complexity is estimated as 1 + `if` + extra `match` arms + `&&`/`||` + loops + `?`, counted in each function's source outside comments and strings. Closures count toward the enclosing function; macros are not expanded."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Line span above which `find_large_functions` reports a function.
+const DEFAULT_LARGE_FUNCTION_LINES: usize = 100;
+
+pub async fn find_large_functions_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let threshold = args
+        .get("threshold")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_LARGE_FUNCTION_LINES as u64) as usize;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut analyzed = 0;
+    let mut functions = Vec::new();
+    for file in &files {
+        let file_path = file.to_string_lossy();
+        let Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) =
+            analyzer.document_symbols(&file_path).await
+        else {
+            continue;
+        };
+
+        let mut found = Vec::new();
+        collect_functions(&symbols, None, &mut found);
+        analyzed += found.len();
+        for (symbol, container) in found {
+            let lines = (symbol.range.end.line - symbol.range.start.line) as usize + 1;
+            if lines > threshold {
+                functions.push((
+                    lines,
+                    json!({
+                        "name": symbol.name,
+                        "container": container,
+                        "file_path": file_path,
+                        "line": symbol.range.start.line + 1,
+                        "end_line": symbol.range.end.line + 1,
+                        "lines": lines
+                    }),
+                ));
+            }
+        }
+    }
+    functions.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let result = json!({
+        "path": path,
+        "threshold": threshold,
+        "files_scanned": files.len(),
+        "functions_analyzed": analyzed,
+        "functions": functions.into_iter().map(|(_, function)| function).collect::<Vec<_>>()
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn must_use_candidates_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let include_private = args
+        .get("include_private")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let apply = args.get("apply").and_then(|v| v.as_bool()).unwrap_or(false);
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MUST_USE_LIMIT as u64)
+        .max(1) as usize;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut candidates = Vec::new();
+    let mut insertions: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+    let mut already_marked = 0;
+    let mut truncated = false;
+    'files: for file in &files {
+        let file_path = file.to_string_lossy().to_string();
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let Ok(DocumentSymbolResponse::DocumentSymbols(symbols)) =
+            analyzer.document_symbols(&file_path).await
+        else {
+            continue;
+        };
+        // A private `mod` between the file and its crate root hides every
+        // item of the file from other crates.
+        let file_hidden = walk_to_crate_root(file).await.links.iter().any(|link| {
+            declared_visibility(link["declaration"].as_str().unwrap_or_default())
+                != ItemVisibility::Public
+        });
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut found = Vec::new();
+        collect_scoped_functions(&symbols, None, file_hidden, &content, &mut found);
+        for (symbol, parent, hidden) in found {
+            let source = text_in_range(&content, &symbol.range);
+            let Some(fn_offset) = source.lines().position(|line| {
+                let line = line.trim_start();
+                !line.starts_with("#[") && !line.starts_with("//") && line.contains("fn ")
+            }) else {
+                continue;
+            };
+            let fn_line = symbol.range.start.line as usize + fn_offset;
+            if has_must_use(&lines, fn_line) {
+                already_marked += 1;
+                continue;
+            }
+
+            let mut visibility = declared_visibility(&source);
+            match parent {
+                // `#[must_use]` on a trait impl's method has no effect; the
+                // trait's declaration decides.
+                Some(parent) if parent.name.contains(" for ") => continue,
+                // LSP `SymbolKind::Interface`: trait items share its visibility.
+                Some(parent) if parent.kind == 11 => {
+                    visibility = declared_visibility(&text_in_range(&content, &parent.range));
+                }
+                _ => {}
+            }
+            let public = visibility == ItemVisibility::Public && !hidden;
+            if !public && !include_private {
+                continue;
+            }
+
+            let start = &symbol.selection_range.start;
+            let hovered = match analyzer
+                .get_hover(&file_path, start.line, start.character)
+                .await
+            {
+                Ok(hover) => parse_hover_signature(&hover),
+                Err(_) => None,
+            };
+            let signature = hovered.or_else(|| {
+                let header: String = lines[fn_line..]
+                    .iter()
+                    .take(8)
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let header = header.split(['{', ';']).next().unwrap_or(&header);
+                parse_hover_signature(&format!("```rust\n{header}\n```"))
+            });
+            let Some(signature) = signature else {
+                continue;
+            };
+            let Some(kind) = must_use_kind(&signature) else {
+                continue;
+            };
+
+            if candidates.len() >= limit {
+                truncated = true;
+                break 'files;
+            }
+            let indent: String = lines[fn_line]
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .collect();
+            let attribute = match kind {
+                // A bare `#[must_use]` on a function returning an already
+                // `#[must_use]` type trips clippy::double_must_use.
+                "result" => format!(
+                    "{indent}#[must_use = \"this returns a Result that should be checked\"]"
+                ),
+                _ => format!("{indent}#[must_use]"),
+            };
+            candidates.push(json!({
+                "name": signature.name,
+                "owner": signature.owner,
+                "kind": kind,
+                "visibility": visibility.to_string(),
+                "public": public,
+                "file_path": file_path,
+                "line": fn_line + 1,
+                "signature": signature.signature,
+                "attribute": attribute.trim()
+            }));
+            insertions
+                .entry(file_path.clone())
+                .or_default()
+                .push((fn_line, attribute));
+        }
+    }
+
+    let count = |kind: &str| candidates.iter().filter(|c| c["kind"] == kind).count();
+    let mut result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "count": candidates.len(),
+        "truncated": truncated,
+        "by_kind": {
+            "result": count("result"),
+            "option": count("option"),
+            "builder": count("builder")
+        },
+        "already_marked": already_marked,
+        "candidates": candidates,
+        "note": "Heuristic: `Result` is already #[must_use] as a type, so its candidates get an attribute with a message; `Option` returns taking `&mut self` (like `pop`) are skipped because ignoring them is normal."
+    });
+
+    if apply {
+        let mut applied = 0;
+        let mut diffs = Vec::new();
+        for (file_path, mut file_insertions) in insertions {
+            let content = fs::read_to_string(&file_path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", file_path, e))?;
+            let mut lines: Vec<&str> = content.lines().collect();
+            file_insertions.sort_by_key(|b| std::cmp::Reverse(b.0));
+            for (line, attribute) in &file_insertions {
+                lines.insert(*line, attribute);
+            }
+            let mut updated = lines.join("\n");
+            if content.ends_with('\n') {
+                updated.push('\n');
+            }
+            match analyzer.write_file(&file_path, &content, &updated).await? {
+                Some(diff) => diffs.push(diff),
+                None if analyzer.document_version(&file_path).is_some() => {
+                    analyzer.sync_document(&file_path, &updated).await?
+                }
+                None => {}
+            }
+            applied += file_insertions.len();
+        }
+        result["applied"] = json!(applied);
+        result["written"] = json!(analyzer.allow_writes());
+        if !diffs.is_empty() {
+            result["diff"] = json!(diffs.join("\n"));
+            result["message"] =
+                json!("Writes are disabled (read-only mode); no files were changed.");
+        }
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+const DEFAULT_MUST_USE_LIMIT: usize = 100;
+
+/// Like [`collect_functions`], with each function's parent symbol and
+/// whether a private inline `mod` (or `hidden`, for the file) encloses it.
+fn collect_scoped_functions<'a>(
+    symbols: &'a [DocumentSymbol],
+    parent: Option<&'a DocumentSymbol>,
+    hidden: bool,
+    content: &str,
+    found: &mut Vec<(&'a DocumentSymbol, Option<&'a DocumentSymbol>, bool)>,
+) {
+    for symbol in symbols {
+        if is_function_kind(symbol.kind) {
+            found.push((symbol, parent, hidden));
+            // Nested functions are never reachable from outside.
+            continue;
+        }
+        // LSP `SymbolKind::Module`
+        let hidden = hidden
+            || (symbol.kind == 2
+                && declared_visibility(&text_in_range(content, &symbol.range))
+                    != ItemVisibility::Public);
+        let children = symbol.children.as_deref().unwrap_or_default();
+        collect_scoped_functions(children, Some(symbol), hidden, content, found);
+    }
+}
+
+/// Whether the attributes directly above the `fn` line `fn_line` include
+/// `#[must_use]`.
+fn has_must_use(lines: &[&str], fn_line: usize) -> bool {
+    lines[..fn_line]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("#[") || line.starts_with("//") || line.ends_with(']'))
+        .any(|line| line.starts_with("#[must_use"))
+}
+
+/// Why the result of a function with `signature` should not be ignored:
+/// `result`, `option`, or `builder` for a method taking `self` by value and
+/// returning `Self`. `None` for anything else.
+fn must_use_kind(signature: &HoverSignature) -> Option<&'static str> {
+    let returns = signature.return_type.as_deref()?.trim();
+    let base = returns.split('<').next().unwrap_or(returns).trim();
+    let base = base.rsplit("::").next().unwrap_or(base);
+    let receiver = signature.receiver.as_deref().map(str::trim);
+    match base {
+        "Result" => Some("result"),
+        "Option" if receiver == Some("&mut self") => None,
+        "Option" => Some("option"),
+        _ if matches!(receiver, Some("self" | "mut self"))
+            && (returns == "Self" || signature.owner.as_deref() == Some(returns)) =>
+        {
+            Some("builder")
+        }
+        _ => None,
+    }
+}
+
+pub async fn find_dyn_usages_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let include_impl = args
+        .get("include_impl")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut usages = Vec::new();
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        for usage in scan_dyn_usages(&content) {
+            if usage.keyword == "impl" && !include_impl {
+                continue;
+            }
+            usages.push(json!({
+                "kind": usage.keyword,
+                "bounds": usage.bounds,
+                "wrapper": usage.wrapper,
+                "position": usage.position.as_str(),
+                "file_path": file.to_string_lossy(),
+                "line": usage.line + 1,
+                "context": lines.get(usage.line).map(|line| line.trim())
+            }));
+        }
+    }
+
+    let count = |key: &str, value: &str| usages.iter().filter(|u| u[key] == value).count();
+    let positions: serde_json::Map<String, Value> = [
+        TypePosition::Argument,
+        TypePosition::Return,
+        TypePosition::Field,
+        TypePosition::Local,
+        TypePosition::TypeAlias,
+        TypePosition::Static,
+        TypePosition::Other,
+    ]
+    .into_iter()
+    .map(|position| {
+        (
+            position.as_str().to_string(),
+            json!(count("position", position.as_str())),
+        )
+    })
+    .collect();
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "counts": {
+            "dyn": count("kind", "dyn"),
+            "impl": count("kind", "impl"),
+            "by_position": positions
+        },
+        "usages": usages
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn async_map_impl(args: Value, _analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut functions = Vec::new();
+    let mut other_contexts = Vec::new();
+    let mut await_count = 0;
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let file_path = file.to_string_lossy();
+        let lines: Vec<&str> = content.lines().collect();
+        let line_of = |offset: usize| content[..offset].matches('\n').count();
+        let awaits_json = |awaits: &[usize]| {
+            awaits
+                .iter()
+                .map(|&offset| {
+                    let line = line_of(offset);
+                    json!({
+                        "line": line + 1,
+                        "code": lines.get(line).map(|text| text.trim())
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let contexts = scan_async_contexts(&content);
+        await_count += contexts
+            .iter()
+            .map(|context| context.awaits.len())
+            .sum::<usize>();
+        for (idx, context) in contexts.iter().enumerate() {
+            let summary = json!({
+                "kind": context.kind.as_str(),
+                "line": line_of(context.start) + 1,
+                "end_line": line_of(context.body.1) + 1,
+                "awaits": awaits_json(&context.awaits)
+            });
+            if context.kind == AsyncKind::Function {
+                let nested: Vec<Value> = contexts
+                    .iter()
+                    .filter(|nested| nested.kind != AsyncKind::Function)
+                    .filter(|nested| nearest_async_fn(&contexts, nested) == Some(idx))
+                    .map(|nested| {
+                        json!({
+                            "kind": nested.kind.as_str(),
+                            "line": line_of(nested.start) + 1,
+                            "end_line": line_of(nested.body.1) + 1,
+                            "awaits": awaits_json(&nested.awaits)
+                        })
+                    })
+                    .collect();
+                functions.push(json!({
+                    "name": context.name,
+                    "file_path": file_path,
+                    "line": line_of(context.start) + 1,
+                    "end_line": line_of(context.body.1) + 1,
+                    "awaits": awaits_json(&context.awaits),
+                    "nested_contexts": nested
+                }));
+            } else if nearest_async_fn(&contexts, context).is_none() {
+                let mut summary = summary;
+                summary["file_path"] = json!(file_path);
+                other_contexts.push(summary);
+            }
+        }
+    }
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "async_functions": functions.len(),
+        "await_points": await_count,
+        "functions": functions,
+        "other_contexts": other_contexts,
+        "note": "Found by scanning source outside comments and strings; macros are not expanded. Each `.await` belongs to the innermost async fn, async block or async closure around it: an await in `nested_contexts` suspends that block's future, not the function's. `other_contexts` are async blocks and closures outside any async fn, e.g. spawned tasks."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AsyncKind {
+    Function,
+    Block,
+    Closure,
+}
+
+impl AsyncKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            AsyncKind::Function => "async_fn",
+            AsyncKind::Block => "async_block",
+            AsyncKind::Closure => "async_closure",
+        }
+    }
+}
+
+/// A future-producing context found by `scan_async_contexts`.
+#[derive(Debug, PartialEq)]
+struct AsyncContext {
+    kind: AsyncKind,
+    /// The function name, for `async fn`.
+    name: Option<String>,
+    /// Offset of the `async` keyword.
+    start: usize,
+    /// Offsets where the body starts and ends; for a brace body, its braces.
+    body: (usize, usize),
+    /// Offsets of the `.await`s directly in this context.
+    awaits: Vec<usize>,
+}
+
+/// Finds `async fn` bodies, `async` blocks and async closures in `content`
+/// and assigns each `.await` outside comments and strings to the innermost
+/// one containing it. Body-less trait declarations are skipped.
+fn scan_async_contexts(content: &str) -> Vec<AsyncContext> {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let is_token = |idx: usize, len: usize| {
+        !content[..idx]
+            .chars()
+            .next_back()
+            .is_some_and(is_ident_char)
+            && !content[idx + len..]
+                .chars()
+                .next()
+                .is_some_and(is_ident_char)
+            && is_valid_code_context(content, idx)
+    };
+
+    let mut contexts = Vec::new();
+    for (idx, _) in content.match_indices("async") {
+        if !is_token(idx, "async".len()) {
+            continue;
+        }
+        let mut rest = content[idx + "async".len()..].trim_start();
+        if let Some(after) = rest
+            .strip_prefix("move")
+            .filter(|after| !after.starts_with(is_ident_char))
+        {
+            rest = after.trim_start();
+        }
+        let at = content.len() - rest.len();
+
+        let context = if let Some(after_fn) = rest
+            .strip_prefix("fn")
+            .filter(|after| after.starts_with(char::is_whitespace))
+        {
+            let name: String = after_fn
+                .trim_start()
+                .chars()
+                .take_while(|c| is_ident_char(*c))
+                .collect();
+            fn_body_start(content, at).and_then(|open| {
+                let close = find_matching_brace(content, open)?;
+                Some((AsyncKind::Function, Some(name), (open, close)))
+            })
+        } else if rest.starts_with('{') {
+            find_matching_brace(content, at).map(|close| (AsyncKind::Block, None, (at, close)))
+        } else if rest.starts_with('|') {
+            closure_body(content, at).map(|body| (AsyncKind::Closure, None, body))
+        } else {
+            None
+        };
+        if let Some((kind, name, body)) = context {
+            contexts.push(AsyncContext {
+                kind,
+                name,
+                start: idx,
+                body,
+                awaits: Vec::new(),
+            });
+        }
+    }
+
+    for (idx, _) in content.match_indices(".await") {
+        if !is_token(idx + 1, "await".len()) {
+            continue;
+        }
+        let innermost = contexts
+            .iter_mut()
+            .filter(|context| context.body.0 < idx && idx < context.body.1)
+            .min_by_key(|context| context.body.1 - context.body.0);
+        if let Some(context) = innermost {
+            context.awaits.push(idx);
+        }
+    }
+    contexts
+}
+
+/// Offset of the `{` opening the body of the function whose signature
+/// continues at `from`, or `None` when a `;` ends it first.
+fn fn_body_start(content: &str, from: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (idx, c) in content[from..].char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '{' if depth == 0 => return Some(from + idx),
+            ';' if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The body of the closure whose parameter list starts at `bar`: its braces,
+/// or an expression body up to the `,`, `;` or closing bracket ending it.
+fn closure_body(content: &str, bar: usize) -> Option<(usize, usize)> {
+    let params_end = bar + 1 + content[bar + 1..].find('|')?;
+    let rest = &content[params_end + 1..];
+    let body_start = params_end + 1 + (rest.len() - rest.trim_start().len());
+    if content[body_start..].starts_with("->") {
+        let open = body_start + content[body_start..].find('{')?;
+        return Some((open, find_matching_brace(content, open)?));
+    }
+    if content[body_start..].starts_with('{') {
+        return Some((body_start, find_matching_brace(content, body_start)?));
+    }
+
+    let mut depth = 0usize;
+    for (idx, c) in content[body_start..].char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return Some((body_start, body_start + idx)),
+            ')' | ']' | '}' => depth -= 1,
+            ',' | ';' if depth == 0 => return Some((body_start, body_start + idx)),
+            _ => {}
+        }
+    }
+    Some((body_start, content.len()))
+}
+
+/// Calls `find_blocking_in_async` flags by default, with the reason each
+/// blocks the executor thread.
+const BLOCKING_PATTERNS: &[(&str, &str)] = &[
+    (
+        "std::fs",
+        "synchronous file I/O; use tokio::fs or spawn_blocking",
+    ),
+    (
+        "std::fs::File::open",
+        "synchronous file I/O; use tokio::fs::File",
+    ),
+    (
+        "std::fs::File::create",
+        "synchronous file I/O; use tokio::fs::File",
+    ),
+    (
+        "std::thread::sleep",
+        "parks the executor thread; use tokio::time::sleep",
+    ),
+    (
+        "std::io::stdin",
+        "blocking read from stdin; use tokio::io::stdin",
+    ),
+    (
+        "std::net::TcpStream::connect",
+        "blocking socket; use tokio::net::TcpStream",
+    ),
+    (
+        "std::net::TcpListener::bind",
+        "blocking socket; use tokio::net::TcpListener",
+    ),
+    (
+        "std::net::UdpSocket::bind",
+        "blocking socket; use tokio::net::UdpSocket",
+    ),
+    (
+        "std::process::Command",
+        "waits for the child synchronously; use tokio::process::Command",
+    ),
+    (
+        "reqwest::blocking",
+        "blocking HTTP client; use the async reqwest::Client",
+    ),
+    (
+        "futures::executor::block_on",
+        "blocks on a future inside a future",
+    ),
+    (
+        ".lock()",
+        "std::sync::Mutex::lock blocks while contended; use tokio::sync::Mutex or keep the guard short",
+    ),
+    (
+        ".recv()",
+        "std::sync::mpsc::Receiver::recv blocks; use tokio::sync::mpsc",
+    ),
+    (
+        ".block_on()",
+        "blocks on a future inside a future; panics within a Tokio runtime",
+    ),
+    (
+        ".blocking_lock()",
+        "Tokio's blocking lock panics inside async code",
+    ),
+    (
+        ".blocking_recv()",
+        "Tokio's blocking receive panics inside async code",
+    ),
+    (
+        ".blocking_send()",
+        "Tokio's blocking send panics inside async code",
+    ),
+];
+
+pub async fn find_blocking_in_async_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let strings = |key: &str| -> Vec<String> {
+        args.get(key)
+            .and_then(|v| v.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let ignore = strings("ignore");
+    let mut patterns: Vec<(String, String)> = BLOCKING_PATTERNS
+        .iter()
+        .filter(|(pattern, _)| !ignore.iter().any(|ignored| ignored == pattern))
+        .map(|(pattern, reason)| (pattern.to_string(), reason.to_string()))
+        .collect();
+    for pattern in strings("patterns") {
+        if !patterns.iter().any(|(known, _)| *known == pattern) {
+            patterns.push((pattern, "configured as blocking".to_string()));
+        }
+    }
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut findings = Vec::new();
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let contexts = scan_async_contexts(&content);
+        if contexts.is_empty() {
+            continue;
+        }
+        let lines: Vec<&str> = content.lines().collect();
+        let line_of = |offset: usize| content[..offset].matches('\n').count();
+        for (offset, (call, pattern, reason)) in blocking_matches(&content, &patterns) {
+            let Some(context) = contexts
+                .iter()
+                .filter(|context| context.body.0 < offset && offset < context.body.1)
+                .min_by_key(|context| context.body.1 - context.body.0)
+            else {
+                continue;
+            };
+            if inside_blocking_section(&content, context.body.0, offset) {
+                continue;
+            }
+            let line = line_of(offset);
+            findings.push(json!({
+                "file_path": file.to_string_lossy(),
+                "line": line + 1,
+                "code": lines.get(line).map(|text| text.trim()),
+                "call": call,
+                "pattern": pattern,
+                "reason": reason,
+                "context": {
+                    "kind": context.kind.as_str(),
+                    "name": context.name,
+                    "line": line_of(context.start) + 1
+                }
+            }));
+        }
+    }
+    findings.sort_by(|a, b| {
+        a["file_path"]
+            .as_str()
+            .cmp(&b["file_path"].as_str())
+            .then_with(|| a["line"].as_u64().cmp(&b["line"].as_u64()))
+    });
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "patterns": patterns.iter().map(|(pattern, _)| pattern).collect::<Vec<_>>(),
+        "findings": findings,
+        "heuristic": "Calls are matched by name in async fn bodies, async blocks and async closures, outside comments, strings and spawn_blocking / block_in_place calls. A path pattern also matches the shorter form its `use` imports (`fs::read` after `use std::fs`); a `.method()` pattern matches calls not followed by `.await`, whatever the receiver type, so `.lock()` on a non-std mutex is a false positive. Review each finding."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Calls in `content` matching any of `patterns`, by offset, with the
+/// matching pattern and its reason. A call several patterns match
+/// (`std::fs` and `std::fs::File::open`) is kept once, under the longest match.
+fn blocking_matches<'a>(
+    content: &str,
+    patterns: &'a [(String, String)],
+) -> BTreeMap<usize, (String, &'a str, &'a str)> {
+    let mut matches: BTreeMap<usize, (String, &str, &str)> = BTreeMap::new();
+    for (pattern, reason) in patterns {
+        for (offset, call) in blocking_calls(content, pattern) {
+            if matches
+                .get(&offset)
+                .is_some_and(|(known, _, _)| known.len() >= call.len())
+            {
+                continue;
+            }
+            matches.insert(offset, (call, pattern, reason));
+        }
+    }
+    matches
+}
+
+/// Offsets and text of the calls in `content` matching a blocking pattern.
+/// `.name()` matches method calls not awaited; a path matches itself and,
+/// when a `use` brings one of its segments into scope, the path from that
+/// segment on (`thread::sleep` or `sleep` for `std::thread::sleep`).
+fn blocking_calls(content: &str, pattern: &str) -> Vec<(usize, String)> {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut calls = Vec::new();
+
+    if let Some(method) = pattern
+        .strip_prefix('.')
+        .map(|method| method.trim_end_matches("()"))
+    {
+        let needle = format!(".{method}(");
+        for (idx, _) in content.match_indices(&needle) {
+            if !is_valid_code_context(content, idx) {
+                continue;
+            }
+            let open = idx + needle.len() - 1;
+            let awaited = find_matching_paren(&content[open..])
+                .map(|close| open + close)
+                .is_some_and(|close| content[close + 1..].trim_start().starts_with(".await"));
+            if !awaited {
+                calls.push((idx, format!(".{method}()")));
+            }
+        }
+        return calls;
+    }
+
+    let segments: Vec<&str> = pattern.split("::").collect();
+    let imports: Vec<&str> = content
+        .split(';')
+        .filter_map(|statement| {
+            let statement = statement.trim_start();
+            let statement = statement.strip_prefix("pub ").unwrap_or(statement);
+            statement.strip_prefix("use ")
+        })
+        .collect();
+    // `use std::thread;` or `use std::{fs, thread};` names every segment
+    // up to the one the shorter form starts with.
+    let imported = |from: usize| {
+        imports.iter().any(|import| {
+            let words: Vec<&str> = import
+                .split(|c: char| !is_ident_char(c))
+                .filter(|word| !word.is_empty())
+                .collect();
+            words.first() == Some(&segments[0])
+                && segments[1..=from]
+                    .iter()
+                    .all(|segment| words.contains(segment))
+        })
+    };
+
+    for from in 0..segments.len() {
+        if from > 0 && !imported(from) {
+            continue;
+        }
+        let suffix = segments[from..].join("::");
+        for (idx, _) in content.match_indices(&suffix) {
+            let before = &content[..idx];
+            let after = &content[idx + suffix.len()..];
+            if before.ends_with(is_ident_char)
+                || before.ends_with("::")
+                || !(after.starts_with('(') || after.starts_with("::"))
+                || in_use_statement(content, idx)
+                || calls.iter().any(|(known, _)| *known == idx)
+                || !is_valid_code_context(content, idx)
+            {
+                continue;
+            }
+            calls.push((idx, suffix.clone()));
+        }
+    }
+    calls
+}
+
+/// Whether `idx` is inside a `use` item, including a `{..}` group of one.
+fn in_use_statement(content: &str, idx: usize) -> bool {
+    let mut end = idx;
+    let start = loop {
+        match content[..end].rfind([';', '{', '}']) {
+            // The brace of a use group (`std::{fs, io}`) does not start a statement.
+            Some(at) if content[..at].ends_with("::") => end = at,
+            Some(at) => break at + 1,
+            None => break 0,
+        }
+    };
+    let statement = content[start..idx].trim_start();
+    statement
+        .strip_prefix("pub ")
+        .unwrap_or(statement)
+        .starts_with("use ")
+}
+
+/// Whether `offset` lies in the arguments of a `spawn_blocking(..)` or
+/// `block_in_place(..)` call opened after `from`.
+fn inside_blocking_section(content: &str, from: usize, offset: usize) -> bool {
+    ["spawn_blocking(", "block_in_place("].iter().any(|call| {
+        content[from..offset].match_indices(call).any(|(idx, _)| {
+            let open = from + idx + call.len() - 1;
+            find_matching_paren(&content[open..])
+                .map(|close| open + close)
+                .is_none_or(|close| close > offset)
+        })
+    })
+}
+
+/// Index of the innermost `async fn` whose body contains `context`.
+fn nearest_async_fn(contexts: &[AsyncContext], context: &AsyncContext) -> Option<usize> {
+    contexts
+        .iter()
+        .enumerate()
+        .filter(|(_, outer)| outer.kind == AsyncKind::Function)
+        .filter(|(_, outer)| outer.body.0 < context.start && context.body.1 < outer.body.1)
+        .min_by_key(|(_, outer)| outer.body.1 - outer.body.0)
+        .map(|(idx, _)| idx)
+}
+
+/// Categories of `find_panics` and what each one matches.
+const PANIC_CATEGORIES: &[(&str, &str)] = &[
+    ("panic", "panic!"),
+    ("unwrap", ".unwrap()"),
+    ("expect", ".expect(..)"),
+    ("unreachable", "unreachable!"),
+    ("todo", "todo!"),
+    ("unimplemented", "unimplemented!"),
+    ("index", "indexing or slicing with `[..]`"),
+];
+
+const DEFAULT_PANIC_LIMIT: usize = 500;
+
+pub async fn find_panics_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let categories: Vec<&str> = match args.get("categories").and_then(|v| v.as_array()) {
+        Some(values) => values.iter().filter_map(|v| v.as_str()).collect(),
+        None => PANIC_CATEGORIES.iter().map(|(name, _)| *name).collect(),
+    };
+    if let Some(unknown) = categories
+        .iter()
+        .find(|category| !PANIC_CATEGORIES.iter().any(|(name, _)| name == *category))
+    {
+        return Err(anyhow::anyhow!(
+            "Unknown category `{}`; expected one of {}",
+            unknown,
+            PANIC_CATEGORIES
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let include_tests = args
+        .get("include_tests")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_PANIC_LIMIT, |v| v as usize);
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut findings = Vec::new();
+    let mut skipped_test_files = 0;
+    for file in &files {
+        // Integration tests and benches only build for `cargo test` / `cargo bench`.
+        let test_target = file
+            .components()
+            .any(|part| matches!(part.as_os_str().to_str(), Some("tests" | "benches")))
+            || file.file_stem().is_some_and(|stem| stem == "tests");
+        if test_target && !include_tests {
+            skipped_test_files += 1;
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        let test_spans = if include_tests {
+            Vec::new()
+        } else {
+            test_code_spans(&content)
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        for site in scan_panic_sites(&content) {
+            if !categories.contains(&site.category)
+                || test_spans
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&site.offset))
+            {
+                continue;
+            }
+            let line = content[..site.offset].matches('\n').count();
+            findings.push(json!({
+                "category": site.category,
+                "call": site.call,
+                "file_path": file.to_string_lossy(),
+                "line": line + 1,
+                "context": lines.get(line).map(|text| text.trim())
+            }));
+        }
+    }
+
+    let mut by_category = serde_json::Map::new();
+    for category in &categories {
+        let count = findings
+            .iter()
+            .filter(|finding| finding["category"] == *category)
+            .count();
+        by_category.insert(category.to_string(), json!(count));
+    }
+    let total = findings.len();
+    findings.truncate(limit);
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len() - skipped_test_files,
+        "include_tests": include_tests,
+        "total": total,
+        "by_category": by_category,
+        "truncated": total > limit,
+        "findings": findings,
+        "heuristic": "Sites are matched by name outside comments and strings, so `.unwrap()` / `.expect(..)` on any type are reported, and indexing is recognized as `[` right after an identifier, `)` or `]`. Panics inside called functions and overflow or division by zero are not covered. Without include_tests, `#[cfg(test)]` items, `#[test]` functions and files under tests/ or benches/ are skipped."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct PanicSite {
+    category: &'static str,
+    offset: usize,
+    /// The call as written, arguments shortened.
+    call: String,
+}
+
+/// Finds the explicit panic sources in `content`, in source order, skipping
+/// comments and string literals.
+fn scan_panic_sites(content: &str) -> Vec<PanicSite> {
+    const MACROS: [(&str, &str); 4] = [
+        ("panic", "panic!"),
+        ("unreachable", "unreachable!"),
+        ("todo", "todo!"),
+        ("unimplemented", "unimplemented!"),
+    ];
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let arguments = |open: usize| {
+        let close = open + find_matching_paren(&content[open..])?;
+        let text = content[open + 1..close]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(match text.char_indices().nth(60) {
+            Some((cut, _)) => format!("{}...", &text[..cut]),
+            None => text,
+        })
+    };
+    // One tokenizer pass instead of rescanning the file for every match.
+    let tokens = tokenize(content);
+    let in_code = |idx: usize| {
+        tokens
+            .get(tokens.partition_point(|token| token.end <= idx))
+            .is_some_and(|token| {
+                !matches!(token.kind, "comment" | "doc_comment" | "string" | "char")
+            })
+    };
+
+    let mut sites = Vec::new();
+    for (category, name) in MACROS {
+        let needle = format!("{name}(");
+        for (idx, _) in content.match_indices(&needle) {
+            if content[..idx].ends_with(is_ident_char) || !in_code(idx) {
+                continue;
+            }
+            let args = arguments(idx + needle.len() - 1).unwrap_or_default();
+            sites.push(PanicSite {
+                category,
+                offset: idx,
+                call: format!("{name}({args})"),
+            });
+        }
+    }
+    for (idx, _) in content.match_indices(".unwrap()") {
+        if in_code(idx) {
+            sites.push(PanicSite {
+                category: "unwrap",
+                offset: idx,
+                call: ".unwrap()".to_string(),
+            });
+        }
+    }
+    for (idx, _) in content.match_indices(".expect(") {
+        if in_code(idx) {
+            let args = arguments(idx + ".expect".len()).unwrap_or_default();
+            sites.push(PanicSite {
+                category: "expect",
+                offset: idx,
+                call: format!(".expect({args})"),
+            });
+        }
+    }
+    for (idx, _) in content.match_indices('[') {
+        let before = content[..idx].chars().next_back();
+        let indexes = before.is_some_and(|c| is_ident_char(c) || c == ')' || c == ']');
+        if !indexes || !in_code(idx) {
+            continue;
+        }
+        let Some(close) = content[idx..].find(']').map(|offset| idx + offset) else {
+            continue;
+        };
+        let index = content[idx + 1..close].trim();
+        // `x[..]` takes the whole slice and cannot fail.
+        if index == ".." || index.contains(['[', '\n']) {
+            continue;
+        }
+        let receiver_start = content[..idx]
+            .rfind(|c: char| !(is_ident_char(c) || c == '.'))
+            .map_or(0, |pos| pos + 1);
+        sites.push(PanicSite {
+            category: "index",
+            offset: idx,
+            call: format!("{}[{}]", &content[receiver_start..idx], index),
+        });
+    }
+
+    sites.sort_by_key(|site| site.offset);
+    sites
+}
+
+/// Macros whose first argument (after the destination for `write!`) is a
+/// format string.
+const FORMAT_MACROS: &[&str] = &[
+    "format",
+    "format_args",
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "write",
+    "writeln",
+    "panic",
+];
+
+const DEFAULT_FORMAT_ISSUE_LIMIT: usize = 200;
+
+pub async fn check_format_args_impl(
+    args: Value,
+    _analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let limit = args
+        .get("limit")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_FORMAT_ISSUE_LIMIT, |v| v as usize);
+
+    let files = collect_rust_files(Path::new(path)).await?;
+    let mut issues = Vec::new();
+    let mut calls_checked = 0;
+    for file in &files {
+        let Ok(content) = fs::read_to_string(file).await else {
+            continue;
+        };
+        for call in scan_format_calls(&content) {
+            calls_checked += 1;
+            let line = content[..call.offset].matches('\n').count();
+            let format = match call.format.char_indices().nth(80) {
+                Some((cut, _)) => format!("{}...", &call.format[..cut]),
+                None => call.format.clone(),
+            };
+            for issue in format_issues(&call) {
+                issues.push(json!({
+                    "file_path": file.to_string_lossy(),
+                    "line": line + 1,
+                    "macro": format!("{}!", call.name),
+                    "format_string": format,
+                    "arguments": call.arguments.len(),
+                    "kind": issue.kind,
+                    "message": issue.message
+                }));
+            }
+        }
+    }
+
+    let total = issues.len();
+    issues.truncate(limit);
+
+    let result = json!({
+        "path": path,
+        "files_scanned": files.len(),
+        "calls_checked": calls_checked,
+        "total": total,
+        "truncated": total > limit,
+        "issues": issues,
+        "heuristic": "Only calls whose format string is a plain string literal are checked; `concat!` and macro-built strings are skipped, as are macros other than format!, format_args!, print!, println!, eprint!, eprintln!, write!, writeln! and panic!. `{name}` without a matching `name = ..` argument is taken as an inline capture of a variable in scope, which is not verified."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// A format macro invocation with a literal format string.
+#[derive(Debug)]
+struct FormatCall {
+    name: String,
+    offset: usize,
+    /// The format string literal as written, quotes included.
+    format: String,
+    arguments: Vec<FormatArgument>,
+}
+
+#[derive(Debug)]
+struct FormatArgument {
+    /// `name` of a `name = value` argument.
+    name: Option<String>,
+    text: String,
+}
+
+/// Finds the format macro calls in `content` whose format string is a
+/// literal, with the arguments that follow it.
+fn scan_format_calls(content: &str) -> Vec<FormatCall> {
+    let tokens: Vec<Token> = tokenize(content)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, "whitespace" | "comment" | "doc_comment"))
+        .collect();
+    let text = |token: &Token| &content[token.start..token.end];
+
+    let mut calls = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        let name = text(token);
+        if token.kind != "identifier"
+            || !FORMAT_MACROS.contains(&name)
+            || tokens.get(idx + 1).map(text) != Some("!")
+            || !tokens
+                .get(idx + 2)
+                .is_some_and(|open| matches!(text(open), "(" | "[" | "{"))
+        {
+            continue;
+        }
+
+        // Token indices of each top-level argument.
+        let mut groups: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut depth = 0;
+        let mut closed = false;
+        for (pos, inner) in tokens.iter().enumerate().skip(idx + 2) {
+            match (inner.kind, text(inner)) {
+                ("punctuation", "(" | "[" | "{") => {
+                    depth += 1;
+                    if depth == 1 {
+                        continue;
+                    }
+                }
+                ("punctuation", ")" | "]" | "}") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        closed = true;
+                        break;
+                    }
+                }
+                ("punctuation", ",") if depth == 1 => {
+                    groups.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+            groups.last_mut().unwrap().push(pos);
+        }
+        if !closed {
+            continue;
+        }
+        if groups.last().is_some_and(Vec::is_empty) {
+            groups.pop();
+        }
+
+        let skip = usize::from(matches!(name, "write" | "writeln"));
+        let Some(format) = groups.get(skip) else {
+            continue;
+        };
+        let [format] = format[..] else {
+            continue;
+        };
+        let format = &tokens[format];
+        if format.kind != "string" || text(format).starts_with('b') {
+            continue;
+        }
+
+        let arguments = groups[skip + 1..]
+            .iter()
+            .filter_map(|group| {
+                let first = tokens[*group.first()?].start;
+                let last = tokens[*group.last()?].end;
+                let named = group.len() >= 2
+                    && tokens[group[0]].kind == "identifier"
+                    && text(&tokens[group[1]]) == "=";
+                Some(FormatArgument {
+                    name: named.then(|| text(&tokens[group[0]]).to_string()),
+                    text: content[first..last]
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                })
+            })
+            .collect();
+        calls.push(FormatCall {
+            name: name.to_string(),
+            offset: token.start,
+            format: text(format).to_string(),
+            arguments,
+        });
+    }
+    calls
+}
+
+/// What a placeholder refers to: `{}`, `{0}` or `{name}`.
+#[derive(Debug, PartialEq)]
+enum FormatReference {
+    Next,
+    Index(usize),
+    Name(String),
+}
+
+/// The argument references of a format string literal (quotes included), in
+/// order, counting `width$` / `.precision$` / `.*` in format specs.
+fn format_references(literal: &str) -> std::result::Result<Vec<FormatReference>, String> {
+    let literal = literal.strip_prefix('c').unwrap_or(literal);
+    let raw = literal.starts_with('r');
+    let body = literal.trim_start_matches('r');
+    let hashes = body.len() - body.trim_start_matches('#').len();
+    let body = body[hashes..]
+        .strip_prefix('"')
+        .and_then(|body| body.strip_suffix(&"#".repeat(hashes)))
+        .and_then(|body| body.strip_suffix('"'))
+        .ok_or_else(|| "unterminated format string".to_string())?;
+
+    let reference = |arg: &str| -> std::result::Result<FormatReference, String> {
+        let arg = arg.trim();
+        if arg.is_empty() {
+            Ok(FormatReference::Next)
+        } else if let Ok(index) = arg.parse() {
+            Ok(FormatReference::Index(index))
+        } else if arg.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && arg.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            Ok(FormatReference::Name(arg.to_string()))
+        } else {
+            Err(format!("invalid argument reference `{arg}`"))
+        }
+    };
+
+    let mut references = Vec::new();
+    let mut chars = body.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if !raw => {
+                let escaped = chars.next();
+                // `\u{..}` braces are not placeholders.
+                if escaped.is_some_and(|(_, c)| c == 'u') {
+                    for (_, c) in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+            '{' if chars.peek().is_some_and(|(_, c)| *c == '{') => {
+                chars.next();
+            }
+            '}' if chars.peek().is_some_and(|(_, c)| *c == '}') => {
+                chars.next();
+            }
+            '}' => return Err("unmatched `}`; write `}}` for a literal brace".to_string()),
+            '{' => {
+                let close = body[idx..]
+                    .find('}')
+                    .map(|offset| idx + offset)
+                    .ok_or_else(|| {
+                        "unterminated `{`; write `{{` for a literal brace".to_string()
+                    })?;
+                let inner = &body[idx + 1..close];
+                let (arg, spec) = inner.split_once(':').unwrap_or((inner, ""));
+                for (dollar, _) in spec.match_indices('$') {
+                    let start = spec[..dollar]
+                        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .map_or(0, |pos| pos + 1);
+                    references.push(reference(&spec[start..dollar])?);
+                }
+                // `.*` takes the precision from the next positional argument.
+                if spec.contains(".*") {
+                    references.push(FormatReference::Next);
+                }
+                references.push(reference(arg)?);
+                while chars.peek().is_some_and(|(pos, _)| *pos <= close) {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(references)
+}
+
+#[derive(Debug, PartialEq)]
+struct FormatIssue {
+    kind: &'static str,
+    message: String,
+}
+
+/// Checks the placeholders of `call` against its arguments the way rustc
+/// does: every placeholder needs an argument and every argument must be used.
+fn format_issues(call: &FormatCall) -> Vec<FormatIssue> {
+    let references = match format_references(&call.format) {
+        Ok(references) => references,
+        Err(message) => {
+            return vec![FormatIssue {
+                kind: "invalid_format_string",
+                message,
+            }];
+        }
+    };
+
+    let arguments = &call.arguments;
+    let mut used = vec![false; arguments.len()];
+    let mut next = 0;
+    let mut needed = 0;
+    for reference in &references {
+        let index = match reference {
+            FormatReference::Next => {
+                next += 1;
+                next - 1
+            }
+            FormatReference::Index(index) => *index,
+            FormatReference::Name(name) => {
+                // Otherwise an inline capture of a variable in scope.
+                if let Some(pos) = arguments
+                    .iter()
+                    .position(|argument| argument.name.as_deref() == Some(name))
+                {
+                    used[pos] = true;
+                }
+                continue;
+            }
+        };
+        needed = needed.max(index + 1);
+        if let Some(slot) = used.get_mut(index) {
+            *slot = true;
+        }
+    }
+
+    let mut issues = Vec::new();
+    if needed > arguments.len() {
+        issues.push(FormatIssue {
+            kind: "missing_argument",
+            message: format!(
+                "The format string refers to {} positional argument{} but {} {} given.",
+                needed,
+                if needed == 1 { "" } else { "s" },
+                arguments.len(),
+                if arguments.len() == 1 { "is" } else { "are" }
+            ),
+        });
+    }
+    for (argument, used) in arguments.iter().zip(used) {
+        if used {
+            continue;
+        }
+        issues.push(match &argument.name {
+            Some(name) => FormatIssue {
+                kind: "unused_argument",
+                message: format!("Named argument `{name}` is never used by the format string."),
+            },
+            None => FormatIssue {
+                kind: "unused_argument",
+                message: format!(
+                    "Argument `{}` is never used by the format string.",
+                    argument.text
+                ),
+            },
+        });
+    }
+    issues
+}
+
+/// LSP `SymbolKind::Method` and `SymbolKind::Function`.
+fn is_function_kind(kind: u32) -> bool {
+    matches!(kind, 6 | 12)
+}
+
+/// Every function and method in `symbols` with the name of the impl, trait
+/// or module it is declared in.
+fn collect_functions<'a>(
+    symbols: &'a [DocumentSymbol],
+    container: Option<&'a str>,
+    found: &mut Vec<(&'a DocumentSymbol, Option<&'a str>)>,
+) {
+    for symbol in symbols {
+        if is_function_kind(symbol.kind) {
+            found.push((symbol, container));
+        }
+        let children = symbol.children.as_deref().unwrap_or_default();
+        collect_functions(children, Some(&symbol.name), found);
+    }
+}
+
+/// Branch points counted in a function's source.
+#[derive(Debug, Default, PartialEq)]
+struct BranchCounts {
+    ifs: usize,
+    /// Arms beyond the first of each `match`.
+    match_arms: usize,
+    boolean_operators: usize,
+    loops: usize,
+    try_operators: usize,
+}
+
+impl BranchCounts {
+    fn of(source: &str) -> Self {
+        let mut counts = Self::default();
+        let mut matches = 0;
+        let mut arms = 0;
+        let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+        // An operator follows an operand; `&&x` and `|| body` start one.
+        let ends_operand = |before: &str| {
+            before
+                .trim_end()
+                .chars()
+                .next_back()
+                .is_some_and(|c| is_ident_char(c) || matches!(c, ')' | ']' | '?'))
+        };
+
+        for (idx, c) in source.char_indices() {
+            let before = &source[..idx];
+            let rest = &source[idx..];
+            let counted = if is_ident_char(c)
+                && !before.chars().next_back().is_some_and(is_ident_char)
+            {
+                let word: String = rest.chars().take_while(|c| is_ident_char(*c)).collect();
+                match word.as_str() {
+                    "if" => Some(&mut counts.ifs),
+                    "while" | "loop" => Some(&mut counts.loops),
+                    // `for<'a>` is a higher-ranked bound, not a loop.
+                    "for" if !rest[3..].trim_start().starts_with('<') => Some(&mut counts.loops),
+                    "match" => Some(&mut matches),
+                    _ => None,
+                }
+            } else if rest.starts_with("=>") {
+                Some(&mut arms)
+            } else if (rest.starts_with("&&") || rest.starts_with("||")) && ends_operand(before) {
+                Some(&mut counts.boolean_operators)
+            } else if c == '?'
+                && before
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| is_ident_char(c) || matches!(c, ')' | ']' | '?'))
+            {
+                Some(&mut counts.try_operators)
+            } else {
+                None
+            };
+
+            if let Some(count) = counted.filter(|_| is_valid_code_context(source, idx)) {
+                *count += 1;
+            }
+        }
+
+        counts.match_arms = arms.saturating_sub(matches);
+        counts
+    }
+
+    fn complexity(&self) -> usize {
+        1 + self.ifs + self.match_arms + self.boolean_operators + self.loops + self.try_operators
+    }
+}
+
+/// Where a `dyn Trait` or `impl Trait` type is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypePosition {
+    Argument,
+    Return,
+    Field,
+    Local,
+    TypeAlias,
+    Static,
+    Other,
+}
+
+impl TypePosition {
+    fn as_str(self) -> &'static str {
+        match self {
+            TypePosition::Argument => "argument",
+            TypePosition::Return => "return",
+            TypePosition::Field => "field",
+            TypePosition::Local => "local",
+            TypePosition::TypeAlias => "type_alias",
+            TypePosition::Static => "static",
+            TypePosition::Other => "other",
+        }
+    }
+}
+
+/// A `dyn Trait` or `impl Trait` type found by `scan_dyn_usages`.
+#[derive(Debug, PartialEq)]
+struct DynUsage {
+    /// `dyn` or `impl`.
+    keyword: &'static str,
+    /// The bounds after the keyword, e.g. `Fn(u8) -> bool + Send`.
+    bounds: String,
+    /// What holds a `dyn` type: `Box`, `Arc`, `&`, `&mut`, `*const`, ...
+    wrapper: Option<String>,
+    position: TypePosition,
+    /// 0-based line.
+    line: usize,
+}
+
+/// Finds `dyn Trait` types and `impl Trait` types (not `impl` blocks) outside
+/// comments and strings.
+fn scan_dyn_usages(content: &str) -> Vec<DynUsage> {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut usages = Vec::new();
+    for (idx, _) in content.match_indices(['d', 'i']) {
+        let rest = &content[idx..];
+        let keyword = if rest.starts_with("dyn") {
+            "dyn"
+        } else if rest.starts_with("impl") {
+            "impl"
+        } else {
+            continue;
+        };
+        let after = &rest[keyword.len()..];
+        if content[..idx]
+            .chars()
+            .next_back()
+            .is_some_and(is_ident_char)
+            || !after.starts_with(char::is_whitespace)
+        {
+            continue;
+        }
+        // `impl` opens an item unless it follows something that expects a type.
+        let before = content[..idx].trim_end();
+        if keyword == "impl"
+            && !(before.ends_with([':', '>', '(', ',', '<', '&', '=', '['])
+                || before.ends_with("mut")
+                || before.ends_with("dyn"))
+        {
+            continue;
+        }
+        if !is_valid_code_context(content, idx) {
+            continue;
+        }
+
+        usages.push(DynUsage {
+            keyword,
+            bounds: type_bounds(after.trim_start()),
+            wrapper: (keyword == "dyn").then(|| type_wrapper(before)).flatten(),
+            position: type_position(content, idx),
+            line: content[..idx].matches('\n').count(),
+        });
+    }
+    usages
+}
+
+/// The bounds of a `dyn`/`impl` type: everything up to the first `,`, `)`,
+/// `>`, `;`, `{`, `=` or `where` outside nested brackets.
+fn type_bounds(text: &str) -> String {
+    let mut depth = 0usize;
+    let mut end = text.len();
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '-' if chars.peek().is_some_and(|(_, next)| *next == '>') => {
+                chars.next();
+            }
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' if depth > 0 => depth -= 1,
+            '>' | ')' | ']' | ',' | ';' | '{' | '=' if depth == 0 => {
+                end = idx;
+                break;
+            }
+            'w' if depth == 0
+                && text[idx..].starts_with("where")
+                && text[..idx].ends_with(char::is_whitespace) =>
+            {
+                end = idx;
+                break;
+            }
+            _ => {}
+        }
+    }
+    text[..end].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The pointer or smart pointer around a `dyn` type, from the text before it.
+fn type_wrapper(before: &str) -> Option<String> {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    if let Some(generic) = before.strip_suffix('<') {
+        let generic = generic.trim_end();
+        let name_start = generic
+            .rfind(|c: char| !is_ident_char(c))
+            .map_or(0, |i| i + 1);
+        return (name_start < generic.len()).then(|| generic[name_start..].to_string());
+    }
+    if before.ends_with("*const") {
+        return Some("*const".to_string());
+    }
+    let (rest, mutable) = match before.strip_suffix("mut") {
+        Some(rest) if !rest.ends_with(is_ident_char) => (rest.trim_end(), true),
+        _ => (before, false),
+    };
+    if rest.ends_with('*') {
+        return Some("*mut".to_string());
+    }
+    // Skip a lifetime: `&'a dyn Trait`.
+    let rest = match rest.rfind('\'') {
+        Some(quote) if rest[quote + 1..].chars().all(is_ident_char) => rest[..quote].trim_end(),
+        _ => rest,
+    };
+    rest.ends_with('&')
+        .then(|| if mutable { "&mut" } else { "&" }.to_string())
+}
+
+/// Classifies the type at `idx` by walking back to the parameter list or
+/// return arrow it belongs to, or else the start of its statement.
+fn type_position(content: &str, idx: usize) -> TypePosition {
+    let bytes = content.as_bytes();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut i = idx;
+    while i > 0 {
+        i -= 1;
+        match bytes[i] {
+            b'>' if i > 0 && bytes[i - 1] == b'-' => {
+                if depth == 0 {
+                    return TypePosition::Return;
+                }
+                i -= 1;
+            }
+            b')' | b']' | b'>' => depth += 1,
+            b'(' | b'[' | b'<' if depth > 0 => depth -= 1,
+            b'(' if is_fn_parameter_list(&content[..i]) => return TypePosition::Argument,
+            b';' | b'{' | b'}' if depth == 0 => {
+                start = i + 1;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let statement = strip_visibility(content[start..idx].trim_start());
+    let starts_with_word = |word: &str| {
+        statement
+            .strip_prefix(word)
+            .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+    };
+    if starts_with_word("let") {
+        TypePosition::Local
+    } else if starts_with_word("type") {
+        TypePosition::TypeAlias
+    } else if starts_with_word("static") || starts_with_word("const") {
+        TypePosition::Static
+    } else if ["struct", "enum", "union"]
+        .iter()
+        .any(|w| starts_with_word(w))
+        || enclosing_block_is_type(&content[..start])
+    {
+        TypePosition::Field
+    } else {
+        TypePosition::Other
+    }
+}
+
+/// Whether `before` (the text up to an opening parenthesis) ends with
+/// `fn name` or `fn name<...>`.
+fn is_fn_parameter_list(before: &str) -> bool {
+    let mut before = before.trim_end();
+    if before.ends_with('>') {
+        let mut depth = 0usize;
+        for (idx, c) in before.char_indices().rev() {
+            match c {
+                '>' => depth += 1,
+                '<' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        before = before[..idx].trim_end();
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let name_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    name_start < before.len()
+        && before[..name_start]
+            .trim_end()
+            .strip_suffix("fn")
+            .is_some_and(|rest| !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// Whether the innermost unclosed `{` in `before` opens a struct, enum or
+/// union body.
+fn enclosing_block_is_type(before: &str) -> bool {
+    let mut depth = 0usize;
+    for (idx, c) in before.char_indices().rev() {
+        match c {
+            '}' => depth += 1,
+            '{' if depth > 0 => depth -= 1,
+            '{' => {
+                let header_start = before[..idx].rfind([';', '{', '}']).map_or(0, |i| i + 1);
+                return before[header_start..idx]
+                    .split_whitespace()
+                    .any(|word| matches!(word, "struct" | "enum" | "union"));
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+}
+
+impl DependencyKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Dev => "dev",
+            DependencyKind::Build => "build",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct DeclaredDependency {
+    /// The key in Cargo.toml, which is also the crate name seen by code.
+    pub(crate) name: String,
+    /// `name` as a Rust identifier (`-` becomes `_`).
+    pub(crate) ident: String,
+    kind: DependencyKind,
+    pub(crate) optional: bool,
+    /// The `cfg(..)` or triple of a `[target.*.dependencies]` table.
+    target: Option<String>,
+}
+
+pub(crate) fn declared_dependencies(manifest: &toml::Table) -> Vec<DeclaredDependency> {
+    const TABLES: [(&str, DependencyKind); 3] = [
+        ("dependencies", DependencyKind::Normal),
+        ("dev-dependencies", DependencyKind::Dev),
+        ("build-dependencies", DependencyKind::Build),
+    ];
+
+    let mut scopes = vec![(None, manifest)];
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        scopes.extend(
+            targets
+                .iter()
+                .filter_map(|(target, table)| Some((Some(target.clone()), table.as_table()?))),
+        );
+    }
+
+    let mut dependencies = Vec::new();
+    for (target, scope) in scopes {
+        for (table, kind) in TABLES {
+            let Some(entries) = scope.get(table).and_then(|t| t.as_table()) else {
+                continue;
+            };
+            for (name, spec) in entries {
+                dependencies.push(DeclaredDependency {
+                    name: name.clone(),
+                    ident: name.replace('-', "_"),
+                    kind,
+                    optional: spec
+                        .get("optional")
+                        .and_then(|optional| optional.as_bool())
+                        .unwrap_or(false),
+                    target: target.clone(),
+                });
+            }
+        }
+    }
+    dependencies
+}
+
+/// Whether `content` refers to the crate `ident`: as the root of a path
+/// (`ident::`, `::ident::`), in `use ident` or in `extern crate ident`.
+/// Comments and string literals are skipped.
+fn references_crate(content: &str, ident: &str) -> bool {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    content.match_indices(ident).any(|(idx, _)| {
+        let before = &content[..idx];
+        let after = &content[idx + ident.len()..];
+        if before.chars().next_back().is_some_and(is_ident_char)
+            || after.chars().next().is_some_and(is_ident_char)
+        {
+            return false;
+        }
+
+        let prefix = before.trim_end();
+        let path_root = after.trim_start().starts_with("::")
+            && !prefix.strip_suffix("::").is_some_and(|rest| {
+                rest.trim_end()
+                    .chars()
+                    .next_back()
+                    .is_some_and(is_ident_char)
+            });
+        let imported = prefix.ends_with("use") || prefix.ends_with("extern crate");
+        (path_root || imported) && is_valid_code_context(content, idx)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_issues() {
+        let content = r##"fn f(out: &mut String, name: &str, w: usize) {
+    println!("{} and {}", name);
+    println!("{name} {0:>w$}", 1, w = w);
+    write!(out, "{}", name, w).unwrap();
+    format!("{{literal}} {:.*} \u{7b}", 2, 1.5);
+    let s = format!(r#"{x}" {"#);
+    eprintln!("{1}", a, b);
+    // println!("{}");
+}
+"##;
+        let calls = scan_format_calls(content);
+        assert_eq!(calls.len(), 6);
+        let kinds: Vec<Vec<&str>> = calls
+            .iter()
+            .map(|call| format_issues(call).iter().map(|issue| issue.kind).collect())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                vec!["missing_argument"],
+                vec![],
+                vec!["unused_argument"],
+                vec![],
+                vec!["invalid_format_string"],
+                vec!["unused_argument"],
+            ]
+        );
+        assert_eq!(calls[2].arguments.len(), 2);
+        assert_eq!(calls[1].arguments[1].name.as_deref(), Some("w"));
+        assert_eq!(
+            format_issues(&calls[5])[0].message,
+            "Argument `a` is never used by the format string."
+        );
+        assert_eq!(
+            format_references("\"{:w$.p$} {}\""),
+            Ok(vec![
+                FormatReference::Name("w".to_string()),
+                FormatReference::Name("p".to_string()),
+                FormatReference::Next,
+                FormatReference::Next,
+            ])
+        );
+    }
+
+    #[test]
+    fn test_scan_panic_sites() {
+        let content = r#"fn load(items: &[u8], map: &Map) -> u8 {
+    // items[0].unwrap() in a comment
+    let first = items[0];
+    let all = &items[..];
+    let cfg = parse().expect("config must parse");
+    let _ = "todo!()";
+    let v: Vec<[u8; 2]> = vec![[1, 2]];
+    if first == 0 { unreachable!("checked above") }
+    map.get(1).unwrap().rows()[2]
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn it_works() { todo!() }
+}
+"#;
+        let sites: Vec<_> = scan_panic_sites(content)
+            .into_iter()
+            .map(|site| (site.category, site.call))
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                ("index", "items[0]".to_string()),
+                ("expect", ".expect(\"config must parse\")".to_string()),
+                ("unreachable", "unreachable!(\"checked above\")".to_string()),
+                ("unwrap", ".unwrap()".to_string()),
+                ("index", "[2]".to_string()),
+                ("todo", "todo!()".to_string()),
+            ]
+        );
+
+        let spans = test_code_spans(content);
+        let todo = content.rfind("todo!()").unwrap();
+        assert!(
+            spans
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&todo))
+        );
+        let unwrap = content.find(".unwrap()").unwrap();
+        assert!(
+            !spans
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&unwrap))
+        );
+    }
+
+    #[test]
+    fn test_must_use_kind() {
+        let kind = |signature: &str| {
+            must_use_kind(&parse_hover_signature(&format!("```rust\n{signature}\n```")).unwrap())
+        };
+        assert_eq!(
+            kind("pub fn load(path: &Path) -> io::Result<Config>"),
+            Some("result")
+        );
+        assert_eq!(
+            kind("pub fn get(&self, key: &str) -> Option<&V>"),
+            Some("option")
+        );
+        assert_eq!(kind("pub fn pop(&mut self) -> Option<T>"), None);
+        assert_eq!(
+            kind("pub fn timeout(mut self, secs: u64) -> Self"),
+            Some("builder")
+        );
+        assert_eq!(kind("pub fn new() -> Self"), None);
+        assert_eq!(kind("pub fn len(&self) -> usize"), None);
+
+        let lines = [
+            "/// Loads it.",
+            "#[must_use]",
+            "#[inline]",
+            "pub fn load() -> Option<u32> {",
+            "}",
+            "pub fn other() -> Option<u32> {",
+        ];
+        assert!(has_must_use(&lines, 3));
+        assert!(!has_must_use(&lines, 5));
+    }
+
+    #[test]
+    fn test_blocking_calls() {
+        let content = r#"use std::{fs, thread};
+use tokio::sync::Mutex;
+
+async fn load(state: &Mutex<u32>) {
+    let text = fs::read_to_string("a.toml");
+    let other = tokio::fs::read("b").await;
+    thread::sleep(ONE);
+    std::thread::sleep(ONE); // std::fs::read("c")
+    let guard = state.lock().await;
+    let raw = cache.lock().unwrap();
+    tokio::task::spawn_blocking(move || fs::write("d", b""));
+}
+"#;
+        let found = |pattern: &str| -> Vec<(usize, String)> {
+            blocking_calls(content, pattern)
+                .into_iter()
+                .map(|(idx, call)| (content[..idx].matches('\n').count() + 1, call))
+                .collect()
+        };
+        assert_eq!(
+            found("std::fs"),
+            vec![(5, "fs".to_string()), (11, "fs".to_string())]
+        );
+        assert_eq!(
+            found("std::thread::sleep"),
+            vec![
+                (8, "std::thread::sleep".to_string()),
+                (7, "thread::sleep".to_string())
+            ]
+        );
+        assert_eq!(found(".lock()"), vec![(10, ".lock()".to_string())]);
+        assert!(found("std::net::TcpStream::connect").is_empty());
+
+        let patterns: Vec<(String, String)> = BLOCKING_PATTERNS
+            .iter()
+            .map(|(pattern, reason)| (pattern.to_string(), reason.to_string()))
+            .collect();
+        let opened = "async fn open() { let file = std::fs::File::open(\"a\"); }";
+        let matches = blocking_matches(opened, &patterns);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches
+                .values()
+                .next()
+                .map(|(call, pattern, _)| (call.as_str(), *pattern)),
+            Some(("std::fs::File::open", "std::fs::File::open"))
+        );
+
+        let spawn = content.find("spawn_blocking").unwrap();
+        let body = content.find("async fn").unwrap();
+        assert!(inside_blocking_section(content, body, spawn + 30));
+        assert!(!inside_blocking_section(content, body, spawn - 5));
+    }
+
+    #[test]
+    fn test_scan_async_contexts() {
+        let content = r#"trait Store {
+    async fn load(&self) -> u32;
+}
+async fn serve(store: &S) -> u32 {
+    // store.load().await in a comment
+    let first = store.load().await;
+    let task = tokio::spawn(async move {
+        ping().await;
+    });
+    let retry = async |n| fetch(n).await;
+    task.await.unwrap();
+    first + "x.await".len() as u32
+}
+fn spawn_all() {
+    tokio::spawn(async { tick().await });
+}
+"#;
+        let contexts = scan_async_contexts(content);
+        let summary: Vec<(AsyncKind, Option<&str>, usize)> = contexts
+            .iter()
+            .map(|context| (context.kind, context.name.as_deref(), context.awaits.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (AsyncKind::Function, Some("serve"), 2),
+                (AsyncKind::Block, None, 1),
+                (AsyncKind::Closure, None, 1),
+                (AsyncKind::Block, None, 1),
+            ]
+        );
+        assert_eq!(nearest_async_fn(&contexts, &contexts[1]), Some(0));
+        assert_eq!(nearest_async_fn(&contexts, &contexts[3]), None);
+    }
+
+    #[test]
+    fn test_apply_suggested_fixes() {
+        let fix = |byte_start, byte_end, replacement: &str| SuggestedFix {
+            file: "src/lib.rs".to_string(),
+            line: 1,
+            column: 1,
+            byte_start,
+            byte_end,
+            replacement: replacement.to_string(),
+            applicability: "MachineApplicable".to_string(),
+        };
+        let content = "let a = name.clone(); let b = n.clone();";
+        let redundant = fix(12, 20, "");
+        let on_copy = fix(30, 39, "n");
+        let duplicate = fix(12, 20, "");
+
+        let (updated, applied) =
+            apply_suggested_fixes(content, vec![&redundant, &on_copy, &duplicate]);
+        assert_eq!(updated, "let a = name; let b = n;");
+        assert_eq!(applied, 2);
+    }
+
+    #[test]
+    fn test_scan_dyn_usages() {
+        let content = r#"type Handler = Box<dyn Fn(u8) -> bool + Send>;
+
+pub struct Registry {
+    sink: Arc<dyn Sink>,
+    // dyn Ignored in a comment
+    hooks: Vec<&'static dyn Hook>,
+}
+
+impl dyn Sink {}
+
+impl<T> Registry<T> {
+    pub fn run<F>(&self, id: u8, out: &mut dyn Write, f: impl Fn() -> u8) -> impl Iterator<Item = u8> {
+        let fallback: Box<dyn Sink> = Box::new(Null);
+        let label = "dyn Trait";
+        std::iter::empty()
+    }
+}
+"#;
+        let usages = scan_dyn_usages(content);
+        let summary: Vec<_> = usages
+            .iter()
+            .map(|u| {
+                (
+                    u.keyword,
+                    u.bounds.as_str(),
+                    u.wrapper.as_deref(),
+                    u.position,
+                    u.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "dyn",
+                    "Fn(u8) -> bool + Send",
+                    Some("Box"),
+                    TypePosition::TypeAlias,
+                    0
+                ),
+                ("dyn", "Sink", Some("Arc"), TypePosition::Field, 3),
+                ("dyn", "Hook", Some("&"), TypePosition::Field, 5),
+                ("dyn", "Sink", None, TypePosition::Other, 8),
+                ("dyn", "Write", Some("&mut"), TypePosition::Argument, 11),
+                ("impl", "Fn() -> u8", None, TypePosition::Argument, 11),
+                (
+                    "impl",
+                    "Iterator<Item = u8>",
+                    None,
+                    TypePosition::Return,
+                    11
+                ),
+                ("dyn", "Sink", Some("Box"), TypePosition::Local, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declared_dependencies() {
+        let manifest: toml::Table = toml::from_str(
+            r#"
+[package]
+name = "app"
+
+[dependencies]
+serde-json = { version = "1", package = "serde_json" }
+tokio = { version = "1", optional = true }
+
+[dev-dependencies]
+tempfile = "3"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#,
+        )
+        .unwrap();
+
+        let summary: Vec<_> = declared_dependencies(&manifest)
+            .into_iter()
+            .map(|d| (d.ident, d.kind, d.optional, d.target))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "serde_json".to_string(),
+                    DependencyKind::Normal,
+                    false,
+                    None
+                ),
+                ("tokio".to_string(), DependencyKind::Normal, true, None),
+                ("tempfile".to_string(), DependencyKind::Dev, false, None),
+                (
+                    "libc".to_string(),
+                    DependencyKind::Normal,
+                    false,
+                    Some("cfg(unix)".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_branch_counts() {
+        let source = r#"fn parse(input: &str) -> Result<Token, Error> {
+    // if this were a loop || a match => it would not count
+    let text = "if && || ?";
+    if input.is_empty() || input.len() > MAX && !strict {
+        return Err(Error::Empty);
+    } else if let Some(rest) = input.strip_prefix('#') {
+        return Ok(Token::Comment(rest.to_string()));
+    }
+    for c in input.chars() {
+        while skip(c) {}
+    }
+    let check = || valid(&&input);
+    match kind(input)? {
+        Kind::A => Ok(Token::A),
+        Kind::B | Kind::C => Ok(Token::B),
+        _ => Err(Error::Unknown),
+    }
+}"#;
+        let counts = BranchCounts::of(source);
+        assert_eq!(
+            counts,
+            BranchCounts {
+                ifs: 2,
+                match_arms: 2,
+                boolean_operators: 2,
+                loops: 2,
+                try_operators: 1,
+            }
+        );
+        assert_eq!(counts.complexity(), 10);
+    }
+
+    #[test]
+    fn test_references_crate() {
+        let content = r#"
+use serde::Deserialize;
+#[tokio::main]
+async fn main() {
+    // regex::Regex is not used
+    let text = "anyhow::Error";
+    let x = crate::rand::next();
+    extern crate log;
+}
+"#;
+        assert!(references_crate(content, "serde"));
+        assert!(references_crate(content, "tokio"));
+        assert!(references_crate(content, "log"));
+        assert!(!references_crate(content, "regex"));
+        assert!(!references_crate(content, "anyhow"));
+        assert!(!references_crate(content, "rand"));
+        assert!(!references_crate(content, "main"));
+    }
+}
@@ -1,9 +1,29 @@
 use crate::analyzer::RustAnalyzerClient;
-use crate::tools::analysis::find_symbol_location;
+use crate::analyzer::protocol::{
+    Diagnostic, DocumentSymbol, DocumentSymbolResponse, Position, Range, TextEdit,
+};
+use crate::analyzer::symbol::{crate_name_from_uri, module_path_from_uri};
+use crate::inspection::{parse_unified_diff, unified_diff};
+use crate::tools::advanced::{
+    EnumDescription, ImplHeader, StructDefinition, VariantField, collapse_whitespace,
+    last_path_segment, parse_enum_source, parse_impl_header, parse_struct_source,
+    split_generic_params,
+};
+use crate::tools::analysis::{
+    DocsOrigin, find_symbol_location, index_to_line_col, line_start_offset, utf16_column_offset,
+};
+use crate::tools::cargo::package_edition;
+use crate::tools::navigation::{
+    KEYWORDS, Token, declared_module, is_crate_root, resolve_unique_path, tokenize,
+    walk_to_crate_root,
+};
 use crate::tools::types::ToolResult;
 use anyhow::Result;
 use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 
 pub async fn rename_symbol_impl(
     args: Value,
@@ -29,6 +49,14 @@ pub async fn rename_symbol_impl(
         .get("new_name")
         .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow::anyhow!("Missing new_name parameter"))?;
+    let scope = args
+        .get("scope")
+        .and_then(|v| v.as_str())
+        .unwrap_or("workspace");
+    let verify = args
+        .get("verify")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
 
     let file_content = fs::read_to_string(file_path)
         .await
@@ -36,9 +64,36 @@ pub async fn rename_symbol_impl(
 
     let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
 
-    // Implementation will use rust-analyzer LSP to rename symbol
+    let limit_to = match scope {
+        "workspace" => None,
+        "local" => Some(
+            analyzer
+                .enclosing_function_range(file_path, Position { line, character })
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "scope \"local\" requires '{}' to be inside a function or method",
+                        symbol
+                    )
+                })?,
+        ),
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid scope '{}': expected \"local\" or \"workspace\"",
+                other
+            ));
+        }
+    };
+
     let result = analyzer
-        .rename_symbol(file_path, line, character, new_name)
+        .rename_symbol(
+            file_path,
+            line,
+            character,
+            new_name,
+            limit_to.as_ref(),
+            verify,
+        )
         .await?;
 
     Ok(ToolResult {
@@ -54,6 +109,213 @@ pub async fn rename_symbol_impl(
     })
 }
 
+pub async fn rename_api_item_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+    let new_name = args
+        .get("new_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing new_name parameter"))?;
+    let verify = args
+        .get("verify")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    // Classify before renaming: the edit rewrites the very source read here.
+    let (source, range, definition_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+    let definition_content = fs::read_to_string(&definition_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", definition_path, e))?;
+    let symbols = match analyzer.document_symbols(&definition_path).await? {
+        DocumentSymbolResponse::DocumentSymbols(symbols) => symbols,
+        DocumentSymbolResponse::SymbolInformation(_) => Vec::new(),
+    };
+    let containers = enclosing_symbols(&symbols, &range);
+
+    let mut visibility = declared_visibility(&source);
+    let mut restricted_by = Vec::new();
+    for container in containers.iter().rev() {
+        let container_source = text_in_range(&definition_content, &container.range);
+        let container_visibility = declared_visibility(&container_source);
+        match container.kind {
+            // Variants and trait items carry the visibility of their enum or trait.
+            10 | 11 if visibility == ItemVisibility::Private => visibility = container_visibility,
+            2 if container_visibility != ItemVisibility::Public => {
+                restricted_by.push(format!("mod {} ({})", container.name, container_visibility))
+            }
+            _ => {}
+        }
+    }
+
+    let walk = walk_to_crate_root(Path::new(&definition_path)).await;
+    for link in &walk.links {
+        let declaration = link["declaration"].as_str().unwrap_or_default();
+        let module_visibility = declared_visibility(declaration);
+        if module_visibility != ItemVisibility::Public {
+            restricted_by.push(format!(
+                "mod {} ({})",
+                link["module"].as_str().unwrap_or_default(),
+                module_visibility
+            ));
+        }
+    }
+    let library = walk
+        .root
+        .as_deref()
+        .is_some_and(|root| root.ends_with("src/lib.rs"));
+
+    let (semver, reason) = if visibility != ItemVisibility::Public {
+        (
+            "none",
+            format!(
+                "'{}' is {}, so no downstream crate can name it",
+                symbol, visibility
+            ),
+        )
+    } else if !library {
+        (
+            "none",
+            format!("'{}' is pub, but it is not part of a library crate", symbol),
+        )
+    } else if !restricted_by.is_empty() {
+        (
+            "none",
+            format!(
+                "'{}' is pub, but unreachable through {}; check it is not re-exported with `pub use`",
+                symbol,
+                restricted_by.join(", ")
+            ),
+        )
+    } else {
+        (
+            "breaking",
+            format!(
+                "'{}' is part of the public API; renaming it requires a major version bump",
+                symbol
+            ),
+        )
+    };
+
+    let edit = analyzer
+        .rename_symbol(file_path, line, character, new_name, None, verify)
+        .await?;
+
+    let result = json!({
+        "symbol": symbol,
+        "new_name": new_name,
+        "definition": {
+            "file": definition_path,
+            "line": range.start.line + 1
+        },
+        "visibility": visibility.to_string(),
+        "restricted_by": restricted_by,
+        "semver": semver,
+        "reason": reason,
+        "edit": edit
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The visibility written on an item's declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ItemVisibility {
+    Public,
+    /// `pub(crate)`, `pub(super)` or `pub(in path)`.
+    Restricted(String),
+    Private,
+}
+
+impl std::fmt::Display for ItemVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemVisibility::Public => f.write_str("pub"),
+            ItemVisibility::Restricted(scope) => write!(f, "pub({})", scope),
+            ItemVisibility::Private => f.write_str("private"),
+        }
+    }
+}
+
+/// Reads the visibility of the first declaration in `source`, skipping doc
+/// comments and (possibly multi-line) attributes.
+pub(crate) fn declared_visibility(source: &str) -> ItemVisibility {
+    let mut attribute_depth = 0i32;
+    for line in source.lines().map(str::trim) {
+        if attribute_depth > 0 || line.starts_with("#[") {
+            attribute_depth += line.matches('[').count() as i32 - line.matches(']').count() as i32;
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("pub") else {
+            return ItemVisibility::Private;
+        };
+        return match rest.trim_start().strip_prefix('(') {
+            Some(scoped) => match scoped.split(')').next().map(str::trim) {
+                Some("self") => ItemVisibility::Private,
+                Some(scope) => ItemVisibility::Restricted(scope.to_string()),
+                None => ItemVisibility::Private,
+            },
+            None if rest.starts_with(char::is_whitespace) => ItemVisibility::Public,
+            None => ItemVisibility::Private,
+        };
+    }
+    ItemVisibility::Private
+}
+
+/// The symbols enclosing `range`, outermost first. A container always starts
+/// before its items, which excludes the symbol of `range` itself.
+fn enclosing_symbols<'a>(symbols: &'a [DocumentSymbol], range: &Range) -> Vec<&'a DocumentSymbol> {
+    let Some(symbol) = symbols.iter().find(|symbol| {
+        (symbol.range.start.line, symbol.range.start.character)
+            < (range.start.line, range.start.character)
+            && RustAnalyzerClient::position_in_range(&symbol.range, &range.start)
+            && RustAnalyzerClient::position_in_range(&symbol.range, &range.end)
+    }) else {
+        return Vec::new();
+    };
+    let mut chain = vec![symbol];
+    if let Some(children) = &symbol.children {
+        chain.extend(enclosing_symbols(children, range));
+    }
+    chain
+}
+
 pub async fn extract_function_impl(
     args: Value,
     analyzer: &mut RustAnalyzerClient,
@@ -150,3 +412,5440 @@ pub async fn inline_function_impl(
         ],
     })
 }
+
+pub async fn scaffold_test_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+
+    let hover = analyzer.get_hover(file_path, line, character).await?;
+    let signature = parse_hover_signature(&hover).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not read a function signature for '{}' from hover information",
+            symbol
+        )
+    })?;
+
+    let test_name = format!("test_{}", signature.name);
+    if file_content.contains(&format!("fn {test_name}(")) {
+        return Err(anyhow::anyhow!(
+            "A test named '{}' already exists in {}",
+            test_name,
+            file_path
+        ));
+    }
+
+    let stub = render_test_stub(&test_name, &signature);
+    let insertion = insert_test_stub(&file_content, &stub);
+
+    let diff = analyzer
+        .write_file(file_path, &file_content, &insertion.content)
+        .await?;
+
+    let mut notes = Vec::new();
+    if diff.is_some() {
+        notes.push("Writes are disabled (read-only mode); the file was not changed. See `diff` for the proposed edit.");
+    }
+    if signature.is_generic {
+        notes.push("The function is generic; placeholder arguments may need explicit types.");
+    }
+    if signature.is_async {
+        notes.push("The function is async; the returned future must be driven by an async runtime.");
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "test_name": test_name,
+        "line": insertion.line + 1,
+        "created_test_module": insertion.created_module,
+        "stub": stub,
+        "written": diff.is_none(),
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn fill_match_arms_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    // The assist is offered on the `match` keyword, before the arm list.
+    let (line, character) = find_symbol_location(&file_content, "match", code_block, occurrence)?;
+
+    let result = match analyzer.fill_match_arms(file_path, line, character).await? {
+        None => json!({
+            "file_path": file_path,
+            "status": "exhaustive",
+            "inserted_arms": [],
+            "message": "rust-analyzer offered no 'Fill match arms' action: the match already covers every variant."
+        }),
+        Some(edit) => {
+            let uri = format!("file://{}", file_path);
+            let inserted_arms = edit
+                .changes
+                .as_ref()
+                .and_then(|changes| changes.get(&uri))
+                .map(|edits| inserted_lines(&file_content, edits))
+                .unwrap_or_default();
+
+            let message = analyzer.apply_workspace_edit(edit).await?;
+            json!({
+                "file_path": file_path,
+                "status": "filled",
+                "written": analyzer.allow_writes(),
+                "inserted_arms": inserted_arms,
+                "message": message
+            })
+        }
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn match_coverage_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, "match", code_block, occurrence)?;
+    let match_start = position_offset(&file_content, &Position { line, character })
+        .ok_or_else(|| anyhow::anyhow!("match keyword is past the end of the file"))?;
+    let body_start = match_start + "match".len();
+    let open = find_top_level(&file_content, body_start, "{")
+        .ok_or_else(|| anyhow::anyhow!("No arm list found after the match keyword"))?;
+    let close = find_matching_brace(&file_content, open)
+        .ok_or_else(|| anyhow::anyhow!("The match arm list is not closed"))?;
+    let scrutinee = file_content[body_start..open].trim();
+    let arms = split_match_arms(&file_content, open + 1, close);
+
+    // The first variant pattern that resolves names the matched enum.
+    let mut matched_enum = None;
+    for (offset, _) in arms
+        .iter()
+        .flat_map(|arm| pattern_variants(&file_content, arm))
+        .take(5)
+    {
+        let (line, character) = index_to_line_col(&file_content, offset);
+        if let Some(found) = matched_enum_source(analyzer, file_path, line, character).await? {
+            matched_enum = Some(found);
+            break;
+        }
+    }
+    let (enum_path, description) = matched_enum.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not resolve the enum matched by `{}`: no arm names one of its variants",
+            scrutinee
+        )
+    })?;
+
+    let coverage = match_coverage(&file_content, &arms, &description);
+    let result = json!({
+        "file_path": file_path,
+        "line": line + 1,
+        "scrutinee": scrutinee,
+        "enum": description.name,
+        "enum_file": enum_path,
+        "status": if coverage.missing.is_empty() { "exhaustive" } else { "non_exhaustive" },
+        "covered": coverage.covered,
+        "partially_covered": coverage.partial,
+        "wildcard_covered": coverage.wildcard_covered,
+        "missing": coverage.missing,
+        "catch_all_arms": coverage.catch_all_lines,
+        "unknown_patterns": coverage.unknown
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Resolves a variant name in a pattern to the source of its enum.
+async fn matched_enum_source(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    line: u32,
+    character: u32,
+) -> Result<Option<(String, EnumDescription)>> {
+    let Some(details) = analyzer
+        .definition_details(file_path, line, character)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let [.., parent, variant] = details.symbol_path.as_slice() else {
+        return Ok(None);
+    };
+    // LSP symbol kinds 10 and 22: an enum and one of its variants.
+    if parent.kind != 10 || variant.kind != 22 {
+        return Ok(None);
+    }
+
+    let enum_path = details
+        .location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&details.location.uri)
+        .to_string();
+    let Ok(content) = fs::read_to_string(&enum_path).await else {
+        return Ok(None);
+    };
+    let Some(variant_offset) = position_offset(&content, &details.location.range.start) else {
+        return Ok(None);
+    };
+    let header = format!("enum {}", parent.name);
+    let Some(start) = content[..variant_offset].rfind(&header) else {
+        return Ok(None);
+    };
+    let Some(close) = content[start..]
+        .find('{')
+        .and_then(|open| find_matching_brace(&content, start + open))
+    else {
+        return Ok(None);
+    };
+    Ok(parse_enum_source(&content[start..=close]).map(|description| (enum_path, description)))
+}
+
+/// Byte offset of a 0-based line and UTF-16 column, if it is inside `content`.
+fn position_offset(content: &str, position: &Position) -> Option<usize> {
+    let range = Range {
+        start: position.clone(),
+        end: position.clone(),
+    };
+    resolve_byte_range(content, &range)
+        .ok()
+        .map(|(start, _)| start)
+}
+
+/// One arm of a `match`: its pattern, guard and byte offset of the pattern.
+#[derive(Debug)]
+struct MatchArm {
+    pattern: String,
+    guarded: bool,
+    offset: usize,
+}
+
+/// Splits the arms between `start` and `end` (the inside of the braces).
+fn split_match_arms(content: &str, start: usize, end: usize) -> Vec<MatchArm> {
+    let mut arms = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let rest = &content[pos..end];
+        let skipped = rest.len()
+            - rest
+                .trim_start_matches(|c: char| c.is_whitespace() || c == ',')
+                .len();
+        pos += skipped;
+        if content[pos..end].starts_with("//") {
+            pos = content[pos..end]
+                .find('\n')
+                .map_or(end, |idx| pos + idx + 1);
+            continue;
+        }
+        if pos >= end {
+            break;
+        }
+        let Some(arrow) = find_top_level(&content[..end], pos, "=>") else {
+            break;
+        };
+        let head = &content[pos..arrow];
+        // Leading attributes such as `#[allow(..)]` are not part of the pattern.
+        let mut pattern_start = 0;
+        while head[pattern_start..].trim_start().starts_with("#[") {
+            let attribute = pattern_start + head[pattern_start..].find("#[").unwrap_or(0);
+            pattern_start = head[attribute..]
+                .find(']')
+                .map_or(head.len(), |idx| attribute + idx + 1);
+        }
+        let pattern_start = pattern_start
+            + (head[pattern_start..].len() - head[pattern_start..].trim_start().len());
+        let head = &head[pattern_start..];
+        let guard = find_top_level(head, 0, " if ").or_else(|| find_top_level(head, 0, "\nif "));
+        arms.push(MatchArm {
+            pattern: guard.map_or(head, |idx| &head[..idx]).trim().to_string(),
+            guarded: guard.is_some(),
+            offset: pos + pattern_start,
+        });
+
+        let body = arrow + 2;
+        let body_start = body + (content[body..end].len() - content[body..end].trim_start().len());
+        pos = if content[body_start..end].starts_with('{') {
+            let block_end = find_matching_brace(content, body_start).map_or(end, |idx| idx + 1);
+            match content[block_end..end].trim_start().chars().next() {
+                Some('.' | '?') => find_top_level(&content[..end], block_end, ",").unwrap_or(end),
+                _ => block_end,
+            }
+        } else {
+            find_top_level(&content[..end], body_start, ",").unwrap_or(end)
+        };
+    }
+    arms
+}
+
+/// Offset of the first `needle` at or after `from` outside any brackets,
+/// strings, char literals and comments.
+fn find_top_level(text: &str, from: usize, needle: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0i32;
+    let mut idx = from;
+    while idx < bytes.len() {
+        if depth == 0 && text[idx..].starts_with(needle) {
+            return Some(idx);
+        }
+        match bytes[idx] {
+            b'/' if bytes.get(idx + 1) == Some(&b'/') => {
+                idx = text[idx..].find('\n').map_or(bytes.len(), |end| idx + end);
+            }
+            b'"' => {
+                idx += 1;
+                while idx < bytes.len() && bytes[idx] != b'"' {
+                    if bytes[idx] == b'\\' {
+                        idx += 1;
+                    }
+                    idx += 1;
+                }
+            }
+            b'\'' if bytes.get(idx + 1) == Some(&b'\\') => {
+                idx = text[idx + 2..]
+                    .find('\'')
+                    .map_or(bytes.len(), |end| idx + 2 + end);
+            }
+            b'\'' if bytes.get(idx + 2) == Some(&b'\'') => idx += 2,
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// How one `|` alternative of an arm's pattern matches the enum.
+#[derive(Debug, PartialEq)]
+enum PatternShape {
+    /// `_` or a plain binding: matches every remaining value.
+    CatchAll,
+    /// A variant, with whether its fields are matched irrefutably.
+    Variant {
+        name: String,
+        complete: bool,
+    },
+    Other,
+}
+
+fn classify_pattern(pattern: &str) -> Vec<PatternShape> {
+    split_alternatives(pattern)
+        .into_iter()
+        .map(|alternative| {
+            let mut pattern = alternative.trim();
+            if let Some(at) = find_top_level(pattern, 0, "@") {
+                pattern = pattern[at + 1..].trim();
+            }
+            pattern = pattern.trim_start_matches('&').trim_start();
+            pattern = pattern.strip_prefix("mut ").unwrap_or(pattern).trim_start();
+            if is_irrefutable(pattern) && !starts_uppercase(pattern) {
+                return PatternShape::CatchAll;
+            }
+
+            let path_end = pattern
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+                .unwrap_or(pattern.len());
+            let (path, rest) = pattern.split_at(path_end);
+            let name = path.rsplit("::").next().unwrap_or(path);
+            if !starts_uppercase(name) {
+                return PatternShape::Other;
+            }
+            let rest = rest.trim();
+            let complete = if let Some(inner) =
+                rest.strip_prefix('(').and_then(|r| r.strip_suffix(')'))
+            {
+                split_top_level(inner)
+                    .iter()
+                    .all(|field| is_irrefutable(field))
+            } else if let Some(inner) = rest.strip_prefix('{').and_then(|r| r.strip_suffix('}')) {
+                split_top_level(inner)
+                    .iter()
+                    .all(|field| match field.split_once(':') {
+                        Some((_, sub)) => is_irrefutable(sub),
+                        None => true,
+                    })
+            } else {
+                rest.is_empty()
+            };
+            PatternShape::Variant {
+                name: name.to_string(),
+                complete,
+            }
+        })
+        .collect()
+}
+
+/// Splits a pattern at its top-level `|`s.
+fn split_alternatives(pattern: &str) -> Vec<&str> {
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+    while let Some(bar) = find_top_level(pattern, start, "|") {
+        alternatives.push(&pattern[start..bar]);
+        start = bar + 1;
+    }
+    alternatives.push(&pattern[start..]);
+    alternatives
+        .into_iter()
+        .filter(|alternative| !alternative.trim().is_empty())
+        .collect()
+}
+
+/// Whether a sub-pattern matches anything: `_`, `..`, a binding or a tuple
+/// of those.
+fn is_irrefutable(pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    if let Some(at) = find_top_level(pattern, 0, "@") {
+        return is_irrefutable(&pattern[at + 1..]);
+    }
+    if let Some(inner) = pattern.strip_prefix('(').and_then(|p| p.strip_suffix(')')) {
+        return split_top_level(inner)
+            .iter()
+            .all(|item| is_irrefutable(item));
+    }
+    let binding = pattern
+        .trim_start_matches("ref ")
+        .trim_start_matches("mut ")
+        .trim();
+    matches!(binding, "_" | "..")
+        || (!binding.is_empty()
+            && !starts_uppercase(binding)
+            && binding.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+fn starts_uppercase(text: &str) -> bool {
+    text.chars().next().is_some_and(char::is_uppercase)
+}
+
+/// Byte offsets and names of the variants an arm's pattern names, for
+/// resolving the matched enum.
+fn pattern_variants(content: &str, arm: &MatchArm) -> Vec<(usize, String)> {
+    let pattern = &content[arm.offset..arm.offset + arm.pattern.len()];
+    classify_pattern(pattern)
+        .into_iter()
+        .filter_map(|shape| match shape {
+            PatternShape::Variant { name, .. } => {
+                let idx = pattern
+                    .match_indices(name.as_str())
+                    .map(|(idx, _)| idx)
+                    .find(|&idx| {
+                        !pattern[idx + name.len()..]
+                            .starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                    })?;
+                Some((arm.offset + idx, name))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct MatchCoverage {
+    covered: Vec<Value>,
+    partial: Vec<Value>,
+    wildcard_covered: Vec<String>,
+    missing: Vec<String>,
+    catch_all_lines: Vec<usize>,
+    unknown: Vec<Value>,
+}
+
+/// Compares the arms of a match with the variants of the matched enum. Arms
+/// with a guard or refutable fields only partially cover their variant.
+fn match_coverage(
+    content: &str,
+    arms: &[MatchArm],
+    description: &EnumDescription,
+) -> MatchCoverage {
+    let mut coverage = MatchCoverage::default();
+    let mut arm_lines: BTreeMap<&str, (Vec<usize>, bool)> = BTreeMap::new();
+    let mut catch_all = false;
+    for arm in arms {
+        let line = content[..arm.offset].matches('\n').count() + 1;
+        for shape in classify_pattern(&arm.pattern) {
+            match shape {
+                PatternShape::CatchAll => {
+                    if !arm.guarded {
+                        catch_all = true;
+                        coverage.catch_all_lines.push(line);
+                    }
+                }
+                PatternShape::Variant { name, complete } => {
+                    match description
+                        .variants
+                        .iter()
+                        .find(|variant| variant.name == name)
+                    {
+                        Some(variant) => {
+                            let entry = arm_lines.entry(variant.name.as_str()).or_default();
+                            entry.0.push(line);
+                            entry.1 |= complete && !arm.guarded;
+                        }
+                        None => coverage
+                            .unknown
+                            .push(json!({"pattern": name, "line": line})),
+                    }
+                }
+                PatternShape::Other => {
+                    coverage
+                        .unknown
+                        .push(json!({"pattern": arm.pattern, "line": line}));
+                }
+            }
+        }
+    }
+
+    for variant in &description.variants {
+        match arm_lines.get(variant.name.as_str()) {
+            Some((lines, true)) => coverage
+                .covered
+                .push(json!({"variant": variant.name, "lines": lines})),
+            other => {
+                if let Some((lines, _)) = other {
+                    coverage
+                        .partial
+                        .push(json!({"variant": variant.name, "lines": lines}));
+                }
+                if catch_all {
+                    coverage.wildcard_covered.push(variant.name.clone());
+                } else {
+                    coverage.missing.push(variant.name.clone());
+                }
+            }
+        }
+    }
+    coverage
+}
+
+pub async fn suggest_import_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let start = Position { line, character };
+    let end = Position {
+        line,
+        character: character + symbol.encode_utf16().count() as u32,
+    };
+
+    let actions = analyzer.code_actions(file_path, start, end).await?;
+    let mut candidates: Vec<Value> = Vec::new();
+    for action in &actions {
+        let Some(path) = import_candidate(&action.title) else {
+            continue;
+        };
+        if candidates.iter().any(|c| c["path"] == path) {
+            continue;
+        }
+        candidates.push(json!({
+            "path": path,
+            "use_statement": format!("use {path};"),
+            "preferred": action.is_preferred.unwrap_or(false)
+        }));
+    }
+
+    let result = if candidates.is_empty() {
+        json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "status": "none",
+            "candidates": candidates,
+            "message": "rust-analyzer offered no imports: the symbol is already resolved or no importable item has this name."
+        })
+    } else {
+        json!({
+            "file_path": file_path,
+            "symbol": symbol,
+            "status": "found",
+            "candidates": candidates
+        })
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn add_import_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args
+        .get("occurrence")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1) as usize;
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing path parameter"))?;
+    let path = path
+        .trim()
+        .trim_start_matches("use ")
+        .trim_end_matches(';')
+        .trim();
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let start = Position { line, character };
+    let end = Position {
+        line,
+        character: character + symbol.encode_utf16().count() as u32,
+    };
+
+    // Prefer rust-analyzer's own assist: it merges into existing `use` trees
+    // and follows the configured import granularity.
+    let title = format!("Import `{path}`");
+    let assist_edit = analyzer
+        .code_actions(file_path, start, end)
+        .await?
+        .into_iter()
+        .find(|action| action.title == title)
+        .and_then(|action| action.edit);
+
+    let (method, inserted, message) = match assist_edit {
+        Some(edit) => {
+            let uri = format!("file://{}", file_path);
+            let inserted = edit
+                .changes
+                .as_ref()
+                .and_then(|changes| changes.get(&uri))
+                .map(|edits| inserted_lines(&file_content, edits))
+                .unwrap_or_default();
+            let message = analyzer.apply_workspace_edit(edit).await?;
+            ("code_action", inserted, message)
+        }
+        None => {
+            let (updated, use_line) = insert_use_statement(&file_content, path);
+            let message = match analyzer.write_file(file_path, &file_content, &updated).await? {
+                Some(diff) => format!(
+                    "Writes are disabled (read-only mode); no files were changed. Proposed edit:\n{}",
+                    diff
+                ),
+                None => format!("Inserted `use {};` at line {}", path, use_line + 1),
+            };
+            ("use_statement", vec![format!("use {path};")], message)
+        }
+    };
+
+    let mut result = json!({
+        "file_path": file_path,
+        "path": path,
+        "method": method,
+        "inserted_lines": inserted,
+        "written": analyzer.allow_writes(),
+        "message": message
+    });
+
+    if analyzer.allow_writes() {
+        // Fresh diagnostics for the written file: whatever was stored for it
+        // still describes the text before the import.
+        let written = fs::read_to_string(file_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        match analyzer.diagnostics_for_text(file_path, &written).await? {
+            Some(diagnostics) => {
+                let remaining: Vec<String> = diagnostics
+                    .iter()
+                    .filter(|diag| {
+                        diag.severity.unwrap_or(1) == 1 && diag.message.contains(symbol)
+                    })
+                    .map(|diag| {
+                        format!(
+                            "{}:{}: {}",
+                            diag.range.start.line + 1,
+                            diag.range.start.character + 1,
+                            diag.message
+                        )
+                    })
+                    .collect();
+                result["resolved"] = json!(remaining.is_empty());
+                result["remaining_errors"] = json!(remaining);
+            }
+            None => {
+                result["resolved"] = Value::Null;
+                result["remaining_errors"] = json!([]);
+            }
+        }
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Import groups in rustfmt's `StdExternalCrate` order.
+fn use_group(path: &str) -> u8 {
+    match path.trim_start_matches("::").split("::").next() {
+        Some("std" | "core" | "alloc") => 0,
+        Some("crate" | "self" | "super") => 2,
+        _ => 1,
+    }
+}
+
+/// Inserts `use <path>;` among the file's top-level imports: sorted into its
+/// group (std, external crates, local) when the group exists, otherwise as a
+/// new block. Returns the updated content and the 0-based line of the `use`.
+fn insert_use_statement(content: &str, path: &str) -> (String, usize) {
+    let lines: Vec<&str> = content.lines().collect();
+    let group = use_group(path);
+
+    // Top-level `use` statements as (first line, last line, path).
+    let mut uses = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        if let Some(body) = line.strip_prefix("use ").or_else(|| line.strip_prefix("pub use ")) {
+            let start = idx;
+            while !lines[idx].trim_end().ends_with(';') && idx + 1 < lines.len() {
+                idx += 1;
+            }
+            uses.push((start, idx, body.trim_end().trim_end_matches(';').to_string()));
+        }
+        idx += 1;
+    }
+
+    let same_group = || uses.iter().filter(|(_, _, used)| use_group(used) == group);
+    let (at, blank_before, blank_after) = if let Some((start, _, _)) =
+        same_group().find(|(_, _, used)| used.as_str() > path)
+    {
+        (*start, false, false)
+    } else if let Some((_, end, _)) = same_group().next_back() {
+        (end + 1, false, false)
+    } else if let Some((_, end, _)) = uses.iter().rfind(|(_, _, used)| use_group(used) < group) {
+        (end + 1, true, false)
+    } else if let Some((start, _, _)) = uses.first() {
+        (*start, false, true)
+    } else {
+        let preamble = lines
+            .iter()
+            .take_while(|line| {
+                let line = line.trim_start();
+                line.is_empty() || line.starts_with("//!") || line.starts_with("#![")
+            })
+            .count();
+        let blank_before = preamble > 0 && !lines[preamble - 1].trim().is_empty();
+        (preamble, blank_before, true)
+    };
+
+    let statement = format!("use {path};");
+    let mut inserted = Vec::new();
+    if blank_before {
+        inserted.push("");
+    }
+    inserted.push(statement.as_str());
+    if blank_after {
+        inserted.push("");
+    }
+
+    let mut updated: Vec<&str> = lines[..at].to_vec();
+    updated.extend(inserted);
+    updated.extend(&lines[at..]);
+    let mut updated = updated.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        updated.push('\n');
+    }
+    (updated, at + blank_before as usize)
+}
+
+/// Extracts the path from rust-analyzer's auto-import assist title,
+/// "Import `std::collections::HashMap`".
+fn import_candidate(title: &str) -> Option<&str> {
+    let path = title.strip_prefix("Import `")?.strip_suffix('`')?;
+    (!path.is_empty()).then_some(path)
+}
+
+pub async fn method_trait_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let mut result = json!({
+        "file_path": file_path,
+        "method": symbol,
+        "line": line + 1
+    });
+
+    let Some(details) = analyzer
+        .definition_details(file_path, line, character)
+        .await?
+    else {
+        // rust-analyzer only resolves trait methods whose trait is in scope,
+        // and then offers to import the traits that would provide one.
+        let start = Position { line, character };
+        let end = Position {
+            line,
+            character: character + symbol.encode_utf16().count() as u32,
+        };
+        let actions = analyzer.code_actions(file_path, start, end).await?;
+        let mut candidates: Vec<Value> = Vec::new();
+        for action in &actions {
+            let Some(path) = import_candidate(&action.title) else {
+                continue;
+            };
+            if candidates.iter().any(|c| c["path"] == path) {
+                continue;
+            }
+            candidates.push(json!({
+                "path": path,
+                "use_statement": format!("use {path};"),
+                "preferred": action.is_preferred.unwrap_or(false)
+            }));
+        }
+        if candidates.is_empty() {
+            result["status"] = json!("unresolved");
+            result["message"] = json!(
+                "The method does not resolve and rust-analyzer offered no trait import: check the receiver type and the method name."
+            );
+        } else {
+            result["status"] = json!("trait_not_in_scope");
+            result["candidates"] = json!(candidates);
+            result["message"] = json!(
+                "The method comes from a trait that is not in scope; importing one of the candidates brings it into scope."
+            );
+        }
+        return method_trait_result(result);
+    };
+
+    let definition_path = details
+        .location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&details.location.uri)
+        .to_string();
+    result["definition"] = json!({
+        "file_path": definition_path,
+        "line": details.location.range.start.line + 1
+    });
+
+    let segments = &details.symbol_path;
+    let parent = segments
+        .len()
+        .checked_sub(2)
+        .map(|idx| (idx, &segments[idx]));
+    // LSP `SymbolKind::Method`, `Function`
+    let is_method = segments
+        .last()
+        .is_some_and(|item| matches!(item.kind, 6 | 12));
+    let trait_definition = match parent {
+        // LSP `SymbolKind::Interface`
+        Some((idx, parent)) if is_method && parent.kind == 11 => {
+            result["status"] = json!("trait");
+            let modules: Vec<String> = segments[..idx]
+                .iter()
+                .filter(|segment| segment.kind == 2)
+                .map(|segment| segment.name.clone())
+                .collect();
+            Some((definition_path.clone(), modules, parent.name.clone()))
+        }
+        Some((_, parent)) if is_method && is_impl_header(&parent.name) => {
+            match parse_impl_header(&parent.name) {
+                Some(ImplHeader::Trait {
+                    trait_path,
+                    self_type,
+                    ..
+                }) => {
+                    result["status"] = json!("trait");
+                    result["self_type"] = json!(self_type);
+                    let trait_name = last_path_segment(&trait_path);
+                    let resolved = resolve_impl_trait(
+                        analyzer,
+                        &definition_path,
+                        details.location.range.start.line,
+                        trait_name,
+                    )
+                    .await;
+                    if resolved.is_none() {
+                        result["trait"] = json!({ "name": trait_name });
+                        result["message"] = json!(
+                            "The trait named by the impl header did not resolve; look it up with suggest_import or workspace_symbols."
+                        );
+                    }
+                    resolved
+                }
+                _ => {
+                    result["status"] = json!("inherent");
+                    result["self_type"] = json!(inherent_self_type(&parent.name));
+                    result["message"] = json!("An inherent method: no import is needed.");
+                    None
+                }
+            }
+        }
+        _ => {
+            result["status"] = json!("not_a_method");
+            result["message"] = json!(format!(
+                "`{}` resolves to {}, not a method of an impl or trait.",
+                symbol,
+                segments
+                    .iter()
+                    .map(|segment| segment.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join("::")
+            ));
+            None
+        }
+    };
+
+    if let Some((trait_file, inline_modules, name)) = trait_definition {
+        let origin = DocsOrigin::of_path(&trait_file);
+        let mut modules = module_path_from_uri(&trait_file);
+        modules.extend(inline_modules);
+        let path = trait_import_path(
+            &origin,
+            crate_name_from_uri(&trait_file).as_deref(),
+            crate_name_from_uri(file_path).as_deref(),
+            &modules,
+            &name,
+        );
+        let in_prelude =
+            matches!(origin, DocsOrigin::Std(_)) && PRELUDE_TRAITS.contains(&name.as_str());
+        result["trait"] = json!({
+            "name": name,
+            "path": path,
+            "origin": origin.kind(),
+            "in_prelude": in_prelude,
+            "file_path": trait_file
+        });
+        result["use_statement"] = if in_prelude {
+            Value::Null
+        } else {
+            json!(format!("use {path};"))
+        };
+        result["message"] = json!(if in_prelude {
+            "The trait is in the prelude: no import is needed.".to_string()
+        } else {
+            "`path` is built from the defining module; a private module behind a `pub use` makes the public path shorter, which suggest_import reports when the trait is out of scope.".to_string()
+        });
+    }
+
+    method_trait_result(result)
+}
+
+fn method_trait_result(result: Value) -> Result<ToolResult> {
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Traits of the std prelude (editions 2021 and 2024) whose methods never
+/// need an import.
+pub(crate) const PRELUDE_TRAITS: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Clone",
+    "Copy",
+    "Default",
+    "DoubleEndedIterator",
+    "Drop",
+    "Eq",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "Future",
+    "Into",
+    "IntoFuture",
+    "IntoIterator",
+    "Iterator",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Send",
+    "Sized",
+    "Sync",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Unpin",
+];
+
+/// rust-analyzer names impl blocks by their header, `impl<T> Display for
+/// Wrapper<T>`.
+fn is_impl_header(name: &str) -> bool {
+    name.starts_with("impl ") || name.starts_with("impl<")
+}
+
+/// The self type of an inherent impl header, `Parser<'a>` for
+/// `impl<'a> Parser<'a>`.
+fn inherent_self_type(name: &str) -> &str {
+    let body = name
+        .strip_prefix("impl")
+        .and_then(split_generic_params)
+        .map_or(name, |(_, body)| body);
+    body.split(" where ").next().unwrap_or(body).trim()
+}
+
+/// Resolves the trait named in the header of the impl enclosing line
+/// `method_line` of `file_path`, as `(file, inline modules, name)`.
+pub(crate) async fn resolve_impl_trait(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    method_line: u32,
+    trait_name: &str,
+) -> Option<(String, Vec<String>, String)> {
+    let content = fs::read_to_string(file_path).await.ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let (header_line, column) = lines
+        .iter()
+        .enumerate()
+        .take(method_line as usize + 1)
+        .rev()
+        .find_map(|(idx, line)| {
+            let trimmed = line.trim_start();
+            if !trimmed.starts_with("impl") {
+                return None;
+            }
+            let column = line.find(trait_name)?;
+            Some((idx as u32, column))
+        })?;
+    let character = lines[header_line as usize][..column].encode_utf16().count() as u32;
+    let details = analyzer
+        .definition_details(file_path, header_line, character)
+        .await
+        .ok()??;
+    let trait_file = details
+        .location
+        .uri
+        .strip_prefix("file://")
+        .unwrap_or(&details.location.uri)
+        .to_string();
+    let (last, ancestors) = details.symbol_path.split_last()?;
+    let modules = ancestors
+        .iter()
+        .filter(|segment| segment.kind == 2)
+        .map(|segment| segment.name.clone())
+        .collect();
+    Some((trait_file, modules, last.name.clone()))
+}
+
+/// The path to `use` for trait `name` defined in `modules` of its crate.
+/// Traits of `core` and `alloc` are re-exported by `std` at the top module of
+/// their definition (`core::iter::traits::iterator::Iterator` is
+/// `std::iter::Iterator`); items of the calling crate start with `crate`.
+pub(crate) fn trait_import_path(
+    origin: &DocsOrigin,
+    trait_crate: Option<&str>,
+    calling_crate: Option<&str>,
+    modules: &[String],
+    name: &str,
+) -> String {
+    let mut path: Vec<String> = match origin {
+        DocsOrigin::Std(krate) if krate == "core" || krate == "alloc" => {
+            let mut path = vec!["std".to_string()];
+            path.extend(modules.first().cloned());
+            path.push(name.to_string());
+            return path.join("::");
+        }
+        DocsOrigin::Std(krate) => vec![krate.clone()],
+        DocsOrigin::Dependency { name, .. } => vec![name.replace('-', "_")],
+        DocsOrigin::Local if trait_crate.is_some() && trait_crate == calling_crate => {
+            vec!["crate".to_string()]
+        }
+        DocsOrigin::Local => vec![trait_crate.unwrap_or("crate").replace('-', "_")],
+    };
+    path.extend(modules.iter().cloned());
+    path.push(name.to_string());
+    path.join("::")
+}
+
+pub async fn self_type_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let ancestors = analyzer
+        .enclosing_symbols(file_path, Position { line, character })
+        .await?;
+
+    // LSP `SymbolKind::Object` is how rust-analyzer reports impl blocks.
+    let Some(block) = ancestors
+        .iter()
+        .rev()
+        .find(|ancestor| ancestor.kind == 19 && is_impl_header(&ancestor.name))
+    else {
+        let message = match ancestors.iter().rev().find(|ancestor| ancestor.kind == 11) {
+            Some(definition) => format!(
+                "'{}' is inside trait `{}`, not an impl: `Self` there is whichever type implements the trait",
+                symbol, definition.name
+            ),
+            None => format!("'{}' is not inside an impl block", symbol),
+        };
+        return Err(anyhow::anyhow!(message));
+    };
+    let target = impl_target(&block.name)
+        .ok_or_else(|| anyhow::anyhow!("Could not parse the impl header `{}`", block.name))?;
+
+    let header_line = block.selection_range.start.line;
+    let self_name = last_path_segment(&target.self_type).to_string();
+    let self_path = if target.blanket {
+        None
+    } else {
+        resolved_item_path(analyzer, file_path, header_line, &self_name).await
+    };
+    let trait_path = match &target.trait_path {
+        Some(trait_path) => {
+            let name = last_path_segment(trait_path).to_string();
+            resolved_item_path(analyzer, file_path, header_line, &name).await
+        }
+        None => None,
+    };
+
+    let mut notes = Vec::new();
+    if target.blanket {
+        notes.push(format!(
+            "A blanket impl: `Self` is the generic parameter `{}`, i.e. any type meeting the impl's bounds.",
+            target.self_type
+        ));
+    }
+    if self_path.is_none() && !target.blanket {
+        notes.push(
+            "The self type did not resolve to a definition (a primitive, reference or external type rust-analyzer could not follow); `self_type` is the type as written.".to_string(),
+        );
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "symbol": symbol,
+        "line": line + 1,
+        "impl": {
+            "header": block.name,
+            "line": header_line + 1,
+            "kind": if target.trait_path.is_some() { "trait" } else { "inherent" }
+        },
+        "self_type": target.self_type,
+        "self_type_path": self_path,
+        "trait": target.trait_path,
+        "trait_path": trait_path,
+        "generic": target.generic,
+        "blanket": target.blanket,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The self type and implemented trait of an impl block, from its header.
+#[derive(Debug, PartialEq)]
+struct ImplTarget {
+    self_type: String,
+    trait_path: Option<String>,
+    generic: bool,
+    blanket: bool,
+}
+
+fn impl_target(header: &str) -> Option<ImplTarget> {
+    match parse_impl_header(header) {
+        Some(ImplHeader::Trait {
+            trait_path,
+            self_type,
+            generic,
+            blanket,
+            negative,
+        }) => Some(ImplTarget {
+            self_type,
+            trait_path: Some(if negative {
+                format!("!{trait_path}")
+            } else {
+                trait_path
+            }),
+            generic,
+            blanket,
+        }),
+        Some(ImplHeader::Derive(_)) => None,
+        None => {
+            let header = header.trim_start_matches("unsafe ");
+            is_impl_header(header).then(|| ImplTarget {
+                self_type: inherent_self_type(header).to_string(),
+                trait_path: None,
+                generic: header.starts_with("impl<"),
+                blanket: false,
+            })
+        }
+    }
+}
+
+/// The `use` path of the item named `name` in the impl header at or above
+/// `line` of `file_path`.
+async fn resolved_item_path(
+    analyzer: &mut RustAnalyzerClient,
+    file_path: &str,
+    line: u32,
+    name: &str,
+) -> Option<String> {
+    let (item_file, inline_modules, name) =
+        resolve_impl_trait(analyzer, file_path, line, name).await?;
+    let origin = DocsOrigin::of_path(&item_file);
+    let mut modules = module_path_from_uri(&item_file);
+    modules.extend(inline_modules);
+    Some(trait_import_path(
+        &origin,
+        crate_name_from_uri(&item_file).as_deref(),
+        crate_name_from_uri(file_path).as_deref(),
+        &modules,
+        &name,
+    ))
+}
+
+pub async fn apply_edit_impl(args: Value, analyzer: &mut RustAnalyzerClient) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let new_text = args
+        .get("new_text")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing new_text parameter"))?;
+    let code_block = args.get("code_block").and_then(|v| v.as_str());
+    let range = args
+        .get("range")
+        .filter(|v| !v.is_null())
+        .map(|v| serde_json::from_value::<Range>(v.clone()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid range parameter: {}", e))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (start, end) = match (range, code_block) {
+        (Some(range), None) => {
+            // A range may come from rust-analyzer positions of an older text.
+            analyzer
+                .ensure_document_current(file_path, &file_content)
+                .await?;
+            resolve_byte_range(&file_content, &range)?
+        }
+        (None, Some(code_block)) => find_unique_block(&file_content, code_block)?,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Provide exactly one of range or code_block"
+            ));
+        }
+    };
+
+    let mut updated = String::with_capacity(file_content.len() + new_text.len());
+    updated.push_str(&file_content[..start]);
+    updated.push_str(new_text);
+    updated.push_str(&file_content[end..]);
+
+    let diff = unified_diff(
+        &file_content,
+        &updated,
+        &format!("a{file_path}"),
+        &format!("b{file_path}"),
+    );
+
+    let result = match analyzer.write_file(file_path, &file_content, &updated).await? {
+        Some(_) => json!({
+            "file_path": file_path,
+            "written": false,
+            "diff": diff,
+            "message": "Writes are disabled (read-only mode); the file was not changed and was not reanalyzed."
+        }),
+        None => {
+            let diagnostics = analyzer.get_diagnostics(file_path).await?;
+            json!({
+                "file_path": file_path,
+                "written": true,
+                "diff": diff,
+                "diagnostics": diagnostics
+            })
+        }
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn dry_run_patch_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let diff = args.get("diff").and_then(|v| v.as_str());
+    let edits = args.get("edits").and_then(|v| v.as_array());
+
+    // (file, original, patched) in the order the files were first touched.
+    let mut files: Vec<(String, String, String)> = Vec::new();
+    match (diff, edits) {
+        (Some(diff), None) => {
+            let patches = parse_unified_diff(diff).map_err(|e| anyhow::anyhow!(e))?;
+            for patch in patches {
+                let target =
+                    patch_target(&patch.old_label, &patch.new_label, analyzer.project_root());
+                let Some(file_path) = target else {
+                    return Err(anyhow::anyhow!(
+                        "{} is not an existing file; new and deleted files are not supported",
+                        patch.new_label
+                    ));
+                };
+                let file_path = file_path.to_string_lossy().into_owned();
+                let index = patched_file(&mut files, &file_path).await?;
+                let patched = patch
+                    .apply(&files[index].2)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                files[index].2 = patched;
+            }
+        }
+        (None, Some(edits)) => {
+            for (idx, edit) in edits.iter().enumerate() {
+                let file_path = edit
+                    .get("file_path")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("Missing file_path in edit {}", idx + 1))?;
+                let new_text = edit
+                    .get("new_text")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("Missing new_text in edit {}", idx + 1))?;
+                let range = edit
+                    .get("range")
+                    .filter(|v| !v.is_null())
+                    .map(|v| serde_json::from_value::<Range>(v.clone()))
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid range in edit {}: {}", idx + 1, e))?;
+                let code_block = edit.get("code_block").and_then(|v| v.as_str());
+
+                let index = patched_file(&mut files, file_path).await?;
+                let content = &files[index].2;
+                let (start, end) = match (range, code_block) {
+                    (Some(range), None) => resolve_byte_range(content, &range)?,
+                    (None, Some(code_block)) => find_unique_block(content, code_block)?,
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Edit {}: provide exactly one of range or code_block",
+                            idx + 1
+                        ));
+                    }
+                };
+                files[index].2 = format!("{}{}{}", &content[..start], new_text, &content[end..]);
+            }
+        }
+        _ => return Err(anyhow::anyhow!("Provide exactly one of diff or edits")),
+    }
+
+    let paths: Vec<&str> = files
+        .iter()
+        .map(|(file_path, _, _)| file_path.as_str())
+        .collect();
+    let checked = analyzer
+        .with_document_text(&paths, async |analyzer| {
+            let mut checked = Vec::new();
+            for (file_path, _, patched) in &files {
+                let diagnostics = analyzer.diagnostics_for_text(file_path, patched).await?;
+                checked.push((file_path.as_str(), diagnostics));
+            }
+            Ok(checked)
+        })
+        .await?;
+
+    let mut errors = 0;
+    let mut unverified = Vec::new();
+    let mut reports = Vec::new();
+    for ((file_path, diagnostics), (_, original, patched)) in checked.iter().zip(&files) {
+        let Some(diagnostics) = diagnostics else {
+            unverified.push(*file_path);
+            continue;
+        };
+        errors += diagnostics
+            .iter()
+            .filter(|diag| diag.severity.unwrap_or(1) == 1)
+            .count();
+        reports.push(json!({
+            "file_path": file_path,
+            "diff": unified_diff(original, patched, &format!("a{file_path}"), &format!("b{file_path}")),
+            "diagnostics": diagnostics
+                .iter()
+                .map(|diag| json!({
+                    "severity": severity_label(diag.severity),
+                    "line": diag.range.start.line + 1,
+                    "column": diag.range.start.character + 1,
+                    "message": diag.message
+                }))
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    let verdict = if errors > 0 {
+        "dirty"
+    } else if unverified.is_empty() {
+        "clean"
+    } else {
+        "unknown"
+    };
+    let result = json!({
+        "verdict": verdict,
+        "errors": errors,
+        "files": reports,
+        "unverified_files": unverified,
+        "note": "Nothing was written. Diagnostics are rust-analyzer's own for the patched files only; cargo check (borrow checking, some trait errors) and breakage in files the patch does not touch are not covered."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn check_function_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let new_body = args
+        .get("new_body")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing new_body parameter"))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    analyzer
+        .ensure_document_current(file_path, &file_content)
+        .await?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let function = analyzer
+        .enclosing_function_range(file_path, Position { line, character })
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not inside a function or method", symbol))?;
+    let start = line_start_offset(&file_content, function.start.line as usize)
+        .ok_or_else(|| anyhow::anyhow!("Function range is past the end of the file"))?;
+    let end = line_start_offset(&file_content, function.end.line as usize + 1)
+        .unwrap_or(file_content.len());
+    let (open, close) = function_body(&file_content[start..end])
+        .map(|(open, close)| (start + open, start + close))
+        .ok_or_else(|| anyhow::anyhow!("'{}' has no body to replace", symbol))?;
+
+    let indent: String = file_content[start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let body = body_block(new_body, &indent);
+    let patched = format!(
+        "{}{}{}",
+        &file_content[..open],
+        body,
+        &file_content[close + 1..]
+    );
+    // The function's lines in the patched text.
+    let first_line = function.start.line;
+    let last_line = patched[..open + body.len()].matches('\n').count() as u32;
+
+    let diagnostics = analyzer
+        .with_document_text(&[file_path], async |analyzer| {
+            analyzer.diagnostics_for_text(file_path, &patched).await
+        })
+        .await?;
+
+    let mut relevant = Vec::new();
+    let mut elsewhere = 0;
+    let mut errors = 0;
+    for diag in diagnostics.iter().flatten() {
+        let inside = diag.range.start.line <= last_line && diag.range.end.line >= first_line;
+        let references = !inside && mentions_identifier(&diag.message, symbol);
+        if !inside && !references {
+            elsewhere += 1;
+            continue;
+        }
+        if diag.severity.unwrap_or(1) == 1 {
+            errors += 1;
+        }
+        relevant.push(json!({
+            "severity": severity_label(diag.severity),
+            "line": diag.range.start.line + 1,
+            "column": diag.range.start.character + 1,
+            "message": diag.message,
+            "location": if inside { "inside" } else { "references" }
+        }));
+    }
+
+    let verdict = match (&diagnostics, errors) {
+        (None, _) => "unknown",
+        (Some(_), 0) => "clean",
+        (Some(_), _) => "dirty",
+    };
+    let result = json!({
+        "file_path": file_path,
+        "function": symbol,
+        "start_line": first_line + 1,
+        "end_line": last_line + 1,
+        "verdict": verdict,
+        "errors": errors,
+        "diagnostics": relevant,
+        "other_diagnostics": elsewhere,
+        "note": "Nothing was written. The body was swapped in rust-analyzer's copy of the file only. Diagnostics inside the function are listed, plus those elsewhere in the file whose message names it (e.g. callers broken by the change); `other_diagnostics` counts the rest. `unknown` means rust-analyzer published no diagnostics in time."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn diagnostics_delta_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let new_text = args
+        .get("new_text")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing new_text parameter"))?;
+    let code_block = args.get("code_block").and_then(|v| v.as_str());
+    let range = args
+        .get("range")
+        .filter(|v| !v.is_null())
+        .map(|v| serde_json::from_value::<Range>(v.clone()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid range parameter: {}", e))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (start, end) = match (range, code_block) {
+        (Some(range), None) => {
+            analyzer
+                .ensure_document_current(file_path, &file_content)
+                .await?;
+            resolve_byte_range(&file_content, &range)?
+        }
+        (None, Some(code_block)) => find_unique_block(&file_content, code_block)?,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Provide exactly one of range or code_block"
+            ));
+        }
+    };
+    let patched = format!(
+        "{}{}{}",
+        &file_content[..start],
+        new_text,
+        &file_content[end..]
+    );
+
+    let (before, after) = analyzer
+        .with_document_text(&[file_path], async |analyzer| {
+            let before = analyzer
+                .diagnostics_for_text(file_path, &file_content)
+                .await?;
+            let after = analyzer.diagnostics_for_text(file_path, &patched).await?;
+            Ok((before, after))
+        })
+        .await?;
+
+    let result = match (before, after) {
+        (Some(before), Some(after)) => {
+            let shift = EditShift::new(&file_content, start, end, new_text);
+            let delta = diagnostic_delta(&before, &after, &shift);
+            let describe = |diags: &[&Diagnostic]| -> Vec<Value> {
+                diags
+                    .iter()
+                    .map(|diag| {
+                        json!({
+                            "severity": severity_label(diag.severity),
+                            "line": diag.range.start.line + 1,
+                            "column": diag.range.start.character + 1,
+                            "code": diag.code,
+                            "message": diag.message
+                        })
+                    })
+                    .collect()
+            };
+            let errors = |diags: &[Diagnostic]| {
+                diags
+                    .iter()
+                    .filter(|diag| diag.severity.unwrap_or(1) == 1)
+                    .count()
+            };
+            let (errors_before, errors_after) = (errors(&before), errors(&after));
+            let verdict = if errors_after < errors_before {
+                "improved"
+            } else if errors_after > errors_before {
+                "regressed"
+            } else if delta.fixed.is_empty() && delta.new.is_empty() {
+                "unchanged"
+            } else {
+                "mixed"
+            };
+            json!({
+                "file_path": file_path,
+                "verdict": verdict,
+                "errors_before": errors_before,
+                "errors_after": errors_after,
+                "fixed": describe(&delta.fixed),
+                "new": describe(&delta.new),
+                "remaining": describe(&delta.remaining),
+                "note": "Nothing was written. Lines of `fixed` refer to the file before the edit, those of `new` and `remaining` to the file after it."
+            })
+        }
+        _ => json!({
+            "file_path": file_path,
+            "verdict": "unknown",
+            "note": "rust-analyzer published no diagnostics in time; nothing was written."
+        }),
+    };
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// How a single replacement moves the positions behind it: the end of the
+/// replaced text before and after the edit, as (line, character).
+struct EditShift {
+    old_end: (u32, u32),
+    new_end: (u32, u32),
+}
+
+impl EditShift {
+    fn new(content: &str, start: usize, end: usize, new_text: &str) -> Self {
+        let position = |text: &str| {
+            let line = text.matches('\n').count() as u32;
+            let line_start = text.rfind('\n').map_or(0, |idx| idx + 1);
+            (line, text[line_start..].encode_utf16().count() as u32)
+        };
+        let start = position(&content[..start]);
+        let new_end = match position(new_text) {
+            (0, character) => (start.0, start.1 + character),
+            (lines, character) => (start.0 + lines, character),
+        };
+        EditShift {
+            old_end: position(&content[..end]),
+            new_end,
+        }
+    }
+
+    /// Where a position of the old text ends up. Positions before the end of
+    /// the replaced text are left as they are.
+    fn apply(&self, position: &Position) -> (u32, u32) {
+        let point = (position.line, position.character);
+        if point < self.old_end {
+            point
+        } else if point.0 == self.old_end.0 {
+            (self.new_end.0, self.new_end.1 + point.1 - self.old_end.1)
+        } else {
+            (self.new_end.0 + point.0 - self.old_end.0, point.1)
+        }
+    }
+}
+
+struct DiagnosticDelta<'a> {
+    fixed: Vec<&'a Diagnostic>,
+    new: Vec<&'a Diagnostic>,
+    remaining: Vec<&'a Diagnostic>,
+}
+
+/// Pairs the diagnostics before and after an edit by (range, code, message),
+/// shifting the old ranges past the edit so untouched code still matches.
+fn diagnostic_delta<'a>(
+    before: &'a [Diagnostic],
+    after: &'a [Diagnostic],
+    shift: &EditShift,
+) -> DiagnosticDelta<'a> {
+    type Key = ((u32, u32), (u32, u32), Option<String>, String);
+    let key = |diag: &Diagnostic, start: (u32, u32), end: (u32, u32)| -> Key {
+        let code = diag.code.as_ref().map(|code| match code {
+            Value::String(code) => code.clone(),
+            code => code.to_string(),
+        });
+        (start, end, code, diag.message.clone())
+    };
+
+    let mut pending: Vec<Option<Key>> = before
+        .iter()
+        .map(|diag| {
+            Some(key(
+                diag,
+                shift.apply(&diag.range.start),
+                shift.apply(&diag.range.end),
+            ))
+        })
+        .collect();
+    let mut new = Vec::new();
+    let mut remaining = Vec::new();
+    for diag in after {
+        let wanted = key(
+            diag,
+            (diag.range.start.line, diag.range.start.character),
+            (diag.range.end.line, diag.range.end.character),
+        );
+        match pending.iter().position(|k| k.as_ref() == Some(&wanted)) {
+            Some(index) => {
+                pending[index] = None;
+                remaining.push(diag);
+            }
+            None => new.push(diag),
+        }
+    }
+    let fixed = before
+        .iter()
+        .zip(&pending)
+        .filter(|(_, key)| key.is_some())
+        .map(|(diag, _)| diag)
+        .collect();
+
+    DiagnosticDelta {
+        fixed,
+        new,
+        remaining,
+    }
+}
+
+/// Byte offsets of the `{` and `}` of the body in a function's source: the
+/// brace pair that closes the function. `None` for a bodiless trait method.
+fn function_body(source: &str) -> Option<(usize, usize)> {
+    let close = source.rfind('}')?;
+    if !source[close + 1..].trim().is_empty() {
+        return None;
+    }
+    source
+        .match_indices('{')
+        .map(|(idx, _)| idx)
+        .find(|&open| find_matching_brace(source, open) == Some(close))
+        .map(|open| (open, close))
+}
+
+/// `new_body` as a braced block. Bare statements are wrapped in braces and
+/// indented one level deeper than the function at `indent`.
+fn body_block(new_body: &str, indent: &str) -> String {
+    let trimmed = new_body.trim();
+    if trimmed.starts_with('{') && trimmed.ends_with('}') {
+        return trimmed.to_string();
+    }
+    let lines: Vec<String> = trimmed
+        .lines()
+        .map(|line| match line.trim() {
+            "" => String::new(),
+            _ => format!("{indent}    {}", line.trim_end()),
+        })
+        .collect();
+    format!("{{\n{}\n{indent}}}", lines.join("\n"))
+}
+
+/// Whether `name` appears in `message` as a whole identifier.
+fn mentions_identifier(message: &str, name: &str) -> bool {
+    message.match_indices(name).any(|(idx, _)| {
+        let before = message[..idx].chars().next_back();
+        let after = message[idx + name.len()..].chars().next();
+        let is_ident = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        !is_ident(before) && !is_ident(after)
+    })
+}
+
+fn severity_label(severity: Option<u32>) -> &'static str {
+    match severity.unwrap_or(1) {
+        1 => "error",
+        2 => "warning",
+        3 => "info",
+        _ => "hint",
+    }
+}
+
+/// Index of `file_path` in the files a dry run patches, reading it from disk
+/// the first time.
+async fn patched_file(files: &mut Vec<(String, String, String)>, file_path: &str) -> Result<usize> {
+    if let Some(index) = files.iter().position(|(path, _, _)| path == file_path) {
+        return Ok(index);
+    }
+    let content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file_path, e))?;
+    files.push((file_path.to_string(), content.clone(), content));
+    Ok(files.len() - 1)
+}
+
+/// The existing file a diff's `---` / `+++` labels name. Labels may be
+/// absolute (`b/home/me/src/lib.rs` as `unified_diff` writes them) or
+/// relative to the project root with git's `a/` / `b/` prefixes.
+fn patch_target(old_label: &str, new_label: &str, root: Option<&Path>) -> Option<PathBuf> {
+    let root = root
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())?;
+    [new_label, old_label]
+        .into_iter()
+        .filter(|label| *label != "/dev/null")
+        .flat_map(|label| {
+            let unprefixed = label
+                .strip_prefix("a/")
+                .or_else(|| label.strip_prefix("b/"))
+                .unwrap_or(label);
+            [
+                PathBuf::from(label),
+                PathBuf::from(&label[label.find('/').unwrap_or(0)..]),
+                root.join(unprefixed),
+            ]
+        })
+        .find(|path| path.is_absolute() && path.is_file())
+}
+
+/// Converts a range of 0-based lines and UTF-16 columns (as LSP counts them)
+/// into byte offsets, refusing positions past the end of a line or the file.
+fn resolve_byte_range(content: &str, range: &Range) -> Result<(usize, usize)> {
+    let offset = |line: u32, character: u32| -> Result<usize> {
+        let line_start = line_start_offset(content, line as usize)
+            .ok_or_else(|| anyhow::anyhow!("Line {} is past the end of the file", line))?;
+        let line_text = content[line_start..].split('\n').next().unwrap_or_default();
+        let width = line_text.encode_utf16().count();
+        if character as usize > width {
+            return Err(anyhow::anyhow!(
+                "Character {} is past the end of line {} ({} UTF-16 code units)",
+                character,
+                line,
+                width
+            ));
+        }
+        Ok(line_start + utf16_column_offset(line_text, character))
+    };
+
+    let start = offset(range.start.line, range.start.character)?;
+    let end = offset(range.end.line, range.end.character)?;
+    if start > end {
+        return Err(anyhow::anyhow!("Range start is after range end"));
+    }
+    Ok((start, end))
+}
+
+/// Byte range of `code_block`, which must occur exactly once in `content`.
+fn find_unique_block(content: &str, code_block: &str) -> Result<(usize, usize)> {
+    if code_block.is_empty() {
+        return Err(anyhow::anyhow!("code_block must not be empty"));
+    }
+    let mut matches = content.match_indices(code_block);
+    match (matches.next(), matches.count()) {
+        (Some((start, _)), 0) => Ok((start, start + code_block.len())),
+        (Some(_), more) => Err(anyhow::anyhow!(
+            "code_block occurs {} times; extend it until it is unique",
+            more + 1
+        )),
+        (None, _) => Err(anyhow::anyhow!("code_block not found in file")),
+    }
+}
+
+pub async fn format_range_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args.get("code_block").and_then(|v| v.as_str());
+    let range = args
+        .get("range")
+        .filter(|v| !v.is_null())
+        .map(|v| serde_json::from_value::<Range>(v.clone()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid range parameter: {}", e))?;
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (start, end) = match (range, code_block) {
+        (Some(range), None) => resolve_byte_range(&file_content, &range)?,
+        (None, Some(code_block)) => find_unique_block(&file_content, code_block)?,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Provide exactly one of range or code_block"
+            ));
+        }
+    };
+    let (first_line, last_line) = line_span(&file_content, start, end);
+    let lines = whole_lines_range(&file_content, first_line, last_line);
+
+    analyzer.sync_document(file_path, &file_content).await?;
+    let mut notes = Vec::new();
+    let (edits, method) = match analyzer.range_formatting(file_path, &lines).await {
+        Ok(edits) => (edits, "rangeFormatting"),
+        Err(e) => {
+            let edits = analyzer.document_formatting(file_path).await?;
+            let total = edits.len();
+            let inside = edits_within_lines(edits, first_line, last_line);
+            notes.push(format!(
+                "Range formatting is unavailable ({}); the whole document was formatted and only the {} of {} edits inside lines {}-{} were kept.",
+                e,
+                inside.len(),
+                total,
+                first_line + 1,
+                last_line + 1
+            ));
+            (inside, "formatting")
+        }
+    };
+
+    let updated = if edits.is_empty() {
+        file_content.clone()
+    } else {
+        analyzer.apply_text_edits(&file_content, edits)?
+    };
+    let diff = unified_diff(
+        &file_content,
+        &updated,
+        &format!("a{file_path}"),
+        &format!("b{file_path}"),
+    );
+
+    let written = if updated == file_content {
+        notes.push("The range is already formatted.".to_string());
+        false
+    } else if analyzer
+        .write_file(file_path, &file_content, &updated)
+        .await?
+        .is_some()
+    {
+        notes.push("Writes are disabled (read-only mode); the file was not changed.".to_string());
+        false
+    } else {
+        analyzer.sync_document(file_path, &updated).await?;
+        true
+    };
+
+    let result = json!({
+        "file_path": file_path,
+        "lines": {"start": first_line + 1, "end": last_line + 1},
+        "method": method,
+        "written": written,
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+pub async fn format_with_config_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let edition = args.get("edition").and_then(|v| v.as_str());
+    let overrides = args.get("config").and_then(|v| v.as_object());
+    let use_project_config = args
+        .get("use_project_config")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let write = args.get("write").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let dir = Path::new(file_path).parent().unwrap_or(Path::new("/"));
+    let config_file = rustfmt_config_file(dir);
+    let file_config = match config_file.as_ref().filter(|_| use_project_config) {
+        Some(path) => Some(
+            fs::read_to_string(path)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?
+                .parse::<toml::Table>()
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    // The edition rustfmt parses with: explicit, then the config file's,
+    // then the package's, as `cargo fmt` passes it.
+    let config_edition = file_config
+        .as_ref()
+        .and_then(|config| config.get("edition"))
+        .and_then(|edition| edition.as_str());
+    let (edition, edition_source) = match (edition, config_edition) {
+        (Some(edition), _) => (Some(edition.to_string()), "parameter"),
+        (None, Some(edition)) => (Some(edition.to_string()), "rustfmt.toml"),
+        (None, None) => match package_edition(Path::new(file_path)).await? {
+            Some(edition) => (Some(edition), "Cargo.toml"),
+            None => (None, "rustfmt default (2015)"),
+        },
+    };
+
+    let mut command = tokio::process::Command::new("rustfmt");
+    command.args(["--emit", "stdout", "--quiet"]);
+    if let Some(edition) = &edition {
+        command.args(["--edition", edition]);
+    }
+    match (&config_file, use_project_config) {
+        (Some(path), true) => {
+            command.arg("--config-path").arg(path);
+        }
+        // Keep rustfmt from finding a config file next to the server.
+        _ => {
+            command.current_dir(std::env::temp_dir());
+        }
+    }
+    let inline = overrides.map(inline_rustfmt_config).unwrap_or_default();
+    if !inline.is_empty() {
+        command.arg("--config").arg(&inline);
+    }
+
+    let mut child = command
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run rustfmt: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(file_content.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("rustfmt failed: {}", stderr));
+    }
+    let formatted = String::from_utf8_lossy(&output.stdout).into_owned();
+
+    let diff = unified_diff(
+        &file_content,
+        &formatted,
+        &format!("a{file_path}"),
+        &format!("b{file_path}"),
+    );
+    let mut notes = Vec::new();
+    let written = if formatted == file_content {
+        notes.push("The file is already formatted with this configuration.".to_string());
+        false
+    } else if !write {
+        false
+    } else if analyzer
+        .write_file(file_path, &file_content, &formatted)
+        .await?
+        .is_some()
+    {
+        notes.push("Writes are disabled (read-only mode); the file was not changed.".to_string());
+        false
+    } else {
+        analyzer.sync_document(file_path, &formatted).await?;
+        true
+    };
+    if config_file.is_some() && !use_project_config {
+        notes.push("The project's rustfmt config was ignored as requested.".to_string());
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "changed": formatted != file_content,
+        "written": written,
+        "diff": diff,
+        "config": {
+            "config_file": config_file.map(|path| path.display().to_string()),
+            "config_file_used": use_project_config && file_config.is_some(),
+            "edition": edition,
+            "edition_source": edition_source,
+            "overrides": inline
+        },
+        "warnings": stderr,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// The `rustfmt.toml` (or `.rustfmt.toml`) rustfmt would use for a file in
+/// `dir`: the first one found walking upward.
+fn rustfmt_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|dir| [dir.join(".rustfmt.toml"), dir.join("rustfmt.toml")])
+        .find(|path| path.is_file())
+}
+
+/// rustfmt's `--config` argument for inline options, `max_width=80,...`.
+fn inline_rustfmt_config(options: &serde_json::Map<String, Value>) -> String {
+    options
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(text) => format!("{key}={text}"),
+            other => format!("{key}={other}"),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// First and last 0-based line touched by the byte range `start..end`.
+fn line_span(content: &str, start: usize, end: usize) -> (u32, u32) {
+    let line_of = |offset: usize| content[..offset].matches('\n').count() as u32;
+    // A selection ending right after a newline does not touch the next line.
+    let last = if end > start && content[..end].ends_with('\n') {
+        end - 1
+    } else {
+        end
+    };
+    (line_of(start), line_of(last))
+}
+
+/// LSP range covering lines `first..=last` including the final line break,
+/// so the formatter may rewrite the lines in full.
+fn whole_lines_range(content: &str, first: u32, last: u32) -> Range {
+    let end = if content.matches('\n').count() > last as usize {
+        Position {
+            line: last + 1,
+            character: 0,
+        }
+    } else {
+        let line = content.lines().nth(last as usize).unwrap_or_default();
+        Position {
+            line: last,
+            character: line.encode_utf16().count() as u32,
+        }
+    };
+    Range {
+        start: Position {
+            line: first,
+            character: 0,
+        },
+        end,
+    }
+}
+
+/// Whole-document formatting edits that start and end inside lines
+/// `first..=last`; an edit may also end at the start of the following line.
+fn edits_within_lines(edits: Vec<TextEdit>, first: u32, last: u32) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .filter(|edit| {
+            let (start, end) = (&edit.range.start, &edit.range.end);
+            (first..=last).contains(&start.line)
+                && (end.line <= last || (end.line == last + 1 && end.character == 0))
+        })
+        .collect()
+}
+
+pub(crate) struct HoverSignature {
+    pub name: String,
+    pub owner: Option<String>,
+    pub has_receiver: bool,
+    pub receiver: Option<String>,
+    /// Parameter patterns (usually plain names), parallel to `param_types`.
+    pub param_names: Vec<String>,
+    pub param_types: Vec<String>,
+    pub return_type: Option<String>,
+    pub is_async: bool,
+    pub is_unsafe: bool,
+    pub is_const: bool,
+    pub is_generic: bool,
+    /// The declaration as shown in the hover, from the `fn` line onwards.
+    pub signature: String,
+}
+
+struct StubInsertion {
+    content: String,
+    line: usize,
+    created_module: bool,
+}
+
+fn hover_code_blocks(hover: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+
+    for line in hover.lines() {
+        if line.trim_start().starts_with("```") {
+            match current.take() {
+                Some(lines) => blocks.push(lines.join("\n")),
+                None => current = Some(Vec::new()),
+            }
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+
+    blocks
+}
+
+pub(crate) fn parse_hover_signature(hover: &str) -> Option<HoverSignature> {
+    let blocks = hover_code_blocks(hover);
+    let sig_index = blocks.iter().position(|block| block.contains("fn "))?;
+    let block = &blocks[sig_index];
+
+    let mut owner = block
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("impl "))
+        .map(|header| {
+            let header = header.rsplit(" for ").next().unwrap_or(header);
+            strip_generics(header.trim()).to_string()
+        });
+    if owner.is_none() && sig_index > 0 {
+        owner = blocks[0]
+            .lines()
+            .next()
+            .and_then(|path| path.rsplit("::").next())
+            .filter(|segment| segment.starts_with(|c: char| c.is_uppercase()))
+            .map(|segment| segment.trim().to_string());
+    }
+
+    let fn_idx = block.find("fn ")?;
+    let line_start = block[..fn_idx].rfind('\n').map_or(0, |idx| idx + 1);
+    let qualifiers = &block[line_start..fn_idx];
+    let is_async = block[..fn_idx].contains("async ");
+    let after_fn = &block[fn_idx + 3..];
+    let name_end = after_fn.find(['<', '('])?;
+    let name = after_fn[..name_end].trim().to_string();
+    let is_generic = after_fn[name_end..].starts_with('<');
+
+    let open = name_end + after_fn[name_end..].find('(')?;
+    let close = open + find_matching_paren(&after_fn[open..])?;
+    let params = split_top_level(&after_fn[open + 1..close]);
+
+    let mut receiver = None;
+    let mut param_names = Vec::new();
+    let mut param_types = Vec::new();
+    for (idx, param) in params.iter().enumerate() {
+        if idx == 0 && is_self_receiver(param) {
+            receiver = Some(param.trim().to_string());
+            continue;
+        }
+        let (name, ty) = param.split_once(": ").unwrap_or(("_", param));
+        param_names.push(name.trim().to_string());
+        param_types.push(ty.trim().to_string());
+    }
+
+    let after_params = after_fn[close + 1..].trim_start();
+    let return_type = after_params.strip_prefix("->").map(|ret| {
+        let ret = ret.split("\nwhere").next().unwrap_or(ret);
+        let ret = ret.split(" where ").next().unwrap_or(ret);
+        ret.trim().trim_end_matches(['{', ';']).trim().to_string()
+    });
+
+    let signature = block[line_start..].trim().to_string();
+
+    Some(HoverSignature {
+        name,
+        owner,
+        has_receiver: receiver.is_some(),
+        receiver,
+        param_names,
+        param_types,
+        return_type,
+        is_async,
+        is_unsafe: qualifiers.contains("unsafe "),
+        is_const: qualifiers.contains("const "),
+        is_generic,
+        signature,
+    })
+}
+
+fn strip_generics(path: &str) -> &str {
+    path.split('<').next().unwrap_or(path).trim()
+}
+
+pub(crate) fn find_matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0i32;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub(crate) fn split_top_level(list: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    let mut prev = '\0';
+
+    for c in list.chars() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' if prev != '-' => depth -= 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                if !current.trim().is_empty() {
+                    parts.push(current.trim().to_string());
+                }
+                current.clear();
+                prev = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        prev = c;
+    }
+
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+    parts
+}
+
+fn is_self_receiver(param: &str) -> bool {
+    let pattern = param.split(':').next().unwrap_or(param).trim();
+    let pattern = pattern.trim_start_matches('&');
+    let pattern = pattern
+        .split_whitespace()
+        .filter(|part| !part.starts_with('\'') && *part != "mut")
+        .collect::<Vec<_>>()
+        .join(" ");
+    pattern == "self"
+}
+
+fn placeholder_for_type(ty: &str) -> String {
+    let ty = ty.trim();
+    match ty {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+        | "u128" | "usize" => "0".to_string(),
+        "f32" | "f64" => "0.0".to_string(),
+        "bool" => "false".to_string(),
+        "char" => "'a'".to_string(),
+        "String" => "String::new()".to_string(),
+        _ if ty.starts_with('&') && ty.ends_with("str") => "\"\"".to_string(),
+        _ if ty.starts_with("&[") => "&[]".to_string(),
+        _ if ty.starts_with("&mut [") => "&mut []".to_string(),
+        _ if ty.starts_with("Vec<") => "Vec::new()".to_string(),
+        _ if ty.starts_with("Option<") => "None".to_string(),
+        _ => "todo!()".to_string(),
+    }
+}
+
+fn render_test_stub(test_name: &str, signature: &HoverSignature) -> String {
+    let path = match &signature.owner {
+        Some(owner) => format!("super::{}::{}", owner, signature.name),
+        None => format!("super::{}", signature.name),
+    };
+
+    let mut args = Vec::new();
+    if signature.has_receiver {
+        args.push("todo!()".to_string());
+    }
+    args.extend(signature.param_types.iter().map(|ty| placeholder_for_type(ty)));
+
+    let call = format!("{}({})", path, args.join(", "));
+    let body = if signature.is_async {
+        format!(
+            "    let _future = {call};\n    // TODO: drive the future with an async runtime and assert on its output\n"
+        )
+    } else {
+        format!("    let _result = {call};\n    // TODO: assert on the result\n")
+    };
+
+    format!("#[test]\nfn {test_name}() {{\n{body}}}")
+}
+
+fn indent_block(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn find_test_module(content: &str) -> Option<(usize, usize)> {
+    let mut search_from = 0;
+    while let Some(offset) = content[search_from..].find("#[cfg(test)]") {
+        let attr_idx = search_from + offset;
+        let after_attr = attr_idx + "#[cfg(test)]".len();
+        let rest = content[after_attr..].trim_start();
+        let rest = rest.strip_prefix("pub ").unwrap_or(rest);
+        if rest.starts_with("mod ") {
+            let mod_idx = content.len() - rest.len();
+            let open_idx = mod_idx + rest.find('{')?;
+            if content[mod_idx..open_idx].contains(';') {
+                search_from = after_attr;
+                continue;
+            }
+            let close_idx = find_matching_brace(content, open_idx)?;
+            return Some((mod_idx, close_idx));
+        }
+        search_from = after_attr;
+    }
+    None
+}
+
+pub(crate) fn find_matching_brace(text: &str, open_idx: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut idx = open_idx;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'/' if bytes.get(idx + 1) == Some(&b'/') => {
+                while idx < bytes.len() && bytes[idx] != b'\n' {
+                    idx += 1;
+                }
+            }
+            b'/' if bytes.get(idx + 1) == Some(&b'*') => {
+                idx += 2;
+                while idx + 1 < bytes.len() && !(bytes[idx] == b'*' && bytes[idx + 1] == b'/') {
+                    idx += 1;
+                }
+                idx += 1;
+            }
+            b'"' => {
+                idx += 1;
+                while idx < bytes.len() && bytes[idx] != b'"' {
+                    if bytes[idx] == b'\\' {
+                        idx += 1;
+                    }
+                    idx += 1;
+                }
+            }
+            b'\'' => {
+                // Skip char literals such as '{' or '\'' but leave lifetimes alone.
+                if bytes.get(idx + 1) == Some(&b'\\') {
+                    idx += 2;
+                    while idx < bytes.len() && bytes[idx] != b'\'' {
+                        idx += 1;
+                    }
+                } else if bytes.get(idx + 2) == Some(&b'\'') {
+                    idx += 2;
+                }
+            }
+            b'{' => depth += 1,
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+
+    None
+}
+
+fn insert_test_stub(content: &str, stub: &str) -> StubInsertion {
+    if let Some((mod_idx, close_idx)) = find_test_module(content) {
+        let mod_line_start = content[..mod_idx].rfind('\n').map(|p| p + 1).unwrap_or(0);
+        let base_indent: String = content[mod_line_start..]
+            .chars()
+            .take_while(|c| c.is_whitespace() && *c != '\n')
+            .collect();
+        let indent = format!("{base_indent}    ");
+
+        let close_line_start = content[..close_idx].rfind('\n').map(|p| p + 1).unwrap_or(0);
+        let (insert_at, insertion) = if content[close_line_start..close_idx].trim().is_empty() {
+            (
+                close_line_start,
+                format!("\n{}\n", indent_block(stub, &indent)),
+            )
+        } else {
+            (
+                close_idx,
+                format!("\n{}\n{}", indent_block(stub, &indent), base_indent),
+            )
+        };
+
+        let mut updated = String::with_capacity(content.len() + insertion.len());
+        updated.push_str(&content[..insert_at]);
+        updated.push_str(&insertion);
+        updated.push_str(&content[insert_at..]);
+
+        let line = content[..insert_at].matches('\n').count() + 1;
+        return StubInsertion {
+            content: updated,
+            line,
+            created_module: false,
+        };
+    }
+
+    let mut updated = content.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push('\n');
+    let line = updated.matches('\n').count() + 2;
+    updated.push_str(&format!(
+        "#[cfg(test)]\nmod tests {{\n{}\n}}\n",
+        indent_block(stub, "    ")
+    ));
+
+    StubInsertion {
+        content: updated,
+        line,
+        created_module: true,
+    }
+}
+
+/// Returns the lines an edit adds to `content`, i.e. the lines of each
+/// replacement that were not already present in the range it replaces.
+fn inserted_lines(content: &str, edits: &[TextEdit]) -> Vec<String> {
+    let mut inserted = Vec::new();
+    for edit in edits {
+        let mut existing: Vec<String> = text_in_range(content, &edit.range)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect();
+
+        for line in edit.new_text.lines() {
+            let line = line.trim();
+            if line.is_empty() || matches!(line, "{" | "}") {
+                continue;
+            }
+            match existing.iter().position(|old| old == line) {
+                Some(idx) => {
+                    existing.remove(idx);
+                }
+                None => inserted.push(line.to_string()),
+            }
+        }
+    }
+    inserted
+}
+
+pub(crate) fn text_in_range(content: &str, range: &Range) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start_line = range.start.line as usize;
+    let end_line = (range.end.line as usize).min(lines.len().saturating_sub(1));
+    if start_line >= lines.len() || start_line > end_line {
+        return String::new();
+    }
+
+    let mut selected = Vec::new();
+    for (idx, line) in lines[start_line..=end_line].iter().enumerate() {
+        let line_idx = start_line + idx;
+        let start = if line_idx == start_line { range.start.character as usize } else { 0 };
+        let text: String = if line_idx == end_line {
+            line.chars().take(range.end.character as usize).skip(start).collect()
+        } else {
+            line.chars().skip(start).collect()
+        };
+        selected.push(text);
+    }
+    selected.join("\n")
+}
+
+pub async fn generate_conversion_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let source_type = args
+        .get("source_type")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing source_type parameter"))?;
+    let target_type = args
+        .get("target_type")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing target_type parameter"))?;
+    let kind = match args.get("kind").and_then(|v| v.as_str()).unwrap_or("From") {
+        "From" => ConversionKind::From,
+        "TryFrom" => ConversionKind::TryFrom {
+            error_type: args
+                .get("error_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or(DEFAULT_CONVERSION_ERROR)
+                .to_string(),
+        },
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid kind '{}': expected \"From\" or \"TryFrom\"",
+                other
+            ));
+        }
+    };
+    let insert = args
+        .get("insert")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let mut definitions = Vec::new();
+    for path in [source_type, target_type] {
+        let location = resolve_unique_path(path, analyzer).await?;
+        let file_path = location
+            .uri
+            .strip_prefix("file://")
+            .unwrap_or(&location.uri)
+            .to_string();
+        let start = &location.range.start;
+        let (source, range, actual_path) = analyzer
+            .get_symbol_source(&file_path, start.line, start.character)
+            .await?;
+        let definition = parse_struct_source(&source)
+            .ok_or_else(|| anyhow::anyhow!("`{}` is not a struct", path))?;
+        definitions.push((definition, range, actual_path));
+    }
+    let (target, target_range, target_file) = definitions.pop().expect("two definitions");
+    let (source, _, source_file) = definitions.pop().expect("two definitions");
+
+    let conversion = render_conversion(&source, &target, &kind);
+
+    let mut notes = Vec::new();
+    if !source.generics.is_empty() || !target.generics.is_empty() {
+        notes
+            .push("Generic parameters are not filled in; add them to the impl header.".to_string());
+    }
+    if source_file != target_file {
+        notes.push(format!(
+            "`{}` is defined in {}; make sure it is in scope where the impl goes.",
+            source.name, source_file
+        ));
+    }
+
+    let mut inserted_line = None;
+    let mut written = false;
+    let mut diff = None;
+    if insert {
+        let file_content = fs::read_to_string(&target_file)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        let header = format!("{}<{}> for {}", kind.trait_name(), source.name, target.name);
+        if file_content.contains(&header) {
+            return Err(anyhow::anyhow!(
+                "{} already contains `impl {}`",
+                target_file,
+                header
+            ));
+        }
+
+        let (updated, line) = insert_after_line(
+            &file_content,
+            target_range.end.line as usize,
+            &conversion.text,
+        );
+        diff = analyzer
+            .write_file(&target_file, &file_content, &updated)
+            .await?;
+        written = diff.is_none();
+        inserted_line = Some(line + 1);
+        if diff.is_some() {
+            notes.push("Writes are disabled (read-only mode); the file was not changed. See `diff` for the proposed edit.".to_string());
+        }
+    }
+
+    let result = json!({
+        "kind": kind.trait_name(),
+        "source_type": source.name,
+        "target_type": target.name,
+        "file_path": target_file,
+        "code": conversion.text,
+        "mapped_fields": conversion.mapped,
+        "todo_fields": conversion.todo,
+        "unused_source_fields": conversion.unused,
+        "inserted_at_line": inserted_line,
+        "written": written,
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Boxed errors accept any `std::error::Error` through `?`, which suits the
+/// pre-filled `try_into()?` mappings.
+const DEFAULT_CONVERSION_ERROR: &str = "Box<dyn std::error::Error + Send + Sync>";
+
+enum ConversionKind {
+    From,
+    TryFrom { error_type: String },
+}
+
+impl ConversionKind {
+    fn trait_name(&self) -> &'static str {
+        match self {
+            ConversionKind::From => "From",
+            ConversionKind::TryFrom { .. } => "TryFrom",
+        }
+    }
+}
+
+struct Conversion {
+    text: String,
+    mapped: Vec<String>,
+    todo: Vec<String>,
+    unused: Vec<String>,
+}
+
+/// Renders `impl From<Source> for Target` (or `TryFrom`), mapping target
+/// fields from source fields of the same name (or position, for tuple
+/// structs). Differing field types go through `into()` / `try_into()?`;
+/// fields without a counterpart are left as `todo!()`.
+fn render_conversion(
+    source: &StructDefinition,
+    target: &StructDefinition,
+    kind: &ConversionKind,
+) -> Conversion {
+    let key = |idx: usize, field: &VariantField| field.name.clone().unwrap_or(idx.to_string());
+    let same_type = |a: &str, b: &str| {
+        a.split_whitespace().collect::<String>() == b.split_whitespace().collect::<String>()
+    };
+
+    let mut mapped = Vec::new();
+    let mut todo = Vec::new();
+    let mut used = Vec::new();
+    let values: Vec<(String, String)> = target
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let name = key(idx, field);
+            let counterpart = source
+                .fields
+                .iter()
+                .enumerate()
+                .find(|(source_idx, source_field)| key(*source_idx, source_field) == name);
+            let value = match (counterpart, kind) {
+                (None, _) => {
+                    todo.push(name.clone());
+                    "todo!()".to_string()
+                }
+                (Some((_, source_field)), _) if same_type(&source_field.ty, &field.ty) => {
+                    format!("value.{name}")
+                }
+                (Some(_), ConversionKind::From) => format!("value.{name}.into()"),
+                (Some(_), ConversionKind::TryFrom { .. }) => format!("value.{name}.try_into()?"),
+            };
+            if counterpart.is_some() {
+                mapped.push(name.clone());
+                used.push(name.clone());
+            }
+            (name, value)
+        })
+        .collect();
+
+    let unused = source
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| key(idx, field))
+        .filter(|name| !used.contains(name))
+        .collect();
+
+    let is_tuple = target
+        .fields
+        .first()
+        .is_some_and(|field| field.name.is_none());
+    let indent = match kind {
+        ConversionKind::From => "        ",
+        ConversionKind::TryFrom { .. } => "            ",
+    };
+    let construct = if target.fields.is_empty() {
+        "Self".to_string()
+    } else if is_tuple {
+        let values: Vec<_> = values.into_iter().map(|(_, value)| value).collect();
+        format!("Self({})", values.join(", "))
+    } else {
+        let mut text = "Self {\n".to_string();
+        for (name, value) in values {
+            text.push_str(&format!("{indent}    {name}: {value},\n"));
+        }
+        text.push_str(&format!("{indent}}}"));
+        text
+    };
+
+    let (source_name, target_name) = (&source.name, &target.name);
+    let text = match kind {
+        ConversionKind::From => format!(
+            "impl From<{source_name}> for {target_name} {{\n    fn from(value: {source_name}) -> Self {{\n        {construct}\n    }}\n}}\n"
+        ),
+        ConversionKind::TryFrom { error_type } => format!(
+            "impl TryFrom<{source_name}> for {target_name} {{\n    type Error = {error_type};\n\n    fn try_from(value: {source_name}) -> Result<Self, Self::Error> {{\n        Ok({construct})\n    }}\n}}\n"
+        ),
+    };
+
+    Conversion {
+        text,
+        mapped,
+        todo,
+        unused,
+    }
+}
+
+/// Inserts `block` after 0-based `line`, separated by a blank line. Returns the
+/// new content and the 0-based line where `block` starts.
+fn insert_after_line(content: &str, line: usize, block: &str) -> (String, usize) {
+    let lines: Vec<&str> = content.lines().collect();
+    let split = (line + 1).min(lines.len());
+    let mut updated = lines[..split].join("\n");
+    updated.push_str("\n\n");
+    updated.push_str(block);
+    if split < lines.len() {
+        updated.push('\n');
+        updated.push_str(&lines[split..].join("\n"));
+    }
+    if content.ends_with('\n') && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    (updated, split + 1)
+}
+
+pub async fn generate_default_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let insert = args
+        .get("insert")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+    let definition = parse_struct_source(&source)
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a struct", symbol))?;
+
+    let default = render_default(&definition);
+    let derivable = default.fallback.is_empty();
+
+    let mut notes = Vec::new();
+    if !definition.generics.is_empty() {
+        notes
+            .push("Generic parameters are not filled in; add them to the impl header.".to_string());
+    }
+    if derivable {
+        notes.push("Every field gets its type's own default, so `#[derive(Default)]` is equivalent and shorter.".to_string());
+    } else {
+        notes.push(format!(
+            "`#[derive(Default)]` also works if {} implement Default.",
+            default.fallback.join(", ")
+        ));
+    }
+
+    let mut inserted_line = None;
+    let mut written = false;
+    let mut diff = None;
+    if insert {
+        let target_content = fs::read_to_string(&actual_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        let header = format!("Default for {}", definition.name);
+        if target_content.contains(&format!("impl {header}"))
+            || derives(&source).iter().any(|derived| derived == "Default")
+        {
+            return Err(anyhow::anyhow!(
+                "`{}` already implements Default",
+                definition.name
+            ));
+        }
+
+        let (updated, line) =
+            insert_after_line(&target_content, range.end.line as usize, &default.text);
+        diff = analyzer
+            .write_file(&actual_path, &target_content, &updated)
+            .await?;
+        written = diff.is_none();
+        inserted_line = Some(line + 1);
+        if diff.is_some() {
+            notes.push("Writes are disabled (read-only mode); the file was not changed. See `diff` for the proposed edit.".to_string());
+        }
+    }
+
+    let result = json!({
+        "struct": definition.name,
+        "file_path": actual_path,
+        "suggestion": if derivable { "derive" } else { "impl" },
+        "derive": derivable.then_some("#[derive(Default)]"),
+        "code": default.text,
+        "defaults": default.values,
+        "fallback_fields": default.fallback,
+        "inserted_at_line": inserted_line,
+        "written": written,
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+struct DefaultImpl {
+    text: String,
+    /// Field name (or position) and the value it gets.
+    values: Vec<(String, String)>,
+    /// Fields left as `Default::default()`.
+    fallback: Vec<String>,
+}
+
+/// Renders `impl Default for Name`, giving each field the literal or
+/// constructor its type defaults to, or `Default::default()` when the type
+/// is not recognized.
+fn render_default(definition: &StructDefinition) -> DefaultImpl {
+    let mut values = Vec::new();
+    let mut fallback = Vec::new();
+    for (idx, field) in definition.fields.iter().enumerate() {
+        let name = field.name.clone().unwrap_or(idx.to_string());
+        let value = default_value(&field.ty).unwrap_or_else(|| {
+            fallback.push(name.clone());
+            "Default::default()".to_string()
+        });
+        values.push((name, value));
+    }
+
+    let is_tuple = definition
+        .fields
+        .first()
+        .is_some_and(|field| field.name.is_none());
+    let construct = if definition.fields.is_empty() {
+        "Self".to_string()
+    } else if is_tuple {
+        let values: Vec<_> = values.iter().map(|(_, value)| value.as_str()).collect();
+        format!("Self({})", values.join(", "))
+    } else {
+        let mut text = "Self {\n".to_string();
+        for (name, value) in &values {
+            text.push_str(&format!("            {name}: {value},\n"));
+        }
+        text.push_str("        }");
+        text
+    };
+
+    let name = &definition.name;
+    DefaultImpl {
+        text: format!(
+            "impl Default for {name} {{\n    fn default() -> Self {{\n        {construct}\n    }}\n}}\n"
+        ),
+        values,
+        fallback,
+    }
+}
+
+/// The value `Default::default()` produces for a std type, spelled out, or
+/// `None` for types whose default is not obvious from their name.
+fn default_value(ty: &str) -> Option<String> {
+    let ty = ty.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (base, args) = match ty.find('<') {
+        Some(open) if ty.ends_with('>') => (&ty[..open], Some(&ty[open + 1..ty.len() - 1])),
+        _ => (ty.as_str(), None),
+    };
+    let name = last_path_segment(base.trim());
+
+    let value = match (name, args) {
+        (
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize",
+            None,
+        ) => "0".to_string(),
+        ("f32" | "f64", None) => "0.0".to_string(),
+        ("bool", None) => "false".to_string(),
+        ("char", None) => "'\\0'".to_string(),
+        ("()", None) => "()".to_string(),
+        ("Option", Some(_)) => "None".to_string(),
+        ("PhantomData", _) => "PhantomData".to_string(),
+        ("Duration", None) => "Duration::ZERO".to_string(),
+        ("AtomicBool", None) => "AtomicBool::new(false)".to_string(),
+        (atomic, None) if atomic.starts_with("Atomic") && atomic != "AtomicPtr" => {
+            format!("{atomic}::new(0)")
+        }
+        (
+            "String" | "PathBuf" | "OsString" | "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap"
+            | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet",
+            _,
+        ) => format!("{name}::new()"),
+        _ if ty == "&str" || ty == "&'static str" => "\"\"".to_string(),
+        _ if ty.starts_with("&[") || ty.starts_with("&'static [") => "&[]".to_string(),
+        _ if ty.starts_with('(') && ty.ends_with(')') => {
+            let items = split_top_level(&ty[1..ty.len() - 1]);
+            let values = items
+                .iter()
+                .map(|item| default_value(item))
+                .collect::<Option<Vec<_>>>()?;
+            match values.len() {
+                1 => format!("({},)", values[0]),
+                _ => format!("({})", values.join(", ")),
+            }
+        }
+        _ if ty.starts_with('[') && ty.ends_with(']') => {
+            // `[v; N]` needs a `Copy` element; numbers and bools are.
+            let (element, len) = ty[1..ty.len() - 1].rsplit_once(';')?;
+            let value = default_value(element)?;
+            if !(value == "0" || value == "0.0" || value == "false") {
+                return None;
+            }
+            format!("[{value}; {}]", len.trim())
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// The traits listed in `#[derive(..)]` attributes of an item's source.
+pub(crate) fn derives(source: &str) -> Vec<String> {
+    source
+        .match_indices("#[derive(")
+        .filter_map(|(idx, _)| {
+            let open = idx + "#[derive".len();
+            let close = source[open..].find(')')?;
+            Some(split_top_level(&source[open + 1..open + close]))
+        })
+        .flatten()
+        .map(|path| last_path_segment(&path).to_string())
+        .collect()
+}
+
+pub async fn generate_builder_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let symbol = args
+        .get("symbol")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing symbol parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let setters = match args
+        .get("setters")
+        .and_then(|v| v.as_str())
+        .unwrap_or("owned")
+    {
+        "owned" => SetterStyle::Owned,
+        "mut_ref" => SetterStyle::MutRef,
+        other => {
+            return Err(anyhow::anyhow!(
+                "Unknown setters style '{}': expected `owned` or `mut_ref`",
+                other
+            ));
+        }
+    };
+    let required: Option<Vec<&str>> = args
+        .get("required")
+        .and_then(|v| v.as_array())
+        .map(|names| names.iter().filter_map(|name| name.as_str()).collect());
+    let insert = args
+        .get("insert")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    let (line, character) = find_symbol_location(&file_content, symbol, code_block, occurrence)?;
+    let (source, range, actual_path) = analyzer
+        .get_symbol_source(file_path, line, character)
+        .await?;
+    let definition = parse_struct_source(&source)
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a struct", symbol))?;
+    if definition.fields.is_empty() || definition.fields.iter().any(|field| field.name.is_none()) {
+        return Err(anyhow::anyhow!(
+            "`{}` has no named fields to build; builders need a struct with named fields",
+            definition.name
+        ));
+    }
+    if let Some(required) = &required
+        && let Some(unknown) = required.iter().find(|name| {
+            !definition
+                .fields
+                .iter()
+                .any(|field| field.name.as_deref() == Some(**name))
+        })
+    {
+        return Err(anyhow::anyhow!(
+            "`{}` has no field named `{}`",
+            definition.name,
+            unknown
+        ));
+    }
+
+    let visibility = match declared_visibility(&source) {
+        ItemVisibility::Private => String::new(),
+        visibility => format!("{visibility} "),
+    };
+    let builder = render_builder(&definition, &visibility, setters, required.as_deref());
+
+    let mut notes = Vec::new();
+    if !definition.generics.is_empty() {
+        notes.push(
+            "Generic parameters are not filled in; add them to the builder and its impls."
+                .to_string(),
+        );
+    }
+    if !builder.defaulted.is_empty() {
+        notes.push(format!(
+            "build() falls back to `Default::default()` for {}, whose types must implement Default.",
+            builder.defaulted.join(", ")
+        ));
+    }
+    if setters == SetterStyle::MutRef {
+        notes.push(
+            "`mut_ref` setters leave the builder in place, so build() clones the fields; their types must implement Clone.".to_string(),
+        );
+    }
+
+    let mut inserted_line = None;
+    let mut written = false;
+    let mut diff = None;
+    if insert {
+        let target_content = fs::read_to_string(&actual_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+        if target_content.contains(&format!("struct {}Builder", definition.name)) {
+            return Err(anyhow::anyhow!(
+                "`{}Builder` already exists in {}",
+                definition.name,
+                actual_path
+            ));
+        }
+
+        let (updated, line) =
+            insert_after_line(&target_content, range.end.line as usize, &builder.text);
+        diff = analyzer
+            .write_file(&actual_path, &target_content, &updated)
+            .await?;
+        written = diff.is_none();
+        inserted_line = Some(line + 1);
+        if diff.is_some() {
+            notes.push("Writes are disabled (read-only mode); the file was not changed. See `diff` for the proposed edit.".to_string());
+        }
+    }
+
+    let result = json!({
+        "struct": definition.name,
+        "builder": format!("{}Builder", definition.name),
+        "file_path": actual_path,
+        "setters": if setters == SetterStyle::Owned { "owned" } else { "mut_ref" },
+        "fallible": !builder.required.is_empty(),
+        "required_fields": builder.required,
+        "optional_fields": builder.optional,
+        "defaulted_fields": builder.defaulted,
+        "code": builder.text,
+        "inserted_at_line": inserted_line,
+        "written": written,
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// How generated builder setters take the builder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetterStyle {
+    /// `fn field(mut self, ..) -> Self`, for chaining from `builder()`.
+    Owned,
+    /// `fn field(&mut self, ..) -> &mut Self`, for builders kept in a variable.
+    MutRef,
+}
+
+struct BuilderCode {
+    text: String,
+    /// Fields build() fails without.
+    required: Vec<String>,
+    /// `Option` fields, left `None` when not set.
+    optional: Vec<String>,
+    /// Fields that are neither, filled with `Default::default()`.
+    defaulted: Vec<String>,
+}
+
+/// Renders `NameBuilder` with one setter per field and a `build()`, plus
+/// `Name::builder()`. Unless `required` names them, the required fields are
+/// those that are not an `Option`; build() returns a `Result` when any are.
+fn render_builder(
+    definition: &StructDefinition,
+    visibility: &str,
+    setters: SetterStyle,
+    required: Option<&[&str]>,
+) -> BuilderCode {
+    let name = &definition.name;
+    let mut fields = String::new();
+    let mut methods = Vec::new();
+    let mut values = String::new();
+    let mut code = BuilderCode {
+        text: String::new(),
+        required: Vec::new(),
+        optional: Vec::new(),
+        defaulted: Vec::new(),
+    };
+    for field in &definition.fields {
+        let Some(field_name) = &field.name else {
+            continue;
+        };
+        let ty = collapse_whitespace(&field.ty);
+        let inner = option_inner_type(&ty);
+        let is_required = match required {
+            Some(required) => required.contains(&field_name.as_str()),
+            None => inner.is_none(),
+        };
+        // An `Option` field is stored as is, and its setter takes the inner type.
+        let (stored, argument) = match inner {
+            Some(inner) => (ty.clone(), inner.to_string()),
+            None => (format!("Option<{ty}>"), ty.clone()),
+        };
+        fields.push_str(&format!("    {field_name}: {stored},\n"));
+
+        let (receiver, returns) = match setters {
+            SetterStyle::Owned => ("mut self", "Self"),
+            SetterStyle::MutRef => ("&mut self", "&mut Self"),
+        };
+        methods.push(format!(
+            "    {visibility}fn {field_name}({receiver}, {field_name}: {argument}) -> {returns} {{\n        self.{field_name} = Some({field_name});\n        self\n    }}\n"
+        ));
+
+        let take = match setters {
+            SetterStyle::Owned => format!("self.{field_name}"),
+            SetterStyle::MutRef => format!("self.{field_name}.clone()"),
+        };
+        let bare_name = field_name.trim_start_matches("r#");
+        let value = if is_required {
+            code.required.push(field_name.clone());
+            if inner.is_some() {
+                format!("Some({take}.ok_or(\"missing field `{bare_name}`\")?)")
+            } else {
+                format!("{take}.ok_or(\"missing field `{bare_name}`\")?")
+            }
+        } else if inner.is_some() {
+            code.optional.push(field_name.clone());
+            take
+        } else {
+            code.defaulted.push(field_name.clone());
+            format!("{take}.unwrap_or_default()")
+        };
+        values.push_str(&format!("            {field_name}: {value},\n"));
+    }
+
+    let build_receiver = match setters {
+        SetterStyle::Owned => "self",
+        SetterStyle::MutRef => "&self",
+    };
+    let build = if code.required.is_empty() {
+        format!(
+            "    {visibility}fn build({build_receiver}) -> {name} {{\n        {name} {{\n{values}        }}\n    }}\n"
+        )
+    } else {
+        format!(
+            "    {visibility}fn build({build_receiver}) -> Result<{name}, &'static str> {{\n        Ok({name} {{\n{values}        }})\n    }}\n"
+        )
+    };
+    methods.push(build);
+
+    code.text = format!(
+        "#[derive(Default)]\n{visibility}struct {name}Builder {{\n{fields}}}\n\nimpl {name}Builder {{\n{}}}\n\nimpl {name} {{\n    {visibility}fn builder() -> {name}Builder {{\n        {name}Builder::default()\n    }}\n}}\n",
+        methods.join("\n")
+    );
+    code
+}
+
+/// `T` for an `Option<T>` type.
+fn option_inner_type(ty: &str) -> Option<&str> {
+    let (base, args) = ty.split_once('<')?;
+    if last_path_segment(base) != "Option" {
+        return None;
+    }
+    Some(args.strip_suffix('>')?.trim())
+}
+
+pub async fn extract_trait_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let code_block = args
+        .get("code_block")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing code_block parameter"))?;
+    let occurrence = args.get("occurrence").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+    let trait_name = args
+        .get("trait_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing trait_name parameter"))?;
+    let methods: Vec<&str> = args
+        .get("methods")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow::anyhow!("Missing methods parameter"))?
+        .iter()
+        .filter_map(|method| method.as_str())
+        .collect();
+    if methods.is_empty() {
+        return Err(anyhow::anyhow!("`methods` names no items to move"));
+    }
+    if trait_name.is_empty()
+        || trait_name.starts_with(|c: char| c.is_ascii_digit())
+        || !trait_name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        return Err(anyhow::anyhow!("Invalid trait name '{}'", trait_name));
+    }
+
+    let file_content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+
+    // The impl is found by its `impl` keyword inside the code block.
+    let (line, character) = find_symbol_location(&file_content, "impl", code_block, occurrence)?;
+    let impl_start = line_start_offset(&file_content, line as usize)
+        .map(|start| start + utf16_column_offset(&file_content[start..], character))
+        .ok_or_else(|| anyhow::anyhow!("Line {} is out of range", line + 1))?;
+    let extraction = extract_trait_edit(&file_content, impl_start, trait_name, &methods)?;
+
+    let mut notes = extraction.notes;
+    let diff = analyzer
+        .write_file(file_path, &file_content, &extraction.updated)
+        .await?;
+    if diff.is_some() {
+        notes.push("Writes are disabled (read-only mode); the file was not changed. See `diff` for the proposed edit.".to_string());
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "trait_name": trait_name,
+        "moved": methods,
+        "trait": extraction.trait_text,
+        "impl": extraction.impl_text,
+        "remaining_inherent_items": extraction.remaining,
+        "written": diff.is_none(),
+        "diff": diff,
+        "notes": notes
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// An inherent impl split into a new trait, its impl, and what is left.
+#[derive(Debug)]
+struct TraitExtraction {
+    updated: String,
+    trait_text: String,
+    impl_text: String,
+    /// Names of the items that stay in the inherent impl.
+    remaining: Vec<String>,
+    notes: Vec<String>,
+}
+
+/// An item in the body of an impl, as byte offsets into the file.
+#[derive(Debug)]
+struct ImplMember {
+    /// `fn`, `const`, `type`, or `other` for macro calls.
+    kind: &'static str,
+    name: String,
+    /// Start of the item's first line, docs and attributes included.
+    start: usize,
+    /// Past the item and the rest of its line.
+    end: usize,
+    /// Where the declaration starts, after docs and attributes.
+    decl_start: usize,
+    /// Where the declaration ends: the body's `{`, or the `=` of a value.
+    decl_end: usize,
+    /// `pub`, `pub(crate)`, ... with the whitespace after it.
+    visibility: Option<(usize, usize)>,
+    /// The `const` of a `const fn`, with the whitespace after it.
+    const_qualifier: Option<(usize, usize)>,
+    /// Doc comments and `#[doc]` attributes.
+    docs: Vec<(usize, usize)>,
+}
+
+/// Splits the items of the impl body between the braces at `open` and `close`.
+fn impl_members(content: &str, open: usize, close: usize) -> Vec<ImplMember> {
+    let tokens: Vec<Token> = tokenize(&content[open + 1..close])
+        .into_iter()
+        .map(|token| Token {
+            kind: token.kind,
+            start: token.start + open + 1,
+            end: token.end + open + 1,
+        })
+        .collect();
+    let text = |token: &Token| &content[token.start..token.end];
+    let is_trivia = |token: &Token| matches!(token.kind, "whitespace" | "comment" | "doc_comment");
+
+    let mut members = Vec::new();
+    let mut first = None;
+    let mut depth = 0i32;
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.kind == "whitespace" {
+            continue;
+        }
+        let first_idx = *first.get_or_insert(idx);
+        let finished = match (token.kind, text(token)) {
+            ("punctuation", "(" | "[" | "{") => {
+                depth += 1;
+                false
+            }
+            ("punctuation", ")" | "]") => {
+                depth -= 1;
+                false
+            }
+            ("punctuation", "}") => {
+                depth -= 1;
+                depth == 0
+            }
+            ("punctuation", ";") => depth == 0,
+            _ => false,
+        };
+        if !finished {
+            continue;
+        }
+        first = None;
+        let item = &tokens[first_idx..=idx];
+
+        // Docs and attributes, up to the declaration.
+        let mut docs = Vec::new();
+        let mut pos = 0;
+        while pos < item.len() {
+            let token = &item[pos];
+            if is_trivia(token) {
+                if token.kind == "doc_comment" {
+                    docs.push((token.start, token.end));
+                }
+                pos += 1;
+            } else if text(token) == "#" {
+                let mut brackets = 0;
+                let attribute_start = pos;
+                while pos < item.len() {
+                    match text(&item[pos]) {
+                        "[" => brackets += 1,
+                        "]" => {
+                            brackets -= 1;
+                            if brackets == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    pos += 1;
+                }
+                let attribute =
+                    &content[item[attribute_start].start..item[pos.min(item.len() - 1)].end];
+                if attribute
+                    .trim_start_matches('#')
+                    .trim_start_matches('[')
+                    .trim_start()
+                    .starts_with("doc")
+                {
+                    docs.push((
+                        item[attribute_start].start,
+                        item[pos.min(item.len() - 1)].end,
+                    ));
+                }
+                pos += 1;
+            } else {
+                break;
+            }
+        }
+        let code: Vec<&Token> = item[pos.min(item.len())..]
+            .iter()
+            .filter(|token| !is_trivia(token))
+            .collect();
+        let Some(decl) = code.first() else {
+            continue;
+        };
+
+        let next_start = |at: usize| code.get(at).map_or(decl.start, |token| token.start);
+        let mut at = 0;
+        let mut visibility = None;
+        if code.get(at).is_some_and(|token| text(token) == "pub") {
+            at += 1;
+            if code.get(at).is_some_and(|token| text(token) == "(") {
+                while code.get(at).is_some_and(|token| text(token) != ")") {
+                    at += 1;
+                }
+                at += 1;
+            }
+            visibility = Some((decl.start, next_start(at)));
+        }
+
+        let mut kind = "other";
+        let mut const_qualifier = None;
+        let mut name = String::new();
+        while let Some(token) = code.get(at) {
+            let next = code.get(at + 1);
+            match text(token) {
+                "fn" | "type" => {
+                    kind = if text(token) == "fn" { "fn" } else { "type" };
+                    name = next.map(|next| text(next).to_string()).unwrap_or_default();
+                    break;
+                }
+                "const"
+                    if next.is_some_and(|next| text(next) == "fn" || next.kind == "keyword") =>
+                {
+                    const_qualifier = Some((token.start, next_start(at + 1)));
+                }
+                "const" => {
+                    kind = "const";
+                    name = next.map(|next| text(next).to_string()).unwrap_or_default();
+                    break;
+                }
+                "async" | "unsafe" | "extern" | "default" => {}
+                _ if token.kind == "string" => {}
+                _ => break,
+            }
+            at += 1;
+        }
+
+        // The body's `{` for functions; the `=` of a const or type value.
+        let mut nesting = 0i32;
+        let decl_end = code[at..]
+            .iter()
+            .find(|token| match text(token) {
+                "(" | "[" => {
+                    nesting += 1;
+                    false
+                }
+                ")" | "]" => {
+                    nesting -= 1;
+                    false
+                }
+                "{" => nesting == 0 && kind == "fn",
+                "=" => nesting == 0 && kind != "fn",
+                ";" => nesting == 0,
+                _ => false,
+            })
+            .map_or(token.start, |token| token.start);
+
+        let first_start = tokens[first_idx].start;
+        let start = content[..first_start].rfind('\n').map_or(0, |idx| idx + 1);
+        let rest = &content[token.end..];
+        let end = match rest.find('\n') {
+            Some(newline) if rest[..newline].trim().is_empty() => token.end + newline + 1,
+            _ => token.end,
+        };
+        members.push(ImplMember {
+            kind,
+            name,
+            start,
+            end,
+            decl_start: decl.start,
+            decl_end,
+            visibility,
+            const_qualifier,
+            docs,
+        });
+    }
+    members
+}
+
+/// `content[start..end]` without the sorted byte ranges in `removed`.
+fn text_without(content: &str, start: usize, end: usize, removed: &[(usize, usize)]) -> String {
+    let mut text = String::new();
+    let mut pos = start;
+    for &(from, to) in removed {
+        let (from, to) = (from.clamp(start, end), to.clamp(start, end));
+        text.push_str(&content[pos..from.max(pos)]);
+        pos = pos.max(to);
+    }
+    text.push_str(&content[pos..end]);
+    text
+}
+
+/// Moves the items named `names` out of the inherent impl whose `impl`
+/// keyword is at `impl_start` into a new trait `trait_name`, declared just
+/// above the impl and implemented just below it. Docs move to the trait;
+/// other attributes, comments and bodies stay with the implementation.
+fn extract_trait_edit(
+    content: &str,
+    impl_start: usize,
+    trait_name: &str,
+    names: &[&str],
+) -> Result<TraitExtraction> {
+    let open = content[impl_start..]
+        .find('{')
+        .map(|idx| impl_start + idx)
+        .ok_or_else(|| anyhow::anyhow!("No impl body after the `impl` keyword"))?;
+    let close = find_matching_brace(content, open)
+        .ok_or_else(|| anyhow::anyhow!("The impl body is not closed"))?;
+    let after_impl = content[impl_start + "impl".len()..open].trim_start();
+    let (params, self_part) = split_generic_params(after_impl)
+        .ok_or_else(|| anyhow::anyhow!("Unbalanced generic parameters in the impl header"))?;
+    let generics_text = &after_impl[..after_impl.len() - self_part.len()];
+    let self_part = self_part.trim();
+    let self_type = collapse_whitespace(self_part);
+    let self_type = self_type
+        .split_once(" where ")
+        .map_or(self_type.as_str(), |(self_type, _)| self_type);
+    if self_type.contains(" for ") || self_type.starts_with("for ") {
+        return Err(anyhow::anyhow!(
+            "`impl {}` already implements a trait; extract_trait works on inherent impls",
+            self_type
+        ));
+    }
+
+    let members = impl_members(content, open, close);
+    let mut moved = Vec::new();
+    for name in names {
+        let member = members
+            .iter()
+            .find(|member| member.kind != "other" && member.name == *name)
+            .ok_or_else(|| {
+                let available: Vec<&str> = members
+                    .iter()
+                    .filter(|member| member.kind != "other")
+                    .map(|member| member.name.as_str())
+                    .collect();
+                anyhow::anyhow!(
+                    "No item named `{}` in `impl {}`; it has: {}",
+                    name,
+                    self_type,
+                    available.join(", ")
+                )
+            })?;
+        moved.push(member);
+    }
+    moved.sort_by_key(|member| member.start);
+    moved.dedup_by_key(|member| member.start);
+
+    let impl_line_start = content[..impl_start].rfind('\n').map_or(0, |idx| idx + 1);
+    let indent = &content[impl_line_start..impl_start];
+    let indent = if indent.trim().is_empty() { indent } else { "" };
+    // Attributes directly above the impl apply to the trait impl too, and its
+    // `cfg`s to the trait; its docs only move when the whole impl does.
+    let mut outer_start = impl_line_start;
+    let mut outer_lines = Vec::new();
+    while outer_start > 0 {
+        let line_start = content[..outer_start - 1]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let line = content[line_start..outer_start].trim();
+        if !line.starts_with("#[") && !line.starts_with("///") {
+            break;
+        }
+        outer_lines.push(line);
+        outer_start = line_start;
+    }
+    outer_lines.reverse();
+
+    let mut notes = Vec::new();
+    let mut declarations = Vec::new();
+    let mut implementations = Vec::new();
+    for member in &moved {
+        let member_indent = &content[member.start
+            ..content[member.start..member.end]
+                .find(|c: char| !c.is_whitespace())
+                .map_or(member.start, |idx| member.start + idx)];
+        let mut skipped: Vec<(usize, usize)> = member
+            .visibility
+            .iter()
+            .chain(&member.const_qualifier)
+            .copied()
+            .collect();
+        let mut declaration = String::new();
+        for &(from, to) in &member.docs {
+            declaration.push_str(&format!("{member_indent}{}\n", &content[from..to]));
+            // A doc line goes with its indentation and newline.
+            let line_start = content[..from].rfind('\n').map_or(0, |idx| idx + 1);
+            let rest = &content[to..];
+            match rest.find('\n') {
+                Some(newline)
+                    if content[line_start..from].trim().is_empty()
+                        && rest[..newline].trim().is_empty() =>
+                {
+                    skipped.push((line_start, to + newline + 1));
+                }
+                _ => skipped.push((from, to)),
+            }
+        }
+        skipped.sort_unstable();
+        let signature = text_without(content, member.decl_start, member.decl_end, &skipped);
+        declaration.push_str(&format!("{member_indent}{};", signature.trim_end()));
+        declarations.push(declaration);
+        implementations.push(text_without(content, member.start, member.end, &skipped));
+
+        if member.const_qualifier.is_some() {
+            notes.push(format!(
+                "`{}` was a `const fn`; trait methods cannot be const, so the qualifier was dropped.",
+                member.name
+            ));
+        }
+        if member.kind == "type" {
+            notes.push(format!(
+                "`type {}` became an associated type of the trait; bounds it needs must be added to the declaration.",
+                member.name
+            ));
+        }
+    }
+
+    // The trait declares the impl's parameters its signatures mention.
+    let used: HashSet<&str> = declarations
+        .iter()
+        .flat_map(|declaration| {
+            tokenize(declaration)
+                .into_iter()
+                .filter(|token| matches!(token.kind, "identifier" | "lifetime"))
+                .map(|token| &declaration[token.start..token.end])
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let mut trait_params = Vec::new();
+    let mut trait_args = Vec::new();
+    for param in &params {
+        let bare = param.trim_start_matches("const ").trim_start();
+        let name = bare.split([':', '=']).next().unwrap_or(bare).trim();
+        if !used.contains(name) {
+            continue;
+        }
+        trait_args.push(name.to_string());
+        trait_params.push(if param.trim_start().starts_with("const ") {
+            param.split('=').next().unwrap_or(param).trim().to_string()
+        } else {
+            name.to_string()
+        });
+    }
+    let angle = |items: &[String]| {
+        if items.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", items.join(", "))
+        }
+    };
+    if !trait_params.is_empty() {
+        notes.push(format!(
+            "The trait takes the impl's parameters {} used by its signatures; their bounds stay on the impl.",
+            angle(&trait_params)
+        ));
+    }
+
+    // The widest visibility of the moved items.
+    let visibilities: Vec<&str> = moved
+        .iter()
+        .filter_map(|member| member.visibility)
+        .map(|(from, to)| content[from..to].trim())
+        .collect();
+    let visibility = if visibilities.contains(&"pub") {
+        "pub ".to_string()
+    } else {
+        visibilities
+            .first()
+            .map(|visibility| format!("{visibility} "))
+            .unwrap_or_default()
+    };
+    if !visibility.is_empty() {
+        notes.push(format!(
+            "Method calls now resolve through the trait: code in other modules needs `{trait_name}` in scope."
+        ));
+    }
+    if moved.iter().any(|member| {
+        content[member.decl_start..member.decl_end]
+            .split_whitespace()
+            .any(|word| word == "async")
+    }) && visibility == "pub "
+    {
+        notes.push("`async fn` in a public trait triggers the `async_fn_in_trait` lint; consider returning `impl Future<Output = ..> + Send`.".to_string());
+    }
+
+    let separator = if moved.iter().any(|member| !member.docs.is_empty()) {
+        "\n\n"
+    } else {
+        "\n"
+    };
+    let mut trait_text = String::new();
+    for attribute in outer_lines.iter().filter(|line| line.starts_with("#[cfg")) {
+        trait_text.push_str(&format!("{indent}{attribute}\n"));
+    }
+    trait_text.push_str(&format!(
+        "{indent}{visibility}trait {trait_name}{} {{\n{}\n{indent}}}\n",
+        angle(&trait_params),
+        declarations.join(separator)
+    ));
+
+    let keeps_inherent = members.len() > moved.len();
+    let mut impl_text = String::new();
+    for line in &outer_lines {
+        if !keeps_inherent || line.starts_with("#[") {
+            impl_text.push_str(&format!("{indent}{line}\n"));
+        }
+    }
+    // A multi-line header keeps its `where` clause layout, brace included.
+    let brace = if self_part.contains('\n') {
+        format!("\n{indent}{{")
+    } else {
+        " {".to_string()
+    };
+    impl_text.push_str(&format!(
+        "{indent}impl{generics_text} {trait_name}{} for {self_part}{brace}\n{}{indent}}}\n",
+        angle(&trait_args),
+        implementations.join("\n")
+    ));
+
+    let remaining: Vec<String> = members
+        .iter()
+        .filter(|member| {
+            member.kind != "other" && !moved.iter().any(|moved| moved.start == member.start)
+        })
+        .map(|member| member.name.clone())
+        .collect();
+    let rest = &content[close + 1..];
+    let impl_end = match rest.find('\n') {
+        Some(newline) if rest[..newline].trim().is_empty() => close + 1 + newline + 1,
+        _ => close + 1,
+    };
+
+    let mut updated = content[..outer_start].to_string();
+    updated.push_str(&trait_text);
+    updated.push('\n');
+    if keeps_inherent {
+        // Removing an item also drops one of the blank lines around it.
+        let mut removed: Vec<(usize, usize)> = Vec::new();
+        for member in &moved {
+            let blank_after = content[member.end..close]
+                .find('\n')
+                .filter(|&newline| content[member.end..member.end + newline].trim().is_empty());
+            let blank_before = content[..member.start]
+                .strip_suffix('\n')
+                .and_then(|before| {
+                    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+                    before[line_start..].trim().is_empty().then_some(line_start)
+                });
+            let after_brace = content[..member.start].trim_end().ends_with('{');
+            let range = match (blank_before, blank_after) {
+                (Some(_), Some(newline)) => (member.start, member.end + newline + 1),
+                (None, Some(newline)) if after_brace => (member.start, member.end + newline + 1),
+                (Some(line_start), None) => (line_start, member.end),
+                _ => (member.start, member.end),
+            };
+            removed.push(range);
+        }
+        updated.push_str(&text_without(content, outer_start, impl_end, &removed));
+        updated.push('\n');
+    }
+    updated.push_str(&impl_text);
+    updated.push_str(&content[impl_end..]);
+
+    Ok(TraitExtraction {
+        updated,
+        trait_text,
+        impl_text,
+        remaining,
+        notes,
+    })
+}
+
+pub async fn create_module_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let parent_file = args
+        .get("parent_file")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing parent_file parameter"))?;
+    let name = args
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing name parameter"))?;
+    let visibility = args
+        .get("visibility")
+        .and_then(|v| v.as_str())
+        .unwrap_or("private");
+    let mod_rs = args
+        .get("mod_rs")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let content = args.get("content").and_then(|v| v.as_str()).unwrap_or("");
+
+    if name.is_empty()
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        return Err(anyhow::anyhow!("Invalid module name '{}'", name));
+    }
+    let prefix = match visibility {
+        "private" | "" => "",
+        "pub" | "pub(crate)" | "pub(super)" => visibility,
+        scoped if scoped.starts_with("pub(in ") && scoped.ends_with(')') => scoped,
+        other => {
+            return Err(anyhow::anyhow!(
+                "Invalid visibility '{}': expected \"private\", \"pub\", \"pub(crate)\", \"pub(super)\" or \"pub(in path)\"",
+                other
+            ));
+        }
+    };
+
+    let parent_content = fs::read_to_string(parent_file)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    if let Some((idx, line)) = parent_content
+        .lines()
+        .enumerate()
+        .find(|(_, line)| declared_module(line) == Some(name))
+    {
+        return Err(anyhow::anyhow!(
+            "Module '{}' is already declared at {}:{}: {}",
+            name,
+            parent_file,
+            idx + 1,
+            line.trim()
+        ));
+    }
+
+    let dir = child_module_dir(Path::new(parent_file))
+        .ok_or_else(|| anyhow::anyhow!("{} is not a Rust module file", parent_file))?;
+    let file_layout = dir.join(format!("{name}.rs"));
+    let mod_rs_layout = dir.join(name).join("mod.rs");
+    // An orphan file is declared as it is rather than shadowed by a new one.
+    let existing = [&file_layout, &mod_rs_layout]
+        .into_iter()
+        .find(|path| path.exists())
+        .cloned();
+    let created = existing.is_none();
+    let module_file = existing.unwrap_or(if mod_rs { mod_rs_layout } else { file_layout });
+    let module_path = module_file.display().to_string();
+
+    let declaration = if prefix.is_empty() {
+        format!("mod {name};")
+    } else {
+        format!("{prefix} mod {name};")
+    };
+    let (updated, line) = insert_mod_declaration(&parent_content, &declaration, name);
+
+    let mut result = json!({
+        "module_file": module_path,
+        "created": created,
+        "parent_file": parent_file,
+        "declaration": declaration,
+        "line": line + 1,
+        "written": analyzer.allow_writes()
+    });
+
+    if !analyzer.allow_writes() {
+        let mut diffs = Vec::new();
+        if created {
+            diffs.push(unified_diff(
+                "",
+                content,
+                "/dev/null",
+                &format!("b{module_path}"),
+            ));
+        }
+        if let Some(diff) = analyzer
+            .write_file(parent_file, &parent_content, &updated)
+            .await?
+        {
+            diffs.push(diff);
+        }
+        result["diff"] = json!(diffs.join("\n"));
+        result["message"] =
+            json!("Writes are disabled (read-only mode); no files were created or changed.");
+    } else {
+        if created {
+            if let Some(module_dir) = module_file.parent() {
+                fs::create_dir_all(module_dir).await.map_err(|e| {
+                    anyhow::anyhow!("Failed to create {}: {}", module_dir.display(), e)
+                })?;
+            }
+            fs::write(&module_file, content)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", module_path, e))?;
+        }
+        if let Err(e) = analyzer
+            .write_file(parent_file, &parent_content, &updated)
+            .await
+        {
+            if created {
+                let _ = fs::remove_file(&module_file).await;
+            }
+            return Err(e);
+        }
+        if analyzer.document_version(parent_file).is_some() {
+            analyzer.sync_document(parent_file, &updated).await?;
+        }
+        result["message"] = json!(format!(
+            "{} {} and declared it at {}:{}",
+            if created { "Created" } else { "Found existing" },
+            module_path,
+            parent_file,
+            line + 1
+        ));
+    }
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// Default `min_lines` of `suggest_module_split`: smaller groups are merged
+/// into the group they are most linked to.
+const DEFAULT_SPLIT_MIN_LINES: usize = 30;
+
+/// Functions whose callers are looked up through the call hierarchy; one
+/// request each, so very large files are only partly covered.
+const MAX_SPLIT_CALL_LOOKUPS: usize = 150;
+
+pub async fn suggest_module_split_impl(
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    let file_path = args
+        .get("file_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing file_path parameter"))?;
+    let min_lines = args
+        .get("min_lines")
+        .and_then(|v| v.as_u64())
+        .map_or(DEFAULT_SPLIT_MIN_LINES, |v| v as usize);
+
+    let content = fs::read_to_string(file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?;
+    let symbols = match analyzer.document_symbols(file_path).await? {
+        DocumentSymbolResponse::DocumentSymbols(symbols) => symbols,
+        DocumentSymbolResponse::SymbolInformation(_) => {
+            return Err(anyhow::anyhow!(
+                "rust-analyzer returned a flat symbol list for {}; the file's items can't be told apart",
+                file_path
+            ));
+        }
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    let items: Vec<SplitItem> = symbols
+        .iter()
+        .map(|symbol| {
+            let line = lines
+                .get(symbol.selection_range.start.line as usize)
+                .copied()
+                .unwrap_or_default();
+            let kind = match symbol.kind {
+                12 if line.contains("macro_rules!") => "macro",
+                12 => "function",
+                23 => "struct",
+                10 => "enum",
+                11 => "trait",
+                19 => "impl",
+                14 => "const",
+                13 => "static",
+                26 => "type_alias",
+                2 => "module",
+                _ => "item",
+            };
+            let impl_of = match impl_target(&symbol.name) {
+                Some(target) if kind == "impl" => std::iter::once(target.self_type)
+                    .chain(target.trait_path)
+                    .map(|name| last_path_segment(name.trim_start_matches('!')).to_string())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            SplitItem {
+                name: symbol.name.clone(),
+                kind,
+                start_line: symbol.range.start.line as usize,
+                end_line: symbol.range.end.line as usize,
+                impl_of,
+            }
+        })
+        .collect();
+    let item_at = |line: u32| {
+        items
+            .iter()
+            .position(|item| (item.start_line..=item.end_line).contains(&(line as usize)))
+    };
+
+    // Which items name which: type, function and constant names used in
+    // another item's source.
+    let mut edges: HashSet<(usize, usize)> = HashSet::new();
+    for (from, item) in items.iter().enumerate() {
+        let source = lines
+            .get(item.start_line..=item.end_line.min(lines.len().saturating_sub(1)))
+            .unwrap_or_default()
+            .join("\n");
+        let identifiers: HashSet<&str> = tokenize(&source)
+            .into_iter()
+            .filter(|token| token.kind == "identifier")
+            .map(|token| &source[token.start..token.end])
+            .collect();
+        for (to, other) in items.iter().enumerate() {
+            if from != to
+                && !matches!(other.kind, "impl" | "module")
+                && identifiers.contains(other.name.as_str())
+            {
+                edges.insert((from, to));
+            }
+        }
+    }
+
+    // Calls, including method calls the names alone don't reveal.
+    let callees: Vec<(usize, Position)> = symbols
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, symbol)| {
+            let methods = symbol.children.as_deref().unwrap_or_default();
+            let own = (symbol.kind == 12).then_some(symbol);
+            own.into_iter()
+                .chain(methods.iter().filter(|child| matches!(child.kind, 6 | 12)))
+                .map(move |function| (idx, function.selection_range.start.clone()))
+        })
+        .collect();
+    let skipped_lookups = callees.len().saturating_sub(MAX_SPLIT_CALL_LOOKUPS);
+    for (to, position) in callees.into_iter().take(MAX_SPLIT_CALL_LOOKUPS) {
+        let Ok(prepared) = analyzer
+            .prepare_call_hierarchy(file_path, position.line, position.character)
+            .await
+        else {
+            continue;
+        };
+        let Some(item) = prepared.into_iter().next() else {
+            continue;
+        };
+        let Ok(calls) = analyzer.incoming_calls(item).await else {
+            continue;
+        };
+        for call in calls {
+            let caller = call
+                .from
+                .uri
+                .strip_prefix("file://")
+                .unwrap_or(&call.from.uri);
+            if caller != file_path {
+                continue;
+            }
+            if let Some(from) = item_at(call.from.range.start.line)
+                && from != to
+            {
+                edges.insert((from, to));
+            }
+        }
+    }
+
+    let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+    edges.sort_unstable();
+    let (clusters, remaining) = cluster_items(&items, &edges, min_lines);
+
+    let module_dir = child_module_dir(Path::new(file_path));
+    let cluster_of = |idx: usize| clusters.iter().position(|c| c.members.contains(&idx));
+    let describe = |idx: usize| {
+        let item = &items[idx];
+        json!({
+            "name": item.name,
+            "kind": item.kind,
+            "start_line": item.start_line + 1,
+            "end_line": item.end_line + 1
+        })
+    };
+    let proposal: Vec<Value> = clusters
+        .iter()
+        .enumerate()
+        .map(|(id, cluster)| {
+            let crossing = edges
+                .iter()
+                .filter(|(a, b)| (cluster_of(*a) == Some(id)) != (cluster_of(*b) == Some(id)))
+                .count();
+            json!({
+                "module": cluster.name,
+                "file": module_dir
+                    .as_ref()
+                    .map(|dir| dir.join(format!("{}.rs", cluster.name)).display().to_string()),
+                "lines": cluster.lines(&items),
+                "items": cluster.members.iter().map(|idx| describe(*idx)).collect::<Vec<_>>(),
+                "links_outside": crossing
+            })
+        })
+        .collect();
+    let cross_module_edges = edges
+        .iter()
+        .filter(|(a, b)| cluster_of(*a) != cluster_of(*b))
+        .count();
+
+    let mut notes = Vec::new();
+    if clusters.len() < 2 {
+        notes.push(format!(
+            "No split suggested: the items do not form two or more groups of at least {min_lines} lines."
+        ));
+    }
+    if skipped_lookups > 0 {
+        notes.push(format!(
+            "Callers were looked up for the first {MAX_SPLIT_CALL_LOOKUPS} functions only; {skipped_lookups} were linked by name alone."
+        ));
+    }
+
+    let result = json!({
+        "file_path": file_path,
+        "total_lines": lines.len(),
+        "items": items.len(),
+        "proposal": if clusters.len() < 2 { Vec::new() } else { proposal },
+        "remain_in_parent": remaining.iter().map(|idx| describe(*idx)).collect::<Vec<_>>(),
+        "cross_module_edges": cross_module_edges,
+        "notes": notes,
+        "heuristic": "Advisory only; nothing is moved. Each struct, enum and trait seeds a group that its impl blocks join; other items join the group they reference or are called from most (names in their source plus call-hierarchy edges within the file), then items sharing a name prefix form groups, and groups under min_lines are merged into their most linked neighbour or left in the parent. Visibility, `use` lines and macro ordering are not considered.",
+        "next_steps": "Create each module with create_module, move its items with apply_edit (cut from the parent, paste into the module), widen visibility to pub(super) where items are used across modules, and add the `use` lines the moved code needs; check with get_diagnostics."
+    });
+
+    Ok(ToolResult {
+        content: vec![
+            json!({
+                "type": "text",
+                "text": serde_json::to_string_pretty(&result)?
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ],
+    })
+}
+
+/// A top-level item of a file being split, lines 0-based and inclusive.
+#[derive(Debug)]
+struct SplitItem {
+    name: String,
+    kind: &'static str,
+    start_line: usize,
+    end_line: usize,
+    /// Self type and trait names of an impl block.
+    impl_of: Vec<String>,
+}
+
+/// A proposed submodule: its name and the indices of its items, in order.
+#[derive(Debug, PartialEq)]
+struct SplitCluster {
+    name: String,
+    members: Vec<usize>,
+}
+
+impl SplitCluster {
+    fn lines(&self, items: &[SplitItem]) -> usize {
+        self.members
+            .iter()
+            .map(|idx| items[*idx].end_line - items[*idx].start_line + 1)
+            .sum()
+    }
+}
+
+/// Groups `items` into submodules from `edges` (`(from, to)` item indices);
+/// returns the groups, in file order, and the items left in the parent.
+fn cluster_items(
+    items: &[SplitItem],
+    edges: &[(usize, usize)],
+    min_lines: usize,
+) -> (Vec<SplitCluster>, Vec<usize>) {
+    let is_type = |item: &SplitItem| matches!(item.kind, "struct" | "enum" | "trait");
+    let mut names: Vec<String> = Vec::new();
+    let mut cluster_of: Vec<Option<usize>> = vec![None; items.len()];
+
+    for (idx, item) in items.iter().enumerate() {
+        if is_type(item) {
+            cluster_of[idx] = Some(names.len());
+            names.push(snake_case(&item.name));
+        }
+    }
+    for (idx, item) in items.iter().enumerate() {
+        cluster_of[idx] = cluster_of[idx].or_else(|| {
+            item.impl_of.iter().find_map(|target| {
+                items
+                    .iter()
+                    .position(|other| is_type(other) && other.name == *target)
+                    .and_then(|seed| cluster_of[seed])
+            })
+        });
+    }
+
+    // Link count from item `idx` to each group.
+    let links = |idx: usize, cluster_of: &[Option<usize>]| {
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for &(a, b) in edges {
+            let other = if a == idx {
+                b
+            } else if b == idx {
+                a
+            } else {
+                continue;
+            };
+            if let Some(cluster) = cluster_of[other] {
+                *counts.entry(cluster).or_default() += 1;
+            }
+        }
+        counts
+    };
+    let best = |counts: &BTreeMap<usize, usize>| {
+        counts
+            .iter()
+            .max_by_key(|(cluster, count)| (**count, std::cmp::Reverse(**cluster)))
+            .map(|(cluster, _)| *cluster)
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..items.len() {
+            if cluster_of[idx].is_some() || items[idx].kind == "module" {
+                continue;
+            }
+            if let Some(cluster) = best(&links(idx, &cluster_of)) {
+                cluster_of[idx] = Some(cluster);
+                changed = true;
+            }
+        }
+    }
+
+    // Unlinked items sharing a name prefix (`parse_*`, `render_*`).
+    let mut prefixes: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, item) in items.iter().enumerate() {
+        if cluster_of[idx].is_none() && !matches!(item.kind, "module" | "impl") {
+            let prefix = item
+                .name
+                .split('_')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            if !prefix.is_empty() && prefix.len() < item.name.len() {
+                prefixes.entry(prefix).or_default().push(idx);
+            }
+        }
+    }
+    for (prefix, members) in prefixes {
+        if members.len() >= 2 {
+            for idx in members {
+                cluster_of[idx] = Some(names.len());
+            }
+            names.push(prefix);
+        }
+    }
+
+    // Fold groups below `min_lines` into their most linked neighbour.
+    loop {
+        let size = |cluster: usize, cluster_of: &[Option<usize>]| -> usize {
+            (0..items.len())
+                .filter(|idx| cluster_of[*idx] == Some(cluster))
+                .map(|idx| items[idx].end_line - items[idx].start_line + 1)
+                .sum()
+        };
+        let Some(small) = (0..names.len())
+            .filter(|cluster| {
+                let lines = size(*cluster, &cluster_of);
+                lines > 0 && lines < min_lines
+            })
+            .min_by_key(|cluster| (size(*cluster, &cluster_of), *cluster))
+        else {
+            break;
+        };
+        let members: Vec<usize> = (0..items.len())
+            .filter(|idx| cluster_of[*idx] == Some(small))
+            .collect();
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for &idx in &members {
+            for (cluster, count) in links(idx, &cluster_of) {
+                if cluster != small {
+                    *counts.entry(cluster).or_default() += count;
+                }
+            }
+        }
+        let target = best(&counts);
+        for idx in members {
+            cluster_of[idx] = target;
+        }
+    }
+
+    let mut clusters: Vec<SplitCluster> = Vec::new();
+    for (id, name) in names.iter().enumerate() {
+        let members: Vec<usize> = (0..items.len())
+            .filter(|idx| cluster_of[*idx] == Some(id))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        let mut name = name.clone();
+        if KEYWORDS.contains(&name.as_str()) {
+            name.push_str("_items");
+        }
+        let taken = clusters
+            .iter()
+            .filter(|cluster| cluster.name.starts_with(&name))
+            .count();
+        if taken > 0 {
+            name = format!("{name}_{}", taken + 1);
+        }
+        clusters.push(SplitCluster { name, members });
+    }
+    clusters.sort_by_key(|cluster| cluster.members[0]);
+    let remaining = (0..items.len())
+        .filter(|idx| cluster_of[*idx].is_none())
+        .collect();
+    (clusters, remaining)
+}
+
+/// `HttpClient` -> `http_client`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (idx, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lower = idx > 0 && chars[idx - 1].is_lowercase();
+            let acronym_end = idx > 0
+                && chars[idx - 1].is_uppercase()
+                && chars.get(idx + 1).is_some_and(|next| next.is_lowercase());
+            if after_lower || acronym_end {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(*c);
+        }
+    }
+    snake
+}
+
+/// The directory holding the submodules of the module defined by `path`:
+/// its own directory for `mod.rs` and crate roots, `a/b/` for `a/b.rs`.
+fn child_module_dir(path: &Path) -> Option<PathBuf> {
+    if path.extension()? != "rs" {
+        return None;
+    }
+    let dir = path.parent()?;
+    let stem = path.file_stem()?.to_str()?;
+    if stem == "mod" || is_crate_root(path) {
+        Some(dir.to_path_buf())
+    } else {
+        Some(dir.join(stem))
+    }
+}
+
+/// Inserts `declaration` among the file's `mod` declarations, keeping them
+/// sorted by name, or before its first `use` (after inner attributes and
+/// docs) when it has none. Returns the new content and the declaration's line.
+fn insert_mod_declaration(content: &str, declaration: &str, name: &str) -> (String, usize) {
+    let lines: Vec<&str> = content.lines().collect();
+    let modules: Vec<(usize, &str)> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with(char::is_whitespace))
+        .filter_map(|(idx, line)| declared_module(line).map(|module| (idx, module)))
+        .collect();
+
+    let (at, blank_before, blank_after) =
+        if let Some((idx, _)) = modules.iter().find(|(_, module)| *module > name) {
+            (*idx, false, false)
+        } else if let Some((idx, _)) = modules.last() {
+            (idx + 1, false, false)
+        } else {
+            let preamble = lines
+                .iter()
+                .take_while(|line| {
+                    let line = line.trim_start();
+                    line.is_empty() || line.starts_with("//!") || line.starts_with("#![")
+                })
+                .count();
+            let blank_before = preamble > 0 && !lines[preamble - 1].trim().is_empty();
+            let blank_after = preamble < lines.len() && !lines[preamble].trim().is_empty();
+            (preamble, blank_before, blank_after)
+        };
+
+    let mut inserted = Vec::new();
+    if blank_before {
+        inserted.push("");
+    }
+    inserted.push(declaration);
+    if blank_after {
+        inserted.push("");
+    }
+
+    let mut updated: Vec<&str> = lines[..at].to_vec();
+    updated.extend(inserted);
+    updated.extend(&lines[at..]);
+    let mut updated = updated.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        updated.push('\n');
+    }
+    (updated, at + blank_before as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::protocol::Position;
+
+    #[test]
+    fn test_cluster_items() {
+        let item = |name: &str, kind: &'static str, start: usize, len: usize, impl_of: &[&str]| {
+            SplitItem {
+                name: name.to_string(),
+                kind,
+                start_line: start,
+                end_line: start + len - 1,
+                impl_of: impl_of.iter().map(|name| name.to_string()).collect(),
+            }
+        };
+        let items = vec![
+            item("HttpClient", "struct", 0, 10, &[]),
+            item("impl HttpClient", "impl", 10, 30, &["HttpClient"]),
+            item("send_request", "function", 40, 10, &[]),
+            item("Config", "struct", 50, 5, &[]),
+            item(
+                "impl Default for Config",
+                "impl",
+                55,
+                10,
+                &["Config", "Default"],
+            ),
+            item("load_config", "function", 65, 20, &[]),
+            item("render_table", "function", 85, 20, &[]),
+            item("render_row", "function", 105, 15, &[]),
+            item("main", "function", 120, 3, &[]),
+            item("tests", "module", 123, 20, &[]),
+        ];
+        // send_request -> HttpClient, load_config -> Config, main -> both.
+
+        let edges = [(2, 0), (5, 3), (8, 1), (8, 5)];
+        let (clusters, remaining) = cluster_items(&items, &edges, 30);
+        assert_eq!(
+            clusters,
+            vec![
+                SplitCluster {
+                    name: "http_client".to_string(),
+                    members: vec![0, 1, 2, 8]
+                },
+                SplitCluster {
+                    name: "config".to_string(),
+                    members: vec![3, 4, 5]
+                },
+                SplitCluster {
+                    name: "render".to_string(),
+                    members: vec![6, 7]
+                },
+            ]
+        );
+        assert_eq!(remaining, vec![9]);
+
+        assert_eq!(snake_case("HttpClient"), "http_client");
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(snake_case("Config"), "config");
+    }
+
+    #[test]
+    fn test_match_coverage() {
+        let description = parse_enum_source(
+            "enum Shape { Circle(f64), Square { side: f64 }, Triangle(f64, f64), Line, Point }",
+        )
+        .unwrap();
+        let content = r#"match shape {
+    Shape::Circle(r) => area(r),
+    // comment => with arrow
+    Shape::Square { side } if side > 0.0 => { side * side }
+    Shape::Triangle(b, 0.0) | Shape::Line => 0.0,
+    #[allow(unused)]
+    Shape::Point => { "}" .len() as f64 }
+    Shape::Hexagon => 1.0,
+    other => fallback(other),
+}"#;
+        let open = content.find('{').unwrap();
+        let close = find_matching_brace(content, open).unwrap();
+        let arms = split_match_arms(content, open + 1, close);
+        let patterns: Vec<_> = arms
+            .iter()
+            .map(|arm| (arm.pattern.as_str(), arm.guarded))
+            .collect();
+        assert_eq!(
+            patterns,
+            vec![
+                ("Shape::Circle(r)", false),
+                ("Shape::Square { side }", true),
+                ("Shape::Triangle(b, 0.0) | Shape::Line", false),
+                ("Shape::Point", false),
+                ("Shape::Hexagon", false),
+                ("other", false),
+            ]
+        );
+        assert_eq!(pattern_variants(content, &arms[2])[1].1, "Line");
+
+        let coverage = match_coverage(content, &arms, &description);
+        let names = |values: &[Value]| {
+            values
+                .iter()
+                .map(|value| value["variant"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&coverage.covered), vec!["Circle", "Line", "Point"]);
+        assert_eq!(names(&coverage.partial), vec!["Square", "Triangle"]);
+        assert_eq!(coverage.wildcard_covered, vec!["Square", "Triangle"]);
+        assert!(coverage.missing.is_empty());
+        assert_eq!(coverage.catch_all_lines, vec![9]);
+        assert_eq!(
+            coverage.unknown,
+            vec![json!({"pattern": "Hexagon", "line": 8})]
+        );
+    }
+    #[test]
+    fn test_diagnostic_delta() {
+        let diagnostic = |line: u32, character: u32, code: &str, message: &str| Diagnostic {
+            range: Range {
+                start: Position { line, character },
+                end: Position {
+                    line,
+                    character: character + 1,
+                },
+            },
+            severity: Some(1),
+            code: Some(json!(code)),
+            source: Some("rustc".to_string()),
+            message: message.to_string(),
+        };
+        let content = "fn a() {\n    let x: u32 = \"1\";\n}\n\nfn b() { y }\n";
+        let start = content.find("let x").unwrap();
+        let end = start + content[start..].find('\n').unwrap();
+        let new_text = "let x: u32 = 1;\n    let z = x;";
+        let shift = EditShift::new(content, start, end, new_text);
+
+        let before = vec![
+            diagnostic(1, 17, "E0308", "mismatched types"),
+            diagnostic(4, 9, "E0425", "cannot find value `y` in this scope"),
+        ];
+        let after = vec![
+            diagnostic(2, 12, "E0425", "cannot find value `w` in this scope"),
+            diagnostic(5, 9, "E0425", "cannot find value `y` in this scope"),
+        ];
+        let delta = diagnostic_delta(&before, &after, &shift);
+        let lines =
+            |diags: &[&Diagnostic]| diags.iter().map(|d| d.range.start.line).collect::<Vec<_>>();
+        assert_eq!(lines(&delta.fixed), vec![1]);
+        assert_eq!(lines(&delta.new), vec![2]);
+        assert_eq!(lines(&delta.remaining), vec![5]);
+
+        // Columns after the edit shift by UTF-16 code units, not bytes.
+        let content = "let s = \"a\"; let t = x;\n";
+        let start = content.find("\"a\"").unwrap();
+        let shift = EditShift::new(content, start, start + 3, "\"héllo\"");
+        let moved = Position {
+            line: 0,
+            character: 21,
+        };
+        assert_eq!(shift.apply(&moved), (0, 25));
+    }
+    #[test]
+    fn test_rustfmt_config_resolution() {
+        let dir = std::env::temp_dir().join(format!("rust-mcp-rustfmt-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("crates/app/src")).unwrap();
+        std::fs::write(dir.join("rustfmt.toml"), "max_width = 80\n").unwrap();
+        assert_eq!(
+            rustfmt_config_file(&dir.join("crates/app/src")),
+            Some(dir.join("rustfmt.toml"))
+        );
+        // The nearer file wins, and `.rustfmt.toml` before `rustfmt.toml`.
+        std::fs::write(dir.join("crates/app/rustfmt.toml"), "").unwrap();
+        std::fs::write(dir.join("crates/app/.rustfmt.toml"), "").unwrap();
+        assert_eq!(
+            rustfmt_config_file(&dir.join("crates/app/src")),
+            Some(dir.join("crates/app/.rustfmt.toml"))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let options = json!({"max_width": 80, "hard_tabs": true, "imports_granularity": "Crate"});
+        assert_eq!(
+            inline_rustfmt_config(options.as_object().unwrap()),
+            "hard_tabs=true,imports_granularity=Crate,max_width=80"
+        );
+    }
+    #[test]
+    fn test_function_body_replacement() {
+        let source = "    /// Uses `{braces}` in docs.\n    fn build<const N: usize>() -> [u8; { N }] where [u8; N]: Default {\n        let map = HashMap::new();\n        { map.len() }\n    }\n";
+        let (open, close) = function_body(source).unwrap();
+        assert!(source[open..].starts_with("{\n        let map"));
+        assert_eq!(close, source.rfind('}').unwrap());
+        assert_eq!(function_body("    fn required(&self) -> u32;\n"), None);
+
+        assert_eq!(
+            body_block("let x = 1;\n\nx + 1", "    "),
+            "{\n        let x = 1;\n\n        x + 1\n    }"
+        );
+        assert_eq!(body_block("  { todo!() }  ", "    "), "{ todo!() }");
+
+        assert!(mentions_identifier(
+            "expected 2 arguments to `build`",
+            "build"
+        ));
+        assert!(!mentions_identifier("cannot find `build_all`", "build"));
+    }
+    #[test]
+    fn test_insert_use_statement() {
+        let content = "//! Server.\n\nuse std::fmt;\nuse std::io;\n\nuse serde::Serialize;\n\nuse crate::config::{\n    Config,\n};\n\nfn main() {}\n";
+
+        let (updated, line) = insert_use_statement(content, "std::collections::HashMap");
+        assert_eq!(line, 2);
+        assert!(updated.contains("\nuse std::collections::HashMap;\nuse std::fmt;\n"));
+
+        let (updated, line) = insert_use_statement(content, "tokio::fs");
+        assert_eq!(line, 6);
+        assert!(updated.contains("use serde::Serialize;\nuse tokio::fs;\n\nuse crate::config"));
+
+        let (updated, line) = insert_use_statement(content, "crate::server::Handler");
+        assert_eq!(line, 10);
+        assert!(updated.contains("};\nuse crate::server::Handler;\n\nfn main"));
+
+        let (updated, line) = insert_use_statement("//! Docs.\nfn main() {}\n", "std::fmt");
+        assert_eq!(line, 2);
+        assert_eq!(updated, "//! Docs.\n\nuse std::fmt;\n\nfn main() {}\n");
+    }
+
+    #[test]
+    fn test_import_candidate() {
+        assert_eq!(
+            import_candidate("Import `std::collections::HashMap`"),
+            Some("std::collections::HashMap")
+        );
+        assert_eq!(import_candidate("Qualify as `std::fmt::Debug`"), None);
+        assert_eq!(import_candidate("Fill match arms"), None);
+    }
+
+    #[test]
+    fn test_impl_target() {
+        assert_eq!(
+            impl_target("impl<T: Clone> fmt::Display for Wrapper<T> where T: Debug"),
+            Some(ImplTarget {
+                self_type: "Wrapper<T>".to_string(),
+                trait_path: Some("fmt::Display".to_string()),
+                generic: true,
+                blanket: false
+            })
+        );
+        assert_eq!(
+            impl_target("impl Config"),
+            Some(ImplTarget {
+                self_type: "Config".to_string(),
+                trait_path: None,
+                generic: false,
+                blanket: false
+            })
+        );
+        assert!(impl_target("impl<T: Display> ToString for T").is_some_and(|t| t.blanket));
+    }
+
+    #[test]
+    fn test_method_trait_paths() {
+        assert_eq!(inherent_self_type("impl<'a> Parser<'a>"), "Parser<'a>");
+        assert_eq!(
+            inherent_self_type("impl<T> Stack<T> where T: Clone"),
+            "Stack<T>"
+        );
+
+        let modules = |path: &[&str]| path.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            trait_import_path(
+                &DocsOrigin::Std("core".to_string()),
+                Some("core"),
+                Some("app"),
+                &modules(&["iter", "traits", "iterator"]),
+                "Iterator"
+            ),
+            "std::iter::Iterator"
+        );
+        assert_eq!(
+            trait_import_path(
+                &DocsOrigin::Std("std".to_string()),
+                Some("std"),
+                Some("app"),
+                &modules(&["io"]),
+                "Write"
+            ),
+            "std::io::Write"
+        );
+        assert_eq!(
+            trait_import_path(
+                &DocsOrigin::Dependency {
+                    name: "tokio-util".to_string(),
+                    version: "0.7.0".to_string()
+                },
+                Some("tokio-util-0.7.0"),
+                Some("app"),
+                &modules(&["codec"]),
+                "Decoder"
+            ),
+            "tokio_util::codec::Decoder"
+        );
+        assert_eq!(
+            trait_import_path(
+                &DocsOrigin::Local,
+                Some("app"),
+                Some("app"),
+                &modules(&["render"]),
+                "Render"
+            ),
+            "crate::render::Render"
+        );
+    }
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        serde_json::from_value(json!({
+            "start": {"line": start.0, "character": start.1},
+            "end": {"line": end.0, "character": end.1}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_declared_visibility() {
+        assert_eq!(
+            declared_visibility("/// Docs.\n#[derive(\n    Debug,\n)]\npub struct Config {}"),
+            ItemVisibility::Public
+        );
+        assert_eq!(
+            declared_visibility("pub(crate) fn load() {}"),
+            ItemVisibility::Restricted("crate".to_string())
+        );
+        assert_eq!(
+            declared_visibility("pub(in crate::server) mod handler;").to_string(),
+            "pub(in crate::server)"
+        );
+        assert_eq!(
+            declared_visibility("pub(self) mod handler;"),
+            ItemVisibility::Private
+        );
+        assert_eq!(
+            declared_visibility("fn public_api() {}"),
+            ItemVisibility::Private
+        );
+        assert_eq!(
+            declared_visibility("Variant(u32),"),
+            ItemVisibility::Private
+        );
+    }
+
+    #[test]
+    fn test_insert_mod_declaration() {
+        let content = "//! Tools.\n\nmod analysis;\npub mod server;\n\nuse std::fmt;\n";
+        let (updated, line) = insert_mod_declaration(content, "pub(crate) mod cargo;", "cargo");
+        assert_eq!(
+            updated,
+            "//! Tools.\n\nmod analysis;\npub(crate) mod cargo;\npub mod server;\n\nuse std::fmt;\n"
+        );
+        assert_eq!(line, 3);
+
+        let (updated, line) =
+            insert_mod_declaration("//! Crate.\nuse std::fmt;\n", "mod config;", "config");
+        assert_eq!(updated, "//! Crate.\n\nmod config;\n\nuse std::fmt;\n");
+        assert_eq!(line, 2);
+
+        assert_eq!(
+            child_module_dir(Path::new("/repo/src/lib.rs")),
+            Some(PathBuf::from("/repo/src"))
+        );
+        assert_eq!(
+            child_module_dir(Path::new("/repo/src/tools/mod.rs")),
+            Some(PathBuf::from("/repo/src/tools"))
+        );
+        assert_eq!(
+            child_module_dir(Path::new("/repo/src/tools.rs")),
+            Some(PathBuf::from("/repo/src/tools"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_byte_range() {
+        let content = "fn main() {\n    let s = \"héllo\";\n}\n";
+
+        assert_eq!(resolve_byte_range(content, &range((1, 4), (1, 7))).unwrap(), (16, 19));
+        assert_eq!(&content[16..19], "let");
+        // End of file (after the trailing newline) is a valid insertion point.
+        assert_eq!(
+            resolve_byte_range(content, &range((3, 0), (3, 0))).unwrap(),
+            (content.len(), content.len())
+        );
+
+        // Columns count UTF-16 code units: `é` is one unit but two bytes.
+        let (start, end) = resolve_byte_range(content, &range((1, 14), (1, 17))).unwrap();
+        assert_eq!(&content[start..end], "éll");
+        let err = resolve_byte_range(content, &range((0, 12), (0, 12))).unwrap_err();
+        assert!(err.to_string().contains("UTF-16"), "{err}");
+        assert!(resolve_byte_range(content, &range((4, 0), (4, 0))).is_err());
+        assert!(resolve_byte_range(content, &range((1, 7), (1, 4))).is_err());
+    }
+
+    #[test]
+    fn test_find_unique_block() {
+        let content = "let a = 1;\nlet b = 1;\n";
+        assert_eq!(find_unique_block(content, "let b").unwrap(), (11, 16));
+        assert!(find_unique_block(content, "= 1;").is_err());
+        assert!(find_unique_block(content, "let c").is_err());
+    }
+
+    #[test]
+    fn test_format_range_lines() {
+        let content = "fn a() {}\nfn b( ) {\n  1 }\nfn c() {}";
+        let (start, end) = find_unique_block(content, "fn b( ) {\n  1 }\n").unwrap();
+        assert_eq!(line_span(content, start, end), (1, 2));
+
+        let range = whole_lines_range(content, 1, 2);
+        assert_eq!((range.start.line, range.start.character), (1, 0));
+        assert_eq!((range.end.line, range.end.character), (3, 0));
+        let range = whole_lines_range(content, 3, 3);
+        assert_eq!((range.end.line, range.end.character), (3, 9));
+
+        let edit = |start: (u32, u32), end: (u32, u32)| TextEdit {
+            range: Range {
+                start: Position {
+                    line: start.0,
+                    character: start.1,
+                },
+                end: Position {
+                    line: end.0,
+                    character: end.1,
+                },
+            },
+            new_text: String::new(),
+        };
+        let kept = edits_within_lines(
+            vec![
+                edit((0, 4), (0, 5)),
+                edit((1, 4), (1, 6)),
+                edit((2, 0), (3, 0)),
+                edit((2, 3), (3, 2)),
+            ],
+            1,
+            2,
+        );
+        let starts: Vec<_> = kept.iter().map(|e| e.range.start.line).collect();
+        assert_eq!(starts, vec![1, 2]);
+        assert_eq!(kept[1].range.end.character, 0);
+    }
+
+    #[test]
+    fn test_parse_hover_signature() {
+        let hover = "```rust\ndemo::shapes::Circle\n```\n\n```rust\npub fn scale(&mut self, factor: f64, label: &str) -> Self\n```\n\n---\n\nScales the circle.";
+        let signature = parse_hover_signature(hover).unwrap();
+
+        assert_eq!(signature.name, "scale");
+        assert_eq!(signature.owner.as_deref(), Some("Circle"));
+        assert!(signature.has_receiver);
+        assert_eq!(signature.receiver.as_deref(), Some("&mut self"));
+        assert_eq!(signature.param_types, vec!["f64", "&str"]);
+        assert_eq!(signature.return_type.as_deref(), Some("Self"));
+        assert_eq!(
+            render_test_stub("test_scale", &signature),
+            "#[test]\nfn test_scale() {\n    let _result = super::Circle::scale(todo!(), 0.0, \"\");\n    // TODO: assert on the result\n}"
+        );
+    }
+
+    #[test]
+    fn test_insert_test_stub_into_existing_module() {
+        let content = "fn add(a: i32) -> i32 { a }\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn existing() {\n        let _ = '{';\n    }\n}\n";
+        let insertion = insert_test_stub(content, "#[test]\nfn test_add() {\n}");
+
+        assert!(!insertion.created_module);
+        assert_eq!(insertion.line, 9);
+        assert!(insertion.content.ends_with(
+            "    }\n\n    #[test]\n    fn test_add() {\n    }\n}\n"
+        ));
+    }
+
+    #[test]
+    fn test_insert_test_stub_creates_module() {
+        let content = "fn add(a: i32) -> i32 { a }";
+        let insertion = insert_test_stub(content, "#[test]\nfn test_add() {\n}");
+
+        assert!(insertion.created_module);
+        assert_eq!(
+            insertion.content,
+            "fn add(a: i32) -> i32 { a }\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn test_add() {\n    }\n}\n"
+        );
+        assert_eq!(insertion.line, 4);
+    }
+
+    #[test]
+    fn test_render_conversion_maps_matching_fields() {
+        let source = parse_struct_source(
+            "pub struct UserDto { pub id: u32, pub name: String, pub age: u8, pub extra: bool }",
+        )
+        .unwrap();
+        let target =
+            parse_struct_source("pub struct User { id: u64, name: String, email: String }")
+                .unwrap();
+
+        let conversion = render_conversion(&source, &target, &ConversionKind::From);
+        assert_eq!(
+            conversion.text,
+            "impl From<UserDto> for User {\n    fn from(value: UserDto) -> Self {\n        Self {\n            id: value.id.into(),\n            name: value.name,\n            email: todo!(),\n        }\n    }\n}\n"
+        );
+        assert_eq!(conversion.mapped, vec!["id", "name"]);
+        assert_eq!(conversion.todo, vec!["email"]);
+        assert_eq!(conversion.unused, vec!["age", "extra"]);
+    }
+
+    #[test]
+    fn test_render_conversion_try_from_tuple_struct() {
+        let source = parse_struct_source("struct Raw(i64, String);").unwrap();
+        let target = parse_struct_source("struct Checked(u32);").unwrap();
+        let kind = ConversionKind::TryFrom {
+            error_type: "String".to_string(),
+        };
+
+        let conversion = render_conversion(&source, &target, &kind);
+        assert!(conversion.text.contains("type Error = String;"));
+        assert!(
+            conversion
+                .text
+                .contains("        Ok(Self(value.0.try_into()?))\n")
+        );
+        assert_eq!(conversion.unused, vec!["1"]);
+    }
+
+    #[test]
+    fn test_render_default() {
+        let definition = parse_struct_source(
+            "pub struct Settings {\n    name: String,\n    retries: u32,\n    timeout: std::time::Duration,\n    tags: HashMap<String, Vec<u8>>,\n    limits: (u8, Option<f64>),\n    mask: [bool; 4],\n    mode: Mode,\n}",
+        )
+        .unwrap();
+        let default = render_default(&definition);
+        assert_eq!(
+            default.text,
+            "impl Default for Settings {\n    fn default() -> Self {\n        Self {\n            name: String::new(),\n            retries: 0,\n            timeout: Duration::ZERO,\n            tags: HashMap::new(),\n            limits: (0, None),\n            mask: [false; 4],\n            mode: Default::default(),\n        }\n    }\n}\n"
+        );
+        assert_eq!(default.fallback, vec!["mode"]);
+
+        let tuple = render_default(&parse_struct_source("struct Id(u64, &'static str);").unwrap());
+        assert!(tuple.text.contains("        Self(0, \"\")\n"));
+        assert!(tuple.fallback.is_empty());
+        assert_eq!(default_value("[String; 2]"), None);
+        assert_eq!(
+            derives("#[derive(Debug, Clone)]\n#[derive(serde::Serialize)]\nstruct A;"),
+            vec!["Debug", "Clone", "Serialize"]
+        );
+    }
+
+    #[test]
+    fn test_render_builder() {
+        let definition = parse_struct_source(
+            "pub struct Server {\n    host: String,\n    port: u16,\n    timeout: Option<Duration>,\n}",
+        )
+        .unwrap();
+        let builder = render_builder(&definition, "pub ", SetterStyle::Owned, None);
+        assert_eq!(
+            builder.text,
+            "#[derive(Default)]\npub struct ServerBuilder {\n    host: Option<String>,\n    port: Option<u16>,\n    timeout: Option<Duration>,\n}\n\nimpl ServerBuilder {\n    pub fn host(mut self, host: String) -> Self {\n        self.host = Some(host);\n        self\n    }\n\n    pub fn port(mut self, port: u16) -> Self {\n        self.port = Some(port);\n        self\n    }\n\n    pub fn timeout(mut self, timeout: Duration) -> Self {\n        self.timeout = Some(timeout);\n        self\n    }\n\n    pub fn build(self) -> Result<Server, &'static str> {\n        Ok(Server {\n            host: self.host.ok_or(\"missing field `host`\")?,\n            port: self.port.ok_or(\"missing field `port`\")?,\n            timeout: self.timeout,\n        })\n    }\n}\n\nimpl Server {\n    pub fn builder() -> ServerBuilder {\n        ServerBuilder::default()\n    }\n}\n"
+        );
+        assert_eq!(builder.required, vec!["host", "port"]);
+        assert_eq!(builder.optional, vec!["timeout"]);
+
+        let builder = render_builder(&definition, "", SetterStyle::MutRef, Some(&[]));
+        assert!(
+            builder
+                .text
+                .contains("    fn port(&mut self, port: u16) -> &mut Self {\n")
+        );
+        assert!(builder.text.contains(
+            "    fn build(&self) -> Server {\n        Server {\n            host: self.host.clone().unwrap_or_default(),\n"
+        ));
+        assert!(builder.required.is_empty());
+        assert_eq!(builder.defaulted, vec!["host", "port"]);
+        assert_eq!(
+            option_inner_type("std::option::Option<Vec<u8>>"),
+            Some("Vec<u8>")
+        );
+    }
+    #[test]
+    fn test_insert_after_line() {
+        let (updated, line) = insert_after_line("struct A;\nfn f() {}\n", 0, "impl X for A {}\n");
+        assert_eq!(updated, "struct A;\n\nimpl X for A {}\n\nfn f() {}\n");
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn test_inserted_lines_reports_new_match_arms() {
+        let content = "fn f(x: Option<u8>) {\n    match x {\n        Some(v) => {}\n    }\n}\n";
+        let edits = vec![TextEdit {
+            range: Range {
+                start: Position { line: 1, character: 12 },
+                end: Position { line: 3, character: 5 },
+            },
+            new_text: "{\n        Some(v) => {}\n        None => todo!(),\n    }".to_string(),
+        }];
+
+        assert_eq!(text_in_range(content, &edits[0].range), "{\n        Some(v) => {}\n    }");
+        assert_eq!(inserted_lines(content, &edits), vec!["None => todo!(),"]);
+    }
+
+    #[test]
+    fn test_patch_target() {
+        let root =
+            std::env::temp_dir().join(format!("rust-mcp-patch-target-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let lib = root.join("src/lib.rs");
+        std::fs::write(&lib, "").unwrap();
+
+        assert_eq!(
+            patch_target("a/src/lib.rs", "b/src/lib.rs", Some(&root)),
+            Some(lib.clone())
+        );
+        let absolute = format!("b{}", lib.display());
+        assert_eq!(
+            patch_target("/dev/null", &absolute, Some(&root)),
+            Some(lib.clone())
+        );
+        assert_eq!(
+            patch_target("a/src/main.rs", "b/src/main.rs", Some(&root)),
+            None
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extract_trait_edit() {
+        let content = r#"use std::fmt;
+
+/// A bounded buffer.
+#[cfg(feature = "std")]
+impl<T: Clone, const N: usize> Buffer<T, N> {
+    pub const CAPACITY: usize = N;
+
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    // Keeps the newest items.
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+"#;
+        let impl_start = content.find("impl<").unwrap();
+        let extraction =
+            extract_trait_edit(content, impl_start, "Bounded", &["push", "CAPACITY"]).unwrap();
+        assert_eq!(
+            extraction.trait_text,
+            "#[cfg(feature = \"std\")]\npub trait Bounded<T> {\n    const CAPACITY: usize;\n    fn push(&mut self, item: T);\n}\n"
+        );
+        assert!(extraction.updated.contains(
+            "impl<T: Clone, const N: usize> Buffer<T, N> {\n    /// Creates an empty buffer.\n"
+        ));
+        assert!(extraction.updated.ends_with(
+            r#"#[cfg(feature = "std")]
+impl<T: Clone, const N: usize> Bounded<T> for Buffer<T, N> {
+    const CAPACITY: usize = N;
+
+    // Keeps the newest items.
+    #[inline]
+    fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+}
+"#
+        ));
+        assert_eq!(extraction.remaining, vec!["new", "len"]);
+
+        let all = extract_trait_edit(
+            content,
+            impl_start,
+            "Bounded",
+            &["new", "len", "push", "CAPACITY"],
+        )
+        .unwrap();
+        assert!(
+            all.trait_text
+                .contains("    /// Creates an empty buffer.\n    fn new() -> Self;\n")
+        );
+        assert!(
+            all.updated
+                .contains("/// A bounded buffer.\n#[cfg(feature = \"std\")]\nimpl<T")
+        );
+        assert_eq!(all.updated.matches("impl<").count(), 1);
+        assert!(
+            all.notes
+                .iter()
+                .any(|note| note.contains("`new` was a `const fn`"))
+        );
+        assert!(extract_trait_edit(content, impl_start, "Bounded", &["clear"]).is_err());
+    }
+}
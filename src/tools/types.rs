@@ -4,6 +4,7 @@ use std::borrow::Cow;
 use std::sync::Arc;
 
 use crate::analyzer::RustAnalyzerClient;
+use crate::compiler::progress::CargoProgressSender;
 
 pub struct ToolDefinition {
     pub name: Cow<'static, str>,
@@ -48,34 +49,250 @@ pub async fn execute_tool(
     name: &str,
     args: Value,
     analyzer: &mut RustAnalyzerClient,
+) -> Result<ToolResult> {
+    execute_tool_with_progress(name, args, analyzer, None).await
+}
+
+/// Same as [`execute_tool`], but long-running cargo tools report build
+/// progress through `progress` while they run.
+pub async fn execute_tool_with_progress(
+    name: &str,
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+    progress: Option<CargoProgressSender>,
+) -> Result<ToolResult> {
+    let requested = match args.get("analysis").and_then(|v| v.as_str()) {
+        Some("full") => Some(true),
+        Some("quick") => Some(false),
+        Some(other) => {
+            return Err(anyhow::anyhow!(
+                "Invalid analysis '{}': expected \"quick\" or \"full\"",
+                other
+            ));
+        }
+        None => None,
+    };
+    let Some(full) = requested else {
+        return dispatch_tool(name, args, analyzer, progress).await;
+    };
+
+    // The mode only applies to this call, so later calls (and heavy tools in
+    // particular) keep the analysis depth they would otherwise get.
+    let previous = analyzer.full_analysis();
+    analyzer.set_full_analysis(full).await?;
+    let result = dispatch_tool(name, args, analyzer, progress).await;
+    let restored = analyzer.set_full_analysis(previous).await;
+    let mut result = result?;
+    if let Err(err) = restored {
+        // The output is still valid; only later calls run in the wrong mode.
+        let warning = format!(
+            "Warning: could not restore {} analysis: {}",
+            if previous { "full" } else { "quick" },
+            err
+        );
+        match result
+            .content
+            .first_mut()
+            .and_then(|item| item.get_mut("text"))
+        {
+            Some(Value::String(text)) => {
+                text.push_str("\n\n");
+                text.push_str(&warning);
+            }
+            _ => result.content.push(
+                json!({"type": "text", "text": warning})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            ),
+        }
+    }
+    Ok(result)
+}
+
+async fn dispatch_tool(
+    name: &str,
+    args: Value,
+    analyzer: &mut RustAnalyzerClient,
+    progress: Option<CargoProgressSender>,
 ) -> Result<ToolResult> {
     match name {
         "find_definition" => crate::tools::analysis::find_definition_impl(args, analyzer).await,
+        "find_definitions_batch" => {
+            crate::tools::analysis::find_definitions_batch_impl(args, analyzer).await
+        }
         "find_references" => crate::tools::analysis::find_references_impl(args, analyzer).await,
+        "find_callers" => crate::tools::analysis::find_callers_impl(args, analyzer).await,
+        "enclosing_blocks" => crate::tools::analysis::enclosing_blocks_impl(args, analyzer).await,
         "get_diagnostics" => crate::tools::analysis::get_diagnostics_impl(args, analyzer).await,
+        "diagnostic_snippet" => {
+            crate::tools::analysis::diagnostic_snippet_impl(args, analyzer).await
+        }
         "workspace_symbols" => {
             crate::tools::navigation::workspace_symbols_impl(args, analyzer).await
         }
         "document_symbols" => crate::tools::navigation::document_symbols_impl(args, analyzer).await,
+        "token_at" => crate::tools::navigation::token_at_impl(args, analyzer).await,
+        "resolve_path" => crate::tools::navigation::resolve_path_impl(args, analyzer).await,
+        "goto_parent_module" => {
+            crate::tools::navigation::goto_parent_module_impl(args, analyzer).await
+        }
+        "is_file_in_module_tree" => {
+            crate::tools::navigation::is_file_in_module_tree_impl(args, analyzer).await
+        }
+        "find_module_importers" => {
+            crate::tools::navigation::find_module_importers_impl(args, analyzer).await
+        }
+        "file_interface" => crate::tools::navigation::file_interface_impl(args, analyzer).await,
+        "list_reexports" => crate::tools::navigation::list_reexports_impl(args, analyzer).await,
+        "list_cfg_regions" => {
+            crate::tools::navigation::list_cfg_regions_impl(args, analyzer).await
+        }
+        "show_cfg" => crate::tools::navigation::show_cfg_impl(args, analyzer).await,
+        "feature_map" => crate::tools::navigation::feature_map_impl(args, analyzer).await,
+        "required_features" => {
+            crate::tools::navigation::required_features_impl(args, analyzer).await
+        }
+        "list_test_only_items" => {
+            crate::tools::navigation::list_test_only_items_impl(args, analyzer).await
+        }
+        "list_macros" => crate::tools::navigation::list_macros_impl(args, analyzer).await,
+        "goto_macro_definition" => {
+            crate::tools::navigation::goto_macro_definition_impl(args, analyzer).await
+        }
+        "expand_derive" => crate::tools::navigation::expand_derive_impl(args, analyzer).await,
+        "find_by_signature" => {
+            crate::tools::navigation::find_by_signature_impl(args, analyzer).await
+        }
         "get_hover" => crate::tools::analysis::get_hover_impl(args, analyzer).await,
+        "infer_type" => crate::tools::analysis::infer_type_impl(args, analyzer).await,
+        "trace_chain" => crate::tools::analysis::trace_chain_impl(args, analyzer).await,
+        "types_equal" => crate::tools::analysis::types_equal_impl(args, analyzer).await,
         "get_symbol_source" => crate::tools::analysis::get_symbol_source_impl(args, analyzer).await,
+        "symbol_diff" => crate::tools::analysis::symbol_diff_impl(args, analyzer).await,
+        "get_external_docs" => {
+            crate::tools::analysis::get_external_docs_impl(args, analyzer).await
+        }
+        "get_external_source" => {
+            crate::tools::analysis::get_external_source_impl(args, analyzer).await
+        }
+        "get_docs" => crate::tools::analysis::get_docs_impl(args, analyzer).await,
+        "check_doc_example" => crate::tools::analysis::check_doc_example_impl(args, analyzer).await,
         "rename_symbol" => crate::tools::refactoring::rename_symbol_impl(args, analyzer).await,
+        "rename_api_item" => crate::tools::refactoring::rename_api_item_impl(args, analyzer).await,
         "extract_function" => {
             crate::tools::refactoring::extract_function_impl(args, analyzer).await
         }
-        "run_cargo_check" => crate::tools::cargo::run_cargo_check_impl(args, analyzer).await,
+        "run_cargo_check" => {
+            crate::tools::cargo::run_cargo_check_impl(args, analyzer, progress).await
+        }
+        "diagnostic_histogram" => {
+            crate::tools::cargo::diagnostic_histogram_impl(args, analyzer).await
+        }
         "inline_function" => crate::tools::refactoring::inline_function_impl(args, analyzer).await,
+        "scaffold_test" => crate::tools::refactoring::scaffold_test_impl(args, analyzer).await,
+        "fill_match_arms" => crate::tools::refactoring::fill_match_arms_impl(args, analyzer).await,
+        "match_coverage" => crate::tools::refactoring::match_coverage_impl(args, analyzer).await,
+        "apply_edit" => crate::tools::refactoring::apply_edit_impl(args, analyzer).await,
+        "dry_run_patch" => crate::tools::refactoring::dry_run_patch_impl(args, analyzer).await,
+        "check_function" => crate::tools::refactoring::check_function_impl(args, analyzer).await,
+        "diagnostics_delta" => {
+            crate::tools::refactoring::diagnostics_delta_impl(args, analyzer).await
+        }
+        "create_module" => crate::tools::refactoring::create_module_impl(args, analyzer).await,
+        "suggest_module_split" => {
+            crate::tools::refactoring::suggest_module_split_impl(args, analyzer).await
+        }
+        "format_range" => crate::tools::refactoring::format_range_impl(args, analyzer).await,
+        "format_with_config" => {
+            crate::tools::refactoring::format_with_config_impl(args, analyzer).await
+        }
+        "suggest_import" => crate::tools::refactoring::suggest_import_impl(args, analyzer).await,
+        "add_import" => crate::tools::refactoring::add_import_impl(args, analyzer).await,
+        "method_trait" => crate::tools::refactoring::method_trait_impl(args, analyzer).await,
+        "self_type" => crate::tools::refactoring::self_type_impl(args, analyzer).await,
+        "generate_conversion" => {
+            crate::tools::refactoring::generate_conversion_impl(args, analyzer).await
+        }
+        "generate_default" => {
+            crate::tools::refactoring::generate_default_impl(args, analyzer).await
+        }
+        "generate_builder" => {
+            crate::tools::refactoring::generate_builder_impl(args, analyzer).await
+        }
+        "extract_trait" => crate::tools::refactoring::extract_trait_impl(args, analyzer).await,
         "apply_clippy_suggestions" => {
             crate::tools::quality::apply_clippy_suggestions_impl(args, analyzer).await
         }
+        "find_unnecessary_clones" => {
+            crate::tools::quality::find_unnecessary_clones_impl(args, analyzer).await
+        }
+        "unused_dependencies" => {
+            crate::tools::quality::unused_dependencies_impl(args, analyzer).await
+        }
+        "complexity_report" => {
+            crate::tools::quality::complexity_report_impl(args, analyzer).await
+        }
+        "find_large_functions" => {
+            crate::tools::quality::find_large_functions_impl(args, analyzer).await
+        }
+        "must_use_candidates" => {
+            crate::tools::quality::must_use_candidates_impl(args, analyzer).await
+        }
+        "find_dyn_usages" => crate::tools::quality::find_dyn_usages_impl(args, analyzer).await,
+        "async_map" => crate::tools::quality::async_map_impl(args, analyzer).await,
+        "find_blocking_in_async" => {
+            crate::tools::quality::find_blocking_in_async_impl(args, analyzer).await
+        }
+        "find_panics" => crate::tools::quality::find_panics_impl(args, analyzer).await,
+        "check_format_args" => crate::tools::quality::check_format_args_impl(args, analyzer).await,
+        "list_runnables" => crate::tools::cargo::list_runnables_impl(args, analyzer).await,
+        "list_tests" => crate::tools::cargo::list_tests_impl(args, analyzer).await,
+        "check_msrv" => crate::tools::cargo::check_msrv_impl(args, analyzer).await,
+        "find_manifest" => crate::tools::cargo::find_manifest_impl(args, analyzer).await,
+        "preview_features" => crate::tools::cargo::preview_features_impl(args, analyzer).await,
+        "describe_build_script" => {
+            crate::tools::cargo::describe_build_script_impl(args, analyzer).await
+        }
         "get_type_hierarchy" => {
             crate::tools::advanced::get_type_hierarchy_impl(args, analyzer).await
         }
+        "check_trait_impl" => crate::tools::advanced::check_trait_impl_impl(args, analyzer).await,
+        "list_methods" => crate::tools::advanced::list_methods_impl(args, analyzer).await,
+        "find_method_impl" => crate::tools::advanced::find_method_impl_impl(args, analyzer).await,
+        "error_map" => crate::tools::advanced::error_map_impl(args, analyzer).await,
+        "trace_question_mark" => {
+            crate::tools::advanced::trace_question_mark_impl(args, analyzer).await
+        }
+        "find_constructions" => {
+            crate::tools::advanced::find_constructions_impl(args, analyzer).await
+        }
+        "describe_enum" => crate::tools::advanced::describe_enum_impl(args, analyzer).await,
+        "describe_function" => {
+            crate::tools::advanced::describe_function_impl(args, analyzer).await
+        }
+        "optimization_attributes" => {
+            crate::tools::advanced::optimization_attributes_impl(args, analyzer).await
+        }
+        "struct_layout" => crate::tools::advanced::struct_layout_impl(args, analyzer).await,
+        "find_recursive_types" => {
+            crate::tools::advanced::find_recursive_types_impl(args, analyzer).await
+        }
+        "describe_generics" => {
+            crate::tools::advanced::describe_generics_impl(args, analyzer).await
+        }
+        "generics_in_scope" => {
+            crate::tools::advanced::generics_in_scope_impl(args, analyzer).await
+        }
+        "derive_bounds" => crate::tools::advanced::derive_bounds_impl(args, analyzer).await,
+        "trace_lifetime" => crate::tools::advanced::trace_lifetime_impl(args, analyzer).await,
         "inspect_mir" => Ok(not_implemented_tool_result("inspect_mir")),
         "inspect_llvm_ir" => Ok(not_implemented_tool_result("inspect_llvm_ir")),
         "inspect_asm" => Ok(not_implemented_tool_result("inspect_asm")),
         "inspect" => Ok(not_implemented_tool_result("inspect")),
+        "inspect_diff" => Ok(not_implemented_tool_result("inspect_diff")),
         "capabilities" => Ok(not_implemented_tool_result("capabilities")),
+        "server_status" => Ok(not_implemented_tool_result("server_status")),
         _ => Err(anyhow::anyhow!("Unknown tool: {}", name)),
     }
 }
@@ -84,36 +301,941 @@ pub fn get_tools() -> Vec<ToolDefinition> {
     vec![
         // Code Analysis
         ToolDefinition::new(
-            "find_definition",
-            "Locates the definition of a specific symbol by searching within a provided code block. More reliable than using raw coordinates.",
+            "find_definition",
+            "Locates the definition of a specific symbol by searching within a provided code block. More reliable than using raw coordinates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name to find"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_definitions_batch",
+            "Resolves the definitions of several symbols in one file in a single call. Each query is located like in find_definition; the results array is aligned with the queries, and a query that fails carries its own error instead of failing the batch.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "queries": {
+                        "type": "array",
+                        "description": "Symbols to resolve, each with the code block containing it",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "symbol": {"type": "string", "description": "The exact symbol name to find"},
+                                "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                                "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                            },
+                            "required": ["symbol", "code_block"]
+                        }
+                    }
+                },
+                "required": ["file_path", "queries"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_references",
+            "Finds all references to a specific symbol by searching within a provided code block. Useful for refactoring and understanding usage patterns. Use `scope` to keep only the references in this file or this file's directory.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name to find references for"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "scope": {"type": "string", "enum": ["file", "directory", "workspace"], "description": "`file` keeps references in file_path, `directory` those under file_path's directory, `workspace` all of them", "default": "workspace"},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_callers",
+            "Lists every call site of a function or method across the workspace, grouped by the caller's module, using rust-analyzer's call hierarchy. Each call site has a 1-based `line` and `column`. Unlike find_references it skips imports, comments and type positions. Locate the function (its definition or any call) within a provided code block.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact function or method name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function name"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "enclosing_blocks",
+            "Lists the loops and blocks enclosing a position, innermost first, with their kind (loop, while, for, if, match, closure, async, ...), label and line range, and which loop a plain break or continue there leaves. Use it before inserting a labeled break or moving code across control flow. Locate the position with a symbol (e.g. `break`) within a provided code block.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "An identifier or keyword at the position"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_diagnostics",
+            "Get compiler diagnostics for a file",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "diagnostic_snippet",
+            "Extracts a self-contained snippet for a diagnostic: the item containing it plus the workspace types, traits and functions it references (followed to a bounded depth), with the imports they need. Paste it into a playground or a scratch crate to isolate and reason about the error. Pick the diagnostic by its 1-based line and, when several share the line, a message substring.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file with the diagnostic"},
+                    "line": {"type": "integer", "description": "1-based line the diagnostic starts on"},
+                    "message": {"type": "string", "description": "Substring of the diagnostic message, to choose among several on the line"},
+                    "max_depth": {"type": "integer", "description": "How many levels of referenced definitions to follow (max 5)", "default": 2}
+                },
+                "required": ["file_path", "line"]
+            }),
+        ),
+        ToolDefinition::new(
+            "workspace_symbols",
+            "Search for symbols in the workspace. Results are sorted by crate, module and name and returned a page at a time along with the total match count.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "limit": {"type": "integer", "description": "Maximum number of symbols to return", "default": 100, "minimum": 1},
+                    "offset": {"type": "integer", "description": "Number of symbols to skip; pass the previous page's next_offset", "default": 0, "minimum": 0},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["query"]
+            }),
+        ),
+        ToolDefinition::new(
+            "document_symbols",
+            "Retrieves the hierarchical structure (symbols) of a file. PREFERRED over `read_file` for large files to understand code organization without consuming massive context tokens. Returns an outline of functions, structs, and impls. Pass start_line/end_line to only return symbols overlapping that line range.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string"},
+                    "start_line": {"type": "integer", "minimum": 0, "description": "0-based first line of the range to outline"},
+                    "end_line": {"type": "integer", "minimum": 0, "description": "0-based last line of the range to outline (inclusive)"},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "token_at",
+            "Returns the token (identifier, keyword, lifetime, literal, operator, comment, ...) at a 0-based line and UTF-16 character, with its kind and exact range, plus the nearest tokens before and after it. A cheap check that a position targets what you expect before issuing a heavier position-based query; it reads the file directly and doesn't involve rust-analyzer.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "line": {"type": "integer", "minimum": 0, "description": "0-based line"},
+                    "character": {"type": "integer", "minimum": 0, "description": "0-based character (UTF-16 code units, as in LSP positions)"}
+                },
+                "required": ["file_path", "line", "character"]
+            }),
+        ),
+        ToolDefinition::new(
+            "resolve_path",
+            "Resolves a fully-qualified Rust path (e.g. `crate::net::Server::bind`) to its definition location. Use this to navigate by name when no code block is available. Lists candidates when the path is ambiguous.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "A Rust path such as `crate::module::Type::method`"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_by_signature",
+            "Finds workspace functions and methods whose name matches a query and whose signature satisfies simple predicates: async or not, a return type substring (e.g. `Result`) and a receiver/parameter type substring (e.g. `&mut self`). Returns each match with its location and full signature.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Workspace symbol query for the function names to consider"},
+                    "is_async": {"type": "boolean", "description": "Only async (true) or only non-async (false) functions"},
+                    "returns": {"type": "string", "description": "Substring the return type must contain, e.g. `Result`"},
+                    "takes": {"type": "string", "description": "Substring the receiver or a parameter type must contain, e.g. `&mut self`"},
+                    "limit": {"type": "integer", "description": "Maximum number of matches to return", "default": 50, "minimum": 1}
+                },
+                "required": ["query"]
+            }),
+        ),
+        ToolDefinition::new(
+            "goto_parent_module",
+            "Finds the parent module file of a Rust source file, i.e. the file holding its `mod` declaration. Handles both `a/b.rs` + `a.rs` (2018) and `a/b.rs` + `a/mod.rs` layouts and reports the declaration line, or `crate_root` for lib.rs/main.rs.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "list_reexports",
+            "Lists the `pub use` re-exports of a module file, the public surface it assembles from elsewhere. Each re-exported name is resolved through chains of re-exports to the item that defines it, with its canonical path. Glob re-exports report how many public items they pull in and are flagged when that surface is large.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the module file (e.g. lib.rs or a prelude.rs)"},
+                    "include_restricted": {"type": "boolean", "description": "Also list `pub(crate)`, `pub(super)` and `pub(in ...)` re-exports", "default": false}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "is_file_in_module_tree",
+            "Checks whether a Rust source file is reachable from its crate root through `mod` declarations. Orphan files are never compiled or analyzed; for those, reports each missing `mod` declaration and the file it belongs in.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_module_importers",
+            "Finds every `use` declaration importing from a module (`use crate::a::b::Item`, `use super::b::*`, `use mycrate::a::b` from tests and other crates) and the `mod b;` declaring it, with the importing file, line, declaration text and what each takes from the module. Answers \"who depends on this module\" before moving or removing it.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "module": {"type": "string", "description": "The module path, e.g. `crate::tools::navigation` (the `crate::` prefix or crate name is optional)"},
+                    "path": {"type": "string", "description": "File or directory to search, inside the module's package; defaults to the project root"}
+                },
+                "required": ["module"]
+            }),
+        ),
+        ToolDefinition::new(
+            "file_interface",
+            "Summarizes a file's place in the module graph: the items it exports (`pub` and `pub(..)` items, public methods of inherent impls, `#[macro_export]` macros and `pub use` re-exports) and the external symbols it uses (`use` leaves and inline qualified paths such as `std::fs::read`), each with a fully qualified path. Built from rust-analyzer's document symbols and the file's `use` declarations, resolved against its module path.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "list_cfg_regions",
+            "Lists the `#[cfg(...)]`, `#![cfg(...)]` and `#[cfg_attr(...)]` attributes and `cfg!(...)` macros in a file. Each region reports the raw condition, a readable rendering (e.g. `unix and not feature \"serde\" enabled`), the gated item and its line range.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "target": {"type": "string", "description": "Optional target triple (or \"host\"); when set, each region reports whether it is `active` for that target"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "feature_map",
+            "Maps every feature declared in a package's Cargo.toml (including implicit features of optional dependencies) to the code gated by `cfg(feature = \"...\")` in its sources: each gate's file, condition, gated item and line range. Also reports declared features that gate no code and features used in `cfg` but never declared.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "manifest_path": {"type": "string", "description": "Absolute path to the package's Cargo.toml"}
+                },
+                "required": ["manifest_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "required_features",
+            "Works out the smallest set of Cargo features under which every item of a file compiles, from its `cfg(feature = \"...\")` gates and those on the `mod` declarations leading to it. Expands features through the manifest's `[features]` table, gives the `--features` argument to check the file with, and reports features the code tests but the manifest never declares.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "list_test_only_items",
+            "Lists the items gated behind `#[cfg(test)]` (or any cfg that `test` can enable, such as `any(test, feature = \"test-utils\")`) in a file or crate: test modules, helpers, mock impls, test-only imports. Each item reports its kind, name, file and line range, and whether it exists only in test builds. Explains why such symbols do not resolve in regular builds.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a file, or a crate directory to scan recursively"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "show_cfg",
+            "Runs `rustc --print cfg` and returns the cfg flags active for a target: `target_os`, `target_arch`, `target_pointer_width`, target features and the rest as key/value pairs. Defaults to the host target.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "target": {"type": "string", "description": "Optional target triple (e.g. \"wasm32-unknown-unknown\"); defaults to the host"},
+                    "file_path": {"type": "string", "description": "Optional file whose directory rustc runs in, so its rust-toolchain.toml applies"}
+                }
+            }),
+        ),
+        ToolDefinition::new(
+            "list_macros",
+            "Lists the macros defined in a crate: `macro_rules!` definitions (with whether they are `#[macro_export]`ed) and `#[proc_macro]`, `#[proc_macro_derive(..)]` and `#[proc_macro_attribute]` functions. Each macro reports its name, kind, file and line range; derives also report the implementing function and helper attributes.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "crate_path": {"type": "string", "description": "Absolute path to the crate directory (or a single file) to scan"}
+                },
+                "required": ["crate_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "goto_macro_definition",
+            "Resolves a macro invocation located within a provided code block and returns the macro's definition source: the `macro_rules!` rules, or the implementing function of a proc macro (derive, attribute or function-like). Reports the kind, file and line range, plus `exported` or the proc-macro function and helper attributes.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file containing the invocation"},
+                    "symbol": {"type": "string", "description": "The macro name, with or without `!` (e.g. `vec!`, `Serialize` for a derive)"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the invocation"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the name's occurrence within the code_block", "default": 1},
+                    "max_lines": {"type": "integer", "description": "Truncate the middle of definitions longer than this many lines", "default": 400}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "expand_derive",
+            "Expands one trait of a `#[derive(..)]` attribute found in a provided code block and returns only the impl it generates, e.g. just the `Debug` impl. Fails with the derived traits when the requested one is not in the list.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "code_block": {"type": "string", "description": "A unique code snippet containing the #[derive(..)] attribute"},
+                    "trait_name": {"type": "string", "description": "The derived trait to show, e.g. `Debug` or `serde::Serialize`"}
+                },
+                "required": ["file_path", "code_block", "trait_name"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_hover",
+            "Retrieves hover information (signature, documentation) for a specific symbol by locating it within a provided code block. This method is more robust than using line/character coordinates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name to hover over"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet (3-5 lines) containing the target symbol to ensure correct context"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the provided code_block. Defaults to 1.", "default": 1},
+                    "expand_aliases": {"type": "boolean", "description": "Also resolve type aliases (e.g. `type Result<T> = std::result::Result<T, MyError>`) to the underlying type", "default": false},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "infer_type",
+            "Returns the type rust-analyzer inferred for a `let` binding (or closure/pattern binding), located by name within a code block. Long types such as iterator chains and closures are returned verbatim, with `qualified_type` spelling linked items as `crate::module::Name`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The binding name, e.g. `iter` in `let iter = ...`"},
+                    "code_block": {"type": "string", "description": "A unique code snippet containing the binding"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the provided code_block. Defaults to 1.", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "trace_chain",
+            "Traces the type through a method chain such as `vec.iter().map(f).filter(g).collect()`: returns an ordered list of steps (the receiver, then each `.call(..)`, `.field` or `.await`) with the type of the chain up to and including that step. Types come from rust-analyzer's range hover, falling back to its chaining inlay hints, and are returned verbatim however long. Useful for debugging iterator-chain type errors.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "expression": {"type": "string", "description": "The chain expression exactly as written in the file, line breaks included"},
+                    "code_block": {"type": "string", "description": "A unique code snippet containing the expression"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the expression's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "expression", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "types_equal",
+            "Tells whether two type expressions, each copied from a file, denote the same type: paths are resolved with go-to-definition to `crate::module::Name` and type aliases are expanded with their generic arguments substituted. Returns `equal`, the `common_type` when they are, and each side's resolved form with the aliases it went through.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file containing `left`"},
+                    "left": {"type": "string", "description": "A type exactly as written in the file, e.g. `io::Result<Vec<u8>>`"},
+                    "right": {"type": "string", "description": "The type to compare it with, exactly as written in its file"},
+                    "right_file_path": {"type": "string", "description": "Absolute path to the file containing `right`. Defaults to file_path."}
+                },
+                "required": ["file_path", "left", "right"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_symbol_source",
+            "Retrieves the source code of a symbol by locating it within a provided code block. Useful for reading implementations.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "include_impl_context": {"type": "boolean", "description": "Wrap the source in the headers of its enclosing impl, trait and module blocks (including generics and where-clauses)", "default": false},
+                    "max_lines": {"type": "integer", "description": "Longer sources keep their first and last lines with a `...[N lines omitted]...` marker in between", "default": 400, "minimum": 2},
+                    "analysis": {"type": "string", "enum": ["quick", "full"], "description": "Switch rust-analyzer to quick (no proc-macros or build scripts) or full analysis for this call only; the previous mode is restored afterwards, and each switch reloads the workspace"}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "symbol_diff",
+            "Shows how one item changed between two git refs: extracts the symbol's source from the file at each ref and returns a unified diff of just that item, with status added, removed, modified or unchanged. Name the symbol by its name or a qualified path such as `Parser::parse`. Omit to_ref to compare against the working tree.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file containing the symbol"},
+                    "symbol": {"type": "string", "description": "Item name, optionally qualified by its impl type, trait or module (`Parser::parse`)"},
+                    "from_ref": {"type": "string", "description": "Git ref of the old version (commit, branch, tag, `HEAD~3`)"},
+                    "to_ref": {"type": "string", "description": "Git ref of the new version; omit for the working tree"}
+                },
+                "required": ["file_path", "symbol", "from_ref"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_external_docs",
+            "Returns the documentation URL (docs.rs for dependencies, doc.rust-lang.org for the standard library) and the hover documentation for a symbol located within a provided code block. Useful for third-party APIs.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_external_source",
+            "Returns the source of a standard library or dependency item (e.g. the `Iterator::map` a `.map(..)` call resolves to) located within a provided code block, together with its enclosing trait or impl. Falls back to the hover signature when the source can't be read, and reports when the `rust-src` component is missing.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "max_lines": {"type": "integer", "description": "Longest source returned in full; longer items keep their first and last lines", "default": 400}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "get_docs",
+            "Returns only the rustdoc comment of a symbol located within a provided code block: its `///` lines and `#[doc]` attributes, plus `//!` docs for modules. Reports explicitly when the symbol is undocumented.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "check_doc_example",
+            "Runs the Rust code blocks in a symbol's documentation with `cargo test --doc` and reports whether each one passed, with the compiler or test output of failures. Honours `ignore`, `no_run`, `should_panic` and `compile_fail`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "run": {"type": "boolean", "description": "Run the doctests; false only lists the examples and their code", "default": true}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "rename_symbol",
+            "Renames a symbol with scope awareness by locating it within a provided code block. This method is more robust than using raw line/character coordinates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact symbol name to rename"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "new_name": {"type": "string", "description": "The new name for the symbol"},
+                    "scope": {"type": "string", "enum": ["workspace", "local"], "description": "`local` only applies edits inside the function or method enclosing the symbol; `workspace` applies every edit", "default": "workspace"},
+                    "verify": {"type": "boolean", "description": "Run `cargo check` after editing and restore every edited file if it fails", "default": false}
+                },
+                "required": ["file_path", "symbol", "code_block", "new_name"]
+            }),
+        ),
+        ToolDefinition::new(
+            "rename_api_item",
+            "Renames an item like rename_symbol and classifies the semver impact: renaming a `pub` item reachable from a library crate's root is a breaking change, anything else is not. Returns the item's visibility, the non-public modules restricting it, and the edit summary.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact name of the item to rename"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "new_name": {"type": "string", "description": "The new name for the item"},
+                    "verify": {"type": "boolean", "description": "Run `cargo check` after editing and restore every edited file if it fails", "default": false}
+                },
+                "required": ["file_path", "symbol", "code_block", "new_name"]
+            }),
+        ),
+        ToolDefinition::new(
+            "run_cargo_check",
+            "Execute cargo check and parse errors. With a file_path, only the workspace package owning that file is checked (`-p <package>`). Reports build progress as MCP progress notifications when the request carries a progress token, otherwise appends a progress log to the result.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "workspace_path": {"type": "string"},
+                    "file_path": {"type": "string", "description": "Absolute path to a source file; limits the check to the package that owns it"}
+                },
+                "required": ["workspace_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "diagnostic_histogram",
+            "Runs `cargo check` and counts its diagnostics by error or lint code (e.g. 40 `unused_variables`, 3 `E0308`) and by crate, most frequent first, to triage a codebase with many warnings. Also returns the deduplicated diagnostics themselves, errors first. With a file_path, only the package owning that file is checked.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "workspace_path": {"type": "string"},
+                    "file_path": {"type": "string", "description": "Absolute path to a source file; limits the check to the package that owns it"},
+                    "limit": {"type": "integer", "description": "Most diagnostics listed individually; the counts always cover all of them", "default": 100}
+                },
+                "required": ["workspace_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "list_runnables",
+            "Lists the runnable tests, test modules, doctests, benches and binaries of a file from rust-analyzer's `experimental/runnables`, each with its label, kind, line and the full cargo command that runs it. Falls back to scanning for `#[test]` functions and `fn main` when the server lacks the extension.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "list_tests",
+            "Lists every `#[test]`, `#[tokio::test]` (any `#[<path>::test]`) and `#[bench]` function of a package with its fully-qualified path, file and line, its suite (`unit` under src/, `integration` under tests/, `benchmark` under benches/, `example` under examples/), the libtest filter and the cargo command that runs exactly that test. Inline module paths come from document symbols, since LSP symbol kinds do not mark tests.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to the package directory or any file inside it"},
+                    "limit": {"type": "integer", "description": "Maximum number of tests to list (default: 500); the counts cover all of them"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "check_msrv",
+            "Reports the edition and rust-version (MSRV) the file's Cargo.toml declares, following workspace inheritance, and heuristically flags language and library features used in the file that were stabilized after that version or need a newer edition: let-else, GATs, OnceLock, is_some_and, async fn and impl Trait in traits, C string literals, #[diagnostic::], inline const, LazyLock, #[expect], &raw, unsafe extern, use<..> capturing, is_none_or, async closures and let chains.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_manifest",
+            "Finds the Cargo.toml governing a file or directory by walking upward, and says whether it is a package manifest, a virtual workspace or both. For a package inside a workspace it also returns the workspace root manifest, checking the workspace's members and exclude lists; a workspace above that does not include the package is reported separately.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to a source file or directory"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "preview_features",
+            "Runs cargo check with the given feature flags and with the default features, and returns the diagnostics each feature set introduces or resolves. Builds use a separate target directory, so the regular build and rust-analyzer's configuration are left untouched.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "workspace_path": {"type": "string"},
+                    "features": {"type": "array", "items": {"type": "string"}, "description": "Features to enable, e.g. [\"serde\", \"tokio/rt\"]"},
+                    "no_default_features": {"type": "boolean", "description": "Disable the default features", "default": false},
+                    "all_features": {"type": "boolean", "description": "Enable every feature", "default": false},
+                    "file_path": {"type": "string", "description": "Absolute path to a source file; limits both checks to the package that owns it"}
+                },
+                "required": ["workspace_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "extract_function",
+            "Extracts a block of code into a new function. Locates the code by matching the provided code_block snippet. More robust than using raw line/character ranges.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "code_block": {"type": "string", "description": "The exact multi-line code snippet to be extracted into a new function"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the code_block's occurrence. Defaults to 1.", "default": 1},
+                    "function_name": {"type": "string", "description": "The name of the new function"}
+                },
+                "required": ["file_path", "code_block", "function_name"]
+            }),
+        ),
+        ToolDefinition::new(
+            "inline_function",
+            "Inlines a function call by locating it within a provided code block. More robust than using raw line/character coordinates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The name of the function to inline"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function call"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the function call's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "scaffold_test",
+            "Adds a #[test] stub for a function located within a provided code block. Reuses the file's #[cfg(test)] module (or creates one) and calls the function with placeholder arguments derived from its signature.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The name of the function to test"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "fill_match_arms",
+            "Applies rust-analyzer's 'Fill match arms' assist to a match expression located within a provided code block, inserting the missing arms with todo!() bodies. Reports when the match is already exhaustive.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the `match` keyword"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the `match` keyword within the code_block", "default": 1}
+                },
+                "required": ["file_path", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "match_coverage",
+            "Read-only companion to fill_match_arms: for a match expression located within a provided code block, reports which variants of the matched enum its arms cover, which are covered only partially (guards, refutable fields), which fall through to a wildcard or binding arm, and which are missing. Patterns naming no variant of the enum are listed separately.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the `match` keyword"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the `match` keyword within the code_block", "default": 1}
+                },
+                "required": ["file_path", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "suggest_import",
+            "Lists the `use` paths rust-analyzer's auto-import assist offers for an unresolved identifier located within a provided code block. Nothing is applied; pick a candidate and add the `use` yourself (e.g. with apply_edit).",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The unresolved identifier"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the identifier"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the identifier's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "method_trait",
+            "Resolves a method call located within a provided code block and reports whether it is an inherent method (with its `self_type`) or a trait method, naming the trait, its fully-qualified `path` and the `use` statement that brings it into scope (none for prelude traits). When the call does not resolve because its trait is not in scope, lists the traits rust-analyzer would import to fix the error.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The method name at the call site"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the call"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the method name's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "self_type",
+            "Reports what `Self` means at a symbol located within a provided code block: walks up the document symbols to the innermost enclosing impl block and returns its self type and implemented trait, as written and as resolved `use` paths, plus whether the impl is generic or blanket. Fails with an explanation when the symbol is not inside an impl.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "Any symbol inside the impl block, e.g. `self`, `Self` or a method name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "add_import",
+            "Adds `use <path>;` for an unresolved identifier located within a provided code block, using rust-analyzer's matching \"Import `path`\" assist when offered and otherwise inserting the line into the right import group. Then re-checks diagnostics and reports whether the identifier now resolves. Get candidate paths from suggest_import.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The unresolved identifier"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the identifier"},
+                    "path": {"type": "string", "description": "The path to import, e.g. `std::collections::HashMap`"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the identifier's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block", "path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "generate_conversion",
+            "Generates an `impl From<Source> for Target` (or `TryFrom`) skeleton for two structs given by path. Target fields are filled from source fields of the same name (or position, for tuple structs), through `into()` / `try_into()?` when the types differ; the rest are `todo!()`. Returns the code, or inserts it after the target struct with `insert: true`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "source_type": {"type": "string", "description": "Path of the struct to convert from, e.g. `dto::UserDto`"},
+                    "target_type": {"type": "string", "description": "Path of the struct to convert into, e.g. `model::User`"},
+                    "kind": {"type": "string", "enum": ["From", "TryFrom"], "default": "From"},
+                    "error_type": {"type": "string", "description": "The `Error` type of a TryFrom impl", "default": "Box<dyn std::error::Error + Send + Sync>"},
+                    "insert": {"type": "boolean", "description": "Write the impl into the target struct's file", "default": false}
+                },
+                "required": ["source_type", "target_type"]
+            }),
+        ),
+        ToolDefinition::new(
+            "generate_default",
+            "Generates an `impl Default` for a struct located within a provided code block, giving each field the value its type defaults to (`0`, `String::new()`, `None`, `Vec::new()`, ...). Fields of unrecognized types are left as `Default::default()` and reported; when there are none, `#[derive(Default)]` is suggested instead. Returns the code, or inserts it after the struct with `insert: true`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The struct name or a use of it"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "insert": {"type": "boolean", "description": "Write the impl into the struct's file", "default": false}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "generate_builder",
+            "Generates a builder for a struct located within a provided code block: a `NameBuilder` holding each field as an `Option`, one setter per field, a `build()` and `Name::builder()`. build() returns `Result<Name, &'static str>` naming the first missing required field, or `Name` when no field is required. `Option` fields stay optional and their setters take the inner type. Returns the code, or inserts it after the struct with `insert: true`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The struct name or a use of it"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "setters": {"type": "string", "enum": ["owned", "mut_ref"], "description": "`owned` setters take and return the builder by value; `mut_ref` setters take `&mut self` and return `&mut Self`", "default": "owned"},
+                    "required": {"type": "array", "items": {"type": "string"}, "description": "Fields build() fails without; other non-`Option` fields fall back to `Default::default()`. Defaults to every non-`Option` field"},
+                    "insert": {"type": "boolean", "description": "Write the builder into the struct's file", "default": false}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "extract_trait",
+            "Moves chosen methods (and associated consts or types) of an inherent impl into a new trait: declares the trait with their signatures and docs above the impl, implements it for the same type below, and removes the items from the inherent impl, or replaces the impl when nothing is left. Locate the impl by its `impl` keyword within a provided code block.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the impl's `impl` keyword"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the `impl` keyword within the code_block", "default": 1},
+                    "trait_name": {"type": "string", "description": "Name of the new trait"},
+                    "methods": {"type": "array", "items": {"type": "string"}, "description": "Names of the methods, consts and types to move into the trait"}
+                },
+                "required": ["file_path", "code_block", "trait_name", "methods"]
+            }),
+        ),
+        ToolDefinition::new(
+            "create_module",
+            "Creates a new module file next to its parent module (`<dir>/<name>.rs`, or `<dir>/<name>/mod.rs` with `mod_rs: true`) and inserts the `mod <name>;` declaration into the parent, so the module is part of the crate. An existing undeclared file is declared instead of overwritten. Returns the module file, the edited parent and the declaration's line.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "parent_file": {"type": "string", "description": "Absolute path to the parent module's file, e.g. src/lib.rs or src/tools/mod.rs"},
+                    "name": {"type": "string", "description": "The new module's name"},
+                    "visibility": {"type": "string", "description": "`private`, `pub`, `pub(crate)`, `pub(super)` or `pub(in path)`", "default": "private"},
+                    "mod_rs": {"type": "boolean", "description": "Create `<name>/mod.rs` instead of `<name>.rs`", "default": false},
+                    "content": {"type": "string", "description": "Initial content of the new file", "default": ""}
+                },
+                "required": ["parent_file", "name"]
+            }),
+        ),
+        ToolDefinition::new(
+            "suggest_module_split",
+            "Proposes how to split a large file into submodules (heuristic and advisory; nothing is moved). Groups the file's top-level items around its structs, enums and traits using name references and call-hierarchy edges, then by shared name prefixes, merging small groups; returns suggested module names and files, the items for each, the items best left in the parent and how many links cross the new boundaries.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file to split"},
+                    "min_lines": {"type": "integer", "description": "Smallest submodule worth proposing, in lines; smaller groups are merged", "default": 30}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "apply_edit",
+            "Replaces a range or a unique code block of a file with new text, writes the file, has rust-analyzer reanalyze it and returns the diff together with the file's diagnostics. Positions are 0-based; `character` counts UTF-16 code units, as in LSP.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "new_text": {"type": "string", "description": "Replacement text"},
+                    "range": {
+                        "type": "object",
+                        "description": "Range to replace; mutually exclusive with code_block",
+                        "properties": {
+                            "start": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]},
+                            "end": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]}
+                        },
+                        "required": ["start", "end"]
+                    },
+                    "code_block": {"type": "string", "description": "Exact text to replace; must occur exactly once in the file"}
+                },
+                "required": ["file_path", "new_text"]
+            }),
+        ),
+        ToolDefinition::new(
+            "dry_run_patch",
+            "Checks whether a multi-file change would compile without writing anything: applies a unified diff or a list of range / code block edits to in-memory copies, sends them to rust-analyzer, collects the diagnostics of every patched file and returns a clean, dirty or unknown verdict. rust-analyzer is switched back to the on-disk content afterwards. Positions are 0-based; `character` counts UTF-16 code units, as in LSP.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "diff": {"type": "string", "description": "Unified diff (git or diff -u); paths are absolute or relative to the project root. Mutually exclusive with edits"},
+                    "edits": {
+                        "type": "array",
+                        "description": "Edits applied in order; later edits see the text produced by earlier ones on the same file. Mutually exclusive with diff",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "file_path": {"type": "string", "description": "Absolute path to the file"},
+                                "new_text": {"type": "string", "description": "Replacement text"},
+                                "range": {
+                                    "type": "object",
+                                    "description": "Range to replace; mutually exclusive with code_block",
+                                    "properties": {
+                                        "start": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]},
+                                        "end": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]}
+                                    },
+                                    "required": ["start", "end"]
+                                },
+                                "code_block": {"type": "string", "description": "Exact text to replace; must occur exactly once in the file"}
+                            },
+                            "required": ["file_path", "new_text"]
+                        }
+                    }
+                }
+            }),
+        ),
+        ToolDefinition::new(
+            "check_function",
+            "Checks a proposed new body for one function without writing anything: swaps the body in rust-analyzer's in-memory copy of the file, collects the diagnostics, and returns only those inside the function or elsewhere in the file naming it, with a clean, dirty or unknown verdict. rust-analyzer is switched back to the on-disk content afterwards. Locate the function with its name within a provided code block.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The exact symbol name to find"},
-                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
-                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                    "symbol": {"type": "string", "description": "The function or method name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function name"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "new_body": {"type": "string", "description": "The new body, either a braced block or bare statements (wrapped in braces and indented)"}
                 },
-                "required": ["file_path", "symbol", "code_block"]
+                "required": ["file_path", "symbol", "code_block", "new_body"]
             }),
         ),
         ToolDefinition::new(
-            "find_references",
-            "Finds all references to a specific symbol by searching within a provided code block. Useful for refactoring and understanding usage patterns.",
+            "diagnostics_delta",
+            "Shows whether a proposed edit helps: collects a file's diagnostics, applies the edit (a range or a unique code block replaced with new text) to rust-analyzer's in-memory copy, collects them again and returns which diagnostics were fixed, which are new and which remain, matched by range, code and message. Nothing is written and rust-analyzer is switched back to the on-disk content afterwards. Positions are 0-based; `character` counts UTF-16 code units, as in LSP.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The exact symbol name to find references for"},
-                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
-                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                    "new_text": {"type": "string", "description": "Replacement text"},
+                    "range": {
+                        "type": "object",
+                        "description": "Range to replace; mutually exclusive with code_block",
+                        "properties": {
+                            "start": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]},
+                            "end": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]}
+                        },
+                        "required": ["start", "end"]
+                    },
+                    "code_block": {"type": "string", "description": "Exact text to replace; must occur exactly once in the file"}
                 },
-                "required": ["file_path", "symbol", "code_block"]
+                "required": ["file_path", "new_text"]
             }),
         ),
         ToolDefinition::new(
-            "get_diagnostics",
-            "Get compiler diagnostics for a file",
+            "format_range",
+            "Formats only the lines covered by a range or a unique code block with rustfmt, leaving the rest of the file untouched, and returns the diff. Falls back to whole-document formatting filtered to those lines when range formatting is unavailable.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "range": {
+                        "type": "object",
+                        "description": "Range to format, widened to whole lines; mutually exclusive with code_block",
+                        "properties": {
+                            "start": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]},
+                            "end": {"type": "object", "properties": {"line": {"type": "integer"}, "character": {"type": "integer"}}, "required": ["line", "character"]}
+                        },
+                        "required": ["start", "end"]
+                    },
+                    "code_block": {"type": "string", "description": "Exact text whose lines to format; must occur exactly once in the file"}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "format_with_config",
+            "Formats a file by running rustfmt directly with an explicit edition and inline options (max_width, imports_granularity, ...), for when rust-analyzer's formatting does not match the project's style. By default the nearest rustfmt.toml / .rustfmt.toml is respected and the edition comes from it or from Cargo.toml. Returns the diff and how the configuration was resolved; writes only with write: true.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "edition": {"type": "string", "description": "Rust edition to parse with (2015, 2018, 2021, 2024); defaults to the config file's, then the package's"},
+                    "config": {"type": "object", "description": "Inline rustfmt options overriding the config file, e.g. {\"max_width\": 80, \"hard_tabs\": false}", "additionalProperties": true},
+                    "use_project_config": {"type": "boolean", "description": "Respect the nearest rustfmt.toml / .rustfmt.toml", "default": true},
+                    "write": {"type": "boolean", "description": "Write the formatted file (ignored in read-only mode); otherwise only the diff is returned", "default": false}
+                },
+                "required": ["file_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "apply_clippy_suggestions",
+            "Apply clippy lint suggestions to improve code quality",
             json!({
                 "type": "object",
                 "properties": {
@@ -123,19 +1245,143 @@ pub fn get_tools() -> Vec<ToolDefinition> {
             }),
         ),
         ToolDefinition::new(
-            "workspace_symbols",
-            "Search for symbols in the workspace",
+            "find_unnecessary_clones",
+            "Runs clippy with the `redundant_clone` and `clone_on_copy` lints and returns each flagged `.clone()` with its location and suggested fix, plus how many are removable and how many clippy can fix automatically. With `apply: true`, writes the machine-applicable fixes.",
             json!({
                 "type": "object",
                 "properties": {
-                    "query": {"type": "string"}
+                    "workspace_path": {"type": "string"},
+                    "file_path": {"type": "string", "description": "Absolute path to a source file; checks only the package that owns it and reports only this file's clones"},
+                    "apply": {"type": "boolean", "description": "Apply the machine-applicable fixes", "default": false}
                 },
-                "required": ["query"]
+                "required": ["workspace_path"]
             }),
         ),
         ToolDefinition::new(
-            "document_symbols",
-            "Retrieves the hierarchical structure (symbols) of a file. PREFERRED over `read_file` for large files to understand code organization without consuming massive context tokens. Returns an outline of functions, structs, and impls.",
+            "unused_dependencies",
+            "Cross-references the dependencies declared in a package's Cargo.toml (normal, dev, build and target-specific) against `name::` paths, `use` and `extern crate` in its source files, and lists the ones that appear unused, with caveats (optional, `-sys`, derive crates) and the scan's limitations. Treat results as candidates to verify, not as safe removals.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "manifest_path": {"type": "string", "description": "Absolute path to the package's Cargo.toml"}
+                },
+                "required": ["manifest_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "complexity_report",
+            "Estimates the cyclomatic complexity of every function and method in a file or crate by counting branch points (`if`, extra `match` arms, `&&`/`||`, loops, `?`) outside comments and strings, and returns them sorted from most to least complex. A heuristic for picking refactoring targets, not an exact metric.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "limit": {"type": "integer", "description": "Maximum number of functions to return", "default": 50}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_large_functions",
+            "Lists every function and method in a file or crate whose line span exceeds a threshold (default 100), largest first, with its location and line count. A cheap first pass for finding extract_function candidates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "threshold": {"type": "integer", "description": "Report functions spanning more than this many lines", "default": 100}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "must_use_candidates",
+            "Flags functions whose result is easy to ignore by mistake and that lack `#[must_use]`: those returning `Result` or `Option` (except `Option` from `&mut self` methods such as `pop`) and builder-style methods taking `self` and returning `Self`. Return types come from hover signatures; by default only functions reachable from outside the crate are listed (declared `pub`, with no private module on the way). Optionally inserts the attribute. A heuristic API-quality aid.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "include_private": {"type": "boolean", "description": "Also list functions not reachable from other crates", "default": false},
+                    "apply": {"type": "boolean", "description": "Insert the suggested attribute above each candidate", "default": false},
+                    "limit": {"type": "integer", "description": "Maximum number of candidates (default: 100)"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_dyn_usages",
+            "Lists every `dyn Trait` type (bare, `Box<dyn ..>`, `&dyn ..`, `Arc<dyn ..>`, ...) and `impl Trait` type in a file or crate, skipping comments and strings. Each usage is categorized by position (argument, return, field, local, type_alias, static, other) with its bounds, wrapper and source line, for auditing dynamic dispatch.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "include_impl": {"type": "boolean", "description": "Also report `impl Trait` in argument and return position", "default": true}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "async_map",
+            "Lists the async functions of a file or crate with the `.await` points in each body, found by scanning the source outside comments and strings. Async blocks and async closures are reported as separate contexts with their own await points, nested under their async fn or listed on their own when outside one.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_blocking_in_async",
+            "Flags calls that block the executor thread inside async fn bodies, async blocks and async closures of a file or crate: std::fs, std::thread::sleep, std::net sockets, std::process::Command, reqwest::blocking, block_on, and un-awaited `.lock()` / `.recv()`. Calls inside spawn_blocking or block_in_place are skipped. The pattern list can be extended or trimmed per call and in `.rust-mcp.toml` under `[blocking]`.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "patterns": {"type": "array", "items": {"type": "string"}, "description": "Extra blocking calls: a path such as `my_db::sync::query` or a method such as `.wait()`"},
+                    "ignore": {"type": "array", "items": {"type": "string"}, "description": "Built-in patterns not to flag, e.g. `.lock()`"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_panics",
+            "Lists the explicit panic sites of a file or crate, found by scanning the source outside comments and strings: `panic!`, `.unwrap()`, `.expect(..)`, `unreachable!`, `todo!`, `unimplemented!` and indexing or slicing with `[..]`. Each site reports its category, the call, file, line and source line. Test code (`#[cfg(test)]` items, `#[test]` functions, tests/ and benches/) is skipped unless include_tests is set.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "categories": {"type": "array", "items": {"type": "string", "enum": ["panic", "unwrap", "expect", "unreachable", "todo", "unimplemented", "index"]}, "description": "Only report these categories (default: all)"},
+                    "include_tests": {"type": "boolean", "description": "Also scan test code", "default": false},
+                    "limit": {"type": "integer", "description": "Maximum number of findings to return", "default": 500}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "check_format_args",
+            "Checks `format!`, `format_args!`, `print!`, `println!`, `eprint!`, `eprintln!`, `write!`, `writeln!` and `panic!` calls in a file or crate without compiling: parses each literal format string for `{}`, `{0}` and `{name}` placeholders (plus `width$` / `.*` specs) and reports placeholders without an argument, arguments never used and malformed braces, with file and line.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a crate directory"},
+                    "limit": {"type": "integer", "description": "Maximum number of issues to return", "default": 200}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "describe_build_script",
+            "Finds a package's build script (`build.rs` or the `build` path in Cargo.toml), returns its document symbols and the `cargo:`/`cargo::` directives it prints (rerun-if, rustc-cfg, link flags, env, metadata) with their lines, found by scanning the source. Reports when the package has no build script.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "manifest_path": {"type": "string", "description": "Absolute path to the package's Cargo.toml"}
+                },
+                "required": ["manifest_path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "validate_lifetimes",
+            "Validate and suggest lifetime annotations",
             json!({
                 "type": "object",
                 "properties": {
@@ -145,22 +1391,23 @@ pub fn get_tools() -> Vec<ToolDefinition> {
             }),
         ),
         ToolDefinition::new(
-            "get_hover",
-            "Retrieves hover information (signature, documentation) for a specific symbol by locating it within a provided code block. This method is more robust than using line/character coordinates.",
+            "trace_lifetime",
+            "Reports every place a named lifetime appears in a function: its declaration and bounds in the generic parameters, the parameters whose types use it, the return type, the where-clause and the body, each with line and column. Also lists the outlives relations involving it. Helps reason about which borrows are tied together when fixing a borrow error. Locate the function with its name within a provided code block.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The exact symbol name to hover over"},
-                    "code_block": {"type": "string", "description": "A unique multi-line code snippet (3-5 lines) containing the target symbol to ensure correct context"},
-                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the provided code_block. Defaults to 1.", "default": 1}
+                    "symbol": {"type": "string", "description": "The function or method name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function name"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "lifetime": {"type": "string", "description": "The lifetime to trace, with or without the leading quote (e.g. `'a` or `a`)"}
                 },
-                "required": ["file_path", "symbol", "code_block"]
+                "required": ["file_path", "symbol", "code_block", "lifetime"]
             }),
         ),
         ToolDefinition::new(
-            "get_symbol_source",
-            "Retrieves the source code of a symbol by locating it within a provided code block. Useful for reading implementations.",
+            "get_type_hierarchy",
+            "Retrieves the type hierarchy (supertypes/traits implemented, subtypes/implementations) for a symbol. Useful for understanding trait relationships and implementations.",
             json!({
                 "type": "object",
                 "properties": {
@@ -173,89 +1420,190 @@ pub fn get_tools() -> Vec<ToolDefinition> {
             }),
         ),
         ToolDefinition::new(
-            "rename_symbol",
-            "Renames a symbol with scope awareness by locating it within a provided code block. This method is more robust than using raw line/character coordinates.",
+            "check_trait_impl",
+            "Checks whether a type implements a given trait in the workspace. Locate the type within a provided code block; returns a boolean plus the matching impl locations, noting generic and derived impls.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The exact symbol name to rename"},
-                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the target symbol"},
+                    "symbol": {"type": "string", "description": "The exact type name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the type"},
                     "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
-                    "new_name": {"type": "string", "description": "The new name for the symbol"}
+                    "trait_name": {"type": "string", "description": "The trait to check, e.g. `Display` or `std::error::Error`"}
                 },
-                "required": ["file_path", "symbol", "code_block", "new_name"]
+                "required": ["file_path", "symbol", "code_block", "trait_name"]
             }),
         ),
         ToolDefinition::new(
-            "run_cargo_check",
-            "Execute cargo check and parse errors",
+            "list_methods",
+            "Lists the methods callable on a type, grouped into inherent methods and one group per implemented trait, each with its signature from hover, sorted by name. Trait groups carry the trait's import path and whether it is in the prelude; derived std traits list the methods the derive generates. Locate the type within a provided code block.",
             json!({
                 "type": "object",
                 "properties": {
-                    "workspace_path": {"type": "string"}
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact type name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the type"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
                 },
-                "required": ["workspace_path"]
+                "required": ["file_path", "symbol", "code_block"]
             }),
         ),
         ToolDefinition::new(
-            "extract_function",
-            "Extracts a block of code into a new function. Locates the code by matching the provided code_block snippet. More robust than using raw line/character ranges.",
+            "error_map",
+            "Maps a crate's error types and the `From` conversions between them, i.e. what `?` can propagate into what. Finds types implementing `std::error::Error` (`impl Error for` blocks, `#[derive(Error)]`, and rust-analyzer's implementations of the trait) and, for each, the types it converts from via `impl From<..>` or thiserror `#[from]` fields, plus the error types it converts into. Notes thiserror, anyhow and `Box<dyn Error>` usage.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to the crate directory (or a single file)"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "trace_question_mark",
+            "Explains one `?` operator: the error type it propagates, the enclosing function's error type it is converted to, and the `From` conversion that bridges them. Reads the types from rust-analyzer's hover on the `?` (falling back to the function's declared return type), looks up where both types are defined, and searches the crate for a matching `impl From` or thiserror `#[from]` field. Identical types and the blanket conversions into `anyhow::Error` and `Box<dyn Error>` count as bridged. Errors if the position is not a `?` operator.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "code_block": {"type": "string", "description": "The exact multi-line code snippet to be extracted into a new function"},
-                    "occurrence": {"type": "integer", "description": "The 1-based index of the code_block's occurrence. Defaults to 1.", "default": 1},
-                    "function_name": {"type": "string", "description": "The name of the new function"}
+                    "code_block": {"type": "string", "description": "A snippet of code containing the `?` operator"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the `?` within the code_block", "default": 1}
                 },
-                "required": ["file_path", "code_block", "function_name"]
+                "required": ["file_path", "code_block"]
             }),
         ),
         ToolDefinition::new(
-            "inline_function",
-            "Inlines a function call by locating it within a provided code block. More robust than using raw line/character coordinates.",
+            "find_method_impl",
+            "Finds the impl method that actually runs for a trait method call such as `x.foo()`, where find_definition would stop at the trait declaration. Locate the method name at the call site within a provided code block; the receiver's type is matched against the trait's impls.",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The name of the function to inline"},
-                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the function call"},
-                    "occurrence": {"type": "integer", "description": "The 1-based index of the function call's occurrence within the code_block", "default": 1}
+                    "symbol": {"type": "string", "description": "The method name at the call site"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the call"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
                 },
                 "required": ["file_path", "symbol", "code_block"]
             }),
         ),
         ToolDefinition::new(
-            "apply_clippy_suggestions",
-            "Apply clippy lint suggestions to improve code quality",
+            "find_constructions",
+            "Finds where instances of a type are created: `Type { .. }`, `Type(..)`, `Type::new(..)` and other constructor-like associated functions, enum variants and constructors passed as values. Searches the type's references and classifies each site from its source line, leaving out annotations, patterns and impl headers. Locate the type (or a use of it) within a provided code block.",
             json!({
                 "type": "object",
                 "properties": {
-                    "file_path": {"type": "string"}
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The type name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the type name"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
                 },
-                "required": ["file_path"]
+                "required": ["file_path", "symbol", "code_block"]
             }),
         ),
         ToolDefinition::new(
-            "validate_lifetimes",
-            "Validate and suggest lifetime annotations",
+            "describe_enum",
+            "Lists the variants of an enum with their kind (unit/tuple/struct) and field types. Locate the enum (or a use of it) within a provided code block; useful for writing exhaustive match arms.",
             json!({
                 "type": "object",
                 "properties": {
-                    "file_path": {"type": "string"}
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The exact enum name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the enum name"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
                 },
-                "required": ["file_path"]
+                "required": ["file_path", "symbol", "code_block"]
             }),
         ),
         ToolDefinition::new(
-            "get_type_hierarchy",
-            "Retrieves the type hierarchy (supertypes/traits implemented, subtypes/implementations) for a symbol. Useful for understanding trait relationships and implementations.",
+            "describe_generics",
+            "Lists the lifetime, type and const parameters of a generic function, struct, enum, trait or impl together with their bounds and where-clause. Locate the item within a provided code block (use `impl` as the symbol for impl blocks).",
             json!({
                 "type": "object",
                 "properties": {
                     "file_path": {"type": "string", "description": "Absolute path to the file"},
-                    "symbol": {"type": "string", "description": "The exact symbol name"},
+                    "symbol": {"type": "string", "description": "The item name, or `impl` for an impl block"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "generics_in_scope",
+            "Lists the lifetime, type and const parameters usable at a position, with their bounds, grouped by the item introducing them: the enclosing impl or trait, then the function (or struct, enum, type alias) itself. Locate the position with a symbol within a provided code block.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "Any token at the position, e.g. a variable in the function body"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "derive_bounds",
+            "Explains the bounds a struct's or enum's std derives put on its type parameters (`#[derive(Clone)]` on `Wrapper<T>` implements Clone only where `T: Clone`). Locate the type within a provided code block; returns each derived trait with the implied bounds, the impl header the derive generates, the parameters whose bound is stricter than the fields need (e.g. only used in `PhantomData` or `Arc`) and missing supertrait derives.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The struct or enum name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "struct_layout",
+            "Reports the size, alignment and per-field offsets of a struct located within a provided code block. On a nightly toolchain the crate is built with `-Zprint-type-sizes` and the numbers are exact (`precision: exact`, one layout per instantiation); otherwise a static estimate from `#[repr]` and the field types is returned (`precision: estimated`), with offsets only where field order is fixed.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The struct name"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1},
+                    "use_compiler": {"type": "boolean", "description": "Build the crate on nightly for exact numbers; false skips straight to the estimate", "default": true}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "find_recursive_types",
+            "Finds structs and enums that contain themselves without indirection (directly or through other types), which rustc rejects with \"recursive type has infinite size\". Scans the field types of every struct and enum under a file or directory, following Option, tuples and arrays but not Box, Rc, Arc, Vec or references; reports each cycle, its fields and the field to wrap in Box with the suggested type.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to a .rs file or a directory to scan recursively"}
+                },
+                "required": ["path"]
+            }),
+        ),
+        ToolDefinition::new(
+            "describe_function",
+            "Returns a structured view of a function's interface located within a provided code block: each parameter's name, type and passing mode (`owned`, `ref`, `mut_ref`, `raw_pointer`), the `self` receiver of methods, the return type and the async/unsafe/const qualifiers. Uses hover, falling back to signature help at call sites.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The function name at its definition or a call site"},
+                    "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
+                    "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
+                },
+                "required": ["file_path", "symbol", "code_block"]
+            }),
+        ),
+        ToolDefinition::new(
+            "optimization_attributes",
+            "Reports the optimization-relevant attributes of a function located within a provided code block: `#[inline]` / `#[inline(always|never)]`, `#[cold]`, `#[no_mangle]` / `#[export_name]`, `#[target_feature]`, `#[track_caller]` and `#[optimize]`, including those applied through `cfg_attr`, and whether the function is monomorphized (type or const parameters, `impl Trait` arguments, a generic impl or a trait default body), with notes on how they interact.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "file_path": {"type": "string", "description": "Absolute path to the file"},
+                    "symbol": {"type": "string", "description": "The function name at its definition or a call site"},
                     "code_block": {"type": "string", "description": "A unique multi-line code snippet containing the symbol"},
                     "occurrence": {"type": "integer", "description": "The 1-based index of the symbol's occurrence within the code_block", "default": 1}
                 },
@@ -328,6 +1676,25 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                 "required": ["view", "file_path", "line", "character"]
             }),
         ),
+        ToolDefinition::new(
+            "inspect_diff",
+            "Diff a symbol's MIR, LLVM IR or assembly between two optimization levels",
+            json!({
+                "type": "object",
+                "properties": {
+                    "view": {"type": "string"},
+                    "file_path": {"type": "string"},
+                    "line": {"type": "integer", "minimum": 0},
+                    "character": {"type": "integer", "minimum": 0},
+                    "symbol_name": {"type": "string"},
+                    "from_opt_level": {"type": "string", "default": "0"},
+                    "to_opt_level": {"type": "string", "default": "3"},
+                    "target": {"type": "string"},
+                    "gating_mode": {"type": "string"}
+                },
+                "required": ["view", "file_path", "line", "character"]
+            }),
+        ),
         ToolDefinition::new(
             "capabilities",
             "Discover supported inspection presets and limits",
@@ -338,5 +1705,13 @@ pub fn get_tools() -> Vec<ToolDefinition> {
                 }
             }),
         ),
+        ToolDefinition::new(
+            "server_status",
+            "Report rust-analyzer health as seen by the keepalive: state, last ping, restarts",
+            json!({
+                "type": "object",
+                "properties": {}
+            }),
+        ),
     ]
 }
\ No newline at end of file